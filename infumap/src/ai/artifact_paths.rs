@@ -9,6 +9,12 @@ pub const TEXT_CONTENT_SUFFIX: &str = "_text";
 pub const TEXT_MANIFEST_SUFFIX: &str = "_manifest.json";
 pub const GEO_CONTENT_SUFFIX: &str = "_geo.json";
 pub const GEO_MANIFEST_SUFFIX: &str = "_geo_manifest.json";
+pub const LINK_SNAPSHOT_CONTENT_SUFFIX: &str = "_snapshot";
+pub const LINK_SNAPSHOT_MANIFEST_SUFFIX: &str = "_snapshot_manifest.json";
+pub const AUDIO_TRANSCRIPT_CONTENT_SUFFIX: &str = "_transcript";
+pub const AUDIO_TRANSCRIPT_MANIFEST_SUFFIX: &str = "_transcript_manifest.json";
+pub const FACES_CONTENT_SUFFIX: &str = "_faces.json";
+pub const FACES_MANIFEST_SUFFIX: &str = "_faces_manifest.json";
 pub const FRAGMENTS_FILENAME: &str = "fragments.jsonl";
 pub const FRAGMENTS_MANIFEST_FILENAME: &str = "fragments_manifest.json";
 
@@ -72,6 +78,30 @@ pub fn item_geo_manifest_path(data_dir: &str, user_id: &str, item_id: &str) -> I
   item_text_artifact_path(data_dir, user_id, item_id, GEO_MANIFEST_SUFFIX)
 }
 
+pub fn item_link_snapshot_content_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, LINK_SNAPSHOT_CONTENT_SUFFIX)
+}
+
+pub fn item_link_snapshot_manifest_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, LINK_SNAPSHOT_MANIFEST_SUFFIX)
+}
+
+pub fn item_audio_transcript_content_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, AUDIO_TRANSCRIPT_CONTENT_SUFFIX)
+}
+
+pub fn item_audio_transcript_manifest_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, AUDIO_TRANSCRIPT_MANIFEST_SUFFIX)
+}
+
+pub fn item_faces_content_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, FACES_CONTENT_SUFFIX)
+}
+
+pub fn item_faces_manifest_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  item_text_artifact_path(data_dir, user_id, item_id, FACES_MANIFEST_SUFFIX)
+}
+
 pub fn user_fragments_dir(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
   let mut path = expand_tilde(data_dir).ok_or("Could not interpret path.")?;
   path.push(format!("user_{}", user_id));
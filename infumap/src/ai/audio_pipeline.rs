@@ -0,0 +1,624 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use config::Config;
+use infusdk::item::{Item, ItemType};
+use infusdk::util::infu::InfuResult;
+use log::{debug, error, info};
+use once_cell::sync::OnceCell;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::sleep;
+
+use crate::ai::artifact_paths::{
+  AUDIO_TRANSCRIPT_CONTENT_SUFFIX, ensure_user_text_dir, item_audio_transcript_content_path,
+  item_audio_transcript_manifest_path,
+};
+use crate::ai::fragment::sources::{AudioTranscriptSegment, build_audio_transcript_fragment_artifact};
+use crate::ai::fragment::{
+  FragmentBuildOutcome, item_fragment_artifact_files_exist, item_fragment_artifacts_are_current,
+};
+use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
+use crate::ai::metrics::{METRIC_AI_AUDIO_TRANSCRIPT_PROCESSED_TOTAL, METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH};
+use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
+use crate::ai::user_id_for_log;
+use crate::config::{CONFIG_DATA_DIR, CONFIG_TRANSCRIBE_MODEL, CONFIG_TRANSCRIBE_URL};
+use crate::storage::db::Db;
+use crate::storage::object::{self as storage_object, ObjectStore};
+use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
+use crate::util::fs::path_exists;
+use crate::util::media::is_audio_mime_type;
+
+const EMPTY_QUEUE_WAIT_MILLIS: u64 = 1000;
+const AUDIO_TRANSCRIPT_MANIFEST_SCHEMA_VERSION: u32 = 1;
+const AUDIO_TRANSCRIPT_CONTENT_MIME_TYPE: &str = "application/json";
+const TRANSCRIBE_ENDPOINT_PATH: &str = "/v1/audio/transcriptions";
+/// Long recordings on CPU-only hosts can take a large fraction of their play length to transcribe.
+const TRANSCRIBE_REQUEST_TIMEOUT_SECS: u64 = 2 * 60 * 60;
+/// Recordings the transcription service rejected are tried again after this long, in case the
+/// service (or its model) has since changed. A successful transcript is kept for the life of the item.
+const AUDIO_TRANSCRIPT_FAILED_RETRY_SECS: i64 = 7 * 24 * 60 * 60;
+
+static AUDIO_TRANSCRIPT_PIPELINE_STATE: OnceCell<Arc<Mutex<AudioTranscriptPipelineState>>> = OnceCell::new();
+
+#[derive(Clone)]
+struct AudioTranscriptPipelineConfig {
+  data_dir: String,
+  transcribe_url: reqwest::Url,
+  transcribe_model: String,
+  object_store: Arc<ObjectStore>,
+}
+
+#[derive(Clone)]
+struct AudioTranscriptCandidate {
+  user_id: String,
+  item_id: String,
+}
+
+impl AudioTranscriptCandidate {
+  fn from_item(item: &Item) -> Option<AudioTranscriptCandidate> {
+    if !is_audio_item(item) {
+      return None;
+    }
+    Some(AudioTranscriptCandidate { user_id: item.owner_id.clone(), item_id: item.id.clone() })
+  }
+}
+
+#[derive(Default)]
+struct AudioTranscriptPipelineState {
+  queue: VecDeque<AudioTranscriptCandidate>,
+  queued_item_ids: HashSet<String>,
+}
+
+enum AudioTranscriptReconcileOutcome {
+  Changed(String),
+  Skipped,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AudioTranscriptManifest {
+  schema_version: u32,
+  status: String,
+  source_mime_type: String,
+  content_mime_type: String,
+  model: String,
+  transcribed_at_unix_secs: i64,
+  duration_ms: Option<u64>,
+  error: Option<String>,
+}
+
+impl AudioTranscriptManifest {
+  fn is_succeeded(&self) -> bool {
+    self.status == "succeeded"
+  }
+}
+
+/// The stored transcript of an audio item. Segment times are in seconds from the start of the recording.
+#[derive(Serialize, Deserialize)]
+struct AudioTranscript {
+  language: Option<String>,
+  duration_secs: Option<f64>,
+  text: String,
+  segments: Vec<AudioTranscriptSegment>,
+}
+
+/// The subset of an OpenAI-style `verbose_json` transcription response that is used.
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+  #[serde(default)]
+  text: String,
+  language: Option<String>,
+  duration: Option<f64>,
+  #[serde(default)]
+  segments: Vec<AudioTranscriptSegment>,
+}
+
+enum TranscribeOutcome {
+  Transcribed(AudioTranscript),
+  /// The service could not be reached or failed internally. Nothing is recorded, so the item is tried
+  /// again the next time the pipeline starts.
+  EndpointUnavailable(String),
+  /// The service rejected the recording or returned something unusable.
+  Failed(String),
+}
+
+pub fn init_audio_transcript_pipeline_loop(
+  config: &Config,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+) -> InfuResult<()> {
+  let Some(pipeline_config) = audio_transcript_pipeline_config(config, object_store)? else {
+    return Ok(());
+  };
+  if AUDIO_TRANSCRIPT_PIPELINE_STATE.get().is_some() {
+    enqueue_all_loaded_audio_items(db, pipeline_config);
+    return Ok(());
+  }
+
+  let state = Arc::new(Mutex::new(AudioTranscriptPipelineState::default()));
+  METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH.set(0);
+  AUDIO_TRANSCRIPT_PIPELINE_STATE
+    .set(state.clone())
+    .map_err(|_| "Audio transcript background pipeline loop is already running in this process.".to_owned())?;
+
+  info!("Starting audio transcript background loop (endpoint={}).", pipeline_config.transcribe_url);
+
+  let worker_config = pipeline_config.clone();
+  let worker_db = db.clone();
+  let worker_state = state.clone();
+  let _worker = task::spawn(async move {
+    run_audio_transcript_loop(worker_config, worker_db, worker_state).await;
+  });
+
+  enqueue_all_loaded_audio_items(db, pipeline_config);
+  Ok(())
+}
+
+pub fn enqueue_audio_transcript_item_if_active(item: &Item) {
+  let Some(candidate) = AudioTranscriptCandidate::from_item(item) else {
+    return;
+  };
+  enqueue_candidate_if_active(candidate);
+}
+
+pub fn dequeue_audio_transcript_item_if_active(item_id: &str) {
+  let Some(state) = AUDIO_TRANSCRIPT_PIPELINE_STATE.get() else {
+    return;
+  };
+  let item_id = item_id.to_owned();
+
+  if let Ok(mut state) = state.try_lock() {
+    remove_candidate(&mut state, &item_id);
+    return;
+  }
+
+  let state = state.clone();
+  let _dequeue = task::spawn(async move {
+    let mut state = state.lock().await;
+    remove_candidate(&mut state, &item_id);
+  });
+}
+
+pub fn is_audio_item(item: &Item) -> bool {
+  item.item_type == ItemType::File && item.mime_type.as_deref().is_some_and(is_audio_mime_type)
+}
+
+pub async fn delete_item_audio_transcript_artifacts(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<()> {
+  let manifest_path = item_audio_transcript_manifest_path(data_dir, user_id, item_id)?;
+  let content_path = item_audio_transcript_content_path(data_dir, user_id, item_id)?;
+  if path_exists(&manifest_path).await {
+    fs::remove_file(&manifest_path).await?;
+  }
+  if path_exists(&content_path).await {
+    fs::remove_file(&content_path).await?;
+  }
+  Ok(())
+}
+
+fn audio_transcript_pipeline_config(
+  config: &Config,
+  object_store: Arc<ObjectStore>,
+) -> InfuResult<Option<AudioTranscriptPipelineConfig>> {
+  let raw_url = config.get_string(CONFIG_TRANSCRIBE_URL).map_err(|e| e.to_string())?;
+  if raw_url.trim().is_empty() {
+    return Ok(None);
+  }
+  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+  let transcribe_model = config.get_string(CONFIG_TRANSCRIBE_MODEL).map_err(|e| e.to_string())?;
+  let transcribe_url = transcribe_endpoint_url(&raw_url)?;
+  Ok(Some(AudioTranscriptPipelineConfig { data_dir, transcribe_url, transcribe_model, object_store }))
+}
+
+/// Accepts either the service base URL or the full transcriptions endpoint URL.
+fn transcribe_endpoint_url(raw_url: &str) -> InfuResult<reqwest::Url> {
+  let trimmed_url = raw_url.trim();
+  let parsed = reqwest::Url::parse(trimmed_url)
+    .map_err(|e| format!("Could not parse {} '{}': {}", CONFIG_TRANSCRIBE_URL, trimmed_url, e))?;
+  if parsed.path().trim_end_matches('/').ends_with(TRANSCRIBE_ENDPOINT_PATH) {
+    return Ok(parsed);
+  }
+  let base_url = reqwest::Url::parse(&format!("{}/", trimmed_url.trim_end_matches('/')))
+    .map_err(|e| format!("Could not parse {} '{}': {}", CONFIG_TRANSCRIBE_URL, trimmed_url, e))?;
+  base_url
+    .join(TRANSCRIBE_ENDPOINT_PATH.trim_start_matches('/'))
+    .map_err(|e| format!("Could not build transcription endpoint from '{}': {}", trimmed_url, e).into())
+}
+
+async fn run_audio_transcript_loop(
+  config: AudioTranscriptPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  state: Arc<Mutex<AudioTranscriptPipelineState>>,
+) {
+  loop {
+    let candidate = {
+      let mut state = state.lock().await;
+      pop_candidate(&mut state)
+    };
+
+    let Some(candidate) = candidate else {
+      sleep(Duration::from_millis(EMPTY_QUEUE_WAIT_MILLIS)).await;
+      continue;
+    };
+
+    wait_for_object_store_upload_quiet_period("audio transcription").await;
+    match reconcile_audio_transcript_item(&config, db.clone(), &candidate).await {
+      Ok(AudioTranscriptReconcileOutcome::Changed(user_id)) => {
+        record_audio_transcript_processed("success");
+        enqueue_fragment_index_rebuild_for_user(&user_id);
+      }
+      Ok(AudioTranscriptReconcileOutcome::Skipped) => {
+        record_audio_transcript_processed("skipped");
+      }
+      Err(e) => {
+        record_audio_transcript_processed("failed");
+        error!(
+          "Audio transcript pipeline failed for item '{}' (user '{}'): {}",
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          e
+        );
+      }
+    }
+  }
+}
+
+async fn reconcile_audio_transcript_item(
+  config: &AudioTranscriptPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  candidate: &AudioTranscriptCandidate,
+) -> InfuResult<AudioTranscriptReconcileOutcome> {
+  let (item_snapshot, object_encryption_key) = {
+    let db = db.lock().await;
+    let item = match db.item.get(&candidate.item_id) {
+      Ok(item) if item.owner_id == candidate.user_id && is_audio_item(item) => item.clone(),
+      _ => return Ok(AudioTranscriptReconcileOutcome::Skipped),
+    };
+    let object_encryption_key =
+      db.user.get(&item.owner_id).ok_or(format!("User '{}' not loaded.", item.owner_id))?.object_encryption_key.clone();
+    (item, object_encryption_key)
+  };
+
+  if let Some(manifest) = read_audio_transcript_manifest(&config.data_dir, &item_snapshot).await? {
+    if manifest.is_succeeded() {
+      if item_fragment_artifacts_are_current(&config.data_dir, &item_snapshot.owner_id, &item_snapshot.id).await? {
+        return Ok(AudioTranscriptReconcileOutcome::Skipped);
+      }
+      let transcript = read_audio_transcript(&config.data_dir, &item_snapshot, &object_encryption_key).await?;
+      let outcome =
+        build_audio_transcript_fragment_artifact(&config.data_dir, &item_snapshot, Some(&transcript.segments)).await?;
+      return Ok(changed_if(outcome, &item_snapshot));
+    }
+    if unix_now_secs()? - manifest.transcribed_at_unix_secs < AUDIO_TRANSCRIPT_FAILED_RETRY_SECS {
+      return Ok(AudioTranscriptReconcileOutcome::Skipped);
+    }
+  }
+
+  let audio_bytes = storage_object::get(
+    config.object_store.clone(),
+    item_snapshot.owner_id.clone(),
+    item_snapshot.id.clone(),
+    &object_encryption_key,
+  )
+  .await
+  .map_err(|e| format!("Could not read source audio object for '{}': {}", item_snapshot.id, e))?;
+
+  let transcribe_started = Instant::now();
+  let outcome = transcribe_audio(config, &item_snapshot, audio_bytes).await;
+  let duration_ms = transcribe_started.elapsed().as_millis() as u64;
+  let transcript = match outcome {
+    TranscribeOutcome::Transcribed(transcript) => transcript,
+    TranscribeOutcome::EndpointUnavailable(e) => {
+      return Err(format!("Transcription endpoint unavailable: {}", e).into());
+    }
+    TranscribeOutcome::Failed(e) => {
+      write_failed_audio_transcript_manifest(config, &item_snapshot, duration_ms, &e).await?;
+      let outcome = build_audio_transcript_fragment_artifact(&config.data_dir, &item_snapshot, None).await?;
+      return Ok(changed_if(outcome, &item_snapshot));
+    }
+  };
+
+  write_success_audio_transcript_artifacts(config, &item_snapshot, duration_ms, &transcript, &object_encryption_key)
+    .await?;
+  let outcome =
+    build_audio_transcript_fragment_artifact(&config.data_dir, &item_snapshot, Some(&transcript.segments)).await?;
+  debug!(
+    "Audio transcript pipeline wrote {} fragment(s) for item '{}' (user {}).",
+    outcome.fragment_count,
+    item_snapshot.id,
+    user_id_for_log(&item_snapshot.owner_id)
+  );
+  Ok(changed_if(outcome, &item_snapshot))
+}
+
+fn changed_if(outcome: FragmentBuildOutcome, item: &Item) -> AudioTranscriptReconcileOutcome {
+  if outcome.wrote_fragments || outcome.cleared_existing_fragments {
+    AudioTranscriptReconcileOutcome::Changed(item.owner_id.clone())
+  } else {
+    AudioTranscriptReconcileOutcome::Skipped
+  }
+}
+
+async fn transcribe_audio(
+  config: &AudioTranscriptPipelineConfig,
+  item: &Item,
+  audio_bytes: Vec<u8>,
+) -> TranscribeOutcome {
+  let client = match reqwest::ClientBuilder::new().timeout(Duration::from_secs(TRANSCRIBE_REQUEST_TIMEOUT_SECS)).build()
+  {
+    Ok(client) => client,
+    Err(e) => return TranscribeOutcome::EndpointUnavailable(format!("Could not build HTTP client: {}", e)),
+  };
+  let file_name = item.title.clone().filter(|title| !title.trim().is_empty()).unwrap_or_else(|| item.id.clone());
+  let mime_type = item.mime_type.clone().unwrap_or_else(|| "application/octet-stream".to_owned());
+  let part = match Part::bytes(audio_bytes).file_name(file_name).mime_str(&mime_type) {
+    Ok(part) => part,
+    Err(e) => return TranscribeOutcome::EndpointUnavailable(format!("Could not build multipart upload: {}", e)),
+  };
+  let form = Form::new()
+    .part("file", part)
+    .text("model", config.transcribe_model.clone())
+    .text("response_format", "verbose_json")
+    .text("timestamp_granularities[]", "segment");
+
+  let response = match client.post(config.transcribe_url.clone()).multipart(form).send().await {
+    Ok(response) => response,
+    Err(e) => return TranscribeOutcome::EndpointUnavailable(format!("Request failed: {}", e)),
+  };
+  let status = response.status();
+  let body = match response.text().await {
+    Ok(body) => body,
+    Err(e) => return TranscribeOutcome::EndpointUnavailable(format!("Could not read response body: {}", e)),
+  };
+  if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+    return TranscribeOutcome::EndpointUnavailable(format!("HTTP {}: {}", status, truncated_body(&body)));
+  }
+  if !status.is_success() {
+    return TranscribeOutcome::Failed(format!("HTTP {}: {}", status, truncated_body(&body)));
+  }
+
+  let response = match serde_json::from_str::<TranscriptionResponse>(&body) {
+    Ok(response) => response,
+    Err(e) => return TranscribeOutcome::Failed(format!("Could not parse transcription response: {}", e)),
+  };
+  TranscribeOutcome::Transcribed(audio_transcript_from_response(response))
+}
+
+/// Services that do not support segment timestamps return only the text, which is then treated as
+/// a single segment spanning the whole recording.
+fn audio_transcript_from_response(response: TranscriptionResponse) -> AudioTranscript {
+  let text = response.text.trim().to_owned();
+  let segments = if response.segments.is_empty() && !text.is_empty() {
+    vec![AudioTranscriptSegment { start: 0.0, end: response.duration.unwrap_or(0.0), text: text.clone() }]
+  } else {
+    response.segments
+  };
+  AudioTranscript { language: response.language, duration_secs: response.duration, text, segments }
+}
+
+fn truncated_body(body: &str) -> String {
+  body.trim().chars().take(500).collect()
+}
+
+async fn read_audio_transcript_manifest(data_dir: &str, item: &Item) -> InfuResult<Option<AudioTranscriptManifest>> {
+  let manifest_path = item_audio_transcript_manifest_path(data_dir, &item.owner_id, &item.id)?;
+  if !path_exists(&manifest_path).await {
+    return Ok(None);
+  }
+  let bytes = fs::read(&manifest_path).await?;
+  Ok(
+    serde_json::from_slice::<AudioTranscriptManifest>(&bytes)
+      .ok()
+      .filter(|manifest| manifest.schema_version == AUDIO_TRANSCRIPT_MANIFEST_SCHEMA_VERSION),
+  )
+}
+
+async fn read_audio_transcript(
+  data_dir: &str,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<AudioTranscript> {
+  let content_path = item_audio_transcript_content_path(data_dir, &item.owner_id, &item.id)?;
+  let encrypted = fs::read(&content_path)
+    .await
+    .map_err(|e| format!("Could not read audio transcript '{}': {}", content_path.display(), e))?;
+  let decrypted = decrypt_file_data(object_encryption_key, &encrypted, &audio_transcript_content_filename(item))?;
+  serde_json::from_slice(&decrypted)
+    .map_err(|e| format!("Could not parse audio transcript for '{}': {}", item.id, e).into())
+}
+
+async fn write_success_audio_transcript_artifacts(
+  config: &AudioTranscriptPipelineConfig,
+  item: &Item,
+  duration_ms: u64,
+  transcript: &AudioTranscript,
+  object_encryption_key: &str,
+) -> InfuResult<()> {
+  ensure_user_text_dir(&config.data_dir, &item.owner_id).await?;
+  let content_path = item_audio_transcript_content_path(&config.data_dir, &item.owner_id, &item.id)?;
+  let manifest_path = item_audio_transcript_manifest_path(&config.data_dir, &item.owner_id, &item.id)?;
+  let encrypted = encrypt_file_data(
+    object_encryption_key,
+    &serde_json::to_vec(transcript)?,
+    &audio_transcript_content_filename(item),
+  )?;
+  fs::write(&content_path, encrypted).await?;
+  let manifest = audio_transcript_manifest(config, item, "succeeded", duration_ms, None)?;
+  fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+  debug!(
+    "Transcribed audio item '{}' (user {}): {} segment(s).",
+    item.id,
+    user_id_for_log(&item.owner_id),
+    transcript.segments.len()
+  );
+  Ok(())
+}
+
+async fn write_failed_audio_transcript_manifest(
+  config: &AudioTranscriptPipelineConfig,
+  item: &Item,
+  duration_ms: u64,
+  error_message: &str,
+) -> InfuResult<()> {
+  ensure_user_text_dir(&config.data_dir, &item.owner_id).await?;
+  let content_path = item_audio_transcript_content_path(&config.data_dir, &item.owner_id, &item.id)?;
+  let manifest_path = item_audio_transcript_manifest_path(&config.data_dir, &item.owner_id, &item.id)?;
+  if path_exists(&content_path).await {
+    fs::remove_file(&content_path).await?;
+  }
+  let manifest = audio_transcript_manifest(config, item, "failed", duration_ms, Some(error_message))?;
+  fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+  debug!(
+    "Audio transcription failed for item '{}' (user {}): {}",
+    item.id,
+    user_id_for_log(&item.owner_id),
+    error_message
+  );
+  Ok(())
+}
+
+fn audio_transcript_manifest(
+  config: &AudioTranscriptPipelineConfig,
+  item: &Item,
+  status: &str,
+  duration_ms: u64,
+  error_message: Option<&str>,
+) -> InfuResult<AudioTranscriptManifest> {
+  Ok(AudioTranscriptManifest {
+    schema_version: AUDIO_TRANSCRIPT_MANIFEST_SCHEMA_VERSION,
+    status: status.to_owned(),
+    source_mime_type: item.mime_type.clone().unwrap_or_default(),
+    content_mime_type: AUDIO_TRANSCRIPT_CONTENT_MIME_TYPE.to_owned(),
+    model: config.transcribe_model.clone(),
+    transcribed_at_unix_secs: unix_now_secs()?,
+    duration_ms: Some(duration_ms),
+    error: error_message.map(str::to_owned),
+  })
+}
+
+/// Bound into the encrypted transcript (as associated data) so it cannot be swapped between items.
+fn audio_transcript_content_filename(item: &Item) -> String {
+  format!("{}{}", item.id, AUDIO_TRANSCRIPT_CONTENT_SUFFIX)
+}
+
+fn enqueue_all_loaded_audio_items(db: Arc<Mutex<Db>>, config: AudioTranscriptPipelineConfig) {
+  let Some(state) = AUDIO_TRANSCRIPT_PIPELINE_STATE.get() else {
+    return;
+  };
+  let state = state.clone();
+  let _enqueue_task = task::spawn(async move {
+    populate_initial_audio_transcript_queue(&config, db, state).await;
+  });
+}
+
+async fn populate_initial_audio_transcript_queue(
+  config: &AudioTranscriptPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  state: Arc<Mutex<AudioTranscriptPipelineState>>,
+) {
+  let candidates = {
+    let db = db.lock().await;
+    db.item
+      .all_loaded_items()
+      .into_iter()
+      .filter_map(|item_key| db.item.get(&item_key.item_id).ok())
+      .filter_map(AudioTranscriptCandidate::from_item)
+      .collect::<Vec<_>>()
+  };
+
+  let total_candidates = candidates.len();
+  let mut already_fragmented = 0usize;
+  let mut skipped_errors = 0usize;
+  let mut queued_candidates = Vec::new();
+  for candidate in candidates {
+    match item_fragment_artifact_files_exist(&config.data_dir, &candidate.user_id, &candidate.item_id).await {
+      Ok(true) => already_fragmented += 1,
+      Ok(false) => queued_candidates.push(candidate),
+      Err(e) => {
+        skipped_errors += 1;
+        debug!(
+          "Skipping audio item '{}' (user {}) during transcript startup artifact check: {}",
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          e
+        );
+      }
+    }
+  }
+
+  let queued_candidate_count = queued_candidates.len();
+  let enqueued_count = {
+    let mut state = state.lock().await;
+    let mut enqueued_count = 0usize;
+    for candidate in queued_candidates {
+      if enqueue_candidate(&mut state, candidate) {
+        enqueued_count += 1;
+      }
+    }
+    enqueued_count
+  };
+
+  info!(
+    "Startup audio transcript reconciliation saw {} audio item(s), queued {} of {}; fragments: already_present={}; skipped_errors={}.",
+    total_candidates, enqueued_count, queued_candidate_count, already_fragmented, skipped_errors
+  );
+}
+
+fn enqueue_candidate_if_active(candidate: AudioTranscriptCandidate) {
+  let Some(state) = AUDIO_TRANSCRIPT_PIPELINE_STATE.get() else {
+    return;
+  };
+
+  if let Ok(mut state) = state.try_lock() {
+    enqueue_candidate(&mut state, candidate);
+    return;
+  }
+
+  let state = state.clone();
+  let _enqueue = task::spawn(async move {
+    let mut state = state.lock().await;
+    enqueue_candidate(&mut state, candidate);
+  });
+}
+
+fn enqueue_candidate(state: &mut AudioTranscriptPipelineState, candidate: AudioTranscriptCandidate) -> bool {
+  if !state.queued_item_ids.insert(candidate.item_id.clone()) {
+    return false;
+  }
+  state.queue.push_back(candidate);
+  record_audio_transcript_queue_depth(state);
+  true
+}
+
+fn pop_candidate(state: &mut AudioTranscriptPipelineState) -> Option<AudioTranscriptCandidate> {
+  let candidate = state.queue.pop_front()?;
+  state.queued_item_ids.remove(&candidate.item_id);
+  record_audio_transcript_queue_depth(state);
+  Some(candidate)
+}
+
+fn remove_candidate(state: &mut AudioTranscriptPipelineState, item_id: &str) {
+  state.queue.retain(|candidate| candidate.item_id != item_id);
+  state.queued_item_ids.remove(item_id);
+  record_audio_transcript_queue_depth(state);
+}
+
+fn record_audio_transcript_queue_depth(state: &AudioTranscriptPipelineState) {
+  METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH.set(state.queue.len() as i64);
+}
+
+fn record_audio_transcript_processed(outcome: &'static str) {
+  METRIC_AI_AUDIO_TRANSCRIPT_PROCESSED_TOTAL.with_label_values(&[outcome]).inc();
+}
+
+fn unix_now_secs() -> InfuResult<i64> {
+  Ok(
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_err(|e| format!("Could not determine current unix time: {}", e))?
+      .as_secs() as i64,
+  )
+}
@@ -0,0 +1,343 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use config::Config;
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::{Uid, is_uid};
+use log::warn;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::config::{CONFIG_CHAT_TRANSCRIPT_MAX_FILES, CONFIG_CHAT_TRANSCRIPT_MAX_MB, CONFIG_ENABLE_CHAT_TRANSCRIPTS};
+use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
+use crate::util::fs::{expand_tilde, path_exists};
+use crate::util::keyed_lock::KeyedLocks;
+
+pub const CHAT_CONVERSATION_SCHEMA_VERSION: u32 = 1;
+const CHAT_DIRNAME: &str = "chat";
+const CHAT_CONVERSATIONS_DIRNAME: &str = "conversations";
+const CHAT_TRANSCRIPTS_DIRNAME: &str = "transcripts";
+const CHAT_TRANSCRIPT_FILENAME: &str = "transcript.log";
+const CHAT_CONVERSATION_TITLE_MAX_CHARS: usize = 80;
+
+// Serializes transcript appends and rotations across concurrent chat requests.
+static CHAT_TRANSCRIPT_WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+// Serializes the read, append and write of each conversation across concurrent chat turns.
+static CHAT_CONVERSATION_LOCKS: Lazy<KeyedLocks> = Lazy::new(KeyedLocks::new);
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatConversation {
+  #[serde(rename = "schemaVersion")]
+  pub schema_version: u32,
+  pub id: Uid,
+  pub title: String,
+  #[serde(rename = "createdAt")]
+  pub created_at_unix_secs: i64,
+  #[serde(rename = "updatedAt")]
+  pub updated_at_unix_secs: i64,
+  pub messages: Vec<ChatConversationMessage>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatConversationMessage {
+  pub role: String,
+  pub text: String,
+  #[serde(rename = "createdAt")]
+  pub created_at_unix_secs: i64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub model: Option<String>,
+  #[serde(rename = "toolCalls", default, skip_serializing_if = "Vec::is_empty")]
+  pub tool_calls: Vec<ChatConversationToolCall>,
+  #[serde(rename = "citedItemIds", default, skip_serializing_if = "Vec::is_empty")]
+  pub cited_item_ids: Vec<Uid>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatConversationToolCall {
+  pub name: String,
+  pub arguments: Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChatConversationSummary {
+  pub id: Uid,
+  pub title: String,
+  #[serde(rename = "createdAt")]
+  pub created_at_unix_secs: i64,
+  #[serde(rename = "updatedAt")]
+  pub updated_at_unix_secs: i64,
+  #[serde(rename = "messageCount")]
+  pub message_count: usize,
+}
+
+impl ChatConversation {
+  pub fn new(id: Uid, now_unix_secs: i64) -> ChatConversation {
+    ChatConversation {
+      schema_version: CHAT_CONVERSATION_SCHEMA_VERSION,
+      id,
+      title: String::new(),
+      created_at_unix_secs: now_unix_secs,
+      updated_at_unix_secs: now_unix_secs,
+      messages: Vec::new(),
+    }
+  }
+
+  /// Appends a message, titling the conversation from its first user message.
+  pub fn push_message(&mut self, message: ChatConversationMessage) {
+    if self.title.is_empty() && message.role == "user" {
+      self.title = conversation_title_from_text(&message.text);
+    }
+    self.updated_at_unix_secs = self.updated_at_unix_secs.max(message.created_at_unix_secs);
+    self.messages.push(message);
+  }
+
+  pub fn summary(&self) -> ChatConversationSummary {
+    ChatConversationSummary {
+      id: self.id.clone(),
+      title: self.title.clone(),
+      created_at_unix_secs: self.created_at_unix_secs,
+      updated_at_unix_secs: self.updated_at_unix_secs,
+      message_count: self.messages.len(),
+    }
+  }
+
+  fn matches_query_terms(&self, terms: &[String]) -> bool {
+    terms.iter().all(|term| {
+      self.title.to_lowercase().contains(term)
+        || self.messages.iter().any(|message| message.text.to_lowercase().contains(term))
+    })
+  }
+}
+
+fn conversation_title_from_text(text: &str) -> String {
+  let first_line = text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+  let mut chars = first_line.chars();
+  let title: String = chars.by_ref().take(CHAT_CONVERSATION_TITLE_MAX_CHARS).collect();
+  if chars.next().is_some() { format!("{}...", title.trim_end()) } else { title }
+}
+
+pub fn user_chat_dir(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = expand_tilde(data_dir).ok_or("Could not interpret path.")?;
+  path.push(format!("user_{}", user_id));
+  path.push(CHAT_DIRNAME);
+  Ok(path)
+}
+
+pub fn chat_conversation_path(data_dir: &str, user_id: &str, conversation_id: &str) -> InfuResult<PathBuf> {
+  if !is_uid(conversation_id) {
+    return Err(format!("Invalid chat conversation id '{}'.", conversation_id).into());
+  }
+  let mut path = user_chat_dir(data_dir, user_id)?;
+  path.push(CHAT_CONVERSATIONS_DIRNAME);
+  path.push(conversation_id);
+  Ok(path)
+}
+
+/// Conversations are encrypted with the user's object encryption key, like other user content.
+pub async fn read_chat_conversation(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  conversation_id: &str,
+) -> InfuResult<Option<ChatConversation>> {
+  let path = chat_conversation_path(data_dir, user_id, conversation_id)?;
+  let encrypted = match fs::read(&path).await {
+    Ok(bytes) => bytes,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(format!("Could not read chat conversation '{}': {}", path.display(), e).into()),
+  };
+  let bytes = decrypt_file_data(object_encryption_key, &encrypted, conversation_id)?;
+  let conversation: ChatConversation = serde_json::from_slice(&bytes)
+    .map_err(|e| format!("Could not parse chat conversation '{}': {}", path.display(), e))?;
+  if conversation.schema_version != CHAT_CONVERSATION_SCHEMA_VERSION {
+    return Err(
+      format!("Unsupported chat conversation schema version {} in '{}'.", conversation.schema_version, path.display())
+        .into(),
+    );
+  }
+  Ok(Some(conversation))
+}
+
+async fn write_chat_conversation(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  conversation: &ChatConversation,
+) -> InfuResult<()> {
+  let path = chat_conversation_path(data_dir, user_id, &conversation.id)?;
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).await?;
+  }
+  let encrypted = encrypt_file_data(object_encryption_key, &serde_json::to_vec(conversation)?, &conversation.id)?;
+  let tmp_path = path.with_extension("tmp");
+  fs::write(&tmp_path, encrypted)
+    .await
+    .map_err(|e| format!("Could not write chat conversation '{}': {}", tmp_path.display(), e))?;
+  fs::rename(&tmp_path, &path)
+    .await
+    .map_err(|e| format!("Could not replace chat conversation '{}': {}", path.display(), e).into())
+}
+
+/// Appends messages to a stored conversation, storing `conversation` first if it has not been
+/// stored yet. The stored conversation is read again under a per-conversation lock, so messages
+/// from concurrent turns are not lost.
+pub async fn append_chat_conversation_messages(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  conversation: ChatConversation,
+  messages: Vec<ChatConversationMessage>,
+) -> InfuResult<()> {
+  let _guard = CHAT_CONVERSATION_LOCKS.lock(&conversation.id).await;
+  let mut stored =
+    read_chat_conversation(data_dir, user_id, object_encryption_key, &conversation.id).await?.unwrap_or(conversation);
+  for message in messages {
+    stored.push_message(message);
+  }
+  write_chat_conversation(data_dir, user_id, object_encryption_key, &stored).await
+}
+
+/// Lists a user's conversations, most recently updated first. When `query` is non-empty, only
+/// conversations whose title or message text contains every whitespace separated term are returned.
+pub async fn list_chat_conversations(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  query: Option<&str>,
+) -> InfuResult<Vec<ChatConversationSummary>> {
+  let mut dir = user_chat_dir(data_dir, user_id)?;
+  dir.push(CHAT_CONVERSATIONS_DIRNAME);
+  let mut entries = match fs::read_dir(&dir).await {
+    Ok(entries) => entries,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => return Err(format!("Could not read chat conversations dir '{}': {}", dir.display(), e).into()),
+  };
+
+  let terms = query.unwrap_or("").split_whitespace().map(|term| term.to_lowercase()).collect::<Vec<_>>();
+  let mut summaries = Vec::new();
+  while let Some(entry) = entries.next_entry().await? {
+    let file_name = entry.file_name();
+    let Some(conversation_id) = file_name.to_str().filter(|name| is_uid(name)) else {
+      continue;
+    };
+    match read_chat_conversation(data_dir, user_id, object_encryption_key, conversation_id).await {
+      Ok(Some(conversation)) => {
+        if conversation.matches_query_terms(&terms) {
+          summaries.push(conversation.summary());
+        }
+      }
+      Ok(None) => {}
+      Err(e) => warn!("Skipping unreadable chat conversation '{}': {}", conversation_id, e),
+    }
+  }
+
+  summaries.sort_by(|a, b| b.updated_at_unix_secs.cmp(&a.updated_at_unix_secs).then_with(|| a.id.cmp(&b.id)));
+  Ok(summaries)
+}
+
+/// Per-user, size-rotated record of raw chat model traffic. Disabled unless
+/// enable_chat_transcripts is set, in which case every append is best effort.
+pub struct ChatTranscript {
+  path: Option<PathBuf>,
+  max_bytes: u64,
+  max_files: usize,
+}
+
+impl ChatTranscript {
+  pub fn disabled() -> ChatTranscript {
+    ChatTranscript { path: None, max_bytes: 0, max_files: 0 }
+  }
+
+  pub fn for_user(config: &Config, data_dir: &str, user_id: &str) -> ChatTranscript {
+    if !config.get_bool(CONFIG_ENABLE_CHAT_TRANSCRIPTS).unwrap_or(false) {
+      return ChatTranscript::disabled();
+    }
+    let path = match user_chat_dir(data_dir, user_id) {
+      Ok(mut path) => {
+        path.push(CHAT_TRANSCRIPTS_DIRNAME);
+        path.push(CHAT_TRANSCRIPT_FILENAME);
+        path
+      }
+      Err(e) => {
+        warn!("Could not resolve chat transcript path for user '{}': {}", user_id, e);
+        return ChatTranscript::disabled();
+      }
+    };
+    let max_mb = config.get_int(CONFIG_CHAT_TRANSCRIPT_MAX_MB).unwrap_or(0).max(1) as u64;
+    let max_files = config.get_int(CONFIG_CHAT_TRANSCRIPT_MAX_FILES).unwrap_or(0).max(0) as usize;
+    ChatTranscript { path: Some(path), max_bytes: max_mb * 1024 * 1024, max_files }
+  }
+
+  pub async fn append_section(&self, title: &str, body: &str) {
+    let Some(path) = self.path.as_ref() else {
+      return;
+    };
+    let entry = format!("\n===== {} =====\n{}\n", title, body);
+
+    let _guard = CHAT_TRANSCRIPT_WRITE_LOCK.lock().await;
+    if let Some(parent) = path.parent() {
+      if let Err(e) = fs::create_dir_all(parent).await {
+        warn!("Could not create chat transcript dir '{}': {}", parent.display(), e);
+        return;
+      }
+    }
+    let current_len = fs::metadata(path).await.map(|metadata| metadata.len()).unwrap_or(0);
+    if current_len > 0 && current_len + entry.len() as u64 > self.max_bytes {
+      self.rotate(path).await;
+    }
+    match fs::OpenOptions::new().create(true).append(true).open(path).await {
+      Ok(mut file) => {
+        if let Err(e) = file.write_all(entry.as_bytes()).await {
+          warn!("Could not write chat transcript '{}': {}", path.display(), e);
+        }
+      }
+      Err(e) => warn!("Could not open chat transcript '{}': {}", path.display(), e),
+    }
+  }
+
+  /// Appends a section, pretty printing the body if it is JSON.
+  pub async fn append_body_section(&self, title: &str, body: &str) {
+    if self.path.is_none() {
+      return;
+    }
+    let body = serde_json::from_str::<Value>(body)
+      .and_then(|value| serde_json::to_string_pretty(&value))
+      .unwrap_or_else(|_| body.to_owned());
+    self.append_section(title, &body).await;
+  }
+
+  pub async fn append_json_section<T: Serialize>(&self, title: &str, value: &T) {
+    if self.path.is_none() {
+      return;
+    }
+    let body = serde_json::to_string_pretty(value)
+      .unwrap_or_else(|e| format!("Could not serialize chat transcript value: {}", e));
+    self.append_section(title, &body).await;
+  }
+
+  async fn rotate(&self, path: &Path) {
+    if self.max_files == 0 {
+      if let Err(e) = fs::remove_file(path).await {
+        warn!("Could not remove chat transcript '{}': {}", path.display(), e);
+      }
+      return;
+    }
+    for index in (1..self.max_files).rev() {
+      let from = rotated_transcript_path(path, index);
+      if path_exists(&from).await {
+        let _ = fs::rename(&from, rotated_transcript_path(path, index + 1)).await;
+      }
+    }
+    if let Err(e) = fs::rename(path, rotated_transcript_path(path, 1)).await {
+      warn!("Could not rotate chat transcript '{}': {}", path.display(), e);
+    }
+  }
+}
+
+fn rotated_transcript_path(path: &Path, index: usize) -> PathBuf {
+  path.with_extension(format!("{}.log", index))
+}
@@ -14,13 +14,14 @@ use tokio::task;
 use tokio::time::sleep;
 
 use crate::ai::fragment::sources::{
-  build_markdown_fragment_artifact, build_pdf_fragment_artifact, build_text_fragment_artifact,
+  DocumentFormat, build_converted_document_fragment_artifact, build_markdown_fragment_artifact,
+  build_pdf_fragment_artifact, build_text_fragment_artifact,
 };
-use crate::ai::fragment::{FragmentBuildOutcome, clear_item_fragments, item_fragment_artifact_files_exist};
-use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
-use crate::ai::gpu_tools::{
-  GPU_TOOL_PDF_EXTRACT, GPU_TOOL_PDF_EXTRACT_CAPTION_ONLY, gpu_tools_url_from_config, resolve_gpu_tool_url,
+use crate::ai::fragment::{
+  FragmentBuildOutcome, clear_item_fragments, item_fragment_artifact_files_exist, item_fragment_artifacts_are_current,
 };
+use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
+use crate::ai::gpu_tools::{GPU_TOOL_PDF_EXTRACT_CAPTION_ONLY, gpu_tools_url_from_config, resolve_gpu_tool_url};
 use crate::ai::metrics::{METRIC_AI_DOCUMENT_FRAGMENT_PROCESSED_TOTAL, METRIC_AI_DOCUMENT_FRAGMENT_QUEUE_DEPTH};
 use crate::ai::text_extraction::{PdfTextArtifactState, pdf_text_artifact_state};
 use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
@@ -49,6 +50,7 @@ enum DocumentFragmentKind {
   Pdf,
   Markdown,
   Text,
+  Document(DocumentFormat),
 }
 
 impl DocumentFragmentKind {
@@ -57,7 +59,7 @@ impl DocumentFragmentKind {
       PDF_SOURCE_MIME_TYPE => Some(DocumentFragmentKind::Pdf),
       MARKDOWN_SOURCE_MIME_TYPE => Some(DocumentFragmentKind::Markdown),
       TEXT_SOURCE_MIME_TYPE => Some(DocumentFragmentKind::Text),
-      _ => None,
+      mime_type => DocumentFormat::from_mime_type(mime_type).map(DocumentFragmentKind::Document),
     }
   }
 
@@ -66,11 +68,12 @@ impl DocumentFragmentKind {
       DocumentFragmentKind::Pdf => "PDF",
       DocumentFragmentKind::Markdown => "Markdown",
       DocumentFragmentKind::Text => "text",
+      DocumentFragmentKind::Document(format) => format.label(),
     }
   }
 
   fn needs_source_object(self) -> bool {
-    matches!(self, DocumentFragmentKind::Markdown | DocumentFragmentKind::Text)
+    matches!(self, DocumentFragmentKind::Markdown | DocumentFragmentKind::Text | DocumentFragmentKind::Document(_))
   }
 }
 
@@ -267,7 +270,7 @@ async fn reconcile_document_fragment_item(
     }
   };
 
-  if item_fragment_artifact_files_exist(&config.data_dir, &item_snapshot.owner_id, &item_snapshot.id).await? {
+  if item_fragment_artifacts_are_current(&config.data_dir, &item_snapshot.owner_id, &item_snapshot.id).await? {
     return Ok(DocumentFragmentReconcileOutcome::Skipped);
   }
 
@@ -394,6 +397,21 @@ async fn build_document_fragment_artifact(
           .outcome,
       )
     }
+    DocumentFragmentKind::Document(format) => {
+      let object_encryption_key = object_encryption_key
+        .as_deref()
+        .ok_or(format!("{} fragmenting requires a source object encryption key.", format.label()))?;
+      Ok(
+        build_converted_document_fragment_artifact(
+          &config.data_dir,
+          config.object_store.clone(),
+          item,
+          object_encryption_key,
+        )
+        .await?
+        .outcome,
+      )
+    }
   }
 }
 
@@ -407,22 +425,12 @@ async fn document_fragment_readiness(
         PdfTextArtifactState::Succeeded => Ok(DocumentFragmentReadiness::Ready),
         PdfTextArtifactState::Failed => Ok(DocumentFragmentReadiness::Unavailable),
         PdfTextArtifactState::Blocked => Ok(DocumentFragmentReadiness::Blocked),
-        PdfTextArtifactState::Pending if pdf_text_extraction_available(config).await => {
-          Ok(DocumentFragmentReadiness::Waiting)
-        }
-        PdfTextArtifactState::Pending => Ok(DocumentFragmentReadiness::Unavailable),
+        // The in-process text layer extractor always runs, with or without GPU tools.
+        PdfTextArtifactState::Pending => Ok(DocumentFragmentReadiness::Waiting),
       }
     }
-    DocumentFragmentKind::Markdown | DocumentFragmentKind::Text => Ok(DocumentFragmentReadiness::Ready),
-  }
-}
-
-async fn pdf_text_extraction_available(config: &DocumentFragmentPipelineConfig) -> bool {
-  match resolve_gpu_tool_url(config.gpu_tools_url.as_deref(), GPU_TOOL_PDF_EXTRACT).await {
-    Ok(url) => url.is_some(),
-    Err(e) => {
-      debug!("Could not discover PDF text extraction GPU tool endpoint: {}", e);
-      config.gpu_tools_url.is_some()
+    DocumentFragmentKind::Markdown | DocumentFragmentKind::Text | DocumentFragmentKind::Document(_) => {
+      Ok(DocumentFragmentReadiness::Ready)
     }
   }
 }
@@ -456,6 +464,7 @@ async fn populate_initial_document_fragment_queue(
   let mut pdf_candidates = 0usize;
   let mut markdown_candidates = 0usize;
   let mut text_candidates = 0usize;
+  let mut converted_candidates = 0usize;
   let mut queued_candidates = Vec::new();
   let mut already_fragmented = 0usize;
   let mut ready = 0usize;
@@ -469,6 +478,7 @@ async fn populate_initial_document_fragment_queue(
       DocumentFragmentKind::Pdf => pdf_candidates += 1,
       DocumentFragmentKind::Markdown => markdown_candidates += 1,
       DocumentFragmentKind::Text => text_candidates += 1,
+      DocumentFragmentKind::Document(_) => converted_candidates += 1,
     }
 
     match item_fragment_artifact_files_exist(&config.data_dir, &candidate.user_id, &candidate.item_id).await {
@@ -531,11 +541,12 @@ async fn populate_initial_document_fragment_queue(
   };
 
   info!(
-    "Startup document fragment reconciliation saw {} document item(s) (pdf={}, markdown={}, text={}), queued {} of {}; fragments: already_present={}; readiness checked for missing fragments: ready={}, unavailable={}, blocked={}, waiting={}; skipped_errors={}.",
+    "Startup document fragment reconciliation saw {} document item(s) (pdf={}, markdown={}, text={}, converted={}), queued {} of {}; fragments: already_present={}; readiness checked for missing fragments: ready={}, unavailable={}, blocked={}, waiting={}; skipped_errors={}.",
     total_candidates,
     pdf_candidates,
     markdown_candidates,
     text_candidates,
+    converted_candidates,
     enqueued_count,
     queued_candidate_count,
     already_fragmented,
@@ -0,0 +1,589 @@
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use config::Config;
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::new_uid;
+use log::debug;
+use once_cell::sync::Lazy;
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::ai::artifact_paths::{
+  FACES_CONTENT_SUFFIX, ensure_user_text_dir, item_faces_content_path, item_faces_manifest_path,
+};
+use crate::ai::image_tagging::ImageTagFaceHints;
+use crate::ai::user_id_for_log;
+use crate::config::CONFIG_FACE_EMBED_URL;
+use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
+use crate::util::fs::{expand_tilde, path_exists};
+
+const FACES_MANIFEST_SCHEMA_VERSION: u32 = 1;
+const FACES_CONTENT_MIME_TYPE: &str = "application/json";
+const FACE_CLUSTERS_FILENAME: &str = "face_clusters.json";
+const FACE_CLUSTERS_SCHEMA_VERSION: u32 = 1;
+const FACE_EMBED_REQUEST_TIMEOUT_SECS: u64 = 5 * 60;
+/// Images the tagging service estimated to show no faces are still sent if it was at least this
+/// confident that a recognizable face is present.
+const FACE_HINT_MIN_CANDIDATE_CONFIDENCE: f64 = 0.2;
+/// Detections below this confidence are kept in the face artifact, but not clustered.
+const FACE_CLUSTER_MIN_DETECTION_CONFIDENCE: f32 = 0.6;
+/// A face joins the most similar cluster if its cosine similarity to the cluster centroid is at
+/// least this, otherwise it starts a new cluster.
+const FACE_CLUSTER_MIN_SIMILARITY: f32 = 0.6;
+
+/// Per-user clusters are loaded on first use and kept for the life of the process. Each user's
+/// clusters have their own lock.
+static USER_FACE_CLUSTERS: Lazy<std::sync::Mutex<HashMap<String, Arc<Mutex<Option<UserFaceClusters>>>>>> =
+  Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+pub struct FaceCandidate {
+  pub user_id: String,
+  pub item_id: String,
+  pub mime_type: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DetectedFace {
+  /// [left, top, right, bottom] as fractions of the image width and height.
+  #[serde(rename = "box")]
+  pub bounding_box: [f32; 4],
+  pub confidence: f32,
+  pub embedding: Vec<f32>,
+}
+
+/// The face embedding service response, which is also what is stored as the face artifact.
+#[derive(Serialize, Deserialize)]
+struct FaceArtifact {
+  #[serde(default)]
+  model_id: Option<String>,
+  #[serde(default)]
+  faces: Vec<DetectedFace>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FacesManifest {
+  schema_version: u32,
+  status: String,
+  source_mime_type: String,
+  content_mime_type: String,
+  extractor: FacesManifestExtractor,
+  error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FacesManifestExtractor {
+  face_embed_url: String,
+  detected_at_unix_secs: i64,
+  duration_ms: Option<u64>,
+  face_count: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct FacesManifestSummary {
+  #[serde(default)]
+  status: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacesManifestStatus {
+  Succeeded,
+  Failed,
+  Skipped,
+}
+
+pub enum FaceProcessOutcome {
+  Succeeded,
+  Failed,
+  /// The service could not be reached or returned a transient error. Nothing was written, so the
+  /// image is tried again on the next startup.
+  EndpointUnavailable(String),
+}
+
+/// A face in a cluster: the face at `face_index` in the face artifact of the item.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FaceRef {
+  pub item_id: String,
+  pub face_index: usize,
+  #[serde(rename = "box")]
+  pub bounding_box: [f32; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FaceCluster {
+  pub id: String,
+  pub name: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  model_id: Option<String>,
+  /// The sum of the normalized member embeddings, so faces can be added and removed without
+  /// re-reading the other members.
+  embedding_sum: Vec<f32>,
+  pub faces: Vec<FaceRef>,
+}
+
+impl FaceCluster {
+  fn similarity(&self, model_id: Option<&str>, embedding: &[f32]) -> Option<f32> {
+    if self.model_id.as_deref() != model_id || self.embedding_sum.len() != embedding.len() {
+      return None;
+    }
+    let norm = vector_norm(&self.embedding_sum);
+    if norm == 0.0 {
+      return None;
+    }
+    Some(self.embedding_sum.iter().zip(embedding.iter()).map(|(a, b)| a * b).sum::<f32>() / norm)
+  }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct UserFaceClusters {
+  schema_version: u32,
+  clusters: Vec<FaceCluster>,
+  /// Items whose face artifacts have been added to the clusters, including those without any faces.
+  clustered_item_ids: HashSet<String>,
+}
+
+impl UserFaceClusters {
+  /// Remove the faces of the item. Their embeddings are subtracted from the cluster centroids if
+  /// they are known. Empty clusters are dropped unless they have been named.
+  fn remove_item(&mut self, item_id: &str, faces: Option<&[DetectedFace]>) -> bool {
+    let mut changed = self.clustered_item_ids.remove(item_id);
+    for cluster in self.clusters.iter_mut() {
+      let before = cluster.faces.len();
+      cluster.faces.retain(|face| {
+        if face.item_id != item_id {
+          return true;
+        }
+        if let Some(embedding) = faces.and_then(|faces| faces.get(face.face_index)).and_then(normalized_embedding)
+          && embedding.len() == cluster.embedding_sum.len()
+        {
+          for (sum, value) in cluster.embedding_sum.iter_mut().zip(embedding) {
+            *sum -= value;
+          }
+        }
+        false
+      });
+      changed |= cluster.faces.len() != before;
+    }
+    let before = self.clusters.len();
+    self.clusters.retain(|cluster| !cluster.faces.is_empty() || cluster.name.is_some());
+    changed || self.clusters.len() != before
+  }
+
+  fn add_item(&mut self, item_id: &str, model_id: Option<&str>, faces: &[DetectedFace]) {
+    for (face_index, face) in faces.iter().enumerate() {
+      if face.confidence < FACE_CLUSTER_MIN_DETECTION_CONFIDENCE {
+        continue;
+      }
+      let Some(embedding) = normalized_embedding(face) else {
+        continue;
+      };
+      let face_ref = FaceRef { item_id: item_id.to_owned(), face_index, bounding_box: face.bounding_box };
+      let best = self
+        .clusters
+        .iter_mut()
+        .filter_map(|cluster| cluster.similarity(model_id, &embedding).map(|similarity| (similarity, cluster)))
+        .filter(|(similarity, _)| *similarity >= FACE_CLUSTER_MIN_SIMILARITY)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b));
+      match best {
+        Some((_, cluster)) => {
+          for (sum, value) in cluster.embedding_sum.iter_mut().zip(embedding) {
+            *sum += value;
+          }
+          cluster.faces.push(face_ref);
+        }
+        None => self.clusters.push(FaceCluster {
+          id: new_uid(),
+          name: None,
+          model_id: model_id.map(str::to_owned),
+          embedding_sum: embedding,
+          faces: vec![face_ref],
+        }),
+      }
+    }
+    self.clustered_item_ids.insert(item_id.to_owned());
+  }
+}
+
+pub fn face_embed_url_from_config(config: &Config) -> InfuResult<Option<reqwest::Url>> {
+  let Ok(raw_url) = config.get_string(CONFIG_FACE_EMBED_URL) else {
+    return Ok(None);
+  };
+  let trimmed_url = raw_url.trim();
+  if trimmed_url.is_empty() {
+    return Ok(None);
+  }
+  reqwest::Url::parse(trimmed_url)
+    .map(Some)
+    .map_err(|e| format!("Could not parse {} '{}': {}", CONFIG_FACE_EMBED_URL, trimmed_url, e).into())
+}
+
+/// False if the tagging service was confident there are no faces in the image.
+pub fn face_hints_suggest_faces(hints: &ImageTagFaceHints) -> bool {
+  match hints.visible_face_count_estimate.as_deref() {
+    Some("0") => hints.face_recognition_candidate_confidence >= FACE_HINT_MIN_CANDIDATE_CONFIDENCE,
+    _ => true,
+  }
+}
+
+pub async fn faces_manifest_status(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+) -> InfuResult<Option<FacesManifestStatus>> {
+  let manifest_path = item_faces_manifest_path(data_dir, user_id, item_id)?;
+  if !path_exists(&manifest_path).await {
+    return Ok(None);
+  }
+  let bytes = fs::read(&manifest_path).await?;
+  let manifest = match serde_json::from_slice::<FacesManifestSummary>(&bytes) {
+    Ok(manifest) => manifest,
+    Err(_) => return Ok(None),
+  };
+  Ok(match manifest.status.as_str() {
+    "succeeded" => Some(FacesManifestStatus::Succeeded),
+    "failed" => Some(FacesManifestStatus::Failed),
+    "skipped" => Some(FacesManifestStatus::Skipped),
+    _ => None,
+  })
+}
+
+/// Send the image to the face embedding service, store the faces it finds and add them to the
+/// user's face clusters, in place of any found before.
+pub async fn detect_and_cluster_item_faces(
+  data_dir: &str,
+  object_encryption_key: &str,
+  client: &reqwest::Client,
+  face_embed_url: &reqwest::Url,
+  candidate: &FaceCandidate,
+  request_mime_type: &str,
+  file_bytes: &[u8],
+) -> InfuResult<FaceProcessOutcome> {
+  let started_at = Instant::now();
+  let part = Part::bytes(file_bytes.to_vec())
+    .mime_str(request_mime_type)
+    .map_err(|e| format!("Could not build multipart upload: {}", e))?;
+  let response = match client.post(face_embed_url.clone()).multipart(Form::new().part("file", part)).send().await {
+    Ok(response) => response,
+    Err(e) => return Ok(FaceProcessOutcome::EndpointUnavailable(e.to_string())),
+  };
+  let status = response.status();
+  let body = match response.text().await {
+    Ok(body) => body,
+    Err(e) => return Ok(FaceProcessOutcome::EndpointUnavailable(format!("Could not read response body: {}", e))),
+  };
+  let duration_ms = Some(started_at.elapsed().as_millis().min(u128::from(u64::MAX)) as u64);
+
+  if matches!(status, reqwest::StatusCode::UNPROCESSABLE_ENTITY | reqwest::StatusCode::PAYLOAD_TOO_LARGE) {
+    let message = format!("HTTP {}: {}", status, body);
+    write_faces_manifest(data_dir, candidate, face_embed_url, "failed", None, duration_ms, Some(&message)).await?;
+    return Ok(FaceProcessOutcome::Failed);
+  }
+  if !status.is_success() {
+    return Ok(FaceProcessOutcome::EndpointUnavailable(format!("HTTP {}: {}", status, body)));
+  }
+  let artifact = match serde_json::from_str::<FaceArtifact>(&body) {
+    Ok(artifact) => artifact,
+    Err(e) => {
+      let message = format!("Could not parse face embedding response: {}", e);
+      write_faces_manifest(data_dir, candidate, face_embed_url, "failed", None, duration_ms, Some(&message)).await?;
+      return Ok(FaceProcessOutcome::Failed);
+    }
+  };
+
+  let (user_id, item_id) = (candidate.user_id.as_str(), candidate.item_id.as_str());
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  // The embeddings of faces found before are subtracted from the clusters, so must be read before
+  // they are replaced.
+  let previous_artifact = read_face_artifact(data_dir, user_id, item_id, object_encryption_key).await.ok().flatten();
+  ensure_user_text_dir(data_dir, user_id).await?;
+  let content_path = item_faces_content_path(data_dir, user_id, item_id)?;
+  let encrypted =
+    encrypt_file_data(object_encryption_key, &serde_json::to_vec(&artifact)?, &faces_content_filename(item_id))?;
+  fs::write(&content_path, encrypted).await?;
+  let face_count = artifact.faces.len();
+  write_faces_manifest(data_dir, candidate, face_embed_url, "succeeded", Some(face_count), duration_ms, None).await?;
+
+  let clusters = user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?;
+  clusters.remove_item(item_id, previous_artifact.as_ref().map(|artifact| artifact.faces.as_slice()));
+  clusters.add_item(item_id, artifact.model_id.as_deref(), &artifact.faces);
+  write_face_clusters(data_dir, user_id, object_encryption_key, clusters).await?;
+  debug!("Detected {} face(s) in image '{}' (user {}).", face_count, item_id, user_id_for_log(user_id));
+  Ok(FaceProcessOutcome::Succeeded)
+}
+
+/// Record that the image was not sent to the face embedding service, so it is not considered again.
+pub async fn write_skipped_faces_manifest(
+  data_dir: &str,
+  candidate: &FaceCandidate,
+  face_embed_url: &reqwest::Url,
+  reason: &str,
+) -> InfuResult<()> {
+  write_faces_manifest(data_dir, candidate, face_embed_url, "skipped", None, None, Some(reason)).await
+}
+
+/// Add the faces of an item with a face artifact to the user's face clusters if they are not there
+/// already, for example because the clusters file was deleted. Returns whether the item was added.
+pub async fn ensure_item_faces_clustered(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  item_id: &str,
+) -> InfuResult<bool> {
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  let clusters = user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?;
+  if clusters.clustered_item_ids.contains(item_id) {
+    return Ok(false);
+  }
+  let Some(artifact) = read_face_artifact(data_dir, user_id, item_id, object_encryption_key).await? else {
+    return Ok(false);
+  };
+  clusters.add_item(item_id, artifact.model_id.as_deref(), &artifact.faces);
+  write_face_clusters(data_dir, user_id, object_encryption_key, clusters).await?;
+  Ok(true)
+}
+
+/// The user's face clusters, largest first.
+pub async fn list_face_clusters(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+) -> InfuResult<Vec<FaceCluster>> {
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  let mut clusters =
+    user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?.clusters.clone();
+  clusters.sort_by(|a, b| b.faces.len().cmp(&a.faces.len()).then_with(|| a.id.cmp(&b.id)));
+  Ok(clusters)
+}
+
+/// Set or clear the name of a face cluster. Several clusters may be given the same name, for
+/// example when the faces of one person were split into more than one cluster.
+pub async fn name_face_cluster(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  cluster_id: &str,
+  name: Option<&str>,
+) -> InfuResult<()> {
+  let name = name.map(str::trim).filter(|name| !name.is_empty());
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  let clusters = user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?;
+  let cluster = clusters
+    .clusters
+    .iter_mut()
+    .find(|cluster| cluster.id == cluster_id)
+    .ok_or(format!("Unknown face cluster '{}'.", cluster_id))?;
+  cluster.name = name.map(str::to_owned);
+  clusters.clusters.retain(|cluster| !cluster.faces.is_empty() || cluster.name.is_some());
+  write_face_clusters(data_dir, user_id, object_encryption_key, clusters).await
+}
+
+/// The items with a face in a cluster of the given name (compared case-insensitively), or None if
+/// no cluster has that name.
+pub async fn item_ids_for_face_name(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  name: &str,
+) -> InfuResult<Option<HashSet<String>>> {
+  let name = name.trim().to_lowercase();
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  let clusters = user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?;
+  let mut named = false;
+  let mut item_ids = HashSet::new();
+  for cluster in clusters.clusters.iter() {
+    if cluster.name.as_ref().is_some_and(|cluster_name| cluster_name.to_lowercase() == name) {
+      named = true;
+      item_ids.extend(cluster.faces.iter().map(|face| face.item_id.clone()));
+    }
+  }
+  Ok(named.then_some(item_ids))
+}
+
+/// Remove the face artifacts of deleted items and their faces from the user's face clusters.
+pub async fn delete_items_face_artifacts(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  item_ids: &[String],
+) -> InfuResult<()> {
+  let mut user_clusters = lock_user_face_clusters(user_id).await;
+  let clusters = user_face_clusters(&mut user_clusters, data_dir, user_id, object_encryption_key).await?;
+  let mut clusters_changed = false;
+  for item_id in item_ids {
+    let artifact = read_face_artifact(data_dir, user_id, item_id, object_encryption_key).await.ok().flatten();
+    clusters_changed |= clusters.remove_item(item_id, artifact.as_ref().map(|artifact| artifact.faces.as_slice()));
+    let manifest_path = item_faces_manifest_path(data_dir, user_id, item_id)?;
+    let content_path = item_faces_content_path(data_dir, user_id, item_id)?;
+    if path_exists(&manifest_path).await {
+      fs::remove_file(&manifest_path).await?;
+    }
+    if path_exists(&content_path).await {
+      fs::remove_file(&content_path).await?;
+    }
+  }
+  if clusters_changed {
+    write_face_clusters(data_dir, user_id, object_encryption_key, clusters).await?;
+  }
+  Ok(())
+}
+
+async fn write_faces_manifest(
+  data_dir: &str,
+  candidate: &FaceCandidate,
+  face_embed_url: &reqwest::Url,
+  status: &str,
+  face_count: Option<usize>,
+  duration_ms: Option<u64>,
+  error: Option<&str>,
+) -> InfuResult<()> {
+  let (user_id, item_id) = (candidate.user_id.as_str(), candidate.item_id.as_str());
+  ensure_user_text_dir(data_dir, user_id).await?;
+  if status != "succeeded" {
+    let content_path = item_faces_content_path(data_dir, user_id, item_id)?;
+    if path_exists(&content_path).await {
+      fs::remove_file(&content_path).await?;
+    }
+  }
+  let manifest = FacesManifest {
+    schema_version: FACES_MANIFEST_SCHEMA_VERSION,
+    status: status.to_owned(),
+    source_mime_type: candidate.mime_type.clone(),
+    content_mime_type: FACES_CONTENT_MIME_TYPE.to_owned(),
+    extractor: FacesManifestExtractor {
+      face_embed_url: face_embed_url.to_string(),
+      detected_at_unix_secs: unix_now_secs()?,
+      duration_ms,
+      face_count,
+    },
+    error: error.map(str::to_owned),
+  };
+  let manifest_path = item_faces_manifest_path(data_dir, user_id, item_id)?;
+  fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+  Ok(())
+}
+
+async fn read_face_artifact(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+  object_encryption_key: &str,
+) -> InfuResult<Option<FaceArtifact>> {
+  let content_path = item_faces_content_path(data_dir, user_id, item_id)?;
+  let encrypted = match fs::read(&content_path).await {
+    Ok(bytes) => bytes,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(format!("Could not read face artifact '{}': {}", content_path.display(), e).into()),
+  };
+  let bytes = decrypt_file_data(object_encryption_key, &encrypted, &faces_content_filename(item_id))?;
+  serde_json::from_slice(&bytes)
+    .map(Some)
+    .map_err(|e| format!("Could not parse face artifact '{}': {}", content_path.display(), e).into())
+}
+
+async fn lock_user_face_clusters(user_id: &str) -> OwnedMutexGuard<Option<UserFaceClusters>> {
+  let user_clusters = USER_FACE_CLUSTERS.lock().unwrap().entry(user_id.to_owned()).or_default().clone();
+  user_clusters.lock_owned().await
+}
+
+async fn user_face_clusters<'a>(
+  user_clusters: &'a mut Option<UserFaceClusters>,
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+) -> InfuResult<&'a mut UserFaceClusters> {
+  if user_clusters.is_none() {
+    *user_clusters = Some(read_face_clusters(data_dir, user_id, object_encryption_key).await?);
+  }
+  Ok(user_clusters.as_mut().unwrap())
+}
+
+fn faces_content_filename(item_id: &str) -> String {
+  format!("{}{}", item_id, FACES_CONTENT_SUFFIX)
+}
+
+fn face_clusters_path(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = expand_tilde(data_dir).ok_or("Could not interpret path.")?;
+  path.push(format!("user_{}", user_id));
+  path.push(FACE_CLUSTERS_FILENAME);
+  Ok(path)
+}
+
+async fn read_face_clusters(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+) -> InfuResult<UserFaceClusters> {
+  let path = face_clusters_path(data_dir, user_id)?;
+  let encrypted = match fs::read(&path).await {
+    Ok(bytes) => bytes,
+    Err(e) if e.kind() == ErrorKind::NotFound => {
+      return Ok(UserFaceClusters { schema_version: FACE_CLUSTERS_SCHEMA_VERSION, ..Default::default() });
+    }
+    Err(e) => return Err(format!("Could not read face clusters '{}': {}", path.display(), e).into()),
+  };
+  let bytes = decrypt_file_data(object_encryption_key, &encrypted, FACE_CLUSTERS_FILENAME)?;
+  let clusters: UserFaceClusters =
+    serde_json::from_slice(&bytes).map_err(|e| format!("Could not parse face clusters '{}': {}", path.display(), e))?;
+  if clusters.schema_version != FACE_CLUSTERS_SCHEMA_VERSION {
+    return Err(
+      format!("Face clusters '{}' have unsupported schema version {}.", path.display(), clusters.schema_version).into(),
+    );
+  }
+  Ok(clusters)
+}
+
+async fn write_face_clusters(
+  data_dir: &str,
+  user_id: &str,
+  object_encryption_key: &str,
+  clusters: &UserFaceClusters,
+) -> InfuResult<()> {
+  let path = face_clusters_path(data_dir, user_id)?;
+  let dir = path.parent().ok_or("Face clusters path has no parent directory.")?.to_path_buf();
+  if !path_exists(&dir).await {
+    fs::create_dir_all(&dir).await?;
+  }
+  let bytes = serde_json::to_vec(clusters).map_err(|e| format!("Could not serialize face clusters: {}", e))?;
+  let bytes = encrypt_file_data(object_encryption_key, &bytes, FACE_CLUSTERS_FILENAME)?;
+  let temp_path = path.with_extension("json.tmp");
+  fs::write(&temp_path, bytes).await?;
+  fs::rename(&temp_path, &path)
+    .await
+    .map_err(|e| format!("Could not replace face clusters '{}': {}", path.display(), e).into())
+}
+
+fn normalized_embedding(face: &DetectedFace) -> Option<Vec<f32>> {
+  if face.embedding.is_empty() || face.embedding.iter().any(|value| !value.is_finite()) {
+    return None;
+  }
+  let norm = vector_norm(&face.embedding);
+  if norm == 0.0 {
+    return None;
+  }
+  Some(face.embedding.iter().map(|value| value / norm).collect())
+}
+
+fn vector_norm(vector: &[f32]) -> f32 {
+  vector.iter().map(|value| value * value).sum::<f32>().sqrt()
+}
+
+fn unix_now_secs() -> InfuResult<i64> {
+  Ok(
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_err(|e| format!("Could not determine current unix time: {}", e))?
+      .as_secs() as i64,
+  )
+}
+
+pub fn face_embed_http_client() -> InfuResult<reqwest::Client> {
+  reqwest::ClientBuilder::new()
+    .timeout(Duration::from_secs(FACE_EMBED_REQUEST_TIMEOUT_SECS))
+    .build()
+    .map_err(|e| format!("Could not build face embedding HTTP client: {}", e).into())
+}
@@ -16,7 +16,8 @@ use crate::ai::artifact_paths::{
 use crate::ai::user_id_for_log;
 use crate::util::fs::{ensure_256_subdirs, path_exists};
 
-use super::types::{FragmentBuildOutcome, FragmentInput, FragmentSourceKind};
+use super::strategy::{ChunkingStrategy, chunking_strategy_for_source_kind};
+use super::types::{FragmentBuildOutcome, FragmentInput, FragmentSourceKind, is_time_offset_source_kind};
 
 const FRAGMENTS_SCHEMA_VERSION: u32 = 1;
 const FRAGMENTER_VERSION: u32 = 15;
@@ -35,6 +36,12 @@ pub struct ItemFragmentRecord {
   pub page_start: Option<usize>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub page_end: Option<usize>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub row_item_id: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub time_start_secs: Option<usize>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub time_end_secs: Option<usize>,
 }
 
 pub struct ItemFragments {
@@ -50,6 +57,20 @@ struct FragmentsManifest {
   source_text_sha256: String,
   generated_at_unix_secs: i64,
   fragment_count: usize,
+  /// The chunking strategy the fragments were cut with, for source kinds that are chunked. Manifests
+  /// written before strategies were configurable don't have this, and used the default strategy.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  chunking_strategy: Option<String>,
+}
+
+impl FragmentsManifest {
+  fn chunking_strategy_is_current(&self) -> bool {
+    let Some(current) = chunking_strategy_for_source_kind(&self.source_kind) else {
+      return true;
+    };
+    let recorded = self.chunking_strategy.clone().unwrap_or_else(|| ChunkingStrategy::default().to_string());
+    recorded == current.to_string()
+  }
 }
 
 #[allow(dead_code)]
@@ -85,11 +106,7 @@ pub async fn write_item_fragments(
     .into_iter()
     .filter_map(|fragment| {
       let text = fragment.text.trim().to_owned();
-      if text.is_empty() {
-        None
-      } else {
-        Some(FragmentInput { text, page_start: fragment.page_start, page_end: fragment.page_end })
-      }
+      if text.is_empty() { None } else { Some(FragmentInput { text, ..fragment }) }
     })
     .collect::<Vec<FragmentInput>>();
 
@@ -102,8 +119,7 @@ pub async fn write_item_fragments(
   let fragments_path = item_fragments_path(data_dir, &item.owner_id, &item.id)?;
   let manifest_path = item_fragments_manifest_path(data_dir, &item.owner_id, &item.id)?;
 
-  let source_text_sha256 =
-    sha256_hex(&fragments.iter().map(|fragment| fragment.text.as_str()).collect::<Vec<_>>().join("\n\n"));
+  let source_text_sha256 = fragments_sha256(&fragments);
   let source_kind_str = source_kind.as_str();
   if existing_fragments_are_current(
     &fragments_path,
@@ -127,6 +143,9 @@ pub async fn write_item_fragments(
       text: fragment.text.clone(),
       page_start: fragment.page_start,
       page_end: fragment.page_end,
+      row_item_id: fragment.row_item_id.clone(),
+      time_start_secs: fragment.time_start_secs,
+      time_end_secs: fragment.time_end_secs,
     };
     let mut line = serde_json::to_vec(&record)?;
     line.push(b'\n');
@@ -141,6 +160,7 @@ pub async fn write_item_fragments(
     source_text_sha256,
     generated_at_unix_secs: unix_now_secs()?,
     fragment_count: fragments.len(),
+    chunking_strategy: chunking_strategy_for_source_kind(source_kind_str).map(|strategy| strategy.to_string()),
   };
   fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
 
@@ -195,6 +215,58 @@ pub async fn item_fragment_artifact_files_exist(data_dir: &str, user_id: &str, i
   Ok(path_exists(&fragments_path).await && path_exists(&manifest_path).await)
 }
 
+/// Whether the fragment artifacts of an item exist and were cut with the currently configured
+/// chunking strategy for their source kind.
+pub async fn item_fragment_artifacts_are_current(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<bool> {
+  let fragments_path = item_fragments_path(data_dir, user_id, item_id)?;
+  let manifest_path = item_fragments_manifest_path(data_dir, user_id, item_id)?;
+  let Some(manifest) = read_fragments_manifest_if_present(&fragments_path, &manifest_path).await? else {
+    return Ok(path_exists(&fragments_path).await && path_exists(&manifest_path).await);
+  };
+  if !manifest.chunking_strategy_is_current() {
+    return Ok(false);
+  }
+  if is_time_offset_source_kind(&manifest.source_kind) {
+    // Transcript fragments written before time offsets had their own fields kept them in the page range.
+    let records = read_item_fragments(data_dir, user_id, item_id).await?.records;
+    return Ok(records.iter().all(|record| record.time_start_secs.is_some() && record.time_end_secs.is_some()));
+  }
+  Ok(true)
+}
+
+/// Whether the item has fragment artifacts cut with a chunking strategy other than the one currently
+/// configured for their source kind.
+pub async fn item_fragment_chunking_strategy_changed(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<bool> {
+  let fragments_path = item_fragments_path(data_dir, user_id, item_id)?;
+  let manifest_path = item_fragments_manifest_path(data_dir, user_id, item_id)?;
+  Ok(
+    read_fragments_manifest_if_present(&fragments_path, &manifest_path)
+      .await?
+      .is_some_and(|manifest| !manifest.chunking_strategy_is_current()),
+  )
+}
+
+/// Digest of the fragment texts, and of the row items of table row fragments and the time ranges of
+/// transcript fragments (so that fragments written before these were recorded are rewritten). Fragments
+/// with neither hash as their text alone.
+fn fragments_sha256(fragments: &[FragmentInput]) -> String {
+  let mut source_text = fragments.iter().map(|fragment| fragment.text.as_str()).collect::<Vec<_>>().join("\n\n");
+  let row_item_ids = fragments.iter().filter_map(|fragment| fragment.row_item_id.as_deref()).collect::<Vec<_>>();
+  if !row_item_ids.is_empty() {
+    source_text.push_str("\n\n");
+    source_text.push_str(&row_item_ids.join("\n"));
+  }
+  let time_ranges = fragments
+    .iter()
+    .filter_map(|fragment| Some(format!("{}-{}", fragment.time_start_secs?, fragment.time_end_secs?)))
+    .collect::<Vec<_>>();
+  if !time_ranges.is_empty() {
+    source_text.push_str("\n\n");
+    source_text.push_str(&time_ranges.join("\n"));
+  }
+  sha256_hex(&source_text)
+}
+
 fn sha256_hex(text: &str) -> String {
   let mut hasher = Sha256::new();
   hasher.update(text.as_bytes());
@@ -217,7 +289,8 @@ async fn existing_fragments_are_current(
       && manifest.fragmenter_version == FRAGMENTER_VERSION
       && manifest.source_kind == source_kind
       && manifest.source_text_sha256 == source_text_sha256
-      && manifest.fragment_count == fragment_count,
+      && manifest.fragment_count == fragment_count
+      && manifest.chunking_strategy_is_current(),
   )
 }
 
@@ -1,13 +1,16 @@
 mod artifacts;
+mod strategy;
 mod types;
 
 pub mod sources;
 
 pub use artifacts::{
   ItemFragmentRecord, ItemFragments, clear_item_fragments, delete_item_fragment_artifacts,
-  item_fragment_artifact_files_exist, read_item_fragments, write_item_fragments,
+  item_fragment_artifact_files_exist, item_fragment_artifacts_are_current, item_fragment_chunking_strategy_changed,
+  read_item_fragments, write_item_fragments,
 };
+pub use strategy::{ChunkingMode, ChunkingStrategy, chunking_strategy_for_source_kind, init_chunking_strategies};
 pub use types::{
   FragmentBuildOutcome, FragmentInput, FragmentSource, FragmentSourceKind, ITEM_TITLE_SOURCE_KIND,
-  is_lexical_search_source_kind, is_markdown_document_source_kind,
+  TABLE_ROWS_SOURCE_KIND, is_lexical_search_source_kind, is_markdown_document_source_kind, is_time_offset_source_kind,
 };
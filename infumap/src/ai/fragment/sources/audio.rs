@@ -0,0 +1,90 @@
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+use serde::{Deserialize, Serialize};
+
+use super::super::chunking_strategy_for_source_kind;
+use super::{FragmentInput, FragmentSource, FragmentSourceKind, normalized_text, write_fragment_source_artifact};
+use crate::ai::fragment::FragmentBuildOutcome;
+
+/// One timestamped span of a transcript, as returned by a whisper-compatible transcription endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioTranscriptSegment {
+  pub start: f64,
+  pub end: f64,
+  pub text: String,
+}
+
+/// Group consecutive transcript segments into fragments of the configured target size. With an
+/// overlap, each fragment after the first starts with the last segments of the one before it. The page
+/// range of each fragment holds the time span it covers, in whole seconds from the start of the
+/// recording.
+pub fn audio_transcript_fragment_source(segments: &[AudioTranscriptSegment]) -> Option<FragmentSource> {
+  let strategy = chunking_strategy_for_source_kind(FragmentSourceKind::AudioTranscript.as_str()).unwrap_or_default();
+  let segments = segments
+    .iter()
+    .filter_map(|segment| normalized_text(Some(&segment.text)).map(|text| (segment, text)))
+    .collect::<Vec<_>>();
+
+  let mut fragments = Vec::new();
+  let mut start = 0;
+  while start < segments.len() {
+    let mut end = start;
+    let mut text_chars = 0;
+    while end < segments.len()
+      && (end == start || text_chars + segments[end].1.chars().count() <= strategy.soft_limit_chars())
+    {
+      text_chars += segments[end].1.chars().count() + 1;
+      end += 1;
+    }
+    fragments.push(transcript_fragment_input(&segments[start..end]));
+    if end >= segments.len() {
+      break;
+    }
+
+    let mut next_start = end;
+    let mut overlap_tokens = 0;
+    while next_start > start + 1 {
+      let segment_tokens = segments[next_start - 1].1.split_whitespace().count();
+      if overlap_tokens + segment_tokens > strategy.overlap_tokens {
+        break;
+      }
+      next_start -= 1;
+      overlap_tokens += segment_tokens;
+    }
+    start = next_start;
+  }
+
+  if fragments.is_empty() {
+    return None;
+  }
+  Some(FragmentSource { source_kind: FragmentSourceKind::AudioTranscript, fragments })
+}
+
+/// Fragment the transcript of an audio item. With no transcript, any existing fragments for the item
+/// are cleared.
+pub async fn build_audio_transcript_fragment_artifact(
+  data_dir: &str,
+  item: &Item,
+  segments: Option<&[AudioTranscriptSegment]>,
+) -> InfuResult<FragmentBuildOutcome> {
+  let fragment_source = segments.and_then(audio_transcript_fragment_source);
+  write_fragment_source_artifact(data_dir, item, fragment_source).await
+}
+
+fn transcript_fragment_input(segments: &[(&AudioTranscriptSegment, String)]) -> FragmentInput {
+  let fragment = FragmentInput::new(segments.iter().map(|(_, text)| text.as_str()).collect::<Vec<_>>().join(" "));
+  let span = segments.iter().fold(None, |span: Option<(f64, f64)>, (segment, _)| {
+    Some(match span {
+      Some((start, end)) => (start.min(segment.start), end.max(segment.end)),
+      None => (segment.start, segment.end),
+    })
+  });
+  match span {
+    Some((start, end)) => {
+      let start_secs = start.max(0.0).floor() as usize;
+      let end_secs = (end.max(0.0).ceil() as usize).max(start_secs);
+      fragment.with_time_range(start_secs, end_secs)
+    }
+    None => fragment,
+  }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use infusdk::util::infu::InfuResult;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use super::{DocumentArchive, MarkdownBuilder, attribute_value, read_zip_text};
+
+const DOCUMENT_PART: &str = "word/document.xml";
+const STYLES_PART: &str = "word/styles.xml";
+const MAX_STYLE_INHERITANCE_DEPTH: usize = 8;
+
+/// Office Open XML word processing documents. Only the main document part is read; headers,
+/// footers, comments and footnotes are left out of search text.
+pub(super) fn convert(archive: &mut DocumentArchive<'_>, markdown: &mut MarkdownBuilder) -> InfuResult<()> {
+  let document_xml =
+    read_zip_text(archive, DOCUMENT_PART)?.ok_or(format!("DOCX archive has no '{}' part.", DOCUMENT_PART))?;
+  let heading_styles = match read_zip_text(archive, STYLES_PART)? {
+    Some(styles_xml) => heading_levels_by_style_id(&styles_xml)?,
+    None => HashMap::new(),
+  };
+
+  let mut reader = Reader::from_str(&document_xml);
+  let mut paragraph = DocxParagraph::default();
+  let mut paragraph_depth = 0usize;
+  let mut in_text = false;
+  let mut skip_depth = 0usize;
+  let mut table_depth = 0usize;
+  let mut row = Vec::<String>::new();
+  let mut cell = None::<String>;
+
+  loop {
+    let event = reader.read_event().map_err(|e| format!("Could not parse '{}': {}", DOCUMENT_PART, e))?;
+    match event {
+      Event::Start(ref e) | Event::Empty(ref e) => {
+        let is_empty = matches!(event, Event::Empty(_));
+        let local_name = e.local_name();
+        let name = local_name.as_ref();
+        if skip_depth > 0 {
+          if !is_empty {
+            skip_depth += 1;
+          }
+          continue;
+        }
+        match name {
+          b"delText" | b"instrText" | b"footnoteReference" | b"commentReference" if !is_empty => skip_depth = 1,
+          b"p" if !is_empty => {
+            if paragraph_depth == 0 {
+              paragraph = DocxParagraph::default();
+            }
+            paragraph_depth += 1;
+          }
+          b"pStyle" => {
+            if let Some(style_id) = attribute_value(e, b"w:val") {
+              paragraph.heading_level = heading_styles
+                .get(&style_id)
+                .copied()
+                .or_else(|| heading_level_from_style_name(&style_id))
+                .or(paragraph.heading_level);
+            }
+          }
+          b"outlineLvl" => {
+            if let Some(level) = attribute_value(e, b"w:val").and_then(|value| value.parse::<usize>().ok()) {
+              // Level 9 means body text.
+              paragraph.heading_level = (level < 9).then_some(level + 1);
+            }
+          }
+          b"numPr" => paragraph.list_level = Some(paragraph.list_level.unwrap_or(0)),
+          b"ilvl" => {
+            paragraph.list_level = attribute_value(e, b"w:val").and_then(|value| value.parse::<usize>().ok());
+          }
+          b"t" if !is_empty => in_text = true,
+          b"tab" if paragraph_depth > 0 => paragraph.text.push(' '),
+          b"br" | b"cr" if paragraph_depth > 0 => paragraph.text.push('\n'),
+          b"tbl" if !is_empty => table_depth += 1,
+          b"tr" if !is_empty && table_depth == 1 => row.clear(),
+          b"tc" if !is_empty && table_depth == 1 => cell = Some(String::new()),
+          _ => {}
+        }
+      }
+      Event::End(ref e) => {
+        if skip_depth > 0 {
+          skip_depth -= 1;
+          continue;
+        }
+        match e.local_name().as_ref() {
+          b"t" => in_text = false,
+          b"p" if paragraph_depth > 0 => {
+            paragraph_depth -= 1;
+            if paragraph_depth == 0 {
+              match cell.as_mut() {
+                Some(cell) => {
+                  cell.push(' ');
+                  cell.push_str(&paragraph.text);
+                }
+                None => paragraph.emit(markdown),
+              }
+            }
+          }
+          b"tc" if table_depth == 1 => {
+            if let Some(cell) = cell.take() {
+              row.push(cell);
+            }
+          }
+          b"tr" if table_depth == 1 => {
+            markdown.table_row(&row);
+            row.clear();
+          }
+          b"tbl" => table_depth = table_depth.saturating_sub(1),
+          _ => {}
+        }
+      }
+      Event::Text(ref e) if in_text && skip_depth == 0 && paragraph_depth > 0 => {
+        let text = e.unescape().map_err(|e| format!("Could not parse '{}': {}", DOCUMENT_PART, e))?;
+        paragraph.text.push_str(&text);
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+#[derive(Default)]
+struct DocxParagraph {
+  text: String,
+  heading_level: Option<usize>,
+  list_level: Option<usize>,
+}
+
+impl DocxParagraph {
+  fn emit(&self, markdown: &mut MarkdownBuilder) {
+    match (self.heading_level, self.list_level) {
+      (Some(level), _) => markdown.heading(level, &self.text),
+      (None, Some(depth)) => markdown.list_item(depth, &self.text),
+      (None, None) => markdown.paragraph(&self.text),
+    }
+  }
+}
+
+struct DocxStyle {
+  heading_level: Option<usize>,
+  based_on: Option<String>,
+}
+
+/// Map paragraph style ids to heading levels using the style name ("heading 1", "Title") or an
+/// explicit outline level, following basedOn so custom heading styles are recognized.
+fn heading_levels_by_style_id(styles_xml: &str) -> InfuResult<HashMap<String, usize>> {
+  let mut reader = Reader::from_str(styles_xml);
+  let mut styles = HashMap::<String, DocxStyle>::new();
+  let mut current = None::<(String, DocxStyle)>;
+
+  loop {
+    let event = reader.read_event().map_err(|e| format!("Could not parse '{}': {}", STYLES_PART, e))?;
+    match event {
+      Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+        b"style" => {
+          let is_paragraph_style = attribute_value(e, b"w:type").as_deref() == Some("paragraph");
+          current = match (is_paragraph_style, attribute_value(e, b"w:styleId")) {
+            (true, Some(style_id)) => Some((style_id, DocxStyle { heading_level: None, based_on: None })),
+            _ => None,
+          };
+          if matches!(event, Event::Empty(_)) {
+            current = None;
+          }
+        }
+        b"name" => {
+          if let Some((_, style)) = current.as_mut()
+            && let Some(name) = attribute_value(e, b"w:val")
+          {
+            style.heading_level = style.heading_level.or_else(|| heading_level_from_style_name(&name));
+          }
+        }
+        b"basedOn" => {
+          if let Some((_, style)) = current.as_mut() {
+            style.based_on = attribute_value(e, b"w:val");
+          }
+        }
+        b"outlineLvl" => {
+          if let Some((_, style)) = current.as_mut()
+            && let Some(level) = attribute_value(e, b"w:val").and_then(|value| value.parse::<usize>().ok())
+            && level < 9
+          {
+            style.heading_level = Some(level + 1);
+          }
+        }
+        _ => {}
+      },
+      Event::End(ref e) if e.local_name().as_ref() == b"style" => {
+        if let Some((style_id, style)) = current.take() {
+          styles.insert(style_id, style);
+        }
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+
+  let mut levels = HashMap::new();
+  for style_id in styles.keys() {
+    let mut next = Some(style_id);
+    for _ in 0..MAX_STYLE_INHERITANCE_DEPTH {
+      let Some(style) = next.and_then(|style_id| styles.get(style_id)) else {
+        break;
+      };
+      if let Some(level) = style.heading_level {
+        levels.insert(style_id.clone(), level);
+        break;
+      }
+      next = style.based_on.as_ref();
+    }
+  }
+  Ok(levels)
+}
+
+fn heading_level_from_style_name(name: &str) -> Option<usize> {
+  let normalized = name.to_ascii_lowercase().replace(' ', "");
+  if normalized == "title" {
+    return Some(1);
+  }
+  normalized.strip_prefix("heading")?.parse::<usize>().ok().filter(|level| (1..=9).contains(level))
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use infusdk::util::infu::InfuResult;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use super::{DocumentArchive, MarkdownBuilder, attribute_value, html, read_zip_text};
+use crate::util::str::percent_decode;
+
+const CONTAINER_PART: &str = "META-INF/container.xml";
+
+struct EpubManifestItem {
+  href: String,
+  media_type: String,
+}
+
+/// EPUB books: chapters are read in spine (reading) order and converted as XHTML.
+pub(super) fn convert(archive: &mut DocumentArchive<'_>, markdown: &mut MarkdownBuilder) -> InfuResult<()> {
+  let container_xml =
+    read_zip_text(archive, CONTAINER_PART)?.ok_or(format!("EPUB archive has no '{}' part.", CONTAINER_PART))?;
+  let package_path = package_document_path(&container_xml)?;
+  let package_xml = read_zip_text(archive, &package_path)?
+    .ok_or(format!("EPUB package document '{}' is missing from the archive.", package_path))?;
+  let package_dir = match package_path.rfind('/') {
+    Some(index) => &package_path[..=index],
+    None => "",
+  };
+
+  for chapter in spine_chapters(&package_xml, &package_path)? {
+    let chapter_path = resolve_href(package_dir, &chapter.href);
+    let Some(chapter_html) = read_zip_text(archive, &chapter_path)? else {
+      continue;
+    };
+    html::convert(&chapter_html, markdown);
+  }
+  Ok(())
+}
+
+fn package_document_path(container_xml: &str) -> InfuResult<String> {
+  let mut reader = Reader::from_str(container_xml);
+  loop {
+    match reader.read_event().map_err(|e| format!("Could not parse '{}': {}", CONTAINER_PART, e))? {
+      Event::Start(ref e) | Event::Empty(ref e) if e.local_name().as_ref() == b"rootfile" => {
+        if let Some(full_path) = attribute_value(e, b"full-path") {
+          return Ok(full_path);
+        }
+      }
+      Event::Eof => return Err(format!("EPUB '{}' does not name a package document.", CONTAINER_PART).into()),
+      _ => {}
+    }
+  }
+}
+
+fn spine_chapters(package_xml: &str, package_path: &str) -> InfuResult<Vec<EpubManifestItem>> {
+  let mut reader = Reader::from_str(package_xml);
+  let mut manifest = HashMap::<String, EpubManifestItem>::new();
+  let mut spine = Vec::<String>::new();
+  loop {
+    match reader.read_event().map_err(|e| format!("Could not parse '{}': {}", package_path, e))? {
+      Event::Start(ref e) | Event::Empty(ref e) => match e.local_name().as_ref() {
+        b"item" => {
+          if let (Some(id), Some(href)) = (attribute_value(e, b"id"), attribute_value(e, b"href")) {
+            let media_type = attribute_value(e, b"media-type").unwrap_or_default();
+            manifest.insert(id, EpubManifestItem { href, media_type });
+          }
+        }
+        b"itemref" => {
+          if let Some(idref) = attribute_value(e, b"idref") {
+            spine.push(idref);
+          }
+        }
+        _ => {}
+      },
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+
+  Ok(
+    spine
+      .into_iter()
+      .filter_map(|idref| manifest.remove(&idref))
+      .filter(|item| matches!(item.media_type.as_str(), "application/xhtml+xml" | "text/html"))
+      .collect(),
+  )
+}
+
+/// Resolve a manifest href (relative to the package document, possibly percent-encoded) to a zip
+/// entry name.
+fn resolve_href(base_dir: &str, href: &str) -> String {
+  let href = href.split('#').next().unwrap_or(href);
+  let mut parts = base_dir.split('/').filter(|part| !part.is_empty()).map(str::to_owned).collect::<Vec<_>>();
+  for part in percent_decode(href).split('/') {
+    match part {
+      "" | "." => {}
+      ".." => {
+        parts.pop();
+      }
+      part => parts.push(part.to_owned()),
+    }
+  }
+  parts.join("/")
+}
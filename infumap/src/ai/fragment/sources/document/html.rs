@@ -0,0 +1,156 @@
+use scraper::{ElementRef, Html, Node};
+
+use super::{MarkdownBuilder, collapse_whitespace};
+
+/// Deeper nesting than this is flattened to its text, which keeps conversion of pathological
+/// pages from exhausting the stack.
+const MAX_ELEMENT_DEPTH: usize = 256;
+
+/// Saved web pages and EPUB chapters (XHTML). Navigation, scripts and other non-content elements
+/// are dropped, block elements become paragraphs and h1-h6 become headings.
+pub(super) fn convert(html: &str, markdown: &mut MarkdownBuilder) {
+  let document = Html::parse_document(html);
+  let mut converter = HtmlConverter::new(markdown, &[]);
+  converter.walk(document.root_element(), 0);
+  converter.flush();
+}
+
+/// Live web pages. Only the page's main content is converted: the largest `<article>`, else
+/// `<main>` (or `role="main"`), else the body without its header, footer and sidebars. Forms and
+/// asides are dropped wherever they are.
+pub(super) fn convert_main_content(html: &str, markdown: &mut MarkdownBuilder) {
+  let document = Html::parse_document(html);
+  let root = document.root_element();
+  let (content_root, skipped_elements) = match main_content_element(root) {
+    Some(element) => (element, PAGE_CHROME_ELEMENTS_IN_CONTENT),
+    None => (root, PAGE_CHROME_ELEMENTS),
+  };
+  let mut converter = HtmlConverter::new(markdown, skipped_elements);
+  converter.element(content_root, 0);
+  converter.flush();
+}
+
+const PAGE_CHROME_ELEMENTS: &[&str] = &["header", "footer", "aside", "form"];
+const PAGE_CHROME_ELEMENTS_IN_CONTENT: &[&str] = &["footer", "aside", "form"];
+
+fn main_content_element(root: ElementRef<'_>) -> Option<ElementRef<'_>> {
+  let mut articles = Vec::new();
+  let mut main = None;
+  for element in root.descendants().filter_map(ElementRef::wrap) {
+    match element.value().name() {
+      "article" => articles.push(element),
+      "main" if main.is_none() => main = Some(element),
+      _ if main.is_none() && element.value().attr("role") == Some("main") => main = Some(element),
+      _ => {}
+    }
+  }
+  articles
+    .into_iter()
+    .max_by_key(|article| element_text_len(*article))
+    .filter(|article| element_text_len(*article) > 0)
+    .or(main)
+}
+
+fn element_text_len(element: ElementRef<'_>) -> usize {
+  element.text().map(|text| text.trim().len()).sum()
+}
+
+struct HtmlConverter<'a> {
+  markdown: &'a mut MarkdownBuilder,
+  skipped_elements: &'static [&'static str],
+  inline_text: String,
+  list_depth: usize,
+  list_item_depth: Option<usize>,
+}
+
+impl<'a> HtmlConverter<'a> {
+  fn new(markdown: &'a mut MarkdownBuilder, skipped_elements: &'static [&'static str]) -> HtmlConverter<'a> {
+    HtmlConverter { markdown, skipped_elements, inline_text: String::new(), list_depth: 0, list_item_depth: None }
+  }
+
+  fn walk(&mut self, element: ElementRef<'_>, depth: usize) {
+    if depth >= MAX_ELEMENT_DEPTH {
+      self.inline_text.extend(element.text());
+      return;
+    }
+    for child in element.children() {
+      match child.value() {
+        Node::Text(text) => self.inline_text.push_str(text),
+        Node::Element(_) => {
+          if let Some(child_element) = ElementRef::wrap(child) {
+            self.element(child_element, depth + 1);
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn element(&mut self, element: ElementRef<'_>, depth: usize) {
+    let name = element.value().name();
+    if self.skipped_elements.contains(&name) {
+      return;
+    }
+    match name {
+      "head" | "script" | "style" | "noscript" | "template" | "svg" | "math" | "iframe" | "object" | "canvas"
+      | "nav" | "select" | "button" => {}
+      "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+        self.flush();
+        let level = name[1..].parse::<usize>().unwrap_or(1);
+        self.markdown.heading(level, &element.text().collect::<String>());
+      }
+      "br" => self.inline_text.push('\n'),
+      "img" => {
+        if let Some(alt) = element.value().attr("alt") {
+          self.inline_text.push(' ');
+          self.inline_text.push_str(alt);
+          self.inline_text.push(' ');
+        }
+      }
+      "tr" => {
+        self.flush();
+        let cells = element
+          .children()
+          .filter_map(ElementRef::wrap)
+          .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+          .map(|cell| cell.text().collect::<String>())
+          .collect::<Vec<_>>();
+        self.markdown.table_row(&cells);
+      }
+      "ul" | "ol" | "menu" => {
+        self.flush();
+        self.list_depth += 1;
+        self.walk(element, depth);
+        self.flush();
+        self.list_depth -= 1;
+      }
+      "li" => {
+        self.flush();
+        let outer_list_item_depth = self.list_item_depth.replace(self.list_depth.saturating_sub(1));
+        self.walk(element, depth);
+        self.flush();
+        self.list_item_depth = outer_list_item_depth;
+      }
+      "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "aside" | "blockquote" | "pre"
+      | "figure" | "figcaption" | "address" | "table" | "thead" | "tbody" | "tfoot" | "caption" | "dl" | "dt"
+      | "dd" | "hr" | "body" | "html" | "form" | "fieldset" | "details" | "summary" => {
+        self.flush();
+        self.walk(element, depth);
+        self.flush();
+      }
+      _ => self.walk(element, depth),
+    }
+  }
+
+  fn flush(&mut self) {
+    if self.inline_text.trim().is_empty() {
+      self.inline_text.clear();
+      return;
+    }
+    match self.list_item_depth {
+      Some(depth) => self.markdown.list_item(depth, &collapse_whitespace(&self.inline_text)),
+      None => self.markdown.paragraph(&self.inline_text),
+    }
+    self.inline_text.clear();
+  }
+}
@@ -0,0 +1,230 @@
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+use quick_xml::events::BytesStart;
+use tokio::task;
+
+use crate::storage::object::{self as storage_object, ObjectStore};
+use crate::util::mime::WEB_ARCHIVE_MIME_TYPE;
+
+use super::markdown::{ObjectTextFragmentBuildResult, decode_text_bytes};
+use super::pdf::markdown_fragment_source;
+use super::{FragmentSource, FragmentSourceKind, write_fragment_source_artifact};
+
+mod docx;
+mod epub;
+mod html;
+mod odt;
+
+const DOCX_SOURCE_MIME_TYPE: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+const ODT_SOURCE_MIME_TYPE: &str = "application/vnd.oasis.opendocument.text";
+const EPUB_SOURCE_MIME_TYPE: &str = "application/epub+zip";
+const HTML_SOURCE_MIME_TYPE: &str = "text/html";
+/// Upper bound on the decompressed size of any single zip entry read while converting a document.
+const MAX_ZIP_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_MARKDOWN_HEADING_LEVEL: usize = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DocumentFormat {
+  Docx,
+  Odt,
+  Epub,
+  Html,
+}
+
+impl DocumentFormat {
+  pub fn from_mime_type(mime_type: &str) -> Option<DocumentFormat> {
+    match mime_type {
+      DOCX_SOURCE_MIME_TYPE => Some(DocumentFormat::Docx),
+      ODT_SOURCE_MIME_TYPE => Some(DocumentFormat::Odt),
+      EPUB_SOURCE_MIME_TYPE => Some(DocumentFormat::Epub),
+      HTML_SOURCE_MIME_TYPE | WEB_ARCHIVE_MIME_TYPE => Some(DocumentFormat::Html),
+      _ => None,
+    }
+  }
+
+  pub fn from_item(item: &Item) -> Option<DocumentFormat> {
+    DocumentFormat::from_mime_type(item.mime_type.as_deref()?)
+  }
+
+  pub fn label(self) -> &'static str {
+    match self {
+      DocumentFormat::Docx => "DOCX",
+      DocumentFormat::Odt => "ODT",
+      DocumentFormat::Epub => "EPUB",
+      DocumentFormat::Html => "HTML",
+    }
+  }
+
+  fn source_kind(self) -> FragmentSourceKind {
+    match self {
+      DocumentFormat::Docx => FragmentSourceKind::DocxMarkdown,
+      DocumentFormat::Odt => FragmentSourceKind::OdtMarkdown,
+      DocumentFormat::Epub => FragmentSourceKind::EpubMarkdown,
+      DocumentFormat::Html => FragmentSourceKind::HtmlMarkdown,
+    }
+  }
+}
+
+pub async fn converted_document_fragment_source_for_item(
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<Option<FragmentSource>> {
+  let Some(format) = DocumentFormat::from_item(item) else {
+    return Err(format!("Item '{}' is not a supported document (mime_type: {:?}).", item.id, item.mime_type).into());
+  };
+  let Some(markdown) = converted_document_markdown_for_item(object_store, item, object_encryption_key).await? else {
+    return Ok(None);
+  };
+
+  Ok(markdown_fragment_source(format.source_kind(), &markdown))
+}
+
+pub(super) async fn converted_document_markdown_for_item(
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<Option<String>> {
+  let Some(format) = DocumentFormat::from_item(item) else {
+    return Err(format!("Item '{}' is not a supported document (mime_type: {:?}).", item.id, item.mime_type).into());
+  };
+  let file_bytes = storage_object::get(object_store, item.owner_id.clone(), item.id.clone(), object_encryption_key)
+    .await
+    .map_err(|e| format!("Could not read source {} object for '{}': {}", format.label(), item.id, e))?;
+  let item_id = item.id.clone();
+  let markdown = task::spawn_blocking(move || document_markdown(format, &file_bytes))
+    .await
+    .map_err(|e| format!("{} conversion task for '{}' failed: {}", format.label(), item_id, e))?
+    .map_err(|e| format!("Could not convert {} '{}' to markdown: {}", format.label(), item_id, e))?;
+  Ok(markdown)
+}
+
+pub async fn build_converted_document_fragment_artifact(
+  data_dir: &str,
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<ObjectTextFragmentBuildResult> {
+  let fragment_source = converted_document_fragment_source_for_item(object_store, item, object_encryption_key).await?;
+  let had_fragment_source = fragment_source.is_some();
+  let outcome = write_fragment_source_artifact(data_dir, item, fragment_source).await?;
+  Ok(ObjectTextFragmentBuildResult { had_fragment_source, outcome })
+}
+
+/// Convert a document to normalized markdown: ATX headings, blank line separated paragraphs and
+/// `- ` list items, which is the structure the shared markdown chunking understands.
+fn document_markdown(format: DocumentFormat, bytes: &[u8]) -> InfuResult<Option<String>> {
+  let mut markdown = MarkdownBuilder::default();
+  match format {
+    DocumentFormat::Docx => docx::convert(&mut open_zip(bytes)?, &mut markdown)?,
+    DocumentFormat::Odt => odt::convert(&mut open_zip(bytes)?, &mut markdown)?,
+    DocumentFormat::Epub => epub::convert(&mut open_zip(bytes)?, &mut markdown)?,
+    DocumentFormat::Html => html::convert(&decode_text_bytes(bytes), &mut markdown),
+  }
+  Ok(markdown.finish())
+}
+
+/// Convert an HTML fragment or page (e.g. an HTML-only email body) to the same normalized markdown.
+pub fn html_to_markdown(html: &str) -> Option<String> {
+  let mut markdown = MarkdownBuilder::default();
+  html::convert(html, &mut markdown);
+  markdown.finish()
+}
+
+/// Convert a fetched web page to normalized markdown, keeping only its main content (see
+/// `html::convert_main_content`).
+pub fn html_main_content_to_markdown(html: &str) -> Option<String> {
+  let mut markdown = MarkdownBuilder::default();
+  html::convert_main_content(html, &mut markdown);
+  markdown.finish()
+}
+
+type DocumentArchive<'a> = zip::ZipArchive<Cursor<&'a [u8]>>;
+
+fn open_zip(bytes: &[u8]) -> InfuResult<DocumentArchive<'_>> {
+  zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Could not open document archive: {}", e).into())
+}
+
+fn read_zip_text(archive: &mut DocumentArchive<'_>, name: &str) -> InfuResult<Option<String>> {
+  let mut file = match archive.by_name(name) {
+    Ok(file) => file,
+    Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+    Err(e) => return Err(format!("Could not read '{}' from document archive: {}", name, e).into()),
+  };
+  if file.size() > MAX_ZIP_ENTRY_BYTES {
+    return Err(format!("Document archive entry '{}' is too large ({} bytes).", name, file.size()).into());
+  }
+  let mut bytes = Vec::with_capacity(file.size() as usize);
+  (&mut file)
+    .take(MAX_ZIP_ENTRY_BYTES)
+    .read_to_end(&mut bytes)
+    .map_err(|e| format!("Could not read '{}' from document archive: {}", name, e))?;
+  Ok(Some(decode_text_bytes(&bytes)))
+}
+
+#[derive(Default)]
+struct MarkdownBuilder {
+  blocks: Vec<String>,
+  last_block_was_item: bool,
+}
+
+impl MarkdownBuilder {
+  fn heading(&mut self, level: usize, text: &str) {
+    let text = collapse_whitespace(text);
+    if text.is_empty() {
+      return;
+    }
+    let level = level.clamp(1, MAX_MARKDOWN_HEADING_LEVEL);
+    self.push_block(format!("{} {}", "#".repeat(level), text), false);
+  }
+
+  fn paragraph(&mut self, text: &str) {
+    let text = text.lines().map(collapse_whitespace).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n");
+    if text.is_empty() {
+      return;
+    }
+    self.push_block(text, false);
+  }
+
+  fn list_item(&mut self, depth: usize, text: &str) {
+    let text = collapse_whitespace(text);
+    if text.is_empty() {
+      return;
+    }
+    self.push_block(format!("{}- {}", "  ".repeat(depth), text), true);
+  }
+
+  fn table_row(&mut self, cells: &[String]) {
+    let cells = cells.iter().map(|cell| collapse_whitespace(cell)).filter(|cell| !cell.is_empty()).collect::<Vec<_>>();
+    if cells.is_empty() {
+      return;
+    }
+    self.push_block(cells.join(" | "), false);
+  }
+
+  fn push_block(&mut self, block: String, is_item: bool) {
+    match self.blocks.last_mut() {
+      Some(last) if is_item && self.last_block_was_item => {
+        last.push('\n');
+        last.push_str(&block);
+      }
+      _ => self.blocks.push(block),
+    }
+    self.last_block_was_item = is_item;
+  }
+
+  fn finish(self) -> Option<String> {
+    if self.blocks.is_empty() { None } else { Some(self.blocks.join("\n\n")) }
+  }
+}
+
+fn attribute_value(element: &BytesStart<'_>, name: &[u8]) -> Option<String> {
+  element.try_get_attribute(name).ok().flatten()?.unescape_value().ok().map(|value| value.into_owned())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+  text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
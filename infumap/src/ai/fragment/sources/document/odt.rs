@@ -0,0 +1,103 @@
+use infusdk::util::infu::InfuResult;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+use super::{DocumentArchive, MarkdownBuilder, attribute_value, read_zip_text};
+
+const CONTENT_PART: &str = "content.xml";
+
+/// OpenDocument text. Headings carry their outline level directly, so no style lookup is needed.
+/// Footnotes, annotations and tracked deletions are left out of search text.
+pub(super) fn convert(archive: &mut DocumentArchive<'_>, markdown: &mut MarkdownBuilder) -> InfuResult<()> {
+  let content_xml =
+    read_zip_text(archive, CONTENT_PART)?.ok_or(format!("ODT archive has no '{}' part.", CONTENT_PART))?;
+
+  let mut reader = Reader::from_str(&content_xml);
+  let mut text = String::new();
+  let mut heading_level = None::<usize>;
+  let mut paragraph_depth = 0usize;
+  let mut skip_depth = 0usize;
+  let mut list_depth = 0usize;
+  let mut list_item_depth = 0usize;
+  let mut table_depth = 0usize;
+  let mut row = Vec::<String>::new();
+  let mut cell = None::<String>;
+
+  loop {
+    let event = reader.read_event().map_err(|e| format!("Could not parse '{}': {}", CONTENT_PART, e))?;
+    match event {
+      Event::Start(ref e) | Event::Empty(ref e) => {
+        let is_empty = matches!(event, Event::Empty(_));
+        if skip_depth > 0 {
+          if !is_empty {
+            skip_depth += 1;
+          }
+          continue;
+        }
+        match e.local_name().as_ref() {
+          b"note" | b"annotation" | b"tracked-changes" | b"sequence-decls" if !is_empty => skip_depth = 1,
+          b"p" | b"h" if !is_empty => {
+            if paragraph_depth == 0 {
+              text.clear();
+              heading_level = (e.local_name().as_ref() == b"h").then(|| {
+                attribute_value(e, b"text:outline-level").and_then(|level| level.parse::<usize>().ok()).unwrap_or(1)
+              });
+            }
+            paragraph_depth += 1;
+          }
+          b"s" | b"tab" if paragraph_depth > 0 => text.push(' '),
+          b"line-break" if paragraph_depth > 0 => text.push('\n'),
+          b"list" if !is_empty => list_depth += 1,
+          b"list-item" | b"list-header" if !is_empty => list_item_depth += 1,
+          b"table" if !is_empty => table_depth += 1,
+          b"table-row" if !is_empty && table_depth == 1 => row.clear(),
+          b"table-cell" if !is_empty && table_depth == 1 => cell = Some(String::new()),
+          _ => {}
+        }
+      }
+      Event::End(ref e) => {
+        if skip_depth > 0 {
+          skip_depth -= 1;
+          continue;
+        }
+        match e.local_name().as_ref() {
+          b"p" | b"h" if paragraph_depth > 0 => {
+            paragraph_depth -= 1;
+            if paragraph_depth > 0 {
+              continue;
+            }
+            match (cell.as_mut(), heading_level) {
+              (Some(cell), _) => {
+                cell.push(' ');
+                cell.push_str(&text);
+              }
+              (None, Some(level)) => markdown.heading(level, &text),
+              (None, None) if list_item_depth > 0 => markdown.list_item(list_depth.saturating_sub(1), &text),
+              (None, None) => markdown.paragraph(&text),
+            }
+          }
+          b"list" => list_depth = list_depth.saturating_sub(1),
+          b"list-item" | b"list-header" => list_item_depth = list_item_depth.saturating_sub(1),
+          b"table-cell" if table_depth == 1 => {
+            if let Some(cell) = cell.take() {
+              row.push(cell);
+            }
+          }
+          b"table-row" if table_depth == 1 => {
+            markdown.table_row(&row);
+            row.clear();
+          }
+          b"table" => table_depth = table_depth.saturating_sub(1),
+          _ => {}
+        }
+      }
+      Event::Text(ref e) if skip_depth == 0 && paragraph_depth > 0 => {
+        let value = e.unescape().map_err(|e| format!("Could not parse '{}': {}", CONTENT_PART, e))?;
+        text.push_str(&value);
+      }
+      Event::Eof => break,
+      _ => {}
+    }
+  }
+  Ok(())
+}
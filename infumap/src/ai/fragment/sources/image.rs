@@ -262,7 +262,8 @@ fn best_geo_location_text(geo_artifact: Option<&StoredGeoArtifact>) -> Option<St
   let mut parts = Vec::new();
   let mut seen = HashSet::new();
 
-  for value in [best_result.city.as_deref(), best_result.province.as_deref(), best_result.country.as_deref()] {
+  let region = best_result.province.as_deref().or(best_result.state.as_deref());
+  for value in [best_result.city.as_deref(), region, best_result.country.as_deref()] {
     let Some(part) = normalized_text(value) else {
       continue;
     };
@@ -324,6 +325,7 @@ struct StoredGeoArtifact {
 struct StoredGeoResult {
   city: Option<String>,
   province: Option<String>,
+  state: Option<String>,
   country: Option<String>,
   #[serde(default)]
   other_names: BTreeMap<String, String>,
@@ -0,0 +1,18 @@
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+
+use super::pdf::markdown_fragment_source;
+use super::{FragmentSourceKind, write_fragment_source_artifact};
+use crate::ai::fragment::FragmentBuildOutcome;
+
+/// Fragment the stored snapshot of a link note's page. With no snapshot text, any existing
+/// fragments for the note are cleared.
+pub async fn build_link_snapshot_fragment_artifact(
+  data_dir: &str,
+  item: &Item,
+  snapshot_markdown: Option<&str>,
+) -> InfuResult<FragmentBuildOutcome> {
+  let fragment_source =
+    snapshot_markdown.and_then(|markdown| markdown_fragment_source(FragmentSourceKind::LinkSnapshotMarkdown, markdown));
+  write_fragment_source_artifact(data_dir, item, fragment_source).await
+}
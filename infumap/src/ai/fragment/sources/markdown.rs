@@ -20,10 +20,7 @@ pub async fn markdown_fragment_source_for_item(
   item: &Item,
   object_encryption_key: &str,
 ) -> InfuResult<Option<FragmentSource>> {
-  let file_bytes = storage_object::get(object_store, item.owner_id.clone(), item.id.clone(), object_encryption_key)
-    .await
-    .map_err(|e| format!("Could not read source markdown object for '{}': {}", item.id, e))?;
-  let Some(markdown) = normalize_utf8_text_source(&file_bytes, &item.id, "Markdown file")? else {
+  let Some(markdown) = markdown_source_for_item(object_store, item, object_encryption_key).await? else {
     return Ok(None);
   };
 
@@ -35,16 +32,35 @@ pub async fn text_fragment_source_for_item(
   item: &Item,
   object_encryption_key: &str,
 ) -> InfuResult<Option<FragmentSource>> {
-  let file_bytes = storage_object::get(object_store, item.owner_id.clone(), item.id.clone(), object_encryption_key)
-    .await
-    .map_err(|e| format!("Could not read source text object for '{}': {}", item.id, e))?;
-  let Some(text) = normalize_plain_text_source(&file_bytes, &item.id) else {
+  let Some(text) = text_source_for_item(object_store, item, object_encryption_key).await? else {
     return Ok(None);
   };
 
   Ok(markdown_fragment_source(FragmentSourceKind::Text, &text))
 }
 
+pub(super) async fn markdown_source_for_item(
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<Option<String>> {
+  let file_bytes = storage_object::get(object_store, item.owner_id.clone(), item.id.clone(), object_encryption_key)
+    .await
+    .map_err(|e| format!("Could not read source markdown object for '{}': {}", item.id, e))?;
+  normalize_utf8_text_source(&file_bytes, &item.id, "Markdown file")
+}
+
+pub(super) async fn text_source_for_item(
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<Option<String>> {
+  let file_bytes = storage_object::get(object_store, item.owner_id.clone(), item.id.clone(), object_encryption_key)
+    .await
+    .map_err(|e| format!("Could not read source text object for '{}': {}", item.id, e))?;
+  Ok(normalize_plain_text_source(&file_bytes, &item.id))
+}
+
 pub async fn build_markdown_fragment_artifact(
   data_dir: &str,
   object_store: Arc<ObjectStore>,
@@ -89,6 +105,10 @@ fn normalize_text_source(text: &str) -> Option<String> {
   if normalized.is_empty() { None } else { Some(normalized) }
 }
 
+pub(super) fn decode_text_bytes(bytes: &[u8]) -> String {
+  decode_plain_text_bytes(bytes).text
+}
+
 fn decode_plain_text_bytes(bytes: &[u8]) -> DecodedText {
   if let Some(text) = decode_utf8_bytes(bytes) {
     return DecodedText { text, encoding: "utf-8" };
@@ -1,27 +1,64 @@
 use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use infusdk::item::{Item, RelationshipToParent};
+use infusdk::item::{Item, ItemType, RelationshipToParent};
 use infusdk::util::infu::InfuResult;
 use serde::de::DeserializeOwned;
 use tokio::fs;
 
 use crate::storage::db::Db;
+use crate::storage::object::ObjectStore;
 
 use super::{
   FragmentBuildOutcome, FragmentInput, FragmentSource, FragmentSourceKind, clear_item_fragments, write_item_fragments,
 };
 
+mod audio;
+mod document;
 mod image;
+mod link_snapshot;
 mod markdown;
 mod pdf;
+mod structured;
 mod title;
 
+pub use audio::{AudioTranscriptSegment, build_audio_transcript_fragment_artifact};
+pub use document::{
+  DocumentFormat, build_converted_document_fragment_artifact, html_main_content_to_markdown, html_to_markdown,
+};
 pub use image::build_image_fragment_artifact;
+pub use link_snapshot::build_link_snapshot_fragment_artifact;
 pub use markdown::{build_markdown_fragment_artifact, build_text_fragment_artifact};
-pub use pdf::{build_pdf_fragment_artifact, pdf_fragment_source_for_item};
+pub use pdf::{build_pdf_fragment_artifact, markdown_fragment_inputs, pdf_fragment_source_for_item};
+pub use structured::{
+  TableCellText, build_structured_fragment_artifact, is_structured_fragment_item, structured_fragment_source_for_item,
+  table_row_cells_for_row_item,
+};
 pub use title::{ItemTitleFragment, item_title_fragment_for_item};
 
+/// The markdown the fragments of a PDF, Markdown, text or converted document item are cut from. None
+/// for other items, and items without text.
+pub async fn chunkable_markdown_for_item(
+  data_dir: &str,
+  object_store: Arc<ObjectStore>,
+  item: &Item,
+  object_encryption_key: &str,
+) -> InfuResult<Option<String>> {
+  if item.item_type != ItemType::File && item.item_type != ItemType::Text {
+    return Ok(None);
+  }
+  match item.mime_type.as_deref() {
+    Some("application/pdf") if item.item_type == ItemType::File => pdf::pdf_markdown_for_item(data_dir, item).await,
+    Some("text/markdown") => markdown::markdown_source_for_item(object_store, item, object_encryption_key).await,
+    Some("text/plain") => markdown::text_source_for_item(object_store, item, object_encryption_key).await,
+    _ if item.item_type == ItemType::File && DocumentFormat::from_item(item).is_some() => {
+      document::converted_document_markdown_for_item(object_store, item, object_encryption_key).await
+    }
+    _ => Ok(None),
+  }
+}
+
 fn single_fragment_source(source_kind: FragmentSourceKind, text: String) -> FragmentSource {
   FragmentSource { source_kind, fragments: vec![FragmentInput::new(text)] }
 }
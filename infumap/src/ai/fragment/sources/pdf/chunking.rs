@@ -1,15 +1,12 @@
+use super::super::super::{ChunkingMode, ChunkingStrategy};
 use super::super::FragmentInput;
 use super::blocks::build_pdf_text_blocks;
 use super::pages::{resolve_pdf_pages, split_pdf_markdown_pages};
 use super::rendering::{heading_paths_equal, render_pdf_fragment_text};
-use super::splitting::{estimate_embedding_token_count, split_pdf_block_text};
+use super::splitting::{estimate_embedding_token_count, overlap_tail, split_pdf_block_text, split_text_into_sentences};
 use super::types::PdfFragmentBlock;
-use super::{
-  PDF_FRAGMENT_HARD_LIMIT_CHARS, PDF_FRAGMENT_HARD_LIMIT_TOKENS, PDF_FRAGMENT_MIN_CHARS, PDF_FRAGMENT_SOFT_LIMIT_CHARS,
-  PDF_FRAGMENT_SOFT_LIMIT_TOKENS,
-};
 
-pub(super) fn build_pdf_fragment_inputs(markdown: &str) -> Vec<FragmentInput> {
+pub(super) fn build_pdf_fragment_inputs(markdown: &str, strategy: &ChunkingStrategy) -> Vec<FragmentInput> {
   let pages = resolve_pdf_pages(split_pdf_markdown_pages(markdown));
   let blocks = build_pdf_text_blocks(&pages);
   if blocks.is_empty() {
@@ -18,7 +15,7 @@ pub(super) fn build_pdf_fragment_inputs(markdown: &str) -> Vec<FragmentInput> {
   let prepared_blocks = blocks
     .into_iter()
     .flat_map(|block| {
-      split_pdf_block_text(&block.text).into_iter().map(move |part| PdfFragmentBlock {
+      split_pdf_block_text(&block.text, strategy).into_iter().map(move |part| PdfFragmentBlock {
         page_number: block.page_number,
         headings: block.headings.clone(),
         text: part,
@@ -26,15 +23,32 @@ pub(super) fn build_pdf_fragment_inputs(markdown: &str) -> Vec<FragmentInput> {
     })
     .collect::<Vec<_>>();
 
+  match strategy.mode {
+    ChunkingMode::HeadingAware => heading_aware_fragment_inputs(&prepared_blocks, strategy),
+    ChunkingMode::SlidingWindow => sliding_window_fragment_inputs(&prepared_blocks, strategy),
+  }
+}
+
+fn heading_aware_fragment_inputs(
+  prepared_blocks: &[PdfFragmentBlock],
+  strategy: &ChunkingStrategy,
+) -> Vec<FragmentInput> {
   let mut fragments = Vec::new();
   let mut current: Option<PdfFragmentAccumulator> = None;
 
   for (index, next_block) in prepared_blocks.iter().enumerate() {
-    let should_flush =
-      current.as_ref().is_some_and(|current| should_flush_pdf_fragment(current, next_block, &prepared_blocks[index..]));
+    let should_flush = current
+      .as_ref()
+      .is_some_and(|current| should_flush_pdf_fragment(current, next_block, &prepared_blocks[index..], strategy));
 
     if should_flush {
+      let overlap = current.as_ref().and_then(|current| overlap_block(current, next_block, strategy));
       push_pdf_fragment_input(&mut fragments, current.take());
+      if let Some(overlap) = overlap {
+        let mut seeded = PdfFragmentAccumulator::new(overlap.page_number);
+        seeded.push(overlap);
+        current = Some(seeded);
+      }
     }
 
     let current_fragment = current.get_or_insert_with(|| PdfFragmentAccumulator::new(next_block.page_number));
@@ -45,6 +59,118 @@ pub(super) fn build_pdf_fragment_inputs(markdown: &str) -> Vec<FragmentInput> {
   fragments
 }
 
+/// The end of the fragment being flushed, to repeat at the start of the next one. Only used when
+/// the next block continues the same section, and when it leaves room for that block.
+fn overlap_block(
+  current: &PdfFragmentAccumulator,
+  next_block: &PdfFragmentBlock,
+  strategy: &ChunkingStrategy,
+) -> Option<PdfFragmentBlock> {
+  if strategy.overlap_tokens == 0 {
+    return None;
+  }
+  let last_block = current.blocks.last()?;
+  if !heading_paths_equal(&last_block.headings, &next_block.headings) {
+    return None;
+  }
+  let text = overlap_tail(&last_block.text, strategy.overlap_tokens, strategy.sentence_boundaries)?;
+  let fits = text.len() + next_block.text.len() < strategy.hard_limit_chars()
+    && estimate_embedding_token_count(&text) + estimate_embedding_token_count(&next_block.text)
+      <= strategy.hard_limit_tokens();
+  if !fits {
+    return None;
+  }
+  Some(PdfFragmentBlock { page_number: last_block.page_number, headings: last_block.headings.clone(), text })
+}
+
+/// Fill each fragment to the target size without regard to headings. Each window after the first
+/// starts with the last units (sentences or words) of the previous one, up to the overlap size.
+fn sliding_window_fragment_inputs(
+  prepared_blocks: &[PdfFragmentBlock],
+  strategy: &ChunkingStrategy,
+) -> Vec<FragmentInput> {
+  let units = prepared_blocks
+    .iter()
+    .enumerate()
+    .flat_map(|(block_index, block)| {
+      let texts = if strategy.sentence_boundaries {
+        split_text_into_sentences(&block.text)
+      } else {
+        block.text.split_whitespace().map(str::to_owned).collect()
+      };
+      texts.into_iter().map(move |text| WindowUnit { block_index, tokens: estimate_embedding_token_count(&text), text })
+    })
+    .collect::<Vec<_>>();
+
+  let mut fragments = Vec::new();
+  let mut start = 0;
+  while start < units.len() {
+    let mut end = start;
+    let mut tokens = 0;
+    let mut chars = 0;
+    while end < units.len()
+      && (end == start
+        || (tokens + units[end].tokens <= strategy.soft_limit_tokens()
+          && chars + units[end].text.len() < strategy.soft_limit_chars()))
+    {
+      tokens += units[end].tokens;
+      chars += units[end].text.len() + 1;
+      end += 1;
+    }
+    push_window_fragment_input(&mut fragments, prepared_blocks, &units[start..end]);
+    if end >= units.len() {
+      break;
+    }
+
+    let mut next_start = end;
+    let mut overlap = 0;
+    while next_start > start + 1 && overlap + units[next_start - 1].tokens <= strategy.overlap_tokens {
+      next_start -= 1;
+      overlap += units[next_start].tokens;
+    }
+    start = next_start;
+  }
+
+  fragments
+}
+
+fn push_window_fragment_input(out: &mut Vec<FragmentInput>, blocks: &[PdfFragmentBlock], units: &[WindowUnit]) {
+  let mut window_blocks = Vec::<PdfFragmentBlock>::new();
+  let mut last_block_index = None;
+  for unit in units {
+    if last_block_index == Some(unit.block_index)
+      && let Some(window_block) = window_blocks.last_mut()
+    {
+      window_block.text.push(' ');
+      window_block.text.push_str(&unit.text);
+      continue;
+    }
+    let block = &blocks[unit.block_index];
+    window_blocks.push(PdfFragmentBlock {
+      page_number: block.page_number,
+      headings: block.headings.clone(),
+      text: unit.text.clone(),
+    });
+    last_block_index = Some(unit.block_index);
+  }
+
+  let (Some(first), Some(last)) = (window_blocks.first(), window_blocks.last()) else {
+    return;
+  };
+  let (page_start, page_end) = (first.page_number, last.page_number);
+  let text = render_pdf_fragment_text(page_start, page_end, &window_blocks);
+  if text.trim().is_empty() {
+    return;
+  }
+  out.push(FragmentInput::new(text).with_page_range(Some(page_start), Some(page_end)));
+}
+
+struct WindowUnit {
+  block_index: usize,
+  tokens: usize,
+  text: String,
+}
+
 fn push_pdf_fragment_input(out: &mut Vec<FragmentInput>, fragment: Option<PdfFragmentAccumulator>) {
   let Some(fragment) = fragment else {
     return;
@@ -60,6 +186,7 @@ fn should_flush_pdf_fragment(
   current: &PdfFragmentAccumulator,
   next_block: &PdfFragmentBlock,
   upcoming_blocks: &[PdfFragmentBlock],
+  strategy: &ChunkingStrategy,
 ) -> bool {
   let continues_same_heading =
     current.blocks.last().map(|block| heading_paths_equal(&block.headings, &next_block.headings)).unwrap_or(false);
@@ -69,7 +196,7 @@ fn should_flush_pdf_fragment(
   let candidate_tokens =
     rendered_pdf_fragment_token_estimate(current.page_start, next_block.page_number, &current.blocks, Some(next_block));
 
-  if candidate_len > PDF_FRAGMENT_HARD_LIMIT_CHARS || candidate_tokens > PDF_FRAGMENT_HARD_LIMIT_TOKENS {
+  if candidate_len > strategy.hard_limit_chars() || candidate_tokens > strategy.hard_limit_tokens() {
     return true;
   }
 
@@ -77,18 +204,19 @@ fn should_flush_pdf_fragment(
     return false;
   }
 
-  if should_flush_before_new_heading_run(current, upcoming_blocks, current_len) {
+  if should_flush_before_new_heading_run(current, upcoming_blocks, current_len, strategy) {
     return true;
   }
 
-  (candidate_len > PDF_FRAGMENT_SOFT_LIMIT_CHARS || candidate_tokens > PDF_FRAGMENT_SOFT_LIMIT_TOKENS)
-    && current_len >= PDF_FRAGMENT_MIN_CHARS
+  (candidate_len > strategy.soft_limit_chars() || candidate_tokens > strategy.soft_limit_tokens())
+    && current_len >= strategy.min_chars()
 }
 
 fn should_flush_before_new_heading_run(
   current: &PdfFragmentAccumulator,
   upcoming_blocks: &[PdfFragmentBlock],
   current_len: usize,
+  strategy: &ChunkingStrategy,
 ) -> bool {
   let Some(next_block) = upcoming_blocks.first() else {
     return false;
@@ -96,7 +224,7 @@ fn should_flush_before_new_heading_run(
   let Some(last_block) = current.blocks.last() else {
     return false;
   };
-  if heading_paths_equal(&last_block.headings, &next_block.headings) || current_len < PDF_FRAGMENT_MIN_CHARS {
+  if heading_paths_equal(&last_block.headings, &next_block.headings) || current_len < strategy.min_chars() {
     return false;
   }
 
@@ -109,8 +237,8 @@ fn should_flush_before_new_heading_run(
     rendered_pdf_fragment_len(heading_run_page_start, heading_run_page_end, heading_run, None);
   let heading_run_render_tokens =
     rendered_pdf_fragment_token_estimate(heading_run_page_start, heading_run_page_end, heading_run, None);
-  let heading_run_fits_hard_limits = heading_run_render_len <= PDF_FRAGMENT_HARD_LIMIT_CHARS
-    && heading_run_render_tokens <= PDF_FRAGMENT_HARD_LIMIT_TOKENS;
+  let heading_run_fits_hard_limits =
+    heading_run_render_len <= strategy.hard_limit_chars() && heading_run_render_tokens <= strategy.hard_limit_tokens();
 
   let mut combined_blocks = current.blocks.clone();
   combined_blocks.extend(heading_run.iter().cloned());
@@ -118,7 +246,7 @@ fn should_flush_before_new_heading_run(
   let combined_render_tokens =
     rendered_pdf_fragment_token_estimate(current.page_start, heading_run_page_end, &combined_blocks, None);
 
-  if combined_render_len <= PDF_FRAGMENT_SOFT_LIMIT_CHARS && combined_render_tokens <= PDF_FRAGMENT_SOFT_LIMIT_TOKENS {
+  if combined_render_len <= strategy.soft_limit_chars() && combined_render_tokens <= strategy.soft_limit_tokens() {
     return false;
   }
 
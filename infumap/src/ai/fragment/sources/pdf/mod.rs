@@ -10,7 +10,7 @@ use serde::Deserialize;
 use crate::ai::user_id_for_log;
 use crate::storage::object::{self as storage_object, ObjectStore};
 
-use super::super::{FragmentBuildOutcome, FragmentInput};
+use super::super::{ChunkingStrategy, FragmentBuildOutcome, FragmentInput, chunking_strategy_for_source_kind};
 use super::{FragmentSource, FragmentSourceKind, write_fragment_source_artifact};
 
 mod blocks;
@@ -24,11 +24,6 @@ mod types;
 use chunking::build_pdf_fragment_inputs;
 use loader::load_pdf_markdown_artifact;
 
-pub(super) const PDF_FRAGMENT_MIN_CHARS: usize = 500;
-pub(super) const PDF_FRAGMENT_SOFT_LIMIT_CHARS: usize = 1400;
-pub(super) const PDF_FRAGMENT_HARD_LIMIT_CHARS: usize = 1900;
-pub(super) const PDF_FRAGMENT_SOFT_LIMIT_TOKENS: usize = 380;
-pub(super) const PDF_FRAGMENT_HARD_LIMIT_TOKENS: usize = 440;
 pub(super) const PDF_PAGE_BREAK_MIN_DASH_COUNT: usize = 8;
 const PDF_CAPTION_REQUEST_TIMEOUT_SECS: u64 = 30 * 60;
 const PDF_SOURCE_MIME_TYPE: &str = "application/pdf";
@@ -43,13 +38,17 @@ struct PdfCaptionResponse {
 }
 
 pub async fn pdf_fragment_source_for_item(data_dir: &str, item: &Item) -> InfuResult<Option<FragmentSource>> {
-  let Some(markdown) = load_pdf_markdown_artifact(data_dir, &item.owner_id, &item.id).await? else {
+  let Some(markdown) = pdf_markdown_for_item(data_dir, item).await? else {
     return Ok(None);
   };
 
   Ok(markdown_fragment_source(FragmentSourceKind::PdfMarkdown, &markdown))
 }
 
+pub(super) async fn pdf_markdown_for_item(data_dir: &str, item: &Item) -> InfuResult<Option<String>> {
+  load_pdf_markdown_artifact(data_dir, &item.owner_id, &item.id).await
+}
+
 pub async fn build_pdf_fragment_artifact(
   data_dir: &str,
   object_store: Arc<ObjectStore>,
@@ -68,8 +67,10 @@ pub async fn build_pdf_fragment_artifact(
   Ok(PdfFragmentBuildResult { outcome })
 }
 
+/// Fragment markdown with the chunking strategy configured for the source kind.
 pub(super) fn markdown_fragment_source(source_kind: FragmentSourceKind, markdown: &str) -> Option<FragmentSource> {
-  let fragments = build_pdf_fragment_inputs(markdown);
+  let strategy = chunking_strategy_for_source_kind(source_kind.as_str()).unwrap_or_default();
+  let fragments = markdown_fragment_inputs(markdown, &strategy);
   if fragments.is_empty() {
     return None;
   }
@@ -77,6 +78,12 @@ pub(super) fn markdown_fragment_source(source_kind: FragmentSourceKind, markdown
   Some(FragmentSource { source_kind, fragments })
 }
 
+/// Cut markdown into fragments with the given chunking strategy. Markdown from all document sources
+/// is chunked this way (not only that extracted from PDFs).
+pub fn markdown_fragment_inputs(markdown: &str, strategy: &ChunkingStrategy) -> Vec<FragmentInput> {
+  build_pdf_fragment_inputs(markdown, strategy)
+}
+
 async fn pdf_first_page_caption_fragment_source_for_item(
   object_store: Arc<ObjectStore>,
   item: &Item,
@@ -1,51 +1,34 @@
+use super::super::super::ChunkingStrategy;
 use super::super::normalized_text;
-use super::{
-  PDF_FRAGMENT_HARD_LIMIT_CHARS, PDF_FRAGMENT_HARD_LIMIT_TOKENS, PDF_FRAGMENT_SOFT_LIMIT_CHARS,
-  PDF_FRAGMENT_SOFT_LIMIT_TOKENS,
-};
 
 const EMBEDDING_TOKEN_ESTIMATE_CHARS_PER_TOKEN: usize = 4;
 
-pub(super) fn split_pdf_block_text(text: &str) -> Vec<String> {
+pub(super) fn split_pdf_block_text(text: &str, strategy: &ChunkingStrategy) -> Vec<String> {
   let text = text.trim();
   if text.is_empty() {
     return vec![];
   }
-  if text.len() <= PDF_FRAGMENT_HARD_LIMIT_CHARS
-    && estimate_embedding_token_count(text) <= PDF_FRAGMENT_HARD_LIMIT_TOKENS
-  {
+  if text.len() <= strategy.hard_limit_chars() && estimate_embedding_token_count(text) <= strategy.hard_limit_tokens() {
     return vec![text.to_owned()];
   }
 
-  let sentences = split_text_into_sentences(text);
+  let sentences = if strategy.sentence_boundaries { split_text_into_sentences(text) } else { vec![] };
   if sentences.len() <= 1 {
-    return split_text_by_words(
-      text,
-      PDF_FRAGMENT_SOFT_LIMIT_CHARS,
-      PDF_FRAGMENT_HARD_LIMIT_CHARS,
-      PDF_FRAGMENT_SOFT_LIMIT_TOKENS,
-      PDF_FRAGMENT_HARD_LIMIT_TOKENS,
-    );
+    return split_text_by_words(text, strategy);
   }
 
   let mut out = Vec::new();
   let mut current = String::new();
 
   for sentence in sentences {
-    if sentence.len() > PDF_FRAGMENT_HARD_LIMIT_CHARS
-      || estimate_embedding_token_count(&sentence) > PDF_FRAGMENT_HARD_LIMIT_TOKENS
+    if sentence.len() > strategy.hard_limit_chars()
+      || estimate_embedding_token_count(&sentence) > strategy.hard_limit_tokens()
     {
       if !current.is_empty() {
         out.push(current);
         current = String::new();
       }
-      out.extend(split_text_by_words(
-        &sentence,
-        PDF_FRAGMENT_SOFT_LIMIT_CHARS,
-        PDF_FRAGMENT_HARD_LIMIT_CHARS,
-        PDF_FRAGMENT_SOFT_LIMIT_TOKENS,
-        PDF_FRAGMENT_HARD_LIMIT_TOKENS,
-      ));
+      out.extend(split_text_by_words(&sentence, strategy));
       continue;
     }
 
@@ -55,8 +38,8 @@ pub(super) fn split_pdf_block_text(text: &str) -> Vec<String> {
     }
 
     let candidate = format!("{current} {sentence}");
-    if candidate.len() > PDF_FRAGMENT_SOFT_LIMIT_CHARS
-      || estimate_embedding_token_count(&candidate) > PDF_FRAGMENT_SOFT_LIMIT_TOKENS
+    if candidate.len() > strategy.soft_limit_chars()
+      || estimate_embedding_token_count(&candidate) > strategy.soft_limit_tokens()
     {
       out.push(current);
       current = sentence;
@@ -73,7 +56,37 @@ pub(super) fn split_pdf_block_text(text: &str) -> Vec<String> {
   out
 }
 
-fn split_text_into_sentences(text: &str) -> Vec<String> {
+/// The end of `text` of at most `max_tokens`, made of whole sentences if `sentence_boundaries` is set
+/// and a sentence fits, otherwise of whole words.
+pub(super) fn overlap_tail(text: &str, max_tokens: usize, sentence_boundaries: bool) -> Option<String> {
+  let units = if sentence_boundaries {
+    let sentences = split_text_into_sentences(text);
+    match sentences.last() {
+      Some(last) if estimate_embedding_token_count(last) <= max_tokens => sentences,
+      _ => text.split_whitespace().map(str::to_owned).collect(),
+    }
+  } else {
+    text.split_whitespace().map(str::to_owned).collect()
+  };
+
+  let mut tail = Vec::new();
+  let mut tokens = 0;
+  for unit in units.iter().rev() {
+    let unit_tokens = estimate_embedding_token_count(unit);
+    if tokens + unit_tokens > max_tokens {
+      break;
+    }
+    tokens += unit_tokens;
+    tail.push(unit.as_str());
+  }
+  if tail.is_empty() {
+    return None;
+  }
+  tail.reverse();
+  Some(tail.join(" "))
+}
+
+pub(super) fn split_text_into_sentences(text: &str) -> Vec<String> {
   let mut out = Vec::new();
   let mut current = String::new();
   let chars = text.chars().collect::<Vec<char>>();
@@ -96,13 +109,11 @@ fn split_text_into_sentences(text: &str) -> Vec<String> {
   out
 }
 
-fn split_text_by_words(
-  text: &str,
-  soft_limit_chars: usize,
-  hard_limit_chars: usize,
-  soft_limit_tokens: usize,
-  hard_limit_tokens: usize,
-) -> Vec<String> {
+fn split_text_by_words(text: &str, strategy: &ChunkingStrategy) -> Vec<String> {
+  let soft_limit_chars = strategy.soft_limit_chars();
+  let hard_limit_chars = strategy.hard_limit_chars();
+  let soft_limit_tokens = strategy.soft_limit_tokens();
+  let hard_limit_tokens = strategy.hard_limit_tokens();
   let words = text.split_whitespace().collect::<Vec<&str>>();
   let mut out = Vec::new();
   let mut current = String::new();
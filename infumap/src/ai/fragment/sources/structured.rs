@@ -0,0 +1,194 @@
+use infusdk::item::{Item, ItemType, RelationshipToParent, TableColumn, is_attachments_item_type};
+use infusdk::util::infu::InfuResult;
+
+use crate::storage::db::Db;
+
+use super::pdf::markdown_fragment_source;
+use super::{
+  FragmentBuildOutcome, FragmentInput, FragmentSource, FragmentSourceKind, normalized_text, parent_title_for_item,
+  write_fragment_source_artifact,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableCellText {
+  pub row_item_id: String,
+  pub row_index: usize,
+  pub cell_item_id: String,
+  pub column_index: usize,
+  pub column_name: String,
+  pub text: String,
+}
+
+impl TableCellText {
+  pub fn line(&self) -> String {
+    format!("{}: {}", self.column_name, self.text)
+  }
+}
+
+pub fn is_structured_fragment_item(item: &Item) -> bool {
+  matches!(item.item_type, ItemType::Table | ItemType::Composite)
+}
+
+/// Assemble the text of a table or composite from its children. Tables produce one fragment per row, with
+/// each cell on its own line prefixed by its column header. Composites produce the text of their children in
+/// display order, chunked as markdown.
+pub fn structured_fragment_source_for_item(db: &Db, item: &Item) -> InfuResult<Option<FragmentSource>> {
+  match item.item_type {
+    ItemType::Table => table_fragment_source(db, item),
+    ItemType::Composite => composite_fragment_source(db, item),
+    _ => Ok(None),
+  }
+}
+
+/// Write the fragments assembled by `structured_fragment_source_for_item`, or clear them if the item no
+/// longer has any text.
+pub async fn build_structured_fragment_artifact(
+  data_dir: &str,
+  item: &Item,
+  fragment_source: Option<FragmentSource>,
+) -> InfuResult<FragmentBuildOutcome> {
+  write_fragment_source_artifact(data_dir, item, fragment_source).await
+}
+
+/// The cells of a table row, looked up from the row item recorded with its fragment. Returns None if the
+/// row is no longer in the table.
+pub fn table_row_cells_for_row_item(
+  db: &Db,
+  table: &Item,
+  row_item_id: &str,
+) -> InfuResult<Option<Vec<TableCellText>>> {
+  if table.item_type != ItemType::Table {
+    return Ok(None);
+  }
+  let Ok(row) = db.item.get(&row_item_id.to_owned()) else {
+    return Ok(None);
+  };
+  if row.parent_id.as_ref() != Some(&table.id) || row.relationship_to_parent != RelationshipToParent::Child {
+    return Ok(None);
+  }
+  let row_index = db
+    .item
+    .get_children(&table.id)?
+    .into_iter()
+    .filter(|child| (&child.ordering, &child.id) < (&row.ordering, &row.id))
+    .count();
+  Ok(Some(table_row_cells_for_row(db, table, row, row_index)?))
+}
+
+fn table_fragment_source(db: &Db, table: &Item) -> InfuResult<Option<FragmentSource>> {
+  let heading = table_heading(table);
+  let fragments = table_row_cells(db, table)?
+    .into_iter()
+    .filter_map(|row_cells| {
+      let row_item_id = row_cells.first()?.row_item_id.clone();
+      Some(FragmentInput::new(table_row_fragment_text(heading.as_deref(), &row_cells)).with_row_item_id(row_item_id))
+    })
+    .collect::<Vec<_>>();
+  if fragments.is_empty() {
+    return Ok(None);
+  }
+  Ok(Some(FragmentSource { source_kind: FragmentSourceKind::TableRows, fragments }))
+}
+
+fn composite_fragment_source(db: &Db, composite: &Item) -> InfuResult<Option<FragmentSource>> {
+  let paragraphs = sorted_children(db, composite)?
+    .into_iter()
+    .map(|child| structured_item_text(db, child))
+    .collect::<InfuResult<Vec<_>>>()?
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+  if paragraphs.is_empty() {
+    return Ok(None);
+  }
+  let markdown = match parent_title_for_item(db, composite, true) {
+    Some(context_title) => format!("## {}\n\n{}", context_title, paragraphs.join("\n\n")),
+    None => paragraphs.join("\n\n"),
+  };
+  Ok(markdown_fragment_source(FragmentSourceKind::CompositeText, &markdown))
+}
+
+fn table_heading(table: &Item) -> Option<String> {
+  normalized_text(table.title.as_deref())
+}
+
+fn table_row_fragment_text(heading: Option<&str>, row_cells: &[TableCellText]) -> String {
+  let lines = row_cells.iter().map(TableCellText::line).collect::<Vec<_>>().join("\n");
+  match heading {
+    Some(heading) => format!("## {}\n\n{}", heading, lines),
+    None => lines,
+  }
+}
+
+fn table_row_cells(db: &Db, table: &Item) -> InfuResult<Vec<Vec<TableCellText>>> {
+  sorted_children(db, table)?
+    .into_iter()
+    .enumerate()
+    .map(|(row_index, row)| table_row_cells_for_row(db, table, row, row_index))
+    .collect()
+}
+
+/// The first column of a table row is the row item itself and later columns are its attachments, in
+/// attachment order, up to the number of visible columns.
+fn table_row_cells_for_row(db: &Db, table: &Item, row: &Item, row_index: usize) -> InfuResult<Vec<TableCellText>> {
+  let columns = table.table_columns.as_deref().unwrap_or(&[]);
+  let visible_columns =
+    table.number_of_visible_columns.map(|count| count.max(1) as usize).unwrap_or(columns.len()).max(1);
+
+  let mut row_items = vec![row];
+  if is_attachments_item_type(row.item_type) {
+    row_items.extend(sorted_attachments(db, row)?.into_iter().take(visible_columns - 1));
+  }
+
+  let mut cells = Vec::new();
+  for (column_index, cell_item) in row_items.into_iter().enumerate() {
+    let Some(text) = structured_item_text(db, cell_item)? else {
+      continue;
+    };
+    cells.push(TableCellText {
+      row_item_id: row.id.clone(),
+      row_index,
+      cell_item_id: cell_item.id.clone(),
+      column_index,
+      column_name: table_column_name(columns, column_index),
+      text,
+    });
+  }
+  Ok(cells)
+}
+
+fn table_column_name(columns: &[TableColumn], column_index: usize) -> String {
+  columns
+    .get(column_index)
+    .and_then(|column| normalized_text(Some(&column.name)))
+    .unwrap_or_else(|| format!("Column {}", column_index + 1))
+}
+
+/// Text of a table cell or composite child. Composites nested in a table or another composite contribute
+/// their children's text on one line.
+fn structured_item_text(db: &Db, item: &Item) -> InfuResult<Option<String>> {
+  match item.item_type {
+    ItemType::Password => Ok(None),
+    ItemType::Composite => {
+      let texts = sorted_children(db, item)?
+        .into_iter()
+        .filter(|child| child.item_type != ItemType::Password && child.item_type != ItemType::Composite)
+        .filter_map(|child| normalized_text(child.title.as_deref()))
+        .collect::<Vec<_>>();
+      Ok(if texts.is_empty() { None } else { Some(texts.join(" ")) })
+    }
+    _ => Ok(normalized_text(item.title.as_deref())),
+  }
+}
+
+fn sorted_children<'a>(db: &'a Db, item: &Item) -> InfuResult<Vec<&'a Item>> {
+  let mut children = db.item.get_children(&item.id)?;
+  children.sort_by(|a, b| a.ordering.cmp(&b.ordering).then(a.id.cmp(&b.id)));
+  Ok(children)
+}
+
+fn sorted_attachments<'a>(db: &'a Db, item: &Item) -> InfuResult<Vec<&'a Item>> {
+  let mut attachments = db.item.get_attachments(&item.id)?;
+  attachments.sort_by(|a, b| a.ordering.cmp(&b.ordering).then(a.id.cmp(&b.id)));
+  Ok(attachments)
+}
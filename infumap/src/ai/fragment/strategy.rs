@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use config::Config;
+use infusdk::util::infu::InfuResult;
+use once_cell::sync::OnceCell;
+
+use super::types::{CHUNKING_CONFIG_KEYS, chunking_config_key};
+
+/// The token target the built-in limits were tuned for. The other limits scale with the target, in
+/// the same proportions.
+const BASE_TARGET_TOKENS: usize = 380;
+const BASE_HARD_LIMIT_TOKENS: usize = 440;
+const BASE_SOFT_LIMIT_CHARS: usize = 1400;
+const BASE_HARD_LIMIT_CHARS: usize = 1900;
+const BASE_MIN_CHARS: usize = 500;
+const MIN_TARGET_TOKENS: usize = 32;
+const MAX_TARGET_TOKENS: usize = 4096;
+
+static CHUNKING_STRATEGIES: OnceCell<HashMap<&'static str, ChunkingStrategy>> = OnceCell::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkingMode {
+  /// Fragments follow the section structure of the document, only crossing a heading when the
+  /// current fragment would otherwise be too small.
+  HeadingAware,
+  /// Fragments are filled to the target size regardless of headings.
+  SlidingWindow,
+}
+
+impl ChunkingMode {
+  fn as_str(self) -> &'static str {
+    match self {
+      ChunkingMode::HeadingAware => "heading_aware",
+      ChunkingMode::SlidingWindow => "sliding_window",
+    }
+  }
+}
+
+/// How text of a source kind is cut into fragments. Written as whitespace separated `key=value`
+/// pairs, e.g. `mode=sliding_window target_tokens=256 overlap_tokens=32 sentence_boundaries=false`.
+/// Omitted keys take their default values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkingStrategy {
+  pub mode: ChunkingMode,
+  pub target_tokens: usize,
+  /// Tokens at the end of a fragment repeated at the start of the next one.
+  pub overlap_tokens: usize,
+  /// Whether fragments are cut between sentences where possible, rather than between words.
+  pub sentence_boundaries: bool,
+}
+
+impl Default for ChunkingStrategy {
+  fn default() -> ChunkingStrategy {
+    ChunkingStrategy {
+      mode: ChunkingMode::HeadingAware,
+      target_tokens: BASE_TARGET_TOKENS,
+      overlap_tokens: 0,
+      sentence_boundaries: true,
+    }
+  }
+}
+
+impl ChunkingStrategy {
+  pub fn parse(spec: &str) -> InfuResult<ChunkingStrategy> {
+    let mut strategy = ChunkingStrategy::default();
+    for pair in spec.split_whitespace() {
+      let Some((key, value)) = pair.split_once('=') else {
+        return Err(format!("Chunking strategy setting '{}' is not of the form key=value.", pair).into());
+      };
+      match key {
+        "mode" => {
+          strategy.mode = match value {
+            "heading_aware" => ChunkingMode::HeadingAware,
+            "sliding_window" => ChunkingMode::SlidingWindow,
+            _ => {
+              return Err(
+                format!("Unknown chunking mode '{}'. Expected 'heading_aware' or 'sliding_window'.", value).into(),
+              );
+            }
+          }
+        }
+        "target_tokens" => strategy.target_tokens = parse_usize(key, value)?,
+        "overlap_tokens" => strategy.overlap_tokens = parse_usize(key, value)?,
+        "sentence_boundaries" => {
+          strategy.sentence_boundaries =
+            value.parse::<bool>().map_err(|_| format!("Chunking setting '{}' must be true or false.", key))?
+        }
+        _ => return Err(format!("Unknown chunking strategy setting '{}'.", key).into()),
+      }
+    }
+
+    if strategy.target_tokens < MIN_TARGET_TOKENS || strategy.target_tokens > MAX_TARGET_TOKENS {
+      return Err(
+        format!("Chunking target_tokens must be between {} and {}.", MIN_TARGET_TOKENS, MAX_TARGET_TOKENS).into(),
+      );
+    }
+    if strategy.overlap_tokens * 2 > strategy.target_tokens {
+      return Err("Chunking overlap_tokens must be at most half of target_tokens.".into());
+    }
+    Ok(strategy)
+  }
+
+  pub fn soft_limit_tokens(&self) -> usize {
+    self.target_tokens
+  }
+
+  pub fn hard_limit_tokens(&self) -> usize {
+    self.scaled(BASE_HARD_LIMIT_TOKENS)
+  }
+
+  pub fn soft_limit_chars(&self) -> usize {
+    self.scaled(BASE_SOFT_LIMIT_CHARS)
+  }
+
+  pub fn hard_limit_chars(&self) -> usize {
+    self.scaled(BASE_HARD_LIMIT_CHARS)
+  }
+
+  pub fn min_chars(&self) -> usize {
+    self.scaled(BASE_MIN_CHARS)
+  }
+
+  fn scaled(&self, base: usize) -> usize {
+    base * self.target_tokens / BASE_TARGET_TOKENS
+  }
+}
+
+/// The canonical form, which is what is recorded in fragment manifests.
+impl fmt::Display for ChunkingStrategy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "mode={} target_tokens={} overlap_tokens={} sentence_boundaries={}",
+      self.mode.as_str(),
+      self.target_tokens,
+      self.overlap_tokens,
+      self.sentence_boundaries
+    )
+  }
+}
+
+fn parse_usize(key: &str, value: &str) -> InfuResult<usize> {
+  value.parse::<usize>().map_err(|_| format!("Chunking setting '{}' must be a non-negative integer.", key).into())
+}
+
+pub fn init_chunking_strategies(config: &Config) -> InfuResult<()> {
+  let mut strategies = HashMap::new();
+  for config_key in CHUNKING_CONFIG_KEYS {
+    let spec = config.get_string(config_key).map_err(|e| e.to_string())?;
+    let strategy = ChunkingStrategy::parse(&spec).map_err(|e| format!("Invalid '{}' setting: {}", config_key, e))?;
+    strategies.insert(config_key, strategy);
+  }
+  CHUNKING_STRATEGIES.set(strategies).map_err(|_| "Chunking strategies have already been initialized.")?;
+  Ok(())
+}
+
+/// The configured chunking strategy for a fragment source kind, or None if fragments of that kind are
+/// not chunked (they are built from a single piece of text). If the configuration has not been
+/// loaded, the default strategy is used.
+pub fn chunking_strategy_for_source_kind(source_kind: &str) -> Option<ChunkingStrategy> {
+  let config_key = chunking_config_key(source_kind)?;
+  Some(CHUNKING_STRATEGIES.get().and_then(|strategies| strategies.get(config_key).copied()).unwrap_or_default())
+}
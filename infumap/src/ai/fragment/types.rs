@@ -1,16 +1,46 @@
+use crate::config::{
+  CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT, CONFIG_FRAGMENT_CHUNKING_DOCUMENT, CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT,
+  CONFIG_FRAGMENT_CHUNKING_MARKDOWN, CONFIG_FRAGMENT_CHUNKING_PDF, CONFIG_FRAGMENT_CHUNKING_TEXT,
+};
+
 pub const PDF_MARKDOWN_SOURCE_KIND: &str = "pdf_markdown";
 pub const PDF_FIRST_PAGE_CAPTION_SOURCE_KIND: &str = "pdf_first_page_caption";
 pub const ITEM_TITLE_SOURCE_KIND: &str = "item_title";
 const MARKDOWN_SOURCE_KIND: &str = "markdown";
 const TEXT_SOURCE_KIND: &str = "text";
+const DOCX_MARKDOWN_SOURCE_KIND: &str = "docx_markdown";
+const ODT_MARKDOWN_SOURCE_KIND: &str = "odt_markdown";
+const EPUB_MARKDOWN_SOURCE_KIND: &str = "epub_markdown";
+const HTML_MARKDOWN_SOURCE_KIND: &str = "html_markdown";
+const LINK_SNAPSHOT_MARKDOWN_SOURCE_KIND: &str = "link_snapshot_markdown";
+pub const TABLE_ROWS_SOURCE_KIND: &str = "table_rows";
+const COMPOSITE_TEXT_SOURCE_KIND: &str = "composite_text";
+const AUDIO_TRANSCRIPT_SOURCE_KIND: &str = "audio_transcript";
 pub const IMAGE_DOCUMENT_SOURCE_KIND: &str = "image_document_contents";
 
+pub(super) const CHUNKING_CONFIG_KEYS: [&str; 6] = [
+  CONFIG_FRAGMENT_CHUNKING_PDF,
+  CONFIG_FRAGMENT_CHUNKING_MARKDOWN,
+  CONFIG_FRAGMENT_CHUNKING_TEXT,
+  CONFIG_FRAGMENT_CHUNKING_DOCUMENT,
+  CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT,
+  CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT,
+];
+
 #[derive(Clone, Copy)]
 pub enum FragmentSourceKind {
   ImageContents,
   ImageDocumentContents,
   Markdown,
   Text,
+  DocxMarkdown,
+  OdtMarkdown,
+  EpubMarkdown,
+  HtmlMarkdown,
+  LinkSnapshotMarkdown,
+  TableRows,
+  CompositeText,
+  AudioTranscript,
   PdfMarkdown,
   PdfFirstPageCaption,
 }
@@ -22,6 +52,14 @@ impl FragmentSourceKind {
       FragmentSourceKind::ImageDocumentContents => IMAGE_DOCUMENT_SOURCE_KIND,
       FragmentSourceKind::Markdown => MARKDOWN_SOURCE_KIND,
       FragmentSourceKind::Text => TEXT_SOURCE_KIND,
+      FragmentSourceKind::DocxMarkdown => DOCX_MARKDOWN_SOURCE_KIND,
+      FragmentSourceKind::OdtMarkdown => ODT_MARKDOWN_SOURCE_KIND,
+      FragmentSourceKind::EpubMarkdown => EPUB_MARKDOWN_SOURCE_KIND,
+      FragmentSourceKind::HtmlMarkdown => HTML_MARKDOWN_SOURCE_KIND,
+      FragmentSourceKind::LinkSnapshotMarkdown => LINK_SNAPSHOT_MARKDOWN_SOURCE_KIND,
+      FragmentSourceKind::TableRows => TABLE_ROWS_SOURCE_KIND,
+      FragmentSourceKind::CompositeText => COMPOSITE_TEXT_SOURCE_KIND,
+      FragmentSourceKind::AudioTranscript => AUDIO_TRANSCRIPT_SOURCE_KIND,
       FragmentSourceKind::PdfMarkdown => PDF_MARKDOWN_SOURCE_KIND,
       FragmentSourceKind::PdfFirstPageCaption => PDF_FIRST_PAGE_CAPTION_SOURCE_KIND,
     }
@@ -36,12 +74,50 @@ pub fn is_lexical_search_source_kind(source_kind: &str) -> bool {
       | PDF_FIRST_PAGE_CAPTION_SOURCE_KIND
       | MARKDOWN_SOURCE_KIND
       | TEXT_SOURCE_KIND
+      | DOCX_MARKDOWN_SOURCE_KIND
+      | ODT_MARKDOWN_SOURCE_KIND
+      | EPUB_MARKDOWN_SOURCE_KIND
+      | HTML_MARKDOWN_SOURCE_KIND
+      | LINK_SNAPSHOT_MARKDOWN_SOURCE_KIND
+      | TABLE_ROWS_SOURCE_KIND
+      | COMPOSITE_TEXT_SOURCE_KIND
+      | AUDIO_TRANSCRIPT_SOURCE_KIND
       | IMAGE_DOCUMENT_SOURCE_KIND
   )
 }
 
+/// Whether fragments of this kind cover a span of a recording, and so carry time offsets.
+pub fn is_time_offset_source_kind(source_kind: &str) -> bool {
+  source_kind == AUDIO_TRANSCRIPT_SOURCE_KIND
+}
+
+/// The setting holding the chunking strategy of a source kind, for the kinds built by cutting longer
+/// text into fragments.
+pub(super) fn chunking_config_key(source_kind: &str) -> Option<&'static str> {
+  match source_kind {
+    PDF_MARKDOWN_SOURCE_KIND => Some(CONFIG_FRAGMENT_CHUNKING_PDF),
+    MARKDOWN_SOURCE_KIND => Some(CONFIG_FRAGMENT_CHUNKING_MARKDOWN),
+    TEXT_SOURCE_KIND => Some(CONFIG_FRAGMENT_CHUNKING_TEXT),
+    DOCX_MARKDOWN_SOURCE_KIND | ODT_MARKDOWN_SOURCE_KIND | EPUB_MARKDOWN_SOURCE_KIND | HTML_MARKDOWN_SOURCE_KIND => {
+      Some(CONFIG_FRAGMENT_CHUNKING_DOCUMENT)
+    }
+    LINK_SNAPSHOT_MARKDOWN_SOURCE_KIND => Some(CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT),
+    AUDIO_TRANSCRIPT_SOURCE_KIND => Some(CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT),
+    _ => None,
+  }
+}
+
 pub fn is_markdown_document_source_kind(source_kind: &str) -> bool {
-  matches!(source_kind, PDF_MARKDOWN_SOURCE_KIND | MARKDOWN_SOURCE_KIND)
+  matches!(
+    source_kind,
+    PDF_MARKDOWN_SOURCE_KIND
+      | MARKDOWN_SOURCE_KIND
+      | DOCX_MARKDOWN_SOURCE_KIND
+      | ODT_MARKDOWN_SOURCE_KIND
+      | EPUB_MARKDOWN_SOURCE_KIND
+      | HTML_MARKDOWN_SOURCE_KIND
+      | LINK_SNAPSHOT_MARKDOWN_SOURCE_KIND
+  )
 }
 
 #[derive(Default)]
@@ -55,11 +131,23 @@ pub struct FragmentInput {
   pub text: String,
   pub page_start: Option<usize>,
   pub page_end: Option<usize>,
+  /// For table row fragments, the row item, so search hits can find their cells without rebuilding the table.
+  pub row_item_id: Option<String>,
+  /// For transcript fragments, the span of the recording covered, in whole seconds.
+  pub time_start_secs: Option<usize>,
+  pub time_end_secs: Option<usize>,
 }
 
 impl FragmentInput {
   pub fn new(text: String) -> FragmentInput {
-    FragmentInput { text, page_start: None, page_end: None }
+    FragmentInput {
+      text,
+      page_start: None,
+      page_end: None,
+      row_item_id: None,
+      time_start_secs: None,
+      time_end_secs: None,
+    }
   }
 
   pub fn with_page_range(mut self, page_start: Option<usize>, page_end: Option<usize>) -> FragmentInput {
@@ -67,6 +155,17 @@ impl FragmentInput {
     self.page_end = page_end;
     self
   }
+
+  pub fn with_time_range(mut self, time_start_secs: usize, time_end_secs: usize) -> FragmentInput {
+    self.time_start_secs = Some(time_start_secs);
+    self.time_end_secs = Some(time_end_secs);
+    self
+  }
+
+  pub fn with_row_item_id(mut self, row_item_id: String) -> FragmentInput {
+    self.row_item_id = Some(row_item_id);
+    self
+  }
 }
 
 pub struct FragmentSource {
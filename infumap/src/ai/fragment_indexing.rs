@@ -1,8 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use config::Config;
+use infusdk::item::Item;
 use infusdk::util::infu::InfuResult;
 use log::{debug, error, info};
 use once_cell::sync::OnceCell;
@@ -10,8 +11,14 @@ use tokio::sync::Mutex;
 use tokio::task;
 use tokio::time::{Instant as TokioInstant, sleep};
 
+use crate::ai::audio_pipeline::{enqueue_audio_transcript_item_if_active, is_audio_item};
+use crate::ai::document_pipeline::{enqueue_document_fragment_item_if_active, is_document_fragment_item};
+use crate::ai::fragment::item_fragment_chunking_strategy_changed;
 use crate::ai::gpu_tools::gpu_tools_url_from_config;
+use crate::ai::image_tagging::is_supported_image_tagging_mime_type;
+use crate::ai::image_vectors::{ImageVectorSyncSummary, sync_user_image_vectors};
 use crate::ai::indexing::{EmbedRebuildSummary, LoadedFragmentIndexItem, reconcile_fragment_indexes_for_loaded_items};
+use crate::ai::link_snapshot_pipeline::{enqueue_link_snapshot_item_if_active, link_snapshot_url};
 use crate::ai::metrics::{METRIC_AI_FRAGMENT_INDEX_REBUILD_DURATION_SECONDS, METRIC_AI_FRAGMENT_INDEX_REBUILDS_TOTAL};
 use crate::ai::text_embedding::{resolve_optional_text_embedding_service_url, text_embed_url_from_config};
 use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
@@ -157,6 +164,7 @@ async fn run_fragment_indexing_loop(
   state: Arc<Mutex<DirtyFragmentIndexState>>,
 ) {
   refresh_semantic_enabled(&config, state.clone()).await;
+  enqueue_items_with_changed_chunking_strategy(&config, db.clone()).await;
   enqueue_all_loaded_users_for_fragment_index_rebuild_inner(db.clone(), state.clone()).await;
 
   loop {
@@ -204,6 +212,51 @@ async fn enqueue_all_loaded_users_for_fragment_index_rebuild_inner(
   );
 }
 
+/// Queue items whose fragments were cut with a chunking strategy other than the one now configured
+/// for their source kind with the pipelines that build them. Only those items are re-fragmented, and
+/// their users' indexes are reconciled once the new fragments are written.
+async fn enqueue_items_with_changed_chunking_strategy(config: &FragmentIndexingConfig, db: Arc<Mutex<Db>>) {
+  let items = {
+    let db = db.lock().await;
+    db.item
+      .all_loaded_items()
+      .into_iter()
+      .filter_map(|item_key| db.item.get(&item_key.item_id).ok())
+      .filter(|item| is_document_fragment_item(item) || is_audio_item(item) || link_snapshot_url(item).is_some())
+      .cloned()
+      .collect::<Vec<Item>>()
+  };
+
+  let mut changed = 0usize;
+  for item in items {
+    match item_fragment_chunking_strategy_changed(&config.data_dir, &item.owner_id, &item.id).await {
+      Ok(true) => {}
+      Ok(false) => continue,
+      Err(e) => {
+        debug!(
+          "Could not check fragment chunking strategy for item '{}' (user {}): {}",
+          item.id,
+          user_id_for_log(&item.owner_id),
+          e
+        );
+        continue;
+      }
+    }
+    changed += 1;
+    if is_document_fragment_item(&item) {
+      enqueue_document_fragment_item_if_active(&item);
+    } else if is_audio_item(&item) {
+      enqueue_audio_transcript_item_if_active(&item);
+    } else {
+      enqueue_link_snapshot_item_if_active(&item);
+    }
+  }
+
+  if changed > 0 {
+    info!("Queued {} item(s) for fragment rebuild because their chunking strategy changed.", changed);
+  }
+}
+
 async fn should_rebuild_dirty_users(state: Arc<Mutex<DirtyFragmentIndexState>>) -> bool {
   let state = state.lock().await;
   state.should_rebuild(TokioInstant::now())
@@ -304,6 +357,12 @@ async fn rebuild_fragment_indexes_for_dirty_users(
     loaded_items.len(),
     user_ids.len()
   );
+  let mut image_item_ids_by_user = user_ids.iter().map(|user_id| (user_id.clone(), vec![])).collect::<HashMap<_, _>>();
+  for item in loaded_items.iter().filter(|item| is_supported_image_tagging_mime_type(item.mime_type.as_deref())) {
+    if let Some(item_ids) = image_item_ids_by_user.get_mut(&item.user_id) {
+      item_ids.push(item.item_id.clone());
+    }
+  }
 
   let rebuild_started = Instant::now();
   let rebuild_result = reconcile_fragment_indexes_for_loaded_items(
@@ -354,6 +413,30 @@ async fn rebuild_fragment_indexes_for_dirty_users(
       record_dirty_users(state, user_ids).await;
     }
   }
+
+  sync_image_vectors_for_users(&config.data_dir, image_item_ids_by_user).await;
+}
+
+/// Image vectors are derived from tag artifacts rather than fragments, but images are tagged and
+/// removed on the same schedule, so they are brought up to date alongside the fragment indexes.
+async fn sync_image_vectors_for_users(data_dir: &str, image_item_ids_by_user: HashMap<String, Vec<String>>) {
+  let mut summary = ImageVectorSyncSummary::default();
+  for (user_id, image_item_ids) in image_item_ids_by_user {
+    match sync_user_image_vectors(data_dir, &user_id, &image_item_ids).await {
+      Ok(user_summary) => {
+        summary.upserted += user_summary.upserted;
+        summary.removed += user_summary.removed;
+        summary.unchanged += user_summary.unchanged;
+      }
+      Err(e) => error!("Image vector sync failed for user {}: {}", user_id_for_log(&user_id), e),
+    }
+  }
+  if summary.upserted > 0 || summary.removed > 0 {
+    info!(
+      "Image vector sync complete: upserted={} removed={} unchanged={}.",
+      summary.upserted, summary.removed, summary.unchanged
+    );
+  }
 }
 
 async fn record_dirty_users(state: Arc<Mutex<DirtyFragmentIndexState>>, user_ids: Vec<String>) {
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use infusdk::util::infu::InfuResult;
+use serde_json::{Value, json};
+use tokio::fs;
+
+use crate::util::fs::{expand_tilde, path_exists};
+
+const GAZETTEER_DIR_NAME: &str = "geo";
+const GAZETTEER_FILENAME: &str = "gazetteer.tsv";
+const GAZETTEER_HEADER: &str = "# infumap gazetteer v1";
+const GAZETTEER_SOURCE_NAME: &str = "geonames";
+
+/// Beyond this distance the nearest place isn't reported as the city, only its region and country.
+const CITY_MAX_DISTANCE_METERS: f64 = 25_000.0;
+/// Beyond this distance (e.g. at sea) nothing is reported.
+const REGION_MAX_DISTANCE_METERS: f64 = 200_000.0;
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+const METERS_PER_DEGREE_LATITUDE: f64 = 111_195.0;
+
+pub struct GazetteerPlace {
+  pub name: String,
+  pub admin1: Option<String>,
+  pub country: Option<String>,
+  pub country_code: String,
+  pub latitude: f64,
+  pub longitude: f64,
+}
+
+/// Populated places from an imported GeoNames dataset, for reverse geocoding without an external
+/// service. Places are bucketed into one degree cells, so a lookup only considers nearby places.
+pub struct Gazetteer {
+  path: PathBuf,
+  places: Vec<GazetteerPlace>,
+  cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Gazetteer {
+  pub fn load(path: &Path) -> InfuResult<Gazetteer> {
+    let text =
+      std::fs::read_to_string(path).map_err(|e| format!("Could not read gazetteer '{}': {}", path.display(), e))?;
+    let mut lines = text.lines();
+    if lines.next() != Some(GAZETTEER_HEADER) {
+      return Err(format!("'{}' is not an Infumap gazetteer file.", path.display()).into());
+    }
+
+    let mut places = vec![];
+    let mut cells = HashMap::<(i32, i32), Vec<usize>>::new();
+    for (line_index, line) in lines.enumerate() {
+      let fields = line.split('\t').collect::<Vec<_>>();
+      let place = parse_gazetteer_row(&fields).ok_or(format!(
+        "Invalid row {} in gazetteer '{}'.",
+        line_index + 2,
+        path.display()
+      ))?;
+      cells.entry(cell_for(place.latitude, place.longitude)).or_default().push(places.len());
+      places.push(place);
+    }
+    Ok(Gazetteer { path: path.to_owned(), places, cells })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// The nearest place within REGION_MAX_DISTANCE_METERS, along with its distance in meters.
+  pub fn nearest(&self, latitude: f64, longitude: f64) -> Option<(&GazetteerPlace, f64)> {
+    let (cell_lat, cell_lon) = cell_for(latitude, longitude);
+    let lat_span = (REGION_MAX_DISTANCE_METERS / METERS_PER_DEGREE_LATITUDE).ceil() as i32;
+    let meters_per_degree_longitude = METERS_PER_DEGREE_LATITUDE * latitude.to_radians().cos().abs();
+    let lon_span = if meters_per_degree_longitude < 1.0 {
+      180
+    } else {
+      ((REGION_MAX_DISTANCE_METERS / meters_per_degree_longitude).ceil() as i32).min(180)
+    };
+
+    let mut best: Option<(&GazetteerPlace, f64)> = None;
+    for lat in (cell_lat - lat_span)..=(cell_lat + lat_span) {
+      for lon_offset in -lon_span..=lon_span {
+        let lon = (cell_lon + lon_offset + 180).rem_euclid(360) - 180;
+        let Some(indexes) = self.cells.get(&(lat, lon)) else { continue };
+        for index in indexes {
+          let place = &self.places[*index];
+          let distance = haversine_meters(latitude, longitude, place.latitude, place.longitude);
+          if distance <= REGION_MAX_DISTANCE_METERS && best.is_none_or(|(_, best_distance)| distance < best_distance) {
+            best = Some((place, distance));
+          }
+        }
+      }
+    }
+    best
+  }
+
+  /// A reverse geocoding response in the shape of a Geoapify response, so that it can be stored and
+  /// consumed in the same way.
+  pub fn reverse_geocode(&self, latitude: f64, longitude: f64) -> Value {
+    let results = match self.nearest(latitude, longitude) {
+      Some((place, distance)) => {
+        let city = if distance <= CITY_MAX_DISTANCE_METERS { Some(place.name.as_str()) } else { None };
+        let formatted = [city, place.admin1.as_deref(), place.country.as_deref()]
+          .into_iter()
+          .flatten()
+          .collect::<Vec<_>>()
+          .join(", ");
+        vec![json!({
+          "datasource": { "sourcename": GAZETTEER_SOURCE_NAME },
+          "city": city,
+          "state": place.admin1,
+          "country": place.country,
+          "country_code": place.country_code.to_lowercase(),
+          "lat": place.latitude,
+          "lon": place.longitude,
+          "distance": distance.round(),
+          "result_type": if city.is_some() { "city" } else if place.admin1.is_some() { "state" } else { "country" },
+          "formatted": formatted,
+        })]
+      }
+      None => vec![],
+    };
+    json!({ "results": results, "query": { "lat": latitude, "lon": longitude } })
+  }
+}
+
+pub fn gazetteer_path(data_dir: &str) -> InfuResult<PathBuf> {
+  let mut path = expand_tilde(data_dir).ok_or("Could not interpret path.")?;
+  path.push(GAZETTEER_DIR_NAME);
+  path.push(GAZETTEER_FILENAME);
+  Ok(path)
+}
+
+/// Convert a GeoNames cities file (e.g. cities1000.txt or cities15000.txt), along with the optional
+/// admin1CodesASCII.txt and countryInfo.txt files used to name regions and countries, into the
+/// gazetteer used for offline reverse geocoding. Returns the number of places imported.
+pub async fn import_geonames_gazetteer(
+  data_dir: &str,
+  cities_path: &Path,
+  admin1_codes_path: Option<&Path>,
+  country_info_path: Option<&Path>,
+) -> InfuResult<usize> {
+  let admin1_names = match admin1_codes_path {
+    Some(path) => read_geonames_names(path, 0, 1).await?,
+    None => HashMap::new(),
+  };
+  let country_names = match country_info_path {
+    Some(path) => read_geonames_names(path, 0, 4).await?,
+    None => HashMap::new(),
+  };
+
+  let cities = fs::read_to_string(cities_path)
+    .await
+    .map_err(|e| format!("Could not read GeoNames cities file '{}': {}", cities_path.display(), e))?;
+  let mut output = String::from(GAZETTEER_HEADER);
+  output.push('\n');
+  let mut count = 0;
+  for (line_index, line) in cities.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let fields = line.split('\t').collect::<Vec<_>>();
+    if fields.len() < 11 {
+      return Err(
+        format!(
+          "Line {} of '{}' is not a GeoNames cities record (expected 19 tab separated fields).",
+          line_index + 1,
+          cities_path.display()
+        )
+        .into(),
+      );
+    }
+    let (Ok(latitude), Ok(longitude)) = (fields[4].parse::<f64>(), fields[5].parse::<f64>()) else {
+      continue;
+    };
+    let country_code = fields[8].trim();
+    let admin1 = admin1_names.get(&format!("{}.{}", country_code, fields[10].trim()));
+    let country = country_names.get(country_code);
+    output.push_str(&format!(
+      "{}\t{}\t{}\t{}\t{}\t{}\n",
+      latitude,
+      longitude,
+      tsv_field(fields[1]),
+      admin1.map(|name| tsv_field(name)).unwrap_or_default(),
+      country.map(|name| tsv_field(name)).unwrap_or_default(),
+      tsv_field(country_code)
+    ));
+    count += 1;
+  }
+  if count == 0 {
+    return Err(format!("No places found in '{}'.", cities_path.display()).into());
+  }
+
+  let path = gazetteer_path(data_dir)?;
+  let dir = path.parent().ok_or("Gazetteer path has no parent directory.")?;
+  if !path_exists(&dir.to_path_buf()).await {
+    fs::create_dir_all(dir).await?;
+  }
+  let temp_path = path.with_extension("tsv.tmp");
+  fs::write(&temp_path, output).await?;
+  fs::rename(&temp_path, &path)
+    .await
+    .map_err(|e| format!("Could not replace gazetteer '{}': {}", path.display(), e))?;
+  Ok(count)
+}
+
+/// Code to name mappings from a GeoNames tab separated file, skipping comment lines.
+async fn read_geonames_names(path: &Path, code_field: usize, name_field: usize) -> InfuResult<HashMap<String, String>> {
+  let text =
+    fs::read_to_string(path).await.map_err(|e| format!("Could not read GeoNames file '{}': {}", path.display(), e))?;
+  Ok(
+    text
+      .lines()
+      .filter(|line| !line.starts_with('#'))
+      .filter_map(|line| {
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let code = fields.get(code_field)?.trim();
+        let name = fields.get(name_field)?.trim();
+        if code.is_empty() || name.is_empty() { None } else { Some((code.to_owned(), name.to_owned())) }
+      })
+      .collect(),
+  )
+}
+
+fn parse_gazetteer_row(fields: &[&str]) -> Option<GazetteerPlace> {
+  if fields.len() != 6 {
+    return None;
+  }
+  let non_empty = |value: &str| if value.is_empty() { None } else { Some(value.to_owned()) };
+  Some(GazetteerPlace {
+    latitude: fields[0].parse().ok()?,
+    longitude: fields[1].parse().ok()?,
+    name: fields[2].to_owned(),
+    admin1: non_empty(fields[3]),
+    country: non_empty(fields[4]),
+    country_code: fields[5].to_owned(),
+  })
+}
+
+fn tsv_field(value: &str) -> String {
+  value.replace(['\t', '\n', '\r'], " ").trim().to_owned()
+}
+
+fn cell_for(latitude: f64, longitude: f64) -> (i32, i32) {
+  (latitude.floor() as i32, (longitude.floor() as i32 + 180).rem_euclid(360) - 180)
+}
+
+fn haversine_meters(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
+  let d_lat = (lat_b - lat_a).to_radians();
+  let d_lon = (lon_b - lon_a).to_radians();
+  let a =
+    (d_lat / 2.0).sin().powi(2) + lat_a.to_radians().cos() * lat_b.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+  2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+}
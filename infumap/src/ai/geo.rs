@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use config::Config;
@@ -15,16 +16,19 @@ use tokio::time::{Instant as TokioInstant, sleep_until};
 use crate::ai::artifact_paths::{
   ensure_user_text_dir, item_geo_content_path, item_geo_manifest_path, item_text_content_path,
 };
+use crate::ai::gazetteer::{Gazetteer, gazetteer_path};
 use crate::ai::image_tagging::{image_tagging_manifest_is_successful, is_supported_image_tagging_mime_type};
 use crate::ai::user_id_for_log;
 use crate::config::{
-  CONFIG_GEOAPIFY_API_KEY, CONFIG_GEOAPIFY_MAX_REQUESTS_PER_MINUTE, CONFIG_GEOAPIFY_URL, CONFIG_GEOAPIFY_URL_DEFAULT,
+  CONFIG_DATA_DIR, CONFIG_GEO_PROVIDER, CONFIG_GEOAPIFY_API_KEY, CONFIG_GEOAPIFY_MAX_REQUESTS_PER_MINUTE,
+  CONFIG_GEOAPIFY_URL, CONFIG_GEOAPIFY_URL_DEFAULT,
 };
 use crate::util::fs::path_exists;
 
 const JSON_CONTENT_MIME_TYPE: &str = "application/json";
 const GEO_MANIFEST_SCHEMA_VERSION: u32 = 1;
-const GEOAPIFY_PROVIDER_NAME: &str = "geoapify";
+pub const GEOAPIFY_PROVIDER_NAME: &str = "geoapify";
+pub const OFFLINE_PROVIDER_NAME: &str = "offline";
 const DEFAULT_GEOAPIFY_RATE_LIMIT_RETRY_SECS: u64 = 60;
 const DEFAULT_GEOAPIFY_QUOTA_RETRY_SECS: u64 = 24 * 60 * 60;
 
@@ -45,6 +49,31 @@ impl GeoCandidate {
   }
 }
 
+/// Where place names for GPS coordinates come from. The offline provider looks them up in a gazetteer
+/// imported into the data directory, so coordinates never leave the server.
+#[derive(Clone)]
+pub enum GeoProvider {
+  Geoapify { service_url: String, api_key: String },
+  Offline(Arc<Gazetteer>),
+}
+
+impl GeoProvider {
+  pub fn name(&self) -> &'static str {
+    match self {
+      GeoProvider::Geoapify { .. } => GEOAPIFY_PROVIDER_NAME,
+      GeoProvider::Offline(_) => OFFLINE_PROVIDER_NAME,
+    }
+  }
+
+  /// Recorded in geo manifests as the service URL.
+  pub fn source(&self) -> String {
+    match self {
+      GeoProvider::Geoapify { service_url, .. } => service_url.clone(),
+      GeoProvider::Offline(gazetteer) => gazetteer.path().display().to_string(),
+    }
+  }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GeoDeferralReason {
   RateLimited,
@@ -69,7 +98,7 @@ impl GeoDeferralReason {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum GeoProcessOutcome {
-  Succeeded { cached: bool },
+  Succeeded { cached: bool, external_request: bool },
   Failed { external_request: bool },
   Deferred { reason: GeoDeferralReason, retry_after_secs: u64 },
   SkippedExisting,
@@ -81,7 +110,7 @@ impl GeoProcessOutcome {
   pub fn sent_external_request(&self) -> bool {
     matches!(
       self,
-      GeoProcessOutcome::Succeeded { cached: false }
+      GeoProcessOutcome::Succeeded { external_request: true, .. }
         | GeoProcessOutcome::Failed { external_request: true }
         | GeoProcessOutcome::Deferred { .. }
     )
@@ -127,11 +156,12 @@ pub struct GeoRunSummary {
 impl GeoRunSummary {
   pub fn record(&mut self, outcome: &GeoProcessOutcome) {
     match outcome {
-      GeoProcessOutcome::Succeeded { cached } => {
+      GeoProcessOutcome::Succeeded { cached, external_request } => {
         self.succeeded += 1;
         if *cached {
           self.cache_hits += 1;
-        } else {
+        }
+        if *external_request {
           self.external_requests += 1;
         }
       }
@@ -233,6 +263,43 @@ pub fn geoapify_url_from_config(config: &Config) -> InfuResult<String> {
   Ok(trimmed.to_owned())
 }
 
+pub fn geo_provider_name_from_config(config: &Config) -> InfuResult<String> {
+  let value = config.get_string(CONFIG_GEO_PROVIDER).unwrap_or(GEOAPIFY_PROVIDER_NAME.to_owned());
+  let value = value.trim().to_ascii_lowercase();
+  if value != GEOAPIFY_PROVIDER_NAME && value != OFFLINE_PROVIDER_NAME {
+    return Err(
+      format!("{} must be '{}' or '{}'.", CONFIG_GEO_PROVIDER, GEOAPIFY_PROVIDER_NAME, OFFLINE_PROVIDER_NAME).into(),
+    );
+  }
+  Ok(value)
+}
+
+/// The configured reverse geocoding provider, or None if Geoapify is selected but has no API key.
+pub fn geo_provider_from_config(config: &Config) -> InfuResult<Option<GeoProvider>> {
+  if geo_provider_name_from_config(config)? == OFFLINE_PROVIDER_NAME {
+    let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+    return Ok(Some(offline_geo_provider(&data_dir)?));
+  }
+  let Some(api_key) = geoapify_api_key_from_config(config)? else {
+    return Ok(None);
+  };
+  Ok(Some(GeoProvider::Geoapify { service_url: geoapify_url_from_config(config)?, api_key }))
+}
+
+pub fn offline_geo_provider(data_dir: &str) -> InfuResult<GeoProvider> {
+  let path = gazetteer_path(data_dir)?;
+  if !path.exists() {
+    return Err(
+      format!(
+        "The offline geo provider requires a gazetteer at '{}'. Import one with 'infumap geo --import-gazetteer'.",
+        path.display()
+      )
+      .into(),
+    );
+  }
+  Ok(GeoProvider::Offline(Arc::new(Gazetteer::load(&path)?)))
+}
+
 pub fn resolve_geoapify_api_key(config: &Config) -> InfuResult<String> {
   geoapify_api_key_from_config(config)?.ok_or(format!("{} must be configured.", CONFIG_GEOAPIFY_API_KEY).into())
 }
@@ -267,8 +334,7 @@ impl GeoRequestThrottle {
 pub async fn reverse_geocode_candidate_if_needed(
   data_dir: &str,
   client: &reqwest::Client,
-  service_url: &str,
-  api_key: &str,
+  provider: &GeoProvider,
   candidate: &GeoCandidate,
   overwrite: bool,
   cache: &mut HashMap<String, Value>,
@@ -284,21 +350,33 @@ pub async fn reverse_geocode_candidate_if_needed(
       return Ok(GeoProcessOutcome::SkippedWithoutImageTagOutput);
     }
     GeoCoordinateLoad::InvalidMetadataOutput(error_message) => {
-      write_failed_geo_manifest(data_dir, candidate, service_url, None, None, false, None, &error_message).await?;
+      write_failed_geo_manifest(data_dir, candidate, provider, None, None, false, None, &error_message).await?;
       return Ok(GeoProcessOutcome::Failed { external_request: false });
     }
     GeoCoordinateLoad::Loaded(coords) => coords,
   };
 
   let Some((lat, lon)) = coords else {
-    write_skipped_geo_manifest(data_dir, candidate, service_url, None, None, false, "missing GPS").await?;
+    write_skipped_geo_manifest(data_dir, candidate, provider, None, None, false, "missing GPS").await?;
     return Ok(GeoProcessOutcome::SkippedNoGps);
   };
 
+  let (service_url, api_key) = match provider {
+    GeoProvider::Geoapify { service_url, api_key } => (service_url, api_key),
+    GeoProvider::Offline(gazetteer) => {
+      let request_started_at = Instant::now();
+      let response_json = gazetteer.reverse_geocode(lat, lon);
+      let duration_ms = elapsed_millis(request_started_at.elapsed());
+      write_success_geo_artifacts(data_dir, candidate, provider, lat, lon, false, Some(duration_ms), &response_json)
+        .await?;
+      return Ok(GeoProcessOutcome::Succeeded { cached: false, external_request: false });
+    }
+  };
+
   let cache_key = format!("{lat:.7},{lon:.7}");
   if let Some(cached_response) = cache.get(&cache_key) {
-    write_success_geo_artifacts(data_dir, candidate, service_url, lat, lon, true, Some(0), cached_response).await?;
-    return Ok(GeoProcessOutcome::Succeeded { cached: true });
+    write_success_geo_artifacts(data_dir, candidate, provider, lat, lon, true, Some(0), cached_response).await?;
+    return Ok(GeoProcessOutcome::Succeeded { cached: true, external_request: false });
   }
 
   if let Some(throttle) = throttle {
@@ -310,9 +388,9 @@ pub async fn reverse_geocode_candidate_if_needed(
     Ok(response_json) => {
       let duration_ms = elapsed_millis(request_started_at.elapsed());
       cache.insert(cache_key, response_json.clone());
-      write_success_geo_artifacts(data_dir, candidate, service_url, lat, lon, false, Some(duration_ms), &response_json)
+      write_success_geo_artifacts(data_dir, candidate, provider, lat, lon, false, Some(duration_ms), &response_json)
         .await?;
-      Ok(GeoProcessOutcome::Succeeded { cached: false })
+      Ok(GeoProcessOutcome::Succeeded { cached: false, external_request: true })
     }
     Err(e) => match e {
       GeoRequestError::Deferred { reason, retry_after_secs, .. } => {
@@ -323,7 +401,7 @@ pub async fn reverse_geocode_candidate_if_needed(
         write_failed_geo_manifest(
           data_dir,
           candidate,
-          service_url,
+          provider,
           Some(lat),
           Some(lon),
           false,
@@ -483,7 +561,7 @@ pub async fn reverse_geocode(
 async fn write_success_geo_artifacts(
   data_dir: &str,
   candidate: &GeoCandidate,
-  service_url: &str,
+  provider: &GeoProvider,
   lat: f64,
   lon: f64,
   cached: bool,
@@ -500,8 +578,8 @@ async fn write_success_geo_artifacts(
     source_mime_type: candidate.mime_type.clone(),
     content_mime_type: JSON_CONTENT_MIME_TYPE.to_owned(),
     extractor: GeoManifestExtractor {
-      provider: GEOAPIFY_PROVIDER_NAME.to_owned(),
-      service_url: service_url.to_owned(),
+      provider: provider.name().to_owned(),
+      service_url: provider.source(),
       reverse_geocoded_at_unix_secs: unix_now_secs()?,
       duration_ms,
       query_latitude: Some(lat),
@@ -523,7 +601,7 @@ async fn write_success_geo_artifacts(
 async fn write_failed_geo_manifest(
   data_dir: &str,
   candidate: &GeoCandidate,
-  service_url: &str,
+  provider: &GeoProvider,
   lat: Option<f64>,
   lon: Option<f64>,
   cached: bool,
@@ -542,8 +620,8 @@ async fn write_failed_geo_manifest(
     source_mime_type: candidate.mime_type.clone(),
     content_mime_type: JSON_CONTENT_MIME_TYPE.to_owned(),
     extractor: GeoManifestExtractor {
-      provider: GEOAPIFY_PROVIDER_NAME.to_owned(),
-      service_url: service_url.to_owned(),
+      provider: provider.name().to_owned(),
+      service_url: provider.source(),
       reverse_geocoded_at_unix_secs: unix_now_secs()?,
       duration_ms,
       query_latitude: lat,
@@ -565,7 +643,7 @@ async fn write_failed_geo_manifest(
 async fn write_skipped_geo_manifest(
   data_dir: &str,
   candidate: &GeoCandidate,
-  service_url: &str,
+  provider: &GeoProvider,
   lat: Option<f64>,
   lon: Option<f64>,
   cached: bool,
@@ -583,8 +661,8 @@ async fn write_skipped_geo_manifest(
     source_mime_type: candidate.mime_type.clone(),
     content_mime_type: JSON_CONTENT_MIME_TYPE.to_owned(),
     extractor: GeoManifestExtractor {
-      provider: GEOAPIFY_PROVIDER_NAME.to_owned(),
-      service_url: service_url.to_owned(),
+      provider: provider.name().to_owned(),
+      service_url: provider.source(),
       reverse_geocoded_at_unix_secs: unix_now_secs()?,
       duration_ms: None,
       query_latitude: lat,
@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+use once_cell::sync::Lazy;
+
+use crate::ai::geo::extract_geo_query_coordinates;
+use crate::ai::image_tag_cache::{ImageTagValueCache, ImageTagValueStore};
+use crate::ai::image_tagging::should_tag_image_item;
+use crate::storage::db::Db;
+
+static EXIF_LOCATIONS: Lazy<ImageTagValueCache<ExifLocations>> =
+  Lazy::new(|| ImageTagValueCache::new("GPS coordinates"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemLocationSource {
+  /// Set explicitly on the item, taking precedence over any other source.
+  Manual,
+  /// The GPS coordinates recorded by the camera, from the image-tag output.
+  Exif,
+}
+
+impl ItemLocationSource {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ItemLocationSource::Manual => "manual",
+      ItemLocationSource::Exif => "exif",
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ItemLocation {
+  pub latitude: f64,
+  pub longitude: f64,
+  pub source: ItemLocationSource,
+}
+
+/// A latitude/longitude rectangle. `west` is greater than `east` if the box crosses the antimeridian.
+#[derive(Clone, Copy, Debug)]
+pub struct GeoBounds {
+  pub south: f64,
+  pub west: f64,
+  pub north: f64,
+  pub east: f64,
+}
+
+impl GeoBounds {
+  pub fn validate(&self) -> InfuResult<()> {
+    for (name, value, limit) in
+      [("south", self.south, 90.0), ("north", self.north, 90.0), ("west", self.west, 180.0), ("east", self.east, 180.0)]
+    {
+      if !value.is_finite() || value.abs() > limit {
+        return Err(format!("Bounding box {} must be within [-{}, {}], got {}.", name, limit, limit, value).into());
+      }
+    }
+    if self.south > self.north {
+      return Err(
+        format!("Bounding box south ({}) must not be greater than north ({}).", self.south, self.north).into(),
+      );
+    }
+    Ok(())
+  }
+
+  pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+    if latitude < self.south || latitude > self.north {
+      return false;
+    }
+    if self.crosses_antimeridian() {
+      longitude >= self.west || longitude <= self.east
+    } else {
+      longitude >= self.west && longitude <= self.east
+    }
+  }
+
+  pub fn crosses_antimeridian(&self) -> bool {
+    self.west > self.east
+  }
+
+  /// Width in degrees of longitude.
+  pub fn longitude_span(&self) -> f64 {
+    if self.crosses_antimeridian() { 360.0 - self.west + self.east } else { self.east - self.west }
+  }
+}
+
+/// GPS coordinates of images from their image-tag output, bucketed into one degree cells.
+#[derive(Default)]
+struct ExifLocations {
+  coordinates: HashMap<String, Option<(f64, f64)>>,
+  cells: HashMap<(i32, i32), HashSet<String>>,
+}
+
+impl ImageTagValueStore for ExifLocations {
+  type Value = (f64, f64);
+
+  fn contains(&self, item_id: &str) -> bool {
+    self.coordinates.contains_key(item_id)
+  }
+
+  fn insert(&mut self, item_id: String, value: Option<(f64, f64)>) {
+    self.remove(&item_id);
+    if let Some((latitude, longitude)) = value {
+      self.cells.entry(cell_for(latitude, longitude)).or_default().insert(item_id.clone());
+    }
+    self.coordinates.insert(item_id, value);
+  }
+
+  fn remove(&mut self, item_id: &str) {
+    if let Some(Some((latitude, longitude))) = self.coordinates.remove(item_id) {
+      let cell = cell_for(latitude, longitude);
+      if let Some(item_ids) = self.cells.get_mut(&cell) {
+        item_ids.remove(item_id);
+        if item_ids.is_empty() {
+          self.cells.remove(&cell);
+        }
+      }
+    }
+  }
+}
+
+impl ExifLocations {
+  fn get(&self, item_id: &str) -> Option<(f64, f64)> {
+    self.coordinates.get(item_id).copied().flatten()
+  }
+
+  fn query(&self, bounds: &GeoBounds) -> Vec<(String, (f64, f64))> {
+    let south_cell = bounds.south.floor() as i32;
+    let north_cell = bounds.north.floor() as i32;
+    let west_cell = cell_for(0.0, bounds.west).1;
+    let longitude_cells = (bounds.longitude_span().ceil() as i32 + 1).min(360);
+    let bounds_cell_count = ((north_cell - south_cell + 1) * longitude_cells) as usize;
+
+    let mut item_ids = vec![];
+    if bounds_cell_count > self.cells.len() {
+      for ((lat, lon), cell_item_ids) in self.cells.iter() {
+        if *lat >= south_cell && *lat <= north_cell {
+          let offset = (lon - west_cell).rem_euclid(360);
+          if offset < longitude_cells {
+            item_ids.extend(cell_item_ids.iter());
+          }
+        }
+      }
+    } else {
+      for lat in south_cell..=north_cell {
+        for offset in 0..longitude_cells {
+          let lon = (west_cell + offset + 180).rem_euclid(360) - 180;
+          if let Some(cell_item_ids) = self.cells.get(&(lat, lon)) {
+            item_ids.extend(cell_item_ids.iter());
+          }
+        }
+      }
+    }
+
+    item_ids
+      .into_iter()
+      .filter_map(|item_id| {
+        let (latitude, longitude) = self.get(item_id)?;
+        if bounds.contains(latitude, longitude) { Some((item_id.clone(), (latitude, longitude))) } else { None }
+      })
+      .collect()
+  }
+}
+
+/// The user's located items within the bounding box. A location set on an item takes precedence over
+/// the GPS coordinates of an image, which are read from the image-tag output of any image not looked
+/// at before. The results may include images that have since been deleted or moved, so callers are
+/// expected to check them against the item DB.
+pub async fn query_user_item_locations(
+  data_dir: &str,
+  db: Arc<tokio::sync::Mutex<Db>>,
+  user_id: &str,
+  bounds: &GeoBounds,
+) -> InfuResult<Vec<(String, ItemLocation)>> {
+  let mut located_items = vec![];
+  let mut manually_located_item_ids = HashSet::new();
+  let mut image_item_ids = vec![];
+  {
+    let db = db.lock().await;
+    for item_and_user_id in db.item.all_loaded_items().iter().filter(|item| item.user_id == user_id) {
+      let item = db.item.get(&item_and_user_id.item_id)?;
+      if let Some(location) = manual_item_location(item) {
+        if bounds.contains(location.latitude, location.longitude) {
+          located_items.push((item.id.clone(), location));
+        }
+        manually_located_item_ids.insert(item.id.clone());
+      } else if should_tag_image_item(item) {
+        image_item_ids.push(item.id.clone());
+      }
+    }
+  }
+
+  EXIF_LOCATIONS.resolve(data_dir, user_id, &image_item_ids, parse_image_tag_coordinates).await?;
+  EXIF_LOCATIONS.with(user_id, |exif_locations| {
+    for (item_id, (latitude, longitude)) in exif_locations.query(bounds) {
+      if !manually_located_item_ids.contains(&item_id) {
+        located_items.push((item_id, ItemLocation { latitude, longitude, source: ItemLocationSource::Exif }));
+      }
+    }
+  });
+  Ok(located_items)
+}
+
+/// The location set on the item, if any.
+fn manual_item_location(item: &Item) -> Option<ItemLocation> {
+  match (item.latitude, item.longitude) {
+    (Some(latitude), Some(longitude)) => Some(ItemLocation { latitude, longitude, source: ItemLocationSource::Manual }),
+    _ => None,
+  }
+}
+
+/// The GPS coordinates of an image item, once it has been tagged.
+pub async fn image_exif_location(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<Option<ItemLocation>> {
+  EXIF_LOCATIONS.resolve(data_dir, user_id, &[item_id.to_owned()], parse_image_tag_coordinates).await?;
+  Ok(
+    EXIF_LOCATIONS
+      .with(user_id, |exif_locations| exif_locations.get(item_id))
+      .map(|(latitude, longitude)| ItemLocation { latitude, longitude, source: ItemLocationSource::Exif }),
+  )
+}
+
+/// Forget the GPS coordinates read for an image, after it is tagged again or deleted.
+pub fn forget_image_location(user_id: &str, item_id: &str) {
+  EXIF_LOCATIONS.forget(user_id, item_id);
+}
+
+fn parse_image_tag_coordinates(bytes: &[u8]) -> InfuResult<Option<(f64, f64)>> {
+  Ok(extract_geo_query_coordinates(bytes)?.filter(|(latitude, longitude)| {
+    latitude.is_finite() && latitude.abs() <= 90.0 && longitude.is_finite() && longitude.abs() <= 180.0
+  }))
+}
+
+fn cell_for(latitude: f64, longitude: f64) -> (i32, i32) {
+  (latitude.floor() as i32, (longitude.floor() as i32 + 180).rem_euclid(360) - 180)
+}
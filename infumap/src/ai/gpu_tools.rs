@@ -12,6 +12,7 @@ use tokio::sync::Mutex;
 use crate::config::CONFIG_GPU_TOOLS_URL;
 
 pub const GPU_TOOL_IMAGE_EXTRACT: &str = "image_extract";
+pub const GPU_TOOL_IMAGE_TEXT_EMBED: &str = "image_text_embed";
 pub const GPU_TOOL_PDF_EXTRACT: &str = "pdf_extract";
 pub const GPU_TOOL_PDF_EXTRACT_JOBS: &str = "pdf_extract_jobs";
 pub const GPU_TOOL_PDF_EXTRACT_CAPTION_ONLY: &str = "pdf_extract_caption_only";
@@ -11,19 +11,25 @@ use tokio::sync::Mutex;
 use tokio::task;
 use tokio::time::sleep;
 
+use crate::ai::faces::{
+  FaceCandidate, FaceProcessOutcome, FacesManifestStatus, detect_and_cluster_item_faces, ensure_item_faces_clustered,
+  face_embed_http_client, face_embed_url_from_config, face_hints_suggest_faces, faces_manifest_status,
+  write_skipped_faces_manifest,
+};
 use crate::ai::fragment::sources::{build_image_fragment_artifact, embedding_context_title_for_item};
 use crate::ai::fragment::{clear_item_fragments, item_fragment_artifact_files_exist};
 use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
 use crate::ai::geo::{
-  GeoCandidate, GeoManifestStatus, GeoProcessOutcome, GeoRequestThrottle, geo_manifest_is_complete,
-  geo_manifest_status, geoapify_api_key_from_config, geoapify_max_requests_per_minute_from_config,
-  geoapify_url_from_config, reverse_geocode_candidate_if_needed,
+  GeoCandidate, GeoManifestStatus, GeoProcessOutcome, GeoProvider, GeoRequestThrottle, geo_manifest_is_complete,
+  geo_manifest_status, geo_provider_from_config, geoapify_max_requests_per_minute_from_config,
+  reverse_geocode_candidate_if_needed,
 };
 use crate::ai::gpu_tools::{GPU_TOOL_IMAGE_EXTRACT, gpu_tools_url_from_config, resolve_gpu_tool_url};
 use crate::ai::image_tagging::{
   ImageTagArtifactPolicy, ImageTagArtifactState, LoadedImageTagging, WebImageTagArtifactReadiness,
   image_tagging_artifact_state, image_tagging_manifest_is_successful, load_image_for_tagging,
-  prepare_image_tag_artifacts_for_web_background, process_loaded_image_tagging, should_tag_image_item,
+  prepare_image_tag_artifacts_for_web_background, process_loaded_image_tagging, read_image_tag_face_hints,
+  should_tag_image_item,
 };
 use crate::ai::metrics::{METRIC_AI_IMAGE_PIPELINE_PROCESSED_TOTAL, METRIC_AI_IMAGE_PIPELINE_QUEUE_DEPTH};
 use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
@@ -34,6 +40,7 @@ use crate::storage::object::ObjectStore;
 
 const EMPTY_QUEUE_WAIT_MILLIS: u64 = 1000;
 const FRAGMENT_NOT_READY_WAIT_MILLIS: u64 = 1000;
+const FACE_ENDPOINT_UNAVAILABLE_WAIT_SECS: u64 = 60;
 const STARTUP_RECONCILIATION_PROGRESS_LOG_SECS: u64 = 10;
 const ENABLE_IMAGE_FRAGMENT_AND_INDEX_BACKGROUND_STAGE: bool = true;
 
@@ -70,6 +77,7 @@ enum PipelineStage {
   Source,
   Geo,
   Fragment,
+  Face,
 }
 
 #[derive(Default)]
@@ -77,6 +85,7 @@ struct ImageSemanticPipelineState {
   source: StageQueue,
   geo: StageQueue,
   fragment: StageQueue,
+  face: StageQueue,
 }
 
 #[derive(Default)]
@@ -94,15 +103,20 @@ struct StartupReconciliationSummary {
   geo_unreadable: usize,
   fragment_already_present: usize,
   fragment_unreadable: usize,
+  face_succeeded: usize,
+  face_failed: usize,
+  face_skipped: usize,
+  face_reclustered: usize,
+  face_unreadable: usize,
 }
 
 #[derive(Clone)]
 struct ImageSemanticPipelineConfig {
   data_dir: String,
   gpu_tools_url: Option<String>,
-  geo_api_key: Option<String>,
-  geo_service_url: String,
+  geo_provider: Option<GeoProvider>,
   geo_max_requests_per_minute: u64,
+  face_embed_url: Option<reqwest::Url>,
 }
 
 enum SourceImageReconcileOutcome {
@@ -136,10 +150,11 @@ pub fn init_image_semantic_pipeline_loop(
   }
 
   if pipeline_config.gpu_tools_url.is_none()
-    && pipeline_config.geo_api_key.is_none()
+    && pipeline_config.geo_provider.is_none()
+    && pipeline_config.face_embed_url.is_none()
     && !ENABLE_IMAGE_FRAGMENT_AND_INDEX_BACKGROUND_STAGE
   {
-    debug!("Disabled because image tagging and reverse geo are unconfigured.");
+    debug!("Disabled because image tagging, reverse geo and face detection are unconfigured.");
     return Ok(());
   }
 
@@ -149,10 +164,11 @@ pub fn init_image_semantic_pipeline_loop(
     .map_err(|_| "Image background pipeline loop is already running in this process.".to_owned())?;
 
   info!(
-    "Starting image background pipeline loops (tag_source=on, gpu_tools={}, reverse_geo={}, fragmenting={}, geo_max_requests_per_minute={}).",
+    "Starting image background pipeline loops (tag_source=on, gpu_tools={}, reverse_geo={}, fragmenting={}, faces={}, geo_max_requests_per_minute={}).",
     on_off(pipeline_config.gpu_tools_url.is_some()),
-    on_off(pipeline_config.geo_api_key.is_some()),
+    pipeline_config.geo_provider.as_ref().map(|provider| provider.name()).unwrap_or("off"),
     on_off(ENABLE_IMAGE_FRAGMENT_AND_INDEX_BACKGROUND_STAGE),
+    on_off(pipeline_config.face_embed_url.is_some()),
     pipeline_config.geo_max_requests_per_minute
   );
 
@@ -164,7 +180,7 @@ pub fn init_image_semantic_pipeline_loop(
     run_source_image_loop(source_config, source_db, source_object_store, source_state).await;
   });
 
-  if pipeline_config.geo_api_key.is_some() {
+  if pipeline_config.geo_provider.is_some() {
     let geo_config = pipeline_config.clone();
     let geo_db = db.clone();
     let geo_state = state.clone();
@@ -182,6 +198,16 @@ pub fn init_image_semantic_pipeline_loop(
     });
   }
 
+  if pipeline_config.face_embed_url.is_some() {
+    let face_config = pipeline_config.clone();
+    let face_db = db.clone();
+    let face_object_store = object_store.clone();
+    let face_state = state.clone();
+    let _face_worker = task::spawn(async move {
+      run_face_loop(face_config, face_db, face_object_store, face_state).await;
+    });
+  }
+
   enqueue_all_loaded_images(db, pipeline_config);
   Ok(())
 }
@@ -227,13 +253,12 @@ pub fn dequeue_image_semantic_pipeline_item_if_active(item_id: &str) {
 fn image_semantic_pipeline_config(config: &Config) -> InfuResult<ImageSemanticPipelineConfig> {
   let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
   let gpu_tools_url = gpu_tools_url_from_config(config)?;
-  let geo_api_key = geoapify_api_key_from_config(config)?;
   Ok(ImageSemanticPipelineConfig {
     data_dir,
     gpu_tools_url,
-    geo_api_key,
-    geo_service_url: geoapify_url_from_config(config)?,
+    geo_provider: geo_provider_from_config(config)?,
     geo_max_requests_per_minute: geoapify_max_requests_per_minute_from_config(config)?,
+    face_embed_url: face_embed_url_from_config(config)?,
   })
 }
 
@@ -424,7 +449,10 @@ fn enqueue_source_candidate_downstream_if_needed(
   candidate: ImagePipelineCandidate,
   reason: &str,
 ) {
-  if config.geo_api_key.is_some() {
+  if config.face_embed_url.is_some() {
+    enqueue_candidate_with_log(state, PipelineStage::Face, candidate.clone(), reason);
+  }
+  if config.geo_provider.is_some() {
     enqueue_candidate_with_log(state, PipelineStage::Geo, candidate, reason);
   } else if ENABLE_IMAGE_FRAGMENT_AND_INDEX_BACKGROUND_STAGE {
     enqueue_candidate_with_log(state, PipelineStage::Fragment, candidate, reason);
@@ -436,7 +464,7 @@ async fn run_reverse_geo_loop(
   db: Arc<Mutex<Db>>,
   state: Arc<Mutex<ImageSemanticPipelineState>>,
 ) {
-  let geo_api_key = config.geo_api_key.clone().expect("reverse geo loop requires geo_api_key");
+  let geo_provider = config.geo_provider.clone().expect("reverse geo loop requires a geo provider");
   let geo_client = match reqwest::ClientBuilder::new().timeout(Duration::from_secs(30)).build() {
     Ok(client) => client,
     Err(e) => {
@@ -484,8 +512,7 @@ async fn run_reverse_geo_loop(
     match reverse_geocode_candidate_if_needed(
       &config.data_dir,
       &geo_client,
-      &config.geo_service_url,
-      &geo_api_key,
+      &geo_provider,
       &geo_candidate,
       false,
       &mut geo_cache,
@@ -531,6 +558,120 @@ async fn run_reverse_geo_loop(
   }
 }
 
+/// Faces are detected independently of reverse geo and fragmenting, which do not use them.
+async fn run_face_loop(
+  config: ImageSemanticPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+  state: Arc<Mutex<ImageSemanticPipelineState>>,
+) {
+  let face_embed_url = config.face_embed_url.clone().expect("face loop requires a face embedding URL");
+  let client = match face_embed_http_client() {
+    Ok(client) => client,
+    Err(e) => {
+      error!("{}; face detection will be skipped.", e);
+      return;
+    }
+  };
+
+  loop {
+    let candidate = {
+      let mut state = state.lock().await;
+      pop_candidate(&mut state, PipelineStage::Face)
+    };
+
+    let Some(candidate) = candidate else {
+      sleep(Duration::from_millis(EMPTY_QUEUE_WAIT_MILLIS)).await;
+      continue;
+    };
+
+    wait_for_object_store_upload_quiet_period("image face detection").await;
+    match process_face_candidate(&config, &face_embed_url, &client, db.clone(), object_store.clone(), &candidate).await
+    {
+      Ok(Some(FaceProcessOutcome::EndpointUnavailable(message))) => {
+        record_image_pipeline_processed(PipelineStage::Face, "deferred");
+        info!(
+          "Suspending face detection for {} after the face embedding service failed for image '{}' (user '{}'): {}",
+          format_duration_for_log(Duration::from_secs(FACE_ENDPOINT_UNAVAILABLE_WAIT_SECS)),
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          message
+        );
+        {
+          let mut state = state.lock().await;
+          enqueue_candidate_with_log(&mut state, PipelineStage::Face, candidate, "after face detection deferral");
+        }
+        sleep(Duration::from_secs(FACE_ENDPOINT_UNAVAILABLE_WAIT_SECS)).await;
+      }
+      Ok(Some(FaceProcessOutcome::Succeeded)) => record_image_pipeline_processed(PipelineStage::Face, "success"),
+      Ok(Some(FaceProcessOutcome::Failed)) => record_image_pipeline_processed(PipelineStage::Face, "failed"),
+      Ok(None) => record_image_pipeline_processed(PipelineStage::Face, "skipped"),
+      Err(e) => {
+        record_image_pipeline_processed(PipelineStage::Face, "failed");
+        error!(
+          "Face detection failed for image '{}' (user '{}'): {}",
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          e
+        );
+      }
+    }
+  }
+}
+
+/// None if the image was skipped, either because its faces were already detected or because the
+/// image tagging service estimated that it shows no faces.
+async fn process_face_candidate(
+  config: &ImageSemanticPipelineConfig,
+  face_embed_url: &reqwest::Url,
+  client: &reqwest::Client,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+  candidate: &ImagePipelineCandidate,
+) -> InfuResult<Option<FaceProcessOutcome>> {
+  if !item_still_supported(db.clone(), candidate).await? {
+    return Ok(None);
+  }
+  if faces_manifest_status(&config.data_dir, &candidate.user_id, &candidate.item_id).await?.is_some() {
+    return Ok(None);
+  }
+  let face_candidate = FaceCandidate {
+    user_id: candidate.user_id.clone(),
+    item_id: candidate.item_id.clone(),
+    mime_type: candidate.mime_type.clone(),
+  };
+  let Some(hints) = read_image_tag_face_hints(&config.data_dir, &candidate.user_id, &candidate.item_id).await? else {
+    return Ok(None);
+  };
+  if !face_hints_suggest_faces(&hints) {
+    write_skipped_faces_manifest(
+      &config.data_dir,
+      &face_candidate,
+      face_embed_url,
+      "Image tagging estimated that no faces are visible.",
+    )
+    .await?;
+    return Ok(None);
+  }
+
+  let loaded = load_image_for_tagging(db.clone(), object_store, &candidate.item_id).await?;
+  if !item_still_supported(db.clone(), candidate).await? {
+    return Ok(None);
+  }
+  let object_encryption_key = user_object_encryption_key(&db, &candidate.user_id).await?;
+  let outcome = detect_and_cluster_item_faces(
+    &config.data_dir,
+    &object_encryption_key,
+    client,
+    face_embed_url,
+    &face_candidate,
+    loaded.request_mime_type(),
+    loaded.file_bytes(),
+  )
+  .await?;
+  Ok(Some(outcome))
+}
+
 async fn run_image_fragment_loop(
   config: ImageSemanticPipelineConfig,
   db: Arc<Mutex<Db>>,
@@ -655,13 +796,18 @@ async fn image_fragment_readiness(
     }
   }
 
-  if config.geo_api_key.is_some() && !geo_manifest_is_complete(&config.data_dir, &item.owner_id, &item.id).await? {
+  if config.geo_provider.is_some() && !geo_manifest_is_complete(&config.data_dir, &item.owner_id, &item.id).await? {
     return Ok(ImageFragmentReadiness::Waiting);
   }
 
   Ok(ImageFragmentReadiness::Ready)
 }
 
+async fn user_object_encryption_key(db: &Arc<Mutex<Db>>, user_id: &str) -> InfuResult<String> {
+  let db = db.lock().await;
+  Ok(db.user.get(user_id).ok_or(format!("User '{}' not loaded.", user_id))?.object_encryption_key.clone())
+}
+
 async fn item_still_supported(db: Arc<Mutex<Db>>, candidate: &ImagePipelineCandidate) -> InfuResult<bool> {
   let db = db.lock().await;
   let Ok(item) = db.item.get(&candidate.item_id) else {
@@ -692,8 +838,21 @@ fn enqueue_all_loaded_images(db: Arc<Mutex<Db>>, config: ImageSemanticPipelineCo
     let mut source_candidates = vec![];
     let mut geo_candidates = vec![];
     let mut fragment_candidates = vec![];
+    let mut face_candidates = vec![];
     let mut summary = StartupReconciliationSummary::default();
     for (index, candidate) in candidates.into_iter().enumerate() {
+      match startup_face_stage_needed(&config, &db, &candidate, &mut summary).await {
+        Ok(true) => face_candidates.push(candidate.clone()),
+        Ok(false) => {}
+        Err(e) => {
+          debug!(
+            "Skipping face detection for image '{}' (user '{}') during image background pipeline startup reconciliation: {}",
+            candidate.item_id,
+            user_id_for_log(&candidate.user_id),
+            e
+          );
+        }
+      }
       match startup_stage_for_candidate(&config, &candidate, &mut summary).await {
         Ok(Some(PipelineStage::Source)) => source_candidates.push(candidate),
         Ok(Some(PipelineStage::Geo)) => geo_candidates.push(candidate),
@@ -730,8 +889,10 @@ fn enqueue_all_loaded_images(db: Arc<Mutex<Db>>, config: ImageSemanticPipelineCo
     let source_enqueued_count = enqueue_candidates(&mut state, PipelineStage::Source, source_candidates);
     let geo_enqueued_count = enqueue_candidates(&mut state, PipelineStage::Geo, geo_candidates);
     let fragment_enqueued_count = enqueue_candidates(&mut state, PipelineStage::Fragment, fragment_candidates);
+    let face_candidate_count = face_candidates.len();
+    let face_enqueued_count = enqueue_candidates(&mut state, PipelineStage::Face, face_candidates);
     info!(
-      "Startup reconciliation saw {} supported image item(s), queued tag={} of {}, reverse_geo={} of {}, and fragment={} of {}; image_tags: succeeded={}, failed={}, pending={}, incomplete={}, unsupported_schema={}, unreadable={}; reverse_geo: succeeded={}, failed={}, skipped={}, pending_after_successful_tag={}, unreadable={}; image_fragments: already_present={}, unreadable={}; queues: {}; elapsed {}.",
+      "Startup reconciliation saw {} supported image item(s), queued tag={} of {}, reverse_geo={} of {}, fragment={} of {}, and face={} of {}; image_tags: succeeded={}, failed={}, pending={}, incomplete={}, unsupported_schema={}, unreadable={}; reverse_geo: succeeded={}, failed={}, skipped={}, pending_after_successful_tag={}, unreadable={}; image_fragments: already_present={}, unreadable={}; faces: succeeded={}, failed={}, skipped={}, reclustered={}, unreadable={}; queues: {}; elapsed {}.",
       candidate_count,
       source_enqueued_count,
       source_candidate_count,
@@ -739,6 +900,8 @@ fn enqueue_all_loaded_images(db: Arc<Mutex<Db>>, config: ImageSemanticPipelineCo
       geo_candidate_count,
       fragment_enqueued_count,
       fragment_candidate_count,
+      face_enqueued_count,
+      face_candidate_count,
       summary.tag_succeeded,
       summary.tag_failed,
       summary.tag_pending,
@@ -752,6 +915,11 @@ fn enqueue_all_loaded_images(db: Arc<Mutex<Db>>, config: ImageSemanticPipelineCo
       summary.geo_unreadable,
       summary.fragment_already_present,
       summary.fragment_unreadable,
+      summary.face_succeeded,
+      summary.face_failed,
+      summary.face_skipped,
+      summary.face_reclustered,
+      summary.face_unreadable,
       queue_depth_summary(&state),
       format_duration_for_log(started_at.elapsed())
     );
@@ -802,7 +970,7 @@ async fn startup_stage_for_candidate(
     }
   }
 
-  if config.geo_api_key.is_none() || !tag_succeeded {
+  if config.geo_provider.is_none() || !tag_succeeded {
     return if tag_succeeded { startup_fragment_stage_if_needed(config, candidate, summary).await } else { Ok(None) };
   }
 
@@ -830,6 +998,57 @@ async fn startup_stage_for_candidate(
   }
 }
 
+/// Images are only queued for face detection once they have been tagged. Images still to be tagged
+/// are queued after tagging succeeds.
+async fn startup_face_stage_needed(
+  config: &ImageSemanticPipelineConfig,
+  db: &Arc<Mutex<Db>>,
+  candidate: &ImagePipelineCandidate,
+  summary: &mut StartupReconciliationSummary,
+) -> InfuResult<bool> {
+  if config.face_embed_url.is_none() {
+    return Ok(false);
+  }
+  if !image_tagging_manifest_is_successful(&config.data_dir, &candidate.user_id, &candidate.item_id).await? {
+    return Ok(false);
+  }
+  match faces_manifest_status(&config.data_dir, &candidate.user_id, &candidate.item_id).await {
+    Ok(Some(FacesManifestStatus::Succeeded)) => {
+      summary.face_succeeded += 1;
+      let object_encryption_key = user_object_encryption_key(db, &candidate.user_id).await?;
+      match ensure_item_faces_clustered(
+        &config.data_dir,
+        &candidate.user_id,
+        &object_encryption_key,
+        &candidate.item_id,
+      )
+      .await
+      {
+        Ok(true) => summary.face_reclustered += 1,
+        Ok(false) => {}
+        Err(e) => {
+          summary.face_unreadable += 1;
+          return Err(e);
+        }
+      }
+      Ok(false)
+    }
+    Ok(Some(FacesManifestStatus::Failed)) => {
+      summary.face_failed += 1;
+      Ok(false)
+    }
+    Ok(Some(FacesManifestStatus::Skipped)) => {
+      summary.face_skipped += 1;
+      Ok(false)
+    }
+    Ok(None) => Ok(true),
+    Err(e) => {
+      summary.face_unreadable += 1;
+      Err(e)
+    }
+  }
+}
+
 async fn startup_fragment_stage_if_needed(
   config: &ImageSemanticPipelineConfig,
   candidate: &ImagePipelineCandidate,
@@ -875,7 +1094,8 @@ fn enqueue_live_candidate_for_all_stages_with_log(
 fn remove_candidate_from_all_stages_with_log(state: &mut ImageSemanticPipelineState, item_id: &str) {
   let removed = remove_candidate(state, PipelineStage::Source, item_id)
     + remove_candidate(state, PipelineStage::Geo, item_id)
-    + remove_candidate(state, PipelineStage::Fragment, item_id);
+    + remove_candidate(state, PipelineStage::Fragment, item_id)
+    + remove_candidate(state, PipelineStage::Face, item_id);
   if removed > 0 {
     debug!("Dequeued image '{}' from {} stage queue(s); queues: {}.", item_id, removed, queue_depth_summary(state));
   }
@@ -886,6 +1106,7 @@ fn queue_for_stage_mut(state: &mut ImageSemanticPipelineState, stage: PipelineSt
     PipelineStage::Source => &mut state.source,
     PipelineStage::Geo => &mut state.geo,
     PipelineStage::Fragment => &mut state.fragment,
+    PipelineStage::Face => &mut state.face,
   }
 }
 
@@ -972,13 +1193,20 @@ fn remove_candidate(state: &mut ImageSemanticPipelineState, stage: PipelineStage
 }
 
 fn queue_depth_summary(state: &ImageSemanticPipelineState) -> String {
-  format!("tag={}, geo={}, fragment={}", state.source.queue.len(), state.geo.queue.len(), state.fragment.queue.len())
+  format!(
+    "tag={}, geo={}, fragment={}, face={}",
+    state.source.queue.len(),
+    state.geo.queue.len(),
+    state.fragment.queue.len(),
+    state.face.queue.len()
+  )
 }
 
 fn record_image_pipeline_queue_depths(state: &ImageSemanticPipelineState) {
   METRIC_AI_IMAGE_PIPELINE_QUEUE_DEPTH.with_label_values(&["tag"]).set(state.source.queue.len() as i64);
   METRIC_AI_IMAGE_PIPELINE_QUEUE_DEPTH.with_label_values(&["geo"]).set(state.geo.queue.len() as i64);
   METRIC_AI_IMAGE_PIPELINE_QUEUE_DEPTH.with_label_values(&["fragment"]).set(state.fragment.queue.len() as i64);
+  METRIC_AI_IMAGE_PIPELINE_QUEUE_DEPTH.with_label_values(&["face"]).set(state.face.queue.len() as i64);
 }
 
 fn record_image_pipeline_processed(stage: PipelineStage, outcome: &'static str) {
@@ -1007,6 +1235,7 @@ impl PipelineStage {
       PipelineStage::Source => "source",
       PipelineStage::Geo => "reverse_geo",
       PipelineStage::Fragment => "fragment",
+      PipelineStage::Face => "face",
     }
   }
 
@@ -1015,6 +1244,7 @@ impl PipelineStage {
       PipelineStage::Source => "tag",
       PipelineStage::Geo => "geo",
       PipelineStage::Fragment => "fragment",
+      PipelineStage::Face => "face",
     }
   }
 }
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use infusdk::util::infu::InfuResult;
+use log::debug;
+use tokio::fs;
+
+use crate::ai::artifact_paths::item_text_content_path;
+use crate::ai::image_tagging::image_tagging_manifest_is_successful;
+use crate::ai::user_id_for_log;
+
+/// Values read from the image-tag output of a user's images, by item id. None records an image without
+/// a value, including one that is not tagged yet or whose tagging failed.
+pub trait ImageTagValueStore: Default + Send + 'static {
+  type Value: Send;
+
+  fn contains(&self, item_id: &str) -> bool;
+  fn insert(&mut self, item_id: String, value: Option<Self::Value>);
+  fn remove(&mut self, item_id: &str);
+}
+
+impl<V: Send + 'static> ImageTagValueStore for HashMap<String, Option<V>> {
+  type Value = V;
+
+  fn contains(&self, item_id: &str) -> bool {
+    self.contains_key(item_id)
+  }
+
+  fn insert(&mut self, item_id: String, value: Option<V>) {
+    HashMap::insert(self, item_id, value);
+  }
+
+  fn remove(&mut self, item_id: &str) {
+    HashMap::remove(self, item_id);
+  }
+}
+
+#[derive(Default)]
+struct UserImageTagValues<S> {
+  /// Incremented whenever an item is forgotten, so values read before then are not stored.
+  generation: u64,
+  store: S,
+}
+
+/// Per-user image-tag values, read on first use and kept for the life of the process. Each user has
+/// their own lock, which is only held to look up or store values and never while files are read.
+/// Callers forget an item when it is tagged again or deleted.
+pub struct ImageTagValueCache<S> {
+  /// Named in log messages.
+  purpose: &'static str,
+  users: Mutex<HashMap<String, Arc<Mutex<UserImageTagValues<S>>>>>,
+}
+
+impl<S: ImageTagValueStore> ImageTagValueCache<S> {
+  pub fn new(purpose: &'static str) -> ImageTagValueCache<S> {
+    ImageTagValueCache { purpose, users: Mutex::new(HashMap::new()) }
+  }
+
+  /// Read the values of any of the image items not looked at before. `parse` is given the image-tag
+  /// output of an image with successful tagging.
+  pub async fn resolve(
+    &self,
+    data_dir: &str,
+    user_id: &str,
+    image_item_ids: &[String],
+    parse: fn(&[u8]) -> InfuResult<Option<S::Value>>,
+  ) -> InfuResult<()> {
+    let user = self.user(user_id);
+    let (generation, unresolved_item_ids) = {
+      let user = user.lock().unwrap();
+      let unresolved_item_ids =
+        image_item_ids.iter().filter(|item_id| !user.store.contains(item_id)).cloned().collect::<Vec<_>>();
+      (user.generation, unresolved_item_ids)
+    };
+    if unresolved_item_ids.is_empty() {
+      return Ok(());
+    }
+
+    let mut values = Vec::with_capacity(unresolved_item_ids.len());
+    for item_id in unresolved_item_ids {
+      let value = if image_tagging_manifest_is_successful(data_dir, user_id, &item_id).await? {
+        self.read_value(data_dir, user_id, &item_id, parse).await
+      } else {
+        None
+      };
+      values.push((item_id, value));
+    }
+
+    let mut user = user.lock().unwrap();
+    if user.generation != generation {
+      // An item was forgotten while reading. Its value may be stale, so all are read again next time.
+      return Ok(());
+    }
+    let newly_resolved = values.len();
+    for (item_id, value) in values {
+      user.store.insert(item_id, value);
+    }
+    debug!("Read {} of {} image(s) for user '{}'.", self.purpose, newly_resolved, user_id_for_log(user_id));
+    Ok(())
+  }
+
+  pub fn with<R>(&self, user_id: &str, f: impl FnOnce(&S) -> R) -> R {
+    f(&self.user(user_id).lock().unwrap().store)
+  }
+
+  pub fn forget(&self, user_id: &str, item_id: &str) {
+    let Some(user) = self.users.lock().unwrap().get(user_id).cloned() else {
+      return;
+    };
+    let mut user = user.lock().unwrap();
+    user.generation += 1;
+    user.store.remove(item_id);
+  }
+
+  fn user(&self, user_id: &str) -> Arc<Mutex<UserImageTagValues<S>>> {
+    self.users.lock().unwrap().entry(user_id.to_owned()).or_default().clone()
+  }
+
+  async fn read_value(
+    &self,
+    data_dir: &str,
+    user_id: &str,
+    item_id: &str,
+    parse: fn(&[u8]) -> InfuResult<Option<S::Value>>,
+  ) -> Option<S::Value> {
+    let path = item_text_content_path(data_dir, user_id, item_id).ok()?;
+    let bytes = match fs::read(&path).await {
+      Ok(bytes) => bytes,
+      Err(e) => {
+        debug!("Could not read image tag output '{}' for {}: {}", path.display(), self.purpose, e);
+        return None;
+      }
+    };
+    match parse(&bytes) {
+      Ok(value) => value,
+      Err(e) => {
+        debug!("Ignoring image tag output '{}' for {}: {}", path.display(), self.purpose, e);
+        None
+      }
+    }
+  }
+}
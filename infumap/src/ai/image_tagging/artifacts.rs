@@ -37,6 +37,7 @@ use super::{ImageCandidate, should_tag_image_item};
 const MANIFEST_SCHEMA_VERSION: u32 = 1;
 const JSON_CONTENT_MIME_TYPE: &str = "application/json";
 pub(super) const IMAGE_TAG_EXTRACTION_MODE_CAPTION_FALLBACK: &str = "caption_fallback";
+const UNKNOWN_IMAGE_EMBEDDING_MODEL: &str = "unknown";
 
 #[derive(Clone)]
 pub struct FailedImageTagInfo {
@@ -82,6 +83,8 @@ pub(super) struct ImageTagArtifact {
   pub(super) image_metadata: Option<ImageMetadata>,
   image_embedding: Vec<f32>,
   #[serde(skip_serializing_if = "Option::is_none")]
+  image_embedding_model_id: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
   extraction_mode: Option<String>,
   #[serde(skip)]
   model_id: Option<String>,
@@ -110,6 +113,7 @@ impl ImageTagArtifact {
       ocr_text: take_string_list(&mut map, "ocr_text"),
       image_metadata: None,
       image_embedding: take_f32_list(&mut map, "image_embedding"),
+      image_embedding_model_id: take_optional_string(&mut map, "image_embedding_model_id"),
       extraction_mode: take_optional_string(&mut map, "extraction_mode"),
       model_id: take_optional_string(&mut map, "model_id"),
       backend: take_optional_string(&mut map, "backend"),
@@ -319,6 +323,73 @@ async fn image_tagging_manifest_check_result(
   })
 }
 
+/// The version of the image embedding stored for an item, which changes whenever the item is
+/// re-tagged. None if the item has not been tagged successfully.
+pub async fn image_tag_embedding_version(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<Option<i64>> {
+  let manifest_path = item_text_manifest_path(data_dir, user_id, item_id)?;
+  if !path_exists(&manifest_path).await {
+    return Ok(None);
+  }
+  let Ok(manifest) = serde_json::from_slice::<ImageTagManifest>(&fs::read(&manifest_path).await?) else {
+    return Ok(None);
+  };
+  if manifest.schema_version != MANIFEST_SCHEMA_VERSION || manifest.status != "succeeded" {
+    return Ok(None);
+  }
+  Ok(Some(manifest.extractor.tagged_at_unix_secs))
+}
+
+/// The image embedding model and embedding stored by the tagging service for an item, if any.
+/// Artifacts written before the model was recorded are attributed to an unknown model.
+pub async fn read_image_tag_embedding(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+) -> InfuResult<Option<(String, Vec<f32>)>> {
+  let text_path = item_text_content_path(data_dir, user_id, item_id)?;
+  if !path_exists(&text_path).await {
+    return Ok(None);
+  }
+  let Ok(value) = serde_json::from_slice::<Value>(&fs::read(&text_path).await?) else {
+    return Ok(None);
+  };
+  let artifact = ImageTagArtifact::from_value(value);
+  if artifact.image_embedding.is_empty() {
+    return Ok(None);
+  }
+  let model = artifact.image_embedding_model_id.unwrap_or_else(|| UNKNOWN_IMAGE_EMBEDDING_MODEL.to_owned());
+  Ok(Some((model, artifact.image_embedding)))
+}
+
+/// What the image tagging service estimated about faces in an image, used to decide whether the
+/// image is worth sending to the face embedding service.
+#[derive(Clone, Debug)]
+pub struct ImageTagFaceHints {
+  pub face_recognition_candidate_confidence: f64,
+  /// One of "0", "1", "2" or "3+", if the tagging service estimated it.
+  pub visible_face_count_estimate: Option<String>,
+}
+
+/// The face hints stored by the tagging service for an item. None if there is no tag output.
+pub async fn read_image_tag_face_hints(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+) -> InfuResult<Option<ImageTagFaceHints>> {
+  let text_path = item_text_content_path(data_dir, user_id, item_id)?;
+  if !path_exists(&text_path).await {
+    return Ok(None);
+  }
+  let Ok(value) = serde_json::from_slice::<Value>(&fs::read(&text_path).await?) else {
+    return Ok(None);
+  };
+  let artifact = ImageTagArtifact::from_value(value);
+  Ok(Some(ImageTagFaceHints {
+    face_recognition_candidate_confidence: artifact.face_recognition_candidate_confidence,
+    visible_face_count_estimate: artifact.visible_face_count_estimate,
+  }))
+}
+
 pub async fn delete_item_image_tag_dir(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<()> {
   clear_item_image_tag_dir(data_dir, user_id, item_id).await
 }
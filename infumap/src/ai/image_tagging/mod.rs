@@ -24,10 +24,13 @@ use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tokio::time;
 
+use crate::ai::geo_index::forget_image_location;
+use crate::ai::timeline::forget_image_capture_time;
 use crate::ai::user_id_for_log;
 use crate::storage::db::Db;
-use crate::storage::object::{self as storage_object, ObjectStore};
+use crate::storage::object::ObjectStore;
 use crate::util::image::extract_image_metadata;
+use crate::util::image_rendition::{IMAGE_RENDITION_MIME_TYPE, get_image_item_bytes, needs_image_rendition};
 use crate::util::retry::endpoint_retry_delay;
 
 mod artifacts;
@@ -36,6 +39,8 @@ pub use artifacts::ImageTagArtifactState;
 #[allow(unused_imports)]
 pub use artifacts::{FailedImageTagInfo, ImageTagManifestStatus, image_tagging_manifest_status};
 pub use artifacts::{delete_item_image_tag_dir, item_needs_image_tagging, list_failed_images};
+pub use artifacts::{ImageTagFaceHints, read_image_tag_face_hints};
+pub use artifacts::{image_tag_embedding_version, read_image_tag_embedding};
 pub use artifacts::{image_tagging_artifact_state, image_tagging_manifest_is_successful};
 
 use self::artifacts::{
@@ -161,6 +166,17 @@ pub(crate) struct LoadedImageTagging {
   file_bytes: Vec<u8>,
 }
 
+impl LoadedImageTagging {
+  /// The bytes sent to the tagging service, which are the rendition for images that need one.
+  pub(crate) fn file_bytes(&self) -> &[u8] {
+    &self.file_bytes
+  }
+
+  pub(crate) fn request_mime_type(&self) -> &str {
+    request_mime_type(&self.candidate)
+  }
+}
+
 enum TagOutcome {
   Success(ImageTagArtifact, Option<u64>),
   DocumentFailed(String),
@@ -178,7 +194,7 @@ pub fn is_supported_image_tagging_mime_type(mime_type: Option<&str>) -> bool {
   let Some(mime_type) = mime_type else {
     return false;
   };
-  SUPPORTED_IMAGE_MIME_TYPES.contains(&mime_type)
+  SUPPORTED_IMAGE_MIME_TYPES.contains(&mime_type) || needs_image_rendition(mime_type)
 }
 
 pub fn should_tag_image_item(item: &Item) -> bool {
@@ -221,7 +237,7 @@ pub(crate) async fn load_image_for_tagging(
   object_store: Arc<ObjectStore>,
   item_id: &str,
 ) -> InfuResult<LoadedImageTagging> {
-  let (candidate, object_encryption_key) = {
+  let (candidate, object_encryption_key, data_dir) = {
     let db = db.lock().await;
     let id = item_id.to_string();
     let item = db.item.get(&id).map_err(|e| e.to_string())?;
@@ -230,7 +246,7 @@ pub(crate) async fn load_image_for_tagging(
     };
     let key =
       db.user.get(&item.owner_id).ok_or(format!("User '{}' not loaded.", item.owner_id))?.object_encryption_key.clone();
-    (candidate, key)
+    (candidate, key, db.item.data_dir().to_owned())
   };
   debug!(
     "Starting object read/decrypt for image '{}' (user {}).",
@@ -238,10 +254,12 @@ pub(crate) async fn load_image_for_tagging(
     user_id_for_log(&candidate.user_id)
   );
   let object_read_started_at = Instant::now();
-  let file_bytes = storage_object::get(
+  let file_bytes = get_image_item_bytes(
+    &data_dir,
     object_store.clone(),
-    candidate.user_id.clone(),
-    candidate.item_id.clone(),
+    &candidate.user_id,
+    &candidate.item_id,
+    &candidate.mime_type,
     &object_encryption_key,
   )
   .await;
@@ -358,6 +376,8 @@ async fn process_image_tagging_for_candidate_and_bytes(
         return Ok(());
       }
       write_success_artifacts(data_dir, image_tagging_url, &candidate, &tag_data, duration_ms).await?;
+      forget_image_location(&candidate.user_id, &candidate.item_id);
+      forget_image_capture_time(&candidate.user_id, &candidate.item_id);
       debug!(
         "Finished image tagging for image '{}' (user {}) in {}.",
         candidate.item_id,
@@ -516,7 +536,8 @@ async fn request_image_tagging_with_retries(
 
   loop {
     let outcome =
-      request_image_tagging(client, image_tagging_url, &candidate.mime_type, file_bytes.to_vec(), request_mode).await;
+      request_image_tagging(client, image_tagging_url, request_mime_type(candidate), file_bytes.to_vec(), request_mode)
+        .await;
     match outcome {
       TagOutcome::ResponseFormatFailed(message) => {
         if response_format_attempt >= MAX_RESPONSE_FORMAT_RETRY_ATTEMPTS {
@@ -617,7 +638,13 @@ async fn request_image_tagging_once(
   file_bytes: &[u8],
   request_mode: ImageTagRequestMode,
 ) -> TagOutcome {
-  request_image_tagging(client, image_tagging_url, &candidate.mime_type, file_bytes.to_vec(), request_mode).await
+  request_image_tagging(client, image_tagging_url, request_mime_type(candidate), file_bytes.to_vec(), request_mode)
+    .await
+}
+
+/// Images that need a rendition are sent to the tagging service as their rendition.
+fn request_mime_type(candidate: &ImageCandidate) -> &str {
+  if needs_image_rendition(&candidate.mime_type) { IMAGE_RENDITION_MIME_TYPE } else { &candidate.mime_type }
 }
 
 async fn candidate_still_current(db: Arc<Mutex<Db>>, candidate: &ImageCandidate) -> InfuResult<bool> {
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use infusdk::util::infu::InfuResult;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::image_tagging::{image_tag_embedding_version, read_image_tag_embedding};
+use crate::ai::text_embedding::validate_text_embedding_vector;
+use crate::ai::vector_db::{FragmentVectorDbBackend, ImageVector, open_user_image_vector_db};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImageVectorSyncSummary {
+  pub upserted: usize,
+  pub removed: usize,
+  pub unchanged: usize,
+}
+
+#[derive(Serialize)]
+struct ImageTextEmbeddingRequest<'a> {
+  input: Vec<&'a str>,
+  encoding_format: &'static str,
+}
+
+#[derive(Deserialize)]
+struct ImageTextEmbeddingResponse {
+  model: Option<String>,
+  data: Vec<ImageTextEmbeddingResult>,
+}
+
+#[derive(Deserialize)]
+struct ImageTextEmbeddingResult {
+  embedding: Vec<f32>,
+}
+
+/// Bring a user's image vector DB in line with the image embeddings stored by the tagging pipeline.
+/// `image_item_ids` are all of the user's image items; vectors of any other item are removed.
+pub async fn sync_user_image_vectors(
+  data_dir: &str,
+  user_id: &str,
+  image_item_ids: &[String],
+) -> InfuResult<ImageVectorSyncSummary> {
+  let vector_db = open_user_image_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  let mut stored_versions = vector_db.stored_versions().await?;
+  let mut summary = ImageVectorSyncSummary::default();
+
+  for item_id in image_item_ids {
+    let stored_version = stored_versions.remove(item_id);
+    let Some(version) = image_tag_embedding_version(data_dir, user_id, item_id).await? else {
+      if stored_version.is_some() && vector_db.delete_item_vector(item_id).await? {
+        summary.removed += 1;
+      }
+      continue;
+    };
+    if stored_version == Some(version) {
+      summary.unchanged += 1;
+      continue;
+    }
+
+    let embedding = read_image_tag_embedding(data_dir, user_id, item_id).await?.filter(|(_, embedding)| {
+      validate_text_embedding_vector(&format!("Image embedding for item '{}'", item_id), embedding).is_ok()
+    });
+    match embedding {
+      Some((model, embedding)) => {
+        vector_db.upsert_item_vector(&ImageVector { item_id: item_id.clone(), model, version, embedding }).await?;
+        summary.upserted += 1;
+      }
+      None => {
+        if stored_version.is_some() && vector_db.delete_item_vector(item_id).await? {
+          summary.removed += 1;
+        }
+      }
+    }
+  }
+
+  let image_item_ids = image_item_ids.iter().collect::<HashSet<_>>();
+  for item_id in stored_versions.keys().filter(|item_id| !image_item_ids.contains(item_id)) {
+    if vector_db.delete_item_vector(item_id).await? {
+      summary.removed += 1;
+    }
+  }
+
+  Ok(summary)
+}
+
+/// Embed a text query into the image embedding space, using the text encoder paired with the image
+/// encoder of the tagging service. Returns the model name along with the embedding, since only image
+/// vectors of the same model can be compared against it. None if the service's image embedding model
+/// has no text encoder.
+pub async fn embed_image_query_text(
+  client: &reqwest::Client,
+  image_text_embed_url: &Url,
+  text: &str,
+) -> InfuResult<Option<(String, Vec<f32>)>> {
+  let request = ImageTextEmbeddingRequest { input: vec![text], encoding_format: "float" };
+  let response = client
+    .post(image_text_embed_url.clone())
+    .json(&request)
+    .send()
+    .await
+    .map_err(|e| format!("Could not call image text embedding service '{}': {}", image_text_embed_url, e))?;
+  let status = response.status();
+  if status == StatusCode::NOT_IMPLEMENTED {
+    return Ok(None);
+  }
+  if !status.is_success() {
+    let body = response.text().await.unwrap_or_else(|_| String::from("<could not read response body>"));
+    return Err(
+      format!("Image text embedding service '{}' returned {}: {}", image_text_embed_url, status, body).into(),
+    );
+  }
+
+  let response: ImageTextEmbeddingResponse = response.json().await.map_err(|e| {
+    format!("Could not deserialize image text embedding response from '{}': {}", image_text_embed_url, e)
+  })?;
+  let model = response
+    .model
+    .map(|model| model.trim().to_owned())
+    .filter(|model| !model.is_empty())
+    .ok_or(format!("Image text embedding service '{}' did not report its model.", image_text_embed_url))?;
+  let embedding = response
+    .data
+    .into_iter()
+    .next()
+    .map(|result| result.embedding)
+    .ok_or(format!("Image text embedding service '{}' did not return an embedding.", image_text_embed_url))?;
+  validate_text_embedding_vector("Image text query embedding", &embedding)?;
+  Ok(Some((model, embedding)))
+}
@@ -14,13 +14,14 @@ use tokio::task::JoinSet;
 
 use crate::ai::artifact_paths::{item_fragments_manifest_path, item_fragments_path, user_fragments_dir};
 use crate::ai::fragment::is_lexical_search_source_kind;
+use crate::ai::fragment::sources::DocumentFormat;
 use crate::ai::image_tagging::{
   ImageTagArtifactState, image_tagging_artifact_state, is_supported_image_tagging_mime_type,
 };
 use crate::ai::lexical_index::{
-  FragmentLexicalIndexRebuildMetadata, LexicalFragment, document_fragment_lexical_index_temp_dir,
-  open_user_document_fragment_lexical_index, remove_document_fragment_lexical_index_dirs,
-  user_document_fragment_lexical_index_exists,
+  DOCUMENT_FRAGMENT_LEXICAL_SCHEMA_VERSION, FragmentLexicalIndexRebuildMetadata, LexicalFragment,
+  document_fragment_lexical_index_temp_dir, open_user_document_fragment_lexical_index,
+  remove_document_fragment_lexical_index_dirs, user_document_fragment_lexical_index_exists,
 };
 use crate::ai::search_status::{SearchStatusArtifact, write_search_status_artifact};
 use crate::ai::text_embedding::{
@@ -29,10 +30,12 @@ use crate::ai::text_embedding::{
 };
 use crate::ai::text_extraction::{PdfTextArtifactState, pdf_text_artifact_state};
 use crate::ai::user_id_for_log;
+use crate::ai::vector_db::sqlite_vec::SQLITE_VEC_INDEX_SCHEMA_VERSION;
 use crate::ai::vector_db::{
   EmbeddedFragment, FragmentVectorDb, FragmentVectorDbBackend, FragmentVectorDbFragmentKey,
   FragmentVectorDbRebuildMetadata, ensure_user_index_dir, fragment_vector_db_operation_lock, fragment_vector_db_path,
-  fragment_vector_db_temp_path, open_fragment_vector_db, open_user_fragment_vector_db, user_fragment_vector_db_exists,
+  fragment_vector_db_temp_path, open_fragment_vector_db, open_user_fragment_vector_db, open_user_image_vector_db,
+  user_fragment_vector_db_exists,
 };
 use crate::storage::db::Db;
 use crate::storage::db::item_db::ItemAndUserId;
@@ -118,6 +121,8 @@ pub async fn delete_item_fragment_index_entries(data_dir: &str, user_id: &str, i
     let lexical_index = open_user_document_fragment_lexical_index(data_dir, user_id)?;
     deleted += lexical_index.delete_item_fragments(item_id).await?;
   }
+  let image_vector_db = open_user_image_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  image_vector_db.delete_item_vector(item_id).await?;
   Ok(deleted)
 }
 
@@ -488,6 +493,7 @@ async fn document_fragment_lexical_index_current_from_manifest(
     return Ok(None);
   };
   if !status.complete
+    || status.schema_version != DOCUMENT_FRAGMENT_LEXICAL_SCHEMA_VERSION
     || status.expected_fragment_count != summary.lexical_fragment_count
     || status.indexed_fragment_count != summary.lexical_fragment_count
   {
@@ -519,6 +525,7 @@ async fn fragment_vector_index_current_from_manifest(
     return Ok(None);
   };
   if !status.complete
+    || status.schema_version != SQLITE_VEC_INDEX_SCHEMA_VERSION
     || status.expected_fragment_count != summary.vector_fragment_count
     || status.embedded_fragment_count != summary.vector_fragment_count
     || status.embedding_row_count != summary.vector_fragment_count
@@ -590,6 +597,9 @@ async fn load_fragment_records_for_index_plan(
         text: record.text,
         page_start: record.page_start,
         page_end: record.page_end,
+        row_item_id: record.row_item_id,
+        time_start_secs: record.time_start_secs,
+        time_end_secs: record.time_end_secs,
       });
     }
 
@@ -731,6 +741,7 @@ async fn rebuild_user_vector_fragment_index(
     if !path_exists(&temp_path).await
       && let Some(status) = final_rebuild_status.as_ref()
       && status.complete
+      && status.schema_version == SQLITE_VEC_INDEX_SCHEMA_VERSION
       && status.source_digest == source_digest
       && status.expected_fragment_count == fragments.len()
     {
@@ -790,6 +801,8 @@ async fn rebuild_user_vector_fragment_index(
             text: fragment.text.clone(),
             page_start: fragment.page_start,
             page_end: fragment.page_end,
+            time_start_secs: fragment.time_start_secs,
+            time_end_secs: fragment.time_end_secs,
             embedding,
           })
         })
@@ -861,6 +874,8 @@ async fn rebuild_user_vector_fragment_index(
         text: fragment.text.clone(),
         page_start: fragment.page_start,
         page_end: fragment.page_end,
+        time_start_secs: fragment.time_start_secs,
+        time_end_secs: fragment.time_end_secs,
         embedding,
       })
       .collect::<Vec<_>>();
@@ -949,6 +964,7 @@ async fn rebuild_user_fragment_lexical_index(
   if skip_current
     && let Some(status) = final_index.rebuild_status().await?
     && status.complete
+    && status.schema_version == DOCUMENT_FRAGMENT_LEXICAL_SCHEMA_VERSION
     && status.source_digest == source_digest
     && status.expected_fragment_count == fragments.len()
     && status.indexed_fragment_count == fragments.len()
@@ -977,6 +993,9 @@ async fn rebuild_user_fragment_lexical_index(
       text: fragment.text.clone(),
       page_start: fragment.page_start,
       page_end: fragment.page_end,
+      row_item_id: fragment.row_item_id.clone(),
+      time_start_secs: fragment.time_start_secs,
+      time_end_secs: fragment.time_end_secs,
     })
     .collect::<Vec<_>>();
   let metadata = FragmentLexicalIndexRebuildMetadata {
@@ -1202,6 +1221,12 @@ fn fragment_corpus_digest(fragments: &[FragmentRecordForIndex]) -> String {
     hasher.update([0_u8]);
     hasher.update(fragment.page_end.map(|v| v.to_string()).unwrap_or_default().as_bytes());
     hasher.update([0_u8]);
+    hasher.update(fragment.row_item_id.as_deref().unwrap_or_default().as_bytes());
+    hasher.update([0_u8]);
+    hasher.update(fragment.time_start_secs.map(|v| v.to_string()).unwrap_or_default().as_bytes());
+    hasher.update([0_u8]);
+    hasher.update(fragment.time_end_secs.map(|v| v.to_string()).unwrap_or_default().as_bytes());
+    hasher.update([0_u8]);
     hasher.update(fragment.text_sha256.as_bytes());
     hasher.update([0xff_u8]);
   }
@@ -1283,6 +1308,7 @@ fn search_status_candidate_kind(
     PDF_SOURCE_MIME_TYPE => Some(SearchStatusCandidateKind::Pdf),
     MARKDOWN_SOURCE_MIME_TYPE => Some(SearchStatusCandidateKind::Markdown),
     TEXT_SOURCE_MIME_TYPE => Some(SearchStatusCandidateKind::Text),
+    mime_type if DocumentFormat::from_mime_type(mime_type).is_some() => Some(SearchStatusCandidateKind::Document),
     mime_type if semantic_enabled && is_supported_image_tagging_mime_type(Some(mime_type)) => {
       Some(SearchStatusCandidateKind::Image)
     }
@@ -1312,7 +1338,9 @@ async fn classify_unindexed_search_item(
         | ImageTagArtifactState::RetryableFailed => SearchStatusClassification::Pending,
       }
     }
-    SearchStatusCandidateKind::Markdown | SearchStatusCandidateKind::Text => SearchStatusClassification::Pending,
+    SearchStatusCandidateKind::Markdown | SearchStatusCandidateKind::Text | SearchStatusCandidateKind::Document => {
+      SearchStatusClassification::Pending
+    }
   })
 }
 
@@ -1322,6 +1350,7 @@ enum SearchStatusCandidateKind {
   Image,
   Markdown,
   Text,
+  Document,
 }
 
 #[derive(Clone, Copy)]
@@ -1540,6 +1569,9 @@ struct FragmentRecordForIndex {
   text: String,
   page_start: Option<usize>,
   page_end: Option<usize>,
+  row_item_id: Option<String>,
+  time_start_secs: Option<usize>,
+  time_end_secs: Option<usize>,
 }
 
 impl FragmentRecordForIndex {
@@ -1558,6 +1590,12 @@ struct StoredFragmentRecord {
   text: String,
   page_start: Option<usize>,
   page_end: Option<usize>,
+  #[serde(default)]
+  row_item_id: Option<String>,
+  #[serde(default)]
+  time_start_secs: Option<usize>,
+  #[serde(default)]
+  time_end_secs: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -14,7 +14,7 @@ use crate::util::fs::expand_tilde;
 pub const DOCUMENT_FRAGMENT_LEXICAL_INDEX_DIR_NAME: &str = "document_fragments_tantivy";
 pub const DOCUMENT_FRAGMENT_LEXICAL_INDEX_TEMP_DIR_NAME: &str = "document_fragments_tantivy.tmp";
 pub const DOCUMENT_FRAGMENT_LEXICAL_METADATA_FILENAME: &str = "infumap_document_fragment_index.json";
-pub const DOCUMENT_FRAGMENT_LEXICAL_SCHEMA_VERSION: u32 = 1;
+pub const DOCUMENT_FRAGMENT_LEXICAL_SCHEMA_VERSION: u32 = 3;
 pub const ITEM_TITLE_LEXICAL_INDEX_DIR_NAME: &str = "item_titles_tantivy";
 #[allow(dead_code)]
 pub const ITEM_TITLE_LEXICAL_INDEX_TEMP_DIR_NAME: &str = "item_titles_tantivy.tmp";
@@ -27,6 +27,9 @@ const ORDINAL_FIELD: &str = "ordinal";
 const SOURCE_KIND_FIELD: &str = "source_kind";
 const PAGE_START_FIELD: &str = "page_start";
 const PAGE_END_FIELD: &str = "page_end";
+const ROW_ITEM_ID_FIELD: &str = "row_item_id";
+const TIME_START_SECS_FIELD: &str = "time_start_secs";
+const TIME_END_SECS_FIELD: &str = "time_end_secs";
 const TEXT_FIELD: &str = "text";
 const INDEX_WRITER_HEAP_BYTES: usize = 50_000_000;
 const DOCUMENT_FRAGMENT_LEXICAL_INDEX_LABEL: &str = "document fragment lexical index";
@@ -40,6 +43,9 @@ pub struct LexicalFragment {
   pub text: String,
   pub page_start: Option<usize>,
   pub page_end: Option<usize>,
+  pub row_item_id: Option<String>,
+  pub time_start_secs: Option<usize>,
+  pub time_end_secs: Option<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -51,6 +57,9 @@ pub struct FragmentLexicalHit {
   pub text: String,
   pub page_start: Option<usize>,
   pub page_end: Option<usize>,
+  pub row_item_id: Option<String>,
+  pub time_start_secs: Option<usize>,
+  pub time_end_secs: Option<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -85,6 +94,11 @@ struct LexicalFields {
   source_kind: Field,
   page_start: Field,
   page_end: Field,
+  /// Missing from indexes built before table row fragments recorded their rows.
+  row_item_id: Option<Field>,
+  /// Missing from indexes built before transcript fragments recorded their time ranges.
+  time_start_secs: Option<Field>,
+  time_end_secs: Option<Field>,
   text: Field,
 }
 
@@ -476,9 +490,23 @@ fn lexical_schema() -> (Schema, LexicalFields) {
   let source_kind = schema_builder.add_text_field(SOURCE_KIND_FIELD, STRING | STORED);
   let page_start = schema_builder.add_u64_field(PAGE_START_FIELD, STORED);
   let page_end = schema_builder.add_u64_field(PAGE_END_FIELD, STORED);
+  let row_item_id = schema_builder.add_text_field(ROW_ITEM_ID_FIELD, STORED);
+  let time_start_secs = schema_builder.add_u64_field(TIME_START_SECS_FIELD, STORED);
+  let time_end_secs = schema_builder.add_u64_field(TIME_END_SECS_FIELD, STORED);
   let text = schema_builder.add_text_field(TEXT_FIELD, TEXT | STORED);
   let schema = schema_builder.build();
-  (schema, LexicalFields { item_id, ordinal, source_kind, page_start, page_end, text })
+  let fields = LexicalFields {
+    item_id,
+    ordinal,
+    source_kind,
+    page_start,
+    page_end,
+    row_item_id: Some(row_item_id),
+    time_start_secs: Some(time_start_secs),
+    time_end_secs: Some(time_end_secs),
+    text,
+  };
+  (schema, fields)
 }
 
 fn fields_from_schema(schema: &Schema, index_label: &str) -> InfuResult<LexicalFields> {
@@ -494,6 +522,9 @@ fn fields_from_schema(schema: &Schema, index_label: &str) -> InfuResult<LexicalF
     page_end: schema
       .get_field(PAGE_END_FIELD)
       .map_err(|e| format!("{} schema missing page_end: {}", index_label, e))?,
+    row_item_id: schema.get_field(ROW_ITEM_ID_FIELD).ok(),
+    time_start_secs: schema.get_field(TIME_START_SECS_FIELD).ok(),
+    time_end_secs: schema.get_field(TIME_END_SECS_FIELD).ok(),
     text: schema.get_field(TEXT_FIELD).map_err(|e| format!("{} schema missing text: {}", index_label, e))?,
   })
 }
@@ -509,6 +540,15 @@ fn tantivy_document_for_fragment(fields: LexicalFields, fragment: &LexicalFragme
   if let Some(page_end) = fragment.page_end {
     doc.add_u64(fields.page_end, page_end as u64);
   }
+  if let (Some(field), Some(row_item_id)) = (fields.row_item_id, fragment.row_item_id.as_deref()) {
+    doc.add_text(field, row_item_id);
+  }
+  if let (Some(field), Some(time_start_secs)) = (fields.time_start_secs, fragment.time_start_secs) {
+    doc.add_u64(field, time_start_secs as u64);
+  }
+  if let (Some(field), Some(time_end_secs)) = (fields.time_end_secs, fragment.time_end_secs) {
+    doc.add_u64(field, time_end_secs as u64);
+  }
   doc.add_text(fields.text, &fragment.text);
   doc
 }
@@ -527,6 +567,21 @@ fn hit_from_document(
     text: required_text_field(doc, fields.text, TEXT_FIELD, index_label)?.to_owned(),
     page_start: optional_usize_field(doc, fields.page_start, PAGE_START_FIELD, index_label)?,
     page_end: optional_usize_field(doc, fields.page_end, PAGE_END_FIELD, index_label)?,
+    row_item_id: fields
+      .row_item_id
+      .and_then(|field| doc.get_first(field))
+      .and_then(|value| value.as_str())
+      .map(str::to_owned),
+    time_start_secs: fields
+      .time_start_secs
+      .map(|field| optional_usize_field(doc, field, TIME_START_SECS_FIELD, index_label))
+      .transpose()?
+      .flatten(),
+    time_end_secs: fields
+      .time_end_secs
+      .map(|field| optional_usize_field(doc, field, TIME_END_SECS_FIELD, index_label))
+      .transpose()?
+      .flatten(),
   })
 }
 
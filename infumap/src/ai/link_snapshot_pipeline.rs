@@ -0,0 +1,504 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use config::Config;
+use infusdk::item::{Item, ItemType};
+use infusdk::util::infu::InfuResult;
+use log::{debug, error, info};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::Mutex;
+use tokio::task;
+use tokio::time::sleep;
+
+use crate::ai::artifact_paths::{
+  LINK_SNAPSHOT_CONTENT_SUFFIX, ensure_user_text_dir, item_link_snapshot_content_path, item_link_snapshot_manifest_path,
+};
+use crate::ai::fragment::sources::{build_link_snapshot_fragment_artifact, html_main_content_to_markdown};
+use crate::ai::fragment::{
+  FragmentBuildOutcome, clear_item_fragments, item_fragment_artifact_files_exist, item_fragment_artifacts_are_current,
+};
+use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
+use crate::ai::metrics::{METRIC_AI_LINK_SNAPSHOT_PROCESSED_TOTAL, METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH};
+use crate::ai::user_id_for_log;
+use crate::config::{CONFIG_DATA_DIR, CONFIG_ENABLE_LINK_SNAPSHOTS};
+use crate::storage::db::Db;
+use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
+use crate::util::fs::path_exists;
+use crate::web::routes::link_titles::{build_link_page_client, fetch_link_page_html, normalize_link_url};
+
+const EMPTY_QUEUE_WAIT_MILLIS: u64 = 1000;
+const LINK_SNAPSHOT_MANIFEST_SCHEMA_VERSION: u32 = 1;
+const LINK_SNAPSHOT_CONTENT_MIME_TYPE: &str = "text/markdown";
+/// Pages that could not be snapshotted are tried again after this long, in case they were only
+/// temporarily unavailable. A successful snapshot is kept until the note's link changes.
+const LINK_SNAPSHOT_FAILED_RETRY_SECS: i64 = 7 * 24 * 60 * 60;
+
+static LINK_SNAPSHOT_PIPELINE_STATE: OnceCell<Arc<Mutex<LinkSnapshotPipelineState>>> = OnceCell::new();
+
+#[derive(Clone)]
+struct LinkSnapshotPipelineConfig {
+  data_dir: String,
+}
+
+#[derive(Clone)]
+struct LinkSnapshotCandidate {
+  user_id: String,
+  item_id: String,
+}
+
+impl LinkSnapshotCandidate {
+  fn from_item(item: &Item) -> Option<LinkSnapshotCandidate> {
+    link_snapshot_url(item)?;
+    Some(LinkSnapshotCandidate { user_id: item.owner_id.clone(), item_id: item.id.clone() })
+  }
+}
+
+#[derive(Default)]
+struct LinkSnapshotPipelineState {
+  queue: VecDeque<LinkSnapshotCandidate>,
+  queued_item_ids: HashSet<String>,
+}
+
+enum LinkSnapshotReconcileOutcome {
+  Changed(String),
+  Skipped,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LinkSnapshotManifest {
+  schema_version: u32,
+  status: String,
+  url: String,
+  content_mime_type: String,
+  fetched_at_unix_secs: i64,
+  duration_ms: Option<u64>,
+  error: Option<String>,
+}
+
+impl LinkSnapshotManifest {
+  fn is_succeeded(&self) -> bool {
+    self.status == "succeeded"
+  }
+}
+
+pub fn init_link_snapshot_pipeline_loop(config: &Config, db: Arc<Mutex<Db>>) -> InfuResult<()> {
+  if !config.get_bool(CONFIG_ENABLE_LINK_SNAPSHOTS).map_err(|e| e.to_string())? {
+    return Ok(());
+  }
+  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+  let pipeline_config = LinkSnapshotPipelineConfig { data_dir };
+  if LINK_SNAPSHOT_PIPELINE_STATE.get().is_some() {
+    enqueue_all_loaded_link_notes(db, pipeline_config);
+    return Ok(());
+  }
+
+  let state = Arc::new(Mutex::new(LinkSnapshotPipelineState::default()));
+  METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH.set(0);
+  LINK_SNAPSHOT_PIPELINE_STATE
+    .set(state.clone())
+    .map_err(|_| "Link snapshot background pipeline loop is already running in this process.".to_owned())?;
+
+  info!("Starting link snapshot background loop.");
+
+  let worker_config = pipeline_config.clone();
+  let worker_db = db.clone();
+  let worker_state = state.clone();
+  let _worker = task::spawn(async move {
+    run_link_snapshot_loop(worker_config, worker_db, worker_state).await;
+  });
+
+  enqueue_all_loaded_link_notes(db, pipeline_config);
+  Ok(())
+}
+
+/// Queue a note for (re)snapshotting. Notes that no longer have a link are queued too, so a
+/// snapshot of a removed link is cleared.
+pub fn enqueue_link_snapshot_item_if_active(item: &Item) {
+  if item.item_type != ItemType::Note {
+    return;
+  }
+  enqueue_candidate_if_active(LinkSnapshotCandidate { user_id: item.owner_id.clone(), item_id: item.id.clone() });
+}
+
+pub fn dequeue_link_snapshot_item_if_active(item_id: &str) {
+  let Some(state) = LINK_SNAPSHOT_PIPELINE_STATE.get() else {
+    return;
+  };
+  let item_id = item_id.to_owned();
+
+  if let Ok(mut state) = state.try_lock() {
+    remove_candidate(&mut state, &item_id);
+    return;
+  }
+
+  let state = state.clone();
+  let _dequeue = task::spawn(async move {
+    let mut state = state.lock().await;
+    remove_candidate(&mut state, &item_id);
+  });
+}
+
+/// The page a link note points to: its legacy `url`, or a link that spans the whole title (as
+/// created by add-link-note). Notes with links only on part of their text are not snapshotted.
+pub fn link_snapshot_url(item: &Item) -> Option<String> {
+  if item.item_type != ItemType::Note {
+    return None;
+  }
+  if let Some(url) = item.url.as_deref().map(str::trim).filter(|url| !url.is_empty()) {
+    return Some(url.to_owned());
+  }
+  let title_len = item.title.as_deref().unwrap_or("").encode_utf16().count() as i64;
+  item
+    .urls
+    .as_ref()?
+    .iter()
+    .find(|note_url| note_url.start <= 0 && note_url.end >= title_len && !note_url.url.trim().is_empty())
+    .map(|note_url| note_url.url.trim().to_owned())
+}
+
+pub async fn delete_item_link_snapshot_artifacts(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<()> {
+  let manifest_path = item_link_snapshot_manifest_path(data_dir, user_id, item_id)?;
+  let content_path = item_link_snapshot_content_path(data_dir, user_id, item_id)?;
+  if path_exists(&manifest_path).await {
+    fs::remove_file(&manifest_path).await?;
+  }
+  if path_exists(&content_path).await {
+    fs::remove_file(&content_path).await?;
+  }
+  Ok(())
+}
+
+async fn run_link_snapshot_loop(
+  config: LinkSnapshotPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  state: Arc<Mutex<LinkSnapshotPipelineState>>,
+) {
+  loop {
+    let candidate = {
+      let mut state = state.lock().await;
+      pop_candidate(&mut state)
+    };
+
+    let Some(candidate) = candidate else {
+      sleep(Duration::from_millis(EMPTY_QUEUE_WAIT_MILLIS)).await;
+      continue;
+    };
+
+    match reconcile_link_snapshot_item(&config, db.clone(), &candidate).await {
+      Ok(LinkSnapshotReconcileOutcome::Changed(user_id)) => {
+        record_link_snapshot_processed("success");
+        enqueue_fragment_index_rebuild_for_user(&user_id);
+      }
+      Ok(LinkSnapshotReconcileOutcome::Skipped) => {
+        record_link_snapshot_processed("skipped");
+      }
+      Err(e) => {
+        record_link_snapshot_processed("failed");
+        error!(
+          "Link snapshot pipeline failed for note '{}' (user '{}'): {}",
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          e
+        );
+      }
+    }
+  }
+}
+
+async fn reconcile_link_snapshot_item(
+  config: &LinkSnapshotPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  candidate: &LinkSnapshotCandidate,
+) -> InfuResult<LinkSnapshotReconcileOutcome> {
+  let (item_snapshot, object_encryption_key) = {
+    let db = db.lock().await;
+    let item = match db.item.get(&candidate.item_id) {
+      Ok(item) if item.owner_id == candidate.user_id && item.item_type == ItemType::Note => item.clone(),
+      _ => return Ok(LinkSnapshotReconcileOutcome::Skipped),
+    };
+    let object_encryption_key =
+      db.user.get(&item.owner_id).ok_or(format!("User '{}' not loaded.", item.owner_id))?.object_encryption_key.clone();
+    (item, object_encryption_key)
+  };
+
+  let manifest = read_link_snapshot_manifest(&config.data_dir, &item_snapshot).await?;
+  let Some(url) = link_snapshot_url(&item_snapshot) else {
+    if manifest.is_none() {
+      return Ok(LinkSnapshotReconcileOutcome::Skipped);
+    }
+    delete_item_link_snapshot_artifacts(&config.data_dir, &item_snapshot.owner_id, &item_snapshot.id).await?;
+    let outcome = clear_item_fragments(&config.data_dir, &item_snapshot).await?;
+    return Ok(changed_if(outcome, &item_snapshot));
+  };
+
+  if let Some(manifest) = manifest.as_ref().filter(|manifest| manifest.url == url) {
+    if manifest.is_succeeded() {
+      if item_fragment_artifacts_are_current(&config.data_dir, &item_snapshot.owner_id, &item_snapshot.id).await? {
+        return Ok(LinkSnapshotReconcileOutcome::Skipped);
+      }
+      let markdown = read_link_snapshot_content(&config.data_dir, &item_snapshot, &object_encryption_key).await?;
+      let outcome = build_link_snapshot_fragment_artifact(&config.data_dir, &item_snapshot, Some(&markdown)).await?;
+      return Ok(changed_if(outcome, &item_snapshot));
+    }
+    if unix_now_secs()? - manifest.fetched_at_unix_secs < LINK_SNAPSHOT_FAILED_RETRY_SECS {
+      return Ok(LinkSnapshotReconcileOutcome::Skipped);
+    }
+  }
+
+  let fetch_started = Instant::now();
+  let markdown = match fetch_link_snapshot_markdown(&url).await {
+    Ok(markdown) => markdown,
+    Err(e) => {
+      let duration_ms = fetch_started.elapsed().as_millis() as u64;
+      write_failed_link_snapshot_manifest(&config.data_dir, &item_snapshot, &url, duration_ms, &e.to_string()).await?;
+      let outcome = clear_item_fragments(&config.data_dir, &item_snapshot).await?;
+      return Ok(changed_if(outcome, &item_snapshot));
+    }
+  };
+  let duration_ms = fetch_started.elapsed().as_millis() as u64;
+  write_success_link_snapshot_artifacts(
+    &config.data_dir,
+    &item_snapshot,
+    &url,
+    duration_ms,
+    &markdown,
+    &object_encryption_key,
+  )
+  .await?;
+  let outcome = build_link_snapshot_fragment_artifact(&config.data_dir, &item_snapshot, Some(&markdown)).await?;
+  debug!(
+    "Link snapshot pipeline wrote {} fragment(s) for note '{}' (user {}).",
+    outcome.fragment_count,
+    item_snapshot.id,
+    user_id_for_log(&item_snapshot.owner_id)
+  );
+  Ok(changed_if(outcome, &item_snapshot))
+}
+
+fn changed_if(outcome: FragmentBuildOutcome, item: &Item) -> LinkSnapshotReconcileOutcome {
+  if outcome.wrote_fragments || outcome.cleared_existing_fragments {
+    LinkSnapshotReconcileOutcome::Changed(item.owner_id.clone())
+  } else {
+    LinkSnapshotReconcileOutcome::Skipped
+  }
+}
+
+async fn fetch_link_snapshot_markdown(url: &str) -> InfuResult<String> {
+  let url = normalize_link_url(url)?;
+  let client = build_link_page_client()?;
+  let (_, html) =
+    fetch_link_page_html(&client, &url).await?.ok_or("Page could not be fetched, is not allowed or is not HTML.")?;
+  let markdown = task::spawn_blocking(move || html_main_content_to_markdown(&html))
+    .await
+    .map_err(|e| format!("Link snapshot conversion task failed: {}", e))?;
+  markdown.ok_or("Page has no readable text.".into())
+}
+
+async fn read_link_snapshot_manifest(data_dir: &str, item: &Item) -> InfuResult<Option<LinkSnapshotManifest>> {
+  let manifest_path = item_link_snapshot_manifest_path(data_dir, &item.owner_id, &item.id)?;
+  if !path_exists(&manifest_path).await {
+    return Ok(None);
+  }
+  let bytes = fs::read(&manifest_path).await?;
+  Ok(
+    serde_json::from_slice::<LinkSnapshotManifest>(&bytes)
+      .ok()
+      .filter(|manifest| manifest.schema_version == LINK_SNAPSHOT_MANIFEST_SCHEMA_VERSION),
+  )
+}
+
+async fn read_link_snapshot_content(data_dir: &str, item: &Item, object_encryption_key: &str) -> InfuResult<String> {
+  let content_path = item_link_snapshot_content_path(data_dir, &item.owner_id, &item.id)?;
+  let encrypted = fs::read(&content_path)
+    .await
+    .map_err(|e| format!("Could not read link snapshot '{}': {}", content_path.display(), e))?;
+  let decrypted = decrypt_file_data(object_encryption_key, &encrypted, &link_snapshot_content_filename(item))?;
+  String::from_utf8(decrypted).map_err(|e| format!("Link snapshot for '{}' is not valid UTF-8: {}", item.id, e).into())
+}
+
+async fn write_success_link_snapshot_artifacts(
+  data_dir: &str,
+  item: &Item,
+  url: &str,
+  duration_ms: u64,
+  markdown: &str,
+  object_encryption_key: &str,
+) -> InfuResult<()> {
+  ensure_user_text_dir(data_dir, &item.owner_id).await?;
+  let content_path = item_link_snapshot_content_path(data_dir, &item.owner_id, &item.id)?;
+  let manifest_path = item_link_snapshot_manifest_path(data_dir, &item.owner_id, &item.id)?;
+  let encrypted = encrypt_file_data(object_encryption_key, markdown.as_bytes(), &link_snapshot_content_filename(item))?;
+  fs::write(&content_path, encrypted).await?;
+  let manifest = LinkSnapshotManifest {
+    schema_version: LINK_SNAPSHOT_MANIFEST_SCHEMA_VERSION,
+    status: "succeeded".to_owned(),
+    url: url.to_owned(),
+    content_mime_type: LINK_SNAPSHOT_CONTENT_MIME_TYPE.to_owned(),
+    fetched_at_unix_secs: unix_now_secs()?,
+    duration_ms: Some(duration_ms),
+    error: None,
+  };
+  fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+  debug!("Snapshotted link '{}' for note '{}' (user {}).", url, item.id, user_id_for_log(&item.owner_id));
+  Ok(())
+}
+
+async fn write_failed_link_snapshot_manifest(
+  data_dir: &str,
+  item: &Item,
+  url: &str,
+  duration_ms: u64,
+  error_message: &str,
+) -> InfuResult<()> {
+  ensure_user_text_dir(data_dir, &item.owner_id).await?;
+  let content_path = item_link_snapshot_content_path(data_dir, &item.owner_id, &item.id)?;
+  let manifest_path = item_link_snapshot_manifest_path(data_dir, &item.owner_id, &item.id)?;
+  if path_exists(&content_path).await {
+    fs::remove_file(&content_path).await?;
+  }
+  let manifest = LinkSnapshotManifest {
+    schema_version: LINK_SNAPSHOT_MANIFEST_SCHEMA_VERSION,
+    status: "failed".to_owned(),
+    url: url.to_owned(),
+    content_mime_type: LINK_SNAPSHOT_CONTENT_MIME_TYPE.to_owned(),
+    fetched_at_unix_secs: unix_now_secs()?,
+    duration_ms: Some(duration_ms),
+    error: Some(error_message.to_owned()),
+  };
+  fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?).await?;
+  debug!(
+    "Link snapshot failed for '{}' on note '{}' (user {}): {}",
+    url,
+    item.id,
+    user_id_for_log(&item.owner_id),
+    error_message
+  );
+  Ok(())
+}
+
+/// Bound into the encrypted snapshot (as associated data) so it cannot be swapped between items.
+fn link_snapshot_content_filename(item: &Item) -> String {
+  format!("{}{}", item.id, LINK_SNAPSHOT_CONTENT_SUFFIX)
+}
+
+fn enqueue_all_loaded_link_notes(db: Arc<Mutex<Db>>, config: LinkSnapshotPipelineConfig) {
+  let Some(state) = LINK_SNAPSHOT_PIPELINE_STATE.get() else {
+    return;
+  };
+  let state = state.clone();
+  let _enqueue_task = task::spawn(async move {
+    populate_initial_link_snapshot_queue(&config, db, state).await;
+  });
+}
+
+async fn populate_initial_link_snapshot_queue(
+  config: &LinkSnapshotPipelineConfig,
+  db: Arc<Mutex<Db>>,
+  state: Arc<Mutex<LinkSnapshotPipelineState>>,
+) {
+  let candidates = {
+    let db = db.lock().await;
+    db.item
+      .all_loaded_items()
+      .into_iter()
+      .filter_map(|item_key| db.item.get(&item_key.item_id).ok())
+      .filter_map(LinkSnapshotCandidate::from_item)
+      .collect::<Vec<_>>()
+  };
+
+  let total_candidates = candidates.len();
+  let mut already_fragmented = 0usize;
+  let mut skipped_errors = 0usize;
+  let mut queued_candidates = Vec::new();
+  for candidate in candidates {
+    match item_fragment_artifact_files_exist(&config.data_dir, &candidate.user_id, &candidate.item_id).await {
+      Ok(true) => already_fragmented += 1,
+      Ok(false) => queued_candidates.push(candidate),
+      Err(e) => {
+        skipped_errors += 1;
+        debug!(
+          "Skipping note '{}' (user {}) during link snapshot startup artifact check: {}",
+          candidate.item_id,
+          user_id_for_log(&candidate.user_id),
+          e
+        );
+      }
+    }
+  }
+
+  let queued_candidate_count = queued_candidates.len();
+  let enqueued_count = {
+    let mut state = state.lock().await;
+    let mut enqueued_count = 0usize;
+    for candidate in queued_candidates {
+      if enqueue_candidate(&mut state, candidate) {
+        enqueued_count += 1;
+      }
+    }
+    enqueued_count
+  };
+
+  info!(
+    "Startup link snapshot reconciliation saw {} link note(s), queued {} of {}; fragments: already_present={}; skipped_errors={}.",
+    total_candidates, enqueued_count, queued_candidate_count, already_fragmented, skipped_errors
+  );
+}
+
+fn enqueue_candidate_if_active(candidate: LinkSnapshotCandidate) {
+  let Some(state) = LINK_SNAPSHOT_PIPELINE_STATE.get() else {
+    return;
+  };
+
+  if let Ok(mut state) = state.try_lock() {
+    enqueue_candidate(&mut state, candidate);
+    return;
+  }
+
+  let state = state.clone();
+  let _enqueue = task::spawn(async move {
+    let mut state = state.lock().await;
+    enqueue_candidate(&mut state, candidate);
+  });
+}
+
+fn enqueue_candidate(state: &mut LinkSnapshotPipelineState, candidate: LinkSnapshotCandidate) -> bool {
+  if !state.queued_item_ids.insert(candidate.item_id.clone()) {
+    return false;
+  }
+  state.queue.push_back(candidate);
+  record_link_snapshot_queue_depth(state);
+  true
+}
+
+fn pop_candidate(state: &mut LinkSnapshotPipelineState) -> Option<LinkSnapshotCandidate> {
+  let candidate = state.queue.pop_front()?;
+  state.queued_item_ids.remove(&candidate.item_id);
+  record_link_snapshot_queue_depth(state);
+  Some(candidate)
+}
+
+fn remove_candidate(state: &mut LinkSnapshotPipelineState, item_id: &str) {
+  state.queue.retain(|candidate| candidate.item_id != item_id);
+  state.queued_item_ids.remove(item_id);
+  record_link_snapshot_queue_depth(state);
+}
+
+fn record_link_snapshot_queue_depth(state: &LinkSnapshotPipelineState) {
+  METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH.set(state.queue.len() as i64);
+}
+
+fn record_link_snapshot_processed(outcome: &'static str) {
+  METRIC_AI_LINK_SNAPSHOT_PROCESSED_TOTAL.with_label_values(&[outcome]).inc();
+}
+
+fn unix_now_secs() -> InfuResult<i64> {
+  Ok(
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .map_err(|e| format!("Could not determine current unix time: {}", e))?
+      .as_secs() as i64,
+  )
+}
@@ -0,0 +1,386 @@
+use async_trait::async_trait;
+use infusdk::util::infu::InfuResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::ai::chat_history::ChatTranscript;
+
+use super::{
+  LLM_MAX_STREAMED_RESPONSE_PARTS, LlmChatMessage, LlmModelSettings, LlmProvider, LlmToolCall, LlmToolCallFunction,
+  LlmToolSpec, SseEventReader, default_llm_tool_call_type, error_chain_for_log, llm_endpoint_url, llm_http_client,
+  llm_streaming_http_client, reqwest_error_for_log, truncate_for_error,
+};
+
+const ANTHROPIC_MESSAGES_PATH: &str = "/v1/messages";
+const ANTHROPIC_DEFAULT_AUTH_HEADER: &str = "x-api-key";
+const ANTHROPIC_VERSION_HEADER: &str = "anthropic-version";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const ANTHROPIC_DEFAULT_MAX_OUTPUT_TOKENS: u64 = 4096;
+
+#[derive(Serialize)]
+struct AnthropicMessagesRequest<'a> {
+  model: &'a str,
+  max_tokens: u64,
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  stream: bool,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  system: String,
+  messages: Vec<AnthropicMessage>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  tools: Vec<AnthropicTool<'a>>,
+}
+
+#[derive(Serialize)]
+struct AnthropicMessage {
+  role: &'static str,
+  content: Vec<AnthropicContentBlock>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContentBlock {
+  Text {
+    text: String,
+  },
+  ToolUse {
+    id: String,
+    name: String,
+    input: Value,
+  },
+  ToolResult {
+    tool_use_id: String,
+    content: String,
+  },
+  #[serde(other)]
+  Unsupported,
+}
+
+#[derive(Serialize)]
+struct AnthropicTool<'a> {
+  name: &'a str,
+  description: &'a str,
+  input_schema: &'a Value,
+}
+
+#[derive(Deserialize)]
+struct AnthropicMessagesResponse {
+  #[serde(default)]
+  content: Vec<AnthropicContentBlock>,
+  usage: Option<Value>,
+}
+
+/// Anthropic style /v1/messages. The system prompt is sent separately, tool calls are tool_use
+/// content blocks and tool results are tool_result blocks in a user message.
+pub struct AnthropicChatProvider {
+  model_name: String,
+  upstream_model: String,
+  url: reqwest::Url,
+  auth_header: Option<(String, String)>,
+  max_tokens: u64,
+  client: reqwest::Client,
+  stream_client: reqwest::Client,
+}
+
+impl AnthropicChatProvider {
+  fn messages_request<'a>(
+    &'a self,
+    messages: &[LlmChatMessage],
+    tools: &'a [LlmToolSpec],
+    stream: bool,
+  ) -> AnthropicMessagesRequest<'a> {
+    let (system, messages) = anthropic_messages(messages);
+    AnthropicMessagesRequest {
+      model: &self.upstream_model,
+      max_tokens: self.max_tokens,
+      stream,
+      system,
+      messages,
+      tools: tools
+        .iter()
+        .map(|tool| AnthropicTool {
+          name: &tool.function.name,
+          description: &tool.function.description,
+          input_schema: &tool.function.parameters,
+        })
+        .collect(),
+    }
+  }
+
+  pub fn new(model_name: &str, settings: &LlmModelSettings) -> InfuResult<AnthropicChatProvider> {
+    Ok(AnthropicChatProvider {
+      model_name: model_name.to_owned(),
+      upstream_model: settings.model.clone().unwrap_or_else(|| model_name.to_owned()),
+      url: llm_endpoint_url(model_name, &settings.url, ANTHROPIC_MESSAGES_PATH)?,
+      auth_header: settings.auth_header_value(ANTHROPIC_DEFAULT_AUTH_HEADER),
+      max_tokens: settings.max_output_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_OUTPUT_TOKENS),
+      client: llm_http_client(model_name, settings)?,
+      stream_client: llm_streaming_http_client(model_name, settings)?,
+    })
+  }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicChatProvider {
+  async fn chat_completion(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+  ) -> InfuResult<LlmChatMessage> {
+    let payload = self.messages_request(messages, tools, false);
+    transcript.append_json_section(&format!("LLM REQUEST {} ({})", llm_turn, self.model_name), &payload).await;
+
+    let mut request =
+      self.client.post(self.url.clone()).header(ANTHROPIC_VERSION_HEADER, ANTHROPIC_VERSION).json(&payload);
+    if let Some((header, value)) = self.auth_header.as_ref() {
+      request = request.header(header.as_str(), value.as_str());
+    }
+    let response = request.send().await.map_err(|e| {
+      format!(
+        "Could not send chat request to model '{}' at '{}': {}",
+        self.model_name,
+        self.url,
+        reqwest_error_for_log(&e)
+      )
+    })?;
+
+    let status = response.status();
+    let body = response.text().await.map_err(|e| {
+      format!("Could not read chat response body from model '{}': {}", self.model_name, reqwest_error_for_log(&e))
+    })?;
+    transcript.append_body_section(&format!("LLM RESPONSE {}", llm_turn), &body).await;
+    if !status.is_success() {
+      return Err(
+        format!(
+          "Chat endpoint '{}' for model '{}' returned {}: {}",
+          self.url,
+          self.model_name,
+          status,
+          truncate_for_error(&body, 1000)
+        )
+        .into(),
+      );
+    }
+
+    let parsed: AnthropicMessagesResponse = serde_json::from_str(&body).map_err(|e| {
+      format!("Could not parse chat response from model '{}': {}", self.model_name, error_chain_for_log(&e))
+    })?;
+    if let Some(usage) = parsed.usage.as_ref() {
+      transcript.append_json_section(&format!("LLM RESPONSE USAGE {}", llm_turn), usage).await;
+    }
+    Ok(llm_message_from_anthropic_content(parsed.content))
+  }
+
+  async fn chat_completion_streaming(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+    deltas: mpsc::UnboundedSender<String>,
+  ) -> InfuResult<LlmChatMessage> {
+    let payload = self.messages_request(messages, tools, true);
+    transcript.append_json_section(&format!("LLM REQUEST {} ({})", llm_turn, self.model_name), &payload).await;
+
+    let mut request =
+      self.stream_client.post(self.url.clone()).header(ANTHROPIC_VERSION_HEADER, ANTHROPIC_VERSION).json(&payload);
+    if let Some((header, value)) = self.auth_header.as_ref() {
+      request = request.header(header.as_str(), value.as_str());
+    }
+    let mut response = request.send().await.map_err(|e| {
+      format!(
+        "Could not send chat request to model '{}' at '{}': {}",
+        self.model_name,
+        self.url,
+        reqwest_error_for_log(&e)
+      )
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+      let body = response.text().await.unwrap_or_default();
+      transcript.append_body_section(&format!("LLM RESPONSE {}", llm_turn), &body).await;
+      return Err(
+        format!(
+          "Chat endpoint '{}' for model '{}' returned {}: {}",
+          self.url,
+          self.model_name,
+          status,
+          truncate_for_error(&body, 1000)
+        )
+        .into(),
+      );
+    }
+
+    // Blocks arrive as content_block_start followed by deltas: text_delta for text and
+    // input_json_delta fragments that only form valid JSON once the block is complete.
+    let mut reader = SseEventReader::default();
+    let mut blocks: Vec<AnthropicContentBlock> = Vec::new();
+    let mut tool_inputs: Vec<String> = Vec::new();
+    let mut usage = serde_json::Map::new();
+    'stream: while let Some(chunk) = response.chunk().await.map_err(|e| {
+      format!("Could not read chat response stream from model '{}': {}", self.model_name, reqwest_error_for_log(&e))
+    })? {
+      for event in reader.push(&chunk) {
+        if event.data.is_empty() {
+          continue;
+        }
+        let data: Value = serde_json::from_str(&event.data).map_err(|e| {
+          format!("Could not parse chat response event from model '{}': {}", self.model_name, error_chain_for_log(&e))
+        })?;
+        match data.get("type").and_then(Value::as_str).or(event.event.as_deref()).unwrap_or("") {
+          "message_start" => {
+            if let Some(Value::Object(start_usage)) = data.pointer("/message/usage") {
+              usage.extend(start_usage.clone());
+            }
+          }
+          "content_block_start" => {
+            let index = data.get("index").and_then(Value::as_u64).unwrap_or(blocks.len() as u64);
+            if index >= LLM_MAX_STREAMED_RESPONSE_PARTS as u64 {
+              return Err(
+                format!(
+                  "Chat model '{}' streamed content block index {}, more than the {} blocks allowed in one response.",
+                  self.model_name, index, LLM_MAX_STREAMED_RESPONSE_PARTS
+                )
+                .into(),
+              );
+            }
+            let index = index as usize;
+            let block = data
+              .get("content_block")
+              .cloned()
+              .and_then(|block| serde_json::from_value::<AnthropicContentBlock>(block).ok())
+              .unwrap_or(AnthropicContentBlock::Unsupported);
+            while blocks.len() <= index {
+              blocks.push(AnthropicContentBlock::Unsupported);
+              tool_inputs.push(String::new());
+            }
+            blocks[index] = block;
+          }
+          "content_block_delta" => {
+            let index = data.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+            let Some(delta) = data.get("delta") else {
+              continue;
+            };
+            match (blocks.get_mut(index), delta.get("type").and_then(Value::as_str)) {
+              (Some(AnthropicContentBlock::Text { text }), Some("text_delta")) => {
+                if let Some(fragment) = delta.get("text").and_then(Value::as_str).filter(|text| !text.is_empty()) {
+                  text.push_str(fragment);
+                  let _ = deltas.send(fragment.to_owned());
+                }
+              }
+              (Some(AnthropicContentBlock::ToolUse { .. }), Some("input_json_delta")) => {
+                if let Some(fragment) = delta.get("partial_json").and_then(Value::as_str) {
+                  tool_inputs[index].push_str(fragment);
+                }
+              }
+              _ => {}
+            }
+          }
+          "message_delta" => {
+            if let Some(Value::Object(delta_usage)) = data.get("usage") {
+              usage.extend(delta_usage.clone());
+            }
+          }
+          "message_stop" => break 'stream,
+          "error" => {
+            let message = data.pointer("/error/message").and_then(Value::as_str).unwrap_or("unknown error");
+            return Err(format!("Chat model '{}' reported a streaming error: {}", self.model_name, message).into());
+          }
+          _ => {}
+        }
+      }
+    }
+
+    for (block, tool_input) in blocks.iter_mut().zip(tool_inputs) {
+      if let AnthropicContentBlock::ToolUse { input, .. } = block
+        && !tool_input.trim().is_empty()
+      {
+        *input = serde_json::from_str(&tool_input).map_err(|e| {
+          format!("Could not parse tool input from model '{}': {}", self.model_name, error_chain_for_log(&e))
+        })?;
+      }
+    }
+    let message = llm_message_from_anthropic_content(blocks);
+    transcript.append_json_section(&format!("LLM RESPONSE {} (streamed)", llm_turn), &message).await;
+    if !usage.is_empty() {
+      transcript.append_json_section(&format!("LLM RESPONSE USAGE {}", llm_turn), &usage).await;
+    }
+    Ok(message)
+  }
+}
+
+/// Splits out the system prompt and converts the remaining messages to content blocks, merging
+/// consecutive messages with the same role since the API requires user and assistant turns to alternate.
+fn anthropic_messages(messages: &[LlmChatMessage]) -> (String, Vec<AnthropicMessage>) {
+  let mut system_parts = Vec::new();
+  let mut result: Vec<AnthropicMessage> = Vec::new();
+  for message in messages {
+    let content = message.content.clone().unwrap_or_default();
+    let (role, blocks) = match message.role.as_str() {
+      "system" => {
+        system_parts.push(content);
+        continue;
+      }
+      "tool" => (
+        "user",
+        vec![AnthropicContentBlock::ToolResult {
+          tool_use_id: message.tool_call_id.clone().unwrap_or_default(),
+          content,
+        }],
+      ),
+      "assistant" => {
+        let mut blocks = Vec::new();
+        if !content.trim().is_empty() {
+          blocks.push(AnthropicContentBlock::Text { text: content });
+        }
+        for tool_call in message.tool_calls.as_deref().unwrap_or(&[]) {
+          let input = match &tool_call.function.arguments {
+            Value::String(arguments) => serde_json::from_str(arguments).unwrap_or_else(|_| serde_json::json!({})),
+            Value::Null => serde_json::json!({}),
+            arguments => arguments.clone(),
+          };
+          blocks.push(AnthropicContentBlock::ToolUse {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            input,
+          });
+        }
+        ("assistant", blocks)
+      }
+      _ => ("user", vec![AnthropicContentBlock::Text { text: content }]),
+    };
+    if blocks.is_empty() {
+      continue;
+    }
+    match result.last_mut() {
+      Some(last) if last.role == role => last.content.extend(blocks),
+      _ => result.push(AnthropicMessage { role, content: blocks }),
+    }
+  }
+  (system_parts.join("\n\n"), result)
+}
+
+fn llm_message_from_anthropic_content(content: Vec<AnthropicContentBlock>) -> LlmChatMessage {
+  let mut text_parts = Vec::new();
+  let mut tool_calls = Vec::new();
+  for block in content {
+    match block {
+      AnthropicContentBlock::Text { text } => text_parts.push(text),
+      AnthropicContentBlock::ToolUse { id, name, input } => tool_calls.push(LlmToolCall {
+        id,
+        tool_type: default_llm_tool_call_type(),
+        function: LlmToolCallFunction { name, arguments: input },
+      }),
+      AnthropicContentBlock::ToolResult { .. } | AnthropicContentBlock::Unsupported => {}
+    }
+  }
+  let text = text_parts.join("");
+  LlmChatMessage {
+    role: "assistant".to_owned(),
+    content: if text.is_empty() { None } else { Some(text) },
+    tool_call_id: None,
+    tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+  }
+}
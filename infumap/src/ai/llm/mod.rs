@@ -0,0 +1,547 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use config::Config;
+use infusdk::util::infu::InfuResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::ai::chat_history::ChatTranscript;
+use crate::config::{CONFIG_DEFAULT_LLM_MODEL, CONFIG_LLAMA_SERVER_URL, CONFIG_LLM_MODELS};
+
+pub mod anthropic;
+pub mod openai;
+
+/// Name given to the model implied by the legacy llama_server_url setting.
+pub const LEGACY_LLM_MODEL_NAME: &str = "default";
+const LLM_REQUEST_TIMEOUT_SECS_DEFAULT: u64 = 120;
+const LLM_CONNECT_TIMEOUT_SECS: u64 = 30;
+/// Upper bound on tool calls (openai) or content blocks (anthropic) accepted from one streamed response.
+pub(crate) const LLM_MAX_STREAMED_RESPONSE_PARTS: usize = 64;
+const LLM_PROMPTED_TOOL_CALL_OPEN: &str = "<tool_call>";
+const LLM_PROMPTED_TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LlmChatMessage {
+  #[serde(default)]
+  pub role: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub content: Option<String>,
+  #[serde(rename = "tool_call_id", skip_serializing_if = "Option::is_none")]
+  pub tool_call_id: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub tool_calls: Option<Vec<LlmToolCall>>,
+}
+
+impl LlmChatMessage {
+  pub fn text(role: &str, content: String) -> Self {
+    Self { role: role.to_owned(), content: Some(content), tool_call_id: None, tool_calls: None }
+  }
+
+  pub fn tool(tool_call_id: String, content: String) -> Self {
+    Self { role: "tool".to_owned(), content: Some(content), tool_call_id: Some(tool_call_id), tool_calls: None }
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LlmToolCall {
+  #[serde(default)]
+  pub id: String,
+  #[serde(rename = "type", default = "default_llm_tool_call_type")]
+  pub tool_type: String,
+  pub function: LlmToolCallFunction,
+}
+
+fn default_llm_tool_call_type() -> String {
+  "function".to_owned()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LlmToolCallFunction {
+  pub name: String,
+  #[serde(default)]
+  pub arguments: Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LlmToolSpec {
+  #[serde(rename = "type")]
+  pub tool_type: String,
+  pub function: LlmToolFunctionSpec,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LlmToolFunctionSpec {
+  pub name: String,
+  pub description: String,
+  pub parameters: Value,
+}
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+  /// Runs one non-streaming completion. Tools are passed in the provider's native form; an empty
+  /// slice means the model should answer in text.
+  async fn chat_completion(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+  ) -> InfuResult<LlmChatMessage>;
+
+  /// Runs one completion, sending answer text to `deltas` as the model produces it. Providers
+  /// without streaming support send the whole answer as a single delta.
+  async fn chat_completion_streaming(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+    deltas: mpsc::UnboundedSender<String>,
+  ) -> InfuResult<LlmChatMessage> {
+    let message = self.chat_completion(transcript, messages, tools, llm_turn).await?;
+    if let Some(content) = message.content.as_deref().filter(|content| !content.is_empty()) {
+      let _ = deltas.send(content.to_owned());
+    }
+    Ok(message)
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProviderKind {
+  /// OpenAI style /v1/chat/completions, as served by llama.cpp, vLLM, Ollama and hosted APIs.
+  #[default]
+  OpenAi,
+  /// Anthropic style /v1/messages.
+  Anthropic,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmToolDialect {
+  /// Tools are sent using the provider's tool calling API.
+  #[default]
+  Native,
+  /// Tools are described in the system prompt and calls are parsed from <tool_call> blocks in the
+  /// answer text, for models or servers without tool calling support.
+  Prompted,
+  /// The model is never offered tools.
+  None,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LlmModelSettings {
+  #[serde(default)]
+  pub provider: LlmProviderKind,
+  pub url: String,
+  /// Model name sent upstream. Defaults to the configured model name.
+  #[serde(default)]
+  pub model: Option<String>,
+  #[serde(default)]
+  pub api_key: Option<String>,
+  /// Header used to send api_key. "Authorization" values are sent as a bearer token, any other
+  /// header receives the key as is. Defaults to the provider's usual header.
+  #[serde(default)]
+  pub auth_header: Option<String>,
+  #[serde(default)]
+  pub context_window_tokens: Option<u64>,
+  #[serde(default)]
+  pub max_output_tokens: Option<u64>,
+  #[serde(default)]
+  pub tool_dialect: LlmToolDialect,
+  #[serde(default)]
+  pub request_timeout_secs: Option<u64>,
+}
+
+impl LlmModelSettings {
+  fn legacy_llama_server(url: String) -> LlmModelSettings {
+    LlmModelSettings {
+      provider: LlmProviderKind::OpenAi,
+      url,
+      model: None,
+      api_key: None,
+      auth_header: None,
+      context_window_tokens: None,
+      max_output_tokens: None,
+      tool_dialect: LlmToolDialect::Native,
+      request_timeout_secs: None,
+    }
+  }
+
+  pub fn request_timeout(&self) -> Duration {
+    Duration::from_secs(self.request_timeout_secs.unwrap_or(LLM_REQUEST_TIMEOUT_SECS_DEFAULT).max(1))
+  }
+
+  /// The (header name, header value) pair carrying api_key, if one is configured.
+  pub fn auth_header_value(&self, default_header: &str) -> Option<(String, String)> {
+    let api_key = self.api_key.as_deref().map(str::trim).filter(|key| !key.is_empty())?;
+    let header =
+      self.auth_header.as_deref().map(str::trim).filter(|header| !header.is_empty()).unwrap_or(default_header);
+    if header.eq_ignore_ascii_case("authorization") {
+      Some((header.to_owned(), format!("Bearer {}", api_key)))
+    } else {
+      Some((header.to_owned(), api_key.to_owned()))
+    }
+  }
+}
+
+/// Public description of a configured model, safe to return to clients.
+#[derive(Clone, Debug, Serialize)]
+pub struct LlmModelSummary {
+  pub name: String,
+  pub provider: LlmProviderKind,
+  #[serde(rename = "contextWindowTokens", skip_serializing_if = "Option::is_none")]
+  pub context_window_tokens: Option<u64>,
+  #[serde(rename = "toolDialect")]
+  pub tool_dialect: LlmToolDialect,
+}
+
+pub struct LlmModel {
+  pub name: String,
+  pub settings: LlmModelSettings,
+  provider: Box<dyn LlmProvider>,
+}
+
+impl LlmModel {
+  pub fn supports_tools(&self) -> bool {
+    self.settings.tool_dialect != LlmToolDialect::None
+  }
+
+  /// Runs one completion, translating tool specs and tool calls for the configured tool dialect.
+  pub async fn chat_completion(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+  ) -> InfuResult<LlmChatMessage> {
+    match self.settings.tool_dialect {
+      LlmToolDialect::Native => self.provider.chat_completion(transcript, messages, tools, llm_turn).await,
+      LlmToolDialect::None => self.provider.chat_completion(transcript, messages, &[], llm_turn).await,
+      LlmToolDialect::Prompted => {
+        if tools.is_empty() {
+          return self.provider.chat_completion(transcript, messages, &[], llm_turn).await;
+        }
+        let prompted_messages = prompted_tool_messages(messages, tools);
+        let mut message = self.provider.chat_completion(transcript, &prompted_messages, &[], llm_turn).await?;
+        parse_prompted_tool_calls(&mut message);
+        Ok(message)
+      }
+    }
+  }
+
+  /// Streaming counterpart of chat_completion. With the prompted tool dialect, answers that may
+  /// contain tool calls are only sent once complete, since a partial <tool_call> block is not text
+  /// the user should see.
+  pub async fn chat_completion_streaming(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+    deltas: mpsc::UnboundedSender<String>,
+  ) -> InfuResult<LlmChatMessage> {
+    let tools = if self.supports_tools() { tools } else { &[] };
+    if self.settings.tool_dialect != LlmToolDialect::Prompted || tools.is_empty() {
+      return self.provider.chat_completion_streaming(transcript, messages, tools, llm_turn, deltas).await;
+    }
+    let message = self.chat_completion(transcript, messages, tools, llm_turn).await?;
+    if message.tool_calls.is_none()
+      && let Some(content) = message.content.as_deref().filter(|content| !content.is_empty())
+    {
+      let _ = deltas.send(content.to_owned());
+    }
+    Ok(message)
+  }
+}
+
+fn llm_model_summary(name: &str, settings: &LlmModelSettings) -> LlmModelSummary {
+  LlmModelSummary {
+    name: name.to_owned(),
+    provider: settings.provider,
+    context_window_tokens: settings.context_window_tokens,
+    tool_dialect: settings.tool_dialect,
+  }
+}
+
+/// All configured chat models by name. A non-empty llama_server_url is included as an OpenAI
+/// compatible model named "default" unless llm_models already defines that name.
+pub fn configured_llm_model_settings(config: &Config) -> InfuResult<BTreeMap<String, LlmModelSettings>> {
+  let mut models = match config.get::<HashMap<String, LlmModelSettings>>(CONFIG_LLM_MODELS) {
+    Ok(models) => models.into_iter().collect::<BTreeMap<_, _>>(),
+    Err(config::ConfigError::NotFound(_)) => BTreeMap::new(),
+    Err(e) => return Err(format!("Could not parse {}: {}", CONFIG_LLM_MODELS, e).into()),
+  };
+  let legacy_url = config.get_string(CONFIG_LLAMA_SERVER_URL).unwrap_or_default();
+  if !legacy_url.trim().is_empty() && !models.contains_key(LEGACY_LLM_MODEL_NAME) {
+    models.insert(LEGACY_LLM_MODEL_NAME.to_owned(), LlmModelSettings::legacy_llama_server(legacy_url));
+  }
+  Ok(models)
+}
+
+pub fn configured_llm_model_summaries(config: &Config) -> InfuResult<Vec<LlmModelSummary>> {
+  Ok(configured_llm_model_settings(config)?.iter().map(|(name, settings)| llm_model_summary(name, settings)).collect())
+}
+
+/// The model used when a conversation does not choose one: default_llm_model if set, otherwise
+/// "default" if present, otherwise the first configured model by name.
+pub fn default_llm_model_name(config: &Config) -> InfuResult<Option<String>> {
+  let models = configured_llm_model_settings(config)?;
+  let configured = config.get_string(CONFIG_DEFAULT_LLM_MODEL).unwrap_or_default();
+  let configured = configured.trim();
+  if !configured.is_empty() {
+    if !models.contains_key(configured) {
+      return Err(format!("{} '{}' is not a configured chat model.", CONFIG_DEFAULT_LLM_MODEL, configured).into());
+    }
+    return Ok(Some(configured.to_owned()));
+  }
+  if models.contains_key(LEGACY_LLM_MODEL_NAME) {
+    return Ok(Some(LEGACY_LLM_MODEL_NAME.to_owned()));
+  }
+  Ok(models.keys().next().cloned())
+}
+
+/// Resolves a configured model by name, or the default model when `name` is None.
+pub fn open_llm_model(config: &Config, name: Option<&str>) -> InfuResult<LlmModel> {
+  let name = match name.map(str::trim).filter(|name| !name.is_empty()) {
+    Some(name) => name.to_owned(),
+    None => default_llm_model_name(config)?.ok_or(format!(
+      "No chat models are configured. Set {} or {} to use Chat.",
+      CONFIG_LLM_MODELS, CONFIG_LLAMA_SERVER_URL
+    ))?,
+  };
+  let settings =
+    configured_llm_model_settings(config)?.remove(&name).ok_or(format!("Chat model '{}' is not configured.", name))?;
+  let provider: Box<dyn LlmProvider> = match settings.provider {
+    LlmProviderKind::OpenAi => Box::new(openai::OpenAiChatProvider::new(&name, &settings)?),
+    LlmProviderKind::Anthropic => Box::new(anthropic::AnthropicChatProvider::new(&name, &settings)?),
+  };
+  Ok(LlmModel { name, settings, provider })
+}
+
+/// Joins the configured base URL and the provider endpoint path, unless the URL already names the endpoint.
+pub(crate) fn llm_endpoint_url(model_name: &str, raw_url: &str, endpoint_path: &str) -> InfuResult<reqwest::Url> {
+  let trimmed_url = raw_url.trim();
+  if trimmed_url.is_empty() {
+    return Err(format!("Chat model '{}' has no url configured.", model_name).into());
+  }
+
+  let parsed = reqwest::Url::parse(trimmed_url)
+    .map_err(|e| format!("Could not parse url '{}' for chat model '{}': {}", trimmed_url, model_name, e))?;
+  if parsed.path().trim_end_matches('/').ends_with(endpoint_path) {
+    return Ok(parsed);
+  }
+
+  let base_url = reqwest::Url::parse(&format!("{}/", trimmed_url.trim_end_matches('/')))
+    .map_err(|e| format!("Could not parse url '{}' for chat model '{}': {}", trimmed_url, model_name, e))?;
+  base_url.join(endpoint_path.trim_start_matches('/')).map_err(|e| {
+    format!("Could not build chat endpoint for model '{}' from '{}': {}", model_name, trimmed_url, e).into()
+  })
+}
+
+pub(crate) fn llm_http_client(model_name: &str, settings: &LlmModelSettings) -> InfuResult<reqwest::Client> {
+  reqwest::ClientBuilder::new().timeout(settings.request_timeout()).build().map_err(|e| {
+    format!("Could not build HTTP client for chat model '{}': {}", model_name, reqwest_error_for_log(&e)).into()
+  })
+}
+
+/// Client for streamed responses. A long answer may take longer than request_timeout_secs to
+/// arrive in full, so there is no total timeout: request_timeout_secs instead bounds how long the
+/// stream may go without delivering any data.
+pub(crate) fn llm_streaming_http_client(model_name: &str, settings: &LlmModelSettings) -> InfuResult<reqwest::Client> {
+  reqwest::ClientBuilder::new()
+    .connect_timeout(Duration::from_secs(LLM_CONNECT_TIMEOUT_SECS))
+    .read_timeout(settings.request_timeout())
+    .build()
+    .map_err(|e| {
+      format!("Could not build HTTP client for chat model '{}': {}", model_name, reqwest_error_for_log(&e)).into()
+    })
+}
+
+/// Splits a text/event-stream body into events as chunks arrive.
+#[derive(Default)]
+pub(crate) struct SseEventReader {
+  buffer: Vec<u8>,
+}
+
+pub(crate) struct SseEvent {
+  pub event: Option<String>,
+  pub data: String,
+}
+
+impl SseEventReader {
+  pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+    self.buffer.extend(chunk.iter().copied().filter(|byte| *byte != b'\r'));
+    let mut events = Vec::new();
+    while let Some(end) = self.buffer.windows(2).position(|window| window == b"\n\n") {
+      let raw = self.buffer.drain(..end + 2).collect::<Vec<_>>();
+      let text = String::from_utf8_lossy(&raw[..end]);
+      let mut event = None;
+      let mut data_lines = Vec::new();
+      for line in text.lines() {
+        if let Some(data) = line.strip_prefix("data:") {
+          data_lines.push(data.strip_prefix(' ').unwrap_or(data));
+        } else if let Some(name) = line.strip_prefix("event:") {
+          event = Some(name.trim().to_owned());
+        }
+      }
+      if event.is_some() || !data_lines.is_empty() {
+        events.push(SseEvent { event, data: data_lines.join("\n") });
+      }
+    }
+    events
+  }
+}
+
+pub fn truncate_for_error(text: &str, max_chars: usize) -> String {
+  let mut chars = text.chars();
+  let truncated: String = chars.by_ref().take(max_chars).collect();
+  if chars.next().is_some() { format!("{}...", truncated) } else { truncated }
+}
+
+pub fn error_chain_for_log(error: &dyn std::error::Error) -> String {
+  let mut result = error.to_string();
+  let mut source_maybe = error.source();
+  while let Some(source) = source_maybe {
+    result.push_str(": ");
+    result.push_str(&source.to_string());
+    source_maybe = source.source();
+  }
+  result
+}
+
+pub fn reqwest_error_for_log(error: &reqwest::Error) -> String {
+  let mut kinds = Vec::new();
+  if error.is_timeout() {
+    kinds.push("timeout");
+  }
+  if error.is_connect() {
+    kinds.push("connect");
+  }
+  if error.is_builder() {
+    kinds.push("builder");
+  }
+  if error.is_redirect() {
+    kinds.push("redirect");
+  }
+  if error.is_status() {
+    kinds.push("status");
+  }
+  if error.is_body() {
+    kinds.push("body");
+  }
+  if error.is_decode() {
+    kinds.push("decode");
+  }
+  let kind_suffix = if kinds.is_empty() { "".to_owned() } else { format!(" [kind={}]", kinds.join(",")) };
+  format!("{}{}", error_chain_for_log(error), kind_suffix)
+}
+
+/// Rewrites a tool-using conversation for a model without tool calling: tool descriptions are
+/// appended to the system prompt, earlier calls become <tool_call> blocks and tool results become
+/// user messages.
+fn prompted_tool_messages(messages: &[LlmChatMessage], tools: &[LlmToolSpec]) -> Vec<LlmChatMessage> {
+  let tool_descriptions = tools
+    .iter()
+    .map(|tool| {
+      serde_json::json!({
+        "name": tool.function.name,
+        "description": tool.function.description,
+        "parameters": tool.function.parameters,
+      })
+      .to_string()
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  let tool_instructions = format!(
+    "You can call tools. To call a tool, reply with only one or more blocks of the form\n\
+     {}{{\"name\": \"<tool name>\", \"arguments\": {{...}}}}{}\n\
+     and wait for the results, which are returned in a user message. Available tools:\n{}",
+    LLM_PROMPTED_TOOL_CALL_OPEN, LLM_PROMPTED_TOOL_CALL_CLOSE, tool_descriptions
+  );
+
+  let mut tool_names_by_call_id = HashMap::new();
+  let mut result = Vec::with_capacity(messages.len() + 1);
+  let mut has_system_message = false;
+  for message in messages {
+    match message.role.as_str() {
+      "system" if !has_system_message => {
+        has_system_message = true;
+        let content = message.content.as_deref().unwrap_or("");
+        result.push(LlmChatMessage::text("system", format!("{}\n\n{}", content, tool_instructions)));
+      }
+      "assistant" if message.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty()) => {
+        let mut content = message.content.clone().unwrap_or_default();
+        for tool_call in message.tool_calls.as_deref().unwrap_or(&[]) {
+          tool_names_by_call_id.insert(tool_call.id.clone(), tool_call.function.name.clone());
+          let call = serde_json::json!({ "name": tool_call.function.name, "arguments": tool_call.function.arguments });
+          content.push_str(&format!("\n{}{}{}", LLM_PROMPTED_TOOL_CALL_OPEN, call, LLM_PROMPTED_TOOL_CALL_CLOSE));
+        }
+        result.push(LlmChatMessage::text("assistant", content.trim().to_owned()));
+      }
+      "tool" => {
+        let tool_name = message
+          .tool_call_id
+          .as_ref()
+          .and_then(|id| tool_names_by_call_id.get(id))
+          .map(String::as_str)
+          .unwrap_or("tool");
+        let content =
+          format!("<tool_result name=\"{}\">\n{}\n</tool_result>", tool_name, message.content.as_deref().unwrap_or(""));
+        result.push(LlmChatMessage::text("user", content));
+      }
+      _ => result.push(message.clone()),
+    }
+  }
+  if !has_system_message {
+    result.insert(0, LlmChatMessage::text("system", tool_instructions));
+  }
+  result
+}
+
+/// Moves <tool_call> blocks in a prompted-dialect answer into tool_calls. Blocks that are not valid
+/// JSON tool calls are left in the text.
+fn parse_prompted_tool_calls(message: &mut LlmChatMessage) {
+  let Some(content) = message.content.as_deref() else {
+    return;
+  };
+
+  let mut remaining_text = String::new();
+  let mut tool_calls = Vec::new();
+  let mut rest = content;
+  while let Some(open_index) = rest.find(LLM_PROMPTED_TOOL_CALL_OPEN) {
+    let after_open = &rest[open_index + LLM_PROMPTED_TOOL_CALL_OPEN.len()..];
+    let Some(close_index) = after_open.find(LLM_PROMPTED_TOOL_CALL_CLOSE) else {
+      break;
+    };
+    remaining_text.push_str(&rest[..open_index]);
+    let block = after_open[..close_index].trim();
+    match serde_json::from_str::<Value>(block) {
+      Ok(call) if call.get("name").and_then(Value::as_str).is_some() => tool_calls.push(LlmToolCall {
+        id: String::new(),
+        tool_type: default_llm_tool_call_type(),
+        function: LlmToolCallFunction {
+          name: call["name"].as_str().unwrap_or_default().to_owned(),
+          arguments: call.get("arguments").cloned().unwrap_or(Value::Null),
+        },
+      }),
+      _ => {
+        let block_end =
+          open_index + LLM_PROMPTED_TOOL_CALL_OPEN.len() + close_index + LLM_PROMPTED_TOOL_CALL_CLOSE.len();
+        remaining_text.push_str(&rest[open_index..block_end]);
+      }
+    }
+    rest = &after_open[close_index + LLM_PROMPTED_TOOL_CALL_CLOSE.len()..];
+  }
+  remaining_text.push_str(rest);
+
+  if tool_calls.is_empty() {
+    return;
+  }
+  let remaining_text = remaining_text.trim();
+  message.content = if remaining_text.is_empty() { None } else { Some(remaining_text.to_owned()) };
+  message.tool_calls = Some(tool_calls);
+}
@@ -0,0 +1,297 @@
+use async_trait::async_trait;
+use infusdk::util::infu::InfuResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::ai::chat_history::ChatTranscript;
+
+use super::{
+  LLM_MAX_STREAMED_RESPONSE_PARTS, LlmChatMessage, LlmModelSettings, LlmProvider, LlmToolCall, LlmToolCallFunction,
+  LlmToolSpec, SseEventReader, default_llm_tool_call_type, error_chain_for_log, llm_endpoint_url, llm_http_client,
+  llm_streaming_http_client, reqwest_error_for_log, truncate_for_error,
+};
+
+const OPENAI_CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+const OPENAI_DEFAULT_AUTH_HEADER: &str = "Authorization";
+
+#[derive(Serialize)]
+struct OpenAiChatCompletionRequest<'a> {
+  model: &'a str,
+  messages: &'a [LlmChatMessage],
+  stream: bool,
+  #[serde(skip_serializing_if = "<[_]>::is_empty")]
+  tools: &'a [LlmToolSpec],
+  #[serde(rename = "max_tokens", skip_serializing_if = "Option::is_none")]
+  max_tokens: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChatCompletionResponse {
+  choices: Vec<OpenAiChatCompletionChoice>,
+  usage: Option<OpenAiChatCompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChatCompletionChoice {
+  message: LlmChatMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChatCompletionChunk {
+  #[serde(default)]
+  choices: Vec<OpenAiChatCompletionChunkChoice>,
+  usage: Option<OpenAiChatCompletionUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChatCompletionChunkChoice {
+  #[serde(default)]
+  delta: OpenAiChatCompletionDelta,
+}
+
+#[derive(Default, Deserialize)]
+struct OpenAiChatCompletionDelta {
+  content: Option<String>,
+  #[serde(default)]
+  tool_calls: Vec<OpenAiToolCallDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiToolCallDelta {
+  #[serde(default)]
+  index: usize,
+  id: Option<String>,
+  function: Option<OpenAiToolCallFunctionDelta>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiToolCallFunctionDelta {
+  name: Option<String>,
+  arguments: Option<String>,
+}
+
+#[derive(Default)]
+struct OpenAiStreamedToolCall {
+  id: String,
+  name: String,
+  arguments: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct OpenAiChatCompletionUsage {
+  #[serde(rename = "prompt_tokens")]
+  prompt_tokens: Option<i64>,
+  #[serde(rename = "completion_tokens")]
+  completion_tokens: Option<i64>,
+  #[serde(rename = "total_tokens")]
+  total_tokens: Option<i64>,
+}
+
+/// OpenAI compatible /v1/chat/completions, as served by llama.cpp llama-server, vLLM, Ollama and
+/// most hosted APIs.
+pub struct OpenAiChatProvider {
+  model_name: String,
+  upstream_model: String,
+  url: reqwest::Url,
+  auth_header: Option<(String, String)>,
+  max_tokens: Option<u64>,
+  client: reqwest::Client,
+  stream_client: reqwest::Client,
+}
+
+impl OpenAiChatProvider {
+  pub fn new(model_name: &str, settings: &LlmModelSettings) -> InfuResult<OpenAiChatProvider> {
+    Ok(OpenAiChatProvider {
+      model_name: model_name.to_owned(),
+      upstream_model: settings.model.clone().unwrap_or_else(|| model_name.to_owned()),
+      url: llm_endpoint_url(model_name, &settings.url, OPENAI_CHAT_COMPLETIONS_PATH)?,
+      auth_header: settings.auth_header_value(OPENAI_DEFAULT_AUTH_HEADER),
+      max_tokens: settings.max_output_tokens,
+      client: llm_http_client(model_name, settings)?,
+      stream_client: llm_streaming_http_client(model_name, settings)?,
+    })
+  }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiChatProvider {
+  async fn chat_completion(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+  ) -> InfuResult<LlmChatMessage> {
+    let payload = OpenAiChatCompletionRequest {
+      model: &self.upstream_model,
+      messages,
+      stream: false,
+      tools,
+      max_tokens: self.max_tokens,
+    };
+    transcript.append_json_section(&format!("LLM REQUEST {} ({})", llm_turn, self.model_name), &payload).await;
+
+    let mut request = self.client.post(self.url.clone()).json(&payload);
+    if let Some((header, value)) = self.auth_header.as_ref() {
+      request = request.header(header.as_str(), value.as_str());
+    }
+    let response = request.send().await.map_err(|e| {
+      format!(
+        "Could not send chat request to model '{}' at '{}': {}",
+        self.model_name,
+        self.url,
+        reqwest_error_for_log(&e)
+      )
+    })?;
+
+    let status = response.status();
+    let body = response.text().await.map_err(|e| {
+      format!("Could not read chat response body from model '{}': {}", self.model_name, reqwest_error_for_log(&e))
+    })?;
+    transcript.append_body_section(&format!("LLM RESPONSE {}", llm_turn), &body).await;
+    if !status.is_success() {
+      return Err(
+        format!(
+          "Chat endpoint '{}' for model '{}' returned {}: {}",
+          self.url,
+          self.model_name,
+          status,
+          truncate_for_error(&body, 1000)
+        )
+        .into(),
+      );
+    }
+
+    let parsed: OpenAiChatCompletionResponse = serde_json::from_str(&body).map_err(|e| {
+      format!("Could not parse chat response from model '{}': {}", self.model_name, error_chain_for_log(&e))
+    })?;
+    if let Some(usage) = parsed.usage.as_ref() {
+      transcript.append_json_section(&format!("LLM RESPONSE USAGE {}", llm_turn), usage).await;
+    }
+    parsed
+      .choices
+      .into_iter()
+      .next()
+      .map(|choice| choice.message)
+      .ok_or_else(|| format!("Chat model '{}' returned no chat response choices.", self.model_name).into())
+  }
+  async fn chat_completion_streaming(
+    &self,
+    transcript: &ChatTranscript,
+    messages: &[LlmChatMessage],
+    tools: &[LlmToolSpec],
+    llm_turn: usize,
+    deltas: mpsc::UnboundedSender<String>,
+  ) -> InfuResult<LlmChatMessage> {
+    let payload = OpenAiChatCompletionRequest {
+      model: &self.upstream_model,
+      messages,
+      stream: true,
+      tools,
+      max_tokens: self.max_tokens,
+    };
+    transcript.append_json_section(&format!("LLM REQUEST {} ({})", llm_turn, self.model_name), &payload).await;
+
+    let mut request = self.stream_client.post(self.url.clone()).json(&payload);
+    if let Some((header, value)) = self.auth_header.as_ref() {
+      request = request.header(header.as_str(), value.as_str());
+    }
+    let mut response = request.send().await.map_err(|e| {
+      format!(
+        "Could not send chat request to model '{}' at '{}': {}",
+        self.model_name,
+        self.url,
+        reqwest_error_for_log(&e)
+      )
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+      let body = response.text().await.unwrap_or_default();
+      transcript.append_body_section(&format!("LLM RESPONSE {}", llm_turn), &body).await;
+      return Err(
+        format!(
+          "Chat endpoint '{}' for model '{}' returned {}: {}",
+          self.url,
+          self.model_name,
+          status,
+          truncate_for_error(&body, 1000)
+        )
+        .into(),
+      );
+    }
+
+    let mut reader = SseEventReader::default();
+    let mut content = String::new();
+    let mut tool_calls: Vec<OpenAiStreamedToolCall> = Vec::new();
+    let mut usage = None;
+    'stream: while let Some(chunk) = response.chunk().await.map_err(|e| {
+      format!("Could not read chat response stream from model '{}': {}", self.model_name, reqwest_error_for_log(&e))
+    })? {
+      for event in reader.push(&chunk) {
+        if event.data.trim() == "[DONE]" {
+          break 'stream;
+        }
+        let parsed: OpenAiChatCompletionChunk = serde_json::from_str(&event.data).map_err(|e| {
+          format!("Could not parse chat response chunk from model '{}': {}", self.model_name, error_chain_for_log(&e))
+        })?;
+        if parsed.usage.is_some() {
+          usage = parsed.usage;
+        }
+        for choice in parsed.choices {
+          if let Some(text) = choice.delta.content.filter(|text| !text.is_empty()) {
+            content.push_str(&text);
+            let _ = deltas.send(text);
+          }
+          for tool_call_delta in choice.delta.tool_calls {
+            if tool_call_delta.index >= LLM_MAX_STREAMED_RESPONSE_PARTS {
+              return Err(
+                format!(
+                  "Chat model '{}' streamed tool call index {}, more than the {} tool calls allowed in one response.",
+                  self.model_name, tool_call_delta.index, LLM_MAX_STREAMED_RESPONSE_PARTS
+                )
+                .into(),
+              );
+            }
+            while tool_calls.len() <= tool_call_delta.index {
+              tool_calls.push(OpenAiStreamedToolCall::default());
+            }
+            let tool_call = &mut tool_calls[tool_call_delta.index];
+            if let Some(id) = tool_call_delta.id {
+              tool_call.id = id;
+            }
+            if let Some(function) = tool_call_delta.function {
+              if let Some(name) = function.name {
+                tool_call.name.push_str(&name);
+              }
+              if let Some(arguments) = function.arguments {
+                tool_call.arguments.push_str(&arguments);
+              }
+            }
+          }
+        }
+      }
+    }
+
+    let tool_calls = tool_calls
+      .into_iter()
+      .filter(|tool_call| !tool_call.name.is_empty())
+      .map(|tool_call| LlmToolCall {
+        id: tool_call.id,
+        tool_type: default_llm_tool_call_type(),
+        function: LlmToolCallFunction { name: tool_call.name, arguments: Value::String(tool_call.arguments) },
+      })
+      .collect::<Vec<_>>();
+    let message = LlmChatMessage {
+      role: "assistant".to_owned(),
+      content: if content.is_empty() { None } else { Some(content) },
+      tool_call_id: None,
+      tool_calls: if tool_calls.is_empty() { None } else { Some(tool_calls) },
+    };
+    transcript.append_json_section(&format!("LLM RESPONSE {} (streamed)", llm_turn), &message).await;
+    if let Some(usage) = usage.as_ref() {
+      transcript.append_json_section(&format!("LLM RESPONSE USAGE {}", llm_turn), usage).await;
+    }
+    Ok(message)
+  }
+}
@@ -58,6 +58,38 @@ pub static METRIC_AI_DOCUMENT_FRAGMENT_PROCESSED_TOTAL: Lazy<IntCounterVec> = La
   .expect("Could not create METRIC_AI_DOCUMENT_FRAGMENT_PROCESSED_TOTAL")
 });
 
+pub static METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+  IntGauge::with_opts(opts!("infumap_ai_link_snapshot_queue_depth", "Current link snapshot background queue depth."))
+    .expect("Could not create METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH")
+});
+
+pub static METRIC_AI_LINK_SNAPSHOT_PROCESSED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+  IntCounterVec::new(
+    opts!("infumap_ai_link_snapshot_processed_total", "Total link snapshot background items processed by outcome."),
+    &["outcome"],
+  )
+  .expect("Could not create METRIC_AI_LINK_SNAPSHOT_PROCESSED_TOTAL")
+});
+
+pub static METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH: Lazy<IntGauge> = Lazy::new(|| {
+  IntGauge::with_opts(opts!(
+    "infumap_ai_audio_transcript_queue_depth",
+    "Current audio transcription background queue depth."
+  ))
+  .expect("Could not create METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH")
+});
+
+pub static METRIC_AI_AUDIO_TRANSCRIPT_PROCESSED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+  IntCounterVec::new(
+    opts!(
+      "infumap_ai_audio_transcript_processed_total",
+      "Total audio transcription background items processed by outcome."
+    ),
+    &["outcome"],
+  )
+  .expect("Could not create METRIC_AI_AUDIO_TRANSCRIPT_PROCESSED_TOTAL")
+});
+
 pub static METRIC_AI_TITLE_INDEX_REBUILDS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
   IntCounterVec::new(
     opts!("infumap_ai_title_index_rebuilds_total", "Total item title lexical index reconciliations by outcome."),
@@ -142,6 +174,10 @@ pub fn register_ai_metrics() {
   prometheus::register(Box::new(METRIC_AI_PDF_TEXT_EXTRACTION_PROCESSED_TOTAL.clone())).unwrap();
   prometheus::register(Box::new(METRIC_AI_DOCUMENT_FRAGMENT_QUEUE_DEPTH.clone())).unwrap();
   prometheus::register(Box::new(METRIC_AI_DOCUMENT_FRAGMENT_PROCESSED_TOTAL.clone())).unwrap();
+  prometheus::register(Box::new(METRIC_AI_LINK_SNAPSHOT_QUEUE_DEPTH.clone())).unwrap();
+  prometheus::register(Box::new(METRIC_AI_LINK_SNAPSHOT_PROCESSED_TOTAL.clone())).unwrap();
+  prometheus::register(Box::new(METRIC_AI_AUDIO_TRANSCRIPT_QUEUE_DEPTH.clone())).unwrap();
+  prometheus::register(Box::new(METRIC_AI_AUDIO_TRANSCRIPT_PROCESSED_TOTAL.clone())).unwrap();
   prometheus::register(Box::new(METRIC_AI_TITLE_INDEX_REBUILDS_TOTAL.clone())).unwrap();
   prometheus::register(Box::new(METRIC_AI_TITLE_INDEX_REBUILD_DURATION_SECONDS.clone())).unwrap();
   prometheus::register(Box::new(METRIC_AI_FRAGMENT_INDEX_REBUILDS_TOTAL.clone())).unwrap();
@@ -1,18 +1,30 @@
 pub mod artifact_paths;
+pub mod audio_pipeline;
 pub mod batch_processing;
+pub mod chat_history;
 pub mod document_pipeline;
+pub mod faces;
 pub mod fragment;
 pub mod fragment_indexing;
+pub mod gazetteer;
 pub mod geo;
+pub mod geo_index;
 pub mod gpu_tools;
 pub mod image_pipeline;
+pub mod image_tag_cache;
 pub mod image_tagging;
+pub mod image_vectors;
 pub mod indexing;
 pub mod lexical_index;
+pub mod link_snapshot_pipeline;
+pub mod llm;
 pub mod metrics;
+pub mod near_duplicates;
 pub mod search_status;
+pub mod structured_indexing;
 pub mod text_embedding;
 pub mod text_extraction;
+pub mod timeline;
 pub mod title_indexing;
 pub mod upload_quiet_period;
 pub mod vector_db;
@@ -0,0 +1,513 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, ErrorKind};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config::Config;
+use image::ImageReader;
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+use infusdk::util::time::unix_now_secs_u64;
+use log::{debug, error, info, warn};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task;
+use tokio::time::{Instant as TokioInstant, timeout_at};
+
+use crate::ai::image_tagging::should_tag_image_item;
+use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
+use crate::ai::user_id_for_log;
+use crate::ai::vector_db::{
+  FragmentVectorDbBackend, ImageVector, ensure_user_index_dir, open_user_image_vector_db, user_index_dir,
+};
+use crate::config::{CONFIG_DATA_DIR, CONFIG_ENABLE_NEAR_DUPLICATE_SCAN};
+use crate::storage::db::Db;
+use crate::storage::object::ObjectStore;
+use crate::util::image::{
+  adjust_image_for_exif_orientation, capture_datetime_wall_clock_secs, extract_image_metadata, get_exif_orientation,
+  perceptual_hash,
+};
+use crate::util::image_rendition::get_image_item_bytes;
+
+const NEAR_DUPLICATE_SCAN_DEBOUNCE_SECS: u64 = 5 * 60;
+const NEAR_DUPLICATE_SCAN_MAX_DEBOUNCE_SECS: u64 = 30 * 60;
+
+/// Re-encoded, resized and lightly edited copies of an image are within this many bits of each other.
+const NEAR_DUPLICATE_MAX_HASH_DISTANCE: u32 = 6;
+/// Burst shots differ by more than re-saved copies do, so are only grouped when they were captured
+/// within a short time of each other and their image embeddings are also very close.
+const BURST_MAX_CAPTURE_GAP_SECS: i64 = 120;
+const BURST_MAX_EMBEDDING_DISTANCE: f32 = 0.1;
+
+const IMAGE_HASHES_FILENAME: &str = "image_hashes.json";
+const IMAGE_HASHES_SCHEMA_VERSION: u32 = 1;
+const IMAGE_HASHES_SAVE_INTERVAL: usize = 100;
+const NEAR_DUPLICATES_FILENAME: &str = "near_duplicates.json";
+const NEAR_DUPLICATES_SCHEMA_VERSION: u32 = 1;
+
+static NEAR_DUPLICATE_SCAN_QUEUE: OnceCell<mpsc::UnboundedSender<String>> = OnceCell::new();
+
+/// The most recent near-duplicate scan of a user's images.
+#[derive(Serialize, Deserialize)]
+pub struct NearDuplicateReport {
+  pub schema_version: u32,
+  pub generated_at_unix_secs: i64,
+  pub clusters: Vec<NearDuplicateCluster>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NearDuplicateCluster {
+  /// Best first: the highest resolution image, then the earliest captured.
+  pub items: Vec<NearDuplicateClusterItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NearDuplicateClusterItem {
+  pub item_id: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub captured_at: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NearDuplicateScanSummary {
+  pub images: usize,
+  pub hashed: usize,
+  pub clusters: usize,
+  pub redundant_images: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImageHashCache {
+  schema_version: u32,
+  images: HashMap<String, ImageHashEntry>,
+}
+
+/// Image data doesn't change once uploaded, so the hash of an item never needs to be recomputed.
+#[derive(Serialize, Deserialize, Clone)]
+struct ImageHashEntry {
+  /// Hex encoded. None if the image could not be decoded.
+  hash: Option<String>,
+  captured_at: Option<String>,
+}
+
+struct ImageCandidate {
+  item_id: String,
+  mime_type: String,
+  pixels: i64,
+  creation_date: i64,
+}
+
+pub fn init_near_duplicate_scan_loop(
+  config: &Config,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+) -> InfuResult<()> {
+  if !config.get_bool(CONFIG_ENABLE_NEAR_DUPLICATE_SCAN).map_err(|e| e.to_string())? {
+    return Ok(());
+  }
+  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+  if NEAR_DUPLICATE_SCAN_QUEUE.get().is_some() {
+    enqueue_all_loaded_users(db);
+    return Ok(());
+  }
+
+  let (sender, receiver) = mpsc::unbounded_channel();
+  NEAR_DUPLICATE_SCAN_QUEUE
+    .set(sender)
+    .map_err(|_| "Near-duplicate image scan loop is already running in this process.".to_owned())?;
+
+  info!("Starting near-duplicate image scan loop.");
+  let _worker = task::spawn(async move {
+    run_near_duplicate_scan_loop(data_dir, db, object_store, receiver).await;
+  });
+
+  Ok(())
+}
+
+pub fn enqueue_near_duplicate_scan_for_user(user_id: &str) {
+  let Some(sender) = NEAR_DUPLICATE_SCAN_QUEUE.get() else {
+    return;
+  };
+  if let Err(e) = sender.send(user_id.to_owned()) {
+    warn!("Could not enqueue near-duplicate image scan for user '{}': {}", user_id_for_log(user_id), e);
+  }
+}
+
+fn enqueue_all_loaded_users(db: Arc<Mutex<Db>>) {
+  let _enqueue_task = task::spawn(async move {
+    let mut user_ids = {
+      let db = db.lock().await;
+      db.user.all_user_ids().iter().map(|user_id| user_id.to_owned()).collect::<Vec<_>>()
+    };
+    user_ids.sort();
+    for user_id in user_ids {
+      enqueue_near_duplicate_scan_for_user(&user_id);
+    }
+  });
+}
+
+/// Scans are comparatively expensive, and uploads tend to arrive in batches, so every request is
+/// debounced, including those at startup.
+async fn run_near_duplicate_scan_loop(
+  data_dir: String,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+  mut receiver: mpsc::UnboundedReceiver<String>,
+) {
+  enqueue_all_loaded_users(db.clone());
+
+  let mut queued_user_ids = HashSet::<String>::new();
+  while let Some(user_id) = receiver.recv().await {
+    queued_user_ids.insert(user_id);
+    let max_debounce_deadline = TokioInstant::now() + Duration::from_secs(NEAR_DUPLICATE_SCAN_MAX_DEBOUNCE_SECS);
+    loop {
+      let quiet_deadline = TokioInstant::now() + Duration::from_secs(NEAR_DUPLICATE_SCAN_DEBOUNCE_SECS);
+      match timeout_at(quiet_deadline.min(max_debounce_deadline), receiver.recv()).await {
+        Ok(Some(user_id)) => {
+          queued_user_ids.insert(user_id);
+          if TokioInstant::now() >= max_debounce_deadline {
+            break;
+          }
+        }
+        Ok(None) => break,
+        Err(_) => break,
+      }
+    }
+
+    let mut user_ids = queued_user_ids.drain().collect::<Vec<_>>();
+    user_ids.sort();
+    for user_id in user_ids {
+      wait_for_object_store_upload_quiet_period("near-duplicate image scan").await;
+      match scan_user_near_duplicates(&data_dir, db.clone(), object_store.clone(), &user_id).await {
+        Ok(summary) => debug!(
+          "User {} near-duplicate image scan: {} image(s), {} newly hashed, {} cluster(s).",
+          user_id_for_log(&user_id),
+          summary.images,
+          summary.hashed,
+          summary.clusters
+        ),
+        Err(e) => error!("Near-duplicate image scan failed for user '{}': {}", user_id_for_log(&user_id), e),
+      }
+    }
+  }
+}
+
+/// Hash any of the user's images that haven't been hashed yet, cluster near-duplicates and write the
+/// report. Images in the trash are not considered.
+pub async fn scan_user_near_duplicates(
+  data_dir: &str,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+  user_id: &str,
+) -> InfuResult<NearDuplicateScanSummary> {
+  let (candidates, object_encryption_key) = {
+    let db = db.lock().await;
+    let user = db.user.get(&user_id.to_owned()).ok_or(format!("User '{}' not found.", user_id))?;
+    let object_encryption_key = user.object_encryption_key.clone();
+    let mut candidates = vec![];
+    for item_and_user_id in db.item.all_loaded_items().iter().filter(|item| item.user_id == user_id) {
+      let item = db.item.get(&item_and_user_id.item_id)?;
+      if !should_tag_image_item(item) || item_is_in_trash(&db, item) {
+        continue;
+      }
+      candidates.push(ImageCandidate {
+        item_id: item.id.clone(),
+        mime_type: item.mime_type.clone().unwrap_or_default(),
+        pixels: item.image_size_px.as_ref().map(|size| size.w * size.h).unwrap_or(0),
+        creation_date: item.original_creation_date.unwrap_or(item.creation_date),
+      });
+    }
+    (candidates, object_encryption_key)
+  };
+
+  ensure_user_index_dir(data_dir, user_id).await?;
+  let mut summary = NearDuplicateScanSummary { images: candidates.len(), ..Default::default() };
+  let mut hash_cache = read_image_hash_cache(data_dir, user_id).await?;
+  let candidate_ids = candidates.iter().map(|candidate| candidate.item_id.as_str()).collect::<HashSet<_>>();
+  hash_cache.images.retain(|item_id, _| candidate_ids.contains(item_id.as_str()));
+
+  for candidate in candidates.iter() {
+    if hash_cache.images.contains_key(&candidate.item_id) {
+      continue;
+    }
+    let bytes = match get_image_item_bytes(
+      data_dir,
+      object_store.clone(),
+      user_id,
+      &candidate.item_id,
+      &candidate.mime_type,
+      &object_encryption_key,
+    )
+    .await
+    {
+      Ok(bytes) => bytes,
+      Err(e) => {
+        // Not cached, so it is retried on the next scan: the rendition may not have been created yet.
+        debug!("Could not read image '{}' for near-duplicate scan: {}", candidate.item_id, e);
+        continue;
+      }
+    };
+    let item_id = candidate.item_id.clone();
+    let entry = task::spawn_blocking(move || hash_image(&bytes, &item_id))
+      .await
+      .map_err(|e| format!("Image hashing task for item '{}' failed: {}", candidate.item_id, e))?;
+    hash_cache.images.insert(candidate.item_id.clone(), entry);
+    summary.hashed += 1;
+    if summary.hashed.is_multiple_of(IMAGE_HASHES_SAVE_INTERVAL) {
+      write_image_hash_cache(data_dir, user_id, &hash_cache).await?;
+    }
+  }
+  write_image_hash_cache(data_dir, user_id, &hash_cache).await?;
+
+  let clusters = cluster_near_duplicates(data_dir, user_id, &candidates, &hash_cache).await?;
+  summary.clusters = clusters.len();
+  summary.redundant_images = clusters.iter().map(|cluster| cluster.items.len() - 1).sum();
+  let report = NearDuplicateReport {
+    schema_version: NEAR_DUPLICATES_SCHEMA_VERSION,
+    generated_at_unix_secs: unix_now_secs_u64()? as i64,
+    clusters,
+  };
+  write_json(&near_duplicates_path(data_dir, user_id)?, &report, "near-duplicate report").await?;
+  Ok(summary)
+}
+
+pub async fn read_near_duplicate_report(data_dir: &str, user_id: &str) -> InfuResult<Option<NearDuplicateReport>> {
+  let report: Option<NearDuplicateReport> =
+    read_json_if_exists(&near_duplicates_path(data_dir, user_id)?, "near-duplicate report").await?;
+  Ok(report.filter(|report| report.schema_version == NEAR_DUPLICATES_SCHEMA_VERSION))
+}
+
+/// Whether the item is the user's trash page, or anywhere beneath it.
+pub fn item_is_in_trash(db: &Db, item: &Item) -> bool {
+  let Some(user) = db.user.get(&item.owner_id) else {
+    return false;
+  };
+  let mut visited = HashSet::new();
+  let mut current_id = Some(item.id.clone());
+  while let Some(id) = current_id {
+    if id == user.trash_page_id {
+      return true;
+    }
+    if !visited.insert(id.clone()) {
+      return false;
+    }
+    current_id = db.item.get(&id).ok().and_then(|item| item.parent_id.clone());
+  }
+  false
+}
+
+fn hash_image(bytes: &[u8], item_id: &str) -> ImageHashEntry {
+  let captured_at = extract_image_metadata(bytes).and_then(|metadata| metadata.captured_at);
+  let hash = match ImageReader::new(Cursor::new(bytes)).with_guessed_format().map(|reader| reader.decode()) {
+    Ok(Ok(img)) => {
+      let img = adjust_image_for_exif_orientation(img, get_exif_orientation(bytes.to_vec(), item_id), item_id);
+      Some(format!("{:016x}", perceptual_hash(&img)))
+    }
+    Ok(Err(e)) => {
+      debug!("Could not decode image '{}' for near-duplicate scan: {}", item_id, e);
+      None
+    }
+    Err(e) => {
+      debug!("Could not determine format of image '{}' for near-duplicate scan: {}", item_id, e);
+      None
+    }
+  };
+  ImageHashEntry { hash, captured_at }
+}
+
+async fn cluster_near_duplicates(
+  data_dir: &str,
+  user_id: &str,
+  candidates: &[ImageCandidate],
+  hash_cache: &ImageHashCache,
+) -> InfuResult<Vec<NearDuplicateCluster>> {
+  let entries = candidates
+    .iter()
+    .map(|candidate| {
+      hash_cache.images.get(&candidate.item_id).cloned().unwrap_or(ImageHashEntry { hash: None, captured_at: None })
+    })
+    .collect::<Vec<_>>();
+  let mut sets = DisjointSets::new(candidates.len());
+
+  // Any two hashes within NEAR_DUPLICATE_MAX_HASH_DISTANCE bits of each other share at least one
+  // byte at the same position, so only images sharing one need to be compared.
+  let hashes = entries
+    .iter()
+    .map(|entry| entry.hash.as_deref().and_then(|hash| u64::from_str_radix(hash, 16).ok()))
+    .collect::<Vec<_>>();
+  let mut buckets = HashMap::<(u32, u8), Vec<usize>>::new();
+  for (index, hash) in hashes.iter().enumerate() {
+    let Some(hash) = hash else { continue };
+    for (position, byte) in hash.to_be_bytes().into_iter().enumerate() {
+      buckets.entry((position as u32, byte)).or_default().push(index);
+    }
+  }
+  for bucket in buckets.values() {
+    for (i, &a) in bucket.iter().enumerate() {
+      for &b in &bucket[i + 1..] {
+        if (hashes[a].unwrap() ^ hashes[b].unwrap()).count_ones() <= NEAR_DUPLICATE_MAX_HASH_DISTANCE {
+          sets.union(a, b);
+        }
+      }
+    }
+  }
+
+  let capture_secs = entries
+    .iter()
+    .map(|entry| entry.captured_at.as_deref().and_then(capture_datetime_wall_clock_secs))
+    .collect::<Vec<_>>();
+  let mut by_capture_time = (0..candidates.len()).filter(|index| capture_secs[*index].is_some()).collect::<Vec<_>>();
+  by_capture_time.sort_by_key(|index| capture_secs[*index]);
+  let vector_db = open_user_image_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  let mut vectors = HashMap::<usize, Option<ImageVector>>::new();
+  for (i, &a) in by_capture_time.iter().enumerate() {
+    for &b in &by_capture_time[i + 1..] {
+      // the time zone is ignored: only the gap between shots from the same camera matters.
+      if capture_secs[b].unwrap() - capture_secs[a].unwrap() > BURST_MAX_CAPTURE_GAP_SECS {
+        break;
+      }
+      if sets.find(a) == sets.find(b) {
+        continue;
+      }
+      for index in [a, b] {
+        if let Entry::Vacant(entry) = vectors.entry(index) {
+          entry.insert(vector_db.item_vector(&candidates[index].item_id).await?);
+        }
+      }
+      if let (Some(Some(vector_a)), Some(Some(vector_b))) = (vectors.get(&a), vectors.get(&b))
+        && vector_a.model == vector_b.model
+        && cosine_distance(&vector_a.embedding, &vector_b.embedding) <= BURST_MAX_EMBEDDING_DISTANCE
+      {
+        sets.union(a, b);
+      }
+    }
+  }
+
+  let mut members_by_root = HashMap::<usize, Vec<usize>>::new();
+  for index in 0..candidates.len() {
+    members_by_root.entry(sets.find(index)).or_default().push(index);
+  }
+  let mut clusters = members_by_root
+    .into_values()
+    .filter(|members| members.len() > 1)
+    .map(|mut members| {
+      members.sort_by(|a, b| {
+        candidates[*b]
+          .pixels
+          .cmp(&candidates[*a].pixels)
+          .then_with(|| match (capture_secs[*a], capture_secs[*b]) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+          })
+          .then_with(|| candidates[*a].creation_date.cmp(&candidates[*b].creation_date))
+          .then_with(|| candidates[*a].item_id.cmp(&candidates[*b].item_id))
+      });
+      NearDuplicateCluster {
+        items: members
+          .into_iter()
+          .map(|index| NearDuplicateClusterItem {
+            item_id: candidates[index].item_id.clone(),
+            captured_at: entries[index].captured_at.clone(),
+          })
+          .collect(),
+      }
+    })
+    .collect::<Vec<_>>();
+  clusters.sort_by(|a, b| a.items[0].item_id.cmp(&b.items[0].item_id));
+  Ok(clusters)
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+  if a.len() != b.len() {
+    return f32::MAX;
+  }
+  let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+  let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+  let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+  if norm_a == 0.0 || norm_b == 0.0 {
+    return f32::MAX;
+  }
+  1.0 - dot / (norm_a * norm_b)
+}
+
+struct DisjointSets {
+  parents: Vec<usize>,
+}
+
+impl DisjointSets {
+  fn new(len: usize) -> DisjointSets {
+    DisjointSets { parents: (0..len).collect() }
+  }
+
+  fn find(&mut self, index: usize) -> usize {
+    let mut root = index;
+    while self.parents[root] != root {
+      root = self.parents[root];
+    }
+    let mut current = index;
+    while self.parents[current] != root {
+      let next = self.parents[current];
+      self.parents[current] = root;
+      current = next;
+    }
+    root
+  }
+
+  fn union(&mut self, a: usize, b: usize) {
+    let root_a = self.find(a);
+    let root_b = self.find(b);
+    if root_a != root_b {
+      self.parents[root_b.max(root_a)] = root_a.min(root_b);
+    }
+  }
+}
+
+fn image_hashes_path(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = user_index_dir(data_dir, user_id)?;
+  path.push(IMAGE_HASHES_FILENAME);
+  Ok(path)
+}
+
+fn near_duplicates_path(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = user_index_dir(data_dir, user_id)?;
+  path.push(NEAR_DUPLICATES_FILENAME);
+  Ok(path)
+}
+
+async fn read_image_hash_cache(data_dir: &str, user_id: &str) -> InfuResult<ImageHashCache> {
+  let cache: Option<ImageHashCache> =
+    read_json_if_exists(&image_hashes_path(data_dir, user_id)?, "image hash cache").await?;
+  Ok(
+    cache
+      .filter(|cache| cache.schema_version == IMAGE_HASHES_SCHEMA_VERSION)
+      .unwrap_or(ImageHashCache { schema_version: IMAGE_HASHES_SCHEMA_VERSION, images: HashMap::new() }),
+  )
+}
+
+async fn write_image_hash_cache(data_dir: &str, user_id: &str, cache: &ImageHashCache) -> InfuResult<()> {
+  write_json(&image_hashes_path(data_dir, user_id)?, cache, "image hash cache").await
+}
+
+async fn read_json_if_exists<T: for<'de> Deserialize<'de>>(path: &PathBuf, label: &str) -> InfuResult<Option<T>> {
+  let bytes = match fs::read(path).await {
+    Ok(bytes) => bytes,
+    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(e) => return Err(format!("Could not read {} '{}': {}", label, path.display(), e).into()),
+  };
+  serde_json::from_slice(&bytes)
+    .map(Some)
+    .map_err(|e| format!("Could not parse {} '{}': {}", label, path.display(), e).into())
+}
+
+async fn write_json<T: Serialize>(path: &PathBuf, value: &T, label: &str) -> InfuResult<()> {
+  let temp_path = path.with_extension("json.tmp");
+  let bytes = serde_json::to_vec(value).map_err(|e| format!("Could not serialize {}: {}", label, e))?;
+  fs::write(&temp_path, bytes).await?;
+  fs::rename(&temp_path, path)
+    .await
+    .map_err(|e| format!("Could not replace {} '{}': {}", label, path.display(), e).into())
+}
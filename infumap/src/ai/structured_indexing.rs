@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+use infusdk::item::Item;
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::Uid;
+use log::debug;
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+use crate::ai::fragment::FragmentSource;
+use crate::ai::fragment::sources::{
+  build_structured_fragment_artifact, is_structured_fragment_item, structured_fragment_source_for_item,
+};
+use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
+use crate::ai::user_id_for_log;
+use crate::storage::db::Db;
+
+/// Items changed since the last reconciliation, by user.
+static CHANGED_ITEM_IDS_BY_USER: Lazy<std::sync::Mutex<HashMap<String, HashSet<Uid>>>> =
+  Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+/// Users whose tables and composites have all been reconciled since startup. Later reconciliations only
+/// look at the tables and composites around changed items.
+static FULLY_RECONCILED_USER_IDS: Lazy<std::sync::Mutex<HashSet<String>>> =
+  Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+/// Note items whose change may alter the text of a table or composite: the changed item and its parent
+/// before and after the change (just the former parent, for a deleted item). They are picked up by the
+/// next reconciliation.
+pub fn record_structured_item_changes<'a>(user_id: &str, item_ids: impl IntoIterator<Item = &'a Uid>) {
+  let mut changed = CHANGED_ITEM_IDS_BY_USER.lock().unwrap();
+  changed.entry(user_id.to_owned()).or_default().extend(item_ids.into_iter().cloned());
+}
+
+/// Bring the fragments of a user's tables and composites in line with their current children. This runs
+/// after each item title index reconciliation, since the same item edits invalidate both. The first run
+/// for a user after startup checks every table and composite; later runs only those containing, or
+/// directly inside, items changed since. Fragment artifacts are only rewritten when their text changed,
+/// and the document fragment index is rebuilt only if any were.
+pub async fn reconcile_user_structured_item_fragments(
+  data_dir: &str,
+  db: Arc<Mutex<Db>>,
+  user_id: &str,
+) -> InfuResult<()> {
+  let reconcile_started = Instant::now();
+  let full_reconcile = !FULLY_RECONCILED_USER_IDS.lock().unwrap().contains(user_id);
+  let changed_item_ids = CHANGED_ITEM_IDS_BY_USER.lock().unwrap().remove(user_id).unwrap_or_default();
+  if !full_reconcile && changed_item_ids.is_empty() {
+    return Ok(());
+  }
+
+  let changed_item_ids_maybe = if full_reconcile { None } else { Some(&changed_item_ids) };
+  let (item_count, changed_count) =
+    match reconcile_structured_item_fragments(data_dir, db, user_id, changed_item_ids_maybe).await {
+      Ok(counts) => counts,
+      Err(e) => {
+        record_structured_item_changes(user_id, &changed_item_ids);
+        return Err(e);
+      }
+    };
+  if full_reconcile {
+    FULLY_RECONCILED_USER_IDS.lock().unwrap().insert(user_id.to_owned());
+  }
+
+  debug!(
+    "User {} structured item fragment reconciliation checked {} table/composite item(s), {} changed, in {:.3}s.",
+    user_id_for_log(user_id),
+    item_count,
+    changed_count,
+    reconcile_started.elapsed().as_secs_f64()
+  );
+  if changed_count > 0 {
+    enqueue_fragment_index_rebuild_for_user(user_id);
+  }
+  Ok(())
+}
+
+/// Rebuild the fragments of the user's tables and composites, or only those around `changed_item_ids`.
+/// Returns the number of items checked and the number whose fragments changed.
+async fn reconcile_structured_item_fragments(
+  data_dir: &str,
+  db: Arc<Mutex<Db>>,
+  user_id: &str,
+  changed_item_ids: Option<&HashSet<Uid>>,
+) -> InfuResult<(usize, usize)> {
+  let sources = {
+    let db = db.lock().await;
+    match changed_item_ids {
+      Some(changed_item_ids) => collect_changed_structured_fragment_sources(&db, user_id, changed_item_ids)?,
+      None => collect_user_structured_fragment_sources(&db, user_id)?,
+    }
+  };
+
+  let item_count = sources.len();
+  let mut changed_count = 0;
+  for (item, fragment_source) in sources {
+    let outcome = build_structured_fragment_artifact(data_dir, &item, fragment_source).await?;
+    if outcome.wrote_fragments || outcome.cleared_existing_fragments {
+      changed_count += 1;
+    }
+  }
+  Ok((item_count, changed_count))
+}
+
+fn collect_user_structured_fragment_sources(db: &Db, user_id: &str) -> InfuResult<Vec<(Item, Option<FragmentSource>)>> {
+  let mut item_ids = db
+    .item
+    .all_loaded_items()
+    .into_iter()
+    .filter(|item_key| item_key.user_id.as_str() == user_id)
+    .map(|item_key| item_key.item_id)
+    .collect::<Vec<_>>();
+  item_ids.sort();
+
+  let mut sources = Vec::new();
+  for item_id in item_ids {
+    let item = db.item.get(&item_id).map_err(|e| e.to_string())?;
+    if !is_structured_fragment_item(item) {
+      continue;
+    }
+    sources.push((item.clone(), structured_fragment_source_for_item(db, item)?));
+  }
+  Ok(sources)
+}
+
+/// The tables and composites whose text may depend on the changed items: each item's structured ancestors
+/// (a cell attachment changes its row's table, a composite child its composite and any table holding it)
+/// and the structured items directly inside it (whose text includes the title of their parent).
+fn collect_changed_structured_fragment_sources(
+  db: &Db,
+  user_id: &str,
+  changed_item_ids: &HashSet<Uid>,
+) -> InfuResult<Vec<(Item, Option<FragmentSource>)>> {
+  let mut item_ids = HashSet::new();
+  for changed_item_id in changed_item_ids {
+    let Ok(changed_item) = db.item.get(changed_item_id) else {
+      continue;
+    };
+    for child in db.item.get_children(changed_item_id)? {
+      if is_structured_fragment_item(child) {
+        item_ids.insert(child.id.clone());
+      }
+    }
+    let mut ancestor_maybe = Some(changed_item);
+    while let Some(ancestor) = ancestor_maybe {
+      if is_structured_fragment_item(ancestor) {
+        item_ids.insert(ancestor.id.clone());
+      }
+      ancestor_maybe = ancestor.parent_id.as_ref().and_then(|parent_id| db.item.get(parent_id).ok());
+    }
+  }
+  let mut item_ids = item_ids.into_iter().collect::<Vec<_>>();
+  item_ids.sort();
+
+  let mut sources = Vec::new();
+  for item_id in item_ids {
+    let item = db.item.get(&item_id).map_err(|e| e.to_string())?;
+    if item.owner_id != user_id {
+      continue;
+    }
+    sources.push((item.clone(), structured_fragment_source_for_item(db, item)?));
+  }
+  Ok(sources)
+}
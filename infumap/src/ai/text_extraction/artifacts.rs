@@ -27,7 +27,9 @@ use crate::ai::user_id_for_log;
 use crate::storage::db::Db;
 use crate::util::fs::path_exists;
 
-use super::{PDF_PASSWORD_REQUIRED_ERROR_CODE, PDF_SOURCE_MIME_TYPE, PdfCandidate, PdfToMdResponse};
+use super::{
+  PDF_NO_TEXT_LAYER_ERROR_CODE, PDF_PASSWORD_REQUIRED_ERROR_CODE, PDF_SOURCE_MIME_TYPE, PdfCandidate, PdfToMdResponse,
+};
 
 const MANIFEST_SCHEMA_VERSION: u32 = 1;
 const MARKDOWN_CONTENT_MIME_TYPE: &str = "text/markdown";
@@ -182,14 +184,20 @@ pub async fn item_needs_text_extraction(data_dir: &str, db: Arc<Mutex<Db>>, item
     }
     PdfCandidate::from_item(item)
   };
-  Ok(matches!(manifest_check(data_dir, &candidate).await?, ManifestCheckResult::NeedsExtraction))
+  Ok(matches!(manifest_check(data_dir, &candidate, true).await?, ManifestCheckResult::NeedsExtraction))
 }
 
 pub async fn delete_item_text_dir(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<()> {
   clear_item_text_dir(data_dir, user_id, item_id).await
 }
 
-pub(super) async fn manifest_check(data_dir: &str, candidate: &PdfCandidate) -> InfuResult<ManifestCheckResult> {
+/// `retry_no_text_layer` re-queues PDFs that failed only because they had no embedded text layer,
+/// which is appropriate once an OCR capable extractor is available.
+pub(super) async fn manifest_check(
+  data_dir: &str,
+  candidate: &PdfCandidate,
+  retry_no_text_layer: bool,
+) -> InfuResult<ManifestCheckResult> {
   let manifest_path = item_text_manifest_path(data_dir, &candidate.user_id, &candidate.item_id)?;
   let text_path = item_text_content_path(data_dir, &candidate.user_id, &candidate.item_id)?;
 
@@ -218,6 +226,9 @@ pub(super) async fn manifest_check(data_dir: &str, candidate: &PdfCandidate) ->
   }
 
   if manifest.status == "failed" {
+    if retry_no_text_layer && manifest.error_code.as_deref() == Some(PDF_NO_TEXT_LAYER_ERROR_CODE) {
+      return Ok(ManifestCheckResult::NeedsExtraction);
+    }
     return Ok(ManifestCheckResult::AlreadyFailed);
   }
 
@@ -277,6 +288,23 @@ pub(super) async fn write_password_required_manifest(
   .await
 }
 
+pub(super) async fn write_no_text_layer_manifest(
+  data_dir: &str,
+  text_extraction_url: &str,
+  candidate: &PdfCandidate,
+  error_message: &str,
+) -> InfuResult<()> {
+  write_terminal_manifest(
+    data_dir,
+    text_extraction_url,
+    candidate,
+    "failed",
+    Some(PDF_NO_TEXT_LAYER_ERROR_CODE),
+    error_message,
+  )
+  .await
+}
+
 async fn write_terminal_manifest(
   data_dir: &str,
   text_extraction_url: &str,
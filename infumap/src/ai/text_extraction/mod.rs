@@ -43,6 +43,7 @@ use crate::storage::object::{self as storage_object, ObjectStore};
 use crate::util::retry::endpoint_retry_delay;
 
 mod artifacts;
+mod text_layer;
 
 #[allow(unused_imports)]
 pub use artifacts::FailedPdfInfo;
@@ -51,9 +52,10 @@ pub use artifacts::{
 };
 
 use self::artifacts::{
-  ManifestCheckResult, clear_item_text_dir, manifest_check, write_failed_manifest, write_password_required_manifest,
-  write_success_artifacts,
+  ManifestCheckResult, clear_item_text_dir, manifest_check, write_failed_manifest, write_no_text_layer_manifest,
+  write_password_required_manifest, write_success_artifacts,
 };
+use self::text_layer::extract_pdf_text_layer;
 
 const IDLE_POLL_SECS: u64 = 60;
 const REQUEST_TIMEOUT_SECS: u64 = 4 * 60 * 60;
@@ -63,7 +65,9 @@ const LARGE_PDF_SIZE_BYTES: i64 = 25 * 1024 * 1024;
 const EMPTY_QUEUE_WAIT_MILLIS: u64 = 1000;
 const PDF_SOURCE_MIME_TYPE: &str = "application/pdf";
 pub(super) const PDF_PASSWORD_REQUIRED_ERROR_CODE: &str = "pdf_password_required";
+pub(super) const PDF_NO_TEXT_LAYER_ERROR_CODE: &str = "pdf_no_text_layer";
 const CLI_FAILED_MANIFEST_EXTRACTOR_URL: &str = "manual://extract-cli";
+const PDF_TEXT_LAYER_EXTRACTOR_URL: &str = "local://pdf-text-layer";
 
 static PROCESSING_STATE: OnceCell<Arc<Mutex<ProcessingState>>> = OnceCell::new();
 
@@ -100,6 +104,7 @@ pub(crate) struct LoadedPdfExtraction {
 struct ProcessingState {
   queue: Vec<PdfCandidate>,
   queued_item_ids: HashSet<String>,
+  ocr_available: bool,
 }
 
 #[derive(Deserialize)]
@@ -154,6 +159,16 @@ struct PdfTextExtractionEndpoint {
   async_jobs_available: bool,
 }
 
+impl PdfTextExtractionEndpoint {
+  fn text_layer_only() -> PdfTextExtractionEndpoint {
+    PdfTextExtractionEndpoint { extract_url: PDF_TEXT_LAYER_EXTRACTOR_URL.to_owned(), async_jobs_available: false }
+  }
+
+  fn ocr_available(&self) -> bool {
+    self.extract_url != PDF_TEXT_LAYER_EXTRACTOR_URL
+  }
+}
+
 struct ExtractionProgress {
   processed: u64,
   succeeded: u64,
@@ -366,6 +381,11 @@ pub(crate) async fn process_loaded_pdf_extraction_web_background(
 ) -> InfuResult<PdfTextExtractionProcessOutcome> {
   let LoadedPdfExtraction { candidate, file_bytes } = loaded;
   clear_item_text_dir(data_dir, &candidate.user_id, &candidate.item_id).await?;
+  if let Some(outcome) =
+    process_pdf_text_layer(data_dir, text_extraction_url, db.clone(), &candidate, &file_bytes).await?
+  {
+    return Ok(outcome);
+  }
   let client = reqwest::ClientBuilder::new()
     .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
     .build()
@@ -407,6 +427,74 @@ pub(crate) async fn process_loaded_pdf_extraction_web_background(
   }
 }
 
+/// Fast first pass using the embedded text layer. Returns None when the document should go on to
+/// the OCR endpoint, which only happens when one is available.
+async fn process_pdf_text_layer(
+  data_dir: &str,
+  text_extraction_url: &str,
+  db: Arc<Mutex<Db>>,
+  candidate: &PdfCandidate,
+  file_bytes: &[u8],
+) -> InfuResult<Option<PdfTextExtractionProcessOutcome>> {
+  let ocr_available = text_extraction_url != PDF_TEXT_LAYER_EXTRACTOR_URL;
+  let text_layer = match extract_pdf_text_layer(file_bytes.to_vec()).await {
+    Ok(text_layer) => Some(text_layer),
+    Err(e) => {
+      debug!(
+        "PDF '{}' (user {}): text layer extraction failed: {}",
+        candidate.item_id,
+        user_id_for_log(&candidate.user_id),
+        e
+      );
+      if ocr_available {
+        return Ok(None);
+      }
+      None
+    }
+  };
+  let text_layer = match text_layer {
+    Some(text_layer) if text_layer.is_complete() || (!ocr_available && text_layer.has_text()) => text_layer,
+    Some(text_layer) if ocr_available => {
+      debug!(
+        "PDF '{}' (user {}): text layer covers {} of {} page(s); falling back to OCR.",
+        candidate.item_id,
+        user_id_for_log(&candidate.user_id),
+        text_layer.text_page_count,
+        text_layer.page_count
+      );
+      return Ok(None);
+    }
+    _ => {
+      if !candidate_still_current(db, candidate).await? {
+        return Err(
+          format!("Item '{}' was deleted or replaced while extraction was in progress.", candidate.item_id).into(),
+        );
+      }
+      let msg = "PDF has no usable embedded text layer and no OCR text extraction endpoint is available.";
+      write_no_text_layer_manifest(data_dir, PDF_TEXT_LAYER_EXTRACTOR_URL, candidate, msg).await?;
+      enqueue_pdf_fragment_ids_if_active(&candidate.user_id, &candidate.item_id);
+      return Err(format!("PDF text extraction failed for '{}': {}", candidate.item_id, msg).into());
+    }
+  };
+
+  if !candidate_still_current(db, candidate).await? {
+    return Err(
+      format!("Item '{}' was deleted or replaced while extraction was in progress.", candidate.item_id).into(),
+    );
+  }
+  debug!(
+    "Extracted text layer for PDF '{}' (user {}): {} of {} page(s) with text.",
+    candidate.item_id,
+    user_id_for_log(&candidate.user_id),
+    text_layer.text_page_count,
+    text_layer.page_count
+  );
+  let response = PdfToMdResponse { success: true, markdown: text_layer.markdown, duration_ms: text_layer.duration_ms };
+  write_success_artifacts(data_dir, PDF_TEXT_LAYER_EXTRACTOR_URL, candidate, response).await?;
+  enqueue_pdf_fragment_ids_if_active(&candidate.user_id, &candidate.item_id);
+  Ok(Some(PdfTextExtractionProcessOutcome::Extracted))
+}
+
 pub async fn mark_item_text_extraction_failed(
   data_dir: &str,
   db: Arc<Mutex<Db>>,
@@ -441,20 +529,20 @@ pub fn init_text_extraction_processing_loop(
   db: Arc<Mutex<Db>>,
   object_store: Arc<ObjectStore>,
 ) -> InfuResult<()> {
-  let gpu_tools_url = match gpu_tools_url_from_config(config)? {
-    Some(url) => url,
-    None => {
-      info!("PDF text extraction disabled: '{}' is not configured.", CONFIG_GPU_TOOLS_URL);
-      return Ok(());
-    }
-  };
+  let gpu_tools_url = gpu_tools_url_from_config(config)?;
+  if gpu_tools_url.is_none() {
+    info!(
+      "PDF OCR text extraction disabled: '{}' is not configured; using embedded PDF text layers only.",
+      CONFIG_GPU_TOOLS_URL
+    );
+  }
   let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
   start_text_extraction_processing_loop(data_dir, gpu_tools_url, Duration::ZERO, db, object_store)
 }
 
 pub fn start_text_extraction_processing_loop(
   data_dir: String,
-  gpu_tools_url: String,
+  gpu_tools_url: Option<String>,
   request_delay: Duration,
   db: Arc<Mutex<Db>>,
   object_store: Arc<ObjectStore>,
@@ -463,20 +551,21 @@ pub fn start_text_extraction_processing_loop(
     enqueue_all_loaded_pdfs(data_dir, db, state.clone());
     return Ok(());
   }
-  let state = Arc::new(Mutex::new(ProcessingState { queue: vec![], queued_item_ids: HashSet::new() }));
+  let state =
+    Arc::new(Mutex::new(ProcessingState { queue: vec![], queued_item_ids: HashSet::new(), ocr_available: false }));
   PROCESSING_STATE
     .set(state.clone())
     .map_err(|_| "Text extraction processing loop is already running in this process.".to_owned())?;
   let progress = Arc::new(Mutex::new(ExtractionProgress { processed: 0, succeeded: 0, blocked: 0, other_failed: 0 }));
 
+  let source = match gpu_tools_url.as_deref() {
+    Some(url) => format!("GPU tools URL '{}'", url),
+    None => "embedded PDF text layers".to_owned(),
+  };
   if request_delay.is_zero() {
-    info!("Starting PDF text extraction loop from GPU tools URL '{}'.", gpu_tools_url);
+    info!("Starting PDF text extraction loop from {}.", source);
   } else {
-    info!(
-      "Starting PDF text extraction loop from GPU tools URL '{}' (delay {:.3}s).",
-      gpu_tools_url,
-      request_delay.as_secs_f64()
-    );
+    info!("Starting PDF text extraction loop from {} (delay {:.3}s).", source, request_delay.as_secs_f64());
   }
   let _worker = task::spawn(async move {
     run_text_extraction_loop(data_dir, gpu_tools_url, request_delay, db, object_store, state, progress).await;
@@ -501,7 +590,7 @@ async fn discover_pdf_text_extraction_endpoint(gpu_tools_url: &str) -> InfuResul
 
 async fn run_text_extraction_loop(
   data_dir: String,
-  gpu_tools_url: String,
+  gpu_tools_url: Option<String>,
   request_delay: Duration,
   db: Arc<Mutex<Db>>,
   object_store: Arc<ObjectStore>,
@@ -509,14 +598,17 @@ async fn run_text_extraction_loop(
   progress: Arc<Mutex<ExtractionProgress>>,
 ) {
   let endpoint = loop {
-    match discover_pdf_text_extraction_endpoint(&gpu_tools_url).await {
+    let Some(gpu_tools_url) = gpu_tools_url.as_deref() else {
+      break PdfTextExtractionEndpoint::text_layer_only();
+    };
+    match discover_pdf_text_extraction_endpoint(gpu_tools_url).await {
       Ok(Some(endpoint)) => break endpoint,
       Ok(None) => {
         info!(
-          "PDF text extraction disabled: GPU tools URL '{}' does not report '{}'.",
+          "PDF OCR text extraction disabled: GPU tools URL '{}' does not report '{}'; using embedded PDF text layers only.",
           gpu_tools_url, GPU_TOOL_PDF_EXTRACT
         );
-        return;
+        break PdfTextExtractionEndpoint::text_layer_only();
       }
       Err(e) => {
         error!(
@@ -529,11 +621,14 @@ async fn run_text_extraction_loop(
       }
     }
   };
-  info!(
-    "PDF text extraction endpoint discovered: '{}' (async_jobs={}).",
-    endpoint.extract_url,
-    on_off(endpoint.async_jobs_available)
-  );
+  if endpoint.ocr_available() {
+    info!(
+      "PDF text extraction endpoint discovered: '{}' (async_jobs={}).",
+      endpoint.extract_url,
+      on_off(endpoint.async_jobs_available)
+    );
+  }
+  state.lock().await.ocr_available = endpoint.ocr_available();
 
   populate_initial_pdf_queue(&data_dir, db.clone(), state.clone()).await;
 
@@ -654,9 +749,7 @@ fn spawn_pdf_prefetch(
   state: Arc<Mutex<ProcessingState>>,
   progress: Arc<Mutex<ExtractionProgress>>,
 ) -> task::JoinHandle<(LoadedPdfExtraction, usize)> {
-  task::spawn(async move {
-    prefetch_next_pdf_extraction(data_dir, endpoint.extract_url, db, object_store, state, progress).await
-  })
+  task::spawn(async move { prefetch_next_pdf_extraction(data_dir, endpoint, db, object_store, state, progress).await })
 }
 
 fn spawn_pdf_process(
@@ -739,7 +832,7 @@ async fn advance_pdf_prefetch_to_process(
 
 async fn prefetch_next_pdf_extraction(
   data_dir: String,
-  text_extraction_url: String,
+  endpoint: PdfTextExtractionEndpoint,
   db: Arc<Mutex<Db>>,
   object_store: Arc<ObjectStore>,
   state: Arc<Mutex<ProcessingState>>,
@@ -748,7 +841,7 @@ async fn prefetch_next_pdf_extraction(
   loop {
     let (candidate, queue_remaining) = wait_for_next_pdf_candidate(state.clone()).await;
     wait_for_object_store_upload_quiet_period("PDF text extraction prefetch").await;
-    match manifest_check(&data_dir, &candidate).await {
+    match manifest_check(&data_dir, &candidate, endpoint.ocr_available()).await {
       Ok(ManifestCheckResult::NeedsExtraction) => {}
       Ok(ManifestCheckResult::AlreadySucceeded) => {
         record_pdf_text_extraction_processed("skipped");
@@ -799,8 +892,14 @@ async fn prefetch_next_pdf_extraction(
       );
     }
 
-    match load_pdf_for_extraction(&data_dir, &text_extraction_url, db.clone(), object_store.clone(), &candidate.item_id)
-      .await
+    match load_pdf_for_extraction(
+      &data_dir,
+      &endpoint.extract_url,
+      db.clone(),
+      object_store.clone(),
+      &candidate.item_id,
+    )
+    .await
     {
       Ok(loaded) => return (loaded, queue_remaining),
       Err(e) => {
@@ -1159,6 +1258,7 @@ async fn populate_initial_pdf_queue(data_dir: &str, db: Arc<Mutex<Db>>, state: A
     candidates
   };
 
+  let ocr_available = state.lock().await.ocr_available;
   let total_candidates = candidates.len();
   let mut pending_candidates = vec![];
   let mut already_succeeded = 0usize;
@@ -1167,7 +1267,7 @@ async fn populate_initial_pdf_queue(data_dir: &str, db: Arc<Mutex<Db>>, state: A
   let mut skipped_errors = 0usize;
 
   for candidate in candidates {
-    match manifest_check(data_dir, &candidate).await {
+    match manifest_check(data_dir, &candidate, ocr_available).await {
       Ok(ManifestCheckResult::NeedsExtraction) => pending_candidates.push(candidate),
       Ok(ManifestCheckResult::AlreadySucceeded) => already_succeeded += 1,
       Ok(ManifestCheckResult::AlreadyFailed) => already_failed += 1,
@@ -0,0 +1,115 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use infusdk::util::infu::InfuResult;
+use std::time::Instant;
+use tokio::task;
+
+/// Pages with fewer meaningful characters than this are treated as image-only (scanned) pages.
+const MIN_PAGE_TEXT_CHARS: usize = 40;
+/// Pages where more than this fraction of characters are replacement or control characters are
+/// treated as having a broken text layer (e.g. fonts without a usable ToUnicode map).
+const MAX_PAGE_JUNK_RATIO: f64 = 0.1;
+/// Fraction of pages that must have a usable text layer for the result to stand in for OCR.
+const MIN_COMPLETE_PAGE_RATIO: f64 = 0.75;
+const PAGE_BREAK_DASH_COUNT: usize = 48;
+
+pub(super) struct PdfTextLayer {
+  pub markdown: String,
+  pub page_count: usize,
+  pub text_page_count: usize,
+  pub duration_ms: u64,
+}
+
+impl PdfTextLayer {
+  /// True when the embedded text layer covers enough of the document that OCR would add little.
+  pub fn is_complete(&self) -> bool {
+    self.page_count > 0 && self.text_page_count as f64 >= self.page_count as f64 * MIN_COMPLETE_PAGE_RATIO
+  }
+
+  pub fn has_text(&self) -> bool {
+    self.text_page_count > 0
+  }
+}
+
+/// Read the embedded text layer of a born-digital PDF, without OCR.
+///
+/// The output uses the same zero-based `{N}------` page-break markers as the GPU pdf_extract tool,
+/// so downstream page splitting treats both sources identically.
+pub(super) async fn extract_pdf_text_layer(file_bytes: Vec<u8>) -> InfuResult<PdfTextLayer> {
+  let started_at = Instant::now();
+  // pdf-extract panics on some malformed documents, so keep it off the async workers and treat a
+  // panic like any other extraction failure.
+  let pages = task::spawn_blocking(move || pdf_extract::extract_text_from_mem_by_pages(&file_bytes))
+    .await
+    .map_err(|e| format!("PDF text layer extraction task failed: {}", e))?
+    .map_err(|e| format!("Could not read PDF text layer: {}", e))?;
+
+  let mut markdown = String::new();
+  let mut text_page_count = 0;
+  for (page_index, page_text) in pages.iter().enumerate() {
+    let page_text = normalize_page_text(page_text);
+    let page_has_text = page_text_is_usable(&page_text);
+    if page_has_text {
+      text_page_count += 1;
+    }
+    if page_index > 0 {
+      markdown.push_str("\n\n");
+    }
+    markdown.push_str(&format!("{{{}}}{}\n\n", page_index, "-".repeat(PAGE_BREAK_DASH_COUNT)));
+    if page_has_text {
+      markdown.push_str(&page_text);
+    }
+  }
+
+  Ok(PdfTextLayer {
+    markdown,
+    page_count: pages.len(),
+    text_page_count,
+    duration_ms: started_at.elapsed().as_millis() as u64,
+  })
+}
+
+fn normalize_page_text(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut blank_run = 0;
+  for line in text.replace("\r\n", "\n").replace('\r', "\n").lines() {
+    let line = line.trim_end();
+    if line.trim().is_empty() {
+      blank_run += 1;
+      continue;
+    }
+    if !out.is_empty() {
+      out.push_str(if blank_run > 0 { "\n\n" } else { "\n" });
+    }
+    out.push_str(line);
+    blank_run = 0;
+  }
+  out
+}
+
+fn page_text_is_usable(text: &str) -> bool {
+  let mut meaningful = 0usize;
+  let mut junk = 0usize;
+  for ch in text.chars().filter(|ch| !ch.is_whitespace()) {
+    if ch == char::REPLACEMENT_CHARACTER || ch.is_control() {
+      junk += 1;
+    } else {
+      meaningful += 1;
+    }
+  }
+  meaningful >= MIN_PAGE_TEXT_CHARS && (junk as f64) <= (meaningful + junk) as f64 * MAX_PAGE_JUNK_RATIO
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use infusdk::util::infu::InfuResult;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use time::{Date, Duration, Month, OffsetDateTime};
+
+use crate::ai::image_tag_cache::ImageTagValueCache;
+use crate::util::image::capture_datetime_wall_clock_secs;
+
+/// Image capture times by item id, read from image-tag output.
+static IMAGE_CAPTURE_TIMES: Lazy<ImageTagValueCache<HashMap<String, Option<i64>>>> =
+  Lazy::new(|| ImageTagValueCache::new("capture times"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelineBucketSize {
+  Day,
+  /// Weeks start on Monday.
+  Week,
+  Month,
+  Year,
+}
+
+impl TimelineBucketSize {
+  pub fn from_str(value: &str) -> InfuResult<TimelineBucketSize> {
+    match value {
+      "day" => Ok(TimelineBucketSize::Day),
+      "week" => Ok(TimelineBucketSize::Week),
+      "month" => Ok(TimelineBucketSize::Month),
+      "year" => Ok(TimelineBucketSize::Year),
+      other => Err(format!("Unknown timeline bucket size '{}', expected day, week, month or year.", other).into()),
+    }
+  }
+
+  /// The first day of the bucket containing the date.
+  pub fn bucket_start(&self, date: Date) -> Date {
+    match self {
+      TimelineBucketSize::Day => date,
+      TimelineBucketSize::Week => date - Duration::days(date.weekday().number_days_from_monday() as i64),
+      TimelineBucketSize::Month => date.replace_day(1).unwrap(),
+      TimelineBucketSize::Year => Date::from_calendar_date(date.year(), Month::January, 1).unwrap(),
+    }
+  }
+
+  /// The last day of the bucket starting on `bucket_start`.
+  pub fn bucket_end(&self, bucket_start: Date) -> Date {
+    match self {
+      TimelineBucketSize::Day => bucket_start,
+      TimelineBucketSize::Week => bucket_start + Duration::days(6),
+      TimelineBucketSize::Month => {
+        bucket_start.replace_day(time::util::days_in_month(bucket_start.month(), bucket_start.year())).unwrap()
+      }
+      TimelineBucketSize::Year => Date::from_calendar_date(bucket_start.year(), Month::December, 31).unwrap(),
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct StoredImageTagArtifact {
+  image_metadata: Option<StoredImageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct StoredImageMetadata {
+  captured_at: Option<String>,
+}
+
+/// Parse a date of the form "YYYY-MM-DD".
+pub fn parse_timeline_date(value: &str) -> InfuResult<Date> {
+  let invalid = || format!("Invalid date '{}', expected YYYY-MM-DD.", value);
+  let mut parts = value.trim().split('-');
+  let (Some(year), Some(month), Some(day), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+    return Err(invalid().into());
+  };
+  let year = year.parse::<i32>().map_err(|_| invalid())?;
+  let month = month.parse::<u8>().ok().and_then(|month| Month::try_from(month).ok()).ok_or_else(invalid)?;
+  let day = day.parse::<u8>().map_err(|_| invalid())?;
+  Date::from_calendar_date(year, month, day).map_err(|_| invalid().into())
+}
+
+pub fn format_timeline_date(date: Date) -> String {
+  format!("{:04}-{:02}-{:02}", date.year(), date.month() as u8, date.day())
+}
+
+/// The calendar date of a time in seconds since the epoch, `utc_offset_secs` east of UTC.
+pub fn local_date_for_unix_secs(unix_secs: i64, utc_offset_secs: i64) -> Option<Date> {
+  OffsetDateTime::from_unix_timestamp(unix_secs.checked_add(utc_offset_secs)?).ok().map(|datetime| datetime.date())
+}
+
+/// Capture times of the given image items, as seconds since the epoch of the camera's wall clock time
+/// taken as UTC. Images without a capture time, including those not tagged yet, are left out.
+pub async fn image_capture_times(
+  data_dir: &str,
+  user_id: &str,
+  image_item_ids: &[String],
+) -> InfuResult<HashMap<String, i64>> {
+  IMAGE_CAPTURE_TIMES.resolve(data_dir, user_id, image_item_ids, parse_image_tag_capture_time).await?;
+  Ok(IMAGE_CAPTURE_TIMES.with(user_id, |capture_times| {
+    image_item_ids
+      .iter()
+      .filter_map(|item_id| capture_times.get(item_id).copied().flatten().map(|secs| (item_id.clone(), secs)))
+      .collect()
+  }))
+}
+
+/// Forget the capture time read for an image, after it is tagged again or deleted.
+pub fn forget_image_capture_time(user_id: &str, item_id: &str) {
+  IMAGE_CAPTURE_TIMES.forget(user_id, item_id);
+}
+
+fn parse_image_tag_capture_time(bytes: &[u8]) -> InfuResult<Option<i64>> {
+  let artifact = serde_json::from_slice::<StoredImageTagArtifact>(bytes)
+    .map_err(|e| format!("Could not parse image tag output JSON while looking for a capture time: {}", e))?;
+  let Some(captured_at) = artifact.image_metadata.and_then(|metadata| metadata.captured_at) else {
+    return Ok(None);
+  };
+  Ok(capture_datetime_wall_clock_secs(&captured_at))
+}
@@ -16,6 +16,7 @@ use crate::ai::lexical_index::{
   open_user_item_title_lexical_index, remove_item_title_lexical_index_dirs,
 };
 use crate::ai::metrics::{METRIC_AI_TITLE_INDEX_REBUILD_DURATION_SECONDS, METRIC_AI_TITLE_INDEX_REBUILDS_TOTAL};
+use crate::ai::structured_indexing::reconcile_user_structured_item_fragments;
 use crate::ai::upload_quiet_period::wait_for_object_store_upload_quiet_period;
 use crate::ai::user_id_for_log;
 use crate::ai::vector_db::ensure_user_index_dir;
@@ -148,6 +149,9 @@ async fn run_item_title_indexing_loop(
       if let Err(e) = reconcile_user_item_title_lexical_index(&data_dir, db.clone(), &user_id).await {
         error!("Item title lexical index reconciliation failed for user '{}': {}", user_id_for_log(&user_id), e);
       }
+      if let Err(e) = reconcile_user_structured_item_fragments(&data_dir, db.clone(), &user_id).await {
+        error!("Structured item fragment reconciliation failed for user '{}': {}", user_id_for_log(&user_id), e);
+      }
     }
   }
 }
@@ -296,6 +300,9 @@ fn lexical_fragment_from_item_title_fragment(fragment: ItemTitleFragment) -> Lex
     text: fragment.text,
     page_start: None,
     page_end: None,
+    row_item_id: None,
+    time_start_secs: None,
+    time_end_secs: None,
   }
 }
 
@@ -12,10 +12,12 @@ use tokio::fs;
 use crate::util::fs::{expand_tilde, path_exists};
 
 pub mod sqlite_vec;
+pub mod sqlite_vec_images;
 
 pub const USER_INDEX_DIR_NAME: &str = "indexes";
 pub const FRAGMENT_VECTOR_DB_FILENAME: &str = "fragments.sqlite3";
 pub const FRAGMENT_VECTOR_DB_TEMP_FILENAME: &str = "fragments.sqlite3.tmp";
+pub const IMAGE_VECTOR_DB_FILENAME: &str = "images.sqlite3";
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct EmbeddedFragment {
@@ -25,6 +27,8 @@ pub struct EmbeddedFragment {
   pub text: String,
   pub page_start: Option<usize>,
   pub page_end: Option<usize>,
+  pub time_start_secs: Option<usize>,
+  pub time_end_secs: Option<usize>,
   pub embedding: Vec<f32>,
 }
 
@@ -37,6 +41,8 @@ pub struct FragmentVectorHit {
   pub text: String,
   pub page_start: Option<usize>,
   pub page_end: Option<usize>,
+  pub time_start_secs: Option<usize>,
+  pub time_end_secs: Option<usize>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -56,6 +62,7 @@ pub struct FragmentVectorDbRebuildMetadata {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FragmentVectorDbRebuildStatus {
+  pub schema_version: i64,
   pub source_digest: String,
   pub expected_fragment_count: usize,
   pub model: String,
@@ -77,6 +84,8 @@ pub trait FragmentVectorDb: Send + Sync {
 
   async fn embedded_fragment_keys(&self) -> InfuResult<HashSet<FragmentVectorDbFragmentKey>>;
 
+  async fn embedded_fragment_keys_for_item(&self, item_id: &str) -> InfuResult<HashSet<FragmentVectorDbFragmentKey>>;
+
   async fn embedded_fragments_for_keys(
     &self,
     keys: &HashSet<FragmentVectorDbFragmentKey>,
@@ -94,6 +103,38 @@ pub trait FragmentVectorDb: Send + Sync {
   async fn search(&self, query_embedding: &[f32], limit: usize) -> InfuResult<Vec<FragmentVectorHit>>;
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageVector {
+  pub item_id: String,
+  /// The image embedding model. Only vectors of the same model are compared.
+  pub model: String,
+  /// Changes whenever the image is re-tagged, so that stale vectors can be detected without reading
+  /// the tag artifact.
+  pub version: i64,
+  pub embedding: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageVectorHit {
+  pub item_id: String,
+  pub distance: f32,
+}
+
+/// Image embeddings of a user's image items, one per item. Unlike the fragment vector DB this is
+/// updated in place, as images are tagged and removed.
+#[async_trait]
+pub trait ImageVectorDb: Send + Sync {
+  async fn stored_versions(&self) -> InfuResult<HashMap<String, i64>>;
+
+  async fn item_vector(&self, item_id: &str) -> InfuResult<Option<ImageVector>>;
+
+  async fn upsert_item_vector(&self, vector: &ImageVector) -> InfuResult<()>;
+
+  async fn delete_item_vector(&self, item_id: &str) -> InfuResult<bool>;
+
+  async fn search(&self, model: &str, query_embedding: &[f32], limit: usize) -> InfuResult<Vec<ImageVectorHit>>;
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FragmentVectorDbBackend {
   SqliteVec,
@@ -161,3 +202,20 @@ pub fn open_user_fragment_vector_db(
 ) -> InfuResult<Box<dyn FragmentVectorDb>> {
   Ok(open_fragment_vector_db(backend, fragment_vector_db_path(data_dir, user_id)?))
 }
+
+pub fn image_vector_db_path(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = user_index_dir(data_dir, user_id)?;
+  path.push(IMAGE_VECTOR_DB_FILENAME);
+  Ok(path)
+}
+
+pub fn open_user_image_vector_db(
+  data_dir: &str,
+  user_id: &str,
+  backend: FragmentVectorDbBackend,
+) -> InfuResult<Box<dyn ImageVectorDb>> {
+  let db_path = image_vector_db_path(data_dir, user_id)?;
+  Ok(match backend {
+    FragmentVectorDbBackend::SqliteVec => Box::new(sqlite_vec_images::SqliteVecImageVectorDb::new(db_path)),
+  })
+}
@@ -16,7 +16,9 @@ use super::{
   FragmentVectorDbRebuildStatus, FragmentVectorHit, fragment_vector_db_operation_lock,
 };
 
-pub const SQLITE_VEC_INDEX_SCHEMA_VERSION: i64 = 1;
+pub const SQLITE_VEC_INDEX_SCHEMA_VERSION: i64 = 2;
+/// The first schema version whose fragments table has time range columns.
+const TIME_RANGE_COLUMNS_SCHEMA_VERSION: i64 = 2;
 const SQLITE_VEC_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
 pub const INDEX_METADATA_TABLE_NAME: &str = "fragment_index_metadata";
 pub const FRAGMENTS_TABLE_NAME: &str = "fragments";
@@ -43,6 +45,8 @@ CREATE TABLE IF NOT EXISTS fragments (
   source_kind TEXT NOT NULL,
   page_start INTEGER,
   page_end INTEGER,
+  time_start_secs INTEGER,
+  time_end_secs INTEGER,
   text_sha256 TEXT NOT NULL,
   text TEXT NOT NULL,
   UNIQUE(item_id, ordinal)
@@ -60,9 +64,11 @@ INSERT INTO fragments (
   source_kind,
   page_start,
   page_end,
+  time_start_secs,
+  time_end_secs,
   text_sha256,
   text
-) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
 "#;
 
 pub const INSERT_FRAGMENT_EMBEDDING_SQL: &str = r#"
@@ -86,7 +92,7 @@ UPDATE fragment_index_metadata SET complete = ?1 WHERE id = 1
 "#;
 
 pub const READ_REBUILD_METADATA_SQL: &str = r#"
-SELECT source_digest, fragment_count, model, embedding_dimensions, complete
+SELECT schema_version, source_digest, fragment_count, model, embedding_dimensions, complete
 FROM fragment_index_metadata
 WHERE id = 1
 "#;
@@ -96,7 +102,30 @@ SELECT item_id, ordinal, text_sha256
 FROM fragments
 "#;
 
+pub const SELECT_ITEM_FRAGMENT_KEYS_SQL: &str = r#"
+SELECT item_id, ordinal, text_sha256
+FROM fragments
+WHERE item_id = ?1
+"#;
+
 pub const SELECT_EMBEDDED_FRAGMENTS_SQL: &str = r#"
+SELECT
+  fragments.item_id,
+  fragments.ordinal,
+  fragments.source_kind,
+  fragments.page_start,
+  fragments.page_end,
+  fragments.text_sha256,
+  fragments.text,
+  fragment_embeddings.embedding,
+  fragments.time_start_secs,
+  fragments.time_end_secs
+FROM fragments
+JOIN fragment_embeddings ON fragment_embeddings.rowid = fragments.fragment_id
+"#;
+
+/// For indexes built before fragments had time range columns, so their embeddings can still be reused.
+pub const SELECT_EMBEDDED_FRAGMENTS_WITHOUT_TIME_RANGES_SQL: &str = r#"
 SELECT
   fragments.item_id,
   fragments.ordinal,
@@ -131,7 +160,9 @@ SELECT
   fragment_embeddings.distance,
   fragments.text,
   fragments.page_start,
-  fragments.page_end
+  fragments.page_end,
+  fragments.time_start_secs,
+  fragments.time_end_secs
 FROM fragment_embeddings
 JOIN fragments ON fragments.fragment_id = fragment_embeddings.rowid
 WHERE fragment_embeddings.embedding MATCH ?1
@@ -188,11 +219,12 @@ impl SqliteVecFragmentVectorDb {
     let metadata = conn
       .query_row(READ_REBUILD_METADATA_SQL, [], |row| {
         Ok(StoredRebuildMetadata {
-          source_digest: row.get(0)?,
-          expected_fragment_count: row.get(1)?,
-          model: row.get(2)?,
-          embedding_dimensions: row.get(3)?,
-          complete: row.get::<_, i64>(4)? != 0,
+          schema_version: row.get(0)?,
+          source_digest: row.get(1)?,
+          expected_fragment_count: row.get(2)?,
+          model: row.get(3)?,
+          embedding_dimensions: row.get(4)?,
+          complete: row.get::<_, i64>(5)? != 0,
         })
       })
       .optional()
@@ -209,6 +241,7 @@ impl SqliteVecFragmentVectorDb {
       count_table_rows(conn, FRAGMENT_EMBEDDINGS_TABLE_NAME, COUNT_FRAGMENT_EMBEDDING_ROWS_SQL)?;
 
     Ok(Some(FragmentVectorDbRebuildStatus {
+      schema_version: metadata.schema_version,
       source_digest: metadata.source_digest,
       expected_fragment_count,
       model: metadata.model,
@@ -224,6 +257,17 @@ impl SqliteVecFragmentVectorDb {
     status: &FragmentVectorDbRebuildStatus,
     metadata: &FragmentVectorDbRebuildMetadata,
   ) -> InfuResult<()> {
+    if status.schema_version != SQLITE_VEC_INDEX_SCHEMA_VERSION {
+      return Err(
+        format!(
+          "Cannot continue sqlite-vec rebuild '{}': schema version differs (temp DB {}, current {}). Run without --continue to start a fresh rebuild.",
+          self.db_path.display(),
+          status.schema_version,
+          SQLITE_VEC_INDEX_SCHEMA_VERSION
+        )
+        .into(),
+      );
+    }
     if status.source_digest != metadata.source_digest {
       return Err(
         format!(
@@ -273,6 +317,7 @@ impl SqliteVecFragmentVectorDb {
 }
 
 struct StoredRebuildMetadata {
+  schema_version: i64,
   source_digest: String,
   expected_fragment_count: i64,
   model: String,
@@ -296,7 +341,7 @@ pub fn fragment_text_sha256(text: &str) -> String {
   format!("{:x}", hasher.finalize())
 }
 
-fn register_sqlite_vec_extension() -> InfuResult<()> {
+pub(super) fn register_sqlite_vec_extension() -> InfuResult<()> {
   static REGISTER_RESULT: OnceLock<i32> = OnceLock::new();
   let rc = *REGISTER_RESULT.get_or_init(|| unsafe {
     rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(::sqlite_vec::sqlite3_vec_init as *const ())))
@@ -308,7 +353,7 @@ fn register_sqlite_vec_extension() -> InfuResult<()> {
   }
 }
 
-fn table_exists(conn: &Connection, table_name: &str) -> InfuResult<bool> {
+pub(super) fn table_exists(conn: &Connection, table_name: &str) -> InfuResult<bool> {
   let count: i64 = conn
     .query_row("SELECT COUNT(*) FROM sqlite_schema WHERE type = 'table' AND name = ?1", params![table_name], |row| {
       row.get(0)
@@ -327,11 +372,11 @@ fn count_table_rows(conn: &Connection, table_name: &str, sql: &str) -> InfuResul
   i64_to_usize(count, table_name)
 }
 
-fn usize_to_i64(value: usize, field_name: &str) -> InfuResult<i64> {
+pub(super) fn usize_to_i64(value: usize, field_name: &str) -> InfuResult<i64> {
   i64::try_from(value).map_err(|_| format!("{} value {} does not fit in sqlite INTEGER.", field_name, value).into())
 }
 
-fn i64_to_usize(value: i64, field_name: &str) -> InfuResult<usize> {
+pub(super) fn i64_to_usize(value: i64, field_name: &str) -> InfuResult<usize> {
   usize::try_from(value)
     .map_err(|_| format!("{} sqlite INTEGER value {} is negative or too large.", field_name, value).into())
 }
@@ -340,7 +385,7 @@ fn optional_usize_to_i64(value: Option<usize>, field_name: &str) -> InfuResult<O
   value.map(|v| usize_to_i64(v, field_name)).transpose()
 }
 
-fn embedding_from_bytes(bytes: &[u8], expected_dimensions: usize) -> InfuResult<Vec<f32>> {
+pub(super) fn embedding_from_bytes(bytes: &[u8], expected_dimensions: usize) -> InfuResult<Vec<f32>> {
   let chunks = bytes.chunks_exact(4);
   if !chunks.remainder().is_empty() {
     return Err(
@@ -471,6 +516,44 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
     Ok(keys)
   }
 
+  async fn embedded_fragment_keys_for_item(&self, item_id: &str) -> InfuResult<HashSet<FragmentVectorDbFragmentKey>> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if item_id.trim().is_empty() || !self.db_path.exists() {
+      return Ok(HashSet::new());
+    }
+    let conn = self.open_connection()?;
+    if !table_exists(&conn, FRAGMENTS_TABLE_NAME)? {
+      return Ok(HashSet::new());
+    }
+
+    let mut stmt = conn.prepare(SELECT_ITEM_FRAGMENT_KEYS_SQL).map_err(|e| {
+      format!("Could not prepare sqlite-vec item fragment key query '{}': {}", self.db_path.display(), e)
+    })?;
+    let mut rows = stmt.query(params![item_id]).map_err(|e| {
+      format!("Could not query sqlite-vec fragment keys for item '{}' in '{}': {}", item_id, self.db_path.display(), e)
+    })?;
+    let mut keys = HashSet::new();
+    while let Some(row) = rows
+      .next()
+      .map_err(|e| format!("Could not read sqlite-vec fragment key row '{}': {}", self.db_path.display(), e))?
+    {
+      let ordinal: i64 = row
+        .get(1)
+        .map_err(|e| format!("Could not read sqlite-vec fragment ordinal '{}': {}", self.db_path.display(), e))?;
+      keys.insert(FragmentVectorDbFragmentKey {
+        item_id: row
+          .get(0)
+          .map_err(|e| format!("Could not read sqlite-vec fragment item id '{}': {}", self.db_path.display(), e))?,
+        ordinal: i64_to_usize(ordinal, "ordinal")?,
+        text_sha256: row
+          .get(2)
+          .map_err(|e| format!("Could not read sqlite-vec fragment text hash '{}': {}", self.db_path.display(), e))?,
+      });
+    }
+    Ok(keys)
+  }
+
   async fn embedded_fragments_for_keys(
     &self,
     keys: &HashSet<FragmentVectorDbFragmentKey>,
@@ -491,7 +574,10 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
       return Ok(Vec::new());
     }
 
-    let mut stmt = conn.prepare(SELECT_EMBEDDED_FRAGMENTS_SQL).map_err(|e| {
+    let has_time_ranges = status.schema_version >= TIME_RANGE_COLUMNS_SCHEMA_VERSION;
+    let select_sql =
+      if has_time_ranges { SELECT_EMBEDDED_FRAGMENTS_SQL } else { SELECT_EMBEDDED_FRAGMENTS_WITHOUT_TIME_RANGES_SQL };
+    let mut stmt = conn.prepare(select_sql).map_err(|e| {
       format!("Could not prepare sqlite-vec embedded fragment query '{}': {}", self.db_path.display(), e)
     })?;
     let mut rows = stmt
@@ -527,6 +613,23 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
         .map_err(|e| format!("Could not read sqlite-vec fragment page_end '{}': {}", self.db_path.display(), e))?
         .map(|value| i64_to_usize(value, "page_end"))
         .transpose()?;
+      let (time_start_secs, time_end_secs) = if has_time_ranges {
+        let time_start_secs = row
+          .get::<_, Option<i64>>(8)
+          .map_err(|e| {
+            format!("Could not read sqlite-vec fragment time_start_secs '{}': {}", self.db_path.display(), e)
+          })?
+          .map(|value| i64_to_usize(value, "time_start_secs"))
+          .transpose()?;
+        let time_end_secs = row
+          .get::<_, Option<i64>>(9)
+          .map_err(|e| format!("Could not read sqlite-vec fragment time_end_secs '{}': {}", self.db_path.display(), e))?
+          .map(|value| i64_to_usize(value, "time_end_secs"))
+          .transpose()?;
+        (time_start_secs, time_end_secs)
+      } else {
+        (None, None)
+      };
       let embedding_bytes: Vec<u8> = row
         .get(7)
         .map_err(|e| format!("Could not read sqlite-vec fragment embedding '{}': {}", self.db_path.display(), e))?;
@@ -538,6 +641,8 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
           .map_err(|e| format!("Could not read sqlite-vec fragment source kind '{}': {}", self.db_path.display(), e))?,
         page_start,
         page_end,
+        time_start_secs,
+        time_end_secs,
         text: row
           .get(6)
           .map_err(|e| format!("Could not read sqlite-vec fragment text '{}': {}", self.db_path.display(), e))?,
@@ -583,6 +688,8 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
           fragment.source_kind,
           optional_usize_to_i64(fragment.page_start, "page_start")?,
           optional_usize_to_i64(fragment.page_end, "page_end")?,
+          optional_usize_to_i64(fragment.time_start_secs, "time_start_secs")?,
+          optional_usize_to_i64(fragment.time_end_secs, "time_end_secs")?,
           fragment_text_sha256(&fragment.text),
           fragment.text,
         ],
@@ -714,7 +821,8 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
     let Some(status) = self.read_rebuild_status(&conn)? else {
       return Ok(Vec::new());
     };
-    if !status.complete {
+    // An index built with an older schema is searched again once it has been rebuilt.
+    if !status.complete || status.schema_version != SQLITE_VEC_INDEX_SCHEMA_VERSION {
       return Ok(Vec::new());
     }
     if query_embedding.len() != status.embedding_dimensions {
@@ -751,6 +859,12 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
       let page_end: Option<i64> = row
         .get(6)
         .map_err(|e| format!("Could not read sqlite-vec hit page_end '{}': {}", self.db_path.display(), e))?;
+      let time_start_secs: Option<i64> = row
+        .get(7)
+        .map_err(|e| format!("Could not read sqlite-vec hit time_start_secs '{}': {}", self.db_path.display(), e))?;
+      let time_end_secs: Option<i64> = row
+        .get(8)
+        .map_err(|e| format!("Could not read sqlite-vec hit time_end_secs '{}': {}", self.db_path.display(), e))?;
       hits.push(FragmentVectorHit {
         item_id: row
           .get(0)
@@ -765,6 +879,8 @@ impl FragmentVectorDb for SqliteVecFragmentVectorDb {
           .map_err(|e| format!("Could not read sqlite-vec hit text '{}': {}", self.db_path.display(), e))?,
         page_start: page_start.map(|v| i64_to_usize(v, "page_start")).transpose()?,
         page_end: page_end.map(|v| i64_to_usize(v, "page_end")).transpose()?,
+        time_start_secs: time_start_secs.map(|v| i64_to_usize(v, "time_start_secs")).transpose()?,
+        time_end_secs: time_end_secs.map(|v| i64_to_usize(v, "time_end_secs")).transpose()?,
       });
     }
     Ok(hits)
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use infusdk::util::infu::InfuResult;
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use zerocopy::IntoBytes;
+
+use super::sqlite_vec::{
+  embedding_from_bytes, i64_to_usize, register_sqlite_vec_extension, table_exists, usize_to_i64,
+};
+use super::{ImageVector, ImageVectorDb, ImageVectorHit, fragment_vector_db_operation_lock};
+
+const SQLITE_VEC_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+pub const IMAGE_VECTORS_TABLE_NAME: &str = "image_vectors";
+pub const IMAGE_VECTOR_MODELS_TABLE_NAME: &str = "image_vector_models";
+
+/// Each (model, dimensions) pair has its own vec0 table, named after the model row id, so that
+/// images tagged before and after a change of image embedding model can coexist.
+pub const CREATE_IMAGE_VECTOR_MODELS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS image_vector_models (
+  model_id INTEGER PRIMARY KEY,
+  model TEXT NOT NULL,
+  embedding_dimensions INTEGER NOT NULL,
+  UNIQUE(model, embedding_dimensions)
+)
+"#;
+
+pub const CREATE_IMAGE_VECTORS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS image_vectors (
+  image_id INTEGER PRIMARY KEY,
+  item_id TEXT NOT NULL UNIQUE,
+  model_id INTEGER NOT NULL,
+  version INTEGER NOT NULL
+)
+"#;
+
+pub const SELECT_IMAGE_VERSIONS_SQL: &str = "SELECT item_id, version FROM image_vectors";
+
+pub const SELECT_IMAGE_VECTOR_SQL: &str = r#"
+SELECT
+  image_vectors.image_id,
+  image_vectors.version,
+  image_vectors.model_id,
+  image_vector_models.model,
+  image_vector_models.embedding_dimensions
+FROM image_vectors
+JOIN image_vector_models ON image_vector_models.model_id = image_vectors.model_id
+WHERE image_vectors.item_id = ?1
+"#;
+
+pub const SELECT_IMAGE_VECTOR_MODEL_SQL: &str = r#"
+SELECT model_id FROM image_vector_models WHERE model = ?1 AND embedding_dimensions = ?2
+"#;
+
+pub const INSERT_IMAGE_VECTOR_MODEL_SQL: &str = r#"
+INSERT INTO image_vector_models (model, embedding_dimensions) VALUES (?1, ?2)
+"#;
+
+pub const INSERT_IMAGE_VECTOR_SQL: &str = r#"
+INSERT INTO image_vectors (item_id, model_id, version) VALUES (?1, ?2, ?3)
+"#;
+
+pub const DELETE_IMAGE_VECTOR_SQL: &str = "DELETE FROM image_vectors WHERE image_id = ?1";
+
+#[derive(Clone, Debug)]
+pub struct SqliteVecImageVectorDb {
+  db_path: PathBuf,
+}
+
+struct StoredImageVectorRow {
+  image_id: i64,
+  version: i64,
+  model_id: i64,
+  model: String,
+  embedding_dimensions: i64,
+}
+
+impl SqliteVecImageVectorDb {
+  pub fn new(db_path: PathBuf) -> SqliteVecImageVectorDb {
+    SqliteVecImageVectorDb { db_path }
+  }
+
+  pub fn db_path(&self) -> &Path {
+    &self.db_path
+  }
+
+  fn open_connection(&self) -> InfuResult<Connection> {
+    register_sqlite_vec_extension()?;
+    let conn = Connection::open(&self.db_path)
+      .map_err(|e| format!("Could not open sqlite-vec image database '{}': {}", self.db_path.display(), e))?;
+    conn
+      .busy_timeout(SQLITE_VEC_BUSY_TIMEOUT)
+      .map_err(|e| format!("Could not configure sqlite-vec busy timeout '{}': {}", self.db_path.display(), e))?;
+    Ok(conn)
+  }
+
+  fn has_schema(&self, conn: &Connection) -> InfuResult<bool> {
+    Ok(table_exists(conn, IMAGE_VECTORS_TABLE_NAME)? && table_exists(conn, IMAGE_VECTOR_MODELS_TABLE_NAME)?)
+  }
+
+  fn read_item_row(&self, conn: &Connection, item_id: &str) -> InfuResult<Option<StoredImageVectorRow>> {
+    conn
+      .query_row(SELECT_IMAGE_VECTOR_SQL, params![item_id], |row| {
+        Ok(StoredImageVectorRow {
+          image_id: row.get(0)?,
+          version: row.get(1)?,
+          model_id: row.get(2)?,
+          model: row.get(3)?,
+          embedding_dimensions: row.get(4)?,
+        })
+      })
+      .optional()
+      .map_err(|e| {
+        format!("Could not read image vector for item '{}' from '{}': {}", item_id, self.db_path.display(), e).into()
+      })
+  }
+
+  fn model_id(&self, conn: &Connection, model: &str, embedding_dimensions: usize) -> InfuResult<Option<i64>> {
+    conn
+      .query_row(
+        SELECT_IMAGE_VECTOR_MODEL_SQL,
+        params![model, usize_to_i64(embedding_dimensions, "embedding_dimensions")?],
+        |row| row.get(0),
+      )
+      .optional()
+      .map_err(|e| {
+        format!("Could not read image vector model '{}' from '{}': {}", model, self.db_path.display(), e).into()
+      })
+  }
+
+  fn delete_item_row(&self, tx: &Transaction, row: &StoredImageVectorRow) -> InfuResult<()> {
+    tx.execute(
+      &format!("DELETE FROM {} WHERE rowid = ?1", image_embeddings_table_name(row.model_id)),
+      params![row.image_id],
+    )
+    .map_err(|e| format!("Could not delete image embedding from '{}': {}", self.db_path.display(), e))?;
+    tx.execute(DELETE_IMAGE_VECTOR_SQL, params![row.image_id])
+      .map_err(|e| format!("Could not delete image vector from '{}': {}", self.db_path.display(), e))?;
+    Ok(())
+  }
+}
+
+fn image_embeddings_table_name(model_id: i64) -> String {
+  format!("image_embeddings_{}", model_id)
+}
+
+#[async_trait]
+impl ImageVectorDb for SqliteVecImageVectorDb {
+  async fn stored_versions(&self) -> InfuResult<HashMap<String, i64>> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if !self.db_path.exists() {
+      return Ok(HashMap::new());
+    }
+    let conn = self.open_connection()?;
+    if !self.has_schema(&conn)? {
+      return Ok(HashMap::new());
+    }
+
+    let mut stmt = conn
+      .prepare(SELECT_IMAGE_VERSIONS_SQL)
+      .map_err(|e| format!("Could not prepare image vector version query '{}': {}", self.db_path.display(), e))?;
+    let mut rows = stmt
+      .query([])
+      .map_err(|e| format!("Could not query image vector versions '{}': {}", self.db_path.display(), e))?;
+    let mut versions = HashMap::new();
+    while let Some(row) =
+      rows.next().map_err(|e| format!("Could not read image vector version row '{}': {}", self.db_path.display(), e))?
+    {
+      let item_id: String =
+        row.get(0).map_err(|e| format!("Could not read image vector item id '{}': {}", self.db_path.display(), e))?;
+      let version: i64 =
+        row.get(1).map_err(|e| format!("Could not read image vector version '{}': {}", self.db_path.display(), e))?;
+      versions.insert(item_id, version);
+    }
+    Ok(versions)
+  }
+
+  async fn item_vector(&self, item_id: &str) -> InfuResult<Option<ImageVector>> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if item_id.trim().is_empty() || !self.db_path.exists() {
+      return Ok(None);
+    }
+    let conn = self.open_connection()?;
+    if !self.has_schema(&conn)? {
+      return Ok(None);
+    }
+    let Some(row) = self.read_item_row(&conn, item_id)? else {
+      return Ok(None);
+    };
+
+    let embedding_bytes: Vec<u8> = conn
+      .query_row(
+        &format!("SELECT embedding FROM {} WHERE rowid = ?1", image_embeddings_table_name(row.model_id)),
+        params![row.image_id],
+        |row| row.get(0),
+      )
+      .map_err(|e| {
+        format!("Could not read image embedding for item '{}' from '{}': {}", item_id, self.db_path.display(), e)
+      })?;
+    Ok(Some(ImageVector {
+      item_id: item_id.to_owned(),
+      model: row.model,
+      version: row.version,
+      embedding: embedding_from_bytes(
+        &embedding_bytes,
+        i64_to_usize(row.embedding_dimensions, "embedding_dimensions")?,
+      )?,
+    }))
+  }
+
+  async fn upsert_item_vector(&self, vector: &ImageVector) -> InfuResult<()> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if vector.embedding.is_empty() {
+      return Err(format!("Image vector for item '{}' has no dimensions.", vector.item_id).into());
+    }
+
+    if let Some(parent) = self.db_path.parent() {
+      tokio::fs::create_dir_all(parent)
+        .await
+        .map_err(|e| format!("Could not create sqlite-vec image directory '{}': {}", parent.display(), e))?;
+    }
+    let mut conn = self.open_connection()?;
+    for sql in [CREATE_IMAGE_VECTOR_MODELS_TABLE_SQL, CREATE_IMAGE_VECTORS_TABLE_SQL] {
+      conn
+        .execute_batch(sql.trim())
+        .map_err(|e| format!("Could not create sqlite-vec image schema '{}': {}", self.db_path.display(), e))?;
+    }
+
+    let existing_row = self.read_item_row(&conn, &vector.item_id)?;
+    let model_id = self.model_id(&conn, &vector.model, vector.embedding.len())?;
+    let tx = conn
+      .transaction()
+      .map_err(|e| format!("Could not start image vector transaction '{}': {}", self.db_path.display(), e))?;
+    if let Some(existing_row) = existing_row {
+      self.delete_item_row(&tx, &existing_row)?;
+    }
+    let model_id = match model_id {
+      Some(model_id) => model_id,
+      None => {
+        tx.execute(
+          INSERT_IMAGE_VECTOR_MODEL_SQL,
+          params![vector.model, usize_to_i64(vector.embedding.len(), "embedding_dimensions")?],
+        )
+        .map_err(|e| format!("Could not add image vector model to '{}': {}", self.db_path.display(), e))?;
+        let model_id = tx.last_insert_rowid();
+        tx.execute_batch(&format!(
+          "CREATE VIRTUAL TABLE IF NOT EXISTS {} USING vec0(embedding float[{}] distance_metric=cosine)",
+          image_embeddings_table_name(model_id),
+          vector.embedding.len()
+        ))
+        .map_err(|e| format!("Could not create image embedding table in '{}': {}", self.db_path.display(), e))?;
+        model_id
+      }
+    };
+    tx.execute(INSERT_IMAGE_VECTOR_SQL, params![vector.item_id, model_id, vector.version]).map_err(|e| {
+      format!("Could not insert image vector for item '{}' into '{}': {}", vector.item_id, self.db_path.display(), e)
+    })?;
+    let image_id = tx.last_insert_rowid();
+    tx.execute(
+      &format!("INSERT INTO {}(rowid, embedding) VALUES (?1, ?2)", image_embeddings_table_name(model_id)),
+      params![image_id, vector.embedding.as_bytes()],
+    )
+    .map_err(|e| {
+      format!("Could not insert image embedding for item '{}' into '{}': {}", vector.item_id, self.db_path.display(), e)
+    })?;
+    tx.commit()
+      .map_err(|e| format!("Could not commit image vector transaction '{}': {}", self.db_path.display(), e))?;
+    Ok(())
+  }
+
+  async fn delete_item_vector(&self, item_id: &str) -> InfuResult<bool> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if item_id.trim().is_empty() || !self.db_path.exists() {
+      return Ok(false);
+    }
+    let mut conn = self.open_connection()?;
+    if !self.has_schema(&conn)? {
+      return Ok(false);
+    }
+    let Some(row) = self.read_item_row(&conn, item_id)? else {
+      return Ok(false);
+    };
+    let tx = conn
+      .transaction()
+      .map_err(|e| format!("Could not start image vector transaction '{}': {}", self.db_path.display(), e))?;
+    self.delete_item_row(&tx, &row)?;
+    tx.commit()
+      .map_err(|e| format!("Could not commit image vector transaction '{}': {}", self.db_path.display(), e))?;
+    Ok(true)
+  }
+
+  async fn search(&self, model: &str, query_embedding: &[f32], limit: usize) -> InfuResult<Vec<ImageVectorHit>> {
+    let operation_lock = fragment_vector_db_operation_lock(&self.db_path);
+    let _operation_guard = operation_lock.lock().await;
+    if limit == 0 || query_embedding.is_empty() || !self.db_path.exists() {
+      return Ok(Vec::new());
+    }
+    let conn = self.open_connection()?;
+    if !self.has_schema(&conn)? {
+      return Ok(Vec::new());
+    }
+    let Some(model_id) = self.model_id(&conn, model, query_embedding.len())? else {
+      return Ok(Vec::new());
+    };
+
+    let sql = format!(
+      "SELECT image_vectors.item_id, embeddings.distance FROM {} AS embeddings JOIN image_vectors ON image_vectors.image_id = embeddings.rowid WHERE embeddings.embedding MATCH ?1 AND k = ?2 ORDER BY embeddings.distance",
+      image_embeddings_table_name(model_id)
+    );
+    let mut stmt =
+      conn.prepare(&sql).map_err(|e| format!("Could not prepare image search '{}': {}", self.db_path.display(), e))?;
+    let mut rows = stmt
+      .query(params![query_embedding.as_bytes(), usize_to_i64(limit, "limit")?])
+      .map_err(|e| format!("Could not query image search '{}': {}", self.db_path.display(), e))?;
+    let mut hits = Vec::new();
+    while let Some(row) =
+      rows.next().map_err(|e| format!("Could not read image search row '{}': {}", self.db_path.display(), e))?
+    {
+      let distance: f64 =
+        row.get(1).map_err(|e| format!("Could not read image hit distance '{}': {}", self.db_path.display(), e))?;
+      hits.push(ImageVectorHit {
+        item_id: row
+          .get(0)
+          .map_err(|e| format!("Could not read image hit item id '{}': {}", self.db_path.display(), e))?,
+        distance: distance as f32,
+      });
+    }
+    Ok(hits)
+  }
+}
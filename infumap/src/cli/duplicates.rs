@@ -0,0 +1,218 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::is_uid;
+use serde_json::Value;
+
+use super::extract::{CliRuntime, init_runtime};
+use crate::ai::near_duplicates::scan_user_near_duplicates;
+use crate::cli::{NamedInfuSession, build_http_client, build_session_headers};
+use crate::web::routes::command::{CommandRequest, CommandResponse};
+
+pub fn make_clap_subcommand() -> Command {
+  Command::new("duplicates")
+    .about("Find near-duplicate images (re-saved copies and burst shots) and move the extra copies to the trash.")
+    .subcommand_required(true)
+    .arg_required_else_help(true)
+    .subcommand(
+      Command::new("scan")
+        .about(
+          "Scan images for near-duplicates without starting the web server, updating the report the web server serves.",
+        )
+        .arg(
+          Arg::new("settings_path")
+            .short('s')
+            .long("settings")
+            .help("Path to a toml settings configuration file. If not specified, the default will be assumed.")
+            .num_args(1)
+            .required(false),
+        )
+        .arg(
+          Arg::new("username")
+            .short('u')
+            .long("username")
+            .help("Scan only the images of this user. All users are scanned if not specified.")
+            .num_args(1)
+            .required(false),
+        ),
+    )
+    .subcommand(
+      Command::new("list")
+        .about("List the near-duplicate clusters found by the most recent scan, best image first.")
+        .arg(session_arg()),
+    )
+    .subcommand(
+      Command::new("trash")
+        .about("Move all but the best image of each near-duplicate cluster to the trash page.")
+        .arg(session_arg())
+        .arg(
+          Arg::new("keep")
+            .short('k')
+            .long("keep")
+            .help("Keep this image rather than the best one in its cluster. Repeat for multiple clusters.")
+            .num_args(1)
+            .action(ArgAction::Append)
+            .required(false),
+        ),
+    )
+}
+
+fn session_arg() -> Arg {
+  Arg::new("session")
+    .short('s')
+    .long("session")
+    .help("The name of the Infumap session to use. 'default' will be used if not specified.")
+    .num_args(1)
+    .default_value("default")
+    .required(false)
+}
+
+pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
+  match sub_matches.subcommand() {
+    Some(("scan", sub_matches)) => execute_scan(sub_matches).await,
+    Some(("list", sub_matches)) => execute_list(sub_matches).await,
+    Some(("trash", sub_matches)) => execute_trash(sub_matches).await,
+    _ => Err("Missing duplicates subcommand. Use 'duplicates scan', 'duplicates list' or 'duplicates trash'.".into()),
+  }
+}
+
+async fn execute_scan(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let CliRuntime { data_dir, db, object_store, .. } =
+    init_runtime(sub_matches.get_one::<String>("settings_path")).await?;
+
+  let mut user_ids = {
+    let db = db.lock().await;
+    match sub_matches.get_one::<String>("username") {
+      Some(username) => vec![
+        db.user.get_by_username_case_insensitive(username).ok_or(format!("User '{}' not found.", username))?.id.clone(),
+      ],
+      None => db.user.all_user_ids(),
+    }
+  };
+  user_ids.sort();
+
+  for user_id in user_ids {
+    let summary = scan_user_near_duplicates(&data_dir, db.clone(), object_store.clone(), &user_id).await?;
+    println!(
+      "User {}: {} image(s), {} newly hashed, {} cluster(s) with {} extra cop{}.",
+      user_id,
+      summary.images,
+      summary.hashed,
+      summary.clusters,
+      summary.redundant_images,
+      if summary.redundant_images == 1 { "y" } else { "ies" }
+    );
+  }
+  Ok(())
+}
+
+async fn execute_list(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let result = send_command(sub_matches, "near-duplicates", String::new()).await?;
+  let clusters = result.get("clusters").and_then(Value::as_array).cloned().unwrap_or_default();
+  if result.get("generatedAtUnixSecs").is_none_or(Value::is_null) {
+    println!("No near-duplicate scan has completed yet.");
+    return Ok(());
+  }
+  if clusters.is_empty() {
+    println!("No near-duplicate images found.");
+    return Ok(());
+  }
+
+  for (index, cluster) in clusters.iter().enumerate() {
+    println!("Cluster {}:", index + 1);
+    let items = cluster.get("items").and_then(Value::as_array).cloned().unwrap_or_default();
+    for (item_index, item) in items.iter().enumerate() {
+      let size = match (item.get("widthPx").and_then(Value::as_i64), item.get("heightPx").and_then(Value::as_i64)) {
+        (Some(width), Some(height)) => format!("{}x{}", width, height),
+        _ => "?x?".to_owned(),
+      };
+      println!(
+        "  {} {} {:>11} {:<25} {}",
+        if item_index == 0 { "keep " } else { "trash" },
+        item.get("id").and_then(Value::as_str).unwrap_or_default(),
+        size,
+        item.get("capturedAt").and_then(Value::as_str).unwrap_or("-"),
+        item.get("title").and_then(Value::as_str).unwrap_or_default()
+      );
+    }
+  }
+  Ok(())
+}
+
+async fn execute_trash(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let keep_item_ids =
+    sub_matches.get_many::<String>("keep").map(|ids| ids.cloned().collect::<Vec<_>>()).unwrap_or_default();
+  if let Some(item_id) = keep_item_ids.iter().find(|item_id| !is_uid(item_id)) {
+    return Err(format!("Invalid item id: '{}'.", item_id).into());
+  }
+
+  // The server only trashes the images it is given, so these are worked out from the current clusters.
+  let listing = send_command(sub_matches, "near-duplicates", String::new()).await?;
+  let clusters = listing.get("clusters").and_then(Value::as_array).cloned().unwrap_or_default();
+  let mut trash_item_ids = vec![];
+  for cluster in clusters {
+    let item_ids = cluster
+      .get("items")
+      .and_then(Value::as_array)
+      .map(|items| items.iter().filter_map(|item| item.get("id").and_then(Value::as_str)).collect::<Vec<_>>())
+      .unwrap_or_default();
+    let keep_index =
+      item_ids.iter().position(|item_id| keep_item_ids.iter().any(|keep| keep.as_str() == *item_id)).unwrap_or(0);
+    trash_item_ids.extend(
+      item_ids.iter().enumerate().filter(|(index, _)| *index != keep_index).map(|(_, item_id)| item_id.to_string()),
+    );
+  }
+  if trash_item_ids.is_empty() {
+    println!("No near-duplicate images to trash.");
+    return Ok(());
+  }
+
+  let request = serde_json::json!({ "itemIds": trash_item_ids });
+  let result = send_command(sub_matches, "trash-near-duplicates", request.to_string()).await?;
+  let moved = result.get("movedItemIds").and_then(Value::as_array).map(Vec::len).unwrap_or(0);
+  let skipped = result.get("skippedItemIds").and_then(Value::as_array).cloned().unwrap_or_default();
+  println!("Moved {} image(s) to the trash.", moved);
+  if !skipped.is_empty() {
+    println!("Skipped {} attached or already moved image(s):", skipped.len());
+    for item_id in skipped {
+      println!("  {}", item_id.as_str().unwrap_or_default());
+    }
+  }
+  Ok(())
+}
+
+async fn send_command(sub_matches: &ArgMatches, command: &str, json_data: String) -> InfuResult<Value> {
+  let session_name = sub_matches.get_one::<String>("session").unwrap();
+  let mut named_session = NamedInfuSession::get(session_name)
+    .await
+    .map_err(|e| format!("A problem occurred getting session '{}': {}.", session_name, e))?
+    .ok_or("Session does not exist - use the login CLI command to create one.")?;
+
+  let request_headers = build_session_headers(&named_session.session)?;
+  let client = build_http_client(Some(request_headers)).await?;
+  let send_request = CommandRequest { command: command.to_owned(), json_data, base64_data: None };
+  let response =
+    client.post(named_session.command_url()?.clone()).json(&send_request).send().await.map_err(|e| format!("{}", e))?;
+  named_session.update_from_response(&response).await?;
+  let command_response: CommandResponse = response.json().await.map_err(|e| format!("{}", e))?;
+  if !command_response.success {
+    return Err(format!("Infumap rejected the {} command. Has your session expired?", command).into());
+  }
+  let json_data = command_response.json_data.ok_or(format!("The {} command returned no data.", command))?;
+  Ok(serde_json::from_str(&json_data)?)
+}
@@ -0,0 +1,192 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Component, Path, PathBuf};
+
+use base64::{Engine as _, engine::general_purpose};
+use clap::{Arg, ArgMatches, Command};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::is_uid;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::cli::{NamedInfuSession, build_http_client, build_session_headers};
+use crate::util::fs::expand_tilde;
+use crate::web::routes::command::{CommandRequest, CommandResponse};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownFile {
+  path: String,
+  base64_data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownAsset {
+  item_id: String,
+  path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownResponse {
+  files: Vec<ExportMarkdownFile>,
+  assets: Vec<ExportMarkdownAsset>,
+  page_count: usize,
+  file_count: usize,
+  item_count: usize,
+  skipped_count: usize,
+}
+
+pub fn make_clap_subcommand() -> Command {
+  Command::new("export-markdown")
+    .about("Export an Infumap container and everything below it as markdown files, with file and image data and a json sidecar preserving layout.")
+    .arg(Arg::new("container_id")
+      .short('c')
+      .long("container-id")
+      .help("The id of the page, table or composite to export.")
+      .num_args(1)
+      .required(true))
+    .arg(Arg::new("output")
+      .short('o')
+      .long("output")
+      .help("The directory to write the export to. It must not exist, or be empty.")
+      .num_args(1)
+      .required(true))
+    .arg(Arg::new("include_passwords")
+      .long("include-passwords")
+      .help("Write password item secrets in plain text. They are masked if not specified.")
+      .action(clap::ArgAction::SetTrue))
+    .arg(Arg::new("session")
+      .short('s')
+      .long("session")
+      .help("The name of the Infumap session to use. 'default' will be used if not specified.")
+      .num_args(1)
+      .default_value("default")
+      .required(false))
+}
+
+pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let session_name = sub_matches.get_one::<String>("session").unwrap();
+  let container_id = sub_matches.get_one::<String>("container_id").unwrap();
+  if !is_uid(container_id) {
+    return Err(format!("Invalid container id: '{}'.", container_id).into());
+  }
+  let output = sub_matches.get_one::<String>("output").unwrap();
+  let output_dir = expand_tilde(output).ok_or(format!("Could not interpret path '{}'.", output))?;
+  if let Ok(mut entries) = fs::read_dir(&output_dir).await
+    && entries.next_entry().await?.is_some()
+  {
+    return Err(format!("Output directory '{}' is not empty.", output_dir.display()).into());
+  }
+
+  let mut named_session = NamedInfuSession::get(session_name)
+    .await
+    .map_err(|e| format!("A problem occurred getting session '{}': {}.", session_name, e))?
+    .ok_or("Session does not exist - use the login CLI command to create one.")?;
+  let request_headers = build_session_headers(&named_session.session)?;
+  let mut client = build_http_client(Some(request_headers)).await?;
+
+  let request = json!({
+    "containerId": container_id,
+    "includePasswords": sub_matches.get_flag("include_passwords"),
+  });
+  let send_request = CommandRequest {
+    command: "export-markdown".to_owned(),
+    json_data: serde_json::to_string(&request)?,
+    base64_data: None,
+  };
+
+  print!("Exporting container '{}'... ", container_id);
+  let response =
+    client.post(named_session.command_url()?.clone()).json(&send_request).send().await.map_err(|e| format!("{}", e))?;
+  if named_session.update_from_response(&response).await? {
+    let request_headers = build_session_headers(&named_session.session)?;
+    client = build_http_client(Some(request_headers)).await?;
+  }
+  let export_response: CommandResponse = response.json().await.map_err(|e| format!("{}", e))?;
+  if !export_response.success {
+    println!("failed.");
+    return Err(
+      format!(
+        "Infumap rejected the export-markdown command (reason: {}).",
+        export_response.fail_reason.unwrap_or("unknown".to_owned())
+      )
+      .into(),
+    );
+  }
+
+  let export = export_response.json_data.ok_or("Export response has no data.")?;
+  let export: ExportMarkdownResponse = serde_json::from_str(&export).map_err(|e| e.to_string())?;
+  for file in &export.files {
+    let path = export_file_path(&output_dir, &file.path).await?;
+    let data = general_purpose::STANDARD
+      .decode(&file.base64_data)
+      .map_err(|e| format!("There was a problem decoding base64 data for '{}': {}", file.path, e))?;
+    fs::write(&path, data).await.map_err(|e| format!("Could not write '{}': {}", path.display(), e))?;
+  }
+  println!("done.");
+
+  // File and image data is downloaded one item at a time, and streamed to disk.
+  for (index, asset) in export.assets.iter().enumerate() {
+    print!("Downloading file {} of {}... ", index + 1, export.assets.len());
+    let path = export_file_path(&output_dir, &asset.path).await?;
+    let mut response =
+      client.get(named_session.file_url(&asset.item_id)?).send().await.map_err(|e| format!("{}", e))?;
+    if named_session.update_from_response(&response).await? {
+      let request_headers = build_session_headers(&named_session.session)?;
+      client = build_http_client(Some(request_headers)).await?;
+    }
+    if !response.status().is_success() {
+      println!("failed.");
+      return Err(format!("Could not download item '{}' (status {}).", asset.item_id, response.status()).into());
+    }
+    let mut file =
+      fs::File::create(&path).await.map_err(|e| format!("Could not create '{}': {}", path.display(), e))?;
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("{}", e))? {
+      file.write_all(&chunk).await.map_err(|e| format!("Could not write '{}': {}", path.display(), e))?;
+    }
+    file.flush().await?;
+    println!("done.");
+  }
+
+  println!(
+    "Exported {} page(s), {} file(s) and {} item(s) to '{}', {} skipped.",
+    export.page_count,
+    export.file_count,
+    export.item_count,
+    output_dir.display(),
+    export.skipped_count
+  );
+
+  Ok(())
+}
+
+/// The path in the output directory for a path in the export, with its parent directories created.
+async fn export_file_path(output_dir: &Path, export_path: &str) -> InfuResult<PathBuf> {
+  let relative_path = Path::new(export_path);
+  if !relative_path.components().all(|component| matches!(component, Component::Normal(_))) {
+    return Err(format!("Export file path '{}' is not a relative path.", export_path).into());
+  }
+  let path = output_dir.join(relative_path);
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).await?;
+  }
+  Ok(path)
+}
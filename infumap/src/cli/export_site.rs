@@ -0,0 +1,108 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use clap::{Arg, ArgMatches, Command};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::is_uid;
+
+use super::extract::{CliRuntime, init_runtime};
+use crate::config::{CONFIG_CACHE_DIR, CONFIG_CACHE_MAX_MB};
+use crate::export::site::{SITE_IMAGE_WIDTH_PX_DEFAULT, export_site};
+use crate::storage::cache as storage_cache;
+use crate::util::fs::expand_tilde;
+
+pub fn make_clap_subcommand() -> Command {
+  Command::new("export-site")
+    .about("Render a public page, and the public pages below it, to a directory of static html.")
+    .arg(
+      Arg::new("settings_path")
+        .short('s')
+        .long("settings")
+        .help("Path to a toml settings configuration file. If not specified, the default will be assumed.")
+        .num_args(1)
+        .required(false),
+    )
+    .arg(
+      Arg::new("page_id")
+        .long("page-id")
+        .help("The id of the public page to export. It becomes index.html of the exported site.")
+        .num_args(1)
+        .required(true),
+    )
+    .arg(
+      Arg::new("output")
+        .short('o')
+        .long("output")
+        .help("The directory to write the site to. It is created if it does not exist.")
+        .num_args(1)
+        .required(true),
+    )
+    .arg(
+      Arg::new("image_width")
+        .long("image-width")
+        .help("The maximum width, in pixels, of exported images.")
+        .num_args(1)
+        .value_parser(clap::value_parser!(u32).range(1..))
+        .required(false),
+    )
+    .arg(
+      Arg::new("base_url")
+        .long("base-url")
+        .help("The url the site will be served from. If specified, a sitemap.xml is also written.")
+        .num_args(1)
+        .required(false),
+    )
+}
+
+pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let page_id = sub_matches.get_one::<String>("page_id").unwrap();
+  if !is_uid(page_id) {
+    return Err(format!("Invalid page id: '{}'.", page_id).into());
+  }
+  let output = sub_matches.get_one::<String>("output").unwrap();
+  let out_dir: PathBuf = expand_tilde(output).ok_or(format!("Could not interpret output path '{}'.", output))?;
+  let image_width_px = sub_matches.get_one::<u32>("image_width").copied().unwrap_or(SITE_IMAGE_WIDTH_PX_DEFAULT);
+
+  let CliRuntime { config, data_dir, db, object_store } =
+    init_runtime(sub_matches.get_one::<String>("settings_path")).await?;
+  let cache_dir = config.get_string(CONFIG_CACHE_DIR).map_err(|e| e.to_string())?;
+  let cache_max_mb = usize::try_from(config.get_int(CONFIG_CACHE_MAX_MB).map_err(|e| e.to_string())?)?;
+  let image_cache =
+    storage_cache::new(&cache_dir, cache_max_mb).await.map_err(|e| format!("Failed to initialize cache: {}", e))?;
+
+  let summary = export_site(
+    &data_dir,
+    db,
+    object_store,
+    image_cache,
+    page_id,
+    &out_dir,
+    image_width_px,
+    sub_matches.get_one::<String>("base_url").map(String::as_str),
+  )
+  .await?;
+  println!(
+    "Exported {} page(s), {} image(s) and {} file(s) to '{}'. {} item(s) were not exported.",
+    summary.pages,
+    summary.images,
+    summary.files,
+    out_dir.display(),
+    summary.skipped_items
+  );
+  Ok(())
+}
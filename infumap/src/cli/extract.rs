@@ -271,11 +271,11 @@ fn settings_arg() -> Arg {
     .required(false)
 }
 
-struct CliRuntime {
-  config: Config,
-  data_dir: String,
-  db: Arc<Mutex<Db>>,
-  object_store: Arc<storage_object::ObjectStore>,
+pub(super) struct CliRuntime {
+  pub(super) config: Config,
+  pub(super) data_dir: String,
+  pub(super) db: Arc<Mutex<Db>>,
+  pub(super) object_store: Arc<storage_object::ObjectStore>,
 }
 
 #[derive(Clone, Copy)]
@@ -484,7 +484,7 @@ async fn execute_image(sub_matches: &ArgMatches) -> InfuResult<()> {
   Ok(())
 }
 
-async fn init_runtime(settings_path_maybe: Option<&String>) -> InfuResult<CliRuntime> {
+pub(super) async fn init_runtime(settings_path_maybe: Option<&String>) -> InfuResult<CliRuntime> {
   let config = get_config(settings_path_maybe).await?;
   let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
   let db = Arc::new(Mutex::new(Db::new(&data_dir).await.map_err(|e| format!("Failed to initialize database: {}", e))?));
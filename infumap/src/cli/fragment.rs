@@ -1,18 +1,28 @@
+use std::collections::HashSet;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use clap::{Arg, ArgMatches, Command};
 use infusdk::item::{Item, ItemType};
 use infusdk::util::infu::InfuResult;
-use log::info;
+use infusdk::util::uid::new_uid;
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::fs;
 use tokio::sync::Mutex;
 
 use crate::ai::fragment::sources::{
-  build_image_fragment_artifact, build_markdown_fragment_artifact, build_text_fragment_artifact,
-  embedding_context_title_for_item, pdf_fragment_source_for_item,
+  DocumentFormat, build_converted_document_fragment_artifact, build_image_fragment_artifact,
+  build_markdown_fragment_artifact, build_text_fragment_artifact, chunkable_markdown_for_item,
+  embedding_context_title_for_item, markdown_fragment_inputs, pdf_fragment_source_for_item,
+};
+use crate::ai::fragment::{
+  ChunkingStrategy, FragmentBuildOutcome, FragmentSource, clear_item_fragments, init_chunking_strategies,
+  write_item_fragments,
 };
-use crate::ai::fragment::{FragmentBuildOutcome, FragmentSource, clear_item_fragments, write_item_fragments};
 use crate::ai::image_tagging::should_tag_image_item;
+use crate::ai::lexical_index::{FragmentLexicalIndexRebuildMetadata, LexicalFragment, TantivyDocumentFragmentIndex};
 use crate::config::{
   CONFIG_DATA_DIR, CONFIG_ENABLE_LOCAL_OBJECT_STORAGE, CONFIG_ENABLE_S3_1_OBJECT_STORAGE,
   CONFIG_ENABLE_S3_2_OBJECT_STORAGE, CONFIG_S3_1_BUCKET, CONFIG_S3_1_ENDPOINT, CONFIG_S3_1_KEY, CONFIG_S3_1_REGION,
@@ -28,6 +38,9 @@ const TEXT_MIME_TYPE: &str = "text/plain";
 const PDF_MIME_TYPE: &str = "application/pdf";
 const FRAGMENT_PROGRESS_ITEM_INTERVAL: usize = 5000;
 const FRAGMENT_PROGRESS_TIME_INTERVAL: Duration = Duration::from_secs(10);
+const COMPARE_DEFAULT_SAMPLE_SIZE: usize = 200;
+const COMPARE_HIT_DEPTHS: [usize; 3] = [1, 5, 10];
+const COMPARE_FRAGMENT_SOURCE_KIND: &str = "chunking_comparison";
 
 #[derive(Clone, Copy)]
 enum FragmentTargetKind {
@@ -35,6 +48,7 @@ enum FragmentTargetKind {
   Markdown,
   Text,
   Pdf,
+  Document,
 }
 
 impl FragmentTargetKind {
@@ -50,6 +64,7 @@ impl FragmentTargetKind {
           && item.mime_type.as_deref() == Some(TEXT_MIME_TYPE)
       }
       FragmentTargetKind::Pdf => item.item_type == ItemType::File && item.mime_type.as_deref() == Some(PDF_MIME_TYPE),
+      FragmentTargetKind::Document => item.item_type == ItemType::File && DocumentFormat::from_item(item).is_some(),
     }
   }
 
@@ -59,6 +74,7 @@ impl FragmentTargetKind {
       FragmentTargetKind::Markdown => "Markdown file",
       FragmentTargetKind::Text => "text file",
       FragmentTargetKind::Pdf => "PDF file",
+      FragmentTargetKind::Document => "DOCX, ODT, EPUB or HTML file",
     }
   }
 
@@ -68,6 +84,7 @@ impl FragmentTargetKind {
       FragmentTargetKind::Markdown => "markdown",
       FragmentTargetKind::Text => "text",
       FragmentTargetKind::Pdf => "pdf",
+      FragmentTargetKind::Document => "document",
     }
   }
 }
@@ -90,6 +107,8 @@ pub fn make_clap_subcommand() -> Command {
     .subcommand(make_markdown_subcommand())
     .subcommand(make_text_subcommand())
     .subcommand(make_pdf_subcommand())
+    .subcommand(make_document_subcommand())
+    .subcommand(make_compare_subcommand())
 }
 
 pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
@@ -98,8 +117,10 @@ pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
     Some(("markdown", sub_matches)) => execute_markdown(sub_matches).await,
     Some(("text", sub_matches)) => execute_text(sub_matches).await,
     Some(("pdf", sub_matches)) => execute_pdf(sub_matches).await,
+    Some(("document", sub_matches)) => execute_document(sub_matches).await,
+    Some(("compare", sub_matches)) => execute_compare(sub_matches).await,
     _ => Err(
-      "Missing fragment subcommand. Use 'fragment image', 'fragment markdown', 'fragment text', or 'fragment pdf'."
+      "Missing fragment subcommand. Use 'fragment image', 'fragment markdown', 'fragment text', 'fragment pdf', 'fragment document', or 'fragment compare'."
         .into(),
     ),
   }
@@ -135,6 +156,53 @@ fn make_pdf_subcommand() -> Command {
     .arg(item_id_arg("Build fragments only for this PDF item."))
 }
 
+fn make_document_subcommand() -> Command {
+  Command::new("document")
+    .about("Build lexical text fragments by converting DOCX, ODT, EPUB and HTML file items to markdown.")
+    .arg(settings_arg())
+    .arg(item_id_arg("Build fragments only for this DOCX, ODT, EPUB or HTML file item."))
+}
+
+fn make_compare_subcommand() -> Command {
+  Command::new("compare")
+    .about(
+      "Compare lexical retrieval quality of two chunking strategies over PDF, Markdown, text and document items, using queries with known answers. Fragment artifacts are not modified.",
+    )
+    .arg(settings_arg())
+    .arg(
+      Arg::new("strategy_a")
+        .long("strategy-a")
+        .help("The first chunking strategy, e.g. 'mode=heading_aware target_tokens=380'.")
+        .num_args(1)
+        .required(true),
+    )
+    .arg(
+      Arg::new("strategy_b")
+        .long("strategy-b")
+        .help("The second chunking strategy, e.g. 'mode=sliding_window target_tokens=256 overlap_tokens=48'.")
+        .num_args(1)
+        .required(true),
+    )
+    .arg(
+      Arg::new("queries_path")
+        .long("queries")
+        .help(
+          "Path to a JSON lines file of queries, each of the form {\"query\": \"...\", \"item_id\": \"...\", \"page\": 3}: the item (and optionally the page) that answers the query.",
+        )
+        .num_args(1)
+        .required(true),
+    )
+    .arg(
+      Arg::new("sample")
+        .long("sample")
+        .help(
+          "Number of other items of the same users to add to the corpus, so that queries compete with unrelated text. Defaults to 200.",
+        )
+        .num_args(1)
+        .required(false),
+    )
+}
+
 fn settings_arg() -> Arg {
   Arg::new("settings_path")
     .short('s')
@@ -254,21 +322,45 @@ async fn execute_pdf(sub_matches: &ArgMatches) -> InfuResult<()> {
   Ok(())
 }
 
+async fn execute_document(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let (data_dir, db, items) = load_db_and_items(sub_matches, FragmentTargetKind::Document).await?;
+  let mut summary = FragmentRunSummary::default();
+  if items.is_empty() {
+    log_fragment_summary(FragmentTargetKind::Document, &summary);
+    return Ok(());
+  }
+
+  let object_store = load_object_store(sub_matches, &data_dir).await?;
+  let single_item_run = sub_matches.get_one::<String>("item_id").is_some();
+  let mut progress = FragmentRunProgress::new(FragmentTargetKind::Document, items.len());
+
+  for (index, item) in items.into_iter().enumerate() {
+    progress.log_before_item(index, &item);
+    let object_encryption_key = {
+      let db = db.lock().await;
+      db.user.get(&item.owner_id).ok_or(format!("User '{}' not loaded.", item.owner_id))?.object_encryption_key.clone()
+    };
+    let build_result =
+      build_converted_document_fragment_artifact(&data_dir, object_store.clone(), &item, &object_encryption_key)
+        .await?;
+    let had_fragment_source = build_result.had_fragment_source;
+    let outcome = build_result.outcome;
+    record_fragment_outcome(&mut summary, &outcome);
+    if single_item_run {
+      log_single_item_fragment_outcome(FragmentTargetKind::Document, &item, had_fragment_source, &outcome);
+    }
+    progress.log_after_item(index + 1, &summary);
+  }
+
+  log_fragment_summary(FragmentTargetKind::Document, &summary);
+  Ok(())
+}
+
 async fn load_db_and_items(
   sub_matches: &ArgMatches,
   target_kind: FragmentTargetKind,
 ) -> InfuResult<(String, Arc<Mutex<Db>>, Vec<Item>)> {
-  let config = get_config(sub_matches.get_one::<String>("settings_path")).await?;
-  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
-  let db = Arc::new(Mutex::new(Db::new(&data_dir).await.map_err(|e| format!("Failed to initialize database: {}", e))?));
-
-  {
-    let mut db = db.lock().await;
-    let all_user_ids: Vec<String> = db.user.all_user_ids().iter().map(|value| value.clone()).collect();
-    for user_id in all_user_ids {
-      db.item.load_user_items(&user_id, false).await?;
-    }
-  }
+  let (data_dir, db) = load_db(sub_matches).await?;
 
   let items = {
     let db = db.lock().await;
@@ -301,6 +393,23 @@ async fn load_db_and_items(
   Ok((data_dir, db, items))
 }
 
+async fn load_db(sub_matches: &ArgMatches) -> InfuResult<(String, Arc<Mutex<Db>>)> {
+  let config = get_config(sub_matches.get_one::<String>("settings_path")).await?;
+  init_chunking_strategies(&config)?;
+  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+  let db = Arc::new(Mutex::new(Db::new(&data_dir).await.map_err(|e| format!("Failed to initialize database: {}", e))?));
+
+  {
+    let mut db = db.lock().await;
+    let all_user_ids: Vec<String> = db.user.all_user_ids().iter().map(|value| value.clone()).collect();
+    for user_id in all_user_ids {
+      db.item.load_user_items(&user_id, false).await?;
+    }
+  }
+
+  Ok((data_dir, db))
+}
+
 async fn load_object_store(sub_matches: &ArgMatches, data_dir: &str) -> InfuResult<Arc<ObjectStore>> {
   let config = get_config(sub_matches.get_one::<String>("settings_path")).await?;
   storage_object::new(
@@ -471,3 +580,223 @@ fn truncate_for_log(value: &str, max_chars: usize) -> String {
   truncated.push_str("...");
   truncated
 }
+
+#[derive(Deserialize)]
+struct CompareQuery {
+  query: String,
+  item_id: String,
+  page: Option<usize>,
+}
+
+struct CompareCorpusItem {
+  item_id: String,
+  markdown: String,
+}
+
+#[derive(Default)]
+struct StrategyEvaluation {
+  fragment_count: usize,
+  fragment_words: usize,
+  hits_at_depth: [usize; COMPARE_HIT_DEPTHS.len()],
+  reciprocal_rank_sum: f64,
+}
+
+async fn execute_compare(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let strategy_a = parse_strategy_arg(sub_matches, "strategy_a")?;
+  let strategy_b = parse_strategy_arg(sub_matches, "strategy_b")?;
+  let sample_size = match sub_matches.get_one::<String>("sample") {
+    Some(value) => value.parse::<usize>().map_err(|e| format!("Could not parse --sample as usize: {}", e))?,
+    None => COMPARE_DEFAULT_SAMPLE_SIZE,
+  };
+  let queries_path = sub_matches.get_one::<String>("queries_path").ok_or("Missing --queries.")?;
+  let queries = read_compare_queries(queries_path).await?;
+
+  let (data_dir, db) = load_db(sub_matches).await?;
+  let object_store = load_object_store(sub_matches, &data_dir).await?;
+  let corpus_items = select_compare_corpus_items(&db, &queries, sample_size).await?;
+  info!("Loading markdown for {} item(s) to compare chunking strategies on.", corpus_items.len());
+
+  let mut corpus = Vec::new();
+  for item in &corpus_items {
+    let object_encryption_key = {
+      let db = db.lock().await;
+      db.user.get(&item.owner_id).ok_or(format!("User '{}' not loaded.", item.owner_id))?.object_encryption_key.clone()
+    };
+    match chunkable_markdown_for_item(&data_dir, object_store.clone(), item, &object_encryption_key).await {
+      Ok(Some(markdown)) => corpus.push(CompareCorpusItem { item_id: item.id.clone(), markdown }),
+      Ok(None) => {}
+      Err(e) => warn!("Could not load markdown for item '{}', leaving it out of the comparison: {}", item.id, e),
+    }
+  }
+
+  let corpus_item_ids = corpus.iter().map(|corpus_item| corpus_item.item_id.as_str()).collect::<HashSet<&str>>();
+  let queries = queries
+    .iter()
+    .filter(|query| {
+      let has_text = corpus_item_ids.contains(query.item_id.as_str());
+      if !has_text {
+        warn!("Item '{}' has no text to search, skipping query '{}'.", query.item_id, query.query);
+      }
+      has_text
+    })
+    .collect::<Vec<&CompareQuery>>();
+  if queries.is_empty() {
+    return Err("None of the queries refer to an item with text to search.".into());
+  }
+
+  let compare_dir = std::env::temp_dir().join(format!("infumap_fragment_compare_{}", new_uid()));
+  let evaluations = evaluate_strategies(&compare_dir, &[strategy_a, strategy_b], &corpus, &queries).await;
+  if let Err(e) = fs::remove_dir_all(&compare_dir).await {
+    warn!("Could not remove chunking comparison directory '{}': {}", compare_dir.display(), e);
+  }
+  let evaluations = evaluations?;
+
+  println!("Compared chunking strategies on {} query(s) over {} item(s).", queries.len(), corpus.len());
+  println!();
+  println!(
+    "{:<10} {:>10} {:>10} {:>8} {:>8} {:>8} {:>8}",
+    "strategy", "fragments", "mean words", "hit@1", "hit@5", "hit@10", "MRR"
+  );
+  for (label, evaluation) in ["A", "B"].iter().zip(evaluations.iter()) {
+    println!(
+      "{:<10} {:>10} {:>10.1} {:>8.3} {:>8.3} {:>8.3} {:>8.3}",
+      label,
+      evaluation.fragment_count,
+      evaluation.fragment_words as f64 / evaluation.fragment_count.max(1) as f64,
+      evaluation.hits_at_depth[0] as f64 / queries.len() as f64,
+      evaluation.hits_at_depth[1] as f64 / queries.len() as f64,
+      evaluation.hits_at_depth[2] as f64 / queries.len() as f64,
+      evaluation.reciprocal_rank_sum / queries.len() as f64
+    );
+  }
+  println!();
+  println!("A: {}", strategy_a);
+  println!("B: {}", strategy_b);
+  Ok(())
+}
+
+fn parse_strategy_arg(sub_matches: &ArgMatches, arg_name: &str) -> InfuResult<ChunkingStrategy> {
+  let flag = format!("--{}", arg_name.replace('_', "-"));
+  let spec = sub_matches.get_one::<String>(arg_name).ok_or(format!("Missing {}.", flag))?;
+  ChunkingStrategy::parse(spec).map_err(|e| format!("Invalid {}: {}", flag, e).into())
+}
+
+async fn read_compare_queries(path: &str) -> InfuResult<Vec<CompareQuery>> {
+  let contents =
+    fs::read_to_string(path).await.map_err(|e| format!("Could not read queries file '{}': {}", path, e))?;
+  let mut queries = Vec::new();
+  for (index, line) in contents.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    let query = serde_json::from_str::<CompareQuery>(line)
+      .map_err(|e| format!("Could not parse query on line {} of '{}': {}", index + 1, path, e))?;
+    queries.push(query);
+  }
+  if queries.is_empty() {
+    return Err(format!("Queries file '{}' contains no queries.", path).into());
+  }
+  Ok(queries)
+}
+
+/// The items the queries refer to, and up to `sample_size` other items of the same users that
+/// fragments are chunked from. The sample is taken in item id order, so it is stable between runs.
+async fn select_compare_corpus_items(
+  db: &Arc<Mutex<Db>>,
+  queries: &[CompareQuery],
+  sample_size: usize,
+) -> InfuResult<Vec<Item>> {
+  let db = db.lock().await;
+  let mut query_items = Vec::new();
+  let mut selected_ids = HashSet::new();
+  for query in queries {
+    let item = db.item.get(&query.item_id).map_err(|e| format!("Query '{}': {}", query.query, e))?;
+    if selected_ids.insert(item.id.clone()) {
+      query_items.push(item.clone());
+    }
+  }
+  let owner_ids = query_items.iter().map(|item| item.owner_id.clone()).collect::<HashSet<String>>();
+
+  let mut sample_items = db
+    .item
+    .all_loaded_items()
+    .into_iter()
+    .filter(|item_and_user_id| owner_ids.contains(&item_and_user_id.user_id))
+    .filter(|item_and_user_id| !selected_ids.contains(&item_and_user_id.item_id))
+    .filter_map(|item_and_user_id| db.item.get(&item_and_user_id.item_id).ok().cloned())
+    .filter(|item| {
+      FragmentTargetKind::Markdown.matches_item(item)
+        || FragmentTargetKind::Text.matches_item(item)
+        || FragmentTargetKind::Pdf.matches_item(item)
+        || FragmentTargetKind::Document.matches_item(item)
+    })
+    .collect::<Vec<Item>>();
+  sample_items.sort_by(|a, b| a.id.cmp(&b.id));
+  sample_items.truncate(sample_size);
+
+  query_items.extend(sample_items);
+  Ok(query_items)
+}
+
+async fn evaluate_strategies(
+  compare_dir: &Path,
+  strategies: &[ChunkingStrategy],
+  corpus: &[CompareCorpusItem],
+  queries: &[&CompareQuery],
+) -> InfuResult<Vec<StrategyEvaluation>> {
+  let max_depth = COMPARE_HIT_DEPTHS[COMPARE_HIT_DEPTHS.len() - 1];
+  let mut evaluations = Vec::new();
+  for (index, strategy) in strategies.iter().enumerate() {
+    let mut evaluation = StrategyEvaluation::default();
+    let mut fragments = Vec::new();
+    for corpus_item in corpus {
+      let inputs = markdown_fragment_inputs(&corpus_item.markdown, strategy);
+      for input in inputs.into_iter().filter(|input| !input.text.trim().is_empty()) {
+        evaluation.fragment_words += input.text.split_whitespace().count();
+        fragments.push(LexicalFragment {
+          item_id: corpus_item.item_id.clone(),
+          ordinal: fragments.len(),
+          source_kind: COMPARE_FRAGMENT_SOURCE_KIND.to_owned(),
+          text: input.text,
+          page_start: input.page_start,
+          page_end: input.page_end,
+          row_item_id: input.row_item_id,
+          time_start_secs: input.time_start_secs,
+          time_end_secs: input.time_end_secs,
+        });
+      }
+    }
+    evaluation.fragment_count = fragments.len();
+    info!("Indexing {} fragment(s) cut with chunking strategy '{}'.", fragments.len(), strategy);
+
+    let index_dir = compare_dir.join(format!("strategy_{}", index));
+    let temp_index_dir = compare_dir.join(format!("strategy_{}_tmp", index));
+    let index = TantivyDocumentFragmentIndex::new(index_dir);
+    let metadata = FragmentLexicalIndexRebuildMetadata {
+      source_digest: strategy.to_string(),
+      expected_fragment_count: fragments.len(),
+    };
+    index.rebuild_from_fragments(&temp_index_dir, &metadata, &fragments).await?;
+
+    for query in queries {
+      let hits = index.search(&query.query, max_depth).await?;
+      let rank = hits.iter().position(|hit| {
+        hit.item_id == query.item_id
+          && match (query.page, hit.page_start, hit.page_end) {
+            (Some(page), Some(page_start), Some(page_end)) => page_start <= page && page <= page_end,
+            _ => true,
+          }
+      });
+      if let Some(rank) = rank {
+        for (depth_index, depth) in COMPARE_HIT_DEPTHS.iter().enumerate() {
+          if rank < *depth {
+            evaluation.hits_at_depth[depth_index] += 1;
+          }
+        }
+        evaluation.reciprocal_rank_sum += 1.0 / (rank + 1) as f64;
+      }
+    }
+    evaluations.push(evaluation);
+  }
+  Ok(evaluations)
+}
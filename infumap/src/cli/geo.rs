@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -9,9 +10,11 @@ use tokio::sync::Mutex;
 use tokio::time::sleep;
 
 use super::build_http_client;
+use crate::ai::gazetteer::{gazetteer_path, import_geonames_gazetteer};
 use crate::ai::geo::{
-  GeoCandidate, GeoProcessOutcome, GeoRequestThrottle, GeoRunSummary, geoapify_max_requests_per_minute_from_config,
-  geoapify_url_from_config, resolve_geoapify_api_key, reverse_geocode_candidate_if_needed,
+  GEOAPIFY_PROVIDER_NAME, GeoCandidate, GeoProcessOutcome, GeoProvider, GeoRequestThrottle, GeoRunSummary,
+  OFFLINE_PROVIDER_NAME, geo_provider_name_from_config, geoapify_max_requests_per_minute_from_config,
+  geoapify_url_from_config, offline_geo_provider, resolve_geoapify_api_key, reverse_geocode_candidate_if_needed,
 };
 use crate::config::CONFIG_DATA_DIR;
 use crate::setup::get_config;
@@ -21,6 +24,13 @@ pub fn make_clap_subcommand() -> Command {
   Command::new("geo")
     .about("Reverse geocode GPS-tagged images that already have image-tag output.")
     .arg(settings_arg())
+    .arg(
+      Arg::new("provider")
+        .long("provider")
+        .help("Reverse geocoding provider: 'geoapify' or 'offline'. Falls back to geo_provider in settings.toml.")
+        .num_args(1)
+        .required(false),
+    )
     .arg(
       Arg::new("service_url")
         .long("service-url")
@@ -28,6 +38,27 @@ pub fn make_clap_subcommand() -> Command {
         .num_args(1)
         .required(false),
     )
+    .arg(
+      Arg::new("import_gazetteer")
+        .long("import-gazetteer")
+        .help("Import a GeoNames cities file (e.g. cities1000.txt) as the gazetteer used by the offline provider, replacing any previous import. Exits after importing.")
+        .num_args(1)
+        .required(false),
+    )
+    .arg(
+      Arg::new("admin1_codes")
+        .long("admin1-codes")
+        .help("GeoNames admin1CodesASCII.txt, used with --import-gazetteer to name regions.")
+        .num_args(1)
+        .required(false),
+    )
+    .arg(
+      Arg::new("country_info")
+        .long("country-info")
+        .help("GeoNames countryInfo.txt, used with --import-gazetteer to name countries.")
+        .num_args(1)
+        .required(false),
+    )
     .arg(
       Arg::new("item_id")
         .long("item-id")
@@ -63,12 +94,37 @@ pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
   let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
   let db = Arc::new(Mutex::new(Db::new(&data_dir).await.map_err(|e| format!("Failed to initialize database: {}", e))?));
 
-  let api_key = resolve_geoapify_api_key(&config)?;
-  let service_url =
-    match sub_matches.get_one::<String>("service_url").map(|value| value.trim()).filter(|value| !value.is_empty()) {
-      Some(value) => value.to_owned(),
-      None => geoapify_url_from_config(&config)?,
+  if let Some(cities_path) = sub_matches.get_one::<String>("import_gazetteer") {
+    let admin1_codes_path = sub_matches.get_one::<String>("admin1_codes").map(PathBuf::from);
+    let country_info_path = sub_matches.get_one::<String>("country_info").map(PathBuf::from);
+    let count = import_geonames_gazetteer(
+      &data_dir,
+      &PathBuf::from(cities_path),
+      admin1_codes_path.as_deref(),
+      country_info_path.as_deref(),
+    )
+    .await?;
+    println!("Imported {} place(s) into '{}'.", count, gazetteer_path(&data_dir)?.display());
+    return Ok(());
+  }
+
+  let provider_name =
+    match sub_matches.get_one::<String>("provider").map(|value| value.trim()).filter(|value| !value.is_empty()) {
+      Some(value) => value.to_ascii_lowercase(),
+      None => geo_provider_name_from_config(&config)?,
     };
+  let provider = if provider_name == GEOAPIFY_PROVIDER_NAME {
+    let service_url =
+      match sub_matches.get_one::<String>("service_url").map(|value| value.trim()).filter(|value| !value.is_empty()) {
+        Some(value) => value.to_owned(),
+        None => geoapify_url_from_config(&config)?,
+      };
+    GeoProvider::Geoapify { service_url, api_key: resolve_geoapify_api_key(&config)? }
+  } else if provider_name == OFFLINE_PROVIDER_NAME {
+    offline_geo_provider(&data_dir)?
+  } else {
+    return Err(format!("--provider must be '{}' or '{}'.", GEOAPIFY_PROVIDER_NAME, OFFLINE_PROVIDER_NAME).into());
+  };
   let overwrite = sub_matches.get_flag("overwrite") || sub_matches.get_one::<String>("item_id").is_some();
   let max_requests = parse_optional_usize(sub_matches, "max_requests")?;
   let delay = parse_delay_secs(sub_matches)?;
@@ -89,7 +145,7 @@ pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
   info!(
     "Running reverse geocoding for {} supported image(s) using '{}' (overwrite={}, delay {:.3}s).",
     candidates.len(),
-    service_url,
+    provider.source(),
     overwrite,
     delay.as_secs_f64()
   );
@@ -105,8 +161,7 @@ pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
     let outcome = reverse_geocode_candidate_if_needed(
       &data_dir,
       &client,
-      &service_url,
-      &api_key,
+      &provider,
       &candidate,
       overwrite,
       &mut cache,
@@ -0,0 +1,180 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use base64::{Engine as _, engine::general_purpose};
+use clap::{Arg, ArgMatches, Command};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::is_uid;
+use serde_json::{Map, Value};
+use tokio::fs;
+
+use crate::cli::{NamedInfuSession, build_http_client, build_session_headers};
+use crate::util::email::is_mbox;
+use crate::util::fs::expand_tilde;
+use crate::web::routes::command::{CommandRequest, CommandResponse};
+
+/// Envelope line used when several .eml files are combined into one mbox for a single import, so
+/// the server can group replies from different files into the same thread.
+const MBOX_SEPARATOR_LINE: &[u8] = b"From MAILER-DAEMON Thu Jan  1 00:00:00 1970\n";
+
+pub fn make_clap_subcommand() -> Command {
+  Command::new("import-email")
+    .about("Import .eml files or .mbox archives into an Infumap container, as a page per thread with a note per message.")
+    .arg(Arg::new("path")
+      .short('p')
+      .long("path")
+      .help("An .eml file, an .mbox archive, or a directory containing either. May be specified more than once.")
+      .num_args(1)
+      .action(clap::ArgAction::Append)
+      .required(true))
+    .arg(Arg::new("container_id")
+      .short('c')
+      .long("container-id")
+      .help("The id of the container to add thread pages to. If omitted, they will be added to the root container of the session user.")
+      .num_args(1)
+      .required(false))
+    .arg(Arg::new("session")
+      .short('s')
+      .long("session")
+      .help("The name of the Infumap session to use. 'default' will be used if not specified.")
+      .num_args(1)
+      .default_value("default")
+      .required(false))
+}
+
+pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let session_name = sub_matches.get_one::<String>("session").unwrap();
+  let container_id_maybe = match sub_matches.get_one::<String>("container_id") {
+    Some(uid_maybe) => {
+      if !is_uid(uid_maybe) {
+        return Err(format!("Invalid container id: '{}'.", uid_maybe).into());
+      }
+      Some(uid_maybe)
+    }
+    None => None,
+  };
+
+  let mut files = vec![];
+  for path in sub_matches.get_many::<String>("path").unwrap() {
+    let path = expand_tilde(path).ok_or(format!("Could not interpret path '{}'.", path))?;
+    files.extend(email_files(path).await?);
+  }
+  if files.is_empty() {
+    return Err("No .eml or .mbox files were found.".into());
+  }
+
+  let mut data = vec![];
+  for file in &files {
+    let bytes = fs::read(file).await.map_err(|e| format!("Could not read '{}': {}", file.display(), e))?;
+    if files.len() == 1 {
+      data = bytes;
+      break;
+    }
+    append_to_mbox(&mut data, &bytes);
+  }
+
+  let mut named_session = NamedInfuSession::get(session_name)
+    .await
+    .map_err(|e| format!("A problem occurred getting session '{}': {}.", session_name, e))?
+    .ok_or("Session does not exist - use the login CLI command to create one.")?;
+  let request_headers = build_session_headers(&named_session.session)?;
+  let client = build_http_client(Some(request_headers)).await?;
+
+  let mut request = Map::new();
+  if let Some(container_id) = container_id_maybe {
+    request.insert("parentId".to_owned(), Value::String(container_id.clone()));
+  }
+  let send_request = CommandRequest {
+    command: "import-email".to_owned(),
+    json_data: serde_json::to_string(&request)?,
+    base64_data: Some(general_purpose::STANDARD.encode(&data)),
+  };
+
+  print!("Importing {} file(s)... ", files.len());
+  let response =
+    client.post(named_session.command_url()?.clone()).json(&send_request).send().await.map_err(|e| format!("{}", e))?;
+  named_session.update_from_response(&response).await?;
+  let import_response: CommandResponse = response.json().await.map_err(|e| format!("{}", e))?;
+  if !import_response.success {
+    println!("failed.");
+    return Err(
+      format!(
+        "Infumap rejected the import-email command (reason: {}).",
+        import_response.fail_reason.unwrap_or("unknown".to_owned())
+      )
+      .into(),
+    );
+  }
+
+  let summary = import_response.json_data.ok_or("Import response has no data.")?;
+  let summary = serde_json::from_str::<Map<String, Value>>(&summary).map_err(|e| e.to_string())?;
+  let count = |name: &str| summary.get(name).and_then(Value::as_u64).unwrap_or(0);
+  let thread_count = summary.get("pageIds").and_then(Value::as_array).map(Vec::len).unwrap_or(0);
+  println!(
+    "done: {} message(s) in {} thread(s), {} attachment(s), {} skipped.",
+    count("messageCount"),
+    thread_count,
+    count("attachmentCount"),
+    count("skippedCount")
+  );
+
+  Ok(())
+}
+
+/// A single file, or the .eml and .mbox files directly inside a directory (sorted by name).
+async fn email_files(path: PathBuf) -> InfuResult<Vec<PathBuf>> {
+  let metadata = fs::metadata(&path).await.map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+  if !metadata.is_dir() {
+    return Ok(vec![path]);
+  }
+  let mut files = vec![];
+  let mut entries = fs::read_dir(&path).await?;
+  while let Some(entry) = entries.next_entry().await? {
+    let entry_path = entry.path();
+    let is_email_file = entry_path
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .is_some_and(|extension| matches!(extension.to_ascii_lowercase().as_str(), "eml" | "mbox"));
+    if is_email_file && entry.file_type().await?.is_file() {
+      files.push(entry_path);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+/// Append a message (or an existing mbox archive) to mbox data, quoting body lines that would
+/// otherwise be read as message separators.
+fn append_to_mbox(mbox: &mut Vec<u8>, bytes: &[u8]) {
+  if is_mbox(bytes) {
+    mbox.extend_from_slice(bytes);
+  } else {
+    mbox.extend_from_slice(MBOX_SEPARATOR_LINE);
+    for line in bytes.split_inclusive(|b| *b == b'\n') {
+      let unquoted = line.iter().position(|b| *b != b'>').is_some_and(|i| line[i..].starts_with(b"From "));
+      if unquoted {
+        mbox.push(b'>');
+      }
+      mbox.extend_from_slice(line);
+    }
+  }
+  if !mbox.ends_with(b"\n") {
+    mbox.push(b'\n');
+  }
+  mbox.push(b'\n');
+}
@@ -0,0 +1,811 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use async_recursion::async_recursion;
+use base64::{Engine as _, engine::general_purpose};
+use clap::{Arg, ArgMatches, Command};
+use infusdk::item::{ArrangeAlgorithm, Item, ItemType, RelationshipToParent, TableColumn, TableFlags};
+use infusdk::util::geometry::{GRID_SIZE, Vector};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::{Uid, is_uid, new_uid};
+use infusdk::web::WebApiJsonSerializable;
+use serde_json::{Map, Value};
+use tokio::fs;
+
+use crate::cli::{NamedInfuSession, build_http_client, build_session_headers};
+use crate::util::fs::expand_tilde;
+use crate::util::markdown::{
+  MarkdownInlineText, MarkdownItem, MarkdownTable, MarkdownUrl, NOTE_FLAG_BULLET1, NOTE_FLAG_NUMBERED,
+  markdown_code_fence_marker, markdown_items_from_text,
+};
+use crate::util::mime::mime_type_from_title_extension;
+use crate::util::ordering::new_ordering_at_end;
+use crate::util::str::percent_decode;
+use crate::web::routes::command::{CommandRequest, CommandResponse};
+
+const MARKDOWN_IMPORT_PAGE_WIDTH_GR: i64 = 8 * GRID_SIZE;
+const MARKDOWN_IMPORT_PAGE_INNER_WIDTH_GR: i64 = 60 * GRID_SIZE;
+const MARKDOWN_IMPORT_ITEM_WIDTH_GR: i64 = 30 * GRID_SIZE;
+const MARKDOWN_IMPORT_TABLE_MAX_HEIGHT_BL: i64 = 12;
+const MARKDOWN_IMPORT_MAX_ATTEMPTS: usize = 5;
+/// Wikilinks are rewritten to markdown links with this url scheme (followed by the index of the wikilink target)
+/// before the markdown is parsed, so they come out of the parser attached to the text they were in.
+const WIKILINK_URL_PREFIX: &str = "wikilink:";
+
+pub fn make_clap_subcommand() -> Command {
+  Command::new("import-markdown")
+    .about(concat!(
+      "Import a directory of markdown files, such as an Obsidian vault, into an Infumap container. Folders and ",
+      "markdown files become pages, wikilinks become links and embedded images become image items."
+    ))
+    .arg(Arg::new("path").short('p').long("path").help("The directory to import.").num_args(1).required(true))
+    .arg(
+      Arg::new("container_id")
+        .short('c')
+        .long("container-id")
+        .help(concat!(
+          "The id of the container to add the page for the directory to. If omitted, it will be added to the root ",
+          "container of the session user."
+        ))
+        .num_args(1)
+        .required(false),
+    )
+    .arg(
+      Arg::new("session")
+        .short('s')
+        .long("session")
+        .help("The name of the Infumap session to use. 'default' will be used if not specified.")
+        .num_args(1)
+        .default_value("default")
+        .required(false),
+    )
+}
+
+pub async fn execute(sub_matches: &ArgMatches) -> InfuResult<()> {
+  let session_name = sub_matches.get_one::<String>("session").unwrap();
+  let container_id_maybe = match sub_matches.get_one::<String>("container_id") {
+    Some(uid_maybe) => {
+      if !is_uid(uid_maybe) {
+        return Err(format!("Invalid container id: '{}'.", uid_maybe).into());
+      }
+      Some(uid_maybe.clone())
+    }
+    None => None,
+  };
+
+  let path = sub_matches.get_one::<String>("path").unwrap();
+  let vault_path = expand_tilde(path).ok_or(format!("Could not interpret path '{}'.", path))?;
+  if !fs::metadata(&vault_path).await.map_err(|e| format!("Could not read '{}': {}", path, e))?.is_dir() {
+    return Err(format!("'{}' is not a directory.", path).into());
+  }
+  let vault_title = vault_path
+    .canonicalize()?
+    .file_name()
+    .and_then(|name| name.to_str())
+    .map(str::to_owned)
+    .unwrap_or("Markdown".to_owned());
+
+  let mut vault = Vault { path: vault_path.clone(), ..Default::default() };
+  let root_folder = read_vault_folder(&mut vault, &vault_path, "", &vault_title)
+    .await?
+    .ok_or(format!("No markdown files were found in '{}'.", path))?;
+
+  let mut named_session = NamedInfuSession::get(session_name)
+    .await
+    .map_err(|e| format!("A problem occurred getting session '{}': {}.", session_name, e))?
+    .ok_or("Session does not exist - use the login CLI command to create one.")?;
+  let request_headers = build_session_headers(&named_session.session)?;
+  let client = build_http_client(Some(request_headers)).await?;
+  let mut importer = MarkdownImporter { named_session, client, summary: Default::default() };
+
+  // All pages are added first, so that every wikilink target exists by the time links to it are added.
+  add_folder_pages(&mut importer, &root_folder, container_id_maybe.as_ref()).await?;
+  let mut imported_item_id_by_file_path = HashMap::new();
+  for document in &vault.documents {
+    print!("Importing '{}'... ", document.path);
+    std::io::stdout().flush()?;
+    import_document(&mut importer, &vault, document, &mut imported_item_id_by_file_path).await?;
+    println!("done.");
+  }
+
+  let summary = &importer.summary;
+  println!(
+    "Imported {} page(s), {} note(s), {} table(s), {} link(s) and {} embedded file(s).",
+    summary.page_count, summary.note_count, summary.table_count, summary.link_count, summary.embed_count
+  );
+  if summary.unresolved_link_count > 0 || summary.missing_embed_count > 0 {
+    println!(
+      "{} wikilink(s) did not match a markdown file and {} embedded file(s) could not be found.",
+      summary.unresolved_link_count, summary.missing_embed_count
+    );
+  }
+  Ok(())
+}
+
+/// The markdown files of a directory tree, and the other files that they may embed. Paths are relative to the
+/// root of the tree, use '/' as the separator and are matched case insensitively, as Obsidian does.
+#[derive(Default)]
+struct Vault {
+  path: PathBuf,
+  documents: Vec<VaultDocument>,
+  page_id_by_document_path: HashMap<String, Uid>,
+  page_id_by_document_name: HashMap<String, Uid>,
+  file_path_by_path: HashMap<String, String>,
+  file_path_by_name: HashMap<String, String>,
+}
+
+struct VaultDocument {
+  page_id: Uid,
+  title: String,
+  path: String,
+  dir: String,
+}
+
+struct VaultFolder {
+  page_id: Uid,
+  title: String,
+  folders: Vec<VaultFolder>,
+  documents: Vec<(Uid, String)>,
+}
+
+#[derive(Default)]
+struct ImportSummary {
+  page_count: usize,
+  note_count: usize,
+  table_count: usize,
+  link_count: usize,
+  embed_count: usize,
+  unresolved_link_count: usize,
+  missing_embed_count: usize,
+}
+
+struct MarkdownImporter {
+  named_session: NamedInfuSession,
+  client: reqwest::Client,
+  summary: ImportSummary,
+}
+
+/// A run of lines of a markdown document, or a file embedded in it with `![[file]]` or `![alt](path)`.
+enum DocumentBlock {
+  Markdown(String),
+  Embed(String),
+}
+
+/// Read a directory of the vault, returning None if there are no markdown files in or below it. Hidden files and
+/// directories (such as .obsidian and .trash) are skipped.
+#[async_recursion]
+async fn read_vault_folder(
+  vault: &mut Vault,
+  dir_path: &Path,
+  dir: &str,
+  title: &str,
+) -> InfuResult<Option<VaultFolder>> {
+  let mut entries = vec![];
+  let mut read_dir = fs::read_dir(dir_path).await?;
+  while let Some(entry) = read_dir.next_entry().await? {
+    let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+      return Err(format!("Could not interpret filename: {:?}", entry.file_name()).into());
+    };
+    if name.starts_with('.') {
+      continue;
+    }
+    entries.push((name, entry.path(), entry.file_type().await?));
+  }
+  entries.sort_by(|a, b| a.0.to_lowercase().cmp(&b.0.to_lowercase()));
+
+  let mut folder = VaultFolder { page_id: new_uid(), title: title.to_owned(), folders: vec![], documents: vec![] };
+  let mut subdirs = vec![];
+  for (name, path, file_type) in entries {
+    let relative_path = if dir.is_empty() { name.clone() } else { format!("{}/{}", dir, name) };
+    if file_type.is_dir() {
+      subdirs.push((name, path, relative_path));
+      continue;
+    }
+    if !file_type.is_file() {
+      continue;
+    }
+    let lowercase_path = relative_path.to_lowercase();
+    let lowercase_name = name.to_lowercase();
+    match lowercase_name.strip_suffix(".md") {
+      Some(lowercase_stem) => {
+        let page_id = new_uid();
+        let title = name[..name.len() - ".md".len()].to_owned();
+        let lowercase_path_stem = lowercase_path[..lowercase_path.len() - ".md".len()].to_owned();
+        vault.page_id_by_document_path.insert(lowercase_path_stem, page_id.clone());
+        vault.page_id_by_document_name.entry(lowercase_stem.to_owned()).or_insert(page_id.clone());
+        vault.documents.push(VaultDocument {
+          page_id: page_id.clone(),
+          title: title.clone(),
+          path: relative_path,
+          dir: dir.to_owned(),
+        });
+        folder.documents.push((page_id, title));
+      }
+      None => {
+        vault.file_path_by_path.insert(lowercase_path, relative_path.clone());
+        vault.file_path_by_name.entry(lowercase_name).or_insert(relative_path);
+      }
+    }
+  }
+  for (name, path, relative_path) in subdirs {
+    if let Some(subfolder) = read_vault_folder(vault, &path, &relative_path, &name).await? {
+      folder.folders.push(subfolder);
+    }
+  }
+
+  if folder.documents.is_empty() && folder.folders.is_empty() { Ok(None) } else { Ok(Some(folder)) }
+}
+
+#[async_recursion]
+async fn add_folder_pages(
+  importer: &mut MarkdownImporter,
+  folder: &VaultFolder,
+  parent_id_maybe: Option<&Uid>,
+) -> InfuResult<()> {
+  importer
+    .add_item(page_item_map(&folder.page_id, parent_id_maybe, &folder.title, ArrangeAlgorithm::List)?, None)
+    .await?;
+  importer.summary.page_count += 1;
+  for subfolder in &folder.folders {
+    add_folder_pages(importer, subfolder, Some(&folder.page_id)).await?;
+  }
+  for (page_id, title) in &folder.documents {
+    importer.add_item(page_item_map(page_id, Some(&folder.page_id), title, ArrangeAlgorithm::Document)?, None).await?;
+    importer.summary.page_count += 1;
+  }
+  Ok(())
+}
+
+/// Add the items for a markdown document to its page. A file embedded more than once is imported the first time,
+/// and linked to after that.
+async fn import_document(
+  importer: &mut MarkdownImporter,
+  vault: &Vault,
+  document: &VaultDocument,
+  imported_item_id_by_file_path: &mut HashMap<String, Uid>,
+) -> InfuResult<()> {
+  let bytes = fs::read(vault.path.join(&document.path)).await?;
+  let markdown = String::from_utf8_lossy(&bytes);
+  let mut wikilinks = vec![];
+  let blocks = document_blocks(strip_front_matter(&markdown), &mut wikilinks);
+
+  for block in blocks {
+    match block {
+      DocumentBlock::Markdown(markdown) => {
+        for item in markdown_items_from_text(&markdown) {
+          match item {
+            MarkdownItem::Note(note) => {
+              let text = MarkdownInlineText { title: note.title, inline_marks: note.inline_marks, urls: note.urls };
+              importer.add_note(vault, document, &wikilinks, text, note.flags).await?;
+            }
+            MarkdownItem::Divider => {
+              let divider = serde_json::json!({ "itemType": ItemType::Divider.as_str(), "parentId": document.page_id });
+              importer.add_item(divider.as_object().unwrap().clone(), None).await?;
+            }
+            MarkdownItem::Table(table) => importer.add_table(vault, document, &wikilinks, table).await?,
+          }
+        }
+      }
+      DocumentBlock::Embed(target) => {
+        let Some(file_path) = vault.file_path_for_embed(&target, &document.dir) else {
+          println!("Embedded file '{}' in '{}' was not found, skipping.", target, document.path);
+          importer.summary.missing_embed_count += 1;
+          continue;
+        };
+        if let Some(item_id) = imported_item_id_by_file_path.get(&file_path) {
+          importer
+            .add_item(link_item_map(&document.page_id, RelationshipToParent::Child, None, item_id)?, None)
+            .await?;
+          importer.summary.link_count += 1;
+          continue;
+        }
+        let item_id = importer.add_file(vault, &document.page_id, &file_path).await?;
+        imported_item_id_by_file_path.insert(file_path, item_id);
+      }
+    }
+  }
+  Ok(())
+}
+
+impl MarkdownImporter {
+  async fn add_item(&mut self, item_map: Map<String, Value>, data_maybe: Option<&[u8]>) -> InfuResult<()> {
+    let send_request = CommandRequest {
+      command: "add-item".to_owned(),
+      json_data: serde_json::to_string(&item_map)?,
+      base64_data: data_maybe.map(|data| general_purpose::STANDARD.encode(data)),
+    };
+
+    let mut attempt = 1;
+    let response = loop {
+      match self.client.post(self.named_session.command_url()?.clone()).json(&send_request).send().await {
+        Ok(response) => break response,
+        Err(e) if attempt < MARKDOWN_IMPORT_MAX_ATTEMPTS => {
+          println!("There was a connection issue sending the add-item request - retrying: {}", e);
+          tokio::time::sleep(Duration::from_secs(2)).await;
+          attempt += 1;
+        }
+        Err(e) => return Err(format!("Could not send add-item request: {}", e).into()),
+      }
+    };
+    if self.named_session.update_from_response(&response).await? {
+      let request_headers = build_session_headers(&self.named_session.session)?;
+      self.client = build_http_client(Some(request_headers)).await?;
+    }
+    let add_item_response: CommandResponse = response.json().await.map_err(|e| e.to_string())?;
+    if !add_item_response.success {
+      return Err(
+        format!(
+          "Infumap rejected the add-item command (reason: {}).",
+          add_item_response.fail_reason.unwrap_or("unknown".to_owned())
+        )
+        .into(),
+      );
+    }
+    Ok(())
+  }
+
+  /// A note whose text is a single wikilink (or a bullet that is) is added as a link to the page it refers to.
+  /// Otherwise wikilinks in the text are added as link attachments of the note.
+  async fn add_note(
+    &mut self,
+    vault: &Vault,
+    document: &VaultDocument,
+    wikilinks: &[String],
+    mut text: MarkdownInlineText,
+    flags: i64,
+  ) -> InfuResult<()> {
+    let links = self.take_document_links(vault, document, wikilinks, &mut text.urls);
+    let title_len = text.title.encode_utf16().count() as i64;
+    let is_single_link = links.len() == 1
+      && text.urls.is_empty()
+      && links[0].0 == 0
+      && links[0].1 == title_len
+      && flags & !(NOTE_FLAG_BULLET1 | NOTE_FLAG_NUMBERED) == 0;
+    if is_single_link {
+      let page_id = &links[0].2;
+      self.add_item(link_item_map(&document.page_id, RelationshipToParent::Child, None, page_id)?, None).await?;
+      self.summary.link_count += 1;
+      return Ok(());
+    }
+
+    let note_id = new_uid();
+    self
+      .add_item(note_item_map(&note_id, &document.page_id, RelationshipToParent::Child, None, text, flags), None)
+      .await?;
+    self.summary.note_count += 1;
+    let mut attachment_orderings = vec![];
+    for (_, _, page_id) in links {
+      let ordering = new_ordering_at_end(attachment_orderings.clone());
+      attachment_orderings.push(ordering.clone());
+      self.add_item(link_item_map(&note_id, RelationshipToParent::Attachment, Some(ordering), &page_id)?, None).await?;
+      self.summary.link_count += 1;
+    }
+    Ok(())
+  }
+
+  /// Tables are added as they are for chat responses: a row is a note with a note attachment for each further cell.
+  /// Wikilinks in cells are left as plain text.
+  async fn add_table(
+    &mut self,
+    vault: &Vault,
+    document: &VaultDocument,
+    wikilinks: &[String],
+    table: MarkdownTable,
+  ) -> InfuResult<()> {
+    let column_count = table.columns.len().max(1);
+    let column_width_gr = (MARKDOWN_IMPORT_ITEM_WIDTH_GR / column_count as i64).max(GRID_SIZE);
+    let row_count = table.rows.len() as i64;
+    let table_item = Item::new_table(
+      &document.page_id,
+      vec![],
+      Vector { x: 0, y: 0 },
+      MARKDOWN_IMPORT_ITEM_WIDTH_GR,
+      row_count.saturating_add(1).clamp(3, MARKDOWN_IMPORT_TABLE_MAX_HEIGHT_BL) * GRID_SIZE,
+      RelationshipToParent::Child,
+      "",
+      TableFlags::ShowColHeader | TableFlags::HideTitle,
+      table.columns.iter().map(|name| TableColumn { width_gr: column_width_gr, name: name.clone() }).collect(),
+      column_count as i64,
+      "",
+    );
+    let mut table_map = table_item.to_api_json()?;
+    table_map.remove("ownerId");
+    table_map.remove("ordering");
+    self.add_item(table_map, None).await?;
+    self.summary.table_count += 1;
+
+    for row in table.rows {
+      let mut cells = row.cells.into_iter();
+      let Some(mut first_cell) = cells.next() else {
+        continue;
+      };
+      self.take_document_links(vault, document, wikilinks, &mut first_cell.urls);
+      let row_id = new_uid();
+      self
+        .add_item(note_item_map(&row_id, &table_item.id, RelationshipToParent::Child, None, first_cell, 0), None)
+        .await?;
+
+      let mut cells = cells.collect::<Vec<_>>();
+      while cells.last().is_some_and(|cell| cell.title.trim().is_empty()) {
+        cells.pop();
+      }
+      let mut attachment_orderings = vec![];
+      for mut cell in cells {
+        let ordering = new_ordering_at_end(attachment_orderings.clone());
+        attachment_orderings.push(ordering.clone());
+        let cell_map = if cell.title.trim().is_empty() {
+          let mut placeholder_map = Item::new_placeholder(&row_id, ordering).to_api_json()?;
+          placeholder_map.remove("ownerId");
+          placeholder_map
+        } else {
+          self.take_document_links(vault, document, wikilinks, &mut cell.urls);
+          note_item_map(&new_uid(), &row_id, RelationshipToParent::Attachment, Some(ordering), cell, 0)
+        };
+        self.add_item(cell_map, None).await?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Add an embedded file of the vault as an image or file item, returning its id.
+  async fn add_file(&mut self, vault: &Vault, page_id: &Uid, file_path: &str) -> InfuResult<Uid> {
+    let path = vault.path.join(file_path);
+    let data = fs::read(&path).await?;
+    let metadata = fs::metadata(&path).await?;
+    let title = file_path.rsplit('/').next().unwrap_or(file_path);
+    let is_image = mime_type_from_title_extension(title).is_some_and(|mime_type| mime_type.starts_with("image/"));
+    let raw_modified_time =
+      metadata.modified().map_err(|e| e.to_string())?.duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let item_id = new_uid();
+    let item_json = serde_json::json!({
+      "itemType": if is_image { ItemType::Image.as_str() } else { ItemType::File.as_str() },
+      "id": item_id,
+      "parentId": page_id,
+      "title": title,
+      "spatialWidthGr": MARKDOWN_IMPORT_ITEM_WIDTH_GR,
+      "fileSizeBytes": data.len(),
+      "originalCreationDate": infusdk::util::time::sanitize_original_creation_date(
+        raw_modified_time,
+        &format!("importing file {}", file_path)
+      ),
+    });
+    self.add_item(item_json.as_object().unwrap().clone(), Some(&data)).await?;
+    self.summary.embed_count += 1;
+    Ok(item_id)
+  }
+
+  /// Remove the urls that are links to markdown files of the vault (wikilinks, or relative links to .md files),
+  /// returning the range of text each covered and the id of the page it refers to.
+  fn take_document_links(
+    &mut self,
+    vault: &Vault,
+    document: &VaultDocument,
+    wikilinks: &[String],
+    urls: &mut Vec<MarkdownUrl>,
+  ) -> Vec<(i64, i64, Uid)> {
+    let mut links = vec![];
+    urls.retain(|url| {
+      let target = match url.url.strip_prefix(WIKILINK_URL_PREFIX) {
+        Some(index) => match index.parse::<usize>().ok().and_then(|index| wikilinks.get(index)) {
+          Some(target) => target.clone(),
+          None => return true,
+        },
+        None if is_relative_markdown_link(&url.url) => percent_decode(&url.url),
+        None => return true,
+      };
+      match vault.page_id_for_link(&target, &document.dir) {
+        Some(page_id) => links.push((url.start, url.end, page_id.clone())),
+        None => {
+          println!("Link to '{}' in '{}' does not match a markdown file, leaving it as text.", target, document.path);
+          self.summary.unresolved_link_count += 1;
+        }
+      }
+      false
+    });
+    links
+  }
+}
+
+impl Vault {
+  /// Resolve a link to a markdown file the way Obsidian does: relative to the linking document, then relative to
+  /// the root of the vault, then by file name alone.
+  fn page_id_for_link(&self, target: &str, document_dir: &str) -> Option<&Uid> {
+    let target = target.split('#').next().unwrap_or(target).trim().to_lowercase();
+    let target = target.strip_suffix(".md").unwrap_or(&target);
+    if target.is_empty() {
+      return None;
+    }
+    join_vault_path(&document_dir.to_lowercase(), target)
+      .and_then(|path| self.page_id_by_document_path.get(&path))
+      .or_else(|| join_vault_path("", target).and_then(|path| self.page_id_by_document_path.get(&path)))
+      .or_else(|| self.page_id_by_document_name.get(target.rsplit('/').next().unwrap_or(target)))
+  }
+
+  fn file_path_for_embed(&self, target: &str, document_dir: &str) -> Option<String> {
+    let target = target.trim().to_lowercase();
+    join_vault_path(&document_dir.to_lowercase(), &target)
+      .and_then(|path| self.file_path_by_path.get(&path))
+      .or_else(|| join_vault_path("", &target).and_then(|path| self.file_path_by_path.get(&path)))
+      .or_else(|| self.file_path_by_name.get(target.rsplit('/').next().unwrap_or(&target)))
+      .cloned()
+  }
+}
+
+/// Join a relative path to a directory of the vault, returning None if the result would be outside the vault.
+fn join_vault_path(dir: &str, relative_path: &str) -> Option<String> {
+  let mut parts = dir.split('/').filter(|part| !part.is_empty()).collect::<Vec<_>>();
+  for part in relative_path.split('/') {
+    match part {
+      "" | "." => {}
+      ".." => {
+        parts.pop()?;
+      }
+      part => parts.push(part),
+    }
+  }
+  Some(parts.join("/"))
+}
+
+fn is_relative_markdown_link(url: &str) -> bool {
+  let path = url.split('#').next().unwrap_or(url);
+  !url.contains("://") && !url.starts_with("mailto:") && path.to_lowercase().ends_with(".md")
+}
+
+fn page_item_map(
+  page_id: &Uid,
+  parent_id_maybe: Option<&Uid>,
+  title: &str,
+  arrange_algorithm: ArrangeAlgorithm,
+) -> InfuResult<Map<String, Value>> {
+  let mut page = Item::new_page(
+    parent_id_maybe,
+    vec![],
+    Vector { x: 0, y: 0 },
+    MARKDOWN_IMPORT_PAGE_WIDTH_GR,
+    RelationshipToParent::Child,
+    title,
+    "",
+    0,
+    0,
+    0,
+    2.0,
+    MARKDOWN_IMPORT_PAGE_INNER_WIDTH_GR,
+    arrange_algorithm,
+    6,
+    1.5,
+    36,
+    7.0,
+    1.0,
+    vec![TableColumn { width_gr: 8 * GRID_SIZE, name: "Title".to_owned() }],
+    1,
+  );
+  page.id = page_id.clone();
+  let mut page_map = page.to_api_json()?;
+  // Let add-item fill in the owner, parent (the home page if none was given) and ordering.
+  page_map.remove("ownerId");
+  page_map.remove("ordering");
+  if parent_id_maybe.is_none() {
+    page_map.remove("parentId");
+  }
+  Ok(page_map)
+}
+
+/// Children are added in document order, so add-item can give them their ordering. Attachments are given theirs.
+fn note_item_map(
+  note_id: &Uid,
+  parent_id: &Uid,
+  relationship_to_parent: RelationshipToParent,
+  ordering_maybe: Option<Vec<u8>>,
+  text: MarkdownInlineText,
+  flags: i64,
+) -> Map<String, Value> {
+  let mut note_json = serde_json::json!({
+    "itemType": ItemType::Note.as_str(),
+    "id": note_id,
+    "parentId": parent_id,
+    "relationshipToParent": relationship_to_parent.as_str(),
+    "title": text.title,
+    "spatialWidthGr": MARKDOWN_IMPORT_ITEM_WIDTH_GR,
+    "flags": flags,
+    "inlineMarks": text.inline_marks,
+    "urls": text.urls.iter().map(|url| {
+      serde_json::json!({ "start": url.start, "end": url.end, "url": url.url })
+    }).collect::<Vec<Value>>(),
+  });
+  if let Some(ordering) = ordering_maybe {
+    note_json["ordering"] = ordering.into();
+  }
+  note_json.as_object().unwrap().clone()
+}
+
+fn link_item_map(
+  parent_id: &Uid,
+  relationship_to_parent: RelationshipToParent,
+  ordering_maybe: Option<Vec<u8>>,
+  link_to: &Uid,
+) -> InfuResult<Map<String, Value>> {
+  let has_ordering = ordering_maybe.is_some();
+  let link = Item::new_link(
+    parent_id,
+    ordering_maybe.unwrap_or_default(),
+    Vector { x: 0, y: 0 },
+    MARKDOWN_IMPORT_ITEM_WIDTH_GR,
+    GRID_SIZE,
+    relationship_to_parent,
+    link_to,
+  );
+  let mut link_map = link.to_api_json()?;
+  link_map.remove("ownerId");
+  if !has_ordering {
+    link_map.remove("ordering");
+  }
+  Ok(link_map)
+}
+
+/// Remove a leading YAML front matter block.
+fn strip_front_matter(markdown: &str) -> &str {
+  let Some(rest) = markdown.strip_prefix("---\n").or(markdown.strip_prefix("---\r\n")) else {
+    return markdown;
+  };
+  let mut offset = 0;
+  for line in rest.split_inclusive('\n') {
+    offset += line.len();
+    if matches!(line.trim_end(), "---" | "...") {
+      return &rest[offset..];
+    }
+  }
+  markdown
+}
+
+/// Split a document into markdown and embedded files, and rewrite its wikilinks to markdown links (see
+/// WIKILINK_URL_PREFIX), appending their targets to `wikilinks`. Code blocks and code spans are left as they are.
+fn document_blocks(markdown: &str, wikilinks: &mut Vec<String>) -> Vec<DocumentBlock> {
+  let mut blocks = vec![];
+  let mut lines = String::new();
+  let mut fence_marker_maybe: Option<&'static str> = None;
+  for line in markdown.lines() {
+    if let Some(fence_marker) = fence_marker_maybe {
+      if line.trim_start().starts_with(fence_marker) {
+        fence_marker_maybe = None;
+      }
+      lines.push_str(line);
+      lines.push('\n');
+      continue;
+    }
+    if let Some(fence_marker) = markdown_code_fence_marker(line) {
+      fence_marker_maybe = Some(fence_marker);
+      lines.push_str(line);
+      lines.push('\n');
+      continue;
+    }
+
+    let (text, embeds) = rewrite_document_line(line, wikilinks);
+    let has_embeds = !embeds.is_empty();
+    for (text_before, embed) in embeds {
+      if !text_before.trim().is_empty() {
+        lines.push_str(&text_before);
+        lines.push('\n');
+      }
+      if !lines.trim().is_empty() {
+        blocks.push(DocumentBlock::Markdown(std::mem::take(&mut lines)));
+      }
+      lines.clear();
+      blocks.push(DocumentBlock::Embed(embed));
+    }
+    if !has_embeds || !text.trim().is_empty() {
+      lines.push_str(&text);
+      lines.push('\n');
+    }
+  }
+  if !lines.trim().is_empty() {
+    blocks.push(DocumentBlock::Markdown(lines));
+  }
+  blocks
+}
+
+/// Rewrite the wikilinks in a line and take out its embeds. Returns the text after the last embed, and each embed
+/// with the text before it.
+fn rewrite_document_line(line: &str, wikilinks: &mut Vec<String>) -> (String, Vec<(String, String)>) {
+  let mut text = String::new();
+  let mut embeds = vec![];
+  let mut index = 0;
+  while index < line.len() {
+    let rest = &line[index..];
+
+    if rest.starts_with('`')
+      && let Some(end) = rest[1..].find('`')
+    {
+      text.push_str(&rest[..end + 2]);
+      index += end + 2;
+      continue;
+    }
+
+    if let Some(body) = rest.strip_prefix("![[")
+      && let Some(end) = body.find("]]")
+    {
+      let target = body[..end].split('|').next().unwrap_or("").trim();
+      if !target.is_empty() {
+        embeds.push((std::mem::take(&mut text), target.to_owned()));
+      }
+      index += "![[".len() + end + "]]".len();
+      continue;
+    }
+
+    if let Some((alt, destination, len)) = markdown_image_at(rest) {
+      if destination.contains("://") {
+        // Remote images stay in the text, as links.
+        let label = if alt.trim().is_empty() { destination } else { alt };
+        text.push_str(&format!("[{}]({})", label, destination));
+      } else {
+        embeds.push((std::mem::take(&mut text), percent_decode(destination)));
+      }
+      index += len;
+      continue;
+    }
+
+    if let Some(body) = rest.strip_prefix("[[")
+      && let Some(end) = body.find("]]")
+    {
+      let mut parts = body[..end].splitn(2, '|');
+      let target = parts.next().unwrap_or("").trim();
+      let display = parts.next().map(str::trim).filter(|alias| !alias.is_empty()).unwrap_or(target);
+      if !target.is_empty() {
+        text.push_str(&format!("[{}]({}{})", escape_markdown(display), WIKILINK_URL_PREFIX, wikilinks.len()));
+        wikilinks.push(target.to_owned());
+      }
+      index += "[[".len() + end + "]]".len();
+      continue;
+    }
+
+    let ch = rest.chars().next().unwrap();
+    text.push(ch);
+    index += ch.len_utf8();
+  }
+  (text, embeds)
+}
+
+/// A markdown image `![alt](destination "title")`, as (alt, destination, length).
+fn markdown_image_at(slice: &str) -> Option<(&str, &str, usize)> {
+  let body = slice.strip_prefix("![")?;
+  let label_end = body.find("](")?;
+  let destination_start = label_end + "](".len();
+  let destination_len = body[destination_start..].find(')')?;
+  let destination = body[destination_start..destination_start + destination_len].trim();
+  let destination = match destination.strip_prefix('<') {
+    Some(bracketed) => bracketed.split('>').next().unwrap_or(""),
+    None => destination.split_whitespace().next().unwrap_or(""),
+  };
+  if destination.is_empty() {
+    return None;
+  }
+  Some((&body[..label_end], destination, "![".len() + destination_start + destination_len + 1))
+}
+
+fn escape_markdown(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    if ch.is_ascii_punctuation() {
+      escaped.push('\\');
+    }
+    escaped.push(ch);
+  }
+  escaped
+}
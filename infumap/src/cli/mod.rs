@@ -31,11 +31,16 @@ use crate::util::fs::{expand_tilde, path_exists};
 use crate::web::cookie::{InfuSession, SESSION_COOKIE_NAME};
 
 pub mod compact;
+pub mod duplicates;
 pub mod embed;
 pub mod emergency;
+pub mod export_markdown;
+pub mod export_site;
 pub mod extract;
 pub mod fragment;
 pub mod geo;
+pub mod import_email;
+pub mod import_markdown;
 pub mod keygen;
 pub mod login;
 pub mod logout;
@@ -70,6 +75,10 @@ impl NamedInfuSession {
     command_url_from_base_url(&self.url)
   }
 
+  pub fn file_url(&self, item_id: &str) -> InfuResult<Url> {
+    file_url_from_base_url(&self.url, item_id)
+  }
+
   pub fn list_pending_users_url(&self) -> InfuResult<Url> {
     list_pending_users_url_from_base_url(&self.url)
   }
@@ -244,6 +253,11 @@ fn command_url_from_base_url(base_url: &str) -> InfuResult<Url> {
   base_url.join("/command").map_err(|e| e.to_string().into())
 }
 
+fn file_url_from_base_url(base_url: &str, item_id: &str) -> InfuResult<Url> {
+  let base_url = Url::parse(base_url).map_err(|e| format!("Could not parse URL: {}", e))?;
+  base_url.join(&format!("/files/{}", item_id)).map_err(|e| e.to_string().into())
+}
+
 fn list_pending_users_url_from_base_url(base_url: &str) -> InfuResult<Url> {
   let base_url = Url::parse(base_url).map_err(|e| format!("Could not parse URL: {}", e))?;
   base_url.join("/admin/list-pending").map_err(|e| e.to_string().into())
@@ -50,6 +50,47 @@ pub const CONFIG_GPU_TOOLS_URL: &'static str = "gpu_tools_url";
 pub const CONFIG_TEXT_EMBED_URL: &'static str = "text_embed_url";
 pub const CONFIG_LLAMA_SERVER_URL: &'static str = "llama_server_url";
 pub const CONFIG_LLAMA_SERVER_URL_DEFAULT: &'static str = "";
+pub const CONFIG_LLM_MODELS: &'static str = "llm_models";
+pub const CONFIG_DEFAULT_LLM_MODEL: &'static str = "default_llm_model";
+pub const CONFIG_DEFAULT_LLM_MODEL_DEFAULT: &'static str = "";
+pub const CONFIG_ENABLE_CHAT_TRANSCRIPTS: &'static str = "enable_chat_transcripts";
+pub const CONFIG_ENABLE_CHAT_TRANSCRIPTS_DEFAULT: bool = false;
+pub const CONFIG_CHAT_TRANSCRIPT_MAX_MB: &'static str = "chat_transcript_max_mb";
+pub const CONFIG_CHAT_TRANSCRIPT_MAX_MB_DEFAULT: u64 = 10;
+pub const CONFIG_CHAT_TRANSCRIPT_MAX_FILES: &'static str = "chat_transcript_max_files";
+pub const CONFIG_CHAT_TRANSCRIPT_MAX_FILES_DEFAULT: u64 = 3;
+pub const CONFIG_ENABLE_LINK_SNAPSHOTS: &'static str = "enable_link_snapshots";
+pub const CONFIG_ENABLE_LINK_SNAPSHOTS_DEFAULT: bool = false;
+pub const CONFIG_ENABLE_NEAR_DUPLICATE_SCAN: &'static str = "enable_near_duplicate_scan";
+pub const CONFIG_ENABLE_NEAR_DUPLICATE_SCAN_DEFAULT: bool = false;
+pub const CONFIG_TRANSCRIBE_URL: &'static str = "transcribe_url";
+pub const CONFIG_TRANSCRIBE_URL_DEFAULT: &'static str = "";
+pub const CONFIG_TRANSCRIBE_MODEL: &'static str = "transcribe_model";
+pub const CONFIG_TRANSCRIBE_MODEL_DEFAULT: &'static str = "whisper-1";
+pub const CONFIG_FACE_EMBED_URL: &'static str = "face_embed_url";
+pub const CONFIG_FACE_EMBED_URL_DEFAULT: &'static str = "";
+pub const CONFIG_FFMPEG_PATH: &'static str = "ffmpeg_path";
+pub const CONFIG_FFMPEG_PATH_DEFAULT: &'static str = "ffmpeg";
+
+pub const CONFIG_FRAGMENT_CHUNKING_PDF: &'static str = "fragment_chunking_pdf";
+pub const CONFIG_FRAGMENT_CHUNKING_PDF_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+
+pub const CONFIG_FRAGMENT_CHUNKING_MARKDOWN: &'static str = "fragment_chunking_markdown";
+pub const CONFIG_FRAGMENT_CHUNKING_MARKDOWN_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+
+pub const CONFIG_FRAGMENT_CHUNKING_TEXT: &'static str = "fragment_chunking_text";
+pub const CONFIG_FRAGMENT_CHUNKING_TEXT_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+
+pub const CONFIG_FRAGMENT_CHUNKING_DOCUMENT: &'static str = "fragment_chunking_document";
+pub const CONFIG_FRAGMENT_CHUNKING_DOCUMENT_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+
+pub const CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT: &'static str = "fragment_chunking_link_snapshot";
+pub const CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+
+pub const CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT: &'static str = "fragment_chunking_audio_transcript";
+pub const CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT_DEFAULT: &'static str = "mode=heading_aware target_tokens=380";
+pub const CONFIG_GEO_PROVIDER: &'static str = "geo_provider";
+pub const CONFIG_GEO_PROVIDER_DEFAULT: &'static str = "geoapify";
 pub const CONFIG_GEOAPIFY_URL: &'static str = "geoapify_url";
 pub const CONFIG_GEOAPIFY_URL_DEFAULT: &'static str = "https://api.geoapify.com/v1/geocode/reverse";
 pub const CONFIG_GEOAPIFY_API_KEY: &'static str = "geoapify_api_key";
@@ -0,0 +1,449 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Markdown export of a container and everything below it.
+//!
+//! Each page is written as `<title>.md`, with the pages inside it in a directory of the same name next to it.
+//! The data of file and image items belongs in `assets/<item_id>/`, and is fetched separately. The markdown is for
+//! reading; the `infumap.json` sidecar holds every exported item as it is returned by the web api (spatial layout,
+//! ordering, arrangement, formatting and so on), along with the path it was exported to, so nothing is lost.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use infusdk::item::{DividerDirection, Item, ItemType, NoteFlags, is_container_item_type, is_data_item_type};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::Uid;
+use infusdk::web::WebApiJsonSerializable;
+use log::debug;
+use serde_json::Value;
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::{children_in_reading_order, note_text_runs, safe_file_name, sorted_attachments};
+use crate::storage::db::Db;
+use crate::web::routes::command::authorize_item;
+
+pub const MARKDOWN_EXPORT_SIDECAR_FILENAME: &str = "infumap.json";
+const MARKDOWN_EXPORT_SIDECAR_VERSION: i64 = 1;
+const MARKDOWN_EXPORT_ASSETS_DIR: &str = "assets";
+const MARKDOWN_EXPORT_MAX_RATING: i64 = 5;
+
+pub struct MarkdownExportFile {
+  /// Relative to the root of the export, with '/' as the separator.
+  pub path: String,
+  pub data: Vec<u8>,
+}
+
+/// The data of a file or image item, which is not part of the export itself, but is to be fetched separately
+/// and written to `path`.
+pub struct MarkdownExportAsset {
+  pub item_id: Uid,
+  /// Relative to the root of the export, with '/' as the separator.
+  pub path: String,
+}
+
+pub struct MarkdownExport {
+  /// The markdown documents and the sidecar.
+  pub files: Vec<MarkdownExportFile>,
+  pub assets: Vec<MarkdownExportAsset>,
+  pub summary: MarkdownExportSummary,
+}
+
+pub struct MarkdownExportSummary {
+  pub pages: usize,
+  pub files: usize,
+  pub items: usize,
+  pub skipped_items: usize,
+}
+
+struct MarkdownRenderer<'a> {
+  db: &'a MutexGuard<'a, Db>,
+  /// The user the export is for, who may not be allowed to see the items that links point to.
+  user_id: &'a str,
+  include_passwords: bool,
+  path_by_item_id: HashMap<Uid, String>,
+  pages: Vec<&'a Item>,
+  assets: Vec<MarkdownExportAsset>,
+  sidecar_items: Vec<Value>,
+  used_paths: HashSet<String>,
+  skipped_items: usize,
+}
+
+/// Export `container_id` and everything below it as markdown documents and a json sidecar for `user_id`. File and
+/// image data is not read, since it may be large; the assets list where each is to be written instead.
+pub async fn export_markdown(
+  db: &Arc<Mutex<Db>>,
+  user_id: &str,
+  container_id: &Uid,
+  include_passwords: bool,
+) -> InfuResult<MarkdownExport> {
+  let db = db.lock().await;
+  let container = db.item.get(container_id)?;
+  if !is_container_item_type(container.item_type) {
+    return Err(format!("Item '{}' is not a container.", container_id).into());
+  }
+
+  let mut renderer = MarkdownRenderer {
+    db: &db,
+    user_id,
+    include_passwords,
+    path_by_item_id: HashMap::new(),
+    pages: vec![],
+    assets: vec![],
+    sidecar_items: vec![],
+    used_paths: HashSet::new(),
+    skipped_items: 0,
+  };
+  renderer.collect_item(container, "")?;
+
+  let mut files = vec![];
+  for page in renderer.pages.clone() {
+    let path = renderer.path_by_item_id.get(&page.id).unwrap().clone();
+    files.push(MarkdownExportFile { path, data: renderer.render_document(page)?.into_bytes() });
+  }
+  let summary = MarkdownExportSummary {
+    pages: files.len(),
+    files: renderer.assets.len(),
+    items: renderer.sidecar_items.len(),
+    skipped_items: renderer.skipped_items,
+  };
+  let sidecar = serde_json::json!({
+    "version": MARKDOWN_EXPORT_SIDECAR_VERSION,
+    "rootId": container.id,
+    "items": renderer.sidecar_items,
+  });
+  files.push(MarkdownExportFile {
+    path: MARKDOWN_EXPORT_SIDECAR_FILENAME.to_owned(),
+    data: serde_json::to_string_pretty(&sidecar)?.into_bytes(),
+  });
+
+  debug!("Exported container '{}' as markdown: {} page(s), {} file(s).", container_id, summary.pages, summary.files);
+  Ok(MarkdownExport { files, assets: renderer.assets, summary })
+}
+
+impl<'a> MarkdownRenderer<'a> {
+  fn db(&self) -> &'a Db {
+    self.db
+  }
+
+  /// Add an item and everything below it to the sidecar, and give pages and data items their export paths. This is
+  /// done before any markdown is rendered, so links can refer to items wherever they are in the tree.
+  fn collect_item(&mut self, item: &'a Item, dir: &str) -> InfuResult<()> {
+    let mut item_json = item.to_api_json()?;
+    if item.item_type == ItemType::Password && !self.include_passwords {
+      item_json.remove("text");
+    }
+
+    let mut child_dir = dir.to_owned();
+    if self.pages.is_empty() || item.item_type == ItemType::Page {
+      let stem = self.unique_path(dir, &safe_file_name(item.title.as_deref().unwrap_or(""), &item.id), ".md");
+      child_dir = stem.clone();
+      let path = format!("{}.md", stem);
+      item_json.insert("exportPath".to_owned(), Value::String(path.clone()));
+      self.path_by_item_id.insert(item.id.clone(), path);
+      self.pages.push(item);
+    } else if is_data_item_type(item.item_type) {
+      let name = safe_file_name(item.title.as_deref().unwrap_or(""), &item.id);
+      let path = format!("{}/{}/{}", MARKDOWN_EXPORT_ASSETS_DIR, item.id, name);
+      item_json.insert("exportPath".to_owned(), Value::String(path.clone()));
+      self.path_by_item_id.insert(item.id.clone(), path.clone());
+      self.assets.push(MarkdownExportAsset { item_id: item.id.clone(), path });
+    }
+    self.sidecar_items.push(Value::Object(item_json));
+
+    for child in children_in_reading_order(self.db(), item)? {
+      self.collect_item(child, &child_dir)?;
+    }
+    for attachment in sorted_attachments(self.db(), item)? {
+      self.collect_item(attachment, &child_dir)?;
+    }
+    Ok(())
+  }
+
+  /// A path (without extension) for a page in `dir` that does not clash with another page, or its directory.
+  fn unique_path(&mut self, dir: &str, name: &str, extension: &str) -> String {
+    let join = |name: &str| if dir.is_empty() { name.to_owned() } else { format!("{}/{}", dir, name) };
+    let mut path = join(name);
+    let mut suffix = 2;
+    while self.used_paths.contains(&path.to_lowercase())
+      || path.eq_ignore_ascii_case(MARKDOWN_EXPORT_ASSETS_DIR)
+      || format!("{}{}", path, extension).eq_ignore_ascii_case(MARKDOWN_EXPORT_SIDECAR_FILENAME)
+    {
+      path = join(&format!("{}-{}", name, suffix));
+      suffix += 1;
+    }
+    self.used_paths.insert(path.to_lowercase());
+    path
+  }
+
+  fn render_document(&mut self, page: &'a Item) -> InfuResult<String> {
+    let path = self.path_by_item_id.get(&page.id).unwrap().clone();
+    let dir = match path.rfind('/') {
+      Some(index) => path[..index].to_owned(),
+      None => String::new(),
+    };
+
+    // Front matter keeps the title, which the file name may not. import-markdown ignores it.
+    let mut markdown = String::new();
+    markdown.push_str("---\n");
+    markdown.push_str(&format!("title: {}\n", serde_json::to_string(page.title.as_deref().unwrap_or(""))?));
+    markdown.push_str(&format!("infumap-id: {}\n", page.id));
+    markdown.push_str("---\n\n");
+
+    let mut blocks = vec![];
+    if page.item_type == ItemType::Page {
+      for child in children_in_reading_order(self.db(), page)? {
+        self.render_item(child, &dir, &mut blocks)?;
+      }
+    } else {
+      self.render_item(page, &dir, &mut blocks)?;
+    }
+    markdown.push_str(&join_blocks(&blocks));
+    Ok(markdown)
+  }
+
+  /// Render an item as one or more blocks, followed by its attachments as a list.
+  fn render_item(&mut self, item: &'a Item, dir: &str, blocks: &mut Vec<String>) -> InfuResult<()> {
+    match item.item_type {
+      ItemType::Note => blocks.push(self.render_note(item)),
+      ItemType::Table => blocks.push(self.render_table(item, dir)?),
+      ItemType::Composite => {
+        for child in children_in_reading_order(self.db(), item)? {
+          self.render_item(child, dir, blocks)?;
+        }
+      }
+      ItemType::Divider => {
+        if item.divider_direction != Some(DividerDirection::Vertical) {
+          blocks.push("---".to_owned());
+        }
+      }
+      ItemType::Page
+      | ItemType::File
+      | ItemType::Text
+      | ItemType::Image
+      | ItemType::Link
+      | ItemType::Rating
+      | ItemType::Password => blocks.push(self.render_inline(item, dir)?),
+      ItemType::Search | ItemType::Placeholder => self.skipped_items += 1,
+    }
+
+    if item.item_type != ItemType::Table {
+      let attachments = sorted_attachments(self.db(), item)?;
+      let mut lines = vec![];
+      for attachment in attachments {
+        if attachment.item_type == ItemType::Placeholder {
+          continue;
+        }
+        lines.push(format!("  - {}", self.render_inline(attachment, dir)?));
+      }
+      if !lines.is_empty() {
+        blocks.push(lines.join("\n"));
+      }
+    }
+    Ok(())
+  }
+
+  fn render_note(&self, note: &Item) -> String {
+    let flags = NoteFlags::from_bits_truncate(note.flags.unwrap_or(0));
+    if flags.contains(NoteFlags::Code) {
+      let code = note.title.as_deref().unwrap_or("");
+      let fence = if code.contains("```") { "~~~" } else { "```" };
+      return format!("{}\n{}\n{}", fence, code, fence);
+    }
+    let text = self.render_note_text(note);
+    let prefix = if flags.contains(NoteFlags::Heading1) {
+      "# "
+    } else if flags.contains(NoteFlags::Heading2) {
+      "## "
+    } else if flags.contains(NoteFlags::Heading3) {
+      "### "
+    } else if flags.contains(NoteFlags::Heading4) {
+      "#### "
+    } else if flags.contains(NoteFlags::Bullet1) {
+      "- "
+    } else if flags.contains(NoteFlags::Numbered) {
+      "1. "
+    } else {
+      return escape_line_start(&text);
+    };
+    format!("{}{}", prefix, text.replace('\n', " "))
+  }
+
+  fn render_note_text(&self, note: &Item) -> String {
+    let mut markdown = String::new();
+    for run in note_text_runs(note) {
+      let mut run_markdown = escape_markdown(&run.text);
+      if run.bold && run.italic {
+        run_markdown = wrap_marked_text(&run_markdown, "***");
+      } else if run.bold {
+        run_markdown = wrap_marked_text(&run_markdown, "**");
+      } else if run.italic {
+        run_markdown = wrap_marked_text(&run_markdown, "*");
+      }
+      if let Some(url) = &run.url {
+        run_markdown = format!("[{}]({})", run_markdown, markdown_destination(url));
+      }
+      markdown.push_str(&run_markdown);
+    }
+    markdown
+  }
+
+  fn render_table(&mut self, table: &'a Item, dir: &str) -> InfuResult<String> {
+    let columns = table.table_columns.as_deref().unwrap_or(&[]);
+    let visible_columns =
+      table.number_of_visible_columns.map(|count| count.max(1) as usize).unwrap_or(columns.len()).max(1);
+
+    let mut lines = vec![];
+    let header = (0..visible_columns)
+      .map(|index| escape_table_cell(&escape_markdown(columns.get(index).map(|c| c.name.as_str()).unwrap_or(""))))
+      .collect::<Vec<_>>();
+    lines.push(format!("| {} |", header.join(" | ")));
+    lines.push(format!("|{}|", vec![" --- "; visible_columns].join("|")));
+    for row in children_in_reading_order(self.db(), table)? {
+      let mut cell_items = vec![row];
+      cell_items.extend(sorted_attachments(self.db(), row)?.into_iter().take(visible_columns - 1));
+      let mut cells = vec![];
+      for index in 0..visible_columns {
+        let cell = match cell_items.get(index) {
+          Some(cell_item) if cell_item.item_type != ItemType::Placeholder => self.render_inline(cell_item, dir)?,
+          _ => String::new(),
+        };
+        cells.push(escape_table_cell(&cell));
+      }
+      lines.push(format!("| {} |", cells.join(" | ")));
+    }
+    Ok(lines.join("\n"))
+  }
+
+  /// Render an item as text that fits on a single line, for table cells, attachments and non-note children.
+  fn render_inline(&mut self, item: &'a Item, dir: &str) -> InfuResult<String> {
+    let title = escape_markdown(item.title.as_deref().unwrap_or(""));
+    Ok(match item.item_type {
+      ItemType::Note => {
+        let flags = NoteFlags::from_bits_truncate(item.flags.unwrap_or(0));
+        if flags.contains(NoteFlags::Code) {
+          format!("`{}`", item.title.as_deref().unwrap_or("").replace('`', "'").replace('\n', " "))
+        } else {
+          self.render_note_text(item).replace('\n', " ")
+        }
+      }
+      ItemType::Page | ItemType::File | ItemType::Text => match self.path_by_item_id.get(&item.id) {
+        Some(path) => format!("[{}]({})", title, relative_path(dir, path)),
+        None => title,
+      },
+      ItemType::Image => match self.path_by_item_id.get(&item.id) {
+        Some(path) => format!("![{}]({})", title, relative_path(dir, path)),
+        None => title,
+      },
+      ItemType::Link => {
+        match item.link_to.as_ref().and_then(|link_to| self.db().item.get(link_to).ok()) {
+          Some(target) if target.item_type == ItemType::Link => String::new(),
+          Some(target) if authorize_item(self.db, target, &Some(self.user_id.to_owned()), 0).is_err() => String::new(),
+          Some(target) => match self.path_by_item_id.get(&target.id) {
+            Some(_) => self.render_inline(target, dir)?,
+            // Links to items outside of the export keep just the title; the sidecar has the id they link to.
+            None => escape_markdown(target.title.as_deref().unwrap_or("")),
+          },
+          None => String::new(),
+        }
+      }
+      ItemType::Rating => {
+        let rating = item.rating.unwrap_or(0).clamp(0, MARKDOWN_EXPORT_MAX_RATING) as usize;
+        format!("{}{}", "★".repeat(rating), "☆".repeat(MARKDOWN_EXPORT_MAX_RATING as usize - rating))
+      }
+      ItemType::Password => {
+        if self.include_passwords {
+          format!("`{}`", item.text.as_deref().unwrap_or("").replace('`', "'"))
+        } else {
+          "`********`".to_owned()
+        }
+      }
+      ItemType::Table | ItemType::Composite => title,
+      ItemType::Divider | ItemType::Search | ItemType::Placeholder => String::new(),
+    })
+  }
+}
+
+fn join_blocks(blocks: &[String]) -> String {
+  let mut markdown = String::new();
+  let mut previous_is_list_item = false;
+  for block in blocks.iter().filter(|block| !block.is_empty()) {
+    let is_list_item = block.starts_with("- ") || block.starts_with("1. ") || block.starts_with("  - ");
+    if !markdown.is_empty() {
+      markdown.push_str(if is_list_item && previous_is_list_item { "\n" } else { "\n\n" });
+    }
+    markdown.push_str(block);
+    previous_is_list_item = is_list_item;
+  }
+  markdown.push('\n');
+  markdown
+}
+
+/// The path of `path` (relative to the export root) from a file in `dir`.
+fn relative_path(dir: &str, path: &str) -> String {
+  let depth = dir.split('/').filter(|part| !part.is_empty()).count();
+  format!("{}{}", "../".repeat(depth), path)
+}
+
+fn markdown_destination(url: &str) -> String {
+  if url.contains(char::is_whitespace) || url.contains(')') { format!("<{}>", url) } else { url.to_owned() }
+}
+
+/// Marks are moved inside surrounding whitespace, which markdown does not allow just inside them.
+fn wrap_marked_text(text: &str, marker: &str) -> String {
+  let trimmed = text.trim();
+  if trimmed.is_empty() {
+    return text.to_owned();
+  }
+  let leading = &text[..text.len() - text.trim_start().len()];
+  let trailing = &text[text.trim_end().len()..];
+  format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn escape_markdown(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    if matches!(ch, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+      escaped.push('\\');
+    }
+    escaped.push(ch);
+  }
+  escaped
+}
+
+/// Escape the start of each line of a paragraph that would otherwise be read as a heading, list item or quote.
+fn escape_line_start(text: &str) -> String {
+  text
+    .split('\n')
+    .map(|line| {
+      let indent = &line[..line.len() - line.trim_start().len()];
+      let trimmed = line.trim_start();
+      let digits = trimmed.len() - trimmed.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+      if trimmed.starts_with(['#', '-', '+']) {
+        format!("{}\\{}", indent, trimmed)
+      } else if digits > 0 && trimmed[digits..].starts_with(". ") {
+        format!("{}{}\\{}", indent, &trimmed[..digits], &trimmed[digits..])
+      } else {
+        line.to_owned()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn escape_table_cell(cell: &str) -> String {
+  cell.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,118 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Rendering of item subtrees to files outside of Infumap.
+
+pub mod markdown;
+pub mod site;
+
+use infusdk::item::{ArrangeAlgorithm, Item, ItemType};
+use infusdk::util::infu::InfuResult;
+
+use crate::storage::db::Db;
+use crate::util::markdown::{NOTE_INLINE_MARK_BOLD, NOTE_INLINE_MARK_ITALIC};
+
+/// A run of note title text with uniform formatting.
+pub struct NoteTextRun {
+  pub text: String,
+  pub bold: bool,
+  pub italic: bool,
+  pub url: Option<String>,
+}
+
+/// Split a note title into runs according to its inline marks and urls. Both are ranges of utf-16 code units,
+/// as they are in the web client.
+pub fn note_text_runs(item: &Item) -> Vec<NoteTextRun> {
+  let title = item.title.as_deref().unwrap_or("");
+  let units = title.encode_utf16().collect::<Vec<u16>>();
+  let len = units.len() as i64;
+
+  let marks = item
+    .inline_marks
+    .as_deref()
+    .unwrap_or(&[])
+    .chunks_exact(3)
+    .map(|mark| (mark[0].clamp(0, len), mark[1].clamp(0, len), mark[2]))
+    .collect::<Vec<_>>();
+  let urls = match (&item.urls, &item.url) {
+    (Some(urls), _) => {
+      urls.iter().map(|url| (url.start.clamp(0, len), url.end.clamp(0, len), url.url.clone())).collect()
+    }
+    (None, Some(url)) if !url.trim().is_empty() => vec![(0, len, url.clone())],
+    _ => vec![],
+  };
+
+  let mut boundaries = vec![0, len];
+  for (start, end, _) in &marks {
+    boundaries.push(*start);
+    boundaries.push(*end);
+  }
+  for (start, end, _) in &urls {
+    boundaries.push(*start);
+    boundaries.push(*end);
+  }
+  boundaries.sort();
+  boundaries.dedup();
+
+  let mut runs = Vec::new();
+  for window in boundaries.windows(2) {
+    let (start, end) = (window[0], window[1]);
+    if start >= end {
+      continue;
+    }
+    let flags = marks.iter().filter(|(s, e, _)| *s <= start && end <= *e).fold(0, |acc, (_, _, flags)| acc | flags);
+    let url = urls.iter().find(|(s, e, _)| *s <= start && end <= *e).map(|(_, _, url)| url.clone());
+    runs.push(NoteTextRun {
+      text: String::from_utf16_lossy(&units[start as usize..end as usize]),
+      bold: flags & NOTE_INLINE_MARK_BOLD != 0,
+      italic: flags & NOTE_INLINE_MARK_ITALIC != 0,
+      url,
+    });
+  }
+  runs
+}
+
+/// Children in reading order: top to bottom then left to right on spatial pages, otherwise in their ordering.
+pub fn children_in_reading_order<'a>(db: &'a Db, container: &Item) -> InfuResult<Vec<&'a Item>> {
+  let mut children = db.item.get_children(&container.id)?;
+  if container.item_type == ItemType::Page && container.arrange_algorithm == Some(ArrangeAlgorithm::SpatialStretch) {
+    children.sort_by(|a, b| {
+      let a_position = a.spatial_position_gr.as_ref().map(|p| (p.y, p.x)).unwrap_or((0, 0));
+      let b_position = b.spatial_position_gr.as_ref().map(|p| (p.y, p.x)).unwrap_or((0, 0));
+      a_position.cmp(&b_position).then(a.ordering.cmp(&b.ordering)).then(a.id.cmp(&b.id))
+    });
+  } else {
+    children.sort_by(|a, b| a.ordering.cmp(&b.ordering).then(a.id.cmp(&b.id)));
+  }
+  Ok(children)
+}
+
+pub fn sorted_attachments<'a>(db: &'a Db, item: &Item) -> InfuResult<Vec<&'a Item>> {
+  let mut attachments = db.item.get_attachments(&item.id)?;
+  attachments.sort_by(|a, b| a.ordering.cmp(&b.ordering).then(a.id.cmp(&b.id)));
+  Ok(attachments)
+}
+
+/// A file name derived from an item title that is safe to use on any common filesystem and in a url path.
+pub fn safe_file_name(title: &str, fallback: &str) -> String {
+  let name = title
+    .trim()
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+    .collect::<String>();
+  let name = name.trim_matches(|c| c == '.' || c == '_').to_owned();
+  if name.is_empty() { fallback.to_owned() } else { name.chars().take(100).collect() }
+}
@@ -0,0 +1,704 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Static html export of a public page and the public pages below it.
+//!
+//! Every exported page is written to the root of the output directory, so that all links between pages are
+//! plain relative file names. Images are written to `images/` at (at most) the configured width, and files
+//! to `files/<item_id>/`. Only what an anonymous visitor of the web client could see is exported.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+
+use image::ImageReader;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use infusdk::item::{
+  CompositeFlags, DividerDirection, Item, ItemType, NoteFlags, PermissionFlags, TableFlags, is_page_item,
+};
+use infusdk::util::geometry::Dimensions;
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::Uid;
+use log::{debug, info, warn};
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::{children_in_reading_order, note_text_runs, safe_file_name, sorted_attachments};
+use crate::storage::cache::{self as storage_cache, ImageCacheKey, ImageSize};
+use crate::storage::db::Db;
+use crate::storage::object::{self as storage_object, ObjectStore};
+use crate::util::image::{adjust_image_for_exif_orientation, get_exif_orientation};
+use crate::util::image_rendition::{get_image_item_bytes, needs_image_rendition};
+use crate::web::routes::command::authorize_item;
+
+pub const SITE_IMAGE_WIDTH_PX_DEFAULT: u32 = 1200;
+
+const SITE_INDEX_FILENAME: &str = "index.html";
+const SITE_SITEMAP_HTML_FILENAME: &str = "sitemap.html";
+const SITE_SITEMAP_XML_FILENAME: &str = "sitemap.xml";
+const SITE_STYLESHEET_FILENAME: &str = "style.css";
+const SITE_IMAGES_DIR: &str = "images";
+const SITE_FILES_DIR: &str = "files";
+const SITE_JPEG_QUALITY: u8 = 85;
+const SITE_MAX_RATING: i64 = 5;
+
+const SITE_STYLESHEET: &str = "\
+body { margin: 0; font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Helvetica, Arial, sans-serif; color: #1e293b; line-height: 1.5; }
+nav { padding: 10px 24px; border-bottom: 1px solid #e2e8f0; font-size: 14px; }
+nav a { color: #334155; }
+main { max-width: 800px; margin: 0 auto; padding: 16px 24px 48px 24px; }
+a { color: #0000aa; }
+pre { background: #f1f5f9; padding: 8px 12px; overflow-x: auto; }
+figure { margin: 16px 0; }
+figure img { max-width: 100%; height: auto; }
+figcaption { font-size: 14px; color: #64748b; }
+table { border-collapse: collapse; margin: 16px 0; }
+caption { text-align: left; font-weight: bold; padding-bottom: 4px; }
+th, td { border: 1px solid #cbd5e1; padding: 4px 8px; text-align: left; vertical-align: top; }
+td img { max-width: 160px; height: auto; }
+.composite { border: 1px solid #e2e8f0; border-radius: 4px; padding: 4px 12px; margin: 12px 0; }
+.composite.no-border { border: none; padding: 0; }
+.align-center { text-align: center; }
+.align-right { text-align: right; }
+.align-justify { text-align: justify; }
+.file-size { color: #64748b; font-size: 14px; }
+.rating { color: #f59e0b; letter-spacing: 2px; }
+";
+
+#[derive(Default)]
+pub struct SiteExportSummary {
+  pub pages: usize,
+  pub images: usize,
+  pub files: usize,
+  pub skipped_items: usize,
+}
+
+struct SitePage {
+  item_id: Uid,
+  parent_page_id: Option<Uid>,
+  title: String,
+  filename: String,
+}
+
+struct ImageAsset {
+  owner_id: Uid,
+  item_id: Uid,
+  mime_type: String,
+  size_px: Dimensions<i64>,
+  object_encryption_key: String,
+  path: String,
+  resize: bool,
+}
+
+struct FileAsset {
+  owner_id: Uid,
+  item_id: Uid,
+  object_encryption_key: String,
+  path: String,
+}
+
+struct SiteRenderer<'a> {
+  db: &'a MutexGuard<'a, Db>,
+  filename_by_page_id: HashMap<Uid, String>,
+  image_width_px: u32,
+  images: Vec<ImageAsset>,
+  files: Vec<FileAsset>,
+  asset_path_by_item_id: HashMap<Uid, String>,
+  skipped_items: usize,
+}
+
+/// Export the public page `page_id` and all public pages below it to `out_dir` as static html.
+pub async fn export_site(
+  data_dir: &str,
+  db: Arc<Mutex<Db>>,
+  object_store: Arc<ObjectStore>,
+  image_cache: Arc<std::sync::Mutex<storage_cache::ImageCache>>,
+  page_id: &Uid,
+  out_dir: &Path,
+  image_width_px: u32,
+  base_url_maybe: Option<&str>,
+) -> InfuResult<SiteExportSummary> {
+  let (pages, page_html, images, files, skipped_items) = {
+    let db = db.lock().await;
+    let root_page = db.item.get(page_id)?;
+    if !is_public_page(root_page) {
+      return Err(format!("Item '{}' is not a public page.", page_id).into());
+    }
+
+    let mut pages = vec![SitePage {
+      item_id: root_page.id.clone(),
+      parent_page_id: None,
+      title: item_title(root_page),
+      filename: SITE_INDEX_FILENAME.to_owned(),
+    }];
+    let mut used_filenames = HashSet::from([SITE_INDEX_FILENAME.to_owned(), SITE_SITEMAP_HTML_FILENAME.to_owned()]);
+    collect_public_pages(&db, root_page, &root_page.id, &mut pages, &mut used_filenames)?;
+
+    let mut renderer = SiteRenderer {
+      db: &db,
+      filename_by_page_id: pages.iter().map(|page| (page.item_id.clone(), page.filename.clone())).collect(),
+      image_width_px,
+      images: vec![],
+      files: vec![],
+      asset_path_by_item_id: HashMap::new(),
+      skipped_items: 0,
+    };
+    let mut page_html = vec![];
+    for page in &pages {
+      page_html.push((page.filename.clone(), renderer.render_page(page, &pages)?));
+    }
+    (pages, page_html, renderer.images, renderer.files, renderer.skipped_items)
+  };
+
+  tokio::fs::create_dir_all(out_dir).await?;
+  tokio::fs::write(out_dir.join(SITE_STYLESHEET_FILENAME), SITE_STYLESHEET).await?;
+  for (filename, html) in &page_html {
+    tokio::fs::write(out_dir.join(filename), html).await?;
+  }
+  tokio::fs::write(out_dir.join(SITE_SITEMAP_HTML_FILENAME), render_sitemap_html(&pages)).await?;
+  if let Some(base_url) = base_url_maybe {
+    tokio::fs::write(out_dir.join(SITE_SITEMAP_XML_FILENAME), render_sitemap_xml(&pages, base_url)).await?;
+  }
+
+  let mut summary = SiteExportSummary { pages: pages.len(), skipped_items, ..Default::default() };
+  if !images.is_empty() {
+    tokio::fs::create_dir_all(out_dir.join(SITE_IMAGES_DIR)).await?;
+  }
+  for image in &images {
+    match export_image(data_dir, object_store.clone(), image_cache.clone(), image, image_width_px).await {
+      Ok(bytes) => {
+        tokio::fs::write(out_dir.join(&image.path), bytes).await?;
+        summary.images += 1;
+      }
+      Err(e) => {
+        warn!("Could not export image item '{}': {}", image.item_id, e);
+        summary.skipped_items += 1;
+      }
+    }
+  }
+  for file in &files {
+    let path = out_dir.join(&file.path);
+    if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    let bytes = storage_object::get(
+      object_store.clone(),
+      file.owner_id.clone(),
+      file.item_id.clone(),
+      &file.object_encryption_key,
+    )
+    .await?;
+    tokio::fs::write(path, bytes).await?;
+    summary.files += 1;
+  }
+
+  info!(
+    "Exported {} page(s), {} image(s) and {} file(s) to '{}'.",
+    summary.pages,
+    summary.images,
+    summary.files,
+    out_dir.display()
+  );
+  Ok(summary)
+}
+
+fn is_public_page(item: &Item) -> bool {
+  is_page_item(item) && item.permission_flags == Some(PermissionFlags::Public as i64)
+}
+
+fn item_title(item: &Item) -> String {
+  item.title.as_deref().map(str::trim).filter(|title| !title.is_empty()).unwrap_or("Untitled").to_owned()
+}
+
+/// Pages are collected depth first, in reading order. Pages that are not public are skipped, along with
+/// everything below them.
+fn collect_public_pages(
+  db: &Db,
+  container: &Item,
+  parent_page_id: &Uid,
+  pages: &mut Vec<SitePage>,
+  used_filenames: &mut HashSet<String>,
+) -> InfuResult<()> {
+  for child in children_in_reading_order(db, container)? {
+    match child.item_type {
+      ItemType::Page => {
+        if !is_public_page(child) {
+          continue;
+        }
+        let mut filename = match &child.label {
+          Some(label) => format!("{}.html", label),
+          None => format!("{}.html", child.id),
+        };
+        if used_filenames.contains(&filename) {
+          filename = format!("{}.html", child.id);
+        }
+        used_filenames.insert(filename.clone());
+        pages.push(SitePage {
+          item_id: child.id.clone(),
+          parent_page_id: Some(parent_page_id.clone()),
+          title: item_title(child),
+          filename,
+        });
+        collect_public_pages(db, child, &child.id, pages, used_filenames)?;
+      }
+      ItemType::Composite | ItemType::Table => {
+        collect_public_pages(db, child, parent_page_id, pages, used_filenames)?;
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+impl<'a> SiteRenderer<'a> {
+  fn db(&self) -> &'a Db {
+    self.db
+  }
+
+  fn render_page(&mut self, page: &SitePage, pages: &[SitePage]) -> InfuResult<String> {
+    let page_item = self.db().item.get(&page.item_id)?;
+
+    let mut breadcrumbs = vec![];
+    let mut parent_page_id_maybe = page.parent_page_id.clone();
+    while let Some(parent_page_id) = parent_page_id_maybe {
+      let Some(parent_page) = pages.iter().find(|p| p.item_id == parent_page_id) else {
+        break;
+      };
+      breadcrumbs.push(format!(
+        "<a href=\"{}\">{}</a>",
+        escape_html(&parent_page.filename),
+        escape_html(&parent_page.title)
+      ));
+      parent_page_id_maybe = parent_page.parent_page_id.clone();
+    }
+    breadcrumbs.reverse();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(&page.title)));
+    html.push_str(&format!("<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n", SITE_STYLESHEET_FILENAME));
+    if !breadcrumbs.is_empty() {
+      html.push_str(&format!("<nav>{}</nav>\n", breadcrumbs.join(" / ")));
+    }
+    html.push_str("<main>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(&page.title)));
+    let children = children_in_reading_order(self.db(), page_item)?;
+    self.render_items(&children, &mut html)?;
+    html.push_str("</main>\n</body>\n</html>\n");
+    Ok(html)
+  }
+
+  /// Consecutive bullet and numbered notes are grouped into lists.
+  fn render_items(&mut self, items: &[&Item], html: &mut String) -> InfuResult<()> {
+    let mut open_list_maybe: Option<&str> = None;
+    for item in items {
+      let list_tag_maybe = match item.item_type {
+        ItemType::Note => {
+          let flags = NoteFlags::from_bits_truncate(item.flags.unwrap_or(0));
+          if flags.contains(NoteFlags::Bullet1) {
+            Some("ul")
+          } else if flags.contains(NoteFlags::Numbered) {
+            Some("ol")
+          } else {
+            None
+          }
+        }
+        _ => None,
+      };
+      if open_list_maybe.is_some() && open_list_maybe != list_tag_maybe {
+        html.push_str(&format!("</{}>\n", open_list_maybe.unwrap()));
+        open_list_maybe = None;
+      }
+      if let Some(list_tag) = list_tag_maybe {
+        if open_list_maybe.is_none() {
+          html.push_str(&format!("<{}>\n", list_tag));
+          open_list_maybe = Some(list_tag);
+        }
+        html.push_str(&format!("<li>{}</li>\n", self.render_note_inline(item)));
+        continue;
+      }
+      self.render_item(item, html, 0)?;
+    }
+    if let Some(list_tag) = open_list_maybe {
+      html.push_str(&format!("</{}>\n", list_tag));
+    }
+    Ok(())
+  }
+
+  fn render_item(&mut self, item: &Item, html: &mut String, link_depth: usize) -> InfuResult<()> {
+    match item.item_type {
+      ItemType::Note => {
+        let flags = NoteFlags::from_bits_truncate(item.flags.unwrap_or(0));
+        if flags.contains(NoteFlags::Code) {
+          html.push_str(&format!("<pre><code>{}</code></pre>\n", escape_html(item.title.as_deref().unwrap_or(""))));
+          return Ok(());
+        }
+        let tag = if flags.contains(NoteFlags::Heading1) {
+          "h2"
+        } else if flags.contains(NoteFlags::Heading2) {
+          "h3"
+        } else if flags.contains(NoteFlags::Heading3) {
+          "h4"
+        } else if flags.contains(NoteFlags::Heading4) {
+          "h5"
+        } else {
+          "p"
+        };
+        let class = if flags.contains(NoteFlags::AlignCenter) {
+          " class=\"align-center\""
+        } else if flags.contains(NoteFlags::AlignRight) {
+          " class=\"align-right\""
+        } else if flags.contains(NoteFlags::AlignJustify) {
+          " class=\"align-justify\""
+        } else {
+          ""
+        };
+        html.push_str(&format!("<{}{}>{}</{}>\n", tag, class, self.render_note_inline(item), tag));
+      }
+      ItemType::Page => match self.filename_by_page_id.get(&item.id) {
+        Some(filename) => {
+          html.push_str(&format!(
+            "<p class=\"page-link\"><a href=\"{}\">{}</a></p>\n",
+            escape_html(filename),
+            escape_html(&item_title(item))
+          ));
+        }
+        None => self.skipped_items += 1,
+      },
+      ItemType::Table => self.render_table(item, html)?,
+      ItemType::Composite => {
+        let hide_border =
+          CompositeFlags::from_bits_truncate(item.flags.unwrap_or(0)).contains(CompositeFlags::HideBorder);
+        html.push_str(if hide_border {
+          "<div class=\"composite no-border\">\n"
+        } else {
+          "<div class=\"composite\">\n"
+        });
+        let children = children_in_reading_order(self.db(), item)?;
+        self.render_items(&children, html)?;
+        html.push_str("</div>\n");
+      }
+      ItemType::Image => {
+        let image_html = self.render_image(item)?;
+        html.push_str(&format!(
+          "<figure>{}<figcaption>{}</figcaption></figure>\n",
+          image_html,
+          escape_html(item.title.as_deref().unwrap_or(""))
+        ));
+      }
+      ItemType::File | ItemType::Text => {
+        html.push_str(&format!("<p class=\"file\">{}</p>\n", self.render_file_link(item)?));
+      }
+      ItemType::Link => match self.resolve_link(item, link_depth)? {
+        Some(target) => self.render_item(target, html, link_depth + 1)?,
+        None => self.skipped_items += 1,
+      },
+      ItemType::Rating => {
+        html.push_str(&format!("<p class=\"rating\">{}</p>\n", rating_stars(item)));
+      }
+      ItemType::Divider => {
+        if item.divider_direction != Some(DividerDirection::Vertical) {
+          html.push_str("<hr>\n");
+        }
+      }
+      _ => {
+        debug!("Not exporting item '{}' of type '{}'.", item.id, item.item_type.as_str());
+        self.skipped_items += 1;
+      }
+    }
+    Ok(())
+  }
+
+  fn render_table(&mut self, table: &Item, html: &mut String) -> InfuResult<()> {
+    let flags = TableFlags::from_bits_truncate(table.flags.unwrap_or(0));
+    let columns = table.table_columns.as_deref().unwrap_or(&[]);
+    let visible_columns =
+      table.number_of_visible_columns.map(|count| count.max(1) as usize).unwrap_or(columns.len()).max(1);
+
+    html.push_str("<table>\n");
+    if !flags.contains(TableFlags::HideTitle) {
+      html.push_str(&format!("<caption>{}</caption>\n", escape_html(table.title.as_deref().unwrap_or(""))));
+    }
+    if flags.contains(TableFlags::ShowColHeader) {
+      html.push_str("<thead><tr>");
+      for column_index in 0..visible_columns {
+        let name = columns.get(column_index).map(|column| column.name.as_str()).unwrap_or("");
+        html.push_str(&format!("<th>{}</th>", escape_html(name)));
+      }
+      html.push_str("</tr></thead>\n");
+    }
+    html.push_str("<tbody>\n");
+    // The first column of a row is the row item itself and later columns are its attachments.
+    for row in children_in_reading_order(self.db(), table)? {
+      let mut cell_items = vec![row];
+      cell_items.extend(sorted_attachments(self.db(), row)?.into_iter().take(visible_columns - 1));
+      html.push_str("<tr>");
+      for column_index in 0..visible_columns {
+        let cell_html = match cell_items.get(column_index) {
+          Some(cell_item) => self.render_cell(cell_item)?,
+          None => String::new(),
+        };
+        html.push_str(&format!("<td>{}</td>", cell_html));
+      }
+      html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+    Ok(())
+  }
+
+  fn render_cell(&mut self, item: &Item) -> InfuResult<String> {
+    Ok(match item.item_type {
+      ItemType::Note => self.render_note_inline(item),
+      ItemType::Image => self.render_image(item)?,
+      ItemType::File | ItemType::Text => self.render_file_link(item)?,
+      ItemType::Page => match self.filename_by_page_id.get(&item.id) {
+        Some(filename) => format!("<a href=\"{}\">{}</a>", escape_html(filename), escape_html(&item_title(item))),
+        None => escape_html(&item_title(item)),
+      },
+      ItemType::Rating => format!("<span class=\"rating\">{}</span>", rating_stars(item)),
+      ItemType::Link => match self.resolve_link(item, 0)? {
+        Some(target) if target.item_type != ItemType::Link => self.render_cell(target)?,
+        _ => String::new(),
+      },
+      ItemType::Password => String::new(),
+      _ => escape_html(item.title.as_deref().unwrap_or("")),
+    })
+  }
+
+  fn render_note_inline(&self, note: &Item) -> String {
+    let mut html = String::new();
+    for run in note_text_runs(note) {
+      let mut run_html = escape_html(&run.text);
+      if run.italic {
+        run_html = format!("<em>{}</em>", run_html);
+      }
+      if run.bold {
+        run_html = format!("<strong>{}</strong>", run_html);
+      }
+      if let Some(url) = &run.url {
+        run_html = format!("<a href=\"{}\">{}</a>", escape_html(url), run_html);
+      }
+      html.push_str(&run_html);
+    }
+    html
+  }
+
+  fn render_image(&mut self, image: &Item) -> InfuResult<String> {
+    let size_px = image.image_size_px.clone().ok_or(format!("Image item '{}' has no image size.", image.id))?;
+    let mime_type = image.mime_type.clone().ok_or(format!("Image item '{}' has no mime type.", image.id))?;
+    let path = match self.asset_path_by_item_id.get(&image.id) {
+      Some(path) => path.clone(),
+      None => {
+        // Images narrower than the export width that browsers can display are copied as they are.
+        let original_extension_maybe =
+          if needs_image_rendition(&mime_type) { None } else { web_image_extension(&mime_type) };
+        let resize = size_px.w > self.image_width_px as i64 || original_extension_maybe.is_none();
+        let path = format!(
+          "{}/{}.{}",
+          SITE_IMAGES_DIR,
+          image.id,
+          if resize { "jpg" } else { original_extension_maybe.unwrap() }
+        );
+        self.images.push(ImageAsset {
+          owner_id: image.owner_id.clone(),
+          item_id: image.id.clone(),
+          mime_type,
+          size_px: size_px.clone(),
+          object_encryption_key: self.object_encryption_key(image)?,
+          path: path.clone(),
+          resize,
+        });
+        self.asset_path_by_item_id.insert(image.id.clone(), path.clone());
+        path
+      }
+    };
+    let width_px = size_px.w.min(self.image_width_px as i64).max(1);
+    let height_px = if size_px.w > 0 { size_px.h * width_px / size_px.w } else { size_px.h };
+    Ok(format!(
+      "<img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\" loading=\"lazy\">",
+      escape_html(&path),
+      escape_html(image.title.as_deref().unwrap_or("")),
+      width_px,
+      height_px
+    ))
+  }
+
+  fn render_file_link(&mut self, file: &Item) -> InfuResult<String> {
+    let path = match self.asset_path_by_item_id.get(&file.id) {
+      Some(path) => path.clone(),
+      None => {
+        let path =
+          format!("{}/{}/{}", SITE_FILES_DIR, file.id, safe_file_name(file.title.as_deref().unwrap_or(""), &file.id));
+        self.files.push(FileAsset {
+          owner_id: file.owner_id.clone(),
+          item_id: file.id.clone(),
+          object_encryption_key: self.object_encryption_key(file)?,
+          path: path.clone(),
+        });
+        self.asset_path_by_item_id.insert(file.id.clone(), path.clone());
+        path
+      }
+    };
+    let size_html = match file.file_size_bytes {
+      Some(size_bytes) => format!(" <span class=\"file-size\">({})</span>", format_file_size(size_bytes)),
+      None => String::new(),
+    };
+    Ok(format!("<a href=\"{}\">{}</a>{}", escape_html(&path), escape_html(&item_title(file)), size_html))
+  }
+
+  /// The item a link points to, if an anonymous visitor could see it. Links are followed one level deep.
+  fn resolve_link(&self, link: &Item, link_depth: usize) -> InfuResult<Option<&'a Item>> {
+    if link_depth > 0 {
+      return Ok(None);
+    }
+    let Some(link_to) = &link.link_to else {
+      return Ok(None);
+    };
+    let Ok(target) = self.db().item.get(link_to) else {
+      return Ok(None);
+    };
+    if target.item_type == ItemType::Page && !self.filename_by_page_id.contains_key(&target.id) {
+      return Ok(None);
+    }
+    Ok(if authorize_item(self.db, target, &None, 0).is_ok() { Some(target) } else { None })
+  }
+
+  fn object_encryption_key(&self, item: &Item) -> InfuResult<String> {
+    Ok(
+      self
+        .db()
+        .user
+        .get(&item.owner_id)
+        .ok_or(format!("User '{}' not found.", item.owner_id))?
+        .object_encryption_key
+        .clone(),
+    )
+  }
+}
+
+async fn export_image(
+  data_dir: &str,
+  object_store: Arc<ObjectStore>,
+  image_cache: Arc<std::sync::Mutex<storage_cache::ImageCache>>,
+  image: &ImageAsset,
+  image_width_px: u32,
+) -> InfuResult<Vec<u8>> {
+  let original_bytes = |object_store: Arc<ObjectStore>| {
+    get_image_item_bytes(
+      data_dir,
+      object_store,
+      &image.owner_id,
+      &image.item_id,
+      &image.mime_type,
+      &image.object_encryption_key,
+    )
+  };
+  if !image.resize {
+    return original_bytes(object_store).await;
+  }
+
+  let width_px = (image.size_px.w.max(1) as u32).min(image_width_px);
+  let cache_key = ImageCacheKey { item_id: image.item_id.clone(), size: ImageSize::Width(width_px) };
+  if let Some(data) = storage_cache::get(image_cache, &image.owner_id, cache_key).await? {
+    return Ok(data);
+  }
+
+  // The image cache belongs to the web server, so renditions made here are not added to it.
+  let bytes = original_bytes(object_store).await?;
+  let exif_orientation = get_exif_orientation(bytes.clone(), &image.item_id);
+  let img = ImageReader::new(Cursor::new(bytes))
+    .with_guessed_format()?
+    .decode()
+    .map_err(|e| format!("Could not read image '{}': {}", image.item_id, e))?;
+  let img = adjust_image_for_exif_orientation(img, exif_orientation, &image.item_id);
+  let aspect = image.size_px.w.max(1) as f64 / image.size_px.h.max(1) as f64;
+  let height_px = (width_px as f64 / aspect).ceil() as u32 + 1;
+  let img = img.resize(width_px, height_px, FilterType::Lanczos3).to_rgb8();
+  let mut cursor = Cursor::new(Vec::new());
+  img
+    .write_with_encoder(JpegEncoder::new_with_quality(&mut cursor, SITE_JPEG_QUALITY))
+    .map_err(|e| format!("Could not encode image '{}': {}", image.item_id, e))?;
+  Ok(cursor.into_inner())
+}
+
+fn render_sitemap_html(pages: &[SitePage]) -> String {
+  let mut html = String::new();
+  html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+  html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+  html.push_str("<title>Sitemap</title>\n");
+  html.push_str(&format!("<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n<main>\n", SITE_STYLESHEET_FILENAME));
+  html.push_str("<h1>Sitemap</h1>\n");
+  render_sitemap_html_level(pages, None, &mut html);
+  html.push_str("</main>\n</body>\n</html>\n");
+  html
+}
+
+fn render_sitemap_html_level(pages: &[SitePage], parent_page_id: Option<&Uid>, html: &mut String) {
+  let level_pages = pages.iter().filter(|page| page.parent_page_id.as_ref() == parent_page_id).collect::<Vec<_>>();
+  if level_pages.is_empty() {
+    return;
+  }
+  html.push_str("<ul>\n");
+  for page in level_pages {
+    html.push_str(&format!("<li><a href=\"{}\">{}</a>", escape_html(&page.filename), escape_html(&page.title)));
+    render_sitemap_html_level(pages, Some(&page.item_id), html);
+    html.push_str("</li>\n");
+  }
+  html.push_str("</ul>\n");
+}
+
+fn render_sitemap_xml(pages: &[SitePage], base_url: &str) -> String {
+  let base_url = base_url.trim_end_matches('/');
+  let mut xml = String::new();
+  xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+  xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+  for page in pages {
+    xml.push_str(&format!("  <url><loc>{}</loc></url>\n", escape_html(&format!("{}/{}", base_url, page.filename))));
+  }
+  xml.push_str("</urlset>\n");
+  xml
+}
+
+fn web_image_extension(mime_type: &str) -> Option<&'static str> {
+  match mime_type {
+    "image/jpeg" | "image/jpg" => Some("jpg"),
+    "image/png" => Some("png"),
+    "image/gif" => Some("gif"),
+    "image/webp" => Some("webp"),
+    _ => None,
+  }
+}
+
+fn rating_stars(item: &Item) -> String {
+  let rating = item.rating.unwrap_or(0).clamp(0, SITE_MAX_RATING) as usize;
+  format!("{}{}", "★".repeat(rating), "☆".repeat(SITE_MAX_RATING as usize - rating))
+}
+
+fn format_file_size(size_bytes: i64) -> String {
+  if size_bytes >= 1024 * 1024 {
+    format!("{:.1} MB", size_bytes as f64 / (1024.0 * 1024.0))
+  } else if size_bytes >= 1024 {
+    format!("{:.1} KB", size_bytes as f64 / 1024.0)
+  } else {
+    format!("{} bytes", size_bytes)
+  }
+}
+
+fn escape_html(value: &str) -> String {
+  value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
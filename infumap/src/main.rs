@@ -17,6 +17,7 @@
 mod ai;
 mod cli;
 mod config;
+mod export;
 mod setup;
 mod storage;
 mod tokiort;
@@ -33,6 +34,7 @@ async fn main() {
   let arg_matches = Command::new("Infumap")
     .version("0.8.0")
     .subcommand(cli::compact::make_clap_subcommand())
+    .subcommand(cli::duplicates::make_clap_subcommand())
     .subcommand(cli::embed::make_clap_subcommand())
     .subcommand(cli::emergency::make_clap_subcommand())
     .subcommand(cli::keygen::make_clap_subcommand())
@@ -45,8 +47,12 @@ async fn main() {
     .subcommand(cli::reconcile::make_clap_subcommand())
     .subcommand(cli::restore::make_clap_subcommand())
     .subcommand(cli::extract::make_clap_subcommand())
+    .subcommand(cli::export_markdown::make_clap_subcommand())
+    .subcommand(cli::export_site::make_clap_subcommand())
     .subcommand(cli::fragment::make_clap_subcommand())
     .subcommand(cli::geo::make_clap_subcommand())
+    .subcommand(cli::import_email::make_clap_subcommand())
+    .subcommand(cli::import_markdown::make_clap_subcommand())
     .subcommand(cli::stats::make_clap_subcommand())
     .subcommand(cli::upload::make_clap_subcommand())
     .subcommand(web::make_clap_subcommand())
@@ -59,6 +65,7 @@ async fn main() {
       Err(e) => Err(e),
       Ok(()) => match command {
         "compact" => cli::compact::execute(&arg_sub_matches).await,
+        "duplicates" => cli::duplicates::execute(&arg_sub_matches).await,
         "embed" => cli::embed::execute(&arg_sub_matches).await,
         "emergency" => cli::emergency::execute(&arg_sub_matches).await,
         "keygen" => cli::keygen::execute(&arg_sub_matches),
@@ -71,8 +78,12 @@ async fn main() {
         "reconcile" => cli::reconcile::execute(&arg_sub_matches).await,
         "restore" => cli::restore::execute(&arg_sub_matches).await,
         "extract" => cli::extract::execute(&arg_sub_matches).await,
+        "export-markdown" => cli::export_markdown::execute(&arg_sub_matches).await,
+        "export-site" => cli::export_site::execute(&arg_sub_matches).await,
         "fragment" => cli::fragment::execute(&arg_sub_matches).await,
         "geo" => cli::geo::execute(&arg_sub_matches).await,
+        "import-email" => cli::import_email::execute(&arg_sub_matches).await,
+        "import-markdown" => cli::import_markdown::execute(&arg_sub_matches).await,
         "stats" => cli::stats::execute(&arg_sub_matches).await,
         "upload" => cli::upload::execute(&arg_sub_matches).await,
         _ => {
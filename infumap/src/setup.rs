@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::ai::llm::configured_llm_model_summaries;
 use crate::storage::cache as storage_cache;
 use crate::util::crypto::generate_key;
 use crate::util::fs::{expand_tilde, expand_tilde_path_exists, path_exists};
@@ -319,6 +320,111 @@ pub async fn init_fs_maybe_and_get_config(settings_path_maybe: Option<&String>)
       info!(" {} = {}", CONFIG_LLAMA_SERVER_URL, "<not set>");
     }
   }
+  match configured_llm_model_summaries(&config) {
+    Ok(models) if !models.is_empty() => {
+      let names = models.iter().map(|model| model.name.as_str()).collect::<Vec<_>>();
+      info!(" {} = [{}]", CONFIG_LLM_MODELS, names.join(", "));
+    }
+    Ok(_) => {
+      info!(" {} = {}", CONFIG_LLM_MODELS, "<not set>");
+    }
+    Err(e) => return Err(e),
+  }
+  match config.get_string(CONFIG_DEFAULT_LLM_MODEL) {
+    Ok(v) if !v.trim().is_empty() => {
+      info!(" {} = '{}'", CONFIG_DEFAULT_LLM_MODEL, v);
+    }
+    _ => {
+      info!(" {} = {}", CONFIG_DEFAULT_LLM_MODEL, "<not set>");
+    }
+  }
+  info!(
+    " {} = {}",
+    CONFIG_ENABLE_CHAT_TRANSCRIPTS,
+    config.get_bool(CONFIG_ENABLE_CHAT_TRANSCRIPTS).map_err(|e| e.to_string())?
+  );
+  if config.get_bool(CONFIG_ENABLE_CHAT_TRANSCRIPTS).map_err(|e| e.to_string())? {
+    info!(
+      "  {} = {}",
+      CONFIG_CHAT_TRANSCRIPT_MAX_MB,
+      config.get_int(CONFIG_CHAT_TRANSCRIPT_MAX_MB).map_err(|e| e.to_string())?
+    );
+    info!(
+      "  {} = {}",
+      CONFIG_CHAT_TRANSCRIPT_MAX_FILES,
+      config.get_int(CONFIG_CHAT_TRANSCRIPT_MAX_FILES).map_err(|e| e.to_string())?
+    );
+  }
+  info!(
+    " {} = {}",
+    CONFIG_ENABLE_LINK_SNAPSHOTS,
+    config.get_bool(CONFIG_ENABLE_LINK_SNAPSHOTS).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = {}",
+    CONFIG_ENABLE_NEAR_DUPLICATE_SCAN,
+    config.get_bool(CONFIG_ENABLE_NEAR_DUPLICATE_SCAN).map_err(|e| e.to_string())?
+  );
+  match config.get_string(CONFIG_TRANSCRIBE_URL) {
+    Ok(v) if !v.trim().is_empty() => {
+      info!(" {} = '{}'", CONFIG_TRANSCRIBE_URL, v);
+      info!(
+        "  {} = '{}'",
+        CONFIG_TRANSCRIBE_MODEL,
+        config.get_string(CONFIG_TRANSCRIBE_MODEL).map_err(|e| e.to_string())?
+      );
+    }
+    _ => {
+      info!(" {} = {}", CONFIG_TRANSCRIBE_URL, "<not set>");
+    }
+  }
+  match config.get_string(CONFIG_FACE_EMBED_URL) {
+    Ok(v) if !v.trim().is_empty() => {
+      info!(" {} = '{}'", CONFIG_FACE_EMBED_URL, v);
+    }
+    _ => {
+      info!(" {} = {}", CONFIG_FACE_EMBED_URL, "<not set>");
+    }
+  }
+  match config.get_string(CONFIG_FFMPEG_PATH) {
+    Ok(v) if !v.trim().is_empty() => {
+      info!(" {} = '{}'", CONFIG_FFMPEG_PATH, v);
+    }
+    _ => {
+      info!(" {} = {}", CONFIG_FFMPEG_PATH, "<not set>");
+    }
+  }
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_PDF,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_PDF).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_MARKDOWN,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_MARKDOWN).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_TEXT,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_TEXT).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_DOCUMENT,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_DOCUMENT).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT).map_err(|e| e.to_string())?
+  );
+  info!(
+    " {} = '{}'",
+    CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT,
+    config.get_string(CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT).map_err(|e| e.to_string())?
+  );
+  info!(" {} = '{}'", CONFIG_GEO_PROVIDER, config.get_string(CONFIG_GEO_PROVIDER).map_err(|e| e.to_string())?);
   info!(" {} = '{}'", CONFIG_GEOAPIFY_URL, config.get_string(CONFIG_GEOAPIFY_URL).map_err(|e| e.to_string())?);
   info!(
     " {} = {}",
@@ -562,6 +668,40 @@ pub fn add_config_defaults(builder: ConfigBuilder<DefaultState>) -> InfuResult<C
       .map_err(|e| e.to_string())?
       .set_default(CONFIG_LLAMA_SERVER_URL, CONFIG_LLAMA_SERVER_URL_DEFAULT)
       .map_err(|e| e.to_string())?
+      .set_default(CONFIG_DEFAULT_LLM_MODEL, CONFIG_DEFAULT_LLM_MODEL_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_ENABLE_CHAT_TRANSCRIPTS, CONFIG_ENABLE_CHAT_TRANSCRIPTS_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_CHAT_TRANSCRIPT_MAX_MB, CONFIG_CHAT_TRANSCRIPT_MAX_MB_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_CHAT_TRANSCRIPT_MAX_FILES, CONFIG_CHAT_TRANSCRIPT_MAX_FILES_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_ENABLE_LINK_SNAPSHOTS, CONFIG_ENABLE_LINK_SNAPSHOTS_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_ENABLE_NEAR_DUPLICATE_SCAN, CONFIG_ENABLE_NEAR_DUPLICATE_SCAN_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_TRANSCRIBE_URL, CONFIG_TRANSCRIBE_URL_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_TRANSCRIBE_MODEL, CONFIG_TRANSCRIBE_MODEL_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FACE_EMBED_URL, CONFIG_FACE_EMBED_URL_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FFMPEG_PATH, CONFIG_FFMPEG_PATH_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_PDF, CONFIG_FRAGMENT_CHUNKING_PDF_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_MARKDOWN, CONFIG_FRAGMENT_CHUNKING_MARKDOWN_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_TEXT, CONFIG_FRAGMENT_CHUNKING_TEXT_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_DOCUMENT, CONFIG_FRAGMENT_CHUNKING_DOCUMENT_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT, CONFIG_FRAGMENT_CHUNKING_LINK_SNAPSHOT_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT, CONFIG_FRAGMENT_CHUNKING_AUDIO_TRANSCRIPT_DEFAULT)
+      .map_err(|e| e.to_string())?
+      .set_default(CONFIG_GEO_PROVIDER, CONFIG_GEO_PROVIDER_DEFAULT)
+      .map_err(|e| e.to_string())?
       .set_default(CONFIG_GEOAPIFY_URL, CONFIG_GEOAPIFY_URL_DEFAULT)
       .map_err(|e| e.to_string())?
       .set_default(CONFIG_GEOAPIFY_MAX_REQUESTS_PER_MINUTE, CONFIG_GEOAPIFY_MAX_REQUESTS_PER_MINUTE_DEFAULT)
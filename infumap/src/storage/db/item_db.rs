@@ -109,6 +109,10 @@ pub struct ItemDb {
   owner_id_by_item_id: HashMap<Uid, Uid>,
   children_of: HashMap<Uid, Vec<Uid>>,
   attachments_of: HashMap<Uid, Vec<Uid>>,
+  item_id_by_label_by_user_id: HashMap<Uid, HashMap<String, Uid>>,
+
+  // previous item labels, so that /{username}/{label} urls keep working after a label has been changed.
+  label_redirects_by_user_id: HashMap<Uid, HashMap<String, Uid>>,
 }
 
 #[derive(Clone)]
@@ -117,25 +121,27 @@ struct ReplayItemState {
   relationship_to_parent: RelationshipToParent,
 }
 
-struct ContainerVersionReplayState {
+struct ItemLogReplayState {
   log_epoch: u64,
   item_by_id: HashMap<Uid, ReplayItemState>,
   version_by_container: HashMap<Uid, u64>,
   has_seen_compaction_versions: bool,
+  label_redirects: HashMap<String, Uid>,
 }
 
-impl ContainerVersionReplayState {
+impl ItemLogReplayState {
   fn new() -> Self {
     Self {
       log_epoch: 0,
       item_by_id: HashMap::new(),
       version_by_container: HashMap::new(),
       has_seen_compaction_versions: false,
+      label_redirects: HashMap::new(),
     }
   }
 
-  fn into_loaded_sync_state(self) -> (u64, HashMap<Uid, u64>) {
-    (self.log_epoch, self.version_by_container)
+  fn into_loaded_state(self) -> (u64, HashMap<Uid, u64>, HashMap<String, Uid>) {
+    (self.log_epoch, self.version_by_container, self.label_redirects)
   }
 
   fn apply_entry(&mut self, item: &Item) {
@@ -170,6 +176,12 @@ impl ContainerVersionReplayState {
 
     self.bump_containers(touched_container_ids);
     self.item_by_id.insert(new_item.id.clone(), replay_item_state(new_item));
+
+    if let Some(old_label) = &old_item.label
+      && new_item.label.as_ref() != Some(old_label)
+    {
+      self.label_redirects.insert(old_label.clone(), old_item.id.clone());
+    }
   }
 
   fn apply_delete(&mut self, old_item: &Item) {
@@ -188,6 +200,7 @@ impl ContainerVersionReplayState {
 
     self.bump_containers(touched_container_ids);
     self.item_by_id.remove(&old_item.id);
+    self.label_redirects.retain(|_, item_id| *item_id != old_item.id);
   }
 
   fn record_compaction_version(&mut self, container_id: &Uid, version: u64) {
@@ -232,7 +245,7 @@ impl ContainerVersionReplayState {
   }
 }
 
-impl LogReplayObserver<Item> for ContainerVersionReplayState {
+impl LogReplayObserver<Item> for ItemLogReplayState {
   fn on_descriptor(
     &mut self,
     descriptor_record: &Map<String, Value>,
@@ -278,6 +291,12 @@ impl LogReplayObserver<Item> for ContainerVersionReplayState {
         self.record_compaction_version(&container_id, version as u64);
         Ok(true)
       }
+      Some("labelRedirect") => {
+        let label = json::get_string_field(record, "label")?.ok_or("Label redirect record missing 'label'.")?;
+        let item_id = json::get_string_field(record, "itemId")?.ok_or("Label redirect record missing 'itemId'.")?;
+        self.label_redirects.insert(label, item_id);
+        Ok(true)
+      }
       _ => Ok(false),
     }
   }
@@ -323,6 +342,25 @@ impl Serialize for ContainerVersionRecord {
   }
 }
 
+struct LabelRedirectRecord {
+  label: String,
+  item_id: Uid,
+}
+
+impl Serialize for LabelRedirectRecord {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    const NUM_FIELDS: usize = 3;
+    let mut state = serializer.serialize_struct("LabelRedirectRecord", NUM_FIELDS)?;
+    state.serialize_field("__recordType", "labelRedirect")?;
+    state.serialize_field("label", &self.label)?;
+    state.serialize_field("itemId", &self.item_id)?;
+    state.end()
+  }
+}
+
 fn replay_item_state(item: &Item) -> ReplayItemState {
   ReplayItemState { parent_id: item.parent_id.clone(), relationship_to_parent: item.relationship_to_parent.clone() }
 }
@@ -353,6 +391,8 @@ impl ItemDb {
       owner_id_by_item_id: HashMap::new(),
       children_of: HashMap::new(),
       attachments_of: HashMap::new(),
+      item_id_by_label_by_user_id: HashMap::new(),
+      label_redirects_by_user_id: HashMap::new(),
     }
   }
 
@@ -450,6 +490,22 @@ impl ItemDb {
       writer.write_all(serde_json::to_string(&version_record)?.as_bytes()).await?;
       writer.write_all("\n".as_bytes()).await?;
     }
+    let mut label_redirects = self
+      .label_redirects_by_user_id
+      .get(&user.id)
+      .map(|redirects| {
+        redirects
+          .iter()
+          .filter(|(label, item_id)| store.get(item_id).is_some() && self.item_id_for_label(&user.id, label).is_none())
+          .map(|(label, item_id)| LabelRedirectRecord { label: label.clone(), item_id: item_id.clone() })
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+    label_redirects.sort_by(|a, b| a.label.cmp(&b.label));
+    for redirect_record in label_redirects {
+      writer.write_all(serde_json::to_string(&redirect_record)?.as_bytes()).await?;
+      writer.write_all("\n".as_bytes()).await?;
+    }
     writer.flush().await?;
     let number_written = ordered_ids.len() + orphaned_ids.len();
     info!("Wrote {} items to the compacted log.", number_written);
@@ -474,22 +530,34 @@ impl ItemDb {
       }
     }
 
-    let mut container_version_state = ContainerVersionReplayState::new();
+    let mut replay_state = ItemLogReplayState::new();
     let store: KVStore<Item> =
-      KVStore::init_with_observer(log_path_str, CURRENT_ITEM_LOG_VERSION, &mut container_version_state).await?;
+      KVStore::init_with_observer(log_path_str, CURRENT_ITEM_LOG_VERSION, &mut replay_state).await?;
     for (_id, item) in store.get_iter() {
       self.add_to_indexes(item)?;
     }
     self.store_by_user_id.insert(String::from(user_id), store);
-    let (loaded_log_epoch, loaded_container_versions) = container_version_state.into_loaded_sync_state();
+    let (loaded_log_epoch, loaded_container_versions, label_redirects) = replay_state.into_loaded_state();
     self.loaded_log_epoch_by_user_id.insert(String::from(user_id), loaded_log_epoch);
     self.loaded_container_versions_by_user_id.insert(String::from(user_id), loaded_container_versions);
+    self.label_redirects_by_user_id.insert(String::from(user_id), label_redirects);
 
     Ok(())
   }
 
   fn add_to_indexes(&mut self, item: &Item) -> InfuResult<()> {
     self.owner_id_by_item_id.insert(item.id.clone(), item.owner_id.clone());
+    if let Some(label) = &item.label {
+      let item_id_by_label = self.item_id_by_label_by_user_id.entry(item.owner_id.clone()).or_default();
+      if let Some(existing_item_id) = item_id_by_label.get(label)
+        && *existing_item_id != item.id
+      {
+        return Err(
+          format!("Label '{}' of item '{}' is already used by item '{}'.", label, item.id, existing_item_id).into(),
+        );
+      }
+      item_id_by_label.insert(label.clone(), item.id.clone());
+    }
     match &item.parent_id {
       Some(parent_id) => match item.relationship_to_parent {
         RelationshipToParent::Child => match self.children_of.get_mut(parent_id) {
@@ -539,6 +607,11 @@ impl ItemDb {
       .owner_id_by_item_id
       .remove(&item.id)
       .ok_or(format!("Item '{}' is missing in the owner_id_by_item_id map.", item.id))?;
+    if let Some(label) = &item.label
+      && let Some(item_id_by_label) = self.item_id_by_label_by_user_id.get_mut(&item.owner_id)
+    {
+      item_id_by_label.remove(label);
+    }
 
     match &item.parent_id {
       Some(parent_id) => match item.relationship_to_parent {
@@ -667,6 +740,7 @@ impl ItemDb {
   }
 
   pub async fn add(&mut self, item: Item) -> InfuResult<()> {
+    self.check_label_available(&item)?;
     self.dirty_user_ids.insert(item.owner_id.clone());
     self
       .store_by_user_id
@@ -715,6 +789,9 @@ impl ItemDb {
     let item = store.remove(id).await?;
 
     self.remove_from_indexes(&item)?;
+    if let Some(label_redirects) = self.label_redirects_by_user_id.get_mut(&item.owner_id) {
+      label_redirects.retain(|_, item_id| *item_id != item.id);
+    }
     self.record_loaded_container_versions_for_item_delete(&item)?;
     Ok(item)
   }
@@ -792,6 +869,10 @@ impl ItemDb {
       }
     }
 
+    if update_json_map.contains_key("label") {
+      self.check_label_available(item)?;
+    }
+
     self.remove_from_indexes(&old_item)?;
     self
       .store_by_user_id
@@ -801,9 +882,43 @@ impl ItemDb {
       .await?;
     self.dirty_user_ids.insert(item.owner_id.clone());
     self.add_to_indexes(item)?;
+    if let Some(old_label) = &old_item.label
+      && item.label.as_ref() != Some(old_label)
+    {
+      self
+        .label_redirects_by_user_id
+        .entry(item.owner_id.clone())
+        .or_default()
+        .insert(old_label.clone(), item.id.clone());
+    }
     self.record_loaded_container_versions_for_item_update(&old_item, item)
   }
 
+  fn check_label_available(&self, item: &Item) -> InfuResult<()> {
+    let Some(label) = &item.label else {
+      return Ok(());
+    };
+    if let Some(existing_item_id) = self.item_id_for_label(&item.owner_id, label)
+      && *existing_item_id != item.id
+    {
+      return Err(format!("Label '{}' is already used by item '{}'.", label, existing_item_id).into());
+    }
+    Ok(())
+  }
+
+  /// The item currently labelled `label` by the given user, if any.
+  pub fn item_id_for_label(&self, user_id: &Uid, label: &str) -> Option<&Uid> {
+    self.item_id_by_label_by_user_id.get(user_id)?.get(label)
+  }
+
+  /// The item that was previously labelled `label` by the given user, if the label has not since been reused.
+  pub fn item_id_for_previous_label(&self, user_id: &Uid, label: &str) -> Option<&Uid> {
+    if self.item_id_for_label(user_id, label).is_some() {
+      return None;
+    }
+    self.label_redirects_by_user_id.get(user_id)?.get(label)
+  }
+
   pub fn get(&self, id: &Uid) -> InfuResult<&Item> {
     let owner_id = self
       .owner_id_by_item_id
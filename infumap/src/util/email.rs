@@ -0,0 +1,599 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Minimal RFC 5322 / MIME parsing for importing `.eml` files and `.mbox` archives.
+
+use std::collections::HashMap;
+
+use base64::{Engine as _, engine::general_purpose};
+use infusdk::util::infu::InfuResult;
+use time::{Date, Month, PrimitiveDateTime, Time};
+
+use crate::util::mime::{mime_type_from_title_extension, normalized_mime_type};
+
+/// Deeper MIME nesting than this is ignored, which keeps hostile messages from exhausting the stack.
+const MAX_MULTIPART_DEPTH: usize = 32;
+const MBOX_FROM_LINE_PREFIX: &[u8] = b"From ";
+
+pub struct EmailMessage {
+  pub message_id: Option<String>,
+  pub in_reply_to: Option<String>,
+  pub references: Vec<String>,
+  pub subject: Option<String>,
+  pub from: Option<String>,
+  pub to: Option<String>,
+  pub cc: Option<String>,
+  /// Unix seconds, from the Date header.
+  pub date: Option<i64>,
+  pub text_body: Option<String>,
+  /// Only set when the message has no text/plain body.
+  pub html_body: Option<String>,
+  pub attachments: Vec<EmailAttachment>,
+}
+
+pub struct EmailAttachment {
+  pub filename: String,
+  pub mime_type: String,
+  pub data: Vec<u8>,
+}
+
+/// True if the bytes look like an mbox archive (one or more messages each introduced by a
+/// "From " separator line) rather than a single RFC 5322 message.
+pub fn is_mbox(bytes: &[u8]) -> bool {
+  bytes.starts_with(MBOX_FROM_LINE_PREFIX)
+}
+
+/// Split an mbox archive into raw messages, undoing ">From " quoting (mboxrd and mboxo).
+pub fn split_mbox(bytes: &[u8]) -> Vec<Vec<u8>> {
+  let mut messages = vec![];
+  let mut current: Option<Vec<u8>> = None;
+  let mut previous_line_blank = true;
+  for line in bytes.split_inclusive(|b| *b == b'\n') {
+    if previous_line_blank && line.starts_with(MBOX_FROM_LINE_PREFIX) {
+      if let Some(message) = current.take() {
+        messages.push(message);
+      }
+      current = Some(vec![]);
+      previous_line_blank = false;
+      continue;
+    }
+    previous_line_blank = line.iter().all(|b| matches!(b, b'\r' | b'\n'));
+    let Some(message) = current.as_mut() else {
+      continue;
+    };
+    let unquoted =
+      line.iter().position(|b| *b != b'>').filter(|i| *i > 0 && line[*i..].starts_with(MBOX_FROM_LINE_PREFIX));
+    match unquoted {
+      Some(_) => message.extend_from_slice(&line[1..]),
+      None => message.extend_from_slice(line),
+    }
+  }
+  if let Some(message) = current {
+    messages.push(message);
+  }
+  messages.retain(|message| !message.iter().all(u8::is_ascii_whitespace));
+  messages
+}
+
+pub fn parse_message(bytes: &[u8]) -> InfuResult<EmailMessage> {
+  let (headers, body) = split_headers(bytes);
+  if headers.is_empty() {
+    return Err("Email message has no headers.".into());
+  }
+
+  let mut message = EmailMessage {
+    message_id: header(&headers, "message-id").and_then(|value| message_ids(value).into_iter().next()),
+    in_reply_to: header(&headers, "in-reply-to").and_then(|value| message_ids(value).into_iter().next()),
+    references: header(&headers, "references").map(message_ids).unwrap_or_default(),
+    subject: header(&headers, "subject").map(decode_header_value).filter(|value| !value.is_empty()),
+    from: header(&headers, "from").map(decode_header_value).filter(|value| !value.is_empty()),
+    to: header(&headers, "to").map(decode_header_value).filter(|value| !value.is_empty()),
+    cc: header(&headers, "cc").map(decode_header_value).filter(|value| !value.is_empty()),
+    date: header(&headers, "date").and_then(parse_date),
+    text_body: None,
+    html_body: None,
+    attachments: vec![],
+  };
+
+  let mut html_bodies = vec![];
+  let mut text_bodies = vec![];
+  collect_parts(&headers, body, 0, &mut text_bodies, &mut html_bodies, &mut message.attachments);
+  if !text_bodies.is_empty() {
+    message.text_body = Some(text_bodies.join("\n\n"));
+  } else if !html_bodies.is_empty() {
+    message.html_body = Some(html_bodies.join("\n"));
+  }
+  Ok(message)
+}
+
+/// Group messages into conversations using Message-ID / In-Reply-To / References, falling back to
+/// the subject with reply and forward prefixes removed. Returns message indices per thread, with
+/// threads and their messages in date order.
+pub fn group_threads(messages: &[EmailMessage]) -> Vec<Vec<usize>> {
+  let mut order = (0..messages.len()).collect::<Vec<_>>();
+  order.sort_by_key(|i| (messages[*i].date.unwrap_or(i64::MAX), *i));
+
+  let mut threads: Vec<Vec<usize>> = vec![];
+  let mut thread_by_message_id = HashMap::<String, usize>::new();
+  let mut thread_by_subject = HashMap::<String, usize>::new();
+  for index in order {
+    let message = &messages[index];
+    let referenced_ids = message.in_reply_to.iter().chain(message.references.iter().rev());
+    let subject_key = message.subject.as_deref().map(thread_subject_key).filter(|key| !key.is_empty());
+    let thread = referenced_ids
+      .filter_map(|id| thread_by_message_id.get(id).copied())
+      .next()
+      .or_else(|| subject_key.as_ref().and_then(|key| thread_by_subject.get(key).copied()));
+    let thread = match thread {
+      Some(thread) => thread,
+      None => {
+        threads.push(vec![]);
+        threads.len() - 1
+      }
+    };
+    threads[thread].push(index);
+    for id in message.message_id.iter().chain(message.in_reply_to.iter()).chain(message.references.iter()) {
+      thread_by_message_id.entry(id.clone()).or_insert(thread);
+    }
+    if let Some(key) = subject_key {
+      thread_by_subject.entry(key).or_insert(thread);
+    }
+  }
+  threads
+}
+
+/// Subject with leading "Re:", "Fwd:" etc. removed, for display as a thread title.
+pub fn thread_subject(subject: &str) -> &str {
+  let mut subject = subject.trim();
+  loop {
+    let Some((prefix, rest)) = subject.split_once(':') else {
+      return subject;
+    };
+    let prefix = prefix.trim().to_ascii_lowercase();
+    let prefix = prefix.split('[').next().unwrap_or_default();
+    if !matches!(prefix, "re" | "fw" | "fwd" | "aw" | "sv" | "wg") {
+      return subject;
+    }
+    subject = rest.trim_start();
+  }
+}
+
+fn thread_subject_key(subject: &str) -> String {
+  thread_subject(subject).split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+type Headers = Vec<(String, String)>;
+
+/// Split raw message bytes into unfolded headers (lowercased names) and the body.
+fn split_headers(bytes: &[u8]) -> (Headers, &[u8]) {
+  let mut headers: Headers = vec![];
+  let mut offset = 0;
+  for line in bytes.split_inclusive(|b| *b == b'\n') {
+    offset += line.len();
+    let line = latin1_or_utf8(line.strip_suffix(b"\n").unwrap_or(line));
+    let line = line.strip_suffix('\r').unwrap_or(&line);
+    if line.is_empty() {
+      return (headers, &bytes[offset..]);
+    }
+    if line.starts_with([' ', '\t']) {
+      if let Some((_, value)) = headers.last_mut() {
+        value.push(' ');
+        value.push_str(line.trim());
+      }
+      continue;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+    }
+  }
+  (headers, &[])
+}
+
+fn header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+  headers.iter().find(|(header_name, _)| header_name == name).map(|(_, value)| value.as_str())
+}
+
+fn message_ids(value: &str) -> Vec<String> {
+  value
+    .split('<')
+    .skip(1)
+    .filter_map(|part| part.split_once('>').map(|(id, _)| id.trim().to_owned()))
+    .filter(|id| !id.is_empty())
+    .collect()
+}
+
+struct ContentType {
+  mime_type: String,
+  params: HashMap<String, String>,
+}
+
+fn content_type(headers: &Headers) -> ContentType {
+  let (mime_type, params) = match header(headers, "content-type") {
+    Some(value) => split_params(value),
+    None => ("text/plain".to_owned(), HashMap::new()),
+  };
+  ContentType { mime_type: mime_type.to_ascii_lowercase(), params }
+}
+
+/// Split a structured header value ("value; key=val; key*=charset''val") into its leading value
+/// and parameters, joining RFC 2231 continuations.
+fn split_params(value: &str) -> (String, HashMap<String, String>) {
+  let mut parts = vec![];
+  let mut current = String::new();
+  let mut in_quotes = false;
+  let mut escaped = false;
+  for c in value.chars() {
+    match c {
+      _ if escaped => {
+        current.push(c);
+        escaped = false;
+      }
+      '\\' if in_quotes => escaped = true,
+      '"' => in_quotes = !in_quotes,
+      ';' if !in_quotes => parts.push(std::mem::take(&mut current)),
+      _ => current.push(c),
+    }
+  }
+  parts.push(current);
+
+  let leading = parts.remove(0).trim().to_owned();
+  let mut continued = HashMap::<String, Vec<(usize, String)>>::new();
+  let mut params = HashMap::new();
+  for part in parts {
+    let Some((name, value)) = part.split_once('=') else {
+      continue;
+    };
+    let name = name.trim().to_ascii_lowercase();
+    let value = value.trim().to_owned();
+    let (name, is_extended) = match name.strip_suffix('*') {
+      Some(name) => (name.to_owned(), true),
+      None => (name, false),
+    };
+    let value = if is_extended { decode_extended_param(&value) } else { value };
+    match name.rsplit_once('*').and_then(|(base, index)| Some((base, index.parse::<usize>().ok()?))) {
+      Some((base, index)) => continued.entry(base.to_owned()).or_default().push((index, value)),
+      None => {
+        params.insert(name, value);
+      }
+    }
+  }
+  for (name, mut pieces) in continued {
+    pieces.sort_by_key(|(index, _)| *index);
+    params.entry(name).or_insert_with(|| pieces.into_iter().map(|(_, value)| value).collect());
+  }
+  (leading, params)
+}
+
+/// RFC 2231 extended parameter value: charset'language'percent-encoded-text. Continuation pieces
+/// after the first have no charset prefix.
+fn decode_extended_param(value: &str) -> String {
+  let (charset, encoded) = match value.splitn(3, '\'').collect::<Vec<_>>().as_slice() {
+    [charset, _, encoded] => (*charset, *encoded),
+    _ => ("utf-8", value),
+  };
+  decode_charset(&percent_decode(encoded), charset)
+}
+
+fn collect_parts(
+  headers: &Headers,
+  body: &[u8],
+  depth: usize,
+  text_bodies: &mut Vec<String>,
+  html_bodies: &mut Vec<String>,
+  attachments: &mut Vec<EmailAttachment>,
+) {
+  if depth > MAX_MULTIPART_DEPTH {
+    return;
+  }
+  let content_type = content_type(headers);
+  let disposition = header(headers, "content-disposition").map(split_params);
+  let filename = disposition
+    .as_ref()
+    .and_then(|(_, params)| params.get("filename"))
+    .or_else(|| content_type.params.get("name"))
+    .map(|name| decode_header_value(name))
+    .filter(|name| !name.trim().is_empty());
+  let is_attachment = disposition.as_ref().is_some_and(|(value, _)| value.eq_ignore_ascii_case("attachment"));
+
+  if content_type.mime_type.starts_with("multipart/")
+    && let Some(boundary) = content_type.params.get("boundary")
+  {
+    let parts = multipart_parts(body, boundary);
+    if content_type.mime_type == "multipart/alternative" {
+      // Prefer the plain text alternative; the HTML alternative is only used for HTML-only mail.
+      let mut alternative_html = vec![];
+      let text_count = text_bodies.len();
+      for part in &parts {
+        let (part_headers, part_body) = split_headers(part);
+        collect_parts(&part_headers, part_body, depth + 1, text_bodies, &mut alternative_html, attachments);
+      }
+      if text_bodies.len() == text_count {
+        html_bodies.extend(alternative_html);
+      }
+    } else {
+      for part in &parts {
+        let (part_headers, part_body) = split_headers(part);
+        collect_parts(&part_headers, part_body, depth + 1, text_bodies, html_bodies, attachments);
+      }
+    }
+    return;
+  }
+
+  let data = decode_transfer_encoding(header(headers, "content-transfer-encoding"), body);
+  let is_inline_text = !is_attachment && filename.is_none();
+  match content_type.mime_type.as_str() {
+    "text/plain" if is_inline_text => {
+      let charset = content_type.params.get("charset").map(String::as_str).unwrap_or("us-ascii");
+      let text = decode_charset(&data, charset);
+      if !text.trim().is_empty() {
+        text_bodies.push(text.trim_end().to_owned());
+      }
+    }
+    "text/html" if is_inline_text => {
+      let charset = content_type.params.get("charset").map(String::as_str).unwrap_or("us-ascii");
+      html_bodies.push(decode_charset(&data, charset));
+    }
+    mime_type => {
+      if data.is_empty() {
+        return;
+      }
+      let filename = filename.unwrap_or_else(|| {
+        let extension = if mime_type == "message/rfc822" { "eml" } else { "bin" };
+        format!("attachment-{}.{}", attachments.len() + 1, extension)
+      });
+      let mime_type = match mime_type {
+        "application/octet-stream" => mime_type_from_title_extension(&filename).unwrap_or(mime_type.to_owned()),
+        mime_type => normalized_mime_type(mime_type),
+      };
+      attachments.push(EmailAttachment { filename, mime_type, data });
+    }
+  }
+}
+
+fn multipart_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+  let delimiter = format!("--{}", boundary);
+  let mut parts = vec![];
+  let mut part_start = None;
+  let mut offset = 0;
+  for line in body.split_inclusive(|b| *b == b'\n') {
+    let line_start = offset;
+    offset += line.len();
+    let trimmed = line.trim_ascii_end();
+    let Some(rest) = trimmed.strip_prefix(delimiter.as_bytes()) else {
+      continue;
+    };
+    if !rest.is_empty() && rest != b"--" {
+      continue;
+    }
+    if let Some(start) = part_start {
+      // The line break before a delimiter belongs to the delimiter.
+      let end = if body[..line_start].ends_with(b"\r\n") {
+        line_start - 2
+      } else if body[..line_start].ends_with(b"\n") {
+        line_start - 1
+      } else {
+        line_start
+      };
+      parts.push(&body[start..end.max(start)]);
+    }
+    if rest == b"--" {
+      return parts;
+    }
+    part_start = Some(offset);
+  }
+  if let Some(start) = part_start {
+    parts.push(&body[start..]);
+  }
+  parts
+}
+
+fn decode_transfer_encoding(encoding: Option<&str>, body: &[u8]) -> Vec<u8> {
+  match encoding.map(|encoding| encoding.trim().to_ascii_lowercase()).as_deref() {
+    Some("base64") => decode_base64_lenient(body),
+    Some("quoted-printable") => decode_quoted_printable(body, false),
+    _ => body.to_vec(),
+  }
+}
+
+fn decode_base64_lenient(data: &[u8]) -> Vec<u8> {
+  let mut cleaned =
+    data.iter().copied().filter(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/')).collect::<Vec<_>>();
+  // A single dangling character cannot encode a byte.
+  if cleaned.len() % 4 == 1 {
+    cleaned.pop();
+  }
+  general_purpose::STANDARD_NO_PAD.decode(&cleaned).unwrap_or_default()
+}
+
+/// Quoted-printable body decoding, or the "Q" encoded-word variant (where '_' is a space).
+fn decode_quoted_printable(data: &[u8], is_encoded_word: bool) -> Vec<u8> {
+  let mut out = Vec::with_capacity(data.len());
+  let mut i = 0;
+  while i < data.len() {
+    match data[i] {
+      b'=' if data[i + 1..].starts_with(b"\r\n") => i += 3,
+      b'=' if data[i + 1..].starts_with(b"\n") => i += 2,
+      b'='
+        if i + 2 < data.len()
+          && let (Some(high), Some(low)) = ((data[i + 1] as char).to_digit(16), (data[i + 2] as char).to_digit(16)) =>
+      {
+        out.push((high * 16 + low) as u8);
+        i += 3;
+      }
+      b'_' if is_encoded_word => {
+        out.push(b' ');
+        i += 1;
+      }
+      b => {
+        out.push(b);
+        i += 1;
+      }
+    }
+  }
+  out
+}
+
+fn percent_decode(value: &str) -> Vec<u8> {
+  let bytes = value.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%'
+      && i + 2 < bytes.len()
+      && let (Some(high), Some(low)) = ((bytes[i + 1] as char).to_digit(16), (bytes[i + 2] as char).to_digit(16))
+    {
+      out.push((high * 16 + low) as u8);
+      i += 3;
+      continue;
+    }
+    out.push(bytes[i]);
+    i += 1;
+  }
+  out
+}
+
+/// Decode RFC 2047 encoded words ("=?utf-8?Q?...?="). Whitespace between adjacent encoded words
+/// is dropped, as the RFC requires.
+pub fn decode_header_value(value: &str) -> String {
+  let mut out = String::new();
+  let mut rest = value;
+  let mut previous_was_encoded_word = false;
+  while let Some(start) = rest.find("=?") {
+    let (before, candidate) = rest.split_at(start);
+    match decode_encoded_word(candidate) {
+      Some((decoded, consumed)) => {
+        if !(previous_was_encoded_word && before.trim().is_empty()) {
+          out.push_str(before);
+        }
+        out.push_str(&decoded);
+        rest = &candidate[consumed..];
+        previous_was_encoded_word = true;
+      }
+      None => {
+        out.push_str(before);
+        out.push_str("=?");
+        rest = &candidate[2..];
+        previous_was_encoded_word = false;
+      }
+    }
+  }
+  out.push_str(rest);
+  out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode one encoded word at the start of `value`, returning the text and the bytes consumed.
+fn decode_encoded_word(value: &str) -> Option<(String, usize)> {
+  let inner = value.strip_prefix("=?")?;
+  let (charset, inner) = inner.split_once('?')?;
+  let (encoding, inner) = inner.split_once('?')?;
+  let end = inner.find("?=")?;
+  let encoded = &inner[..end];
+  if charset.contains(char::is_whitespace) || encoded.contains(char::is_whitespace) {
+    return None;
+  }
+  let bytes = match encoding.to_ascii_lowercase().as_str() {
+    "b" => decode_base64_lenient(encoded.as_bytes()),
+    "q" => decode_quoted_printable(encoded.as_bytes(), true),
+    _ => return None,
+  };
+  let consumed = "=?".len() + charset.len() + 1 + encoding.len() + 1 + end + "?=".len();
+  // RFC 2231 allows a language suffix on the charset ("utf-8*en").
+  let charset = charset.split('*').next().unwrap_or(charset);
+  Some((decode_charset(&bytes, charset), consumed))
+}
+
+/// Charset aware decoding for the charsets mail clients commonly emit. Unknown charsets are
+/// treated as UTF-8 with replacement characters.
+fn decode_charset(bytes: &[u8], charset: &str) -> String {
+  match charset.trim().to_ascii_lowercase().as_str() {
+    "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "windows-1252" | "cp1252" => {
+      bytes.iter().map(|b| *b as char).collect()
+    }
+    "us-ascii" | "ascii" => latin1_or_utf8(bytes),
+    _ => String::from_utf8_lossy(bytes).into_owned(),
+  }
+}
+
+/// Raw (unencoded) header bytes are supposed to be ASCII, but UTF-8 and Latin-1 are common.
+fn latin1_or_utf8(bytes: &[u8]) -> String {
+  match std::str::from_utf8(bytes) {
+    Ok(text) => text.to_owned(),
+    Err(_) => bytes.iter().map(|b| *b as char).collect(),
+  }
+}
+
+/// Parse an RFC 5322 date ("Tue, 1 Jul 2003 10:52:37 +0200"), tolerating a missing weekday,
+/// missing seconds, two digit years, named zones and trailing comments.
+fn parse_date(value: &str) -> Option<i64> {
+  let value = value.split('(').next().unwrap_or(value);
+  let value = value.split_once(',').map(|(_, rest)| rest).unwrap_or(value);
+  let mut tokens = value.split_whitespace();
+  let day = tokens.next()?.parse::<u8>().ok()?;
+  let month = match tokens.next()?.get(..3)?.to_ascii_lowercase().as_str() {
+    "jan" => Month::January,
+    "feb" => Month::February,
+    "mar" => Month::March,
+    "apr" => Month::April,
+    "may" => Month::May,
+    "jun" => Month::June,
+    "jul" => Month::July,
+    "aug" => Month::August,
+    "sep" => Month::September,
+    "oct" => Month::October,
+    "nov" => Month::November,
+    "dec" => Month::December,
+    _ => return None,
+  };
+  let year = tokens.next()?.parse::<i32>().ok()?;
+  let year = match year {
+    0..=49 => year + 2000,
+    50..=999 => year + 1900,
+    _ => year,
+  };
+  let mut time_parts = tokens.next()?.split(':').map(|part| part.parse::<u8>().ok());
+  let hour = time_parts.next()??;
+  let minute = time_parts.next()??;
+  let second = time_parts.next().flatten().unwrap_or(0);
+  let offset_secs = match tokens.next() {
+    Some(zone) => zone_offset_secs(zone)?,
+    None => 0,
+  };
+  let date = Date::from_calendar_date(year, month, day).ok()?;
+  let time = Time::from_hms(hour, minute, second.min(59)).ok()?;
+  Some(PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp() - offset_secs)
+}
+
+fn zone_offset_secs(zone: &str) -> Option<i64> {
+  if let Some(digits) = zone.strip_prefix(['+', '-']) {
+    if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+      return None;
+    }
+    let hours = digits[..2].parse::<i64>().ok()?;
+    let minutes = digits[2..].parse::<i64>().ok()?;
+    let offset = hours * 3600 + minutes * 60;
+    return Some(if zone.starts_with('-') { -offset } else { offset });
+  }
+  let hours = match zone.to_ascii_uppercase().as_str() {
+    "EDT" => -4,
+    "EST" | "CDT" => -5,
+    "CST" | "MDT" => -6,
+    "MST" | "PDT" => -7,
+    "PST" => -8,
+    // UT, GMT, Z and the (unreliable) military zones are all treated as UTC.
+    _ => 0,
+  };
+  Some(hours * 3600)
+}
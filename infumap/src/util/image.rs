@@ -18,8 +18,10 @@ use std::io::Cursor;
 
 use exif::{Exif, In, Tag, Value};
 use image::DynamicImage;
+use image::imageops::FilterType;
 use log::debug;
 use serde::{Deserialize, Serialize};
+use time::{Date, Month, PrimitiveDateTime, Time};
 
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct ImageMetadata {
@@ -219,6 +221,23 @@ fn normalize_exif_datetime(value: &str) -> String {
   format!("{}-{}-{}T{}", date_parts[0], date_parts[1], date_parts[2], time_part.trim())
 }
 
+/// The capture time of an image, as recorded by `extract_image_metadata`, in seconds since the epoch
+/// as if the camera's wall clock time were UTC. Any time zone offset is ignored.
+pub fn capture_datetime_wall_clock_secs(captured_at: &str) -> Option<i64> {
+  let (date, time) = captured_at.get(..19)?.split_once('T')?;
+  let mut date_parts = date.split('-').map(|part| part.parse::<i32>().ok());
+  let year = date_parts.next()??;
+  let month = Month::try_from(u8::try_from(date_parts.next()??).ok()?).ok()?;
+  let day = u8::try_from(date_parts.next()??).ok()?;
+  let mut time_parts = time.split(':').map(|part| part.parse::<u8>().ok());
+  let hour = time_parts.next()??;
+  let minute = time_parts.next()??;
+  let second = time_parts.next()??;
+  let date = Date::from_calendar_date(year, month, day).ok()?;
+  let time = Time::from_hms(hour, minute, second.min(59)).ok()?;
+  Some(PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp())
+}
+
 pub fn adjust_image_for_exif_orientation(
   img: DynamicImage,
   exif_orientation: u16,
@@ -267,3 +286,21 @@ pub fn adjust_image_for_exif_orientation(
   }
   img
 }
+
+/// A 64 bit difference hash of an image: each bit records whether a pixel of a 9x8 grayscale
+/// thumbnail is brighter than its right hand neighbour. Re-encoded, resized and lightly edited copies
+/// of an image have hashes a small Hamming distance apart. The image should be upright, since the
+/// hash is not invariant under rotation.
+pub fn perceptual_hash(img: &DynamicImage) -> u64 {
+  let thumbnail = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+  let mut hash = 0u64;
+  for y in 0..8 {
+    for x in 0..8 {
+      hash <<= 1;
+      if thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0] {
+        hash |= 1;
+      }
+    }
+  }
+  hash
+}
@@ -0,0 +1,282 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Web friendly renditions of image items in formats browsers and the image crate can't read (HEIF,
+//! AVIF and camera RAW). The original is kept in the object store, and remains what is downloaded.
+//! The rendition is an upright JPEG carrying the original's EXIF metadata (other than orientation),
+//! kept encrypted under the data directory, and is what is used for display, thumbnails and tagging.
+
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use exif::experimental::Writer;
+use exif::{Context, Field, In, Tag};
+use image::ImageReader;
+use image::codecs::jpeg::JpegEncoder;
+use infusdk::util::infu::InfuResult;
+use tokio::fs;
+
+use crate::storage::object::{self as storage_object, ObjectStore};
+use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
+use crate::util::fs::{ensure_256_subdirs, expand_tilde, path_exists};
+use crate::util::image::{adjust_image_for_exif_orientation, get_exif_orientation};
+use crate::util::media::ffmpeg_frame_jpeg;
+
+pub const IMAGE_RENDITION_MIME_TYPE: &str = "image/jpeg";
+
+const RENDITION_JPEG_QUALITY: u8 = 90;
+const RENDITION_FILENAME_SUFFIX: &str = "_rendition";
+
+const RAW_IMAGE_MIME_TYPES: [&str; 8] = [
+  "image/x-adobe-dng",
+  "image/x-canon-cr2",
+  "image/x-canon-cr3",
+  "image/x-fuji-raf",
+  "image/x-nikon-nef",
+  "image/x-olympus-orf",
+  "image/x-panasonic-rw2",
+  "image/x-sony-arw",
+];
+
+/// Only these TIFF (top level) tags are carried over. The others describe the layout of the original
+/// image data, which the rendition doesn't share.
+const RENDITION_TIFF_TAGS: [Tag; 7] =
+  [Tag::Make, Tag::Model, Tag::Software, Tag::DateTime, Tag::Artist, Tag::Copyright, Tag::ImageDescription];
+
+pub fn is_raw_image_mime_type(mime_type: &str) -> bool {
+  RAW_IMAGE_MIME_TYPES.contains(&mime_type)
+}
+
+pub fn needs_image_rendition(mime_type: &str) -> bool {
+  matches!(mime_type, "image/heif" | "image/avif") || is_raw_image_mime_type(mime_type)
+}
+
+/// Create the rendition of an image. Camera RAW files use the full size JPEG preview that cameras
+/// embed, so no RAW decoding is required. HEIF and AVIF images are converted with ffmpeg.
+pub async fn create_image_rendition(mime_type: &str, data: &[u8], image_identifier: &str) -> InfuResult<Vec<u8>> {
+  let (img, exif_source) = if is_raw_image_mime_type(mime_type) {
+    let preview = largest_embedded_jpeg(data)
+      .ok_or(format!("RAW image '{}' does not contain an embedded JPEG preview.", image_identifier))?;
+    // Not all RAW formats are TIFF based. For those that aren't, the preview's own EXIF is used.
+    let exif_source = if has_exif(data) { data } else { preview };
+    let img = decode_image(preview, image_identifier)?;
+    // The preview is stored in sensor orientation, and shares the orientation of the RAW image.
+    let exif_orientation = get_exif_orientation(exif_source.to_vec(), image_identifier);
+    (adjust_image_for_exif_orientation(img, exif_orientation, image_identifier), exif_source)
+  } else {
+    // ffmpeg applies the rotation recorded in the container.
+    let frame = ffmpeg_frame_jpeg(data, 0.0).await?;
+    (decode_image(&frame, image_identifier)?, data)
+  };
+
+  let img = img.to_rgb8();
+  let mut cursor = Cursor::new(Vec::new());
+  img
+    .write_with_encoder(JpegEncoder::new_with_quality(&mut cursor, RENDITION_JPEG_QUALITY))
+    .map_err(|e| format!("Could not encode rendition of image '{}': {}", image_identifier, e))?;
+  let jpeg = cursor.into_inner();
+  Ok(match rendition_exif(exif_source) {
+    Some(exif) => jpeg_with_exif(&jpeg, &exif),
+    None => jpeg,
+  })
+}
+
+/// The bytes to use when displaying or analysing an image item: the rendition if the format requires
+/// one, otherwise the original object.
+pub async fn get_image_item_bytes(
+  data_dir: &str,
+  object_store: Arc<ObjectStore>,
+  user_id: &str,
+  item_id: &str,
+  mime_type: &str,
+  object_encryption_key: &str,
+) -> InfuResult<Vec<u8>> {
+  if needs_image_rendition(mime_type) {
+    read_image_rendition(data_dir, user_id, item_id, object_encryption_key).await
+  } else {
+    storage_object::get(object_store, user_id.to_owned(), item_id.to_owned(), object_encryption_key).await
+  }
+}
+
+pub async fn write_image_rendition(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+  jpeg: &[u8],
+  object_encryption_key: &str,
+) -> InfuResult<()> {
+  let renditions_dir = user_renditions_dir(data_dir, user_id)?;
+  if !path_exists(&renditions_dir).await {
+    fs::create_dir_all(&renditions_dir).await?;
+  }
+  ensure_256_subdirs(&renditions_dir).await?;
+  let encrypted = encrypt_file_data(object_encryption_key, jpeg, &rendition_filename(item_id))?;
+  fs::write(item_rendition_path(data_dir, user_id, item_id)?, encrypted).await?;
+  Ok(())
+}
+
+pub async fn read_image_rendition(
+  data_dir: &str,
+  user_id: &str,
+  item_id: &str,
+  object_encryption_key: &str,
+) -> InfuResult<Vec<u8>> {
+  let path = item_rendition_path(data_dir, user_id, item_id)?;
+  let encrypted =
+    fs::read(&path).await.map_err(|e| format!("Could not read image rendition '{}': {}", path.display(), e))?;
+  decrypt_file_data(object_encryption_key, &encrypted, &rendition_filename(item_id))
+}
+
+pub async fn delete_image_rendition(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<()> {
+  let path = item_rendition_path(data_dir, user_id, item_id)?;
+  if path_exists(&path).await {
+    fs::remove_file(&path).await?;
+  }
+  Ok(())
+}
+
+fn user_renditions_dir(data_dir: &str, user_id: &str) -> InfuResult<PathBuf> {
+  let mut path = expand_tilde(data_dir).ok_or("Could not interpret path.")?;
+  path.push(format!("user_{}", user_id));
+  path.push("renditions");
+  Ok(path)
+}
+
+fn item_rendition_path(data_dir: &str, user_id: &str, item_id: &str) -> InfuResult<PathBuf> {
+  if item_id.len() < 2 {
+    return Err(format!("Item id '{}' is too short.", item_id).into());
+  }
+  let mut path = user_renditions_dir(data_dir, user_id)?;
+  path.push(&item_id[..2]);
+  path.push(rendition_filename(item_id));
+  Ok(path)
+}
+
+fn rendition_filename(item_id: &str) -> String {
+  format!("{}{}", item_id, RENDITION_FILENAME_SUFFIX)
+}
+
+fn decode_image(data: &[u8], image_identifier: &str) -> InfuResult<image::DynamicImage> {
+  ImageReader::new(Cursor::new(data))
+    .with_guessed_format()?
+    .decode()
+    .map_err(|e| format!("Could not decode image data for '{}': {}", image_identifier, e).into())
+}
+
+fn has_exif(data: &[u8]) -> bool {
+  exif::Reader::new().read_from_container(&mut Cursor::new(data)).is_ok()
+}
+
+/// The EXIF metadata of the original, re-encoded to suit the rendition. None if there is none, or it
+/// would not fit in a JPEG APP1 segment.
+fn rendition_exif(original: &[u8]) -> Option<Vec<u8>> {
+  let exif = exif::Reader::new().read_from_container(&mut Cursor::new(original)).ok()?;
+  let fields: Vec<&Field> = exif
+    .fields()
+    .filter(|field| field.ifd_num == In::PRIMARY)
+    .filter(|field| match field.tag.context() {
+      Context::Tiff => RENDITION_TIFF_TAGS.contains(&field.tag),
+      Context::Exif => field.tag != Tag::MakerNote,
+      Context::Gps => true,
+      _ => false,
+    })
+    .collect();
+  if fields.is_empty() {
+    return None;
+  }
+  let mut writer = Writer::new();
+  for field in fields {
+    writer.push_field(field);
+  }
+  let mut cursor = Cursor::new(Vec::new());
+  writer.write(&mut cursor, exif.little_endian()).ok()?;
+  let tiff = cursor.into_inner();
+  if tiff.len() + 8 > u16::MAX as usize { None } else { Some(tiff) }
+}
+
+/// Insert an EXIF APP1 segment immediately after the start of image marker.
+fn jpeg_with_exif(jpeg: &[u8], tiff: &[u8]) -> Vec<u8> {
+  let segment_len = (2 + 6 + tiff.len()) as u16;
+  let mut result = Vec::with_capacity(jpeg.len() + tiff.len() + 10);
+  result.extend_from_slice(&jpeg[..2]);
+  result.extend_from_slice(&[0xFF, 0xE1]);
+  result.extend_from_slice(&segment_len.to_be_bytes());
+  result.extend_from_slice(b"Exif\0\0");
+  result.extend_from_slice(tiff);
+  result.extend_from_slice(&jpeg[2..]);
+  result
+}
+
+/// The largest baseline or progressive JPEG stream embedded in `data`. RAW formats differ in where
+/// (and whether) they record the location of their previews, but the previews themselves are always
+/// ordinary JPEG streams. Lossless JPEG streams, which some formats use for the sensor data itself,
+/// are skipped.
+fn largest_embedded_jpeg(data: &[u8]) -> Option<&[u8]> {
+  let mut best: Option<&[u8]> = None;
+  let mut offset = 0;
+  while offset + 3 <= data.len() {
+    if data[offset] != 0xFF || data[offset + 1] != 0xD8 || data[offset + 2] != 0xFF {
+      offset += 1;
+      continue;
+    }
+    match jpeg_stream_end(data, offset) {
+      Some((end, is_decodable)) => {
+        if is_decodable && best.is_none_or(|best| end - offset > best.len()) {
+          best = Some(&data[offset..end]);
+        }
+        offset = end;
+      }
+      None => offset += 1,
+    }
+  }
+  best
+}
+
+/// The end offset of the JPEG stream starting at `start`, and whether it is baseline or progressive.
+fn jpeg_stream_end(data: &[u8], start: usize) -> Option<(usize, bool)> {
+  let mut is_decodable = false;
+  let mut pos = start + 2;
+  loop {
+    if *data.get(pos)? != 0xFF {
+      return None;
+    }
+    let marker = *data.get(pos + 1)?;
+    match marker {
+      0xFF => pos += 1,
+      0xD9 => return Some((pos + 2, is_decodable)),
+      0x01 | 0xD0..=0xD7 => pos += 2,
+      _ => {
+        let segment_len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        if segment_len < 2 {
+          return None;
+        }
+        if matches!(marker, 0xC0..=0xC2) {
+          is_decodable = true;
+        }
+        pos += 2 + segment_len;
+        if marker == 0xDA {
+          // Entropy coded data runs to the next marker that isn't a stuffed byte or a restart marker.
+          while pos + 1 < data.len()
+            && (data[pos] != 0xFF || data[pos + 1] == 0x00 || (0xD0..=0xD7).contains(&data[pos + 1]))
+          {
+            pos += 1;
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,48 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Async locks created on demand for string keys (such as user or conversation ids), so that work
+/// on one key does not wait on work on another. A lock is dropped once nothing holds or waits on it.
+pub struct KeyedLocks {
+  locks: Mutex<HashMap<String, Weak<AsyncMutex<()>>>>,
+}
+
+impl KeyedLocks {
+  pub fn new() -> KeyedLocks {
+    KeyedLocks { locks: Mutex::new(HashMap::new()) }
+  }
+
+  pub async fn lock(&self, key: &str) -> OwnedMutexGuard<()> {
+    let lock = {
+      let mut locks = self.locks.lock().unwrap();
+      match locks.get(key).and_then(Weak::upgrade) {
+        Some(lock) => lock,
+        None => {
+          locks.retain(|_, lock| lock.strong_count() > 0);
+          let lock = Arc::new(AsyncMutex::new(()));
+          locks.insert(key.to_owned(), Arc::downgrade(&lock));
+          lock
+        }
+      }
+    };
+    lock.lock_owned().await
+  }
+}
@@ -0,0 +1,569 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of markdown text to the note, divider and table items it maps to.
+
+pub const NOTE_FLAG_HEADING3: i64 = 0x001;
+pub const NOTE_FLAG_HEADING1: i64 = 0x004;
+pub const NOTE_FLAG_HEADING2: i64 = 0x008;
+pub const NOTE_FLAG_BULLET1: i64 = 0x010;
+pub const NOTE_FLAG_CODE: i64 = 0x200;
+pub const NOTE_FLAG_HEADING4: i64 = 0x1000;
+pub const NOTE_FLAG_NUMBERED: i64 = 0x8000;
+pub const NOTE_INLINE_MARK_BOLD: i64 = 0x001;
+pub const NOTE_INLINE_MARK_ITALIC: i64 = 0x002;
+
+pub struct MarkdownNote {
+  pub title: String,
+  pub flags: i64,
+  pub inline_marks: Vec<i64>,
+  pub urls: Vec<MarkdownUrl>,
+}
+
+#[derive(Clone)]
+pub struct MarkdownInlineText {
+  pub title: String,
+  pub inline_marks: Vec<i64>,
+  pub urls: Vec<MarkdownUrl>,
+}
+
+#[derive(Clone)]
+pub struct MarkdownUrl {
+  pub start: i64,
+  pub end: i64,
+  pub url: String,
+}
+
+pub struct MarkdownTableRow {
+  pub cells: Vec<MarkdownInlineText>,
+}
+
+pub struct MarkdownTable {
+  pub columns: Vec<String>,
+  pub rows: Vec<MarkdownTableRow>,
+}
+
+pub enum MarkdownItem {
+  Note(MarkdownNote),
+  Divider,
+  Table(MarkdownTable),
+}
+
+pub fn markdown_items_from_text(markdown: &str) -> Vec<MarkdownItem> {
+  let mut items = Vec::new();
+  let mut paragraph_lines: Vec<String> = Vec::new();
+  let lines: Vec<&str> = markdown.lines().collect();
+  let mut line_index = 0;
+
+  while line_index < lines.len() {
+    let line = lines[line_index];
+
+    if line.trim().is_empty() {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      line_index += 1;
+      continue;
+    }
+
+    if let Some(fence_marker) = markdown_code_fence_marker(line) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      line_index += 1;
+      let mut code_lines = Vec::new();
+      while line_index < lines.len() {
+        let code_line = lines[line_index];
+        if code_line.trim_start().starts_with(fence_marker) {
+          break;
+        }
+        code_lines.push(code_line.to_owned());
+        line_index += 1;
+      }
+      if line_index < lines.len() {
+        line_index += 1;
+      }
+      push_markdown_note(&mut items, &code_lines.join("\n"), NOTE_FLAG_CODE);
+      continue;
+    }
+
+    if let Some((table, next_line_index)) = markdown_table_at(&lines, line_index) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      items.push(MarkdownItem::Table(table));
+      line_index = next_line_index;
+      continue;
+    }
+
+    if markdown_divider_line(line) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      items.push(MarkdownItem::Divider);
+      line_index += 1;
+      continue;
+    }
+
+    if let Some((flags, title)) = markdown_heading(line) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      push_markdown_note(&mut items, title, flags);
+      line_index += 1;
+      continue;
+    }
+
+    if let Some((flags, title)) = markdown_list_item(line) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      push_markdown_note(&mut items, title, flags);
+      line_index += 1;
+      continue;
+    }
+
+    if markdown_standalone_inline_heading(line) {
+      flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+      push_markdown_note(&mut items, line, 0);
+      line_index += 1;
+      continue;
+    }
+
+    paragraph_lines.push(line.to_owned());
+    line_index += 1;
+  }
+
+  flush_markdown_paragraph(&mut items, &mut paragraph_lines);
+  items
+}
+
+fn flush_markdown_paragraph(items: &mut Vec<MarkdownItem>, paragraph_lines: &mut Vec<String>) {
+  if paragraph_lines.is_empty() {
+    return;
+  }
+  let paragraph = paragraph_lines.join("\n");
+  paragraph_lines.clear();
+  push_markdown_note(items, &paragraph, 0);
+}
+
+pub fn push_markdown_note(items: &mut Vec<MarkdownItem>, raw_title: &str, flags: i64) {
+  let title =
+    if flags & NOTE_FLAG_CODE != 0 { raw_title.trim_matches('\n').to_owned() } else { raw_title.trim().to_owned() };
+  if title.is_empty() {
+    return;
+  }
+
+  let inline_text = if flags & NOTE_FLAG_CODE != 0 {
+    MarkdownInlineText { title, inline_marks: Vec::new(), urls: Vec::new() }
+  } else {
+    parse_markdown_inline(&title)
+  };
+  if inline_text.title.trim().is_empty() {
+    return;
+  }
+
+  items.push(MarkdownItem::Note(MarkdownNote {
+    title: inline_text.title,
+    flags,
+    inline_marks: inline_text.inline_marks,
+    urls: inline_text.urls,
+  }));
+}
+
+pub fn markdown_code_fence_marker(line: &str) -> Option<&'static str> {
+  let trimmed = line.trim_start();
+  if trimmed.starts_with("```") {
+    Some("```")
+  } else if trimmed.starts_with("~~~") {
+    Some("~~~")
+  } else {
+    None
+  }
+}
+
+fn markdown_table_at(lines: &[&str], index: usize) -> Option<(MarkdownTable, usize)> {
+  if index + 1 >= lines.len() {
+    return None;
+  }
+
+  let header_cells = split_markdown_table_row(lines[index])?;
+  let separator_cells = split_markdown_table_row(lines[index + 1])?;
+  if separator_cells.len() < header_cells.len() || !markdown_table_separator_row(&separator_cells) {
+    return None;
+  }
+
+  let column_count = header_cells.len();
+  let mut rows = Vec::new();
+  let mut row_index = index + 2;
+  while row_index < lines.len() {
+    let Some(row_cells) = split_markdown_table_row(lines[row_index]) else {
+      break;
+    };
+    if markdown_table_separator_row(&row_cells) {
+      break;
+    }
+    rows.push(MarkdownTableRow { cells: normalize_markdown_table_inline_cells(&row_cells, column_count) });
+    row_index += 1;
+  }
+
+  Some((
+    MarkdownTable {
+      columns: normalize_markdown_table_cells(&header_cells, column_count)
+        .iter()
+        .map(|cell| parse_markdown_inline(cell).title)
+        .collect(),
+      rows,
+    },
+    row_index,
+  ))
+}
+
+fn split_markdown_table_row(line: &str) -> Option<Vec<String>> {
+  let mut body = line.trim();
+  if body.is_empty() {
+    return None;
+  }
+
+  if body.starts_with('|') {
+    body = &body[1..];
+  }
+  if body.ends_with('|') && (body.len() < 2 || !body[..body.len() - 1].ends_with('\\')) {
+    body = &body[..body.len() - 1];
+  }
+
+  let mut cells = Vec::new();
+  let mut cell = String::new();
+  let mut saw_separator = false;
+  let mut chars = body.chars().peekable();
+  while let Some(ch) = chars.next() {
+    if ch == '\\' && chars.peek() == Some(&'|') {
+      cell.push('|');
+      chars.next();
+      continue;
+    }
+    if ch == '|' {
+      cells.push(cell.trim().to_owned());
+      cell.clear();
+      saw_separator = true;
+      continue;
+    }
+    cell.push(ch);
+  }
+  cells.push(cell.trim().to_owned());
+
+  if !saw_separator || cells.len() < 2 {
+    return None;
+  }
+  Some(cells)
+}
+
+fn markdown_table_separator_cell(cell: &str) -> bool {
+  let trimmed = cell.trim();
+  let body = trimmed.strip_prefix(':').unwrap_or(trimmed);
+  let body = body.strip_suffix(':').unwrap_or(body);
+  body.len() >= 3 && body.chars().all(|ch| ch == '-')
+}
+
+fn markdown_table_separator_row(cells: &[String]) -> bool {
+  cells.len() >= 2 && cells.iter().all(|cell| markdown_table_separator_cell(cell))
+}
+
+fn normalize_markdown_table_cells(cells: &[String], column_count: usize) -> Vec<String> {
+  let mut result = cells.iter().take(column_count).cloned().collect::<Vec<_>>();
+  while result.len() < column_count {
+    result.push("".to_owned());
+  }
+  result
+}
+
+fn normalize_markdown_table_inline_cells(cells: &[String], column_count: usize) -> Vec<MarkdownInlineText> {
+  normalize_markdown_table_cells(cells, column_count).iter().map(|cell| parse_markdown_inline(cell)).collect()
+}
+
+fn markdown_divider_line(line: &str) -> bool {
+  let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+  if chars.len() < 3 {
+    return false;
+  }
+  let divider_char = chars[0];
+  matches!(divider_char, '-' | '_' | '*') && chars.iter().all(|c| *c == divider_char)
+}
+
+fn markdown_heading(line: &str) -> Option<(i64, &str)> {
+  let trimmed = line.trim_start();
+  let heading_level = trimmed.chars().take_while(|c| *c == '#').count();
+  if heading_level == 0 || heading_level > 6 {
+    return None;
+  }
+  let title = &trimmed[heading_level..];
+  if !title.chars().next().map(|c| c.is_whitespace()).unwrap_or(false) {
+    return None;
+  }
+  let title = title.trim();
+  if title.is_empty() {
+    return None;
+  }
+  Some((
+    match heading_level {
+      1 => NOTE_FLAG_HEADING1,
+      2 => NOTE_FLAG_HEADING2,
+      3 => NOTE_FLAG_HEADING3,
+      _ => NOTE_FLAG_HEADING4,
+    },
+    title,
+  ))
+}
+
+fn markdown_list_item(line: &str) -> Option<(i64, &str)> {
+  let trimmed = line.trim_start();
+  let mut chars = trimmed.char_indices();
+  if let Some((_, marker)) = chars.next() {
+    if matches!(marker, '-' | '*' | '+') {
+      if let Some((content_start, separator)) = chars.next() {
+        if separator.is_whitespace() {
+          return Some((NOTE_FLAG_BULLET1, trimmed[content_start..].trim_start()));
+        }
+      }
+    }
+  }
+
+  let mut digit_end = 0;
+  let mut has_digit = false;
+  for (index, ch) in trimmed.char_indices() {
+    if !ch.is_ascii_digit() {
+      break;
+    }
+    has_digit = true;
+    digit_end = index + ch.len_utf8();
+  }
+  if !has_digit || !trimmed[digit_end..].starts_with('.') {
+    return None;
+  }
+
+  let content = &trimmed[digit_end + 1..];
+  if !content.chars().next().map(|c| c.is_whitespace()).unwrap_or(false) {
+    return None;
+  }
+  Some((NOTE_FLAG_NUMBERED, content.trim_start()))
+}
+
+fn markdown_standalone_inline_heading(line: &str) -> bool {
+  let trimmed = line.trim();
+  for marker in ["***", "___", "**", "__"] {
+    if trimmed.len() > marker.len() * 2 && trimmed.starts_with(marker) && trimmed.ends_with(marker) {
+      return true;
+    }
+  }
+  false
+}
+
+pub fn parse_markdown_inline(input: &str) -> MarkdownInlineText {
+  let mut output = String::new();
+  let mut inline_marks = Vec::new();
+  let mut urls = Vec::new();
+  let mut index = 0;
+
+  while index < input.len() {
+    if let Some((escaped, len)) = markdown_escaped_ascii_punctuation(&input[index..]) {
+      output.push(escaped);
+      index += len;
+      continue;
+    }
+
+    if input[index..].starts_with("`") {
+      let content_start = index + 1;
+      if let Some(content_end) = input[content_start..].find('`').map(|offset| content_start + offset) {
+        output.push_str(&input[content_start..content_end]);
+        index = content_end + 1;
+        continue;
+      }
+    }
+
+    if let Some((label, url, len)) = markdown_link_at(&input[index..]) {
+      append_linked_inline_text(&mut output, &mut inline_marks, &mut urls, label, url);
+      index += len;
+      continue;
+    }
+
+    if let Some((marker, flags)) = markdown_inline_marker(&input[index..]) {
+      let content_start = index + marker.len();
+      if let Some(content_end) = input[content_start..].find(marker).map(|offset| content_start + offset) {
+        if content_end > content_start {
+          append_marked_inline_text(&mut output, &mut inline_marks, &input[content_start..content_end], flags);
+          index = content_end + marker.len();
+          continue;
+        }
+      }
+    }
+
+    let ch = input[index..].chars().next().unwrap();
+    output.push(ch);
+    index += ch.len_utf8();
+  }
+
+  MarkdownInlineText { title: output, inline_marks, urls }
+}
+
+fn markdown_link_at(slice: &str) -> Option<(&str, String, usize)> {
+  if !slice.starts_with('[') {
+    return None;
+  }
+
+  let label_end = slice[1..].find("](").map(|offset| offset + 1)?;
+  let label = &slice[1..label_end];
+  if label.trim().is_empty() {
+    return None;
+  }
+
+  let destination_start = label_end + 2;
+  let destination_end = markdown_link_destination_end(&slice[destination_start..])?;
+  let destination = markdown_link_destination(&slice[destination_start..destination_start + destination_end])?;
+  Some((label, destination, destination_start + destination_end + 1))
+}
+
+fn markdown_link_destination_end(slice: &str) -> Option<usize> {
+  let mut escaped = false;
+  for (index, ch) in slice.char_indices() {
+    if escaped {
+      escaped = false;
+      continue;
+    }
+    if ch == '\\' {
+      escaped = true;
+      continue;
+    }
+    if ch == ')' {
+      return Some(index);
+    }
+  }
+  None
+}
+
+fn markdown_link_destination(raw_destination: &str) -> Option<String> {
+  let trimmed = raw_destination.trim();
+  if trimmed.is_empty() {
+    return None;
+  }
+
+  let destination = if let Some(body) = trimmed.strip_prefix('<') {
+    let end = body.find('>')?;
+    &body[..end]
+  } else {
+    trimmed.split_whitespace().next().unwrap_or("")
+  };
+  let destination = destination.trim();
+  if destination.is_empty() { None } else { Some(destination.to_owned()) }
+}
+
+fn markdown_escaped_ascii_punctuation(slice: &str) -> Option<(char, usize)> {
+  let mut chars = slice.chars();
+  if chars.next()? != '\\' {
+    return None;
+  }
+
+  let escaped = chars.next()?;
+  if !escaped.is_ascii_punctuation() {
+    return None;
+  }
+
+  Some((escaped, '\\'.len_utf8() + escaped.len_utf8()))
+}
+
+fn markdown_inline_marker(slice: &str) -> Option<(&'static str, i64)> {
+  for (marker, flags) in [
+    ("***", NOTE_INLINE_MARK_BOLD | NOTE_INLINE_MARK_ITALIC),
+    ("___", NOTE_INLINE_MARK_BOLD | NOTE_INLINE_MARK_ITALIC),
+    ("**", NOTE_INLINE_MARK_BOLD),
+    ("__", NOTE_INLINE_MARK_BOLD),
+    ("*", NOTE_INLINE_MARK_ITALIC),
+    ("_", NOTE_INLINE_MARK_ITALIC),
+  ] {
+    if slice.starts_with(marker) {
+      return Some((marker, flags));
+    }
+  }
+  None
+}
+
+fn append_marked_inline_text(output: &mut String, inline_marks: &mut Vec<i64>, text: &str, flags: i64) {
+  let start = output.encode_utf16().count() as i64;
+  append_markdown_unescaped_text(output, text);
+  let end = output.encode_utf16().count() as i64;
+  push_inline_mark(inline_marks, start, end, flags);
+}
+
+fn append_linked_inline_text(
+  output: &mut String,
+  inline_marks: &mut Vec<i64>,
+  urls: &mut Vec<MarkdownUrl>,
+  label: &str,
+  url: String,
+) {
+  let start = output.encode_utf16().count() as i64;
+  let parsed_label = parse_markdown_inline(label);
+  output.push_str(&parsed_label.title);
+  append_shifted_inline_marks(inline_marks, &parsed_label.inline_marks, start);
+  let end = output.encode_utf16().count() as i64;
+  push_url(urls, start, end, url);
+}
+
+fn append_shifted_inline_marks(inline_marks: &mut Vec<i64>, shifted_marks: &[i64], offset: i64) {
+  for chunk in shifted_marks.chunks_exact(3) {
+    push_inline_mark(inline_marks, chunk[0] + offset, chunk[1] + offset, chunk[2]);
+  }
+}
+
+fn append_markdown_unescaped_text(output: &mut String, text: &str) {
+  let mut index = 0;
+  while index < text.len() {
+    if let Some((escaped, len)) = markdown_escaped_ascii_punctuation(&text[index..]) {
+      output.push(escaped);
+      index += len;
+      continue;
+    }
+
+    let ch = text[index..].chars().next().unwrap();
+    output.push(ch);
+    index += ch.len_utf8();
+  }
+}
+
+fn push_inline_mark(inline_marks: &mut Vec<i64>, start: i64, end: i64, flags: i64) {
+  if start >= end || flags == 0 {
+    return;
+  }
+  if inline_marks.len() >= 3 {
+    let last_start_index = inline_marks.len() - 3;
+    let last_end = inline_marks[last_start_index + 1];
+    let last_flags = inline_marks[last_start_index + 2];
+    if start < last_end {
+      return;
+    }
+    if start == last_end && flags == last_flags {
+      inline_marks[last_start_index + 1] = end;
+      return;
+    }
+  }
+
+  inline_marks.extend([start, end, flags]);
+}
+
+fn push_url(urls: &mut Vec<MarkdownUrl>, start: i64, end: i64, url: String) {
+  let url = url.trim().to_owned();
+  if start >= end || url.is_empty() {
+    return;
+  }
+  if let Some(last) = urls.last_mut() {
+    if start < last.end {
+      return;
+    }
+    if start == last.end && last.url == url {
+      last.end = end;
+      return;
+    }
+  }
+
+  urls.push(MarkdownUrl { start, end, url });
+}
@@ -0,0 +1,537 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Container level probing of audio and video files. Only enough of each format is parsed to
+//! determine the play length and frame size; nothing is decoded. Frames, which do require decoding,
+//! are extracted by an external ffmpeg process.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use config::Config;
+use infusdk::item::{Item, ItemType};
+use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::new_uid;
+use once_cell::sync::OnceCell;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::{CONFIG_DATA_DIR, CONFIG_FFMPEG_PATH};
+use crate::util::fs::expand_tilde;
+
+const FFMPEG_TIMEOUT: Duration = Duration::from_secs(60);
+const POSTER_FRAME_MAX_OFFSET_SECS: f64 = 1.0;
+const FFMPEG_INPUT_DIR_NAME: &str = "ffmpeg_input";
+
+static FFMPEG_PATH: OnceCell<Option<String>> = OnceCell::new();
+static FFMPEG_INPUT_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+pub fn is_audio_mime_type(mime_type: &str) -> bool {
+  mime_type.starts_with("audio/")
+}
+
+pub fn is_video_mime_type(mime_type: &str) -> bool {
+  mime_type.starts_with("video/")
+}
+
+pub fn is_video_item(item: &Item) -> bool {
+  item.item_type == ItemType::File && item.mime_type.as_deref().is_some_and(is_video_mime_type)
+}
+
+/// The play length of an audio or video file in milliseconds, or None if the format is not supported
+/// or the data could not be understood. Supports WAV, MP3, MP4/M4A/MOV, Ogg (Vorbis and Opus),
+/// WebM/Matroska and AVI.
+pub fn media_duration_ms(mime_type: &str, data: &[u8]) -> Option<i64> {
+  let duration_ms = match mime_type {
+    "audio/wav" => wav_duration_ms(data),
+    "audio/mpeg" => mp3_duration_ms(data),
+    "audio/mp4" | "audio/aac" | "video/mp4" | "video/quicktime" => mp4_duration_ms(data),
+    "audio/ogg" | "audio/opus" => ogg_duration_ms(data),
+    "audio/webm" | "video/webm" | "video/x-matroska" => matroska_duration_ms(data),
+    "video/x-msvideo" => avi_duration_ms(data),
+    _ => None,
+  }?;
+  if duration_ms > 0 { Some(duration_ms) } else { None }
+}
+
+/// The display size (width, height) of the first video track in pixels, with any rotation recorded in
+/// the container applied. None if the format is not supported or there is no video track.
+pub fn video_size_px(mime_type: &str, data: &[u8]) -> Option<(u32, u32)> {
+  let (width, height) = match mime_type {
+    "video/mp4" | "video/quicktime" => mp4_video_size_px(data),
+    "video/webm" | "video/x-matroska" => matroska_video_size_px(data),
+    "video/x-msvideo" => avi_video_size_px(data),
+    _ => None,
+  }?;
+  if width > 0 && height > 0 { Some((width, height)) } else { None }
+}
+
+/// Record the configured ffmpeg executable, and the directory in the data directory that its input
+/// files are written to. Until this is called (as in command line tools), nothing that needs ffmpeg is
+/// attempted. Input files left behind by an earlier run are removed.
+pub fn init_ffmpeg_path(config: &Config) -> InfuResult<()> {
+  let ffmpeg_path = config.get_string(CONFIG_FFMPEG_PATH).map_err(|e| e.to_string())?;
+  let ffmpeg_path = Some(ffmpeg_path.trim().to_owned()).filter(|path| !path.is_empty());
+  let data_dir = config.get_string(CONFIG_DATA_DIR).map_err(|e| e.to_string())?;
+  let input_dir =
+    expand_tilde(&data_dir).ok_or("Could not interpret data directory path.")?.join(FFMPEG_INPUT_DIR_NAME);
+  if input_dir.exists() {
+    std::fs::remove_dir_all(&input_dir)
+      .map_err(|e| format!("Could not remove ffmpeg input directory '{}': {}", input_dir.display(), e))?;
+  }
+  FFMPEG_PATH.set(ffmpeg_path).map_err(|_| "ffmpeg path has already been initialized.")?;
+  FFMPEG_INPUT_DIR.set(input_dir).map_err(|_| "ffmpeg input directory has already been initialized.")?;
+  Ok(())
+}
+
+pub fn is_ffmpeg_configured() -> bool {
+  FFMPEG_PATH.get().is_some_and(|path| path.is_some())
+}
+
+/// Extract a single frame from near the start of a video as a JPEG. The frame is taken a short way in,
+/// since screen recordings and camera footage often start on a blank frame.
+pub async fn extract_video_poster_jpeg(data: &[u8], duration_ms: Option<i64>) -> InfuResult<Vec<u8>> {
+  let offset_secs = match duration_ms {
+    Some(duration_ms) => (duration_ms as f64 / 1000.0 / 10.0).min(POSTER_FRAME_MAX_OFFSET_SECS),
+    None => 0.0,
+  };
+  ffmpeg_frame_jpeg(data, offset_secs).await
+}
+
+/// Decode the first frame at or after `offset_secs` of any input ffmpeg understands (a video, or a
+/// still image such as HEIF or AVIF) and encode it as a JPEG, with any rotation recorded in the
+/// container applied.
+///
+/// Most containers can't be read from a pipe (MP4 files commonly keep their index at the end), so the
+/// input, which is decrypted item data, is written to a file in the data directory that only the
+/// server's user can read, for the duration of the call.
+pub async fn ffmpeg_frame_jpeg(data: &[u8], offset_secs: f64) -> InfuResult<Vec<u8>> {
+  let ffmpeg_path = FFMPEG_PATH.get().and_then(|path| path.as_deref()).ok_or("ffmpeg is not configured.")?;
+  let input_dir = FFMPEG_INPUT_DIR.get().ok_or("ffmpeg is not configured.")?;
+  let input_path = input_dir.join(new_uid());
+  if let Err(e) = write_private_file(input_dir, &input_path, data).await {
+    let _ = tokio::fs::remove_file(&input_path).await;
+    return Err(format!("Could not write ffmpeg input file '{}': {}", input_path.display(), e).into());
+  }
+
+  let output = Command::new(ffmpeg_path)
+    .args(["-nostdin", "-v", "error", "-ss", &format!("{:.3}", offset_secs), "-i"])
+    .arg(&input_path)
+    .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "mjpeg", "-q:v", "3", "pipe:1"])
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .output();
+  let output = tokio::time::timeout(FFMPEG_TIMEOUT, output).await;
+  let _ = tokio::fs::remove_file(&input_path).await;
+
+  let output = output
+    .map_err(|_| format!("ffmpeg did not produce a frame within {} seconds.", FFMPEG_TIMEOUT.as_secs()))?
+    .map_err(|e| format!("Could not run ffmpeg ('{}'): {}", ffmpeg_path, e))?;
+  if !output.status.success() {
+    return Err(
+      format!(
+        "ffmpeg failed to extract a frame ({}): {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+      )
+      .into(),
+    );
+  }
+  if output.stdout.is_empty() {
+    return Err("ffmpeg did not output a frame.".into());
+  }
+  Ok(output.stdout)
+}
+
+async fn write_private_file(dir: &PathBuf, path: &PathBuf, data: &[u8]) -> std::io::Result<()> {
+  tokio::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir).await?;
+  let mut file = tokio::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path).await?;
+  file.write_all(data).await?;
+  file.flush().await
+}
+
+fn wav_duration_ms(data: &[u8]) -> Option<i64> {
+  if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"WAVE" {
+    return None;
+  }
+  let mut byte_rate = None;
+  let mut offset = 12;
+  while offset + 8 <= data.len() {
+    let chunk_id = &data[offset..offset + 4];
+    let chunk_size = u32_le(data, offset + 4)? as usize;
+    let body = offset + 8;
+    if chunk_id == b"fmt " {
+      byte_rate = Some(u32_le(data, body + 8)?);
+    } else if chunk_id == b"data" {
+      // Streamed recordings can leave the size unset (0 or 0xFFFFFFFF), in which case the data runs to the end.
+      let available = data.len() - body;
+      let data_size = if chunk_size == 0 || chunk_size > available { available } else { chunk_size };
+      let byte_rate = byte_rate.filter(|rate| *rate > 0)? as u64;
+      return Some((data_size as u64 * 1000 / byte_rate) as i64);
+    }
+    offset = body.checked_add(chunk_size)?.checked_add(chunk_size % 2)?;
+  }
+  None
+}
+
+fn mp3_duration_ms(data: &[u8]) -> Option<i64> {
+  let mut start = 0;
+  if data.get(0..3)? == b"ID3" {
+    let tag_size = syncsafe_u32(data, 6)? as usize;
+    let has_footer = data.get(5)? & 0x10 != 0;
+    start = 10 + tag_size + if has_footer { 10 } else { 0 };
+  }
+  let mut end = data.len();
+  if end >= 128 && &data[end - 128..end - 125] == b"TAG" {
+    end -= 128;
+  }
+
+  let (offset, frame) = (start..end.saturating_sub(4))
+    .find_map(|offset| Mp3FrameHeader::parse(data.get(offset..offset + 4)?).map(|frame| (offset, frame)))?;
+
+  // A Xing/Info or VBRI header in the first frame gives the exact frame count of variable bitrate files.
+  let xing_offset = offset + 4 + frame.side_info_len();
+  let frame_count = if data.get(xing_offset..xing_offset + 4) == Some(b"Xing")
+    || data.get(xing_offset..xing_offset + 4) == Some(b"Info")
+  {
+    let flags = u32_be(data, xing_offset + 4)?;
+    if flags & 1 != 0 { Some(u32_be(data, xing_offset + 8)?) } else { None }
+  } else if data.get(offset + 36..offset + 40) == Some(b"VBRI") {
+    Some(u32_be(data, offset + 36 + 14)?)
+  } else {
+    None
+  };
+
+  match frame_count {
+    Some(frame_count) => {
+      Some((frame_count as u64 * frame.samples_per_frame as u64 * 1000 / frame.sample_rate as u64) as i64)
+    }
+    None => Some(((end - offset) as u64 * 8 / frame.bitrate_kbps as u64) as i64),
+  }
+}
+
+struct Mp3FrameHeader {
+  is_mpeg1: bool,
+  is_mono: bool,
+  bitrate_kbps: u32,
+  sample_rate: u32,
+  samples_per_frame: u32,
+}
+
+impl Mp3FrameHeader {
+  fn parse(header: &[u8]) -> Option<Mp3FrameHeader> {
+    if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+      return None;
+    }
+    let version = (header[1] >> 3) & 0x03;
+    let layer = (header[1] >> 1) & 0x03;
+    let bitrate_index = (header[2] >> 4) as usize;
+    let sample_rate_index = ((header[2] >> 2) & 0x03) as usize;
+    if version == 1 || layer == 0 || bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+      return None;
+    }
+
+    let is_mpeg1 = version == 3;
+    let bitrates: [u32; 15] = match (is_mpeg1, layer) {
+      (true, 3) => [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+      (true, 2) => [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+      (true, _) => [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+      (false, 3) => [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+      (false, _) => [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    };
+    let sample_rates: [u32; 3] = match version {
+      3 => [44100, 48000, 32000],
+      2 => [22050, 24000, 16000],
+      _ => [11025, 12000, 8000],
+    };
+    let samples_per_frame = match layer {
+      3 => 384,
+      2 => 1152,
+      _ if is_mpeg1 => 1152,
+      _ => 576,
+    };
+    Some(Mp3FrameHeader {
+      is_mpeg1,
+      is_mono: (header[3] >> 6) == 3,
+      bitrate_kbps: bitrates[bitrate_index],
+      sample_rate: sample_rates[sample_rate_index],
+      samples_per_frame,
+    })
+  }
+
+  fn side_info_len(&self) -> usize {
+    match (self.is_mpeg1, self.is_mono) {
+      (true, true) => 17,
+      (true, false) => 32,
+      (false, true) => 9,
+      (false, false) => 17,
+    }
+  }
+}
+
+fn mp4_duration_ms(data: &[u8]) -> Option<i64> {
+  let moov = mp4_find_box(data, b"moov")?;
+  let mvhd = mp4_find_box(moov, b"mvhd")?;
+  let (timescale, duration) = match *mvhd.first()? {
+    0 => (u32_be(mvhd, 12)?, u32_be(mvhd, 16)? as u64),
+    1 => (u32_be(mvhd, 20)?, u64_be(mvhd, 24)?),
+    _ => return None,
+  };
+  if timescale == 0 || duration == u64::MAX || duration == u32::MAX as u64 {
+    return None;
+  }
+  Some((duration as u128 * 1000 / timescale as u128) as i64)
+}
+
+fn mp4_video_size_px(data: &[u8]) -> Option<(u32, u32)> {
+  let moov = mp4_find_box(data, b"moov")?;
+  mp4_boxes(moov).into_iter().filter(|(box_type, _)| box_type == b"trak").find_map(|(_, trak)| {
+    let hdlr = mp4_find_box(mp4_find_box(trak, b"mdia")?, b"hdlr")?;
+    if hdlr.get(8..12)? != b"vide" {
+      return None;
+    }
+    let tkhd = mp4_find_box(trak, b"tkhd")?;
+    let matrix_offset = match *tkhd.first()? {
+      0 => 40,
+      1 => 52,
+      _ => return None,
+    };
+    // Width and height are 16.16 fixed point values following the 3x3 transformation matrix.
+    let width = u32_be(tkhd, matrix_offset + 36)? >> 16;
+    let height = u32_be(tkhd, matrix_offset + 40)? >> 16;
+    // Phone footage is commonly stored in landscape with a 90 or 270 degree rotation in the matrix.
+    let a = u32_be(tkhd, matrix_offset)?;
+    let b = u32_be(tkhd, matrix_offset + 4)?;
+    if a == 0 && b != 0 { Some((height, width)) } else { Some((width, height)) }
+  })
+}
+
+/// The body of the first box of the given type at the top level of `data`.
+fn mp4_find_box<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+  mp4_boxes(data).into_iter().find(|(t, _)| t == box_type).map(|(_, body)| body)
+}
+
+/// The type and body of each box at the top level of `data`, up to the first that can't be parsed.
+fn mp4_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+  let mut boxes = Vec::new();
+  let mut offset = 0;
+  while offset + 8 <= data.len() {
+    let Some(size) = u32_be(data, offset) else { break };
+    let (header_len, box_len) = match size {
+      0 => (8, (data.len() - offset) as u64),
+      1 => match u64_be(data, offset + 8) {
+        Some(box_len) => (16, box_len),
+        None => break,
+      },
+      _ => (8, size as u64),
+    };
+    if box_len < header_len as u64 {
+      break;
+    }
+    let Some(end) = usize::try_from(box_len).ok().and_then(|box_len| offset.checked_add(box_len)) else { break };
+    let end = end.min(data.len());
+    let Some(body) = data.get(offset + header_len..end) else { break };
+    boxes.push((data[offset + 4..offset + 8].try_into().unwrap(), body));
+    offset = end;
+  }
+  boxes
+}
+
+const EBML_SEGMENT: u32 = 0x18538067;
+const EBML_INFO: u32 = 0x1549A966;
+const EBML_TIMECODE_SCALE: u32 = 0x2AD7B1;
+const EBML_DURATION: u32 = 0x4489;
+const EBML_TRACKS: u32 = 0x1654AE6B;
+const EBML_TRACK_ENTRY: u32 = 0xAE;
+const EBML_VIDEO: u32 = 0xE0;
+const EBML_PIXEL_WIDTH: u32 = 0xB0;
+const EBML_PIXEL_HEIGHT: u32 = 0xBA;
+const EBML_CLUSTER: u32 = 0x1F43B675;
+
+fn matroska_duration_ms(data: &[u8]) -> Option<i64> {
+  let segment = ebml_find(data, EBML_SEGMENT)?;
+  let info = ebml_find(segment, EBML_INFO)?;
+  // Recordings made in a browser with MediaRecorder usually have no duration at all.
+  let duration = ebml_float(ebml_find(info, EBML_DURATION)?)?;
+  let timecode_scale_ns = ebml_find(info, EBML_TIMECODE_SCALE).and_then(ebml_uint).unwrap_or(1_000_000);
+  if !duration.is_finite() || duration <= 0.0 {
+    return None;
+  }
+  Some((duration * timecode_scale_ns as f64 / 1_000_000.0) as i64)
+}
+
+fn matroska_video_size_px(data: &[u8]) -> Option<(u32, u32)> {
+  let segment = ebml_find(data, EBML_SEGMENT)?;
+  let tracks = ebml_find(segment, EBML_TRACKS)?;
+  ebml_elements(tracks).into_iter().filter(|(id, _)| *id == EBML_TRACK_ENTRY).find_map(|(_, track_entry)| {
+    let video = ebml_find(track_entry, EBML_VIDEO)?;
+    let width = ebml_uint(ebml_find(video, EBML_PIXEL_WIDTH)?)?;
+    let height = ebml_uint(ebml_find(video, EBML_PIXEL_HEIGHT)?)?;
+    Some((u32::try_from(width).ok()?, u32::try_from(height).ok()?))
+  })
+}
+
+fn ebml_find(data: &[u8], id: u32) -> Option<&[u8]> {
+  ebml_elements(data).into_iter().find(|(element_id, _)| *element_id == id).map(|(_, body)| body)
+}
+
+/// The id and body of each element at the top level of `data`. Scanning stops at the first cluster,
+/// since all the metadata precedes the media data, or at the first element that can't be parsed.
+fn ebml_elements(data: &[u8]) -> Vec<(u32, &[u8])> {
+  let mut elements = Vec::new();
+  let mut offset = 0;
+  while offset < data.len() {
+    let Some((Some(id), id_len)) = ebml_vint(data, offset, false) else { break };
+    let id = id as u32;
+    if id == EBML_CLUSTER {
+      break;
+    }
+    let Some((size, size_len)) = ebml_vint(data, offset + id_len, true) else { break };
+    let body_start = offset + id_len + size_len;
+    // An unknown size (all value bits set) means the element runs to the end of its parent.
+    let body_end = match size {
+      None => data.len(),
+      Some(size) => match usize::try_from(size).ok().and_then(|size| body_start.checked_add(size)) {
+        Some(end) => end.min(data.len()),
+        None => break,
+      },
+    };
+    let Some(body) = data.get(body_start..body_end) else { break };
+    elements.push((id, body));
+    offset = body_end;
+  }
+  elements
+}
+
+/// A variable length integer and its length in bytes. Element ids keep their length marker bit, sizes
+/// do not. A size with all value bits set is unknown, and returned as None.
+fn ebml_vint(data: &[u8], offset: usize, is_size: bool) -> Option<(Option<u64>, usize)> {
+  let first = *data.get(offset)?;
+  let len = first.leading_zeros() as usize + 1;
+  if len > if is_size { 8 } else { 4 } {
+    return None;
+  }
+  let bytes = data.get(offset..offset + len)?;
+  let raw = bytes.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+  if !is_size {
+    return Some((Some(raw), len));
+  }
+  let value_mask = (1u64 << (7 * len)) - 1;
+  let value = raw & value_mask;
+  Some((if value == value_mask { None } else { Some(value) }, len))
+}
+
+fn ebml_uint(body: &[u8]) -> Option<u64> {
+  if body.is_empty() || body.len() > 8 {
+    return None;
+  }
+  Some(body.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64))
+}
+
+fn ebml_float(body: &[u8]) -> Option<f64> {
+  match body.len() {
+    4 => Some(f32::from_be_bytes(body.try_into().ok()?) as f64),
+    8 => Some(f64::from_be_bytes(body.try_into().ok()?)),
+    _ => None,
+  }
+}
+
+/// The main AVI header, which immediately follows the RIFF and hdrl list headers.
+fn avi_main_header(data: &[u8]) -> Option<&[u8]> {
+  if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"AVI " {
+    return None;
+  }
+  if data.get(12..16)? != b"LIST" || data.get(20..24)? != b"hdrl" || data.get(24..28)? != b"avih" {
+    return None;
+  }
+  let len = u32_le(data, 28)? as usize;
+  data.get(32..32 + len)
+}
+
+fn avi_duration_ms(data: &[u8]) -> Option<i64> {
+  let avih = avi_main_header(data)?;
+  let micro_secs_per_frame = u32_le(avih, 0)? as u64;
+  let total_frames = u32_le(avih, 16)? as u64;
+  Some((micro_secs_per_frame * total_frames / 1000) as i64)
+}
+
+fn avi_video_size_px(data: &[u8]) -> Option<(u32, u32)> {
+  let avih = avi_main_header(data)?;
+  Some((u32_le(avih, 32)?, u32_le(avih, 36)?))
+}
+
+fn ogg_duration_ms(data: &[u8]) -> Option<i64> {
+  if data.get(0..4)? != b"OggS" {
+    return None;
+  }
+  let serial = u32_le(data, 14)?;
+  let segment_count = *data.get(26)? as usize;
+  let packet = data.get(27 + segment_count..)?;
+
+  let (sample_rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+    (u32_le(packet, 12)? as u64, 0)
+  } else if packet.starts_with(b"OpusHead") {
+    // Opus granule positions are always in 48kHz samples, regardless of the input rate.
+    (48000, u16_le(packet, 10)? as u64)
+  } else {
+    return None;
+  };
+  if sample_rate == 0 {
+    return None;
+  }
+
+  let mut offset = data.len().saturating_sub(27);
+  loop {
+    if &data[offset..offset + 4] == b"OggS" && u32_le(data, offset + 14) == Some(serial) {
+      let granule = u64_le(data, offset + 6)?;
+      if granule != u64::MAX {
+        return Some((granule.saturating_sub(pre_skip) * 1000 / sample_rate) as i64);
+      }
+    }
+    if offset == 0 {
+      return None;
+    }
+    offset -= 1;
+  }
+}
+
+fn u16_le(data: &[u8], offset: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u64_le(data: &[u8], offset: usize) -> Option<u64> {
+  Some(u64::from_le_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+fn u32_be(data: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn u64_be(data: &[u8], offset: usize) -> Option<u64> {
+  Some(u64::from_be_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+fn syncsafe_u32(data: &[u8], offset: usize) -> Option<u32> {
+  let bytes = data.get(offset..offset + 4)?;
+  Some(bytes.iter().fold(0u32, |acc, byte| (acc << 7) | (*byte & 0x7F) as u32))
+}
@@ -3,6 +3,9 @@ use std::path::Path;
 
 const APPLICATION_OCTET_STREAM: &str = "application/octet-stream";
 const TEXT_PLAIN: &str = "text/plain";
+/// Web archives created by archive-link are HTML, but are given their own mime type so that they
+/// (and no other HTML) are viewed inline, in a sandbox.
+pub const WEB_ARCHIVE_MIME_TYPE: &str = "application/x-infumap-web-archive";
 
 pub fn detect_mime_type(data: &[u8]) -> String {
   if let Some(kind) = infer::get(data) {
@@ -37,6 +40,10 @@ pub fn normalized_mime_type(raw: &str) -> String {
     "application/x-gzip" => "application/gzip",
     "application/x-pdf" => "application/pdf",
     "application/x-zip-compressed" => "application/zip",
+    "audio/m4a" | "audio/x-m4a" => "audio/mp4",
+    "audio/mp3" | "audio/x-mp3" => "audio/mpeg",
+    "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => "audio/wav",
+    "image/heic" | "image/heic-sequence" | "image/heif-sequence" => "image/heif",
     "image/jpg" | "image/pjpeg" => "image/jpeg",
     "text/xml" => "application/xml",
     _ => trimmed,
@@ -52,21 +59,37 @@ pub fn mime_type_from_title_extension(title: &str) -> Option<String> {
   let extension = Path::new(title.trim()).extension()?.to_str()?.to_ascii_lowercase();
   let mime_type = match extension.as_str() {
     "aac" => "audio/aac",
+    "arw" => "image/x-sony-arw",
     "avi" => "video/x-msvideo",
+    "avif" => "image/avif",
+    "cr2" => "image/x-canon-cr2",
+    "cr3" => "image/x-canon-cr3",
     "csv" => "text/csv",
+    "dng" => "image/x-adobe-dng",
+    "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "eml" => "message/rfc822",
+    "epub" => "application/epub+zip",
     "gif" => "image/gif",
     "gz" => "application/gzip",
+    "heic" | "heif" => "image/heif",
+    "htm" | "html" => "text/html",
     "jpeg" | "jpg" => "image/jpeg",
     "json" => "application/json",
     "m4a" => "audio/mp4",
     "m4v" | "mp4" => "video/mp4",
+    "mbox" => "application/mbox",
     "md" | "markdown" => "text/markdown",
     "mkv" => "video/x-matroska",
     "mov" | "qt" => "video/quicktime",
     "mp3" => "audio/mpeg",
+    "nef" => "image/x-nikon-nef",
+    "odt" => "application/vnd.oasis.opendocument.text",
     "ogg" => "audio/ogg",
+    "orf" => "image/x-olympus-orf",
     "pdf" => "application/pdf",
     "png" => "image/png",
+    "raf" => "image/x-fuji-raf",
+    "rw2" => "image/x-panasonic-rw2",
     "svg" => "image/svg+xml",
     "txt" => "text/plain",
     "wav" => "audio/wav",
@@ -15,9 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod crypto;
+pub mod email;
 pub mod fs;
 pub mod image;
+pub mod image_rendition;
+pub mod keyed_lock;
 pub mod lang;
+pub mod markdown;
+pub mod media;
 pub mod mime;
 pub mod ordering;
 pub mod retry;
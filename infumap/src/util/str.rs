@@ -27,3 +27,22 @@ pub fn encode_hex(bytes: &[u8]) -> String {
   }
   s
 }
+
+pub fn percent_decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%'
+      && i + 2 < bytes.len()
+      && let (Some(high), Some(low)) = ((bytes[i + 1] as char).to_digit(16), (bytes[i + 2] as char).to_digit(16))
+    {
+      out.push((high * 16 + low) as u8);
+      i += 3;
+      continue;
+    }
+    out.push(bytes[i]);
+    i += 1;
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
@@ -42,9 +42,13 @@ use tokio::sync::Mutex;
 use tokio::task::spawn_blocking;
 use tokio::{task, time};
 
+use crate::ai::audio_pipeline::init_audio_transcript_pipeline_loop;
 use crate::ai::document_pipeline::init_document_fragment_pipeline_loop;
+use crate::ai::fragment::init_chunking_strategies;
 use crate::ai::fragment_indexing::init_fragment_indexing_loop;
 use crate::ai::image_pipeline::init_image_semantic_pipeline_loop;
+use crate::ai::link_snapshot_pipeline::init_link_snapshot_pipeline_loop;
+use crate::ai::near_duplicates::init_near_duplicate_scan_loop;
 use crate::ai::text_extraction::init_text_extraction_processing_loop;
 use crate::ai::title_indexing::init_item_title_indexing_loop;
 use crate::config::*;
@@ -58,6 +62,7 @@ use crate::storage::object::{self as storage_object, ObjectStore};
 use crate::tokiort::TokioIo;
 use crate::util::crypto::{decrypt_file_data, encrypt_file_data};
 use crate::util::fs::expand_tilde;
+use crate::util::media::init_ffmpeg_path;
 
 use self::prometheus::spawn_prometheus_listener;
 use self::serve::http_serve;
@@ -237,11 +242,17 @@ pub async fn start_server_with_options(config: Config, skip_backup_validation: b
     info!("Done loading all items for all users.");
   }
 
+  init_chunking_strategies(config.as_ref())?;
   init_item_title_indexing_loop(data_dir.clone(), db.clone())?;
-  init_fragment_indexing_loop(config.as_ref(), db.clone())?;
   init_document_fragment_pipeline_loop(config.as_ref(), db.clone(), object_store.clone())?;
   init_text_extraction_processing_loop(config.as_ref(), db.clone(), object_store.clone())?;
   init_image_semantic_pipeline_loop(config.clone(), db.clone(), object_store.clone())?;
+  init_link_snapshot_pipeline_loop(config.as_ref(), db.clone())?;
+  init_audio_transcript_pipeline_loop(config.as_ref(), db.clone(), object_store.clone())?;
+  // After the fragment pipelines, so items found to need new fragments at startup can be queued.
+  init_fragment_indexing_loop(config.as_ref(), db.clone())?;
+  init_near_duplicate_scan_loop(config.as_ref(), db.clone(), object_store.clone())?;
+  init_ffmpeg_path(config.as_ref())?;
 
   if config.get_bool(CONFIG_ENABLE_S3_BACKUP).map_err(|e| e.to_string())? && !skip_backup_validation {
     let s3_region = config.get_string(CONFIG_S3_BACKUP_REGION).ok();
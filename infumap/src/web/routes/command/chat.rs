@@ -17,13 +17,23 @@
 use super::*;
 use http_body_util::{BodyExt as _, StreamBody};
 use hyper::body::Frame;
-use std::io::Write as _;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio_stream::wrappers::ReceiverStream;
 
+use crate::ai::chat_history::{
+  ChatConversation, ChatConversationMessage, ChatConversationToolCall, ChatTranscript,
+  append_chat_conversation_messages, list_chat_conversations, read_chat_conversation,
+};
+use crate::ai::llm::{
+  LlmChatMessage, LlmModel, LlmToolCall, LlmToolFunctionSpec, LlmToolSpec, configured_llm_model_settings,
+  configured_llm_model_summaries, default_llm_model_name, error_chain_for_log, open_llm_model,
+};
+use crate::util::markdown::{
+  MarkdownInlineText, MarkdownItem, MarkdownNote, MarkdownTable, MarkdownTableRow, markdown_items_from_text,
+  push_markdown_note,
+};
 use crate::web::serve::empty_body;
 
-const CHAT_LLAMA_REQUEST_TIMEOUT_SECS: u64 = 120;
 const CHAT_MAX_TOOL_ROUNDS: usize = 9;
 const CHAT_HISTORY_MAX_PREVIOUS_MESSAGES: usize = 8;
 const CHAT_HISTORY_MAX_MESSAGE_CHARS: usize = 4_000;
@@ -31,12 +41,15 @@ const CHAT_HISTORY_MAX_TOTAL_CHARS: usize = 12_000;
 const CHAT_LEXICAL_SEARCH_TOOL_DEFAULT_NUM_RESULTS: i64 = 8;
 const CHAT_LEXICAL_SEARCH_TOOL_MAX_NUM_RESULTS: i64 = 20;
 const CHAT_FRAGMENT_TOOL_DEFAULT_MAX_CHARS: usize = 2_500;
+const CHAT_LIST_CHILDREN_TOOL_DEFAULT_NUM_RESULTS: i64 = 50;
+const CHAT_LIST_CHILDREN_TOOL_MAX_NUM_RESULTS: i64 = 200;
+const CHAT_ITEM_METADATA_MAX_PATH_DEPTH: usize = 32;
+const CHAT_CONFIRMATION_TIMEOUT_SECS: u64 = 300;
 const CHAT_RESPONSE_ITEM_WIDTH_GR: i64 = 30 * GRID_SIZE;
 const CHAT_RESPONSE_TABLE_MAX_HEIGHT_BL: i64 = 12;
 const CHAT_RESPONSE_TABLE_MIN_COLUMN_WIDTH_GR: i64 = 3 * GRID_SIZE;
 const CHAT_RESPONSE_TABLE_TEXT_SCORE_CAP: usize = 120;
 const CHAT_RESPONSE_TABLE_LONG_WORD_SCORE_CAP: usize = 40;
-const LLM_LOG_PATH: &str = "/tmp/llm.txt";
 const CHAT_INFUMAP_SYSTEM_PROMPT: &str = "\
 You are a chat assistant for an information workspace.
 
@@ -44,22 +57,20 @@ Use lexical_search by default to locate items, discover likely relevant items, o
 lexical_search searches titles and document text using lexical matching.
 lexical_search results include linkUrl values. When you mention a specific search result item by title, link the title using Markdown with that exact linkUrl, for example [title](infumap://0123456789abcdef0123456789abcdef).
 Only use linkUrl values returned by tools; do not invent item links or expose raw item ids in visible text.
-Use get_fragment when a lexical_search snippet is truncated, ambiguous, or too small to answer from confidently.
-If lexical_search results are insufficient, say what is missing rather than inventing details.
+Use semantic_search when lexical_search misses items that are related by meaning rather than by exact words.
+Use get_fragment when a search snippet is truncated, ambiguous, or too small to answer from confidently.
+Use list_children to see what a page, table or composite contains, and get_item_metadata for an item's type, dates, location and size.
+If search results are insufficient, say what is missing rather than inventing details.
 Return a concise Markdown answer.";
+const CHAT_INFUMAP_WRITE_SYSTEM_PROMPT: &str = "\
+You can also change the workspace using create_note, move_item and add_to_page.
+Only use these tools when the user asks for a change. Each change is shown to the user, who must approve it before it is applied.
+If a change is declined, do not retry it; tell the user it was not made.";
 const CHAT_GENERAL_SYSTEM_PROMPT: &str = "\
 You are a helpful chat assistant.
 Return a concise Markdown answer.";
 const CHAT_CAPABILITY_INFUMAP_DATA: &str = "infumap_data";
-const NOTE_FLAG_HEADING3: i64 = 0x001;
-const NOTE_FLAG_HEADING1: i64 = 0x004;
-const NOTE_FLAG_HEADING2: i64 = 0x008;
-const NOTE_FLAG_BULLET1: i64 = 0x010;
-const NOTE_FLAG_CODE: i64 = 0x200;
-const NOTE_FLAG_HEADING4: i64 = 0x1000;
-const NOTE_FLAG_NUMBERED: i64 = 0x8000;
-const NOTE_INLINE_MARK_BOLD: i64 = 0x001;
-const NOTE_INLINE_MARK_ITALIC: i64 = 0x002;
+const CHAT_CAPABILITY_INFUMAP_WRITE: &str = "infumap_write";
 const TABLE_FLAG_SHOW_COL_HEADER: i64 = 0x001;
 const TABLE_FLAG_HIDE_TITLE: i64 = 0x002;
 
@@ -71,12 +82,20 @@ struct ChatRequest {
   user_text: String,
   #[serde(default)]
   capabilities: Vec<String>,
+  #[serde(rename = "conversationId", default)]
+  conversation_id: Option<Uid>,
+  #[serde(default)]
+  model: Option<String>,
 }
 
 impl ChatRequest {
   fn uses_infumap_data(&self) -> bool {
     self.capabilities.iter().any(|capability| capability == CHAT_CAPABILITY_INFUMAP_DATA)
   }
+
+  fn uses_infumap_write(&self) -> bool {
+    self.uses_infumap_data() && self.capabilities.iter().any(|capability| capability == CHAT_CAPABILITY_INFUMAP_WRITE)
+  }
 }
 
 #[derive(Serialize)]
@@ -93,6 +112,12 @@ struct ChatStreamEvent {
   items: Option<Value>,
   #[serde(skip_serializing_if = "Option::is_none")]
   message: Option<String>,
+  #[serde(rename = "confirmationId", skip_serializing_if = "Option::is_none")]
+  confirmation_id: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  details: Option<Value>,
+  #[serde(rename = "conversationId", skip_serializing_if = "Option::is_none")]
+  conversation_id: Option<Uid>,
 }
 
 impl ChatStreamEvent {
@@ -104,6 +129,39 @@ impl ChatStreamEvent {
       summary: None,
       items: None,
       message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
+    }
+  }
+
+  fn delta(text: &str) -> Self {
+    Self {
+      event_type: "delta".to_owned(),
+      text: Some(text.to_owned()),
+      name: None,
+      summary: None,
+      items: None,
+      message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
+    }
+  }
+
+  /// Streamed text turned out to precede tool calls rather than be the answer, so the client
+  /// should discard the deltas it has shown since the last reset.
+  fn delta_reset() -> Self {
+    Self {
+      event_type: "delta_reset".to_owned(),
+      text: None,
+      name: None,
+      summary: None,
+      items: None,
+      message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
     }
   }
 
@@ -115,6 +173,9 @@ impl ChatStreamEvent {
       summary: None,
       items: None,
       message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
     }
   }
 
@@ -126,10 +187,13 @@ impl ChatStreamEvent {
       summary: Some(summary.to_owned()),
       items: None,
       message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
     }
   }
 
-  fn final_items(items: Value) -> Self {
+  fn final_items(items: Value, conversation_id: &Uid) -> Self {
     Self {
       event_type: "final_items".to_owned(),
       text: None,
@@ -137,6 +201,9 @@ impl ChatStreamEvent {
       summary: None,
       items: Some(items),
       message: None,
+      confirmation_id: None,
+      details: None,
+      conversation_id: Some(conversation_id.clone()),
     }
   }
 
@@ -148,6 +215,23 @@ impl ChatStreamEvent {
       summary: None,
       items: None,
       message: Some(message.to_owned()),
+      confirmation_id: None,
+      details: None,
+      conversation_id: None,
+    }
+  }
+
+  fn confirmation_required(confirmation_id: &str, name: &str, summary: &str, details: Value) -> Self {
+    Self {
+      event_type: "confirmation_required".to_owned(),
+      text: None,
+      name: Some(name.to_owned()),
+      summary: Some(summary.to_owned()),
+      items: None,
+      message: None,
+      confirmation_id: Some(confirmation_id.to_owned()),
+      details: Some(details),
+      conversation_id: None,
     }
   }
 }
@@ -177,104 +261,106 @@ impl ChatProgressReporter {
   async fn tool_call_finished(&self, name: &str, summary: &str) {
     self.send(ChatStreamEvent::tool_call_finished(name, summary)).await;
   }
-}
 
-#[derive(Clone, Deserialize, Serialize)]
-struct LlamaChatMessage {
-  #[serde(default)]
-  role: String,
-  #[serde(skip_serializing_if = "Option::is_none")]
-  content: Option<String>,
-  #[serde(rename = "tool_call_id", skip_serializing_if = "Option::is_none")]
-  tool_call_id: Option<String>,
-  #[serde(skip_serializing_if = "Option::is_none")]
-  tool_calls: Option<Vec<LlamaToolCall>>,
-}
+  /// Ask the client to approve a workspace change and wait for the matching 'chat-confirm' command.
+  /// Anything other than an explicit approval (timeout, disconnect, decline) counts as declined.
+  async fn request_confirmation(&self, user_id: &Uid, name: &str, summary: &str, details: Value) -> bool {
+    if self.tx.is_closed() {
+      return false;
+    }
 
-impl LlamaChatMessage {
-  fn text(role: &str, content: String) -> Self {
-    Self { role: role.to_owned(), content: Some(content), tool_call_id: None, tool_calls: None }
-  }
+    let confirmation_id = new_uid();
+    let (confirm_tx, confirm_rx) = oneshot::channel::<bool>();
+    PENDING_CHAT_CONFIRMATIONS
+      .lock()
+      .unwrap()
+      .insert(confirmation_id.clone(), PendingChatConfirmation { user_id: user_id.clone(), tx: confirm_tx });
+    let _pending_guard = PendingChatConfirmationGuard { confirmation_id: confirmation_id.clone() };
+
+    self.send(ChatStreamEvent::confirmation_required(&confirmation_id, name, summary, details)).await;
+    let approved = tokio::select! {
+      result = tokio::time::timeout(Duration::from_secs(CHAT_CONFIRMATION_TIMEOUT_SECS), confirm_rx) => {
+        matches!(result, Ok(Ok(true)))
+      }
+      _ = self.tx.closed() => false,
+    };
 
-  fn tool(tool_call_id: String, content: String) -> Self {
-    Self { role: "tool".to_owned(), content: Some(content), tool_call_id: Some(tool_call_id), tool_calls: None }
+    approved
   }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-struct LlamaToolCall {
-  #[serde(default)]
-  id: String,
-  #[serde(rename = "type", default = "default_llama_tool_call_type")]
-  tool_type: String,
-  function: LlamaToolCallFunction,
+/// Removes a pending confirmation however the wait ends, including when the chat task is
+/// cancelled because the client disconnected.
+struct PendingChatConfirmationGuard {
+  confirmation_id: String,
 }
 
-fn default_llama_tool_call_type() -> String {
-  "function".to_owned()
+impl Drop for PendingChatConfirmationGuard {
+  fn drop(&mut self) {
+    if let Ok(mut pending) = PENDING_CHAT_CONFIRMATIONS.lock() {
+      pending.remove(&self.confirmation_id);
+    }
+  }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-struct LlamaToolCallFunction {
-  name: String,
-  #[serde(default)]
-  arguments: Value,
+struct PendingChatConfirmation {
+  user_id: Uid,
+  tx: oneshot::Sender<bool>,
 }
 
-#[derive(Clone, Serialize)]
-struct LlamaToolSpec {
-  #[serde(rename = "type")]
-  tool_type: String,
-  function: LlamaToolFunctionSpec,
-}
+static PENDING_CHAT_CONFIRMATIONS: Lazy<std::sync::Mutex<HashMap<String, PendingChatConfirmation>>> =
+  Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
 
-#[derive(Clone, Serialize)]
-struct LlamaToolFunctionSpec {
-  name: String,
-  description: String,
-  parameters: Value,
+#[derive(Deserialize)]
+struct ChatConfirmRequest {
+  #[serde(rename = "confirmationId")]
+  confirmation_id: String,
+  approved: bool,
 }
 
-#[derive(Serialize)]
-struct LlamaChatCompletionRequest {
-  model: String,
-  messages: Vec<LlamaChatMessage>,
-  stream: bool,
-  #[serde(skip_serializing_if = "Vec::is_empty")]
-  tools: Vec<LlamaToolSpec>,
+#[derive(Deserialize)]
+struct ChatSearchToolArguments {
+  text: Option<String>,
+  query: Option<String>,
+  #[serde(rename = "pageId")]
+  page_id: Option<Uid>,
+  #[serde(rename = "numResults")]
+  num_results: Option<i64>,
+  #[serde(rename = "pageNum")]
+  page_num: Option<i64>,
 }
 
 #[derive(Deserialize)]
-struct LlamaChatCompletionResponse {
-  choices: Vec<LlamaChatCompletionChoice>,
-  usage: Option<LlamaChatCompletionUsage>,
+struct ChatItemToolArguments {
+  #[serde(rename = "itemId")]
+  item_id: Option<Uid>,
+  #[serde(rename = "numResults")]
+  num_results: Option<i64>,
+  #[serde(rename = "pageNum")]
+  page_num: Option<i64>,
 }
 
 #[derive(Deserialize)]
-struct LlamaChatCompletionChoice {
-  message: LlamaChatMessage,
+struct ChatCreateNoteToolArguments {
+  title: Option<String>,
+  #[serde(rename = "parentId")]
+  parent_id: Option<Uid>,
 }
 
-#[derive(Deserialize, Serialize)]
-struct LlamaChatCompletionUsage {
-  #[serde(rename = "prompt_tokens")]
-  prompt_tokens: Option<i64>,
-  #[serde(rename = "completion_tokens")]
-  completion_tokens: Option<i64>,
-  #[serde(rename = "total_tokens")]
-  total_tokens: Option<i64>,
+#[derive(Deserialize)]
+struct ChatMoveItemToolArguments {
+  #[serde(rename = "itemId")]
+  item_id: Option<Uid>,
+  #[serde(rename = "newParentId")]
+  new_parent_id: Option<Uid>,
 }
 
 #[derive(Deserialize)]
-struct ChatLexicalSearchToolArguments {
-  text: Option<String>,
-  query: Option<String>,
+struct ChatAddToPageToolArguments {
+  #[serde(rename = "itemId")]
+  item_id: Option<Uid>,
   #[serde(rename = "pageId")]
   page_id: Option<Uid>,
-  #[serde(rename = "numResults")]
-  num_results: Option<i64>,
-  #[serde(rename = "pageNum")]
-  page_num: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -289,6 +375,7 @@ struct ChatFragmentToolArguments {
 pub(super) async fn handle_chat(
   config: Arc<Config>,
   db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
   json_data: &str,
   session_maybe: &Option<Session>,
 ) -> InfuResult<Option<String>> {
@@ -301,14 +388,118 @@ pub(super) async fn handle_chat(
 
   let request: ChatRequest =
     serde_json::from_str(json_data).map_err(|e| format!("Could not parse chat request: {}", e))?;
-  let assistant_text = run_chat_with_tools(config, db, session, &request).await?;
+  let outcome = run_chat_with_tools(config, db, object_store, session, &request).await?;
+  let mut response = chat_response_items_json(&session.user_id, &outcome.assistant_text);
+  response["conversationId"] = Value::String(outcome.conversation_id);
+
+  Ok(Some(response.to_string()))
+}
+
+#[derive(Deserialize)]
+struct ListChatConversationsRequest {
+  #[serde(default)]
+  query: Option<String>,
+}
+
+pub(super) async fn handle_list_chat_conversations(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    Some(session) => session,
+    None => {
+      return Err(format!("Session is required to list chat conversations.").into());
+    }
+  };
+
+  let request: ListChatConversationsRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse list chat conversations request: {}", e))?;
+  let (data_dir, object_encryption_key) = chat_storage_for_user(db, &session.user_id).await?;
+  let conversations =
+    list_chat_conversations(&data_dir, &session.user_id, &object_encryption_key, request.query.as_deref()).await?;
+
+  debug!("Executed 'chat-conversations' command for user '{}'.", session.user_id);
+
+  Ok(Some(serde_json::json!({ "conversations": conversations }).to_string()))
+}
+
+#[derive(Deserialize)]
+struct GetChatConversationRequest {
+  id: Uid,
+}
+
+pub(super) async fn handle_get_chat_conversation(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    Some(session) => session,
+    None => {
+      return Err(format!("Session is required to get a chat conversation.").into());
+    }
+  };
+
+  let request: GetChatConversationRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse get chat conversation request: {}", e))?;
+  let (data_dir, object_encryption_key) = chat_storage_for_user(db, &session.user_id).await?;
+  let conversation = read_chat_conversation(&data_dir, &session.user_id, &object_encryption_key, &request.id)
+    .await?
+    .ok_or(format!("Chat conversation '{}' not found.", request.id))?;
+
+  debug!("Executed 'get-chat-conversation' command for user '{}'.", session.user_id);
+
+  Ok(Some(serde_json::to_string(&conversation)?))
+}
+
+pub(super) async fn handle_list_chat_models(
+  config: Arc<Config>,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  if session_maybe.is_none() {
+    return Err(format!("Session is required to list chat models.").into());
+  }
+
+  let models = configured_llm_model_summaries(config.as_ref())?;
+  let default_model = default_llm_model_name(config.as_ref())?;
+
+  Ok(Some(serde_json::json!({ "models": models, "defaultModel": default_model }).to_string()))
+}
+
+pub(super) async fn handle_chat_confirm(
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    Some(session) => session,
+    None => {
+      return Err(format!("Session is required to confirm a chat action.").into());
+    }
+  };
+
+  let request: ChatConfirmRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse chat confirm request: {}", e))?;
+
+  let pending = {
+    let mut pending_confirmations = PENDING_CHAT_CONFIRMATIONS.lock().unwrap();
+    match pending_confirmations.get(&request.confirmation_id) {
+      Some(pending) if pending.user_id == session.user_id => pending_confirmations.remove(&request.confirmation_id),
+      _ => None,
+    }
+  };
+  let pending = pending.ok_or(format!("Chat confirmation '{}' was not found.", request.confirmation_id))?;
+  let _ = pending.tx.send(request.approved);
+
+  debug!("Executed 'chat-confirm' command for user '{}' (approved: {}).", session.user_id, request.approved);
 
-  Ok(Some(chat_response_items_json(&session.user_id, &assistant_text).to_string()))
+  Ok(None)
 }
 
 pub async fn serve_chat_stream_route(
   config: Arc<Config>,
   db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: &Arc<object::ObjectStore>,
   request: Request<hyper::body::Incoming>,
 ) -> Response<BoxBody<Bytes, hyper::Error>> {
   if request.method() == "OPTIONS" {
@@ -336,16 +527,25 @@ pub async fn serve_chat_stream_route(
   let progress = ChatProgressReporter { tx: tx.clone() };
   let user_id = session.user_id.clone();
   let db = db.clone();
+  let object_store = object_store.clone();
 
   tokio::spawn(async move {
     progress.status("Preparing request").await;
-    let result = run_chat_with_tools_with_progress(config, &db, &session, &request, Some(&progress)).await;
+    // The response body owns the receiver, so a closed channel means the client went away. Dropping
+    // the chat future then aborts any in-flight model request; the unfinished turn is not saved.
+    let result = tokio::select! {
+      result = run_chat_with_tools_with_progress(config, &db, object_store, &session, &request, Some(&progress)) => result,
+      _ = tx.closed() => {
+        debug!("Chat stream client for user '{}' disconnected, cancelling chat request.", user_id);
+        return;
+      }
+    };
     match result {
-      Ok(assistant_text) => {
+      Ok(outcome) => {
         progress.status("Preparing response").await;
-        let response = chat_response_items_json(&user_id, &assistant_text);
+        let response = chat_response_items_json(&user_id, &outcome.assistant_text);
         let items = response.get("items").cloned().unwrap_or_else(|| Value::Array(Vec::new()));
-        progress.send(ChatStreamEvent::final_items(items)).await;
+        progress.send(ChatStreamEvent::final_items(items, &outcome.conversation_id)).await;
       }
       Err(e) => {
         warn!("An error occurred servicing a streaming chat request for user '{}': {}.", user_id, e);
@@ -378,27 +578,6 @@ fn chat_stream_response(
     .unwrap_or_else(|_| Response::builder().status(500).body(empty_body()).unwrap())
 }
 
-fn configured_llama_chat_url(config: &Config) -> InfuResult<reqwest::Url> {
-  let raw_url = config.get_string(CONFIG_LLAMA_SERVER_URL).map_err(|e| e.to_string())?;
-  let trimmed_url = raw_url.trim();
-  if trimmed_url.is_empty() {
-    return Err(format!("{} must be configured to use Chat.", CONFIG_LLAMA_SERVER_URL).into());
-  }
-
-  let endpoint_path = "/v1/chat/completions";
-  let parsed = reqwest::Url::parse(trimmed_url)
-    .map_err(|e| format!("Could not parse {} '{}': {}", CONFIG_LLAMA_SERVER_URL, trimmed_url, e))?;
-  if parsed.path().trim_end_matches('/').ends_with(endpoint_path) {
-    return Ok(parsed);
-  }
-
-  let base_url = reqwest::Url::parse(&format!("{}/", trimmed_url.trim_end_matches('/')))
-    .map_err(|e| format!("Could not parse {} '{}': {}", CONFIG_LLAMA_SERVER_URL, trimmed_url, e))?;
-  base_url
-    .join("v1/chat/completions")
-    .map_err(|e| format!("Could not build llama-server chat endpoint from '{}': {}", trimmed_url, e).into())
-}
-
 fn chat_item_id(item: &Value) -> Option<&str> {
   item.get("id").and_then(Value::as_str).filter(|id| !id.is_empty())
 }
@@ -466,7 +645,7 @@ fn clamp_text_chars(text: &str, max_chars: usize) -> (String, bool) {
   (truncated, chars.next().is_some())
 }
 
-fn clamp_message_content(message: &mut LlamaChatMessage, max_chars: usize) {
+fn clamp_message_content(message: &mut LlmChatMessage, max_chars: usize) {
   let Some(content) = message.content.as_mut() else {
     return;
   };
@@ -474,18 +653,19 @@ fn clamp_message_content(message: &mut LlamaChatMessage, max_chars: usize) {
   *content = clamped;
 }
 
-fn message_content_chars(message: &LlamaChatMessage) -> usize {
+fn message_content_chars(message: &LlmChatMessage) -> usize {
   message.content.as_deref().map(text_char_count).unwrap_or(0)
 }
 
-fn total_message_content_chars(messages: &[LlamaChatMessage]) -> usize {
+fn total_message_content_chars(messages: &[LlmChatMessage]) -> usize {
   messages.iter().map(message_content_chars).sum()
 }
 
 fn trim_chat_messages_for_prompt(
-  previous_messages: Vec<LlamaChatMessage>,
+  previous_messages: Vec<LlmChatMessage>,
   current_user_text: String,
-) -> Vec<LlamaChatMessage> {
+  max_total_chars: usize,
+) -> Vec<LlmChatMessage> {
   let skip_count = previous_messages.len().saturating_sub(CHAT_HISTORY_MAX_PREVIOUS_MESSAGES);
   let mut messages = previous_messages.into_iter().skip(skip_count).collect::<Vec<_>>();
   for message in &mut messages {
@@ -495,17 +675,41 @@ fn trim_chat_messages_for_prompt(
   let current_user_text = current_user_text.trim();
   if !current_user_text.is_empty() {
     let (content, _) = clamp_text_chars(current_user_text, CHAT_HISTORY_MAX_MESSAGE_CHARS);
-    messages.push(LlamaChatMessage::text("user", content));
+    messages.push(LlmChatMessage::text("user", content));
   }
 
-  while messages.len() > 1 && total_message_content_chars(&messages) > CHAT_HISTORY_MAX_TOTAL_CHARS {
+  while messages.len() > 1 && total_message_content_chars(&messages) > max_total_chars {
     messages.remove(0);
   }
 
   messages
 }
 
-fn llama_messages_from_chat_request(request: &ChatRequest) -> Vec<LlamaChatMessage> {
+/// History budget for a model: roughly a quarter of its context window at ~4 chars per token,
+/// leaving room for the system prompt, tool schemas, tool results and the answer.
+fn chat_history_max_total_chars(model: &LlmModel) -> usize {
+  match model.settings.context_window_tokens {
+    Some(tokens) => (tokens as usize).max(CHAT_HISTORY_MAX_MESSAGE_CHARS),
+    None => CHAT_HISTORY_MAX_TOTAL_CHARS,
+  }
+}
+
+fn llm_messages_from_chat_request(
+  request: &ChatRequest,
+  conversation: &ChatConversation,
+  max_total_chars: usize,
+) -> Vec<LlmChatMessage> {
+  // A resumed conversation supplies its own history; context items are only used to seed new ones.
+  if !conversation.messages.is_empty() {
+    let messages = conversation
+      .messages
+      .iter()
+      .filter(|message| matches!(message.role.as_str(), "user" | "assistant"))
+      .map(|message| LlmChatMessage::text(&message.role, message.text.clone()))
+      .collect::<Vec<_>>();
+    return trim_chat_messages_for_prompt(messages, request.user_text.clone(), max_total_chars);
+  }
+
   let mut ids = HashSet::new();
   let mut items_by_id: HashMap<String, &Value> = HashMap::new();
   for item in &request.context_items {
@@ -542,88 +746,19 @@ fn llama_messages_from_chat_request(request: &ChatRequest) -> Vec<LlamaChatMessa
     collect_chat_text(item_id, &items_by_id, &children_by_parent_id, &mut visited, &mut text_parts);
     let content = text_parts.join("\n\n").trim().to_owned();
     if !content.is_empty() {
-      messages.push(LlamaChatMessage::text(role, content));
-    }
-  }
-
-  trim_chat_messages_for_prompt(messages, request.user_text.clone())
-}
-
-fn truncate_for_error(text: &str, max_chars: usize) -> String {
-  let mut chars = text.chars();
-  let truncated: String = chars.by_ref().take(max_chars).collect();
-  if chars.next().is_some() { format!("{}...", truncated) } else { truncated }
-}
-
-fn error_chain_for_log(error: &dyn std::error::Error) -> String {
-  let mut result = error.to_string();
-  let mut source_maybe = error.source();
-  while let Some(source) = source_maybe {
-    result.push_str(": ");
-    result.push_str(&source.to_string());
-    source_maybe = source.source();
-  }
-  result
-}
-
-fn reqwest_error_for_log(error: &reqwest::Error) -> String {
-  let mut kinds = Vec::new();
-  if error.is_timeout() {
-    kinds.push("timeout");
-  }
-  if error.is_connect() {
-    kinds.push("connect");
-  }
-  if error.is_builder() {
-    kinds.push("builder");
-  }
-  if error.is_redirect() {
-    kinds.push("redirect");
-  }
-  if error.is_status() {
-    kinds.push("status");
-  }
-  if error.is_body() {
-    kinds.push("body");
-  }
-  if error.is_decode() {
-    kinds.push("decode");
-  }
-  let kind_suffix = if kinds.is_empty() { "".to_owned() } else { format!(" [kind={}]", kinds.join(",")) };
-  format!("{}{}", error_chain_for_log(error), kind_suffix)
-}
-
-fn reset_llm_log() {
-  if let Err(e) = std::fs::write(LLM_LOG_PATH, "") {
-    warn!("Could not reset LLM log '{}': {}", LLM_LOG_PATH, e);
-  }
-}
-
-fn append_llm_log_section(title: &str, body: &str) {
-  let body = pretty_llm_log_body(body);
-  match std::fs::OpenOptions::new().create(true).append(true).open(LLM_LOG_PATH) {
-    Ok(mut file) => {
-      if let Err(e) = writeln!(file, "\n===== {} =====\n{}", title, body) {
-        warn!("Could not write LLM log '{}': {}", LLM_LOG_PATH, e);
-      }
+      messages.push(LlmChatMessage::text(role, content));
     }
-    Err(e) => warn!("Could not open LLM log '{}': {}", LLM_LOG_PATH, e),
   }
-}
-
-fn pretty_llm_log_body(body: &str) -> String {
-  serde_json::from_str::<Value>(body)
-    .and_then(|value| serde_json::to_string_pretty(&value))
-    .unwrap_or_else(|_| body.to_owned())
-}
 
-fn append_llm_json_log_section<T: Serialize>(title: &str, value: &T) {
-  let body =
-    serde_json::to_string_pretty(value).unwrap_or_else(|e| format!("Could not serialize LLM log value: {}", e));
-  append_llm_log_section(title, &body);
+  trim_chat_messages_for_prompt(messages, request.user_text.clone(), max_total_chars)
 }
 
-fn append_llm_request_metrics_log(llm_turn: usize, messages: &[LlamaChatMessage], tools: &[LlamaToolSpec]) {
+async fn append_llm_request_metrics_log(
+  transcript: &ChatTranscript,
+  llm_turn: usize,
+  messages: &[LlmChatMessage],
+  tools: &[LlmToolSpec],
+) {
   let content_chars = total_message_content_chars(messages);
   let tool_schema_chars = serde_json::to_string(tools).map(|text| text_char_count(&text)).unwrap_or(0);
   let total_request_chars = content_chars + tool_schema_chars;
@@ -639,13 +774,13 @@ fn append_llm_request_metrics_log(llm_turn: usize, messages: &[LlamaChatMessage]
     "approxRequestTokens": (total_request_chars + 3) / 4,
     "toolResultChars": tool_result_chars
   });
-  append_llm_json_log_section(&format!("LLM REQUEST METRICS {}", llm_turn), &metrics);
+  transcript.append_json_section(&format!("LLM REQUEST METRICS {}", llm_turn), &metrics).await;
 }
 
-fn lexical_search_tool_spec() -> LlamaToolSpec {
-  LlamaToolSpec {
+fn lexical_search_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
     tool_type: "function".to_owned(),
-    function: LlamaToolFunctionSpec {
+    function: LlmToolFunctionSpec {
       name: "lexical_search".to_owned(),
       description: "Search workspace titles and document text using lexical matching. Use this as the default lookup and search tool.".to_owned(),
       parameters: serde_json::json!({
@@ -678,10 +813,46 @@ fn lexical_search_tool_spec() -> LlamaToolSpec {
   }
 }
 
-fn get_fragment_tool_spec() -> LlamaToolSpec {
-  LlamaToolSpec {
+fn semantic_search_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "semantic_search".to_owned(),
+      description: "Search the workspace by meaning using the vector index. Use this when lexical_search misses related items that use different words.".to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "text": {
+            "type": "string",
+            "description": "Natural language description of what to find."
+          },
+          "pageId": {
+            "type": ["string", "null"],
+            "description": "Optional workspace page id to search within. Use null or omit it to search the user's home scope."
+          },
+          "numResults": {
+            "type": "integer",
+            "minimum": 1,
+            "maximum": CHAT_LEXICAL_SEARCH_TOOL_MAX_NUM_RESULTS,
+            "description": "Maximum number of search results to return."
+          },
+          "pageNum": {
+            "type": "integer",
+            "minimum": 1,
+            "description": "Optional one-based page of search results."
+          }
+        },
+        "required": ["text"],
+        "additionalProperties": false
+      }),
+    },
+  }
+}
+
+fn get_fragment_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
     tool_type: "function".to_owned(),
-    function: LlamaToolFunctionSpec {
+    function: LlmToolFunctionSpec {
       name: "get_fragment".to_owned(),
       description:
         "Fetch bounded full text for a specific lexical_search result fragment by item id and fragment ordinal."
@@ -706,45 +877,307 @@ fn get_fragment_tool_spec() -> LlamaToolSpec {
   }
 }
 
-async fn run_chat_with_tools(
-  config: Arc<Config>,
-  db: &Arc<tokio::sync::Mutex<Db>>,
-  session: &Session,
-  request: &ChatRequest,
-) -> InfuResult<String> {
-  run_chat_with_tools_with_progress(config, db, session, request, None).await
+fn list_children_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "list_children".to_owned(),
+      description: "List the items directly inside a page, table or composite, in display order.".to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "itemId": {
+            "type": "string",
+            "description": "Id of the page, table or composite to list."
+          },
+          "numResults": {
+            "type": "integer",
+            "minimum": 1,
+            "maximum": CHAT_LIST_CHILDREN_TOOL_MAX_NUM_RESULTS,
+            "description": "Maximum number of children to return."
+          },
+          "pageNum": {
+            "type": "integer",
+            "minimum": 1,
+            "description": "Optional one-based page of children."
+          }
+        },
+        "required": ["itemId"],
+        "additionalProperties": false
+      }),
+    },
+  }
 }
 
-async fn run_chat_with_tools_with_progress(
-  config: Arc<Config>,
-  db: &Arc<tokio::sync::Mutex<Db>>,
-  session: &Session,
-  request: &ChatRequest,
-  progress: Option<&ChatProgressReporter>,
-) -> InfuResult<String> {
-  reset_llm_log();
-
-  let mut messages = llama_messages_from_chat_request(request);
-  if messages.is_empty() {
-    return Err("Chat request did not contain any message text.".into());
+fn get_item_metadata_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "get_item_metadata".to_owned(),
+      description: "Fetch an item's type, title, location path, dates, file details and child counts.".to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "itemId": {
+            "type": "string",
+            "description": "Id of the item."
+          }
+        },
+        "required": ["itemId"],
+        "additionalProperties": false
+      }),
+    },
   }
-  let uses_infumap_data = request.uses_infumap_data();
-  let system_prompt = if uses_infumap_data { CHAT_INFUMAP_SYSTEM_PROMPT } else { CHAT_GENERAL_SYSTEM_PROMPT };
-  messages.insert(0, LlamaChatMessage::text("system", system_prompt.to_owned()));
+}
+
+fn create_note_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "create_note".to_owned(),
+      description: "Create a new note at the end of a page, table or composite. The user must approve the change."
+        .to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "title": {
+            "type": "string",
+            "description": "Text of the note."
+          },
+          "parentId": {
+            "type": ["string", "null"],
+            "description": "Id of the page, table or composite to add the note to. Use null or omit it for the user's home page."
+          }
+        },
+        "required": ["title"],
+        "additionalProperties": false
+      }),
+    },
+  }
+}
+
+fn move_item_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "move_item".to_owned(),
+      description: "Move an item to the end of a different page, table or composite. The user must approve the change."
+        .to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "itemId": {
+            "type": "string",
+            "description": "Id of the item to move."
+          },
+          "newParentId": {
+            "type": "string",
+            "description": "Id of the destination page, table or composite."
+          }
+        },
+        "required": ["itemId", "newParentId"],
+        "additionalProperties": false
+      }),
+    },
+  }
+}
+
+fn add_to_page_tool_spec() -> LlmToolSpec {
+  LlmToolSpec {
+    tool_type: "function".to_owned(),
+    function: LlmToolFunctionSpec {
+      name: "add_to_page".to_owned(),
+      description:
+        "Add a link to an existing item on a page, leaving the original where it is. The user must approve the change."
+          .to_owned(),
+      parameters: serde_json::json!({
+        "type": "object",
+        "properties": {
+          "itemId": {
+            "type": "string",
+            "description": "Id of the item to link to."
+          },
+          "pageId": {
+            "type": "string",
+            "description": "Id of the page to add the link to."
+          }
+        },
+        "required": ["itemId", "pageId"],
+        "additionalProperties": false
+      }),
+    },
+  }
+}
+
+async fn run_chat_with_tools(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  session: &Session,
+  request: &ChatRequest,
+) -> InfuResult<ChatRunOutcome> {
+  run_chat_with_tools_with_progress(config, db, object_store, session, request, None).await
+}
+
+/// The model named by the request, otherwise the model last used in the conversation if it is still
+/// configured. None selects the configured default.
+fn chat_model_name_for_conversation(
+  config: &Config,
+  request: &ChatRequest,
+  conversation: &ChatConversation,
+) -> InfuResult<Option<String>> {
+  if let Some(model_name) = request.model.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+    return Ok(Some(model_name.to_owned()));
+  }
+  let configured_models = configured_llm_model_settings(config)?;
+  Ok(
+    conversation
+      .messages
+      .iter()
+      .rev()
+      .filter_map(|message| message.model.as_deref())
+      .find(|model_name| configured_models.contains_key(*model_name))
+      .map(str::to_owned),
+  )
+}
+
+struct ChatRunOutcome {
+  conversation_id: Uid,
+  assistant_text: String,
+}
+
+/// Runs one chat turn against a new or resumed conversation and persists both sides of the exchange.
+async fn run_chat_with_tools_with_progress(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  session: &Session,
+  request: &ChatRequest,
+  progress: Option<&ChatProgressReporter>,
+) -> InfuResult<ChatRunOutcome> {
+  let (data_dir, object_encryption_key) = chat_storage_for_user(db, &session.user_id).await?;
+  let started_at = unix_now_secs_u64().unwrap() as i64;
+  let conversation = match request.conversation_id.as_deref() {
+    Some(conversation_id) => {
+      read_chat_conversation(&data_dir, &session.user_id, &object_encryption_key, conversation_id)
+        .await?
+        .ok_or(format!("Chat conversation '{}' not found.", conversation_id))?
+    }
+    None => ChatConversation::new(new_uid(), started_at),
+  };
+  let model_name = chat_model_name_for_conversation(config.as_ref(), request, &conversation)?;
+  let model = open_llm_model(config.as_ref(), model_name.as_deref())?;
+  let transcript = ChatTranscript::for_user(config.as_ref(), &data_dir, &session.user_id);
+  transcript.append_section("CHAT", &format!("conversation {} model {}", conversation.id, model.name)).await;
+
+  let messages = llm_messages_from_chat_request(request, &conversation, chat_history_max_total_chars(&model));
+  if messages.is_empty() {
+    return Err("Chat request did not contain any message text.".into());
+  }
+  let (assistant_text, tool_calls) =
+    run_chat_tool_loop(config, db, object_store, session, request, &model, messages, &transcript, progress).await?;
+
+  let mut new_messages = Vec::new();
+  let user_text = request.user_text.trim();
+  if !user_text.is_empty() {
+    new_messages.push(ChatConversationMessage {
+      role: "user".to_owned(),
+      text: user_text.to_owned(),
+      created_at_unix_secs: started_at,
+      model: None,
+      tool_calls: Vec::new(),
+      cited_item_ids: Vec::new(),
+    });
+  }
+  new_messages.push(ChatConversationMessage {
+    role: "assistant".to_owned(),
+    text: assistant_text.clone(),
+    created_at_unix_secs: unix_now_secs_u64().unwrap() as i64,
+    model: Some(model.name.clone()),
+    tool_calls,
+    cited_item_ids: chat_cited_item_ids(&assistant_text),
+  });
+  let conversation_id = conversation.id.clone();
+  append_chat_conversation_messages(&data_dir, &session.user_id, &object_encryption_key, conversation, new_messages)
+    .await?;
+
+  Ok(ChatRunOutcome { conversation_id, assistant_text })
+}
+
+/// The data dir, and the key conversations of the user are encrypted with.
+async fn chat_storage_for_user(db: &Arc<tokio::sync::Mutex<Db>>, user_id: &str) -> InfuResult<(String, String)> {
+  let db = db.lock().await;
+  let user = db.user.get(&user_id.to_owned()).ok_or(format!("User '{}' not found.", user_id))?;
+  Ok((db.item.data_dir().to_owned(), user.object_encryption_key.clone()))
+}
 
-  let tools = if uses_infumap_data {
-    vec![lexical_search_tool_spec(), get_fragment_tool_spec()]
+async fn run_chat_tool_loop(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  session: &Session,
+  request: &ChatRequest,
+  model: &LlmModel,
+  mut messages: Vec<LlmChatMessage>,
+  transcript: &ChatTranscript,
+  progress: Option<&ChatProgressReporter>,
+) -> InfuResult<(String, Vec<ChatConversationToolCall>)> {
+  let uses_infumap_data = request.uses_infumap_data();
+  if uses_infumap_data && !model.supports_tools() {
+    return Err(
+      format!("Chat model '{}' does not support tools, which Infumap data chat requires.", model.name).into(),
+    );
+  }
+  // Changes need an explicit approval round-trip, which only the streaming route can provide.
+  let uses_infumap_write = request.uses_infumap_write() && progress.is_some();
+  let system_prompt = if uses_infumap_write {
+    format!("{}\n\n{}", CHAT_INFUMAP_SYSTEM_PROMPT, CHAT_INFUMAP_WRITE_SYSTEM_PROMPT)
+  } else if uses_infumap_data {
+    CHAT_INFUMAP_SYSTEM_PROMPT.to_owned()
+  } else {
+    CHAT_GENERAL_SYSTEM_PROMPT.to_owned()
+  };
+  messages.insert(0, LlmChatMessage::text("system", system_prompt));
+
+  let mut tools = if uses_infumap_data {
+    vec![
+      lexical_search_tool_spec(),
+      semantic_search_tool_spec(),
+      get_fragment_tool_spec(),
+      list_children_tool_spec(),
+      get_item_metadata_tool_spec(),
+    ]
   } else {
     Vec::new()
   };
+  if uses_infumap_write {
+    tools.extend([create_note_tool_spec(), move_item_tool_spec(), add_to_page_tool_spec()]);
+  }
   let mut llm_turn = 1usize;
   let mut tool_rounds = 0usize;
+  let mut recorded_tool_calls = Vec::new();
 
   loop {
     if let Some(progress) = progress {
       progress.status("Asking model").await;
     }
-    let mut message = llama_chat_completion(config.as_ref(), &messages, &tools, llm_turn).await?;
+    append_llm_request_metrics_log(transcript, llm_turn, &messages, &tools).await;
+    let mut streamed_delta = false;
+    let mut message = match progress {
+      Some(progress) => {
+        let (delta_tx, mut delta_rx) = mpsc::unbounded_channel::<String>();
+        let completion = model.chat_completion_streaming(transcript, &messages, &tools, llm_turn, delta_tx);
+        let forward_deltas = async {
+          while let Some(delta) = delta_rx.recv().await {
+            streamed_delta = true;
+            progress.send(ChatStreamEvent::delta(&delta)).await;
+          }
+        };
+        let (message, _) = tokio::join!(completion, forward_deltas);
+        message?
+      }
+      None => model.chat_completion(transcript, &messages, &tools, llm_turn).await?,
+    };
     llm_turn += 1;
     if message.role.trim().is_empty() {
       message.role = "assistant".to_owned();
@@ -759,31 +1192,56 @@ async fn run_chat_with_tools_with_progress(
         return Err(format!("Chat tool loop exceeded maximum tool rounds ({CHAT_MAX_TOOL_ROUNDS}).").into());
       }
 
+      if streamed_delta && let Some(progress) = progress {
+        progress.send(ChatStreamEvent::delta_reset()).await;
+      }
       tool_rounds += 1;
       messages.push(message);
       for tool_call in tool_calls {
         if let Some(progress) = progress {
           progress.tool_call_started(&tool_call.function.name).await;
         }
-        let tool_result = execute_chat_tool_call(db, session, &tool_call).await?;
+        let tool_result = if tools.iter().any(|tool| tool.function.name == tool_call.function.name) {
+          execute_chat_tool_call(config.clone(), db, &object_store, session, &tool_call, progress).await?
+        } else {
+          tool_error_json(&format!("Tool '{}' is not available.", tool_call.function.name))
+        };
         if let Some(progress) = progress {
           progress.tool_call_finished(&tool_call.function.name, "Done").await;
         }
-        append_llm_log_section(&format!("TOOL RESULT {} {}", tool_call.function.name, tool_call.id), &tool_result);
-        messages.push(LlamaChatMessage::tool(tool_call.id.clone(), tool_result));
+        transcript
+          .append_body_section(&format!("TOOL RESULT {} {}", tool_call.function.name, tool_call.id), &tool_result)
+          .await;
+        recorded_tool_calls.push(ChatConversationToolCall {
+          name: tool_call.function.name.clone(),
+          arguments: tool_call_arguments_value(&tool_call).unwrap_or_else(|_| tool_call.function.arguments.clone()),
+        });
+        messages.push(LlmChatMessage::tool(tool_call.id.clone(), tool_result));
       }
       continue;
     }
 
     let content = message.content.unwrap_or_default().trim().to_owned();
     if content.is_empty() {
-      return Err("llama-server returned an empty chat response.".into());
+      return Err(format!("Chat model '{}' returned an empty chat response.", model.name).into());
     }
-    return Ok(content);
+    return Ok((content, recorded_tool_calls));
   }
 }
 
-fn normalize_tool_calls(message: &mut LlamaChatMessage, tool_round: usize) -> Vec<LlamaToolCall> {
+/// Item ids linked from assistant text via infumap:// URLs, in first-mention order.
+fn chat_cited_item_ids(text: &str) -> Vec<Uid> {
+  let mut cited_item_ids: Vec<Uid> = Vec::new();
+  for (index, _) in text.match_indices("infumap://") {
+    let candidate = text[index + "infumap://".len()..].chars().take(32).collect::<String>();
+    if is_uid(&candidate) && !cited_item_ids.contains(&candidate) {
+      cited_item_ids.push(candidate);
+    }
+  }
+  cited_item_ids
+}
+
+fn normalize_tool_calls(message: &mut LlmChatMessage, tool_round: usize) -> Vec<LlmToolCall> {
   let Some(tool_calls) = message.tool_calls.as_mut() else {
     return Vec::new();
   };
@@ -801,34 +1259,53 @@ fn normalize_tool_calls(message: &mut LlamaChatMessage, tool_round: usize) -> Ve
 }
 
 async fn execute_chat_tool_call(
+  config: Arc<Config>,
   db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: &Arc<object::ObjectStore>,
   session: &Session,
-  tool_call: &LlamaToolCall,
+  tool_call: &LlmToolCall,
+  progress: Option<&ChatProgressReporter>,
 ) -> InfuResult<String> {
   match tool_call.function.name.as_str() {
-    "lexical_search" => execute_lexical_search_tool_call(db, session, tool_call).await,
+    "lexical_search" => execute_search_tool_call(None, db, session, tool_call).await,
+    "semantic_search" => execute_search_tool_call(Some(config), db, session, tool_call).await,
     "get_fragment" => execute_get_fragment_tool_call(db, session, tool_call).await,
+    "list_children" => execute_list_children_tool_call(db, session, tool_call).await,
+    "get_item_metadata" => execute_get_item_metadata_tool_call(db, session, tool_call).await,
+    "create_note" | "move_item" | "add_to_page" => {
+      let Some(progress) = progress else {
+        return Ok(tool_error_json("Workspace changes require a streaming chat session."));
+      };
+      match tool_call.function.name.as_str() {
+        "create_note" => execute_create_note_tool_call(db, object_store, session, tool_call, progress).await,
+        "move_item" => execute_move_item_tool_call(db, session, tool_call, progress).await,
+        _ => execute_add_to_page_tool_call(db, object_store, session, tool_call, progress).await,
+      }
+    }
     name => Ok(tool_error_json(&format!("Unknown tool '{name}'."))),
   }
 }
 
-async fn execute_lexical_search_tool_call(
+/// Runs lexical_search, or semantic_search when a config is supplied for the text embedding service.
+async fn execute_search_tool_call(
+  config: Option<Arc<Config>>,
   db: &Arc<tokio::sync::Mutex<Db>>,
   session: &Session,
-  tool_call: &LlamaToolCall,
+  tool_call: &LlmToolCall,
 ) -> InfuResult<String> {
+  let tool_name = tool_call.function.name.as_str();
   let arguments = match tool_call_arguments_value(tool_call) {
     Ok(arguments) => arguments,
     Err(e) => return Ok(tool_error_json(&e.to_string())),
   };
-  let arguments: ChatLexicalSearchToolArguments = match serde_json::from_value(arguments) {
+  let arguments: ChatSearchToolArguments = match serde_json::from_value(arguments) {
     Ok(arguments) => arguments,
-    Err(e) => return Ok(tool_error_json(&format!("Could not parse lexical_search tool arguments: {}", e))),
+    Err(e) => return Ok(tool_error_json(&format!("Could not parse {} tool arguments: {}", tool_name, e))),
   };
 
   let search_text = arguments.text.or(arguments.query).unwrap_or_default().trim().to_owned();
   if search_text.is_empty() {
-    return Ok(tool_error_json("lexical_search tool argument 'text' is required."));
+    return Ok(tool_error_json(&format!("{} tool argument 'text' is required.", tool_name)));
   }
 
   let num_results = arguments
@@ -836,18 +1313,23 @@ async fn execute_lexical_search_tool_call(
     .unwrap_or(CHAT_LEXICAL_SEARCH_TOOL_DEFAULT_NUM_RESULTS)
     .clamp(1, CHAT_LEXICAL_SEARCH_TOOL_MAX_NUM_RESULTS);
   let page_num = arguments.page_num.map(|page_num| page_num.max(1));
-  let search_request = search::SearchRequest { page_id: arguments.page_id, text: search_text, num_results, page_num };
+  let search_request =
+    search::SearchRequest { page_id: arguments.page_id, text: search_text, num_results, page_num, person: None };
 
-  match search::run_lexical_search(db, search_request, session).await {
+  let response = match config {
+    Some(config) => search::run_semantic_search(config, db, search_request, session).await,
+    None => search::run_lexical_search(db, search_request, session).await,
+  };
+  match response {
     Ok(response) => search::compact_search_response_json(&response),
-    Err(e) => Ok(tool_error_json(&format!("lexical_search failed: {}", e))),
+    Err(e) => Ok(tool_error_json(&format!("{} failed: {}", tool_name, e))),
   }
 }
 
 async fn execute_get_fragment_tool_call(
   db: &Arc<tokio::sync::Mutex<Db>>,
   session: &Session,
-  tool_call: &LlamaToolCall,
+  tool_call: &LlmToolCall,
 ) -> InfuResult<String> {
   let arguments = match tool_call_arguments_value(tool_call) {
     Ok(arguments) => arguments,
@@ -933,122 +1415,383 @@ async fn execute_get_fragment_tool_call(
   )
 }
 
-fn tool_call_arguments_value(tool_call: &LlamaToolCall) -> InfuResult<Value> {
-  match &tool_call.function.arguments {
-    Value::String(arguments) => serde_json::from_str(arguments).map_err(|e| {
-      format!("Could not parse arguments for tool '{}': {}", tool_call.function.name, error_chain_for_log(&e)).into()
-    }),
-    Value::Object(_) => Ok(tool_call.function.arguments.clone()),
-    Value::Null => Ok(serde_json::json!({})),
-    other => Err(
-      format!(
-        "Tool '{}' arguments must be a JSON object or JSON-encoded object string, got: {}",
-        tool_call.function.name, other
-      )
-      .into(),
-    ),
+async fn execute_list_children_tool_call(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  session: &Session,
+  tool_call: &LlmToolCall,
+) -> InfuResult<String> {
+  let arguments: ChatItemToolArguments = match parse_tool_arguments(tool_call) {
+    Ok(arguments) => arguments,
+    Err(e) => return Ok(e),
+  };
+  let Some(item_id) = non_empty_tool_uid(arguments.item_id.as_ref()) else {
+    return Ok(tool_error_json("list_children tool argument 'itemId' is required."));
+  };
+  let num_results = arguments
+    .num_results
+    .unwrap_or(CHAT_LIST_CHILDREN_TOOL_DEFAULT_NUM_RESULTS)
+    .clamp(1, CHAT_LIST_CHILDREN_TOOL_MAX_NUM_RESULTS) as usize;
+  let start = (arguments.page_num.unwrap_or(1).max(1) as usize - 1).saturating_mul(num_results);
+
+  let db = db.lock().await;
+  let Some(container) = owned_chat_tool_item(&db, session, &item_id) else {
+    return Ok(tool_error_json("Item was not found."));
+  };
+  if !is_container_item_type(container.item_type) {
+    return Ok(tool_error_json("Item is not a page, table or composite and has no children."));
   }
+
+  let mut children = db.item.get_children(&item_id)?;
+  children.sort_by(|a, b| a.ordering.cmp(&b.ordering));
+  let has_more = children.len() > start.saturating_add(num_results);
+  let children =
+    children.iter().skip(start).take(num_results).map(|child| chat_tool_item_json(child)).collect::<Vec<_>>();
+
+  Ok(
+    serde_json::json!({
+      "itemId": item_id,
+      "linkUrl": format!("infumap://{}", item_id),
+      "itemType": container.item_type.as_str(),
+      "title": container.title,
+      "children": children,
+      "hasMore": has_more
+    })
+    .to_string(),
+  )
 }
 
-fn tool_error_json(message: &str) -> String {
-  serde_json::json!({ "error": message }).to_string()
+async fn execute_get_item_metadata_tool_call(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  session: &Session,
+  tool_call: &LlmToolCall,
+) -> InfuResult<String> {
+  let arguments: ChatItemToolArguments = match parse_tool_arguments(tool_call) {
+    Ok(arguments) => arguments,
+    Err(e) => return Ok(e),
+  };
+  let Some(item_id) = non_empty_tool_uid(arguments.item_id.as_ref()) else {
+    return Ok(tool_error_json("get_item_metadata tool argument 'itemId' is required."));
+  };
+
+  let db = db.lock().await;
+  let Some(item) = owned_chat_tool_item(&db, session, &item_id) else {
+    return Ok(tool_error_json("Item was not found."));
+  };
+
+  let mut path = Vec::new();
+  let mut parent_id_maybe = item.parent_id.clone();
+  while let Some(parent_id) = parent_id_maybe {
+    if path.len() >= CHAT_ITEM_METADATA_MAX_PATH_DEPTH {
+      break;
+    }
+    let Ok(parent) = db.item.get(&parent_id) else {
+      break;
+    };
+    path.push(chat_tool_item_json(parent));
+    parent_id_maybe = parent.parent_id.clone();
+  }
+  path.reverse();
+
+  let child_count =
+    if is_container_item_type(item.item_type) { Some(db.item.get_children(&item.id)?.len()) } else { None };
+  let attachment_count =
+    if is_attachments_item_type(item.item_type) { Some(db.item.get_attachments(&item.id)?.len()) } else { None };
+
+  Ok(
+    serde_json::json!({
+      "itemId": item.id,
+      "linkUrl": format!("infumap://{}", item.id),
+      "itemType": item.item_type.as_str(),
+      "title": item.title,
+      "relationshipToParent": item.relationship_to_parent.as_str(),
+      "path": path,
+      "creationDate": item.creation_date,
+      "lastModifiedDate": item.last_modified_date,
+      "dateTime": item.datetime,
+      "mimeType": item.mime_type,
+      "fileSizeBytes": item.file_size_bytes,
+      "childCount": child_count,
+      "attachmentCount": attachment_count,
+      "linkTo": item.link_to
+    })
+    .to_string(),
+  )
 }
 
-async fn llama_chat_completion(
-  config: &Config,
-  messages: &[LlamaChatMessage],
-  tools: &[LlamaToolSpec],
-  llm_turn: usize,
-) -> InfuResult<LlamaChatMessage> {
-  let url = configured_llama_chat_url(config)?;
-
-  let client = reqwest::ClientBuilder::new()
-    .timeout(Duration::from_secs(CHAT_LLAMA_REQUEST_TIMEOUT_SECS))
-    .build()
-    .map_err(|e| format!("Could not build llama-server HTTP client: {}", reqwest_error_for_log(&e)))?;
-  let payload = LlamaChatCompletionRequest {
-    model: "default".to_owned(),
-    messages: messages.to_vec(),
-    stream: false,
-    tools: tools.to_vec(),
+async fn execute_create_note_tool_call(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: &Arc<object::ObjectStore>,
+  session: &Session,
+  tool_call: &LlmToolCall,
+  progress: &ChatProgressReporter,
+) -> InfuResult<String> {
+  let arguments: ChatCreateNoteToolArguments = match parse_tool_arguments(tool_call) {
+    Ok(arguments) => arguments,
+    Err(e) => return Ok(e),
   };
-  append_llm_request_metrics_log(llm_turn, messages, tools);
-  append_llm_json_log_section(&format!("LLM REQUEST {}", llm_turn), &payload);
-  let response = client
-    .post(url.clone())
-    .json(&payload)
-    .send()
-    .await
-    .map_err(|e| format!("Could not send chat request to llama-server '{}': {}", url, reqwest_error_for_log(&e)))?;
-
-  let status = response.status();
-  let body = response
-    .text()
-    .await
-    .map_err(|e| format!("Could not read llama-server response body: {}", reqwest_error_for_log(&e)))?;
-  append_llm_log_section(&format!("LLM RESPONSE {}", llm_turn), &body);
-  if !status.is_success() {
-    return Err(
-      format!("llama-server chat endpoint '{}' returned {}: {}", url, status, truncate_for_error(&body, 1000)).into(),
-    );
+  let title = arguments.title.unwrap_or_default().trim().to_owned();
+  if title.is_empty() {
+    return Ok(tool_error_json("create_note tool argument 'title' is required."));
+  }
+
+  let (parent_id, parent_title) = {
+    let db = db.lock().await;
+    let parent_id = match non_empty_tool_uid(arguments.parent_id.as_ref()) {
+      Some(parent_id) => parent_id,
+      None => match db.user.get(&session.user_id) {
+        Some(user) => user.home_page_id.clone(),
+        None => return Ok(tool_error_json("User was not found.")),
+      },
+    };
+    let Some(parent) = owned_chat_tool_item(&db, session, &parent_id) else {
+      return Ok(tool_error_json("Parent item was not found."));
+    };
+    if !is_container_item_type(parent.item_type) {
+      return Ok(tool_error_json("Notes can only be created in a page, table or composite."));
+    }
+    (parent_id, chat_tool_item_label(parent))
+  };
+
+  let summary = format!("Create note \"{}\" in \"{}\"", clamp_text_chars(&title, 80).0, parent_title);
+  let details = serde_json::json!({ "title": title, "parentId": parent_id });
+  if !progress.request_confirmation(&session.user_id, "create_note", &summary, details).await {
+    return Ok(chat_tool_declined_json());
   }
 
-  let parsed: LlamaChatCompletionResponse = serde_json::from_str(&body)
-    .map_err(|e| format!("Could not parse llama-server chat response: {}", error_chain_for_log(&e)))?;
-  if let Some(usage) = parsed.usage.as_ref() {
-    append_llm_json_log_section(&format!("LLM RESPONSE USAGE {}", llm_turn), usage);
+  let note_id = new_uid();
+  let item_json = serde_json::json!({
+    "itemType": "note",
+    "id": note_id,
+    "parentId": parent_id,
+    "title": title,
+    "spatialWidthGr": 8 * GRID_SIZE,
+  })
+  .to_string();
+  match add_item_for_user(db, object_store.clone(), &item_json, &None, &session.user_id).await {
+    Ok(_) => Ok(chat_tool_applied_json(&note_id)),
+    Err(e) => Ok(tool_error_json(&format!("create_note failed: {}", e))),
   }
-  parsed
-    .choices
-    .into_iter()
-    .next()
-    .map(|choice| choice.message)
-    .ok_or_else(|| "llama-server returned no chat response choices.".into())
 }
 
-struct ChatMarkdownNote {
-  title: String,
-  flags: i64,
-  inline_marks: Vec<i64>,
-  urls: Vec<ChatMarkdownUrl>,
+async fn execute_move_item_tool_call(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  session: &Session,
+  tool_call: &LlmToolCall,
+  progress: &ChatProgressReporter,
+) -> InfuResult<String> {
+  let arguments: ChatMoveItemToolArguments = match parse_tool_arguments(tool_call) {
+    Ok(arguments) => arguments,
+    Err(e) => return Ok(e),
+  };
+  let Some(item_id) = non_empty_tool_uid(arguments.item_id.as_ref()) else {
+    return Ok(tool_error_json("move_item tool argument 'itemId' is required."));
+  };
+  let Some(new_parent_id) = non_empty_tool_uid(arguments.new_parent_id.as_ref()) else {
+    return Ok(tool_error_json("move_item tool argument 'newParentId' is required."));
+  };
+
+  let summary = {
+    let db = db.lock().await;
+    let moved_item = match chat_moved_item(&db, session, &item_id, &new_parent_id) {
+      Ok(moved_item) => moved_item,
+      Err(e) => return Ok(tool_error_json(&e)),
+    };
+    let item = db.item.get(&item_id)?;
+    let old_parent_label = item
+      .parent_id
+      .as_ref()
+      .and_then(|parent_id| db.item.get(parent_id).ok())
+      .map(chat_tool_item_label)
+      .unwrap_or_default();
+    let new_parent_label = db.item.get(&new_parent_id).map(chat_tool_item_label).unwrap_or_default();
+    format!("Move \"{}\" from \"{}\" to \"{}\"", chat_tool_item_label(&moved_item), old_parent_label, new_parent_label)
+  };
+  let details = serde_json::json!({ "itemId": item_id, "newParentId": new_parent_id });
+  if !progress.request_confirmation(&session.user_id, "move_item", &summary, details).await {
+    return Ok(chat_tool_declined_json());
+  }
+
+  // Rebuild the update from current state; the item may have changed while waiting for approval.
+  let item_json = {
+    let db = db.lock().await;
+    let moved_item = match chat_moved_item(&db, session, &item_id, &new_parent_id) {
+      Ok(moved_item) => moved_item,
+      Err(e) => return Ok(tool_error_json(&e)),
+    };
+    Value::Object(moved_item.to_api_json()?).to_string()
+  };
+  match item_ops::update_item_for_user(db, &item_json, &session.user_id).await {
+    Ok(_) => Ok(chat_tool_applied_json(&item_id)),
+    Err(e) => Ok(tool_error_json(&format!("move_item failed: {}", e))),
+  }
 }
 
-#[derive(Clone)]
-struct ChatMarkdownInlineText {
-  title: String,
-  inline_marks: Vec<i64>,
-  urls: Vec<ChatMarkdownUrl>,
+async fn execute_add_to_page_tool_call(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: &Arc<object::ObjectStore>,
+  session: &Session,
+  tool_call: &LlmToolCall,
+  progress: &ChatProgressReporter,
+) -> InfuResult<String> {
+  let arguments: ChatAddToPageToolArguments = match parse_tool_arguments(tool_call) {
+    Ok(arguments) => arguments,
+    Err(e) => return Ok(e),
+  };
+  let Some(item_id) = non_empty_tool_uid(arguments.item_id.as_ref()) else {
+    return Ok(tool_error_json("add_to_page tool argument 'itemId' is required."));
+  };
+  let Some(page_id) = non_empty_tool_uid(arguments.page_id.as_ref()) else {
+    return Ok(tool_error_json("add_to_page tool argument 'pageId' is required."));
+  };
+
+  let (summary, width_gr, height_gr) = {
+    let db = db.lock().await;
+    let Some(item) = owned_chat_tool_item(&db, session, &item_id) else {
+      return Ok(tool_error_json("Item was not found."));
+    };
+    let Some(page) = owned_chat_tool_item(&db, session, &page_id) else {
+      return Ok(tool_error_json("Page was not found."));
+    };
+    if !is_page_item(page) {
+      return Ok(tool_error_json("Items can only be added to a page."));
+    }
+    (
+      format!("Add a link to \"{}\" on page \"{}\"", chat_tool_item_label(item), chat_tool_item_label(page)),
+      item.spatial_width_gr.filter(|width_gr| *width_gr > 0).unwrap_or(4 * GRID_SIZE),
+      item.spatial_height_gr.filter(|height_gr| *height_gr > 0).unwrap_or(4 * GRID_SIZE),
+    )
+  };
+  let details = serde_json::json!({ "itemId": item_id, "pageId": page_id });
+  if !progress.request_confirmation(&session.user_id, "add_to_page", &summary, details).await {
+    return Ok(chat_tool_declined_json());
+  }
+
+  let link_id = new_uid();
+  let item_json = serde_json::json!({
+    "itemType": "link",
+    "id": link_id,
+    "parentId": page_id,
+    "linkTo": item_id,
+    "spatialWidthGr": width_gr,
+    "spatialHeightGr": height_gr,
+  })
+  .to_string();
+  match add_item_for_user(db, object_store.clone(), &item_json, &None, &session.user_id).await {
+    Ok(_) => Ok(chat_tool_applied_json(&link_id)),
+    Err(e) => Ok(tool_error_json(&format!("add_to_page failed: {}", e))),
+  }
 }
 
-#[derive(Clone)]
-struct ChatMarkdownUrl {
-  start: i64,
-  end: i64,
-  url: String,
+/// The item as it would be after moving it to the end of `new_parent_id`, or a tool-facing error message.
+fn chat_moved_item(db: &Db, session: &Session, item_id: &Uid, new_parent_id: &Uid) -> Result<Item, String> {
+  let item = owned_chat_tool_item(db, session, item_id).ok_or("Item was not found.")?;
+  let new_parent = owned_chat_tool_item(db, session, new_parent_id).ok_or("Destination item was not found.")?;
+  if !is_container_item_type(new_parent.item_type) {
+    return Err("Items can only be moved into a page, table or composite.".to_owned());
+  }
+  if item.relationship_to_parent != RelationshipToParent::Child {
+    return Err("Only child items can be moved; attachments and root pages cannot.".to_owned());
+  }
+  if item.parent_id.as_ref() == Some(new_parent_id) {
+    return Err("Item is already in the destination container.".to_owned());
+  }
+
+  let mut visited = HashSet::new();
+  let mut ancestor_id_maybe = Some(new_parent_id.clone());
+  while let Some(ancestor_id) = ancestor_id_maybe {
+    if &ancestor_id == item_id {
+      return Err("An item cannot be moved into itself or one of its descendants.".to_owned());
+    }
+    if !visited.insert(ancestor_id.clone()) {
+      break;
+    }
+    ancestor_id_maybe = db.item.get(&ancestor_id).ok().and_then(|ancestor| ancestor.parent_id.clone());
+  }
+
+  let orderings = db
+    .item
+    .get_children(new_parent_id)
+    .map_err(|e| e.to_string())?
+    .iter()
+    .map(|child| child.ordering.clone())
+    .collect::<Vec<_>>();
+  let mut moved_item = item.clone();
+  moved_item.parent_id = Some(new_parent_id.clone());
+  moved_item.ordering = new_ordering_at_end(orderings);
+  if moved_item.spatial_position_gr.is_some() {
+    moved_item.spatial_position_gr = Some(Vector { x: 0, y: 0 });
+  }
+  moved_item.last_modified_date = unix_now_secs_u64().unwrap() as i64;
+  Ok(moved_item)
+}
+
+fn owned_chat_tool_item<'a>(db: &'a Db, session: &Session, item_id: &Uid) -> Option<&'a Item> {
+  db.item.get(item_id).ok().filter(|item| item.owner_id == session.user_id)
+}
+
+fn chat_tool_item_label(item: &Item) -> String {
+  match item.title.as_deref().map(str::trim).filter(|title| !title.is_empty()) {
+    Some(title) => clamp_text_chars(title, 80).0,
+    None => item.item_type.as_str().to_owned(),
+  }
+}
+
+fn chat_tool_item_json(item: &Item) -> Value {
+  serde_json::json!({
+    "itemId": item.id,
+    "linkUrl": format!("infumap://{}", item.id),
+    "itemType": item.item_type.as_str(),
+    "title": item.title
+  })
+}
+
+fn chat_tool_applied_json(item_id: &Uid) -> String {
+  serde_json::json!({ "applied": true, "itemId": item_id, "linkUrl": format!("infumap://{}", item_id) }).to_string()
 }
 
-struct ChatMarkdownTableRow {
-  cells: Vec<ChatMarkdownInlineText>,
+fn chat_tool_declined_json() -> String {
+  serde_json::json!({ "applied": false, "message": "The user declined this change." }).to_string()
 }
 
-struct ChatMarkdownTable {
-  columns: Vec<String>,
-  rows: Vec<ChatMarkdownTableRow>,
+fn non_empty_tool_uid(value: Option<&Uid>) -> Option<Uid> {
+  value.map(|value| value.trim()).filter(|value| !value.is_empty()).map(str::to_owned)
 }
 
-enum ChatMarkdownItem {
-  Note(ChatMarkdownNote),
-  Divider,
-  Table(ChatMarkdownTable),
+/// Parses tool call arguments, returning a tool error JSON string on failure.
+fn parse_tool_arguments<T: serde::de::DeserializeOwned>(tool_call: &LlmToolCall) -> Result<T, String> {
+  let arguments = tool_call_arguments_value(tool_call).map_err(|e| tool_error_json(&e.to_string()))?;
+  serde_json::from_value(arguments)
+    .map_err(|e| tool_error_json(&format!("Could not parse {} tool arguments: {}", tool_call.function.name, e)))
+}
+
+fn tool_call_arguments_value(tool_call: &LlmToolCall) -> InfuResult<Value> {
+  match &tool_call.function.arguments {
+    Value::String(arguments) => serde_json::from_str(arguments).map_err(|e| {
+      format!("Could not parse arguments for tool '{}': {}", tool_call.function.name, error_chain_for_log(&e)).into()
+    }),
+    Value::Object(_) => Ok(tool_call.function.arguments.clone()),
+    Value::Null => Ok(serde_json::json!({})),
+    other => Err(
+      format!(
+        "Tool '{}' arguments must be a JSON object or JSON-encoded object string, got: {}",
+        tool_call.function.name, other
+      )
+      .into(),
+    ),
+  }
+}
+
+fn tool_error_json(message: &str) -> String {
+  serde_json::json!({ "error": message }).to_string()
 }
 
 fn chat_response_items_json(owner_id: &Uid, assistant_text: &str) -> Value {
   let now = unix_now_secs_u64().unwrap();
   let composite_id = new_uid();
-  let mut parsed_items = chat_markdown_items_from_text(assistant_text);
+  let mut parsed_items = markdown_items_from_text(assistant_text);
 
   if parsed_items.is_empty() {
-    push_chat_markdown_note(&mut parsed_items, assistant_text, 0);
+    push_markdown_note(&mut parsed_items, assistant_text, 0);
   }
 
   let mut items = Vec::with_capacity(parsed_items.len() + 1);
@@ -1075,11 +1818,11 @@ fn chat_response_items_json(owner_id: &Uid, assistant_text: &str) -> Value {
     let ordering = new_ordering_at_end(child_orderings.clone());
     child_orderings.push(ordering.clone());
     match parsed_item {
-      ChatMarkdownItem::Note(note) => {
+      MarkdownItem::Note(note) => {
         items.push(chat_response_note_json(owner_id, &composite_id, "child", now, ordering, new_uid(), note))
       }
-      ChatMarkdownItem::Divider => items.push(chat_response_divider_json(owner_id, &composite_id, now, ordering)),
-      ChatMarkdownItem::Table(table) => {
+      MarkdownItem::Divider => items.push(chat_response_divider_json(owner_id, &composite_id, now, ordering)),
+      MarkdownItem::Table(table) => {
         items.extend(chat_response_table_json(owner_id, &composite_id, now, ordering, table))
       }
     }
@@ -1095,7 +1838,7 @@ fn chat_response_note_json(
   now: u64,
   ordering: Vec<u8>,
   item_id: Uid,
-  note: ChatMarkdownNote,
+  note: MarkdownNote,
 ) -> Value {
   serde_json::json!({
       "itemType": "note",
@@ -1150,7 +1893,7 @@ fn chat_response_table_json(
   parent_id: &Uid,
   now: u64,
   ordering: Vec<u8>,
-  table: ChatMarkdownTable,
+  table: MarkdownTable,
 ) -> Vec<Value> {
   let table_id = new_uid();
   let column_widths_gr = chat_response_table_column_widths_gr(CHAT_RESPONSE_ITEM_WIDTH_GR, &table);
@@ -1195,7 +1938,7 @@ fn chat_response_table_json(
       now,
       row_ordering,
       row_id.clone(),
-      ChatMarkdownNote {
+      MarkdownNote {
         title: first_cell.title,
         flags: 0,
         inline_marks: first_cell.inline_marks,
@@ -1219,7 +1962,7 @@ fn chat_response_table_json(
           now,
           attachment_ordering,
           new_uid(),
-          ChatMarkdownNote { title: cell.title, flags: 0, inline_marks: cell.inline_marks, urls: cell.urls },
+          MarkdownNote { title: cell.title, flags: 0, inline_marks: cell.inline_marks, urls: cell.urls },
         ));
       }
     }
@@ -1243,11 +1986,11 @@ fn chat_response_placeholder_json(owner_id: &Uid, parent_id: &Uid, now: u64, ord
   })
 }
 
-fn empty_chat_markdown_inline_text() -> ChatMarkdownInlineText {
-  ChatMarkdownInlineText { title: "".to_owned(), inline_marks: Vec::new(), urls: Vec::new() }
+fn empty_chat_markdown_inline_text() -> MarkdownInlineText {
+  MarkdownInlineText { title: "".to_owned(), inline_marks: Vec::new(), urls: Vec::new() }
 }
 
-fn last_non_empty_chat_table_attachment_cell_index(row: &ChatMarkdownTableRow) -> usize {
+fn last_non_empty_chat_table_attachment_cell_index(row: &MarkdownTableRow) -> usize {
   for index in (1..row.cells.len()).rev() {
     if !row.cells[index].title.trim().is_empty() {
       return index;
@@ -1308,7 +2051,7 @@ fn chat_table_cell_text_width_score(text: &str) -> f64 {
   capped_text_length as f64 * 0.7 + capped_longest_word_length as f64 * 1.3
 }
 
-fn chat_table_column_text_weight(table: &ChatMarkdownTable, column_index: usize) -> f64 {
+fn chat_table_column_text_weight(table: &MarkdownTable, column_index: usize) -> f64 {
   let mut scores =
     vec![chat_table_cell_text_width_score(table.columns.get(column_index).map(String::as_str).unwrap_or(""))];
   for row in &table.rows {
@@ -1327,514 +2070,8 @@ fn chat_table_column_text_weight(table: &ChatMarkdownTable, column_index: usize)
   (max_score * 0.75 + average_score * 0.25).powf(0.8)
 }
 
-fn chat_response_table_column_widths_gr(total_width_gr: i64, table: &ChatMarkdownTable) -> Vec<i64> {
+fn chat_response_table_column_widths_gr(total_width_gr: i64, table: &MarkdownTable) -> Vec<i64> {
   let weights: Vec<f64> =
     table.columns.iter().enumerate().map(|(index, _)| chat_table_column_text_weight(table, index)).collect();
   weighted_integer_column_widths_gr(total_width_gr, &weights, CHAT_RESPONSE_TABLE_MIN_COLUMN_WIDTH_GR)
 }
-
-fn chat_markdown_items_from_text(markdown: &str) -> Vec<ChatMarkdownItem> {
-  let mut items = Vec::new();
-  let mut paragraph_lines: Vec<String> = Vec::new();
-  let lines: Vec<&str> = markdown.lines().collect();
-  let mut line_index = 0;
-
-  while line_index < lines.len() {
-    let line = lines[line_index];
-
-    if line.trim().is_empty() {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      line_index += 1;
-      continue;
-    }
-
-    if let Some(fence_marker) = markdown_code_fence_marker(line) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      line_index += 1;
-      let mut code_lines = Vec::new();
-      while line_index < lines.len() {
-        let code_line = lines[line_index];
-        if code_line.trim_start().starts_with(fence_marker) {
-          break;
-        }
-        code_lines.push(code_line.to_owned());
-        line_index += 1;
-      }
-      if line_index < lines.len() {
-        line_index += 1;
-      }
-      push_chat_markdown_note(&mut items, &code_lines.join("\n"), NOTE_FLAG_CODE);
-      continue;
-    }
-
-    if let Some((table, next_line_index)) = markdown_table_at(&lines, line_index) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      items.push(ChatMarkdownItem::Table(table));
-      line_index = next_line_index;
-      continue;
-    }
-
-    if markdown_divider_line(line) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      items.push(ChatMarkdownItem::Divider);
-      line_index += 1;
-      continue;
-    }
-
-    if let Some((flags, title)) = markdown_heading(line) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      push_chat_markdown_note(&mut items, title, flags);
-      line_index += 1;
-      continue;
-    }
-
-    if let Some((flags, title)) = markdown_list_item(line) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      push_chat_markdown_note(&mut items, title, flags);
-      line_index += 1;
-      continue;
-    }
-
-    if markdown_standalone_inline_heading(line) {
-      flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-      push_chat_markdown_note(&mut items, line, 0);
-      line_index += 1;
-      continue;
-    }
-
-    paragraph_lines.push(line.to_owned());
-    line_index += 1;
-  }
-
-  flush_chat_markdown_paragraph(&mut items, &mut paragraph_lines);
-  items
-}
-
-fn flush_chat_markdown_paragraph(items: &mut Vec<ChatMarkdownItem>, paragraph_lines: &mut Vec<String>) {
-  if paragraph_lines.is_empty() {
-    return;
-  }
-  let paragraph = paragraph_lines.join("\n");
-  paragraph_lines.clear();
-  push_chat_markdown_note(items, &paragraph, 0);
-}
-
-fn push_chat_markdown_note(items: &mut Vec<ChatMarkdownItem>, raw_title: &str, flags: i64) {
-  let title =
-    if flags & NOTE_FLAG_CODE != 0 { raw_title.trim_matches('\n').to_owned() } else { raw_title.trim().to_owned() };
-  if title.is_empty() {
-    return;
-  }
-
-  let inline_text = if flags & NOTE_FLAG_CODE != 0 {
-    ChatMarkdownInlineText { title, inline_marks: Vec::new(), urls: Vec::new() }
-  } else {
-    parse_markdown_inline(&title)
-  };
-  if inline_text.title.trim().is_empty() {
-    return;
-  }
-
-  items.push(ChatMarkdownItem::Note(ChatMarkdownNote {
-    title: inline_text.title,
-    flags,
-    inline_marks: inline_text.inline_marks,
-    urls: inline_text.urls,
-  }));
-}
-
-fn markdown_code_fence_marker(line: &str) -> Option<&'static str> {
-  let trimmed = line.trim_start();
-  if trimmed.starts_with("```") {
-    Some("```")
-  } else if trimmed.starts_with("~~~") {
-    Some("~~~")
-  } else {
-    None
-  }
-}
-
-fn markdown_table_at(lines: &[&str], index: usize) -> Option<(ChatMarkdownTable, usize)> {
-  if index + 1 >= lines.len() {
-    return None;
-  }
-
-  let header_cells = split_markdown_table_row(lines[index])?;
-  let separator_cells = split_markdown_table_row(lines[index + 1])?;
-  if separator_cells.len() < header_cells.len() || !markdown_table_separator_row(&separator_cells) {
-    return None;
-  }
-
-  let column_count = header_cells.len();
-  let mut rows = Vec::new();
-  let mut row_index = index + 2;
-  while row_index < lines.len() {
-    let Some(row_cells) = split_markdown_table_row(lines[row_index]) else {
-      break;
-    };
-    if markdown_table_separator_row(&row_cells) {
-      break;
-    }
-    rows.push(ChatMarkdownTableRow { cells: normalize_markdown_table_inline_cells(&row_cells, column_count) });
-    row_index += 1;
-  }
-
-  Some((
-    ChatMarkdownTable {
-      columns: normalize_markdown_table_cells(&header_cells, column_count)
-        .iter()
-        .map(|cell| parse_markdown_inline(cell).title)
-        .collect(),
-      rows,
-    },
-    row_index,
-  ))
-}
-
-fn split_markdown_table_row(line: &str) -> Option<Vec<String>> {
-  let mut body = line.trim();
-  if body.is_empty() {
-    return None;
-  }
-
-  if body.starts_with('|') {
-    body = &body[1..];
-  }
-  if body.ends_with('|') && (body.len() < 2 || !body[..body.len() - 1].ends_with('\\')) {
-    body = &body[..body.len() - 1];
-  }
-
-  let mut cells = Vec::new();
-  let mut cell = String::new();
-  let mut saw_separator = false;
-  let mut chars = body.chars().peekable();
-  while let Some(ch) = chars.next() {
-    if ch == '\\' && chars.peek() == Some(&'|') {
-      cell.push('|');
-      chars.next();
-      continue;
-    }
-    if ch == '|' {
-      cells.push(cell.trim().to_owned());
-      cell.clear();
-      saw_separator = true;
-      continue;
-    }
-    cell.push(ch);
-  }
-  cells.push(cell.trim().to_owned());
-
-  if !saw_separator || cells.len() < 2 {
-    return None;
-  }
-  Some(cells)
-}
-
-fn markdown_table_separator_cell(cell: &str) -> bool {
-  let trimmed = cell.trim();
-  let body = trimmed.strip_prefix(':').unwrap_or(trimmed);
-  let body = body.strip_suffix(':').unwrap_or(body);
-  body.len() >= 3 && body.chars().all(|ch| ch == '-')
-}
-
-fn markdown_table_separator_row(cells: &[String]) -> bool {
-  cells.len() >= 2 && cells.iter().all(|cell| markdown_table_separator_cell(cell))
-}
-
-fn normalize_markdown_table_cells(cells: &[String], column_count: usize) -> Vec<String> {
-  let mut result = cells.iter().take(column_count).cloned().collect::<Vec<_>>();
-  while result.len() < column_count {
-    result.push("".to_owned());
-  }
-  result
-}
-
-fn normalize_markdown_table_inline_cells(cells: &[String], column_count: usize) -> Vec<ChatMarkdownInlineText> {
-  normalize_markdown_table_cells(cells, column_count).iter().map(|cell| parse_markdown_inline(cell)).collect()
-}
-
-fn markdown_divider_line(line: &str) -> bool {
-  let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
-  if chars.len() < 3 {
-    return false;
-  }
-  let divider_char = chars[0];
-  matches!(divider_char, '-' | '_' | '*') && chars.iter().all(|c| *c == divider_char)
-}
-
-fn markdown_heading(line: &str) -> Option<(i64, &str)> {
-  let trimmed = line.trim_start();
-  let heading_level = trimmed.chars().take_while(|c| *c == '#').count();
-  if heading_level == 0 || heading_level > 6 {
-    return None;
-  }
-  let title = &trimmed[heading_level..];
-  if !title.chars().next().map(|c| c.is_whitespace()).unwrap_or(false) {
-    return None;
-  }
-  let title = title.trim();
-  if title.is_empty() {
-    return None;
-  }
-  Some((
-    match heading_level {
-      1 => NOTE_FLAG_HEADING1,
-      2 => NOTE_FLAG_HEADING2,
-      3 => NOTE_FLAG_HEADING3,
-      _ => NOTE_FLAG_HEADING4,
-    },
-    title,
-  ))
-}
-
-fn markdown_list_item(line: &str) -> Option<(i64, &str)> {
-  let trimmed = line.trim_start();
-  let mut chars = trimmed.char_indices();
-  if let Some((_, marker)) = chars.next() {
-    if matches!(marker, '-' | '*' | '+') {
-      if let Some((content_start, separator)) = chars.next() {
-        if separator.is_whitespace() {
-          return Some((NOTE_FLAG_BULLET1, trimmed[content_start..].trim_start()));
-        }
-      }
-    }
-  }
-
-  let mut digit_end = 0;
-  let mut has_digit = false;
-  for (index, ch) in trimmed.char_indices() {
-    if !ch.is_ascii_digit() {
-      break;
-    }
-    has_digit = true;
-    digit_end = index + ch.len_utf8();
-  }
-  if !has_digit || !trimmed[digit_end..].starts_with('.') {
-    return None;
-  }
-
-  let content = &trimmed[digit_end + 1..];
-  if !content.chars().next().map(|c| c.is_whitespace()).unwrap_or(false) {
-    return None;
-  }
-  Some((NOTE_FLAG_NUMBERED, content.trim_start()))
-}
-
-fn markdown_standalone_inline_heading(line: &str) -> bool {
-  let trimmed = line.trim();
-  for marker in ["***", "___", "**", "__"] {
-    if trimmed.len() > marker.len() * 2 && trimmed.starts_with(marker) && trimmed.ends_with(marker) {
-      return true;
-    }
-  }
-  false
-}
-
-fn parse_markdown_inline(input: &str) -> ChatMarkdownInlineText {
-  let mut output = String::new();
-  let mut inline_marks = Vec::new();
-  let mut urls = Vec::new();
-  let mut index = 0;
-
-  while index < input.len() {
-    if let Some((escaped, len)) = markdown_escaped_ascii_punctuation(&input[index..]) {
-      output.push(escaped);
-      index += len;
-      continue;
-    }
-
-    if input[index..].starts_with("`") {
-      let content_start = index + 1;
-      if let Some(content_end) = input[content_start..].find('`').map(|offset| content_start + offset) {
-        output.push_str(&input[content_start..content_end]);
-        index = content_end + 1;
-        continue;
-      }
-    }
-
-    if let Some((label, url, len)) = markdown_link_at(&input[index..]) {
-      append_chat_linked_inline_text(&mut output, &mut inline_marks, &mut urls, label, url);
-      index += len;
-      continue;
-    }
-
-    if let Some((marker, flags)) = markdown_inline_marker(&input[index..]) {
-      let content_start = index + marker.len();
-      if let Some(content_end) = input[content_start..].find(marker).map(|offset| content_start + offset) {
-        if content_end > content_start {
-          append_chat_marked_inline_text(&mut output, &mut inline_marks, &input[content_start..content_end], flags);
-          index = content_end + marker.len();
-          continue;
-        }
-      }
-    }
-
-    let ch = input[index..].chars().next().unwrap();
-    output.push(ch);
-    index += ch.len_utf8();
-  }
-
-  ChatMarkdownInlineText { title: output, inline_marks, urls }
-}
-
-fn markdown_link_at(slice: &str) -> Option<(&str, String, usize)> {
-  if !slice.starts_with('[') {
-    return None;
-  }
-
-  let label_end = slice[1..].find("](").map(|offset| offset + 1)?;
-  let label = &slice[1..label_end];
-  if label.trim().is_empty() {
-    return None;
-  }
-
-  let destination_start = label_end + 2;
-  let destination_end = markdown_link_destination_end(&slice[destination_start..])?;
-  let destination = markdown_link_destination(&slice[destination_start..destination_start + destination_end])?;
-  Some((label, destination, destination_start + destination_end + 1))
-}
-
-fn markdown_link_destination_end(slice: &str) -> Option<usize> {
-  let mut escaped = false;
-  for (index, ch) in slice.char_indices() {
-    if escaped {
-      escaped = false;
-      continue;
-    }
-    if ch == '\\' {
-      escaped = true;
-      continue;
-    }
-    if ch == ')' {
-      return Some(index);
-    }
-  }
-  None
-}
-
-fn markdown_link_destination(raw_destination: &str) -> Option<String> {
-  let trimmed = raw_destination.trim();
-  if trimmed.is_empty() {
-    return None;
-  }
-
-  let destination = if let Some(body) = trimmed.strip_prefix('<') {
-    let end = body.find('>')?;
-    &body[..end]
-  } else {
-    trimmed.split_whitespace().next().unwrap_or("")
-  };
-  let destination = destination.trim();
-  if destination.is_empty() { None } else { Some(destination.to_owned()) }
-}
-
-fn markdown_escaped_ascii_punctuation(slice: &str) -> Option<(char, usize)> {
-  let mut chars = slice.chars();
-  if chars.next()? != '\\' {
-    return None;
-  }
-
-  let escaped = chars.next()?;
-  if !escaped.is_ascii_punctuation() {
-    return None;
-  }
-
-  Some((escaped, '\\'.len_utf8() + escaped.len_utf8()))
-}
-
-fn markdown_inline_marker(slice: &str) -> Option<(&'static str, i64)> {
-  for (marker, flags) in [
-    ("***", NOTE_INLINE_MARK_BOLD | NOTE_INLINE_MARK_ITALIC),
-    ("___", NOTE_INLINE_MARK_BOLD | NOTE_INLINE_MARK_ITALIC),
-    ("**", NOTE_INLINE_MARK_BOLD),
-    ("__", NOTE_INLINE_MARK_BOLD),
-    ("*", NOTE_INLINE_MARK_ITALIC),
-    ("_", NOTE_INLINE_MARK_ITALIC),
-  ] {
-    if slice.starts_with(marker) {
-      return Some((marker, flags));
-    }
-  }
-  None
-}
-
-fn append_chat_marked_inline_text(output: &mut String, inline_marks: &mut Vec<i64>, text: &str, flags: i64) {
-  let start = output.encode_utf16().count() as i64;
-  append_chat_markdown_unescaped_text(output, text);
-  let end = output.encode_utf16().count() as i64;
-  push_chat_inline_mark(inline_marks, start, end, flags);
-}
-
-fn append_chat_linked_inline_text(
-  output: &mut String,
-  inline_marks: &mut Vec<i64>,
-  urls: &mut Vec<ChatMarkdownUrl>,
-  label: &str,
-  url: String,
-) {
-  let start = output.encode_utf16().count() as i64;
-  let parsed_label = parse_markdown_inline(label);
-  output.push_str(&parsed_label.title);
-  append_shifted_chat_inline_marks(inline_marks, &parsed_label.inline_marks, start);
-  let end = output.encode_utf16().count() as i64;
-  push_chat_url(urls, start, end, url);
-}
-
-fn append_shifted_chat_inline_marks(inline_marks: &mut Vec<i64>, shifted_marks: &[i64], offset: i64) {
-  for chunk in shifted_marks.chunks_exact(3) {
-    push_chat_inline_mark(inline_marks, chunk[0] + offset, chunk[1] + offset, chunk[2]);
-  }
-}
-
-fn append_chat_markdown_unescaped_text(output: &mut String, text: &str) {
-  let mut index = 0;
-  while index < text.len() {
-    if let Some((escaped, len)) = markdown_escaped_ascii_punctuation(&text[index..]) {
-      output.push(escaped);
-      index += len;
-      continue;
-    }
-
-    let ch = text[index..].chars().next().unwrap();
-    output.push(ch);
-    index += ch.len_utf8();
-  }
-}
-
-fn push_chat_inline_mark(inline_marks: &mut Vec<i64>, start: i64, end: i64, flags: i64) {
-  if start >= end || flags == 0 {
-    return;
-  }
-  if inline_marks.len() >= 3 {
-    let last_start_index = inline_marks.len() - 3;
-    let last_end = inline_marks[last_start_index + 1];
-    let last_flags = inline_marks[last_start_index + 2];
-    if start < last_end {
-      return;
-    }
-    if start == last_end && flags == last_flags {
-      inline_marks[last_start_index + 1] = end;
-      return;
-    }
-  }
-
-  inline_marks.extend([start, end, flags]);
-}
-
-fn push_chat_url(urls: &mut Vec<ChatMarkdownUrl>, start: i64, end: i64, url: String) {
-  let url = url.trim().to_owned();
-  if start >= end || url.is_empty() {
-    return;
-  }
-  if let Some(last) = urls.last_mut() {
-    if start < last.end {
-      return;
-    }
-    if start == last.end && last.url == url {
-      last.end = end;
-      return;
-    }
-  }
-
-  urls.push(ChatMarkdownUrl { start, end, url });
-}
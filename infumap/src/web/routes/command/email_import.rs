@@ -0,0 +1,305 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc2822;
+
+use crate::ai::fragment::sources::html_to_markdown;
+use crate::util::email::{
+  EmailAttachment, EmailMessage, group_threads, is_mbox, parse_message, split_mbox, thread_subject,
+};
+
+const EMAIL_IMPORT_MAX_MESSAGES: usize = 10_000;
+const EMAIL_THREAD_PAGE_WIDTH_GR: i64 = 8 * GRID_SIZE;
+const EMAIL_THREAD_PAGE_INNER_WIDTH_GR: i64 = 60 * GRID_SIZE;
+const EMAIL_MESSAGE_NOTE_WIDTH_GR: i64 = 8 * GRID_SIZE;
+const EMAIL_ATTACHMENT_WIDTH_GR: i64 = 6 * GRID_SIZE;
+const EMAIL_NO_SUBJECT_TITLE: &str = "(no subject)";
+/// Title of the text file attached to each message note. It holds the From/To/Date summary and the
+/// message body, and is what the document pipeline fragments for search.
+const EMAIL_BODY_FILE_TITLE: &str = "message.txt";
+
+#[derive(Deserialize)]
+struct ImportEmailRequest {
+  #[serde(rename = "parentId")]
+  parent_id: Option<Uid>,
+}
+
+#[derive(Serialize)]
+struct ImportEmailResponse {
+  #[serde(rename = "pageIds")]
+  page_ids: Vec<Uid>,
+  #[serde(rename = "messageCount")]
+  message_count: usize,
+  #[serde(rename = "attachmentCount")]
+  attachment_count: usize,
+  #[serde(rename = "skippedCount")]
+  skipped_count: usize,
+}
+
+/// Import an .eml message or .mbox archive (base64 data) as a page per conversation thread under
+/// the given parent, with a note per message. Each note has the message text and any attachments
+/// as file/image attachments.
+pub(super) async fn handle_import_email(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  json_data: &str,
+  base64_data_maybe: &Option<String>,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    Some(session) => session,
+    None => {
+      return Err(format!("Session is required to import email.").into());
+    }
+  };
+
+  let request: ImportEmailRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse import email request: {}", e))?;
+  if let Some(parent_id) = &request.parent_id
+    && !is_uid(parent_id)
+  {
+    return Err(format!("Invalid parent id '{}' in import email request.", parent_id).into());
+  }
+  let base64_data = base64_data_maybe.as_ref().ok_or("Import email request has no base64 data.")?;
+  let decoded = general_purpose::STANDARD
+    .decode(base64_data)
+    .map_err(|e| format!("There was a problem decoding base64 data for email import: {}", e))?;
+
+  let (messages, skipped_count) = tokio::task::spawn_blocking(move || parse_email_data(&decoded))
+    .await
+    .map_err(|e| format!("Email parsing task failed: {}", e))??;
+  if messages.is_empty() {
+    return Err(format!("No email messages could be read from the import data ({} skipped).", skipped_count).into());
+  }
+
+  let mut response = ImportEmailResponse { page_ids: vec![], message_count: 0, attachment_count: 0, skipped_count };
+  for thread in group_threads(&messages) {
+    let first_subject = thread.iter().find_map(|index| messages[*index].subject.as_deref());
+    let page_title = first_subject.map(thread_subject).filter(|subject| !subject.is_empty());
+    let page_id = add_thread_page(
+      db,
+      object_store.clone(),
+      &session.user_id,
+      request.parent_id.as_ref(),
+      page_title.unwrap_or(EMAIL_NO_SUBJECT_TITLE),
+    )
+    .await?;
+    for index in thread {
+      response.attachment_count +=
+        add_message_note(db, object_store.clone(), &session.user_id, &page_id, &messages[index]).await?;
+      response.message_count += 1;
+    }
+    response.page_ids.push(page_id);
+  }
+
+  debug!(
+    "Imported {} email message(s) in {} thread(s) for user '{}' ({} skipped).",
+    response.message_count,
+    response.page_ids.len(),
+    session.user_id,
+    response.skipped_count
+  );
+  Ok(Some(serde_json::to_string(&response)?))
+}
+
+/// Parse a single message or an mbox archive. Messages that cannot be parsed are counted and skipped.
+fn parse_email_data(data: &[u8]) -> InfuResult<(Vec<EmailMessage>, usize)> {
+  let raw_messages = if is_mbox(data) { split_mbox(data) } else { vec![data.to_vec()] };
+  if raw_messages.len() > EMAIL_IMPORT_MAX_MESSAGES {
+    return Err(
+      format!(
+        "Email import contains {} messages, more than the maximum of {}.",
+        raw_messages.len(),
+        EMAIL_IMPORT_MAX_MESSAGES
+      )
+      .into(),
+    );
+  }
+  let mut messages = vec![];
+  let mut skipped_count = 0;
+  for raw_message in raw_messages {
+    match parse_message(&raw_message) {
+      Ok(message) => messages.push(message),
+      Err(e) => {
+        debug!("Skipping email message that could not be parsed: {}", e);
+        skipped_count += 1;
+      }
+    }
+  }
+  Ok((messages, skipped_count))
+}
+
+async fn add_thread_page(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  user_id: &Uid,
+  parent_id: Option<&Uid>,
+  title: &str,
+) -> InfuResult<Uid> {
+  let page = Item::new_page(
+    parent_id,
+    vec![],
+    Vector { x: 0, y: 0 },
+    EMAIL_THREAD_PAGE_WIDTH_GR,
+    RelationshipToParent::Child,
+    title,
+    "",
+    0,
+    0,
+    0,
+    2.0,
+    EMAIL_THREAD_PAGE_INNER_WIDTH_GR,
+    ArrangeAlgorithm::List,
+    6,
+    1.5,
+    36,
+    7.0,
+    1.0,
+    vec![TableColumn { width_gr: 8 * GRID_SIZE, name: "Title".to_owned() }],
+    1,
+  );
+  let mut page_map = page.to_api_json()?;
+  // Let add-item fill in the owner, parent (the home page if none was given) and ordering.
+  page_map.remove("ownerId");
+  page_map.remove("ordering");
+  if parent_id.is_none() {
+    page_map.remove("parentId");
+  }
+  add_item_for_user(db, object_store, &Value::Object(page_map).to_string(), &None, user_id).await?;
+  Ok(page.id)
+}
+
+/// Add the note for one message, returning the number of message attachments added.
+async fn add_message_note(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  user_id: &Uid,
+  page_id: &Uid,
+  message: &EmailMessage,
+) -> InfuResult<usize> {
+  let note_id = new_uid();
+  let mut note_json = serde_json::json!({
+    "itemType": "note",
+    "id": note_id,
+    "parentId": page_id,
+    "title": message.subject.as_deref().unwrap_or(EMAIL_NO_SUBJECT_TITLE),
+    "spatialWidthGr": EMAIL_MESSAGE_NOTE_WIDTH_GR,
+  });
+  if let Some(date) = message.date {
+    note_json["dateTime"] = date.into();
+  }
+  add_item_for_user(db, object_store.clone(), &note_json.to_string(), &None, user_id).await?;
+
+  let body_text = message_body_text(message);
+  add_message_attachment(
+    db,
+    object_store.clone(),
+    user_id,
+    &note_id,
+    EMAIL_BODY_FILE_TITLE,
+    body_text.as_bytes(),
+    false,
+    message.date,
+  )
+  .await?;
+  for attachment in &message.attachments {
+    add_message_attachment(
+      db,
+      object_store.clone(),
+      user_id,
+      &note_id,
+      &attachment.filename,
+      &attachment.data,
+      is_importable_image(attachment),
+      message.date,
+    )
+    .await?;
+  }
+  Ok(message.attachments.len())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn add_message_attachment(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  user_id: &Uid,
+  note_id: &Uid,
+  title: &str,
+  data: &[u8],
+  is_image: bool,
+  date: Option<i64>,
+) -> InfuResult<()> {
+  let mut item_json = serde_json::json!({
+    "itemType": if is_image { ItemType::Image.as_str() } else { ItemType::File.as_str() },
+    "parentId": note_id,
+    "relationshipToParent": "attachment",
+    "title": title,
+    "spatialWidthGr": EMAIL_ATTACHMENT_WIDTH_GR,
+    "fileSizeBytes": data.len(),
+    "originalCreationDate": date.unwrap_or_else(|| unix_now_secs_u64().unwrap() as i64),
+  });
+  if let Some(date) = date {
+    item_json["dateTime"] = date.into();
+  }
+  let base64_data = Some(general_purpose::STANDARD.encode(data));
+  add_item_for_user(db, object_store, &item_json.to_string(), &base64_data, user_id).await?;
+  Ok(())
+}
+
+/// Headers summary followed by the message text. HTML-only messages are converted to markdown.
+fn message_body_text(message: &EmailMessage) -> String {
+  let mut text = String::new();
+  let date = message
+    .date
+    .and_then(|date| OffsetDateTime::from_unix_timestamp(date).ok())
+    .and_then(|date| date.format(&Rfc2822).ok());
+  let headers = [
+    ("From", message.from.as_deref()),
+    ("To", message.to.as_deref()),
+    ("Cc", message.cc.as_deref()),
+    ("Date", date.as_deref()),
+    ("Subject", message.subject.as_deref()),
+  ];
+  for (name, value) in headers {
+    if let Some(value) = value {
+      text.push_str(&format!("{}: {}\n", name, value));
+    }
+  }
+  let body = match (&message.text_body, &message.html_body) {
+    (Some(text_body), _) => Some(text_body.clone()),
+    (None, Some(html_body)) => html_to_markdown(html_body),
+    (None, None) => None,
+  };
+  if let Some(body) = body {
+    text.push('\n');
+    text.push_str(&body);
+    text.push('\n');
+  }
+  text
+}
+
+/// JPEG and PNG attachments that decode become image items, everything else is added as a file.
+fn is_importable_image(attachment: &EmailAttachment) -> bool {
+  if !matches!(attachment.mime_type.as_str(), "image/jpeg" | "image/png") {
+    return false;
+  }
+  let Ok(reader) = ImageReader::new(Cursor::new(&attachment.data)).with_guessed_format() else {
+    return false;
+  };
+  reader.decode().is_ok()
+}
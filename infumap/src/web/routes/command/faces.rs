@@ -0,0 +1,102 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use crate::ai::faces::{list_face_clusters, name_face_cluster};
+use crate::ai::near_duplicates::item_is_in_trash;
+
+/// The faces listed for each cluster. Clusters may be much larger, so this is a sample.
+const FACE_CLUSTER_MAX_LISTED_FACES: usize = 12;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameFaceClusterRequest {
+  cluster_id: String,
+  /// None or empty to clear the name.
+  name: Option<String>,
+}
+
+/// The session user's face clusters, largest first, each with a sample of its faces. Faces of items
+/// that have been moved to the trash are left out, as are clusters left without any faces that have
+/// not been named.
+pub(super) async fn handle_face_clusters(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to list face clusters.")?;
+  let (data_dir, object_encryption_key) = data_dir_and_object_encryption_key(db, &session.user_id).await?;
+  let clusters = list_face_clusters(&data_dir, &session.user_id, &object_encryption_key).await?;
+
+  let db = db.lock().await;
+  let clusters = clusters
+    .iter()
+    .filter_map(|cluster| {
+      let faces = cluster
+        .faces
+        .iter()
+        .filter(|face| {
+          db.item.get(&face.item_id).is_ok_and(|item| item.owner_id == session.user_id && !item_is_in_trash(&db, item))
+        })
+        .collect::<Vec<_>>();
+      if faces.is_empty() && cluster.name.is_none() {
+        return None;
+      }
+      let item_count = faces.iter().map(|face| face.item_id.as_str()).collect::<HashSet<_>>().len();
+      let sample = faces
+        .iter()
+        .take(FACE_CLUSTER_MAX_LISTED_FACES)
+        .map(|face| serde_json::json!({ "itemId": face.item_id, "box": face.bounding_box }))
+        .collect::<Vec<_>>();
+      Some(serde_json::json!({
+        "id": cluster.id,
+        "name": cluster.name,
+        "faceCount": faces.len(),
+        "itemCount": item_count,
+        "faces": sample,
+      }))
+    })
+    .collect::<Vec<_>>();
+
+  debug!("Executed 'face-clusters' command for user '{}': {} cluster(s).", session.user_id, clusters.len());
+  Ok(Some(serde_json::json!({ "clusters": clusters }).to_string()))
+}
+
+/// Set or clear the name of one of the session user's face clusters. Named clusters can be used to
+/// restrict search to photos of that person.
+pub(super) async fn handle_name_face_cluster(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to name a face cluster.")?;
+  let request: NameFaceClusterRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse name face cluster request: {}", e))?;
+  let (data_dir, object_encryption_key) = data_dir_and_object_encryption_key(db, &session.user_id).await?;
+  name_face_cluster(&data_dir, &session.user_id, &object_encryption_key, &request.cluster_id, request.name.as_deref())
+    .await?;
+  let name = request.name.as_deref().map(str::trim).filter(|name| !name.is_empty());
+  Ok(Some(serde_json::json!({ "id": request.cluster_id, "name": name }).to_string()))
+}
+
+async fn data_dir_and_object_encryption_key(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  user_id: &str,
+) -> InfuResult<(String, String)> {
+  let db = db.lock().await;
+  let user = db.user.get(user_id).ok_or(format!("User '{}' not found.", user_id))?;
+  Ok((db.item.data_dir().to_owned(), user.object_encryption_key.clone()))
+}
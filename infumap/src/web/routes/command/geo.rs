@@ -0,0 +1,205 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use crate::ai::geo_index::{
+  GeoBounds, ItemLocation, ItemLocationSource, image_exif_location, query_user_item_locations,
+};
+use crate::ai::image_tagging::should_tag_image_item;
+use crate::ai::near_duplicates::item_is_in_trash;
+
+/// Items are grouped into clusters below this zoom level.
+const GEO_QUERY_CLUSTER_MAX_ZOOM: u32 = 16;
+/// Clusters cover a quarter of a 256px map tile in each direction, so are roughly 64px across.
+const GEO_QUERY_CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+const GEO_QUERY_MAX_ITEMS: usize = 2000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeoQueryRequest {
+  south: f64,
+  west: f64,
+  north: f64,
+  east: f64,
+  /// The map zoom level (0 shows the whole world in one 256px tile). Nearby items are clustered if
+  /// this is below GEO_QUERY_CLUSTER_MAX_ZOOM. If not specified, items are never clustered.
+  zoom: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetItemLocationRequest {
+  id: Uid,
+  /// Both, or neither to clear the item's location.
+  latitude: Option<f64>,
+  longitude: Option<f64>,
+}
+
+struct LocatedItem {
+  json: Value,
+  location: ItemLocation,
+  creation_date: i64,
+}
+
+/// The session user's items located within a bounding box, most recently created first. At low zoom
+/// levels, items close to each other are returned as clusters instead.
+pub(super) async fn handle_geo_query(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to query item locations.")?;
+  let request: GeoQueryRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse geo query request: {}", e))?;
+  let bounds = GeoBounds { south: request.south, west: request.west, north: request.north, east: request.east };
+  bounds.validate()?;
+
+  let data_dir = db.lock().await.item.data_dir().to_owned();
+  let locations = query_user_item_locations(&data_dir, db.clone(), &session.user_id, &bounds).await?;
+
+  let mut located_items = {
+    let db = db.lock().await;
+    locations
+      .into_iter()
+      .filter_map(|(item_id, location)| {
+        let item = db.item.get(&item_id).ok()?;
+        if item.owner_id != session.user_id || item_is_in_trash(&db, item) {
+          return None;
+        }
+        Some(LocatedItem {
+          json: serde_json::json!({
+            "id": item.id,
+            "itemType": item.item_type.as_str(),
+            "title": item.title,
+            "mimeType": item.mime_type,
+            "latitude": location.latitude,
+            "longitude": location.longitude,
+            "locationSource": location.source.as_str(),
+          }),
+          location,
+          creation_date: item.original_creation_date.unwrap_or(item.creation_date),
+        })
+      })
+      .collect::<Vec<_>>()
+  };
+  located_items.sort_by_key(|item| std::cmp::Reverse(item.creation_date));
+
+  let (items, clusters) = match request.zoom.filter(|zoom| *zoom < GEO_QUERY_CLUSTER_MAX_ZOOM) {
+    Some(zoom) => cluster_located_items(located_items, &bounds, zoom),
+    None => (located_items, vec![]),
+  };
+  let truncated = items.len() > GEO_QUERY_MAX_ITEMS;
+  let items = items.into_iter().take(GEO_QUERY_MAX_ITEMS).map(|item| item.json).collect::<Vec<_>>();
+
+  debug!(
+    "Executed 'geo-query' command for user '{}': {} item(s), {} cluster(s).",
+    session.user_id,
+    items.len(),
+    clusters.len()
+  );
+  let result = serde_json::json!({ "items": items, "clusters": clusters, "truncated": truncated });
+  Ok(Some(result.to_string()))
+}
+
+/// Set the location of any item owned by the session user, or clear it. A location set on an image
+/// takes precedence over its GPS coordinates.
+pub(super) async fn handle_set_item_location(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to set an item location.")?;
+  let request: SetItemLocationRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse set item location request: {}", e))?;
+  let (data_dir, item_json, is_image) = {
+    let db = db.lock().await;
+    let item = db.item.get(&request.id)?;
+    if item.owner_id != session.user_id {
+      return Err(format!("Not authorized to set the location of item '{}'.", request.id).into());
+    }
+    let mut item_json = item.to_api_json()?;
+    // Both fields are always sent, since an update without them leaves the location unchanged.
+    item_json.insert("latitude".to_owned(), request.latitude.map(Value::from).unwrap_or(Value::Null));
+    item_json.insert("longitude".to_owned(), request.longitude.map(Value::from).unwrap_or(Value::Null));
+    (db.item.data_dir().to_owned(), Value::Object(item_json).to_string(), should_tag_image_item(item))
+  };
+  item_ops::update_item_for_user(db, &item_json, &session.user_id).await?;
+
+  // Clearing the location of an image may reveal its GPS coordinates.
+  let location = match (request.latitude, request.longitude) {
+    (Some(latitude), Some(longitude)) => Some(ItemLocation { latitude, longitude, source: ItemLocationSource::Manual }),
+    _ if is_image => image_exif_location(&data_dir, &session.user_id, &request.id).await?,
+    _ => None,
+  };
+  let result = serde_json::json!({
+    "id": request.id,
+    "latitude": location.map(|location| location.latitude),
+    "longitude": location.map(|location| location.longitude),
+    "locationSource": location.map(|location| location.source.as_str()),
+  });
+  Ok(Some(result.to_string()))
+}
+
+/// Group the items into a grid whose cell size follows the zoom level. Cells with a single item are
+/// left as items, others become a cluster represented by their most recently created item.
+fn cluster_located_items(
+  located_items: Vec<LocatedItem>,
+  bounds: &GeoBounds,
+  zoom: u32,
+) -> (Vec<LocatedItem>, Vec<Value>) {
+  let cell_degrees = 360.0 / (2f64.powi(zoom as i32) * GEO_QUERY_CLUSTER_CELLS_PER_TILE);
+  let longitude_offset = |longitude: f64| (longitude - bounds.west).rem_euclid(360.0);
+
+  let mut cells = HashMap::<(i64, i64), Vec<LocatedItem>>::new();
+  let mut cell_order = vec![];
+  for located_item in located_items {
+    let cell = (
+      ((located_item.location.latitude + 90.0) / cell_degrees).floor() as i64,
+      (longitude_offset(located_item.location.longitude) / cell_degrees).floor() as i64,
+    );
+    let cell_items = cells.entry(cell).or_default();
+    if cell_items.is_empty() {
+      cell_order.push(cell);
+    }
+    cell_items.push(located_item);
+  }
+
+  let mut items = vec![];
+  let mut clusters = vec![];
+  for cell in cell_order {
+    let mut cell_items = cells.remove(&cell).unwrap();
+    if cell_items.len() == 1 {
+      items.push(cell_items.pop().unwrap());
+      continue;
+    }
+    let count = cell_items.len() as f64;
+    let latitudes = cell_items.iter().map(|item| item.location.latitude).collect::<Vec<_>>();
+    let offsets = cell_items.iter().map(|item| longitude_offset(item.location.longitude)).collect::<Vec<_>>();
+    let to_longitude = |offset: f64| (bounds.west + offset + 180.0).rem_euclid(360.0) - 180.0;
+    clusters.push(serde_json::json!({
+      "latitude": latitudes.iter().sum::<f64>() / count,
+      "longitude": to_longitude(offsets.iter().sum::<f64>() / count),
+      "count": cell_items.len(),
+      "south": latitudes.iter().cloned().fold(f64::INFINITY, f64::min),
+      "north": latitudes.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+      "west": to_longitude(offsets.iter().cloned().fold(f64::INFINITY, f64::min)),
+      "east": to_longitude(offsets.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+      "item": cell_items[0].json,
+    }));
+  }
+  (items, clusters)
+}
@@ -134,6 +134,19 @@ pub async fn add_item_for_user(
   json_data: &str,
   base64_data_maybe: &Option<String>,
   session_user_id: &str,
+) -> InfuResult<Option<String>> {
+  add_item_with_mime_type_for_user(db, object_store, json_data, base64_data_maybe, session_user_id, None).await
+}
+
+/// As `add_item_for_user`, but with the mime type of a data item set by the server (rather than
+/// detected from its data) when `mime_type_maybe` is given. Clients can not set the mime type.
+pub(super) async fn add_item_with_mime_type_for_user(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  json_data: &str,
+  base64_data_maybe: &Option<String>,
+  session_user_id: &str,
+  mime_type_maybe: Option<&str>,
 ) -> InfuResult<Option<String>> {
   let session_user_id = session_user_id.to_owned();
 
@@ -389,12 +402,48 @@ pub async fn add_item_for_user(
         .into(),
       );
     }
-    item.mime_type = Some(detect_data_item_mime_type(&item, &decoded));
+    item.mime_type = Some(match mime_type_maybe {
+      Some(mime_type) => mime_type.to_owned(),
+      None => detect_data_item_mime_type(&item, &decoded),
+    });
+    if item.item_type == ItemType::File {
+      let mime_type = item.mime_type.as_deref().unwrap_or_default();
+      if is_audio_mime_type(mime_type) || is_video_mime_type(mime_type) {
+        item.duration_ms = media_duration_ms(mime_type, &decoded);
+      }
+      if let Some((width, height)) = video_size_px(mime_type, &decoded) {
+        item.image_size_px = Some(Dimensions { w: width as i64, h: height as i64 });
+        item.natural_aspect = Some(((width as f64) / (height as f64) * 1000.0).round() / 1000.0);
+      }
+    }
     let object_encryption_key = object_encryption_key_maybe
       .as_ref()
       .ok_or("Internal error: encryption key should have been set for data item.")?;
     object::put(object_store.clone(), &session_user_id, &item.id, &decoded, object_encryption_key).await?;
 
+    // Images in formats that can't be displayed directly are kept as they are, with a JPEG rendition
+    // used in their place everywhere but download. If no rendition can be made, the image is kept as
+    // a file instead.
+    let mut image_data = decoded;
+    let mime_type = item.mime_type.clone().unwrap_or_default();
+    if is_image_item(&item) && needs_image_rendition(&mime_type) {
+      match create_image_rendition(&mime_type, &image_data, &item.id).await {
+        Ok(rendition) => {
+          let data_dir = db.lock().await.item.data_dir().to_owned();
+          write_image_rendition(&data_dir, &session_user_id, &item.id, &rendition, object_encryption_key).await?;
+          // The rendition of a RAW image is made from its preview, which may be smaller than the sensor.
+          item.image_size_px = Some(Dimensions { w: -1, h: -1 });
+          image_data = rendition;
+        }
+        Err(e) => {
+          warn!("Could not create a rendition of image '{}' ({}), adding it as a file: {}", item.id, mime_type, e);
+          item.item_type = ItemType::File;
+          item.image_size_px = None;
+          item.thumbnail = None;
+        }
+      }
+    }
+
     if is_image_item(&item) {
       let title = match &item.title {
         Some(title) => title,
@@ -403,8 +452,8 @@ pub async fn add_item_for_user(
         }
       };
       // TODO (LOW): clone here seems a bit excessive.
-      let exif_orientation = get_exif_orientation(decoded.clone(), title);
-      let file_cursor = Cursor::new(decoded);
+      let exif_orientation = get_exif_orientation(image_data.clone(), title);
+      let file_cursor = Cursor::new(image_data);
       let file_reader = ImageReader::new(file_cursor).with_guessed_format()?;
       let img = file_reader
         .decode()
@@ -503,12 +552,19 @@ pub async fn add_item_for_user(
     if is_data_item_type(queued_item.item_type) {
       record_object_store_backed_item_upload(&queued_item.id);
     }
+    record_structured_item_changes(
+      &queued_item.owner_id,
+      [Some(&queued_item.id), queued_item.parent_id.as_ref()].into_iter().flatten(),
+    );
     enqueue_item_title_index_reconcile_for_user(&queued_item.owner_id);
     if should_tag_image_item(&queued_item) {
       enqueue_image_semantic_pipeline_item_if_active(&queued_item);
+      enqueue_near_duplicate_scan_for_user(&queued_item.owner_id);
     }
     enqueue_pdf_item_if_active(&queued_item);
     enqueue_document_fragment_item_if_active(&queued_item);
+    enqueue_link_snapshot_item_if_active(&queued_item);
+    enqueue_audio_transcript_item_if_active(&queued_item);
     return json_with_sync_ack(sync_ack, Some(serialized_item));
   }
 }
@@ -518,8 +574,6 @@ pub(super) async fn handle_update_item(
   json_data: &str,
   session_maybe: &Option<Session>,
 ) -> InfuResult<Option<String>> {
-  let mut db = db.lock().await;
-
   let session = match session_maybe {
     Some(session) => session,
     None => {
@@ -527,21 +581,36 @@ pub(super) async fn handle_update_item(
     }
   };
 
+  update_item_for_user(db, json_data, &session.user_id).await
+}
+
+pub(super) async fn update_item_for_user(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_user_id: &str,
+) -> InfuResult<Option<String>> {
+  let mut db = db.lock().await;
+
   let deserializer = serde_json::Deserializer::from_str(json_data);
   let mut iterator = deserializer.into_iter::<serde_json::Value>();
   let item_map_maybe = iterator.next().ok_or("Update item request has no item.")??;
   let item_map = item_map_maybe.as_object().ok_or("Update item request body is not a JSON object.")?;
-  let item: Item = Item::from_api_json(item_map)?;
-  if search_status_page_kind_for_id(&session.user_id, &item.id).is_some() {
+  let mut item: Item = Item::from_api_json(item_map)?;
+  if search_status_page_kind_for_id(session_user_id, &item.id).is_some() {
     return Err(format!("Virtual search status page '{}' cannot be updated.", item.id).into());
   }
   let old_item = db.item.get(&item.id)?.clone();
+  // The web client does not know item locations, so an update without them keeps the current one.
+  if !item_map.contains_key("latitude") && !item_map.contains_key("longitude") {
+    item.latitude = old_item.latitude;
+    item.longitude = old_item.longitude;
+  }
 
-  if &old_item.owner_id != &session.user_id {
+  if old_item.owner_id != session_user_id {
     return Err(
       format!(
         "Item owner_id '{}' mismatch with session user '{}' when updating item '{}'.",
-        item.owner_id, session.user_id, item.id
+        item.owner_id, session_user_id, item.id
       )
       .into(),
     );
@@ -607,6 +676,10 @@ pub(super) async fn handle_update_item(
     image_fragment_context_dependents_for_parent_title_change(&db, &old_item, &item)?;
   debug!("Executed 'update-item' command for item '{}'.", item.id);
   drop(db);
+  record_structured_item_changes(
+    &owner_id,
+    [Some(&item.id), old_item.parent_id.as_ref(), item.parent_id.as_ref()].into_iter().flatten(),
+  );
   enqueue_item_title_index_reconcile_for_user(&owner_id);
   if should_tag_image_item(&item) {
     enqueue_image_semantic_pipeline_item_if_active(&item);
@@ -619,6 +692,9 @@ pub(super) async fn handle_update_item(
   if should_fragment_document_item(&item) {
     enqueue_document_fragment_item_if_active(&item);
   }
+  if link_snapshot_url(&item) != link_snapshot_url(&old_item) {
+    enqueue_link_snapshot_item_if_active(&item);
+  }
 
   json_with_sync_ack(sync_ack, None)
 }
@@ -653,6 +729,25 @@ fn detect_data_item_mime_type(item: &Item, data: &[u8]) -> String {
       return extension_mime_type;
     }
   }
+  // Many camera RAW formats are TIFF based, and are only recognizable as RAW by their extension.
+  if matches!(detected_mime_type.as_str(), "image/tiff" | "image/x-canon-cr2" | "application/octet-stream") {
+    if let Some(extension_mime_type) = item
+      .title
+      .as_deref()
+      .and_then(mime_type_from_title_extension)
+      .filter(|mime_type| is_raw_image_mime_type(mime_type))
+    {
+      return extension_mime_type;
+    }
+  }
+  // M4A files are MP4 containers, and are only recognizable as audio by their brand or extension.
+  if detected_mime_type == "video/mp4" {
+    if let Some(extension_mime_type) =
+      item.title.as_deref().and_then(mime_type_from_title_extension).filter(|mime_type| mime_type == "audio/mp4")
+    {
+      return extension_mime_type;
+    }
+  }
   detected_mime_type
 }
 
@@ -705,6 +800,7 @@ pub(super) async fn handle_delete_item<'a>(
   }
 
   let data_dir = db.item.data_dir().to_owned();
+  let object_encryption_key = db.user.get(&session.user_id).ok_or("User not found.")?.object_encryption_key.clone();
   let item = db.item.get(&request.id)?.clone();
   let old_child_container_id = maybe_container_id_for_child_item(&item);
   let old_attachment_parent_id =
@@ -712,11 +808,16 @@ pub(super) async fn handle_delete_item<'a>(
   dequeue_image_semantic_pipeline_item_if_active(&request.id);
   dequeue_pdf_item_if_active(&request.id);
   dequeue_document_fragment_item_if_active(&request.id);
+  dequeue_link_snapshot_item_if_active(&request.id);
+  dequeue_audio_transcript_item_if_active(&request.id);
 
-  if is_image_item(&item) {
+  if is_image_item(&item) || is_video_item(&item) {
     let num_removed = storage_cache::delete_all(image_cache, &session.user_id, &request.id).await?;
     debug!("Deleted all {} entries related to item '{}' from image cache.", num_removed, request.id);
   }
+  if is_image_item(&item) {
+    delete_image_rendition(&data_dir, &session.user_id, &request.id).await?;
+  }
 
   if is_data_item_type(item.item_type) {
     object::delete(object_store.clone(), &session.user_id, &request.id).await?;
@@ -726,6 +827,8 @@ pub(super) async fn handle_delete_item<'a>(
   delete_item_text_dir(&data_dir, &session.user_id, &request.id).await?;
   delete_item_image_tag_dir(&data_dir, &session.user_id, &request.id).await?;
   delete_item_geo_artifacts(&data_dir, &session.user_id, &request.id).await?;
+  delete_item_link_snapshot_artifacts(&data_dir, &session.user_id, &request.id).await?;
+  delete_item_audio_transcript_artifacts(&data_dir, &session.user_id, &request.id).await?;
   delete_item_fragment_artifacts(&data_dir, &session.user_id, &request.id).await?;
   let deleted_index_fragments = delete_item_fragment_index_entries(&data_dir, &session.user_id, &request.id).await?;
   if deleted_index_fragments > 0 {
@@ -752,6 +855,13 @@ pub(super) async fn handle_delete_item<'a>(
   let owner_id = item.owner_id.clone();
   debug!("Deleted item '{}' from database.", request.id);
   drop(db);
+  forget_image_location(&owner_id, &request.id);
+  forget_image_capture_time(&owner_id, &request.id);
+  let deleted_item_ids = [request.id.clone()];
+  if let Err(e) = delete_items_face_artifacts(&data_dir, &owner_id, &object_encryption_key, &deleted_item_ids).await {
+    warn!("Could not delete the face artifacts of deleted item '{}': {}", request.id, e);
+  }
+  record_structured_item_changes(&owner_id, item.parent_id.as_ref());
   enqueue_item_title_index_reconcile_for_user(&owner_id);
   enqueue_fragment_index_rebuild_for_user(&owner_id);
 
@@ -774,15 +884,18 @@ pub(super) async fn handle_empty_trash<'a>(
   };
 
   let trash_page_id;
+  let object_encryption_key;
   {
     let user = db.user.get(&session.user_id).ok_or(format!("user not found").as_str())?;
     trash_page_id = user.trash_page_id.clone();
+    object_encryption_key = user.object_encryption_key.clone();
   }
 
   let mut count = 0;
   let mut img_cache_count = 0;
   let mut object_count = 0;
   let mut touched_container_ids = HashSet::new();
+  let mut deleted_item_ids = vec![];
   delete_recursive(
     &mut db,
     object_store,
@@ -794,11 +907,20 @@ pub(super) async fn handle_empty_trash<'a>(
     &mut img_cache_count,
     &mut object_count,
     &mut touched_container_ids,
+    &mut deleted_item_ids,
   )
   .await?;
   let sync_ack = build_sync_ack(&db, &session.user_id, &touched_container_ids);
   let user_id = session.user_id.clone();
+  let data_dir = db.item.data_dir().to_owned();
   drop(db);
+  for item_id in &deleted_item_ids {
+    forget_image_location(&user_id, item_id);
+    forget_image_capture_time(&user_id, item_id);
+  }
+  if let Err(e) = delete_items_face_artifacts(&data_dir, &user_id, &object_encryption_key, &deleted_item_ids).await {
+    warn!("Could not delete the face artifacts of {} item(s) deleted from the trash: {}", deleted_item_ids.len(), e);
+  }
   enqueue_item_title_index_reconcile_for_user(&user_id);
   enqueue_fragment_index_rebuild_for_user(&user_id);
 
@@ -823,6 +945,7 @@ async fn delete_recursive(
   img_cache_count: &mut u64,
   object_count: &mut u64,
   touched_container_ids: &mut HashSet<Uid>,
+  deleted_item_ids: &mut Vec<Uid>,
 ) -> InfuResult<()> {
   for attachment_id in db.item.get_attachment_ids(&item_id)? {
     delete_recursive(
@@ -836,6 +959,7 @@ async fn delete_recursive(
       img_cache_count,
       object_count,
       touched_container_ids,
+      deleted_item_ids,
     )
     .await?;
   }
@@ -851,6 +975,7 @@ async fn delete_recursive(
       img_cache_count,
       object_count,
       touched_container_ids,
+      deleted_item_ids,
     )
     .await?;
   }
@@ -864,12 +989,17 @@ async fn delete_recursive(
     dequeue_image_semantic_pipeline_item_if_active(&item_id);
     dequeue_pdf_item_if_active(&item_id);
     dequeue_document_fragment_item_if_active(&item_id);
+    dequeue_link_snapshot_item_if_active(&item_id);
+    dequeue_audio_transcript_item_if_active(&item_id);
 
-    if is_image_item(&item) {
+    if is_image_item(&item) || is_video_item(&item) {
       let num_removed = storage_cache::delete_all(image_cache, &user_id, &item.id).await?;
       debug!("Deleted all {} entries related to item '{}' from image cache.", num_removed, item.id);
       *img_cache_count = *img_cache_count + num_removed as u64;
     }
+    if is_image_item(&item) {
+      delete_image_rendition(&data_dir, &user_id, &item.id).await?;
+    }
 
     if is_data_item_type(item.item_type) {
       object::delete(object_store.clone(), &user_id, &item.id).await?;
@@ -880,6 +1010,8 @@ async fn delete_recursive(
     delete_item_text_dir(&data_dir, user_id, &item.id).await?;
     delete_item_image_tag_dir(&data_dir, user_id, &item.id).await?;
     delete_item_geo_artifacts(&data_dir, user_id, &item.id).await?;
+    delete_item_link_snapshot_artifacts(&data_dir, user_id, &item.id).await?;
+    delete_item_audio_transcript_artifacts(&data_dir, user_id, &item.id).await?;
     delete_item_fragment_artifacts(&data_dir, user_id, &item.id).await?;
     let deleted_index_fragments = delete_item_fragment_index_entries(&data_dir, user_id, &item.id).await?;
     if deleted_index_fragments > 0 {
@@ -896,6 +1028,7 @@ async fn delete_recursive(
       }
     }
     debug!("Deleted item '{}' from database.", item_id);
+    deleted_item_ids.push(item_id);
 
     *count = *count + 1;
   }
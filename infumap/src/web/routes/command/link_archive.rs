@@ -0,0 +1,90 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use time::OffsetDateTime;
+
+use super::item_ops::add_item_with_mime_type_for_user;
+use crate::util::mime::WEB_ARCHIVE_MIME_TYPE;
+use crate::web::routes::web_archive::archive_link_page;
+
+const WEB_ARCHIVE_ATTACHMENT_WIDTH_GR: i64 = 6 * GRID_SIZE;
+
+#[derive(Deserialize)]
+struct ArchiveLinkRequest {
+  id: Uid,
+}
+
+/// Fetch the page a link note points to as a self-contained HTML archive and add it as a file
+/// attachment of the note.
+pub(super) async fn handle_archive_link(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  object_store: Arc<object::ObjectStore>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    Some(session) => session,
+    None => {
+      return Err(format!("Session is required to archive a link.").into());
+    }
+  };
+
+  let request: ArchiveLinkRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse archive link request: {}", e))?;
+  if !is_uid(&request.id) {
+    return Err(format!("Invalid item id '{}' in archive link request.", request.id).into());
+  }
+
+  let url = {
+    let db = db.lock().await;
+    let item = db.item.get(&request.id)?;
+    if item.owner_id != session.user_id {
+      return Err(format!("Item '{}' is not owned by the session user.", request.id).into());
+    }
+    link_snapshot_url(item).ok_or(format!("Item '{}' is not a link note.", request.id))?
+  };
+  let url = link_titles::normalize_link_url(&url)?;
+
+  let archive = archive_link_page(&url).await?.ok_or(format!("Could not fetch '{}' to archive it.", url))?;
+  let now = OffsetDateTime::now_utc();
+  let page_title = archive.title.clone().or_else(|| archive.url.host_str().map(str::to_owned)).unwrap_or_default();
+  let title = format!("{} (archived {:04}-{:02}-{:02}).html", page_title, now.year(), u8::from(now.month()), now.day());
+  let item_json = serde_json::json!({
+    "itemType": ItemType::File.as_str(),
+    "parentId": request.id,
+    "relationshipToParent": "attachment",
+    "title": title,
+    "spatialWidthGr": WEB_ARCHIVE_ATTACHMENT_WIDTH_GR,
+    "fileSizeBytes": archive.html.len(),
+    "originalCreationDate": unix_now_secs_u64().unwrap() as i64,
+  });
+  let base64_data = Some(general_purpose::STANDARD.encode(archive.html.as_bytes()));
+
+  debug!(
+    "Archived link '{}' for note '{}' ({} resource(s) inlined, {} skipped).",
+    archive.url, request.id, archive.resource_count, archive.skipped_resource_count
+  );
+  add_item_with_mime_type_for_user(
+    db,
+    object_store,
+    &item_json.to_string(),
+    &base64_data,
+    &session.user_id,
+    Some(WEB_ARCHIVE_MIME_TYPE),
+  )
+  .await
+}
@@ -0,0 +1,92 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use crate::export::markdown::export_markdown;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownRequest {
+  container_id: Uid,
+  /// Include password item secrets in the markdown and sidecar. They are masked otherwise.
+  #[serde(default)]
+  include_passwords: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownFile {
+  path: String,
+  base64_data: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownAsset {
+  item_id: Uid,
+  path: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMarkdownResponse {
+  files: Vec<ExportMarkdownFile>,
+  assets: Vec<ExportMarkdownAsset>,
+  page_count: usize,
+  file_count: usize,
+  item_count: usize,
+  skipped_count: usize,
+}
+
+/// Export a container the session user owns, and everything below it, as markdown documents and an
+/// infumap.json layout sidecar, each base64 encoded with its path relative to the root of the export.
+/// File and image data is not included: each asset gives the path its item's data belongs at, and is
+/// downloaded separately from /files/{itemId}.
+pub(super) async fn handle_export_markdown(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to export markdown.")?;
+  let request: ExportMarkdownRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse export markdown request: {}", e))?;
+  if !is_uid(&request.container_id) {
+    return Err(format!("Invalid container id '{}' in export markdown request.", request.container_id).into());
+  }
+  if db.lock().await.item.get(&request.container_id)?.owner_id != session.user_id {
+    return Err(format!("Not authorized to access container '{}'.", request.container_id).into());
+  }
+
+  let export = export_markdown(db, &session.user_id, &request.container_id, request.include_passwords).await?;
+  let response = ExportMarkdownResponse {
+    files: export
+      .files
+      .into_iter()
+      .map(|file| ExportMarkdownFile { path: file.path, base64_data: general_purpose::STANDARD.encode(file.data) })
+      .collect(),
+    assets: export
+      .assets
+      .into_iter()
+      .map(|asset| ExportMarkdownAsset { item_id: asset.item_id, path: asset.path })
+      .collect(),
+    page_count: export.summary.pages,
+    file_count: export.summary.files,
+    item_count: export.summary.items,
+    skipped_count: export.summary.skipped_items,
+  };
+  Ok(Some(serde_json::to_string(&response)?))
+}
@@ -47,47 +47,68 @@ use std::time::{Duration, Instant};
 use tokio::sync::MutexGuard;
 
 use super::link_titles;
+use crate::ai::audio_pipeline::{
+  delete_item_audio_transcript_artifacts, dequeue_audio_transcript_item_if_active,
+  enqueue_audio_transcript_item_if_active,
+};
 use crate::ai::document_pipeline::{
   dequeue_document_fragment_item_if_active, enqueue_document_fragment_item_if_active, is_document_fragment_item,
 };
+use crate::ai::faces::{delete_items_face_artifacts, item_ids_for_face_name};
+use crate::ai::fragment::sources::{TableCellText, table_row_cells_for_row_item};
 use crate::ai::fragment::{
-  ITEM_TITLE_SOURCE_KIND, delete_item_fragment_artifacts, is_lexical_search_source_kind,
+  ITEM_TITLE_SOURCE_KIND, TABLE_ROWS_SOURCE_KIND, delete_item_fragment_artifacts, is_lexical_search_source_kind,
   is_markdown_document_source_kind,
 };
 use crate::ai::fragment_indexing::enqueue_fragment_index_rebuild_for_user;
 use crate::ai::geo::delete_item_geo_artifacts;
+use crate::ai::geo_index::forget_image_location;
+use crate::ai::gpu_tools::{GPU_TOOL_IMAGE_TEXT_EMBED, resolve_configured_gpu_tool_url};
 use crate::ai::image_pipeline::{
   dequeue_image_semantic_pipeline_item_if_active, enqueue_image_semantic_pipeline_item_if_active,
 };
 use crate::ai::image_tagging::{delete_item_image_tag_dir, should_tag_image_item};
+use crate::ai::image_vectors::embed_image_query_text;
 use crate::ai::indexing::delete_item_fragment_index_entries;
 use crate::ai::lexical_index::{
   FragmentLexicalHit, open_user_document_fragment_lexical_index, open_user_item_title_lexical_index,
   user_document_fragment_lexical_index_exists, user_item_title_lexical_index_exists,
 };
+use crate::ai::link_snapshot_pipeline::{
+  delete_item_link_snapshot_artifacts, dequeue_link_snapshot_item_if_active, enqueue_link_snapshot_item_if_active,
+  link_snapshot_url,
+};
 use crate::ai::metrics::{METRIC_SEARCH_BACKEND_DURATION_SECONDS, METRIC_SEARCH_BACKEND_FAILURES_TOTAL};
+use crate::ai::near_duplicates::enqueue_near_duplicate_scan_for_user;
 use crate::ai::search_status::{
   SearchStatusArtifact, SearchStatusPageKind, read_search_status_artifact, search_failed_page_id,
   search_pending_page_id, search_status_link_id, search_status_page_id, search_status_page_kind_for_route_id,
 };
+use crate::ai::structured_indexing::record_structured_item_changes;
 use crate::ai::text_embedding::{
   TextEmbeddingInput, embed_texts, resolve_configured_text_embedding_service_url, text_embedding_vector_fingerprint,
   text_embedding_vector_norm, validate_text_embedding_vector,
 };
 use crate::ai::text_extraction::{delete_item_text_dir, dequeue_pdf_item_if_active, enqueue_pdf_item_if_active};
+use crate::ai::timeline::forget_image_capture_time;
 use crate::ai::title_indexing::enqueue_item_title_index_reconcile_for_user;
 use crate::ai::upload_quiet_period::record_object_store_backed_item_upload;
 use crate::ai::vector_db::{
-  FragmentVectorDbBackend, FragmentVectorHit, open_user_fragment_vector_db, user_fragment_vector_db_exists,
+  EmbeddedFragment, FragmentVectorDbBackend, FragmentVectorHit, ImageVectorHit, image_vector_db_path,
+  open_user_fragment_vector_db, open_user_image_vector_db, user_fragment_vector_db_exists,
 };
-use crate::config::CONFIG_LLAMA_SERVER_URL;
 use crate::storage::cache as storage_cache;
 use crate::storage::db::Db;
 use crate::storage::db::container_sync::{ContainerSyncDelta, ContainerSyncLookup, ContainerSyncVersion};
 use crate::storage::db::session::Session;
 use crate::storage::db::user::ROOT_USER_NAME;
 use crate::storage::object;
+use crate::util::fs::path_exists;
 use crate::util::image::{adjust_image_for_exif_orientation, get_exif_orientation};
+use crate::util::image_rendition::{
+  create_image_rendition, delete_image_rendition, is_raw_image_mime_type, needs_image_rendition, write_image_rendition,
+};
+use crate::util::media::{is_audio_mime_type, is_video_item, is_video_mime_type, media_duration_ms, video_size_px};
 use crate::util::mime::{detect_mime_type, mime_type_from_title_extension};
 use crate::util::ordering::{new_ordering, new_ordering_after, new_ordering_at_end};
 use crate::web::serve::{cors_response, incoming_json_with_limit, json_response};
@@ -95,8 +116,15 @@ use crate::web::session::get_and_validate_session;
 use std::collections::{HashMap, HashSet};
 
 mod chat;
+mod email_import;
+mod faces;
+mod geo;
 mod item_ops;
+mod link_archive;
+mod markdown_export;
+mod near_duplicates;
 mod search;
+mod timeline;
 
 pub use chat::serve_chat_stream_route;
 pub use item_ops::add_item_for_user;
@@ -210,13 +238,42 @@ pub async fn serve_command_route(
     "add-link-note" => {
       item_ops::handle_add_link_note(db, object_store.clone(), &request.json_data, &session_maybe).await
     }
+    "archive-link" => {
+      link_archive::handle_archive_link(db, object_store.clone(), &request.json_data, &session_maybe).await
+    }
+    "import-email" => {
+      email_import::handle_import_email(
+        db,
+        object_store.clone(),
+        &request.json_data,
+        &request.base64_data,
+        &session_maybe,
+      )
+      .await
+    }
     "update-item" => item_ops::handle_update_item(db, &request.json_data, &session_maybe).await,
     "delete-item" => {
       item_ops::handle_delete_item(db, object_store.clone(), image_cache, &request.json_data, &session_maybe).await
     }
     "sync-containers" => handle_sync_containers(db, &request.json_data, &session_maybe).await,
     "search" => search::handle_search(config, db, &request.json_data, &session_maybe).await,
-    "chat" => chat::handle_chat(config, db, &request.json_data, &session_maybe).await,
+    "related-items" => search::handle_related_items(db, &request.json_data, &session_maybe).await,
+    "similar-images" => search::handle_similar_images(db, &request.json_data, &session_maybe).await,
+    "near-duplicates" => near_duplicates::handle_near_duplicates(db, &session_maybe).await,
+    "trash-near-duplicates" => {
+      near_duplicates::handle_trash_near_duplicates(db, &request.json_data, &session_maybe).await
+    }
+    "geo-query" => geo::handle_geo_query(db, &request.json_data, &session_maybe).await,
+    "set-item-location" => geo::handle_set_item_location(db, &request.json_data, &session_maybe).await,
+    "timeline" => timeline::handle_timeline(db, &request.json_data, &session_maybe).await,
+    "export-markdown" => markdown_export::handle_export_markdown(db, &request.json_data, &session_maybe).await,
+    "face-clusters" => faces::handle_face_clusters(db, &session_maybe).await,
+    "name-face-cluster" => faces::handle_name_face_cluster(db, &request.json_data, &session_maybe).await,
+    "chat" => chat::handle_chat(config, db, object_store.clone(), &request.json_data, &session_maybe).await,
+    "chat-confirm" => chat::handle_chat_confirm(&request.json_data, &session_maybe).await,
+    "chat-models" => chat::handle_list_chat_models(config, &session_maybe).await,
+    "chat-conversations" => chat::handle_list_chat_conversations(db, &request.json_data, &session_maybe).await,
+    "get-chat-conversation" => chat::handle_get_chat_conversation(db, &request.json_data, &session_maybe).await,
     "empty-trash" => item_ops::handle_empty_trash(db, object_store.clone(), image_cache, &session_maybe).await,
     _ => {
       if let Some(session) = &session_maybe {
@@ -1163,8 +1220,13 @@ async fn resolve_get_items_request_item_id(
   }
 
   let parts = request_id.split('/').collect::<Vec<&str>>();
+  if parts.len() == 2 {
+    let db = db.lock().await;
+    let item_id = item_id_for_username_and_label(&db, parts[0], parts[1])
+      .ok_or(format!("Item with label '{}' is unknown for user '{}'.", parts[1], parts[0]))?;
+    return Ok(item_id);
+  }
   if parts.len() != 1 {
-    // TODO (MEDIUM): implement ids of the form: /{username}/{item_label}.
     return Err(format!("Get items request id '{}' has unexpected format.", request_id).into());
   }
 
@@ -1179,6 +1241,13 @@ async fn resolve_get_items_request_item_id(
   }
 }
 
+/// Resolves /{username}/{label} to an item id. Labels an item was previously known by are also resolved, so that
+/// links keep working when a label is changed.
+pub fn item_id_for_username_and_label(db: &MutexGuard<'_, Db>, username: &str, label: &str) -> Option<Uid> {
+  let user = db.user.get_by_username_case_insensitive(username)?;
+  db.item.item_id_for_label(&user.id, label).or_else(|| db.item.item_id_for_previous_label(&user.id, label)).cloned()
+}
+
 async fn maybe_handle_get_virtual_search_status_page_items(
   db: &Arc<tokio::sync::Mutex<Db>>,
   item_id: &Uid,
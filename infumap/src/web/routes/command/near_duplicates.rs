@@ -0,0 +1,186 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use crate::ai::near_duplicates::{item_is_in_trash, read_near_duplicate_report};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrashNearDuplicatesRequest {
+  /// The items to move to the trash. Each must be in a cluster of the most recent report.
+  item_ids: Vec<Uid>,
+}
+
+/// The clusters of the user's most recent near-duplicate scan, best item first. Items that have since
+/// been deleted or moved to the trash are left out, as are clusters with fewer than two items left.
+pub(super) async fn handle_near_duplicates(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to list near-duplicate images.")?;
+  let (generated_at_unix_secs, clusters) = current_near_duplicate_clusters(db, &session.user_id).await?;
+
+  let db = db.lock().await;
+  let clusters = clusters
+    .iter()
+    .map(|item_ids| {
+      let items = item_ids
+        .iter()
+        .filter_map(|(item_id, captured_at)| {
+          let item = db.item.get(item_id).ok()?;
+          Some(serde_json::json!({
+            "id": item.id,
+            "title": item.title,
+            "mimeType": item.mime_type,
+            "widthPx": item.image_size_px.as_ref().map(|size| size.w),
+            "heightPx": item.image_size_px.as_ref().map(|size| size.h),
+            "capturedAt": captured_at,
+          }))
+        })
+        .collect::<Vec<_>>();
+      serde_json::json!({ "items": items })
+    })
+    .collect::<Vec<_>>();
+  let result = serde_json::json!({ "generatedAtUnixSecs": generated_at_unix_secs, "clusters": clusters });
+  Ok(Some(result.to_string()))
+}
+
+/// Move the given items of near-duplicate clusters to the trash page. Items no longer in a cluster
+/// (for example, because they have already been moved) are skipped. The request is refused if it names
+/// an item that was never in a cluster, or every remaining item of a cluster, so that at least one copy
+/// of each image is always kept.
+pub(super) async fn handle_trash_near_duplicates(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to trash near-duplicate images.")?;
+  let request: TrashNearDuplicatesRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse trash near-duplicates request: {}", e))?;
+  if request.item_ids.is_empty() {
+    return Err("No near-duplicate images to trash were given.".into());
+  }
+  let trash_item_ids = request.item_ids.into_iter().collect::<HashSet<_>>();
+
+  let data_dir = db.lock().await.item.data_dir().to_owned();
+  let reported_item_ids = read_near_duplicate_report(&data_dir, &session.user_id)
+    .await?
+    .map(|report| {
+      report.clusters.into_iter().flat_map(|cluster| cluster.items).map(|item| item.item_id).collect::<HashSet<_>>()
+    })
+    .unwrap_or_default();
+  if let Some(item_id) = trash_item_ids.iter().find(|item_id| !reported_item_ids.contains(*item_id)) {
+    return Err(format!("Item '{}' is not in a near-duplicate cluster.", item_id).into());
+  }
+
+  let (_, clusters) = current_near_duplicate_clusters(db, &session.user_id).await?;
+  if let Some(item_ids) =
+    clusters.iter().find(|item_ids| item_ids.iter().all(|(item_id, _)| trash_item_ids.contains(item_id)))
+  {
+    return Err(
+      format!("Refusing to trash every remaining image of the near-duplicate cluster of item '{}'.", item_ids[0].0)
+        .into(),
+    );
+  }
+
+  let cluster_item_ids = clusters.into_iter().flatten().map(|(item_id, _)| item_id).collect::<Vec<_>>();
+  let mut moved_item_ids = vec![];
+  let mut skipped_item_ids =
+    trash_item_ids.iter().filter(|item_id| !cluster_item_ids.contains(*item_id)).cloned().collect::<Vec<_>>();
+  skipped_item_ids.sort();
+  for item_id in cluster_item_ids {
+    if !trash_item_ids.contains(&item_id) {
+      continue;
+    }
+    let item_json = {
+      let db = db.lock().await;
+      let trash_page_id = db.user.get(&session.user_id).ok_or("User not found.")?.trash_page_id.clone();
+      match item_moved_to_trash(&db, &item_id, &trash_page_id)? {
+        Some(moved_item) => Value::Object(moved_item.to_api_json()?).to_string(),
+        None => {
+          skipped_item_ids.push(item_id);
+          continue;
+        }
+      }
+    };
+    item_ops::update_item_for_user(db, &item_json, &session.user_id).await?;
+    moved_item_ids.push(item_id);
+  }
+
+  debug!(
+    "Moved {} near-duplicate image(s) to the trash for user '{}' ({} skipped).",
+    moved_item_ids.len(),
+    session.user_id,
+    skipped_item_ids.len()
+  );
+  enqueue_near_duplicate_scan_for_user(&session.user_id);
+  let result = serde_json::json!({ "movedItemIds": moved_item_ids, "skippedItemIds": skipped_item_ids });
+  Ok(Some(result.to_string()))
+}
+
+/// The report's clusters, as (item id, capture time) pairs, reduced to the items still present outside
+/// the trash.
+async fn current_near_duplicate_clusters(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  user_id: &str,
+) -> InfuResult<(Option<i64>, Vec<Vec<(Uid, Option<String>)>>)> {
+  let data_dir = db.lock().await.item.data_dir().to_owned();
+  let Some(report) = read_near_duplicate_report(&data_dir, user_id).await? else {
+    return Ok((None, vec![]));
+  };
+
+  let db = db.lock().await;
+  let clusters = report
+    .clusters
+    .into_iter()
+    .map(|cluster| {
+      cluster
+        .items
+        .into_iter()
+        .filter(|cluster_item| {
+          db.item.get(&cluster_item.item_id).is_ok_and(|item| item.owner_id == user_id && !item_is_in_trash(&db, item))
+        })
+        .map(|cluster_item| (cluster_item.item_id, cluster_item.captured_at))
+        .collect::<Vec<_>>()
+    })
+    .filter(|item_ids| item_ids.len() > 1)
+    .collect();
+  Ok((Some(report.generated_at_unix_secs), clusters))
+}
+
+/// The item as it would be after moving it to the end of the trash page, or None if it can't be moved
+/// there. Attachments are left alone, since they belong with their parent.
+fn item_moved_to_trash(db: &Db, item_id: &Uid, trash_page_id: &Uid) -> InfuResult<Option<Item>> {
+  let Ok(item) = db.item.get(item_id) else {
+    return Ok(None);
+  };
+  if item.relationship_to_parent != RelationshipToParent::Child || item.parent_id.as_ref() == Some(trash_page_id) {
+    return Ok(None);
+  }
+  let Ok(trash_children) = db.item.get_children(trash_page_id) else {
+    return Ok(None);
+  };
+  let orderings = trash_children.iter().map(|child| child.ordering.clone()).collect();
+  let mut moved_item = item.clone();
+  moved_item.parent_id = Some(trash_page_id.clone());
+  moved_item.ordering = new_ordering_at_end(orderings);
+  if moved_item.spatial_position_gr.is_some() {
+    moved_item.spatial_position_gr = Some(Vector { x: 0, y: 0 });
+  }
+  moved_item.last_modified_date = unix_now_secs_u64()? as i64;
+  Ok(Some(moved_item))
+}
@@ -20,6 +20,7 @@ const SEARCH_RRF_K: f64 = 60.0;
 const SEARCH_TITLE_LEXICAL_WEIGHT: f64 = 1.35;
 const SEARCH_LEXICAL_WEIGHT: f64 = 1.15;
 const SEARCH_SEMANTIC_WEIGHT: f64 = 1.0;
+const SEARCH_IMAGE_WEIGHT: f64 = 0.85;
 const SEARCH_CANDIDATE_OVERFETCH: i64 = 50;
 const SEARCH_LEXICAL_FRAGMENT_MULTIPLIER: usize = 4;
 const SEARCH_LEXICAL_MATCHES_PER_RESULT: usize = 2;
@@ -31,6 +32,8 @@ const SEARCH_MATCH_SNIPPET_MAX_SENTENCE_CHARS: usize = 220;
 const SEARCH_MATCH_SNIPPET_CONTEXT_BEFORE_CHARS: usize = 70;
 const SEARCH_MATCH_SNIPPET_BOUNDARY_SLOP_CHARS: usize = 20;
 const SEARCH_BM25_SCORE_SATURATION: f32 = 4.0;
+/// Search results considered when the results are restricted to photos of a person.
+const SEARCH_PERSON_FILTER_MAX_CANDIDATES: i64 = 1000;
 const SEARCH_SNIPPET_ELLIPSIS: &str = "...";
 const PDF_CATALOG_OMITTED_LABELS: [&str; 3] = ["document", "context", "section"];
 const SEARCH_SNIPPET_STOP_WORDS: [&str; 32] = [
@@ -47,6 +50,10 @@ pub struct SearchRequest {
   pub num_results: i64,
   #[serde(rename = "pageNum")]
   pub page_num: Option<i64>,
+  /// Restrict results to images with a face in a face cluster of this name. The search text may
+  /// then be empty, to list all of them.
+  #[serde(default)]
+  pub person: Option<String>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -98,6 +105,38 @@ pub struct SearchFragmentMatch {
   pub page_start: Option<usize>,
   #[serde(rename = "pageEnd", skip_serializing_if = "Option::is_none")]
   pub page_end: Option<usize>,
+  /// For transcripts of recordings, the span the fragment covers in seconds from the start, in place of a page range.
+  #[serde(rename = "timeStartSecs", skip_serializing_if = "Option::is_none")]
+  pub time_start_secs: Option<usize>,
+  #[serde(rename = "timeEndSecs", skip_serializing_if = "Option::is_none")]
+  pub time_end_secs: Option<usize>,
+  #[serde(rename = "tableCell", skip_serializing_if = "Option::is_none")]
+  pub table_cell: Option<SearchTableCellMatch>,
+}
+
+/// The table cell a table row fragment match points at: the cell whose text matched the query, or the
+/// first cell of the row if only the table title matched.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SearchTableCellMatch {
+  #[serde(rename = "rowItemId")]
+  pub row_item_id: Uid,
+  #[serde(rename = "rowIndex")]
+  pub row_index: usize,
+  #[serde(rename = "cellItemId")]
+  pub cell_item_id: Uid,
+  #[serde(rename = "columnIndex")]
+  pub column_index: usize,
+  #[serde(rename = "columnName")]
+  pub column_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct RelatedItemsRequest {
+  pub id: Uid,
+  #[serde(rename = "numResults")]
+  pub num_results: i64,
+  #[serde(rename = "pageNum")]
+  pub page_num: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -112,11 +151,13 @@ struct IndexedSearchBackends {
   title_lexical: bool,
   document_lexical: bool,
   semantic: bool,
+  image: bool,
 }
 
 impl IndexedSearchBackends {
-  const MIXED: Self = Self { title_lexical: true, document_lexical: true, semantic: true };
-  const LEXICAL: Self = Self { title_lexical: true, document_lexical: true, semantic: false };
+  const MIXED: Self = Self { title_lexical: true, document_lexical: true, semantic: true, image: true };
+  const LEXICAL: Self = Self { title_lexical: true, document_lexical: true, semantic: false, image: false };
+  const SEMANTIC: Self = Self { title_lexical: false, document_lexical: false, semantic: true, image: true };
 }
 
 #[allow(dead_code)]
@@ -161,6 +202,12 @@ pub(super) mod compact {
     pub page_start: Option<usize>,
     #[serde(rename = "pageEnd", skip_serializing_if = "Option::is_none")]
     pub page_end: Option<usize>,
+    #[serde(rename = "timeStartSecs", skip_serializing_if = "Option::is_none")]
+    pub time_start_secs: Option<usize>,
+    #[serde(rename = "timeEndSecs", skip_serializing_if = "Option::is_none")]
+    pub time_end_secs: Option<usize>,
+    #[serde(rename = "tableCell", skip_serializing_if = "Option::is_none")]
+    pub table_cell: Option<SearchTableCellMatch>,
   }
 
   pub(super) fn compact_search_response(response: &SearchResponse) -> CompactSearchResponse {
@@ -206,6 +253,9 @@ pub(super) mod compact {
       text_truncated: fragment_match.text_truncated,
       page_start: fragment_match.page_start,
       page_end: fragment_match.page_end,
+      time_start_secs: fragment_match.time_start_secs,
+      time_end_secs: fragment_match.time_end_secs,
+      table_cell: fragment_match.table_cell.clone(),
     }
   }
 }
@@ -246,6 +296,16 @@ pub(super) async fn run_search(
 
   let (data_dir, search_root_id) = resolve_search_scope(db, request.page_id, session).await?;
 
+  if let Some(person) = request.person.as_deref().map(str::trim).filter(|person| !person.is_empty()) {
+    let results =
+      person_search_results(config, db, &data_dir, &search_root_id, full_user_search, &request.text, person, session)
+        .await?;
+    return Ok(search_response_from_results(
+      paginate_mixed_results(results, start_result, end_result),
+      request.num_results,
+    ));
+  }
+
   let results = if full_user_search {
     indexed_search_results(
       Some(config),
@@ -271,6 +331,299 @@ pub(super) async fn run_search(
   Ok(search_response_from_results(results, request.num_results))
 }
 
+/// Images with a face in a face cluster named `person`, which match the search text if there is any.
+/// Without search text, all of them under the search root are returned, most recently created first.
+async fn person_search_results(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  data_dir: &str,
+  search_root_id: &Uid,
+  full_user_search: bool,
+  search_text: &str,
+  person: &str,
+  session: &Session,
+) -> InfuResult<Vec<SearchResult>> {
+  let object_encryption_key = {
+    let db = db.lock().await;
+    db.user.get(&session.user_id).ok_or("User not found.")?.object_encryption_key.clone()
+  };
+  let Some(item_ids) = item_ids_for_face_name(data_dir, &session.user_id, &object_encryption_key, person).await? else {
+    return Err(format!("No face cluster is named '{}'.", person).into());
+  };
+
+  if search_text.trim().is_empty() {
+    let db = db.lock().await;
+    let mut items = item_ids
+      .iter()
+      .filter_map(|item_id| db.item.get(item_id).ok())
+      .map(|item| (item.original_creation_date.unwrap_or(item.creation_date), item.id.clone()))
+      .collect::<Vec<_>>();
+    items.sort_by(|a, b| b.cmp(a));
+    let mut results = vec![];
+    for (_, item_id) in items {
+      if let Some(mut result) = search_result_path_for_item(&db, &item_id, &session.user_id, search_root_id)? {
+        result.score = 1.0;
+        results.push(result);
+      }
+    }
+    return Ok(results);
+  }
+
+  let results = if full_user_search {
+    indexed_search_results(
+      Some(config),
+      db,
+      data_dir,
+      &session.user_id,
+      search_root_id,
+      search_text,
+      0,
+      SEARCH_PERSON_FILTER_MAX_CANDIDATES,
+      IndexedSearchBackends::MIXED,
+    )
+    .await?
+  } else {
+    let mut db = db.lock().await;
+    search_exact_paginated(
+      &mut db,
+      &search_text.to_lowercase(),
+      search_root_id.clone(),
+      &session.user_id,
+      0,
+      SEARCH_PERSON_FILTER_MAX_CANDIDATES,
+    )?
+  };
+  Ok(
+    results
+      .into_iter()
+      .filter(|result| search_result_item_id(result).is_some_and(|item_id| item_ids.contains(&item_id)))
+      .collect(),
+  )
+}
+
+pub(super) async fn handle_related_items(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    None => return Err("Sessionless related items lookup not supported".into()),
+    Some(s) => s,
+  };
+
+  let request: RelatedItemsRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("could not parse json_data {json_data}: {e}"))?;
+
+  let response = run_related_items(db, request, session).await?;
+  let serialized_results = serde_json::to_string(&response)?;
+
+  debug!("Executed 'related-items' command for user '{}'.", session.user_id);
+
+  Ok(Some(serialized_results))
+}
+
+pub(super) async fn run_related_items(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  request: RelatedItemsRequest,
+  session: &Session,
+) -> InfuResult<SearchResponse> {
+  if request.num_results <= 0 {
+    return Err(format!("Related items request has invalid numResults {}.", request.num_results).into());
+  }
+
+  let start_result = if let Some(page_num) = request.page_num { (page_num - 1) * request.num_results } else { 0 };
+  let end_result = start_result + request.num_results + 1;
+
+  {
+    let db = db.lock().await;
+    let item = db.item.get(&request.id)?;
+    if item.owner_id != session.user_id {
+      return Err(format!("Not authorized to access item '{}'.", request.id).into());
+    }
+  }
+
+  let (data_dir, search_root_id) = resolve_search_scope(db, None, session).await?;
+  let limit = usize::try_from(end_result.saturating_add(SEARCH_CANDIDATE_OVERFETCH).max(1))
+    .map_err(|_| "Related items result limit is too large.")?;
+
+  let started = Instant::now();
+  let result = related_item_results_inner(db, &data_dir, &session.user_id, &search_root_id, &request.id, limit).await;
+  record_search_backend_metrics("related", started, &result);
+
+  Ok(search_response_from_results(paginate_mixed_results(result?, start_result, end_result), request.num_results))
+}
+
+pub(super) async fn handle_similar_images(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = match session_maybe {
+    None => return Err("Sessionless similar images lookup not supported".into()),
+    Some(s) => s,
+  };
+
+  let request: RelatedItemsRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("could not parse json_data {json_data}: {e}"))?;
+
+  let response = run_similar_images(db, request, session).await?;
+  let serialized_results = serde_json::to_string(&response)?;
+
+  debug!("Executed 'similar-images' command for user '{}'.", session.user_id);
+
+  Ok(Some(serialized_results))
+}
+
+pub(super) async fn run_similar_images(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  request: RelatedItemsRequest,
+  session: &Session,
+) -> InfuResult<SearchResponse> {
+  if request.num_results <= 0 {
+    return Err(format!("Similar images request has invalid numResults {}.", request.num_results).into());
+  }
+
+  let start_result = if let Some(page_num) = request.page_num { (page_num - 1) * request.num_results } else { 0 };
+  let end_result = start_result + request.num_results + 1;
+
+  {
+    let db = db.lock().await;
+    let item = db.item.get(&request.id)?;
+    if item.owner_id != session.user_id {
+      return Err(format!("Not authorized to access item '{}'.", request.id).into());
+    }
+    if !is_image_item(item) {
+      return Err(format!("Item '{}' is not an image.", request.id).into());
+    }
+  }
+
+  let (data_dir, search_root_id) = resolve_search_scope(db, None, session).await?;
+  let limit = usize::try_from(end_result.saturating_add(SEARCH_CANDIDATE_OVERFETCH).max(1))
+    .map_err(|_| "Similar images result limit is too large.")?;
+
+  let started = Instant::now();
+  let result = similar_image_results_inner(db, &data_dir, &session.user_id, &search_root_id, &request.id, limit).await;
+  record_search_backend_metrics("similar_images", started, &result);
+
+  Ok(search_response_from_results(paginate_mixed_results(result?, start_result, end_result), request.num_results))
+}
+
+async fn similar_image_results_inner(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  data_dir: &str,
+  user_id: &Uid,
+  search_root_id: &Uid,
+  item_id: &Uid,
+  limit: usize,
+) -> InfuResult<Vec<SearchResult>> {
+  if limit == 0 || !path_exists(&image_vector_db_path(data_dir, user_id)?).await {
+    return Ok(Vec::new());
+  }
+
+  let image_vector_db = open_user_image_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  let Some(image_vector) = image_vector_db.item_vector(item_id).await? else {
+    debug!("Item '{}' has no stored image embedding; no similar images for user '{}'.", item_id, user_id);
+    return Ok(Vec::new());
+  };
+
+  // the image itself is the nearest neighbour, and is discarded.
+  let hits = image_vector_db
+    .search(&image_vector.model, &image_vector.embedding, limit + 1)
+    .await?
+    .into_iter()
+    .filter(|hit| &hit.item_id != item_id)
+    .collect::<Vec<_>>();
+  image_search_results_for_hits(db, hits, user_id, search_root_id).await
+}
+
+async fn related_item_results_inner(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  data_dir: &str,
+  user_id: &Uid,
+  search_root_id: &Uid,
+  item_id: &Uid,
+  limit: usize,
+) -> InfuResult<Vec<SearchResult>> {
+  if limit == 0 || !user_fragment_vector_db_exists(data_dir, user_id).await? {
+    return Ok(Vec::new());
+  }
+
+  let vector_db = open_user_fragment_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  let Some(index_status) = vector_db.rebuild_status().await? else {
+    return Ok(Vec::new());
+  };
+  if !index_status.complete {
+    return Ok(Vec::new());
+  }
+
+  let item_keys = vector_db.embedded_fragment_keys_for_item(item_id).await?;
+  let item_fragments = vector_db.embedded_fragments_for_keys(&item_keys).await?;
+  let Some(query_embedding) = mean_fragment_embedding(&item_fragments) else {
+    debug!("Item '{}' has no stored fragment embeddings; no related items for user '{}'.", item_id, user_id);
+    return Ok(Vec::new());
+  };
+
+  // over-fetch by the item's own fragment count, since those are the nearest neighbours and are discarded.
+  let fragment_limit = limit.saturating_mul(SEARCH_SEMANTIC_FRAGMENT_MULTIPLIER).max(limit) + item_fragments.len();
+  let fragment_hits = vector_db
+    .search(&query_embedding, fragment_limit)
+    .await?
+    .into_iter()
+    .filter(|hit| &hit.item_id != item_id && !is_lexical_search_source_kind(&hit.source_kind))
+    .collect::<Vec<_>>();
+  if !fragment_hits.is_empty() {
+    debug!(
+      "Related items top fragment hits for item '{}' of user '{}': {}",
+      item_id,
+      user_id,
+      fragment_hits
+        .iter()
+        .take(8)
+        .map(|hit| format!("{}:{}@{:.6}", hit.item_id, hit.ordinal, hit.distance))
+        .collect::<Vec<_>>()
+        .join(", ")
+    );
+  }
+  let fragment_hits = select_best_fragment_hit_per_item(fragment_hits);
+
+  let mut results = Vec::new();
+  let db = db.lock().await;
+  for hit in fragment_hits {
+    if results.len() >= limit {
+      break;
+    }
+    if let Some(mut result) = search_result_path_for_item(&db, &hit.item_id, user_id, search_root_id)? {
+      result.score = semantic_distance_to_search_score(hit.distance);
+      result.fragment_match = Some(search_fragment_match_for_hit(&hit, ""));
+      results.push(result);
+    }
+  }
+  Ok(results)
+}
+
+/// Centroid of an item's fragment embeddings, normalized to unit length. Returns None if there
+/// is nothing usable to aggregate (no fragments, inconsistent dimensions or a zero vector).
+fn mean_fragment_embedding(fragments: &[EmbeddedFragment]) -> Option<Vec<f32>> {
+  let dimensions = fragments.first()?.embedding.len();
+  if dimensions == 0 {
+    return None;
+  }
+  let mut sum = vec![0.0_f64; dimensions];
+  for fragment in fragments {
+    if fragment.embedding.len() != dimensions {
+      return None;
+    }
+    for (total, value) in sum.iter_mut().zip(fragment.embedding.iter()) {
+      *total += *value as f64;
+    }
+  }
+  let norm = sum.iter().map(|value| value * value).sum::<f64>().sqrt();
+  if !norm.is_finite() || norm <= 0.0 {
+    return None;
+  }
+  Some(sum.into_iter().map(|value| (value / norm) as f32).collect())
+}
+
 pub(super) async fn run_lexical_search(
   db: &Arc<tokio::sync::Mutex<Db>>,
   request: SearchRequest,
@@ -296,6 +649,32 @@ pub(super) async fn run_lexical_search(
   Ok(search_response_from_results(results, request.num_results))
 }
 
+pub(super) async fn run_semantic_search(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  request: SearchRequest,
+  session: &Session,
+) -> InfuResult<SearchResponse> {
+  let start_result = if let Some(page_num) = request.page_num { (page_num - 1) * request.num_results } else { 0 };
+  let end_result = start_result + request.num_results + 1;
+  let (data_dir, search_root_id) = resolve_search_scope(db, request.page_id, session).await?;
+
+  let results = indexed_search_results(
+    Some(config),
+    db,
+    &data_dir,
+    &session.user_id,
+    &search_root_id,
+    &request.text,
+    start_result,
+    end_result,
+    IndexedSearchBackends::SEMANTIC,
+  )
+  .await?;
+
+  Ok(search_response_from_results(results, request.num_results))
+}
+
 pub(super) fn compact_search_response_json(response: &SearchResponse) -> InfuResult<String> {
   serde_json::to_string(&compact::compact_search_response(response))
     .map_err(|e| format!("Could not serialize compact search response: {}", e).into())
@@ -331,7 +710,16 @@ async fn indexed_search_results(
   let fragment_result_limit = usize::try_from(end_result.saturating_add(SEARCH_CANDIDATE_OVERFETCH).max(1))
     .map_err(|_| "Search result limit is too large.")?;
 
-  let title_results = if backends.title_lexical {
+  let semantic_config =
+    if backends.semantic { Some(config.clone().ok_or("Semantic search requires configuration.")?) } else { None };
+  let image_config = if backends.image { Some(config.ok_or("Image search requires configuration.")?) } else { None };
+
+  // The backends are independent, so they're queried concurrently and a MIXED search takes as long as
+  // the slowest of them rather than their sum.
+  let title_results = async {
+    if !backends.title_lexical {
+      return Vec::new();
+    }
     match title_lexical_search_results(db, data_dir, user_id, search_root_id, search_text, fragment_result_limit).await
     {
       Ok(results) => results,
@@ -340,11 +728,12 @@ async fn indexed_search_results(
         Vec::new()
       }
     }
-  } else {
-    Vec::new()
   };
 
-  let lexical_results = if backends.document_lexical {
+  let lexical_results = async {
+    if !backends.document_lexical {
+      return Vec::new();
+    }
     match lexical_search_results(db, data_dir, user_id, search_root_id, search_text, fragment_result_limit).await {
       Ok(results) => results,
       Err(e) => {
@@ -355,12 +744,12 @@ async fn indexed_search_results(
         Vec::new()
       }
     }
-  } else {
-    Vec::new()
   };
 
-  let semantic_results = if backends.semantic {
-    let config = config.ok_or("Semantic search requires configuration.")?;
+  let semantic_results = async {
+    let Some(config) = semantic_config else {
+      return Vec::new();
+    };
     match semantic_search_results(config, db, data_dir, user_id, search_root_id, search_text, fragment_result_limit)
       .await
     {
@@ -370,11 +759,25 @@ async fn indexed_search_results(
         Vec::new()
       }
     }
-  } else {
-    Vec::new()
   };
 
-  let mixed = mix_search_results(title_results, lexical_results, semantic_results);
+  let image_results = async {
+    let Some(config) = image_config else {
+      return Vec::new();
+    };
+    match image_search_results(config, db, data_dir, user_id, search_root_id, search_text, fragment_result_limit).await
+    {
+      Ok(results) => results,
+      Err(e) => {
+        warn!("Image search failed for user '{}'; falling back without image results: {}", user_id, e);
+        Vec::new()
+      }
+    }
+  };
+
+  let (title_results, lexical_results, semantic_results, image_results) =
+    tokio::join!(title_results, lexical_results, semantic_results, image_results);
+  let mixed = mix_search_results(title_results, lexical_results, semantic_results, image_results);
   Ok(paginate_mixed_results(mixed, start_result, end_result))
 }
 
@@ -477,7 +880,7 @@ async fn title_lexical_search_results_inner(
       break;
     }
     if let Some(mut result) = search_result_path_for_item(&db, &hit.item_id, user_id, search_root_id)? {
-      let mut match_result = search_fragment_match_for_lexical_hit(&hit, search_text);
+      let mut match_result = search_fragment_match_for_lexical_hit(&db, &hit, search_text)?;
       let exact_title_score = result
         .path
         .last()
@@ -562,7 +965,10 @@ async fn lexical_search_results_inner(
       continue;
     };
     if let Some(mut result) = search_result_path_for_item(&db, &best_hit.item_id, user_id, search_root_id)? {
-      let matches = hits.iter().map(|hit| search_fragment_match_for_lexical_hit(hit, search_text)).collect::<Vec<_>>();
+      let matches = hits
+        .iter()
+        .map(|hit| search_fragment_match_for_lexical_hit(&db, hit, search_text))
+        .collect::<InfuResult<Vec<_>>>()?;
       result.score = bm25_score_to_search_score(best_hit.score);
       result.fragment_match = matches.first().cloned();
       result.additional_fragment_matches = matches.into_iter().skip(1).collect();
@@ -675,6 +1081,79 @@ async fn semantic_search_results_inner(
   Ok(results)
 }
 
+async fn image_search_results(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  data_dir: &str,
+  user_id: &Uid,
+  search_root_id: &Uid,
+  search_text: &str,
+  limit: usize,
+) -> InfuResult<Vec<SearchResult>> {
+  let started = Instant::now();
+  let result = image_search_results_inner(config, db, data_dir, user_id, search_root_id, search_text, limit).await;
+  record_search_backend_metrics("image", started, &result);
+  result
+}
+
+/// Text-to-image search, for when the tagging service exposes the text encoder paired with its image
+/// encoder. Images match on what they look like, so this finds images whose tags and captions miss
+/// the query.
+async fn image_search_results_inner(
+  config: Arc<Config>,
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  data_dir: &str,
+  user_id: &Uid,
+  search_root_id: &Uid,
+  search_text: &str,
+  limit: usize,
+) -> InfuResult<Vec<SearchResult>> {
+  if limit == 0 || search_text.trim().is_empty() || !path_exists(&image_vector_db_path(data_dir, user_id)?).await {
+    return Ok(Vec::new());
+  }
+  let Some(image_text_embed_url) = resolve_configured_gpu_tool_url(config.as_ref(), GPU_TOOL_IMAGE_TEXT_EMBED).await?
+  else {
+    return Ok(Vec::new());
+  };
+
+  let client = reqwest::ClientBuilder::new()
+    .timeout(Duration::from_secs(SEARCH_EMBEDDING_TIMEOUT_SECS))
+    .build()
+    .map_err(|e| format!("Could not build image search HTTP client: {}", e))?;
+  let Some((model, query_embedding)) = embed_image_query_text(&client, &image_text_embed_url, search_text).await?
+  else {
+    return Ok(Vec::new());
+  };
+  let image_vector_db = open_user_image_vector_db(data_dir, user_id, FragmentVectorDbBackend::SqliteVec)?;
+  let hits = image_vector_db.search(&model, &query_embedding, limit).await?;
+  debug!(
+    "Image search for user '{}' with model '{}' returned {} hit(s): {}",
+    user_id,
+    model,
+    hits.len(),
+    hits.iter().take(8).map(|hit| format!("{}@{:.6}", hit.item_id, hit.distance)).collect::<Vec<_>>().join(", ")
+  );
+
+  image_search_results_for_hits(db, hits, user_id, search_root_id).await
+}
+
+async fn image_search_results_for_hits(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  hits: Vec<ImageVectorHit>,
+  user_id: &Uid,
+  search_root_id: &Uid,
+) -> InfuResult<Vec<SearchResult>> {
+  let mut results = Vec::new();
+  let db = db.lock().await;
+  for hit in hits {
+    if let Some(mut result) = search_result_path_for_item(&db, &hit.item_id, user_id, search_root_id)? {
+      result.score = semantic_distance_to_search_score(hit.distance);
+      results.push(result);
+    }
+  }
+  Ok(results)
+}
+
 fn select_best_fragment_hit_per_item(fragment_hits: Vec<FragmentVectorHit>) -> Vec<FragmentVectorHit> {
   let mut best_by_item = HashMap::<String, FragmentVectorHit>::new();
   for hit in fragment_hits {
@@ -813,12 +1292,14 @@ fn mix_search_results(
   title_results: Vec<SearchResult>,
   lexical_results: Vec<SearchResult>,
   semantic_results: Vec<SearchResult>,
+  image_results: Vec<SearchResult>,
 ) -> Vec<SearchResult> {
   let mut candidates: HashMap<Uid, SearchMergeCandidate> = HashMap::new();
 
   add_ranked_search_results(&mut candidates, title_results, SEARCH_TITLE_LEXICAL_WEIGHT);
   add_ranked_search_results(&mut candidates, lexical_results, SEARCH_LEXICAL_WEIGHT);
   add_ranked_search_results(&mut candidates, semantic_results, SEARCH_SEMANTIC_WEIGHT);
+  add_ranked_search_results(&mut candidates, image_results, SEARCH_IMAGE_WEIGHT);
 
   let mut candidates = candidates.into_values().collect::<Vec<_>>();
   candidates.sort_by(|a, b| {
@@ -859,7 +1340,14 @@ fn add_ranked_search_results(
     entry.rank_score += rank_score;
     entry.best_rank = entry.best_rank.min(rank);
     if should_replace_fragment_result {
+      // image results have no fragment match, and shouldn't displace one found by another backend.
+      let previous_fragment_match = entry.result.fragment_match.take();
+      let previous_additional_fragment_matches = std::mem::take(&mut entry.result.additional_fragment_matches);
       entry.result = result;
+      if entry.result.fragment_match.is_none() {
+        entry.result.fragment_match = previous_fragment_match;
+        entry.result.additional_fragment_matches = previous_additional_fragment_matches;
+      }
     } else if entry.result.fragment_match.is_none() {
       entry.result.fragment_match = fragment_match;
       entry.result.additional_fragment_matches = additional_fragment_matches;
@@ -921,10 +1409,19 @@ pub(super) fn exact_title_search_score(title: &str, search_text: &str) -> f32 {
   clamp_search_score(0.65 + 0.25 * (query_chars / title_chars).min(1.0)).min(0.89)
 }
 
-fn search_fragment_match_for_lexical_hit(hit: &FragmentLexicalHit, search_text: &str) -> SearchFragmentMatch {
+fn search_fragment_match_for_lexical_hit(
+  db: &Db,
+  hit: &FragmentLexicalHit,
+  search_text: &str,
+) -> InfuResult<SearchFragmentMatch> {
   let (text, text_truncated) =
     search_match_excerpt(&hit.source_kind, &hit.text, search_text, SEARCH_FRAGMENT_MATCH_MAX_CHARS);
-  SearchFragmentMatch {
+  let table_cell = if hit.source_kind == TABLE_ROWS_SOURCE_KIND {
+    search_table_cell_match_for_lexical_hit(db, hit, search_text)?
+  } else {
+    None
+  };
+  Ok(SearchFragmentMatch {
     fragment_ordinal: hit.ordinal,
     source_kind: hit.source_kind.clone(),
     semantic_distance: None,
@@ -934,6 +1431,41 @@ fn search_fragment_match_for_lexical_hit(hit: &FragmentLexicalHit, search_text:
     text_truncated,
     page_start: hit.page_start,
     page_end: hit.page_end,
+    time_start_secs: hit.time_start_secs,
+    time_end_secs: hit.time_end_secs,
+    table_cell,
+  })
+}
+
+fn search_table_cell_match_for_lexical_hit(
+  db: &Db,
+  hit: &FragmentLexicalHit,
+  search_text: &str,
+) -> InfuResult<Option<SearchTableCellMatch>> {
+  let Some(row_item_id) = hit.row_item_id.as_deref() else {
+    return Ok(None);
+  };
+  let Ok(table) = db.item.get(&hit.item_id) else {
+    return Ok(None);
+  };
+  let Some(row_cells) = table_row_cells_for_row_item(db, table, row_item_id)? else {
+    return Ok(None);
+  };
+  let query_terms = normalized_search_terms(search_text);
+  let cell = row_cells
+    .iter()
+    .find(|cell| sentence_matches_query_terms(&cell.line(), &query_terms))
+    .or_else(|| row_cells.first());
+  Ok(cell.map(search_table_cell_match))
+}
+
+fn search_table_cell_match(cell: &TableCellText) -> SearchTableCellMatch {
+  SearchTableCellMatch {
+    row_item_id: cell.row_item_id.clone(),
+    row_index: cell.row_index,
+    cell_item_id: cell.cell_item_id.clone(),
+    column_index: cell.column_index,
+    column_name: cell.column_name.clone(),
   }
 }
 
@@ -953,6 +1485,9 @@ fn search_fragment_match_for_hit(
     text_truncated,
     page_start: hit.page_start,
     page_end: hit.page_end,
+    time_start_secs: hit.time_start_secs,
+    time_end_secs: hit.time_end_secs,
+    table_cell: None,
   }
 }
 
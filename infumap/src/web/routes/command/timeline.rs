@@ -0,0 +1,238 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use std::collections::BTreeMap;
+use time::Date;
+
+use crate::ai::timeline::{
+  TimelineBucketSize, format_timeline_date, image_capture_times, local_date_for_unix_secs, parse_timeline_date,
+};
+
+const TIMELINE_DEFAULT_MAX_ITEMS_PER_BUCKET: usize = 50;
+const TIMELINE_MAX_ITEMS_PER_BUCKET: usize = 1000;
+const TIMELINE_MAX_UTC_OFFSET_MINUTES: i64 = 14 * 60;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineRequest {
+  /// First day of the range (YYYY-MM-DD), inclusive.
+  start: String,
+  /// Last day of the range (YYYY-MM-DD), inclusive.
+  end: String,
+  /// day, week, month or year.
+  bucket: String,
+  /// The client's time zone, in minutes east of UTC. Item datetimes are placed on days in this time
+  /// zone. Image capture times are already local to where the image was taken, so are not adjusted.
+  #[serde(default)]
+  utc_offset_minutes: i64,
+  /// Only include items of these types.
+  item_types: Option<Vec<String>>,
+  /// Only include items beneath this container (at any depth), including its attachments.
+  container_id: Option<Uid>,
+  /// Only include items on this day of the year (MM-DD), for "on this day" views.
+  month_day: Option<String>,
+  max_items_per_bucket: Option<usize>,
+}
+
+struct TimelineEntry {
+  date: Date,
+  json: Value,
+  sort_secs: i64,
+}
+
+/// The session user's items dated within a range, grouped into buckets. Only buckets with at least
+/// one item are returned. Every bucket has a full count, but lists at most `maxItemsPerBucket` items,
+/// earliest first. Images are dated by their EXIF capture time when they have one, other items by
+/// their datetime. Items in the trash are not included.
+pub(super) async fn handle_timeline(
+  db: &Arc<tokio::sync::Mutex<Db>>,
+  json_data: &str,
+  session_maybe: &Option<Session>,
+) -> InfuResult<Option<String>> {
+  let session = session_maybe.as_ref().ok_or("Session is required to query the timeline.")?;
+  let request: TimelineRequest =
+    serde_json::from_str(json_data).map_err(|e| format!("Could not parse timeline request: {}", e))?;
+  let start = parse_timeline_date(&request.start)?;
+  let end = parse_timeline_date(&request.end)?;
+  if start > end {
+    return Err(format!("Timeline start '{}' is after end '{}'.", request.start, request.end).into());
+  }
+  let bucket_size = TimelineBucketSize::from_str(&request.bucket)?;
+  if request.utc_offset_minutes.abs() > TIMELINE_MAX_UTC_OFFSET_MINUTES {
+    return Err(format!("Timeline utcOffsetMinutes {} is out of range.", request.utc_offset_minutes).into());
+  }
+  let utc_offset_secs = request.utc_offset_minutes * 60;
+  let item_types = match &request.item_types {
+    Some(item_types) => {
+      Some(item_types.iter().map(|item_type| ItemType::from_str(item_type)).collect::<InfuResult<Vec<ItemType>>>()?)
+    }
+    None => None,
+  };
+  let month_day = match &request.month_day {
+    Some(month_day) => Some(parse_month_day(month_day)?),
+    None => None,
+  };
+  let max_items_per_bucket =
+    request.max_items_per_bucket.unwrap_or(TIMELINE_DEFAULT_MAX_ITEMS_PER_BUCKET).min(TIMELINE_MAX_ITEMS_PER_BUCKET);
+
+  let (data_dir, item_ids) = {
+    let db = db.lock().await;
+    let item_ids = match &request.container_id {
+      Some(container_id) => {
+        if db.item.get(container_id)?.owner_id != session.user_id {
+          return Err(format!("Not authorized to access container '{}'.", container_id).into());
+        }
+        subtree_item_ids(&db, container_id).into_iter().filter(|item_id| item_id != container_id).collect()
+      }
+      None => {
+        let user = db.user.get(&session.user_id).ok_or(format!("User '{}' not found.", session.user_id))?;
+        let trash_item_ids = subtree_item_ids(&db, &user.trash_page_id).into_iter().collect::<HashSet<_>>();
+        db.item
+          .all_loaded_items()
+          .iter()
+          .filter(|item| item.user_id == session.user_id && !trash_item_ids.contains(&item.item_id))
+          .map(|item| item.item_id.clone())
+          .collect::<Vec<_>>()
+      }
+    };
+    (db.item.data_dir().to_owned(), item_ids)
+  };
+
+  let image_item_ids = {
+    let db = db.lock().await;
+    item_ids
+      .iter()
+      .filter(|item_id| db.item.get(item_id).is_ok_and(should_tag_image_item))
+      .cloned()
+      .collect::<Vec<_>>()
+  };
+  let capture_times = image_capture_times(&data_dir, &session.user_id, &image_item_ids).await?;
+
+  let mut entries = {
+    let db = db.lock().await;
+    item_ids
+      .iter()
+      .filter_map(|item_id| {
+        let item = db.item.get(item_id).ok()?;
+        if item.owner_id != session.user_id
+          || matches!(item.item_type, ItemType::Password | ItemType::Placeholder)
+          || item_types.as_ref().is_some_and(|item_types| !item_types.contains(&item.item_type))
+        {
+          return None;
+        }
+        let (date, end_date, unix_secs, date_source) = match capture_times.get(item_id) {
+          Some(wall_clock_secs) => {
+            (local_date_for_unix_secs(*wall_clock_secs, 0)?, None, wall_clock_secs - utc_offset_secs, "exif")
+          }
+          None => (
+            local_date_for_unix_secs(item.datetime, utc_offset_secs)?,
+            item.end_datetime.and_then(|end_datetime| local_date_for_unix_secs(end_datetime, utc_offset_secs)),
+            item.datetime,
+            "datetime",
+          ),
+        };
+        if date > end || end_date.unwrap_or(date) < start {
+          return None;
+        }
+        if let Some((month, day)) = month_day
+          && (date.month() as u8 != month || date.day() != day)
+        {
+          return None;
+        }
+        Some(TimelineEntry {
+          // items spanning the start of the range are placed on its first day.
+          date: date.max(start),
+          json: serde_json::json!({
+            "id": item.id,
+            "itemType": item.item_type.as_str(),
+            "title": item.title,
+            "mimeType": item.mime_type,
+            "parentId": item.parent_id,
+            "date": format_timeline_date(date),
+            "dateTime": unix_secs,
+            "endDateTime": if date_source == "datetime" { item.end_datetime } else { None },
+            "dateSource": date_source,
+          }),
+          sort_secs: unix_secs,
+        })
+      })
+      .collect::<Vec<_>>()
+  };
+  entries.sort_by(|a, b| a.sort_secs.cmp(&b.sort_secs).then_with(|| a.date.cmp(&b.date)));
+
+  let total = entries.len();
+  let mut buckets = BTreeMap::<Date, (usize, Vec<Value>)>::new();
+  for entry in entries {
+    let (count, items) = buckets.entry(bucket_size.bucket_start(entry.date)).or_default();
+    *count += 1;
+    if items.len() < max_items_per_bucket {
+      items.push(entry.json);
+    }
+  }
+  let buckets = buckets
+    .into_iter()
+    .map(|(bucket_start, (count, items))| {
+      serde_json::json!({
+        "start": format_timeline_date(bucket_start),
+        "end": format_timeline_date(bucket_size.bucket_end(bucket_start)),
+        "count": count,
+        "items": items,
+      })
+    })
+    .collect::<Vec<_>>();
+
+  debug!(
+    "Executed 'timeline' command for user '{}': {} item(s) in {} bucket(s).",
+    session.user_id,
+    total,
+    buckets.len()
+  );
+  let result = serde_json::json!({ "total": total, "buckets": buckets });
+  Ok(Some(result.to_string()))
+}
+
+/// The container and everything beneath it, including attachments.
+fn subtree_item_ids(db: &Db, root_id: &Uid) -> Vec<Uid> {
+  let mut item_ids = vec![];
+  let mut visited = HashSet::new();
+  let mut pending = vec![root_id.clone()];
+  while let Some(item_id) = pending.pop() {
+    if !visited.insert(item_id.clone()) {
+      continue;
+    }
+    for child in db.item.get_children(&item_id).unwrap_or_default() {
+      pending.push(child.id.clone());
+    }
+    for attachment in db.item.get_attachments(&item_id).unwrap_or_default() {
+      pending.push(attachment.id.clone());
+    }
+    item_ids.push(item_id);
+  }
+  item_ids
+}
+
+fn parse_month_day(value: &str) -> InfuResult<(u8, u8)> {
+  let invalid = || format!("Invalid monthDay '{}', expected MM-DD.", value);
+  let (month, day) = value.trim().split_once('-').ok_or_else(invalid)?;
+  let month = month.parse::<u8>().map_err(|_| invalid())?;
+  let day = day.parse::<u8>().map_err(|_| invalid())?;
+  if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+    return Err(invalid().into());
+  }
+  Ok((month, day))
+}
@@ -17,12 +17,13 @@
 use bytes::Bytes;
 use config::Config;
 use http_body_util::combinators::BoxBody;
-use hyper::{Request, Response};
+use hyper::{Request, Response, StatusCode};
 use image::ImageReader;
 use image::codecs::jpeg::JpegEncoder;
 use image::imageops::FilterType;
+use infusdk::item::is_valid_item_label;
 use infusdk::util::infu::InfuResult;
-use infusdk::util::uid::is_uid;
+use infusdk::util::uid::{Uid, is_uid};
 use log::{debug, warn};
 use once_cell::sync::Lazy;
 use prometheus::{IntCounterVec, opts};
@@ -30,12 +31,13 @@ use serde::Deserialize;
 use std::io::Cursor;
 use std::sync::Arc;
 use tokio::fs;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
 
 use crate::ai::artifact_paths::{
   item_fragments_manifest_path, item_fragments_path, item_geo_content_path, item_text_content_path,
   item_text_manifest_path,
 };
+use crate::ai::audio_pipeline::is_audio_item;
 use crate::ai::image_tagging::is_supported_image_tagging_mime_type;
 use crate::config::{
   CONFIG_BROWSER_CACHE_MAX_AGE_SECONDS, CONFIG_MAX_SCALE_IMAGE_DOWN_PERCENT, CONFIG_MAX_SCALE_IMAGE_UP_PERCENT,
@@ -45,12 +47,15 @@ use crate::storage::cache::{ImageCacheKey, ImageSize};
 use crate::storage::db::Db;
 use crate::storage::object;
 use crate::util::image::{adjust_image_for_exif_orientation, get_exif_orientation};
+use crate::util::image_rendition::{IMAGE_RENDITION_MIME_TYPE, get_image_item_bytes, needs_image_rendition};
+use crate::util::media::{extract_video_poster_jpeg, is_ffmpeg_configured, is_video_item};
+use crate::util::mime::WEB_ARCHIVE_MIME_TYPE;
 use crate::web::serve::{
   cors_response, forbidden_response, full_body, internal_server_error_response, not_found_response,
 };
 use crate::web::session::get_and_validate_session;
 
-use super::command::authorize_item;
+use super::command::{authorize_item, item_id_for_username_and_label};
 
 pub static METRIC_CACHED_IMAGE_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
   IntCounterVec::new(opts!("cached_image_requests_total", "Total number of images served from cache."), &["name"])
@@ -73,6 +78,11 @@ const GEO_INFO_NOT_AVAILABLE_MESSAGE: &str = "[geo info not available]";
 // 75 and below => starting to see significant loss in quality.
 // TODO (LOW): Make this configurable.
 const JPEG_QUALITY: u8 = 80;
+
+/// Poster frame extraction decrypts the whole video into memory and runs ffmpeg on it, so only a few
+/// are done at once. Further requests wait for a permit.
+const MAX_CONCURRENT_POSTER_FRAME_EXTRACTIONS: usize = 2;
+static POSTER_FRAME_EXTRACTION_PERMITS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_POSTER_FRAME_EXTRACTIONS);
 const FRAGMENT_VIEW_RULE: &str = "-----------------";
 
 #[derive(Deserialize)]
@@ -111,10 +121,20 @@ fn is_safe_inline_mime(mime_type: &str) -> bool {
       | "audio/webm"
       | "video/mp4"
       | "video/ogg"
+      | "video/quicktime"
       | "video/webm"
   )
 }
 
+/// Web archives of linked pages are shown inline as HTML, but only inside a sandbox:
+/// scripts, forms and plugins are disabled, the document gets an opaque origin, and nothing may be
+/// loaded from the network, so only inlined (data URI) styles, images and fonts render.
+const SANDBOXED_HTML_CONTENT_SECURITY_POLICY: &str = "sandbox; default-src 'none'; img-src data:; style-src 'unsafe-inline' data:; font-src data:; media-src data:; form-action 'none'; base-uri 'none'; frame-ancestors 'self'";
+
+fn is_sandboxed_inline_mime(mime_type: &str) -> bool {
+  mime_type == WEB_ARCHIVE_MIME_TYPE
+}
+
 fn response_filename(uid: &str, title_maybe: Option<&str>) -> String {
   match title_maybe.map(str::trim).filter(|title| !title.is_empty()) {
     Some(title) => title.to_owned(),
@@ -185,6 +205,14 @@ fn response_content_headers_for_generated_item_text(filename: &str, mime_type: &
   (content_type, content_disposition)
 }
 
+fn parse_labelled_file_route(name: &str) -> Option<(&str, &str)> {
+  let (username, label) = name.split_once('/')?;
+  if username.is_empty() || !is_valid_item_label(label) {
+    return None;
+  }
+  Some((username, label))
+}
+
 fn parse_resized_image_name(name: &str) -> Option<(&str, u32)> {
   let (uid, width) = name.split_once('_')?;
   if !is_uid(uid) {
@@ -249,6 +277,19 @@ pub async fn serve_files_route(
         internal_server_error_response(&format!("get_item_fragments failed for '{}': {}", uid, e))
       }
     }
+  } else if let Some((username, label)) = parse_labelled_file_route(name) {
+    let item_id_maybe = item_id_for_username_and_label(&db.lock().await, username, label);
+    let Some(item_id) = item_id_maybe else {
+      return not_found_response();
+    };
+    let range_header = req.headers().get(hyper::header::RANGE).and_then(|value| value.to_str().ok());
+    match get_file(config, db, object_store, &session_user_id_maybe, &item_id, range_header).await {
+      Ok(file_response) => file_response,
+      Err(e) => {
+        METRIC_CACHED_IMAGE_REQUESTS_TOTAL.with_label_values(&[LABEL_FAILED]).inc();
+        internal_server_error_response(&format!("get_file failed for '{}': {}", name, e))
+      }
+    }
   } else if name.contains("_") {
     match get_cached_resized_img(config, db, object_store, image_cache, &session_user_id_maybe, name).await {
       Ok(img_response) => img_response,
@@ -258,7 +299,8 @@ pub async fn serve_files_route(
       }
     }
   } else {
-    match get_file(config, db, object_store, &session_user_id_maybe, name).await {
+    let range_header = req.headers().get(hyper::header::RANGE).and_then(|value| value.to_str().ok());
+    match get_file(config, db, object_store, &session_user_id_maybe, name, range_header).await {
       Ok(file_response) => file_response,
       Err(e) => {
         METRIC_CACHED_IMAGE_REQUESTS_TOTAL.with_label_values(&[LABEL_FAILED]).inc();
@@ -299,6 +341,10 @@ async fn get_cached_resized_img(
   let original_mime_type_string; // TODO (LOW): validation.
   let owner_id;
   let title_maybe;
+  let data_dir;
+  let item_mime_type_string;
+  // Videos are served as images of their poster frame, which is kept in the image cache as the original.
+  let video_duration_ms_maybe;
   {
     let db = db.lock().await;
     let item = match db.item.get(&uid) {
@@ -310,13 +356,33 @@ async fn get_cached_resized_img(
       return Ok(forbidden_response());
     }
     owner_id = item.owner_id.clone();
-    title_maybe = item.title.clone();
+    data_dir = db.item.data_dir().to_owned();
 
     object_encryption_key =
       db.user.get(&item.owner_id).ok_or(format!("User '{}' not found.", item.owner_id))?.object_encryption_key.clone();
-    original_dimensions_px =
-      item.image_size_px.as_ref().ok_or("Image item does not have image dimensions set.")?.clone();
-    original_mime_type_string = item.mime_type.as_ref().ok_or("Image item does not have mime type set.")?.clone();
+    if is_video_item(item) {
+      original_dimensions_px = match &item.image_size_px {
+        Some(image_size_px) => image_size_px.clone(),
+        None => return Ok(not_found_response()),
+      };
+      item_mime_type_string = item.mime_type.clone().unwrap_or_default();
+      original_mime_type_string = String::from("image/jpeg");
+      title_maybe = None;
+      video_duration_ms_maybe = Some(item.duration_ms);
+    } else {
+      original_dimensions_px =
+        item.image_size_px.as_ref().ok_or("Image item does not have image dimensions set.")?.clone();
+      item_mime_type_string = item.mime_type.as_ref().ok_or("Image item does not have mime type set.")?.clone();
+      // Images with a rendition are served as the rendition, in place of the original.
+      if needs_image_rendition(&item_mime_type_string) {
+        original_mime_type_string = String::from(IMAGE_RENDITION_MIME_TYPE);
+        title_maybe = None;
+      } else {
+        original_mime_type_string = item_mime_type_string.clone();
+        title_maybe = item.title.clone();
+      }
+      video_duration_ms_maybe = None;
+    }
   }
   if original_dimensions_px.w <= 0 || original_dimensions_px.h <= 0 {
     return Err(
@@ -424,8 +490,21 @@ async fn get_cached_resized_img(
     }
   }
 
-  let original_file_bytes =
-    object::get(object_store, owner_id.clone(), String::from(&uid), &object_encryption_key).await?;
+  let original_file_bytes = match video_duration_ms_maybe {
+    Some(duration_ms) => {
+      let poster_maybe =
+        get_video_poster_jpeg(object_store, image_cache.clone(), &owner_id, &uid, &object_encryption_key, duration_ms)
+          .await?;
+      match poster_maybe {
+        Some(poster) => poster,
+        None => return Ok(not_found_response()),
+      }
+    }
+    None => {
+      get_image_item_bytes(&data_dir, object_store, &owner_id, &uid, &item_mime_type_string, &object_encryption_key)
+        .await?
+    }
+  };
 
   if respond_with_cached_original {
     let cache_key = ImageCacheKey { item_id: uid.clone(), size: ImageSize::Original };
@@ -501,12 +580,52 @@ async fn get_cached_resized_img(
   }
 }
 
+/// The poster frame of a video item, from the image cache or else extracted with ffmpeg and cached. None
+/// if ffmpeg is not configured or could not extract a frame.
+async fn get_video_poster_jpeg(
+  object_store: Arc<object::ObjectStore>,
+  image_cache: Arc<std::sync::Mutex<storage_cache::ImageCache>>,
+  owner_id: &Uid,
+  uid: &str,
+  object_encryption_key: &str,
+  duration_ms: Option<i64>,
+) -> InfuResult<Option<Vec<u8>>> {
+  let cache_key = ImageCacheKey { item_id: uid.to_owned(), size: ImageSize::Original };
+  if let Some(poster) = storage_cache::get(image_cache.clone(), owner_id, cache_key).await? {
+    return Ok(Some(poster));
+  }
+
+  if !is_ffmpeg_configured() {
+    return Ok(None);
+  }
+  let _permit =
+    POSTER_FRAME_EXTRACTION_PERMITS.acquire().await.map_err(|e| format!("Could not wait for ffmpeg: {}", e))?;
+  // Another request may have extracted the frame while this one waited.
+  let cache_key = ImageCacheKey { item_id: uid.to_owned(), size: ImageSize::Original };
+  if let Some(poster) = storage_cache::get(image_cache.clone(), owner_id, cache_key).await? {
+    return Ok(Some(poster));
+  }
+  let video_bytes = object::get(object_store, owner_id.to_owned(), uid.to_owned(), object_encryption_key).await?;
+  let poster = match extract_video_poster_jpeg(&video_bytes, duration_ms).await {
+    Ok(poster) => poster,
+    Err(e) => {
+      warn!("Could not extract a poster frame for video item '{}': {}", uid, e);
+      return Ok(None);
+    }
+  };
+  let cache_key = ImageCacheKey { item_id: uid.to_owned(), size: ImageSize::Original };
+  debug!("Inserting poster frame '{}' into cache.", cache_key);
+  storage_cache::put_if_not_exist(image_cache, owner_id, cache_key, poster.clone()).await?;
+  Ok(Some(poster))
+}
+
 async fn get_file(
   config: Arc<Config>,
   db: &Arc<Mutex<Db>>,
   object_store: Arc<object::ObjectStore>,
   session_user_id_maybe: &Option<String>,
   uid: &str,
+  range_header: Option<&str>,
 ) -> InfuResult<Response<BoxBody<Bytes, hyper::Error>>> {
   if !is_uid(uid) {
     return Ok(not_found_response());
@@ -539,17 +658,78 @@ async fn get_file(
 
   METRIC_CACHED_IMAGE_REQUESTS_TOTAL.with_label_values(&[LABEL_FULL]).inc();
 
-  let (content_type, content_disposition) = response_content_headers(&filename, mime_type_string);
+  let sandboxed = is_sandboxed_inline_mime(mime_type_string);
+  let (content_type, content_disposition) = if sandboxed {
+    ("text/html; charset=utf-8".to_owned(), content_disposition_header(&filename, true))
+  } else {
+    response_content_headers(&filename, mime_type_string)
+  };
 
-  Ok(
-    Response::builder()
-      .header(hyper::header::CONTENT_TYPE, content_type)
-      .header("Content-Disposition", content_disposition)
-      .header("X-Content-Type-Options", "nosniff")
-      .header(hyper::header::CACHE_CONTROL, calc_cache_control(browser_cache_max_age_seconds))
-      .body(full_body(data))
-      .unwrap(),
-  )
+  let mut response = Response::builder()
+    .header(hyper::header::CONTENT_TYPE, content_type)
+    .header("Content-Disposition", content_disposition)
+    .header("X-Content-Type-Options", "nosniff")
+    .header(hyper::header::ACCEPT_RANGES, "bytes")
+    .header(hyper::header::CACHE_CONTROL, calc_cache_control(browser_cache_max_age_seconds));
+  if sandboxed {
+    response = response.header("Content-Security-Policy", SANDBOXED_HTML_CONTENT_SECURITY_POLICY);
+  }
+
+  // Audio and video elements request ranges to seek. The object is decrypted as a whole, so this saves
+  // bandwidth but not server work.
+  match range_header.and_then(|range_header| parse_byte_range(range_header, data.len())) {
+    Some(ByteRange::Satisfiable(start, end)) => {
+      let content_range = format!("bytes {}-{}/{}", start, end, data.len());
+      Ok(
+        response
+          .status(StatusCode::PARTIAL_CONTENT)
+          .header(hyper::header::CONTENT_RANGE, content_range)
+          .body(full_body(Bytes::from(data).slice(start..=end)))
+          .unwrap(),
+      )
+    }
+    Some(ByteRange::Unsatisfiable) => Ok(
+      response
+        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+        .header(hyper::header::CONTENT_RANGE, format!("bytes */{}", data.len()))
+        .body(full_body(Vec::<u8>::new()))
+        .unwrap(),
+    ),
+    None => Ok(response.body(full_body(data)).unwrap()),
+  }
+}
+
+enum ByteRange {
+  /// Inclusive start and end offsets.
+  Satisfiable(usize, usize),
+  Unsatisfiable,
+}
+
+/// Parse a single range `Range` header. Malformed headers and requests for more than one range give
+/// None, in which case the whole file is served.
+fn parse_byte_range(range_header: &str, len: usize) -> Option<ByteRange> {
+  let spec = range_header.trim().strip_prefix("bytes=")?.trim();
+  if spec.contains(',') {
+    return None;
+  }
+  let (start, end) = spec.split_once('-')?;
+  let (start, end) = (start.trim(), end.trim());
+  if start.is_empty() {
+    let suffix_len = end.parse::<usize>().ok()?;
+    if suffix_len == 0 || len == 0 {
+      return Some(ByteRange::Unsatisfiable);
+    }
+    return Some(ByteRange::Satisfiable(len.saturating_sub(suffix_len), len - 1));
+  }
+  let start = start.parse::<usize>().ok()?;
+  let end = if end.is_empty() { None } else { Some(end.parse::<usize>().ok()?) };
+  if end.is_some_and(|end| end < start) {
+    return None;
+  }
+  if start >= len {
+    return Some(ByteRange::Unsatisfiable);
+  }
+  Some(ByteRange::Satisfiable(start, end.map(|end| end.min(len - 1)).unwrap_or(len - 1)))
 }
 
 async fn get_item_text(
@@ -652,7 +832,7 @@ async fn get_item_fragments(
     Err(_) => return Ok(fragments_not_available_response()),
   };
 
-  let fragments_text = match parse_fragments_text(&fragments_bytes) {
+  let fragments_text = match parse_fragments_text(&fragments_bytes, is_audio_item(&item)) {
     Ok(text) if !text.is_empty() => text,
     _ => return Ok(fragments_not_available_response()),
   };
@@ -704,7 +884,7 @@ async fn get_item_fragment(
     Err(_) => return Ok(fragments_not_available_response()),
   };
 
-  let fragment_text = match parse_fragment_text(&fragments_bytes, ordinal) {
+  let fragment_text = match parse_fragment_text(&fragments_bytes, ordinal, is_audio_item(&item)) {
     Ok(Some(text)) if !text.is_empty() => text,
     _ => return Ok(fragments_not_available_response()),
   };
@@ -743,24 +923,24 @@ fn fragments_not_available_response() -> Response<BoxBody<Bytes, hyper::Error>>
     .unwrap()
 }
 
-fn parse_fragments_text(data: &[u8]) -> InfuResult<Vec<u8>> {
+fn parse_fragments_text(data: &[u8], time_offsets: bool) -> InfuResult<Vec<u8>> {
   let mut fragments = parse_fragment_records(data)?;
   fragments.sort_by(|a, b| a.ordinal.cmp(&b.ordinal));
   let text = fragments
     .into_iter()
-    .map(render_fragment_text)
+    .map(|fragment| render_fragment_text(fragment, time_offsets))
     .filter(|fragment| !fragment.is_empty())
     .collect::<Vec<String>>()
     .join("");
   Ok(text.into_bytes())
 }
 
-fn parse_fragment_text(data: &[u8], ordinal: usize) -> InfuResult<Option<Vec<u8>>> {
+fn parse_fragment_text(data: &[u8], ordinal: usize, time_offsets: bool) -> InfuResult<Option<Vec<u8>>> {
   Ok(
     parse_fragment_records(data)?
       .into_iter()
       .find(|fragment| fragment.ordinal == ordinal)
-      .map(|fragment| render_fragment_text(fragment).into_bytes()),
+      .map(|fragment| render_fragment_text(fragment, time_offsets).into_bytes()),
   )
 }
 
@@ -783,11 +963,17 @@ fn parse_item_fragment_route(name: &str) -> Option<(&str, usize)> {
   suffix.parse::<usize>().ok().map(|ordinal| (uid, ordinal))
 }
 
-fn render_fragment_text(fragment: FragmentRecord) -> String {
+/// Transcript fragments of recordings (`time_offsets`) store a time span in seconds in their page range.
+fn render_fragment_text(fragment: FragmentRecord, time_offsets: bool) -> String {
   let text = fragment.text.trim();
   let mut metadata = vec![format!("Ordinal: {}", fragment.ordinal)];
-  if let Some(page_label) = fragment_page_label(fragment.page_start, fragment.page_end) {
-    metadata.push(page_label);
+  let range_label = if time_offsets {
+    fragment_time_label(fragment.page_start, fragment.page_end)
+  } else {
+    fragment_page_label(fragment.page_start, fragment.page_end)
+  };
+  if let Some(range_label) = range_label {
+    metadata.push(range_label);
   }
   format!("{FRAGMENT_VIEW_RULE}\n{}\n{FRAGMENT_VIEW_RULE}\n\n{text}\n\n\n", metadata.join("\n"))
 }
@@ -800,6 +986,21 @@ fn fragment_page_label(page_start: Option<usize>, page_end: Option<usize>) -> Op
   }
 }
 
+fn fragment_time_label(start_secs: Option<usize>, end_secs: Option<usize>) -> Option<String> {
+  match (start_secs, end_secs) {
+    (Some(start), Some(end)) => Some(format!("Time: {}-{}", clock_time(start), clock_time(end))),
+    _ => None,
+  }
+}
+
+fn clock_time(secs: usize) -> String {
+  if secs >= 3600 {
+    format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+  } else {
+    format!("{}:{:02}", secs / 60, secs % 60)
+  }
+}
+
 fn item_text_filename(uid: &str, content_mime_type: &str) -> String {
   let extension = match content_mime_type {
     "text/markdown" => ".md",
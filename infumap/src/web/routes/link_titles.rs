@@ -30,6 +30,9 @@ const MAX_LINK_TITLE_REDIRECTS: usize = 3;
 const MAX_LINK_TITLE_CHARS: usize = 1000;
 const MAX_X_OEMBED_JSON_BYTES: usize = 64 * 1024;
 const LINK_TITLE_USER_AGENT: &str = "Infumap link title fetcher";
+const LINK_PAGE_FETCH_TIMEOUT_SECS: u64 = 20;
+const MAX_LINK_PAGE_HTML_BYTES: usize = 4 * 1024 * 1024;
+const LINK_PAGE_USER_AGENT: &str = "Infumap link snapshot fetcher";
 const LINK_TITLE_HTML_ACCEPT: &str = "text/html,application/xhtml+xml;q=0.9,*/*;q=0.1";
 const LINK_TITLE_JSON_ACCEPT: &str = "application/json,*/*;q=0.1";
 
@@ -65,7 +68,7 @@ pub async fn fetch_link_title(url: &Url) -> InfuResult<Option<String>> {
     .build()
     .map_err(|e| format!("Could not build link title HTTP client: {}", e))?;
 
-  let html_maybe = fetch_html_prefix(&client, url.clone()).await?;
+  let html_maybe = fetch_html_prefix(&client, url.clone(), MAX_LINK_TITLE_HTML_BYTES).await?.map(|(_, html)| html);
 
   if let Some(html) = &html_maybe {
     if let Some(title) = extract_title_from_html(html) {
@@ -80,7 +83,83 @@ pub async fn fetch_link_title(url: &Url) -> InfuResult<Option<String>> {
   Ok(None)
 }
 
-async fn fetch_html_prefix(client: &reqwest::Client, mut page_url: Url) -> InfuResult<Option<String>> {
+/// HTTP client for fetching linked pages and their resources in full (for snapshots and
+/// archives). Redirects are followed manually so every hop is checked against the URL restrictions.
+pub fn build_link_page_client() -> InfuResult<reqwest::Client> {
+  reqwest::ClientBuilder::new()
+    .timeout(Duration::from_secs(LINK_PAGE_FETCH_TIMEOUT_SECS))
+    .redirect(reqwest::redirect::Policy::none())
+    .user_agent(LINK_PAGE_USER_AGENT)
+    .build()
+    .map_err(|e| format!("Could not build link page HTTP client: {}", e).into())
+}
+
+/// Fetch the HTML of a linked page, under the same URL restrictions as title fetching. Returns
+/// the final URL (after redirects) and the HTML, or None if the page is unavailable, disallowed
+/// or not HTML.
+pub async fn fetch_link_page_html(client: &reqwest::Client, url: &Url) -> InfuResult<Option<(Url, String)>> {
+  fetch_html_prefix(client, url.clone(), MAX_LINK_PAGE_HTML_BYTES).await
+}
+
+pub struct LinkResource {
+  pub url: Url,
+  pub content_type: Option<String>,
+  pub bytes: Vec<u8>,
+}
+
+/// Fetch a resource referenced by a linked page (a stylesheet, image or font), under the same URL
+/// restrictions as the page itself. Returns None if it is unavailable, disallowed or larger than
+/// `max_bytes`.
+pub async fn fetch_link_resource(
+  client: &reqwest::Client,
+  mut resource_url: Url,
+  max_bytes: usize,
+) -> InfuResult<Option<LinkResource>> {
+  for _ in 0..=MAX_LINK_TITLE_REDIRECTS {
+    if !url_allowed_for_title_fetch(&resource_url).await {
+      debug!("Skipping link resource fetch for disallowed URL '{}'.", resource_url);
+      return Ok(None);
+    }
+
+    let response = match client.get(resource_url.clone()).send().await {
+      Ok(response) => response,
+      Err(e) => {
+        debug!("Link resource fetch for '{}' failed: {}", resource_url, e);
+        return Ok(None);
+      }
+    };
+
+    if response.status().is_redirection() {
+      let Some(location) = response.headers().get(LOCATION).and_then(|v| v.to_str().ok()) else {
+        return Ok(None);
+      };
+      resource_url = match resource_url.join(location) {
+        Ok(next_url) => next_url,
+        Err(_) => return Ok(None),
+      };
+      continue;
+    }
+
+    if !response.status().is_success() {
+      debug!("Link resource fetch for '{}' returned status {}.", resource_url, response.status());
+      return Ok(None);
+    }
+
+    let content_type = response.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(|v| v.to_owned());
+    let Some(bytes) = response_bytes_limited(response, max_bytes).await else {
+      return Ok(None);
+    };
+    return Ok(Some(LinkResource { url: resource_url, content_type, bytes }));
+  }
+
+  Ok(None)
+}
+
+async fn fetch_html_prefix(
+  client: &reqwest::Client,
+  mut page_url: Url,
+  max_bytes: usize,
+) -> InfuResult<Option<(Url, String)>> {
   for _ in 0..=MAX_LINK_TITLE_REDIRECTS {
     if !url_allowed_for_title_fetch(&page_url).await {
       debug!("Skipping link title fetch for disallowed URL '{}'.", page_url);
@@ -121,12 +200,12 @@ async fn fetch_html_prefix(client: &reqwest::Client, mut page_url: Url) -> InfuR
       return Ok(None);
     }
 
-    let bytes = response_bytes_prefix(response, MAX_LINK_TITLE_HTML_BYTES).await;
+    let bytes = response_bytes_prefix(response, max_bytes).await;
     let html = decode_html_bytes(&bytes);
     if !looks_like_html(&html) {
       return Ok(None);
     }
-    return Ok(Some(html));
+    return Ok(Some((page_url, html)));
   }
 
   Ok(None)
@@ -749,3 +828,20 @@ async fn response_bytes_prefix(response: reqwest::Response, max_bytes: usize) ->
   }
   result
 }
+
+async fn response_bytes_limited(response: reqwest::Response, max_bytes: usize) -> Option<Vec<u8>> {
+  if response.content_length().map(|v| v > max_bytes as u64).unwrap_or(false) {
+    return None;
+  }
+
+  let mut result = Vec::new();
+  let mut stream = response.bytes_stream();
+  while let Some(chunk) = stream.next().await {
+    let chunk = chunk.ok()?;
+    if result.len() + chunk.len() > max_bytes {
+      return None;
+    }
+    result.extend_from_slice(&chunk);
+  }
+  Some(result)
+}
@@ -29,6 +29,7 @@ pub mod favicons;
 pub mod files;
 pub mod ingest;
 pub mod link_titles;
+pub mod web_archive;
 
 pub fn default_home_page(
   owner_id: &str,
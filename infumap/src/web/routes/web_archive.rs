@@ -0,0 +1,577 @@
+// Copyright (C) The Infumap Authors
+// This file is part of Infumap.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use base64::{Engine as _, engine::general_purpose};
+use infusdk::util::infu::InfuResult;
+use log::debug;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::link_titles::{build_link_page_client, fetch_link_page_html, fetch_link_resource};
+use crate::util::mime::detect_mime_type;
+
+const MAX_WEB_ARCHIVE_RESOURCES: usize = 300;
+const MAX_WEB_ARCHIVE_RESOURCE_BYTES: usize = 8 * 1024 * 1024;
+const MAX_WEB_ARCHIVE_BYTES: usize = 64 * 1024 * 1024;
+const MAX_CSS_IMPORT_DEPTH: usize = 4;
+/// Resources still pending when this much time has passed are left as (blocked) remote references.
+const WEB_ARCHIVE_DEADLINE_SECS: u64 = 120;
+/// Preferred upper bound on the width of the image chosen from a `srcset`.
+const MAX_SRCSET_WIDTH: u32 = 1600;
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Deeper nesting than this is flattened to its text, which keeps archiving of pathological pages
+/// from exhausting the stack.
+const MAX_ELEMENT_DEPTH: usize = 256;
+
+/// Elements dropped (with their content) since they would load active or remote content.
+const DROPPED_ELEMENTS: &[&str] = &["script", "iframe", "frame", "frameset", "object", "embed", "applet", "portal"];
+const VOID_ELEMENTS: &[&str] =
+  &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr"];
+/// Elements whose text is serialized as is rather than escaped (the parser runs with scripting
+/// enabled, so noscript content is raw text too).
+const RAW_TEXT_ELEMENTS: &[&str] = &["style", "xmp", "noembed", "noframes", "noscript", "plaintext"];
+
+pub struct WebArchive {
+  pub url: Url,
+  pub title: Option<String>,
+  pub html: String,
+  pub resource_count: usize,
+  pub skipped_resource_count: usize,
+}
+
+/// Fetch a linked page and the stylesheets, images and fonts it uses, and combine them into a
+/// single self-contained HTML document: stylesheets are inlined as `<style>` elements, images and
+/// fonts become data URIs, and scripts and embedded frames are removed. All fetches go through the
+/// same URL restrictions as link title fetching. Returns None if the page could not be fetched.
+pub async fn archive_link_page(url: &Url) -> InfuResult<Option<WebArchive>> {
+  let client = build_link_page_client()?;
+  let Some((page_url, html)) = fetch_link_page_html(&client, url).await? else {
+    return Ok(None);
+  };
+
+  let mut archiver = WebArchiver {
+    client,
+    resources: HashMap::new(),
+    archive_bytes: 0,
+    fetch_count: 0,
+    resource_count: 0,
+    skipped_resource_count: 0,
+    deadline: Instant::now() + Duration::from_secs(WEB_ARCHIVE_DEADLINE_SECS),
+    title: None,
+  };
+  let html = archiver.rewrite_html(&html, &page_url).await;
+  debug!(
+    "Archived '{}' with {} inlined resource(s) ({} skipped).",
+    page_url, archiver.resource_count, archiver.skipped_resource_count
+  );
+  Ok(Some(WebArchive {
+    url: page_url,
+    title: archiver.title,
+    html,
+    resource_count: archiver.resource_count,
+    skipped_resource_count: archiver.skipped_resource_count,
+  }))
+}
+
+struct WebArchiver {
+  client: reqwest::Client,
+  /// Data URIs by resource URL, None for resources that could not be inlined.
+  resources: HashMap<String, Option<String>>,
+  archive_bytes: usize,
+  fetch_count: usize,
+  resource_count: usize,
+  skipped_resource_count: usize,
+  deadline: Instant,
+  title: Option<String>,
+}
+
+impl WebArchiver {
+  async fn rewrite_html(&mut self, html: &str, page_url: &Url) -> String {
+    let root = parse_archive_document(html.trim_start_matches('\u{feff}'));
+    self.title = root.find(&|element| element.name == "title").and_then(|title| normalized_title(&title.text()));
+    let base_url = root
+      .find(&|element| element.name == "base" && element.attr("href").is_some())
+      .and_then(|base| base.attr("href"))
+      .and_then(|href| page_url.join(href.trim()).ok())
+      .unwrap_or_else(|| page_url.clone());
+    let mut out = String::with_capacity(html.len());
+    out.push_str("<!DOCTYPE html>\n");
+    self.write_element(root, page_url, &base_url, &mut out).await;
+    out
+  }
+
+  /// Serialize an element with its resources inlined, or leave it out entirely.
+  fn write_element<'a>(
+    &'a mut self,
+    mut element: ArchiveElement,
+    page_url: &'a Url,
+    base_url: &'a Url,
+    out: &'a mut String,
+  ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+    Box::pin(async move {
+      match element.name.as_str() {
+        name if DROPPED_ELEMENTS.contains(&name) => return,
+        "base" => return,
+        "meta" if element.attr("http-equiv").is_some() || element.attr("charset").is_some() => return,
+        "source" if element.attr("srcset").is_some() && element.attr("src").is_none() => return,
+        "link" => {
+          if let Some(replacement) = self.rewrite_link_tag(&element, base_url).await {
+            out.push_str(&replacement);
+          }
+          return;
+        }
+        "style" => {
+          let css = self.rewrite_css(&element.text(), base_url, 0).await;
+          out.push_str(&serialize_start_tag(&element));
+          out.push_str(&escape_style_text(&css));
+          out.push_str("</style>");
+          return;
+        }
+        _ => {}
+      }
+
+      self.rewrite_attrs(&mut element, base_url).await;
+      out.push_str(&serialize_start_tag(&element));
+      if element.name == "head" {
+        out.push_str(&archive_head_elements(page_url));
+      }
+      if VOID_ELEMENTS.contains(&element.name.as_str()) {
+        return;
+      }
+      let raw_text = RAW_TEXT_ELEMENTS.contains(&element.name.as_str());
+      for child in std::mem::take(&mut element.children) {
+        match child {
+          ArchiveNode::Text(text) if raw_text => out.push_str(&text),
+          ArchiveNode::Text(text) => out.push_str(&escape_html_text(&text)),
+          ArchiveNode::Comment(comment) => out.push_str(&format!("<!--{}-->", comment)),
+          ArchiveNode::Element(child) => self.write_element(child, page_url, base_url, out).await,
+        }
+      }
+      out.push_str(&format!("</{}>", element.name));
+    })
+  }
+
+  async fn rewrite_link_tag(&mut self, tag: &ArchiveElement, base_url: &Url) -> Option<String> {
+    let rel = tag.attr("rel")?.to_ascii_lowercase();
+    let href = tag.attr("href")?;
+    let rel_tokens = rel.split_ascii_whitespace().collect::<Vec<_>>();
+    if rel_tokens.contains(&"stylesheet") {
+      if rel_tokens.contains(&"alternate") {
+        return None;
+      }
+      let stylesheet_url = base_url.join(href.trim()).ok()?;
+      let (stylesheet_url, css) = self.fetch_stylesheet(stylesheet_url).await?;
+      let css = self.rewrite_css(&css, &stylesheet_url, 1).await;
+      let media = tag.attr("media").map(|media| format!(" media=\"{}\"", escape_html_attr(media))).unwrap_or_default();
+      return Some(format!("<style{}>{}</style>", media, escape_style_text(&css)));
+    }
+    if rel_tokens.iter().any(|token| *token == "icon" || *token == "apple-touch-icon") {
+      let data_uri = self.data_uri(base_url, href).await?;
+      return Some(format!("<link rel=\"{}\" href=\"{}\">", escape_html_attr(&rel), data_uri));
+    }
+    None
+  }
+
+  async fn rewrite_attrs(&mut self, tag: &mut ArchiveElement, base_url: &Url) {
+    tag.attrs.retain(|(name, _)| {
+      !name.starts_with("on") && !matches!(name.as_str(), "integrity" | "crossorigin" | "nonce" | "ping")
+    });
+
+    if tag.name == "img" {
+      // Lazy loading scripts are removed, so use the real image URL they would have swapped in.
+      let src_missing = tag.attr("src").is_none_or(|src| src.trim().is_empty() || src.trim().starts_with("data:"));
+      if src_missing
+        && let Some(lazy_src) =
+          ["data-src", "data-lazy-src", "data-original"].iter().find_map(|name| tag.attr(name)).map(str::to_owned)
+      {
+        tag.set_attr("src", lazy_src);
+      }
+      if tag.attr("src").is_none()
+        && let Some(candidate) = tag.attr("srcset").and_then(best_srcset_candidate)
+      {
+        tag.set_attr("src", candidate);
+      }
+      tag.attrs.retain(|(name, _)| name != "srcset" && name != "sizes");
+    }
+
+    let url_attrs: &[&str] = match tag.name.as_str() {
+      "img" => &["src"],
+      "input" if tag.attr("type").is_some_and(|kind| kind.eq_ignore_ascii_case("image")) => &["src"],
+      "video" => &["poster"],
+      "body" | "table" | "td" | "th" => &["background"],
+      _ => &[],
+    };
+    for attr_name in url_attrs {
+      let Some(value) = tag.attr(attr_name).map(str::to_owned) else {
+        continue;
+      };
+      let replacement = match self.data_uri(base_url, &value).await {
+        Some(data_uri) => data_uri,
+        None => absolute_url(base_url, &value),
+      };
+      tag.set_attr(attr_name, replacement);
+    }
+
+    if matches!(tag.name.as_str(), "a" | "area")
+      && let Some(href) = tag.attr("href").map(str::to_owned)
+    {
+      tag.set_attr("href", absolute_url(base_url, &href));
+    }
+
+    if let Some(style) = tag.attr("style").map(str::to_owned) {
+      let style = self.rewrite_css(&style, base_url, MAX_CSS_IMPORT_DEPTH).await;
+      tag.set_attr("style", style);
+    }
+  }
+
+  /// Inline `url(...)` references as data URIs and `@import`ed stylesheets in place.
+  fn rewrite_css<'a>(
+    &'a mut self,
+    css: &'a str,
+    base_url: &'a Url,
+    depth: usize,
+  ) -> Pin<Box<dyn Future<Output = String> + Send + 'a>> {
+    Box::pin(async move {
+      let lower = css.to_ascii_lowercase();
+      let mut out = String::with_capacity(css.len());
+      let mut i = 0;
+      while i < css.len() {
+        let next_url = lower[i..].find("url(").map(|offset| i + offset);
+        let next_import = lower[i..].find("@import").map(|offset| i + offset);
+        let Some(start) = [next_url, next_import].into_iter().flatten().min() else {
+          out.push_str(&css[i..]);
+          break;
+        };
+        out.push_str(&css[i..start]);
+
+        if Some(start) == next_import {
+          let statement_end = css[start..].find(';').map(|offset| start + offset + 1).unwrap_or(css.len());
+          let statement = &css[start + "@import".len()..statement_end];
+          if depth < MAX_CSS_IMPORT_DEPTH
+            && let Some(import_url) = css_import_reference(statement).and_then(|href| base_url.join(&href).ok())
+            && let Some((import_url, imported_css)) = self.fetch_stylesheet(import_url).await
+          {
+            out.push_str(&self.rewrite_css(&imported_css, &import_url, depth + 1).await);
+          }
+          i = statement_end;
+          continue;
+        }
+
+        let value_start = start + "url(".len();
+        let Some((reference, value_end)) = css_url_reference(css, value_start) else {
+          out.push_str(&css[start..value_start]);
+          i = value_start;
+          continue;
+        };
+        let replacement = if reference.starts_with('#') || reference.is_empty() {
+          reference.clone()
+        } else {
+          match self.data_uri(base_url, &reference).await {
+            Some(data_uri) => data_uri,
+            None => absolute_url(base_url, &reference),
+          }
+        };
+        out.push_str(&format!("url(\"{}\")", replacement.replace('\\', "%5C").replace('"', "%22")));
+        i = value_end;
+      }
+      out
+    })
+  }
+
+  async fn fetch_stylesheet(&mut self, stylesheet_url: Url) -> Option<(Url, String)> {
+    if !self.reserve_fetch() {
+      return None;
+    }
+    let resource = match fetch_link_resource(&self.client, stylesheet_url, MAX_WEB_ARCHIVE_RESOURCE_BYTES).await {
+      Ok(Some(resource)) => resource,
+      Ok(None) | Err(_) => {
+        self.skipped_resource_count += 1;
+        return None;
+      }
+    };
+    let is_html = resource.content_type.as_deref().is_some_and(|content_type| content_type.contains("html"));
+    if is_html {
+      self.skipped_resource_count += 1;
+      return None;
+    }
+    let css = String::from_utf8_lossy(&resource.bytes).trim_start_matches('\u{feff}').to_owned();
+    self.archive_bytes += css.len();
+    self.resource_count += 1;
+    Some((resource.url, css))
+  }
+
+  /// The referenced image or font as a data URI, or None if it could not be inlined.
+  async fn data_uri(&mut self, base_url: &Url, reference: &str) -> Option<String> {
+    let reference = reference.trim();
+    if reference.starts_with("data:") {
+      return Some(reference.to_owned());
+    }
+    let resource_url = base_url.join(reference).ok()?;
+    if let Some(data_uri) = self.resources.get(resource_url.as_str()) {
+      return data_uri.clone();
+    }
+    let data_uri = self.fetch_data_uri(resource_url.clone()).await;
+    if data_uri.is_none() {
+      self.skipped_resource_count += 1;
+    }
+    self.resources.insert(resource_url.to_string(), data_uri.clone());
+    data_uri
+  }
+
+  async fn fetch_data_uri(&mut self, resource_url: Url) -> Option<String> {
+    if !self.reserve_fetch() {
+      return None;
+    }
+    let resource = fetch_link_resource(&self.client, resource_url, MAX_WEB_ARCHIVE_RESOURCE_BYTES).await.ok()??;
+    let mime_type = resource
+      .content_type
+      .as_deref()
+      .and_then(|content_type| content_type.split(';').next())
+      .map(|mime_type| mime_type.trim().to_ascii_lowercase())
+      .filter(|mime_type| is_inlinable_mime_type(mime_type))
+      .unwrap_or_else(|| detect_mime_type(&resource.bytes));
+    if !is_inlinable_mime_type(&mime_type) {
+      return None;
+    }
+    let data_uri = format!("data:{};base64,{}", mime_type, general_purpose::STANDARD.encode(&resource.bytes));
+    if self.archive_bytes + data_uri.len() > MAX_WEB_ARCHIVE_BYTES {
+      return None;
+    }
+    self.archive_bytes += data_uri.len();
+    self.resource_count += 1;
+    Some(data_uri)
+  }
+
+  fn reserve_fetch(&mut self) -> bool {
+    if self.fetch_count >= MAX_WEB_ARCHIVE_RESOURCES || Instant::now() >= self.deadline {
+      return false;
+    }
+    self.fetch_count += 1;
+    true
+  }
+}
+
+enum ArchiveNode {
+  Text(String),
+  Comment(String),
+  Element(ArchiveElement),
+}
+
+/// An owned copy of a parsed element. scraper's DOM is not Send, so the page is copied out of it
+/// before any of its resources are fetched.
+struct ArchiveElement {
+  name: String,
+  attrs: Vec<(String, String)>,
+  children: Vec<ArchiveNode>,
+}
+
+impl ArchiveElement {
+  fn attr(&self, name: &str) -> Option<&str> {
+    self.attrs.iter().find(|(attr_name, _)| attr_name == name).map(|(_, value)| value.as_str())
+  }
+
+  fn set_attr(&mut self, name: &str, value: String) {
+    match self.attrs.iter_mut().find(|(attr_name, _)| attr_name == name) {
+      Some((_, existing)) => *existing = value,
+      None => self.attrs.push((name.to_owned(), value)),
+    }
+  }
+
+  /// The text of the element's direct text children.
+  fn text(&self) -> String {
+    self
+      .children
+      .iter()
+      .filter_map(|child| match child {
+        ArchiveNode::Text(text) => Some(text.as_str()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// The first element (in document order, including this one) matching `predicate`.
+  fn find(&self, predicate: &dyn Fn(&ArchiveElement) -> bool) -> Option<&ArchiveElement> {
+    if predicate(self) {
+      return Some(self);
+    }
+    self.children.iter().find_map(|child| match child {
+      ArchiveNode::Element(element) => element.find(predicate),
+      _ => None,
+    })
+  }
+}
+
+/// Parse the page the way a browser would. The result is always rooted at an `<html>` element
+/// with `<head>` and `<body>` children.
+fn parse_archive_document(html: &str) -> ArchiveElement {
+  let document = Html::parse_document(html);
+  archive_element(document.root_element(), 0)
+}
+
+fn archive_element(element: ElementRef<'_>, depth: usize) -> ArchiveElement {
+  let children = if depth >= MAX_ELEMENT_DEPTH {
+    vec![ArchiveNode::Text(element.text().collect())]
+  } else {
+    element
+      .children()
+      .filter_map(|child| match child.value() {
+        Node::Text(text) => Some(ArchiveNode::Text(str::to_owned(text))),
+        Node::Comment(comment) => Some(ArchiveNode::Comment(str::to_owned(comment))),
+        Node::Element(_) => {
+          ElementRef::wrap(child).map(|child| ArchiveNode::Element(archive_element(child, depth + 1)))
+        }
+        _ => None,
+      })
+      .collect()
+  };
+  ArchiveElement {
+    name: element.value().name().to_owned(),
+    attrs: element.value().attrs().map(|(name, value)| (name.to_owned(), value.to_owned())).collect(),
+    children,
+  }
+}
+
+fn serialize_start_tag(element: &ArchiveElement) -> String {
+  let mut out = format!("<{}", element.name);
+  for (name, value) in &element.attrs {
+    out.push(' ');
+    out.push_str(name);
+    if !value.is_empty() {
+      out.push_str("=\"");
+      out.push_str(&escape_html_attr(value));
+      out.push('"');
+    }
+  }
+  out.push('>');
+  out
+}
+
+/// Records where and when the archive was made. The page's own charset declaration is dropped
+/// since the archive is always stored as UTF-8.
+fn archive_head_elements(page_url: &Url) -> String {
+  let archived_at = OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default();
+  format!(
+    "<meta charset=\"utf-8\"><meta name=\"infumap-archived-from\" content=\"{}\"><meta name=\"infumap-archived-at\" content=\"{}\">",
+    escape_html_attr(page_url.as_str()),
+    archived_at
+  )
+}
+
+fn css_url_reference(css: &str, value_start: usize) -> Option<(String, usize)> {
+  let rest = &css[value_start..];
+  let trimmed = rest.trim_start();
+  let leading = rest.len() - trimmed.len();
+  match trimmed.chars().next()? {
+    quote @ ('"' | '\'') => {
+      let value_end = trimmed[1..].find(quote)? + 1;
+      let close = trimmed[value_end + 1..].find(')')? + value_end + 1;
+      Some((trimmed[1..value_end].trim().to_owned(), value_start + leading + close + 1))
+    }
+    _ => {
+      let close = trimmed.find(')')?;
+      Some((trimmed[..close].trim().to_owned(), value_start + leading + close + 1))
+    }
+  }
+}
+
+fn css_import_reference(statement: &str) -> Option<String> {
+  let statement = statement.trim_start();
+  if statement.len() >= 4 && statement[..4].eq_ignore_ascii_case("url(") {
+    return css_url_reference(statement, 4).map(|(reference, _)| reference);
+  }
+  let quote = statement.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+  let end = statement[1..].find(quote)? + 1;
+  Some(statement[1..end].trim().to_owned())
+}
+
+/// The candidate from a `srcset` that is widest without exceeding MAX_SRCSET_WIDTH (or the
+/// narrowest, if all are wider). Density descriptors are treated as 1000px per 1x.
+fn best_srcset_candidate(srcset: &str) -> Option<String> {
+  let candidates = srcset
+    .split(',')
+    .filter_map(|candidate| {
+      let mut parts = candidate.split_ascii_whitespace();
+      let url = parts.next()?;
+      let width = match parts.next() {
+        Some(descriptor) if descriptor.ends_with('w') => descriptor[..descriptor.len() - 1].parse::<u32>().ok()?,
+        Some(descriptor) if descriptor.ends_with('x') => {
+          (descriptor[..descriptor.len() - 1].parse::<f32>().ok()? * 1000.0) as u32
+        }
+        _ => 1000,
+      };
+      Some((url, width))
+    })
+    .collect::<Vec<_>>();
+  candidates
+    .iter()
+    .filter(|(_, width)| *width <= MAX_SRCSET_WIDTH)
+    .max_by_key(|(_, width)| *width)
+    .or_else(|| candidates.iter().min_by_key(|(_, width)| *width))
+    .map(|(url, _)| (*url).to_owned())
+}
+
+fn is_inlinable_mime_type(mime_type: &str) -> bool {
+  mime_type.starts_with("image/")
+    || mime_type.starts_with("font/")
+    || mime_type.starts_with("application/font-")
+    || mime_type.starts_with("application/x-font-")
+    || mime_type == "application/vnd.ms-fontobject"
+}
+
+fn absolute_url(base_url: &Url, reference: &str) -> String {
+  match base_url.join(reference.trim()) {
+    Ok(url) => url.to_string(),
+    Err(_) => reference.to_owned(),
+  }
+}
+
+fn normalized_title(value: &str) -> Option<String> {
+  let title = value.split_whitespace().collect::<Vec<_>>().join(" ");
+  if title.is_empty() { None } else { Some(title.chars().take(MAX_TITLE_CHARS).collect()) }
+}
+
+fn escape_html_attr(value: &str) -> String {
+  value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_html_text(value: &str) -> String {
+  value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Keep inlined stylesheet text from closing the `<style>` element it is placed in.
+fn escape_style_text(css: &str) -> String {
+  let lower = css.to_ascii_lowercase();
+  if !lower.contains("</style") {
+    return css.to_owned();
+  }
+  let mut out = String::with_capacity(css.len());
+  let mut i = 0;
+  while let Some(offset) = lower[i..].find("</style") {
+    out.push_str(&css[i..i + offset]);
+    out.push_str("<\\/style");
+    i += offset + "</style".len();
+  }
+  out.push_str(&css[i..]);
+  out
+}
@@ -19,7 +19,9 @@ use config::Config;
 use http_body_util::{BodyExt, Empty, Full, combinators::BoxBody};
 use hyper::header::HeaderValue;
 use hyper::{Method, Request, Response, StatusCode};
+use infusdk::item::is_valid_item_label;
 use infusdk::util::infu::InfuResult;
+use infusdk::util::uid::is_uid;
 use log::{debug, error, warn};
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -79,7 +81,7 @@ pub async fn http_serve(
   let (mut response, cors_policy) = if req.uri().path() == "/command" {
     (serve_command_route(config.clone(), &db, &object_store, image_cache.clone(), req).await, CorsPolicy::EmbedAllowed)
   } else if req.uri().path() == "/chat/stream" {
-    (serve_chat_stream_route(config.clone(), &db, req).await, CorsPolicy::EmbedAllowed)
+    (serve_chat_stream_route(config.clone(), &db, &object_store, req).await, CorsPolicy::EmbedAllowed)
   } else if req.uri().path().starts_with("/account/") {
     (serve_account_route(config.clone(), &db, req).await, CorsPolicy::EmbedAllowed)
   } else if req.uri().path().starts_with("/ingest/") {
@@ -94,11 +96,7 @@ pub async fn http_serve(
     match serve_dist_routes(&req) {
       Some(response) => (response, CorsPolicy::Disabled),
       None => {
-        if req.method() == Method::GET {
-          // &&
-          // TODO (MEDIUM): explicit support only for /{item_id}, /{username} and /{username}/{label}
-          //       req.uri().path().len() > 32 &&
-          //       is_uid(&req.uri().path()[req.uri().path().len()-32..]) {
+        if req.method() == Method::GET && is_index_route_path(req.uri().path()) {
           (serve_index(), CorsPolicy::Disabled)
         } else {
           (not_found_response(), CorsPolicy::Disabled)
@@ -218,6 +216,18 @@ pub fn _service_unavailable_response(reason: &str) -> Response<BoxBody<Bytes, hy
   Response::builder().status(StatusCode::SERVICE_UNAVAILABLE).body(empty_body()).unwrap()
 }
 
+/// Paths handled by the web client: /, /{item_id}, /{username} and the other single segment routes (/login etc.),
+/// /{username}/{label} and /remote/{origin}/{item_id}.
+fn is_index_route_path(path: &str) -> bool {
+  let segments = path.trim_start_matches('/').split('/').collect::<Vec<&str>>();
+  match segments.as_slice() {
+    [_] => true,
+    [username, label] => !username.is_empty() && is_valid_item_label(label),
+    ["remote", origin, item_id] => !origin.is_empty() && is_uid(item_id),
+    _ => false,
+  }
+}
+
 pub fn not_found_response() -> Response<BoxBody<Bytes, hyper::Error>> {
   Response::builder().status(StatusCode::NOT_FOUND).body(empty_body()).unwrap()
 }
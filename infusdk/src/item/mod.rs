@@ -445,7 +445,51 @@ pub fn is_popup_positionable_item_type(item_type: ItemType) -> bool {
   item_type == ItemType::Page || item_type == ItemType::Image
 }
 
-const ALL_JSON_FIELDS: [&'static str; 55] = [
+pub fn is_labelled_item_type(item_type: ItemType) -> bool {
+  item_type == ItemType::Page || item_type == ItemType::File
+}
+
+pub const ITEM_LABEL_MAX_LENGTH: usize = 64;
+
+/// Item labels are url path segments: lower case ascii letters, digits and single dashes between them. A label
+/// may not look like an item id, since /{username}/{label} and /{item_id} urls must be unambiguous.
+pub fn is_valid_item_label(label: &str) -> bool {
+  if label.is_empty() || label.len() > ITEM_LABEL_MAX_LENGTH || is_uid(label) {
+    return false;
+  }
+  if label.starts_with('-') || label.ends_with('-') || label.contains("--") {
+    return false;
+  }
+  label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn validate_item_label(label: &str, item_id: &str) -> InfuResult<()> {
+  if !is_valid_item_label(label) {
+    return Err(format!("'label' field for item '{}' is not a valid label: '{}'.", item_id, label).into());
+  }
+  Ok(())
+}
+
+/// An item location is a latitude and longitude in degrees, both or neither set.
+fn validate_item_location(latitude: Option<f64>, longitude: Option<f64>, item_id: &str) -> InfuResult<()> {
+  match (latitude, longitude) {
+    (Some(latitude), Some(longitude)) => {
+      if !latitude.is_finite() || latitude.abs() > 90.0 {
+        return Err(format!("'latitude' field for item '{}' must be within [-90, 90]: {}.", item_id, latitude).into());
+      }
+      if !longitude.is_finite() || longitude.abs() > 180.0 {
+        return Err(
+          format!("'longitude' field for item '{}' must be within [-180, 180]: {}.", item_id, longitude).into(),
+        );
+      }
+      Ok(())
+    }
+    (None, None) => Ok(()),
+    _ => Err(format!("Item '{}' must have both 'latitude' and 'longitude' fields, or neither.", item_id).into()),
+  }
+}
+
+const ALL_JSON_FIELDS: [&'static str; 59] = [
   "__recordType",
   "itemType",
   "ownerId",
@@ -459,6 +503,9 @@ const ALL_JSON_FIELDS: [&'static str; 55] = [
   "endDateTime",
   "ordering",
   "title",
+  "label",
+  "latitude",
+  "longitude",
   "spatialPositionGr",
   "spatialWidthGr",
   "innerSpatialWidthGr",
@@ -483,6 +530,7 @@ const ALL_JSON_FIELDS: [&'static str; 55] = [
   "thumbnail",
   "mimeType",
   "fileSizeBytes",
+  "durationMs",
   "rating",
   "ratingType",
   "dividerDirection",
@@ -542,6 +590,13 @@ pub struct Item {
   // titled
   pub title: Option<String>,
 
+  // labelled (page, file)
+  pub label: Option<String>,
+
+  // located (all item types). Set explicitly, taking precedence over a location found in the item's data.
+  pub latitude: Option<f64>,
+  pub longitude: Option<f64>,
+
   // data
   pub original_creation_date: Option<i64>,
   pub mime_type: Option<String>,
@@ -593,6 +648,7 @@ pub struct Item {
   pub inline_marks: Option<Vec<i64>>,
 
   // file
+  pub duration_ms: Option<i64>,
 
   // password
   pub text: Option<String>,
@@ -600,6 +656,7 @@ pub struct Item {
   // table
 
   // image
+  // image_size_px (and natural_aspect) are also set on video files, giving the frame size.
   pub image_size_px: Option<Dimensions<i64>>,
   pub thumbnail: Option<String>,
 
@@ -634,6 +691,9 @@ impl Clone for Item {
       spatial_width_gr: self.spatial_width_gr.clone(),
       spatial_height_gr: self.spatial_height_gr.clone(),
       title: self.title.clone(),
+      label: self.label.clone(),
+      latitude: self.latitude,
+      longitude: self.longitude,
       original_creation_date: self.original_creation_date.clone(),
       mime_type: self.mime_type.clone(),
       file_size_bytes: self.file_size_bytes.clone(),
@@ -667,6 +727,7 @@ impl Clone for Item {
       number_of_visible_columns: self.number_of_visible_columns.clone(),
       image_size_px: self.image_size_px.clone(),
       thumbnail: self.thumbnail.clone(),
+      duration_ms: self.duration_ms.clone(),
       rating: self.rating.clone(),
       rating_type: self.rating_type.clone(),
       divider_direction: self.divider_direction.clone(),
@@ -1017,6 +1078,36 @@ impl JsonLogSerializable<Item> for Item {
       }
     }
 
+    // labelled
+    match (&old.label, &new.label) {
+      (Some(_), None) => {
+        if !is_labelled_item_type(old.item_type) {
+          cannot_modify_err("label", &old.id)?;
+        }
+        result.insert(String::from("label"), Value::Null);
+      }
+      (o, Some(n)) if o.as_ref() != Some(n) => {
+        if !is_labelled_item_type(old.item_type) {
+          cannot_modify_err("label", &old.id)?;
+        }
+        validate_item_label(n, &old.id)?;
+        result.insert(String::from("label"), Value::String(n.clone()));
+      }
+      _ => {}
+    }
+
+    // located
+    if old.latitude != new.latitude || old.longitude != new.longitude {
+      validate_item_location(new.latitude, new.longitude, &old.id)?;
+      for (field_name, value) in [("latitude", new.latitude), ("longitude", new.longitude)] {
+        let value = match value {
+          Some(v) => Value::Number(Number::from_f64(v).ok_or(nan_err(field_name, &old.id))?),
+          None => Value::Null,
+        };
+        result.insert(String::from(field_name), value);
+      }
+    }
+
     // data
     // Like the data file, all these fields are immutable.
     if let Some(new_original_creation_date) = new.original_creation_date {
@@ -1137,7 +1228,7 @@ impl JsonLogSerializable<Item> for Item {
         Some(o) => o != new_natural_aspect,
         None => true,
       } {
-        if !is_aspect_item_type(old.item_type) {
+        if !is_aspect_item_type(old.item_type) && old.item_type != ItemType::File {
           cannot_modify_err("naturalAspect", &old.id)?;
         }
         result.insert(
@@ -1418,6 +1509,18 @@ impl JsonLogSerializable<Item> for Item {
     }
 
     // file
+    // Set by the server once the media of an audio or video file has been probed.
+    if let Some(new_duration_ms) = new.duration_ms {
+      if match old.duration_ms {
+        Some(o) => o != new_duration_ms,
+        None => true,
+      } {
+        if old.item_type != ItemType::File {
+          cannot_modify_err("durationMs", &old.id)?;
+        }
+        result.insert(String::from("durationMs"), Value::Number(new_duration_ms.into()));
+      }
+    }
 
     // password
     if let Some(new_text) = &new.text {
@@ -1440,7 +1543,7 @@ impl JsonLogSerializable<Item> for Item {
         Some(o) => o != new_image_size_px,
         None => true,
       } {
-        if old.item_type != ItemType::Image {
+        if old.item_type != ItemType::Image && old.item_type != ItemType::File {
           cannot_modify_err("imageSizePx", &old.id)?;
         }
         result.insert(String::from("imageSizePx"), json::dimensions_to_object(&new_image_size_px));
@@ -1663,6 +1766,27 @@ impl JsonLogSerializable<Item> for Item {
       self.title = Some(v);
     }
 
+    // labelled
+    if map.contains_key("label") {
+      if !is_labelled_item_type(self.item_type) {
+        not_applicable_err("label", self.item_type, &self.id)?;
+      }
+      let label = json::get_string_field(map, "label")?;
+      if let Some(label) = &label {
+        validate_item_label(label, &self.id)?;
+      }
+      self.label = label;
+    }
+
+    // located
+    if map.contains_key("latitude") || map.contains_key("longitude") {
+      let latitude = json::get_float_field(map, "latitude")?;
+      let longitude = json::get_float_field(map, "longitude")?;
+      validate_item_location(latitude, longitude, &self.id)?;
+      self.latitude = latitude;
+      self.longitude = longitude;
+    }
+
     // flags
     if let Some(v) = json::get_integer_field(map, "flags")? {
       if !is_flags_item_type(self.item_type) {
@@ -1729,7 +1853,7 @@ impl JsonLogSerializable<Item> for Item {
 
     // aspect
     if let Some(v) = json::get_float_field(map, "naturalAspect")? {
-      if !is_aspect_item_type(self.item_type) {
+      if !is_aspect_item_type(self.item_type) && self.item_type != ItemType::File {
         not_applicable_err("naturalAspect", self.item_type, &self.id)?;
       }
       self.natural_aspect = Some(v);
@@ -1895,6 +2019,12 @@ impl JsonLogSerializable<Item> for Item {
     }
 
     // file
+    if let Some(v) = json::get_integer_field(map, "durationMs")? {
+      if self.item_type != ItemType::File {
+        not_applicable_err("durationMs", self.item_type, &self.id)?;
+      }
+      self.duration_ms = Some(v);
+    }
 
     // password
     if let Some(v) = json::get_string_field(map, "text")? {
@@ -1909,7 +2039,7 @@ impl JsonLogSerializable<Item> for Item {
 
     // image
     if let Some(v) = json::get_dimensions_field(map, "imageSizePx")? {
-      if self.item_type != ItemType::Image {
+      if self.item_type != ItemType::Image && self.item_type != ItemType::File {
         not_applicable_err("imageSizePx", self.item_type, &self.id)?;
       }
       self.image_size_px = Some(v);
@@ -2043,6 +2173,27 @@ fn to_json(item: &Item) -> InfuResult<serde_json::Map<String, serde_json::Value>
     result.insert(String::from("title"), Value::String(title.clone()));
   }
 
+  // labelled
+  if let Some(label) = &item.label {
+    if !is_labelled_item_type(item.item_type) {
+      unexpected_field_err("label", &item.id, item.item_type)?
+    }
+    result.insert(String::from("label"), Value::String(label.clone()));
+  }
+
+  // located
+  validate_item_location(item.latitude, item.longitude, &item.id)?;
+  if let (Some(latitude), Some(longitude)) = (item.latitude, item.longitude) {
+    result.insert(
+      String::from("latitude"),
+      Value::Number(Number::from_f64(latitude).ok_or(nan_err("latitude", &item.id))?),
+    );
+    result.insert(
+      String::from("longitude"),
+      Value::Number(Number::from_f64(longitude).ok_or(nan_err("longitude", &item.id))?),
+    );
+  }
+
   // data
   if let Some(original_creation_date) = item.original_creation_date {
     if !is_data_item_type(item.item_type) {
@@ -2117,7 +2268,7 @@ fn to_json(item: &Item) -> InfuResult<serde_json::Map<String, serde_json::Value>
 
   // aspect
   if let Some(natural_aspect) = item.natural_aspect {
-    if !is_aspect_item_type(item.item_type) {
+    if !is_aspect_item_type(item.item_type) && item.item_type != ItemType::File {
       unexpected_field_err("naturalAspect", &item.id, item.item_type)?
     }
     result.insert(
@@ -2274,6 +2425,12 @@ fn to_json(item: &Item) -> InfuResult<serde_json::Map<String, serde_json::Value>
   }
 
   // file
+  if let Some(duration_ms) = item.duration_ms {
+    if item.item_type != ItemType::File {
+      unexpected_field_err("durationMs", &item.id, item.item_type)?
+    }
+    result.insert(String::from("durationMs"), Value::Number(duration_ms.into()));
+  }
 
   // password
   if let Some(text) = &item.text {
@@ -2287,7 +2444,7 @@ fn to_json(item: &Item) -> InfuResult<serde_json::Map<String, serde_json::Value>
 
   // image
   if let Some(image_size_px) = &item.image_size_px {
-    if item.item_type != ItemType::Image {
+    if item.item_type != ItemType::Image && item.item_type != ItemType::File {
       unexpected_field_err("imageSizePx", &item.id, item.item_type)?
     }
     result.insert(String::from("imageSizePx"), json::dimensions_to_object(&image_size_px));
@@ -2393,6 +2550,10 @@ fn from_json(map: &serde_json::Map<String, serde_json::Value>) -> InfuResult<Ite
   let end_datetime = json::get_integer_field(map, "endDateTime")?;
   validate_datetime_range(item_type, datetime, end_datetime, &id)?;
 
+  let latitude = json::get_float_field(map, "latitude")?;
+  let longitude = json::get_float_field(map, "longitude")?;
+  validate_item_location(latitude, longitude, &id)?;
+
   let r = Item {
     item_type: item_type.clone(),
     id: id.clone(),
@@ -2518,6 +2679,24 @@ fn from_json(map: &serde_json::Map<String, serde_json::Value>) -> InfuResult<Ite
       }
     }?,
 
+    // labelled
+    label: match json::get_string_field(map, "label")? {
+      Some(v) => {
+        if !is_labelled_item_type(item_type) {
+          Err(not_applicable_err("label", item_type, &id))
+        } else if !is_valid_item_label(&v) {
+          Err(format!("'label' field for item '{}' is not a valid label: '{}'.", id, v).into())
+        } else {
+          Ok(Some(v))
+        }
+      }
+      None => Ok(None),
+    }?,
+
+    // located
+    latitude,
+    longitude,
+
     // data
     original_creation_date: match json::get_integer_field(map, "originalCreationDate")? {
       Some(v) => {
@@ -2687,7 +2866,7 @@ fn from_json(map: &serde_json::Map<String, serde_json::Value>) -> InfuResult<Ite
     // aspect
     natural_aspect: match json::get_float_field(map, "naturalAspect")? {
       Some(v) => {
-        if is_aspect_item_type(item_type) {
+        if is_aspect_item_type(item_type) || item_type == ItemType::File {
           Ok(Some(v))
         } else {
           Err(not_applicable_err("naturalAspect", item_type, &id))
@@ -2987,6 +3166,16 @@ fn from_json(map: &serde_json::Map<String, serde_json::Value>) -> InfuResult<Ite
     }?,
 
     // file
+    duration_ms: match json::get_integer_field(map, "durationMs")? {
+      Some(v) => {
+        if item_type == ItemType::File {
+          Ok(Some(v))
+        } else {
+          Err(not_applicable_err("durationMs", item_type, &id))
+        }
+      }
+      None => Ok(None),
+    }?,
 
     // password
     text: match json::get_string_field(map, "text")? {
@@ -3011,7 +3200,7 @@ fn from_json(map: &serde_json::Map<String, serde_json::Value>) -> InfuResult<Ite
     // image
     image_size_px: match json::get_dimensions_field(map, "imageSizePx")? {
       Some(v) => {
-        if item_type == ItemType::Image {
+        if item_type == ItemType::Image || item_type == ItemType::File {
           Ok(Some(v))
         } else {
           Err(not_applicable_err("imageSizePx", item_type, &id))
@@ -3181,6 +3370,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3248,6 +3441,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3319,6 +3516,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3378,6 +3579,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3443,6 +3648,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3520,6 +3729,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: Some(divider_direction),
@@ -3609,6 +3822,10 @@ impl Item {
       text: None,
       image_size_px: None,
       thumbnail: None,
+      duration_ms: None,
+      label: None,
+      latitude: None,
+      longitude: None,
       rating: None,
       rating_type: None,
       divider_direction: None,
@@ -3680,6 +3897,21 @@ impl Item {
       }
     }
 
+    // Labelled properties
+    if is_labelled_item_type(self.item_type)
+      && let Some(label) = &self.label
+    {
+      hashes.push(hash_string_to_uid(label));
+    }
+
+    // Located properties
+    if let Some(latitude) = self.latitude {
+      hashes.push(hash_f64_to_uid(latitude));
+    }
+    if let Some(longitude) = self.longitude {
+      hashes.push(hash_f64_to_uid(longitude));
+    }
+
     // Data properties
     if is_data_item_type(self.item_type) {
       if let Some(original_creation_date) = self.original_creation_date {
@@ -3840,6 +4072,20 @@ impl Item {
       }
     }
 
+    // File-specific properties
+    if self.item_type == ItemType::File {
+      if let Some(duration_ms) = self.duration_ms {
+        hashes.push(hash_i64_to_uid(duration_ms));
+      }
+      if let Some(image_size_px) = &self.image_size_px {
+        let size_str = format!("{},{}", image_size_px.w, image_size_px.h);
+        hashes.push(hash_string_to_uid(&size_str));
+      }
+      if let Some(natural_aspect) = self.natural_aspect {
+        hashes.push(hash_f64_to_uid(natural_aspect));
+      }
+    }
+
     // Image-specific properties
     if self.item_type == ItemType::Image {
       if let Some(image_size_px) = &self.image_size_px {
@@ -0,0 +1,108 @@
+#!/usr/bin/env python3
+
+# Copyright (C) The Infumap Authors
+# This file is part of Infumap.
+#
+# This program is free software: you can redistribute it and/or modify
+# it under the terms of the GNU Affero General Public License as
+# published by the Free Software Foundation, either version 3 of the
+# License, or (at your option) any later version.
+#
+# This program is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+# GNU Affero General Public License for more details.
+#
+# You should have received a copy of the GNU Affero General Public License
+# along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+"""Stand-in face detection and embedding server for testing Infumap's face clustering.
+
+Answers POST /face-embed with a single face covering the middle of the image, without
+looking at the pixels. The face embedding is chosen from --identities fixed vectors by
+a hash of the uploaded bytes, with a little noise added, so that uploading the same
+few images repeatedly produces a handful of face clusters. Only the Python standard
+library is used.
+
+    ./tools/face_embed_stub.py --port 8792
+
+then set face_embed_url = "http://127.0.0.1:8792/face-embed" in Infumap's settings.
+"""
+
+from __future__ import annotations
+
+import argparse
+import hashlib
+import json
+import math
+import random
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+ENDPOINT_PATH = "/face-embed"
+MODEL_ID = "face-embed-stub"
+
+
+def identity_vectors(count: int, dimensions: int) -> list[list[float]]:
+    rng = random.Random(0)
+    return [[rng.gauss(0.0, 1.0) for _ in range(dimensions)] for _ in range(count)]
+
+
+def build_response(body: bytes, identities: list[list[float]], noise: float) -> dict:
+    digest = hashlib.sha256(body).digest()
+    identity = identities[int.from_bytes(digest[:4], "big") % len(identities)]
+    rng = random.Random(digest)
+    embedding = [value + rng.gauss(0.0, noise) for value in identity]
+    norm = math.sqrt(sum(value * value for value in embedding)) or 1.0
+    return {
+        "model_id": MODEL_ID,
+        "faces": [
+            {
+                "box": [0.3, 0.2, 0.7, 0.7],
+                "confidence": 0.99,
+                "embedding": [value / norm for value in embedding],
+            }
+        ],
+    }
+
+
+def make_handler(identities: list[list[float]], noise: float) -> type[BaseHTTPRequestHandler]:
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self) -> None:
+            length = int(self.headers.get("Content-Length") or 0)
+            body = self.rfile.read(length)
+            if self.path.rstrip("/") != ENDPOINT_PATH:
+                self.send_json(404, {"error": f"Unknown path {self.path}"})
+                return
+            if b'name="file"' not in body:
+                self.send_json(422, {"error": "Missing multipart field 'file'."})
+                return
+            self.send_json(200, build_response(body, identities, noise))
+
+        def send_json(self, status: int, payload: dict) -> None:
+            data = json.dumps(payload).encode("utf-8")
+            self.send_response(status)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            self.end_headers()
+            self.wfile.write(data)
+
+    return Handler
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("--host", default="127.0.0.1")
+    parser.add_argument("--port", type=int, default=8792)
+    parser.add_argument("--identities", type=int, default=3, help="number of distinct people to pretend to see")
+    parser.add_argument("--dimensions", type=int, default=128)
+    parser.add_argument("--noise", type=float, default=0.05, help="per-image embedding noise")
+    args = parser.parse_args()
+
+    identities = identity_vectors(max(args.identities, 1), args.dimensions)
+    server = ThreadingHTTPServer((args.host, args.port), make_handler(identities, args.noise))
+    print(f"Stand-in face embedding server listening on http://{args.host}:{args.port}{ENDPOINT_PATH}")
+    server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
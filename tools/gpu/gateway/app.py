@@ -276,6 +276,18 @@ def service_registry() -> dict[str, ServiceProxy]:
                 8788,
             ),
         ),
+        # Text queries for text-to-image search are embedded by the image extraction service's own
+        # image embedding model rather than llama-server, so like /text-embed they bypass the lock.
+        ServiceProxy(
+            service_name="image_text_embed",
+            public_paths=("/image-text-embed",),
+            upstream_base_url=upstream_base_url(
+                "GPU_IMAGE_EXTRACT_UPSTREAM_URL",
+                "127.0.0.1",
+                8788,
+            ),
+            uses_global_gpu_lock=False,
+        ),
         ServiceProxy(
             service_name="text_embed",
             public_paths=("/text-embed",),
@@ -326,6 +338,13 @@ def gpu_tools_endpoints() -> list[dict[str, Any]]:
             "service": "image_extract",
             "description": "Render the first page of a PDF and extract only a detailed visual caption.",
         },
+        {
+            "id": "image_text_embed",
+            "method": "POST",
+            "path": "/image-text-embed",
+            "service": "image_text_embed",
+            "description": "Embed text queries into the image embedding space, for text-to-image search. Returns 501 if the image embedding model has no text encoder.",
+        },
         {
             "id": "text_embed",
             "method": "POST",
@@ -37,7 +37,13 @@ from PIL import Image, ImageOps, UnidentifiedImageError
 from python_multipart import MultipartParser
 from python_multipart.multipart import parse_options_header
 
-from backend_api import ImageCaptionResponse, ImageTagResponse
+from backend_api import (
+    ImageCaptionResponse,
+    ImageTagResponse,
+    ImageTextEmbeddingRequest,
+    ImageTextEmbeddingResponse,
+    ImageTextEmbeddingResult,
+)
 
 APP_STATE: dict[str, Any] = {}
 LOGGER = logging.getLogger("uvicorn.error")
@@ -319,10 +325,10 @@ def build_runtime_summary() -> list[str]:
     ]
 
 
-def load_image_embedding_backend() -> tuple[Any, Any, Any, str]:
+def load_image_embedding_backend() -> tuple[Any, Any, Any, Any, str]:
     try:
         import torch
-        from transformers import AutoImageProcessor, AutoModel
+        from transformers import AutoImageProcessor, AutoModel, AutoTokenizer
     except Exception as exc:
         raise RuntimeError(
             "Image embedding dependencies are unavailable. Install torch, torchvision, and transformers in the image-tagging venv."
@@ -342,9 +348,18 @@ def load_image_embedding_backend() -> tuple[Any, Any, Any, str]:
             "Image embedding dependencies are incomplete. Install torch, torchvision, and transformers in the image-tagging venv."
         ) from exc
 
+    # Models with a paired text encoder (CLIP, SigLIP and similar) can also embed text queries into
+    # the image embedding space.
+    tokenizer = None
+    if hasattr(model, "get_text_features"):
+        try:
+            tokenizer = AutoTokenizer.from_pretrained(model_id)
+        except Exception as exc:
+            LOGGER.warning("Image embedding model has a text encoder, but its tokenizer could not be loaded: %s", exc)
+
     model = model.to(device)
     model.eval()
-    return torch, processor, model, device
+    return torch, processor, model, tokenizer, device
 
 
 def disable_image_embedding(reason: str) -> None:
@@ -354,6 +369,11 @@ def disable_image_embedding(reason: str) -> None:
     APP_STATE.pop("embedding_torch", None)
     APP_STATE.pop("embedding_processor", None)
     APP_STATE.pop("embedding_model", None)
+    APP_STATE.pop("embedding_tokenizer", None)
+
+
+def image_text_embedding_supported() -> bool:
+    return bool(APP_STATE.get("image_embedding_enabled")) and APP_STATE.get("embedding_tokenizer") is not None
 
 
 def build_embedding_processor_inputs(processor: Any, image: Image.Image) -> dict[str, Any]:
@@ -397,10 +417,11 @@ async def lifespan(_: FastAPI):
     APP_STATE["image_embedding_error"] = None
     if APP_STATE["image_embedding_enabled"]:
         try:
-            torch, processor, model, device = load_image_embedding_backend()
+            torch, processor, model, tokenizer, device = load_image_embedding_backend()
             APP_STATE["embedding_torch"] = torch
             APP_STATE["embedding_processor"] = processor
             APP_STATE["embedding_model"] = model
+            APP_STATE["embedding_tokenizer"] = tokenizer
             APP_STATE["image_embedding_device"] = device
         except Exception as exc:
             disable_image_embedding(str(exc))
@@ -987,8 +1008,11 @@ def compute_image_embedding_sync(prepared_bytes: bytes) -> list[float]:
             normalized_inputs[key] = value.to(device)
 
     with torch.inference_mode():
-        outputs = model(**normalized_inputs)
-        embedding = outputs.last_hidden_state[:, 0, :]
+        if hasattr(model, "get_image_features"):
+            embedding = model.get_image_features(**normalized_inputs)
+        else:
+            outputs = model(**normalized_inputs)
+            embedding = outputs.last_hidden_state[:, 0, :]
         embedding = torch.nn.functional.normalize(embedding, p=2, dim=-1)
         vector = embedding[0].detach().to("cpu", dtype=torch.float32).tolist()
 
@@ -1015,6 +1039,23 @@ async def compute_image_embedding(prepared_bytes: bytes) -> list[float]:
         return []
 
 
+def compute_image_text_embeddings_sync(texts: list[str]) -> list[list[float]]:
+    torch = APP_STATE.get("embedding_torch")
+    model = APP_STATE.get("embedding_model")
+    tokenizer = APP_STATE.get("embedding_tokenizer")
+    device = APP_STATE.get("image_embedding_device")
+    if torch is None or model is None or tokenizer is None or not isinstance(device, str):
+        raise RuntimeError("Image text embedding model is not ready.")
+
+    inputs = tokenizer(texts, padding="max_length", truncation=True, return_tensors="pt")
+    inputs = {key: value.to(device) for key, value in inputs.items()}
+    with torch.inference_mode():
+        embeddings = model.get_text_features(**inputs)
+        embeddings = torch.nn.functional.normalize(embeddings, p=2, dim=-1)
+        vectors = embeddings.detach().to("cpu", dtype=torch.float32).tolist()
+    return [[round(float(value), 8) for value in vector] for vector in vectors]
+
+
 def validate_image_embedding_result(image_embedding: list[float]) -> None:
     if not APP_STATE.get("image_embedding_enabled"):
         return
@@ -1364,35 +1405,46 @@ async def root(request: Request) -> dict[str, str]:
         "gpu_tools": rooted_path(request, "/gpu-tools"),
         "image_extract": rooted_path(request, "/image-extract"),
         "image_extract_caption_only": rooted_path(request, "/image-extract-caption-only"),
+        "image_text_embed": rooted_path(request, "/image-text-embed"),
         "pdf_extract_caption_only": rooted_path(request, "/pdf-extract-caption-only"),
     }
 
 
 @app.get("/gpu-tools")
 async def gpu_tools() -> dict[str, Any]:
+    endpoints: list[dict[str, Any]] = [
+        {
+            "id": "image_extract",
+            "method": "POST",
+            "path": "/image-extract",
+            "description": "Extract image captions, tags, OCR snippets, document confidence, face counts, and image embeddings.",
+        },
+        {
+            "id": "image_extract_caption_only",
+            "method": "POST",
+            "path": "/image-extract-caption-only",
+            "description": "Extract only a detailed visual caption from an image.",
+        },
+        {
+            "id": "pdf_extract_caption_only",
+            "method": "POST",
+            "path": "/pdf-extract-caption-only",
+            "description": "Render the first page of a PDF and extract only a detailed visual caption.",
+        },
+    ]
+    if image_text_embedding_supported():
+        endpoints.append(
+            {
+                "id": "image_text_embed",
+                "method": "POST",
+                "path": "/image-text-embed",
+                "description": "Embed text queries into the image embedding space, for text-to-image search.",
+            }
+        )
     return {
         "schema_version": 1,
         "service": "infumap-image-extract",
-        "endpoints": [
-            {
-                "id": "image_extract",
-                "method": "POST",
-                "path": "/image-extract",
-                "description": "Extract image captions, tags, OCR snippets, document confidence, face counts, and image embeddings.",
-            },
-            {
-                "id": "image_extract_caption_only",
-                "method": "POST",
-                "path": "/image-extract-caption-only",
-                "description": "Extract only a detailed visual caption from an image.",
-            },
-            {
-                "id": "pdf_extract_caption_only",
-                "method": "POST",
-                "path": "/pdf-extract-caption-only",
-                "description": "Render the first page of a PDF and extract only a detailed visual caption.",
-            },
-        ],
+        "endpoints": endpoints,
     }
 
 
@@ -1561,6 +1613,7 @@ async def tag_upload(request: Request) -> ImageTagResponse:
             tags=tags,
             ocr_text=ocr_text,
             image_embedding=image_embedding,
+            image_embedding_model_id=APP_STATE.get("image_embedding_model_id") if image_embedding else None,
             model_id=APP_STATE.get("model_id") or None,
             backend=LLAMA_BACKEND_NAME,
             extraction_mode=IMAGE_EXTRACT_MODE_CAPTION_FALLBACK if used_caption_fallback else IMAGE_EXTRACT_MODE_FULL,
@@ -1578,6 +1631,27 @@ async def tag_upload(request: Request) -> ImageTagResponse:
         raise HTTPException(status_code=500, detail=str(exc)) from exc
 
 
+@app.post("/image-text-embed", response_model=ImageTextEmbeddingResponse)
+async def image_text_embed(body: ImageTextEmbeddingRequest) -> ImageTextEmbeddingResponse:
+    if not image_text_embedding_supported():
+        raise HTTPException(
+            status_code=501,
+            detail="The configured image embedding model does not have a text encoder.",
+        )
+    texts = [body.input] if isinstance(body.input, str) else body.input
+    if not texts or any(not text.strip() for text in texts):
+        raise HTTPException(status_code=422, detail="Input must be non-empty text.")
+    try:
+        vectors = await asyncio.to_thread(compute_image_text_embeddings_sync, texts)
+    except Exception as exc:
+        LOGGER.exception("Image text embedding failed: inputs=%d", len(texts))
+        raise HTTPException(status_code=500, detail=str(exc)) from exc
+    return ImageTextEmbeddingResponse(
+        model=APP_STATE.get("image_embedding_model_id") or image_embedding_model_id(),
+        data=[ImageTextEmbeddingResult(index=index, embedding=vector) for index, vector in enumerate(vectors)],
+    )
+
+
 @app.post("/image-extract-caption-only", response_model=ImageCaptionResponse, openapi_extra=TAG_UPLOAD_OPENAPI_EXTRA)
 async def caption_upload(request: Request) -> ImageCaptionResponse:
     request_started_at = time.perf_counter()
@@ -28,6 +28,7 @@ class ImageTagResponse(BaseModel):
     tags: list[str] = Field(default_factory=list)
     ocr_text: list[str] = Field(default_factory=list)
     image_embedding: list[float] = Field(default_factory=list)
+    image_embedding_model_id: str | None = None
     model_id: str | None = None
     backend: str | None = None
     extraction_mode: str | None = None
@@ -37,3 +38,18 @@ class ImageCaptionResponse(BaseModel):
     detailed_caption: str | None = None
     model_id: str | None = None
     backend: str | None = None
+
+
+class ImageTextEmbeddingRequest(BaseModel):
+    input: str | list[str]
+    encoding_format: str = "float"
+
+
+class ImageTextEmbeddingResult(BaseModel):
+    index: int
+    embedding: list[float]
+
+
+class ImageTextEmbeddingResponse(BaseModel):
+    model: str
+    data: list[ImageTextEmbeddingResult]
@@ -0,0 +1,273 @@
+#!/usr/bin/env python3
+
+# Copyright (C) The Infumap Authors
+# This file is part of Infumap.
+#
+# This program is free software: you can redistribute it and/or modify
+# it under the terms of the GNU Affero General Public License as
+# published by the Free Software Foundation, either version 3 of the
+# License, or (at your option) any later version.
+#
+# This program is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+# GNU Affero General Public License for more details.
+#
+# You should have received a copy of the GNU Affero General Public License
+# along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+"""Stand-in chat model server for testing Infumap's LLM providers.
+
+Answers both OpenAI-compatible POST /v1/chat/completions and Anthropic-style
+POST /v1/messages, streamed (server-sent events) or not, without running a
+model. Only the Python standard library is used.
+
+    ./tools/llm_stub.py --port 8792
+
+then configure one model per provider in Infumap's settings:
+
+    [llm_models.stub-openai]
+    provider = "openai"
+    url = "http://127.0.0.1:8792"
+
+    [llm_models.stub-anthropic]
+    provider = "anthropic"
+    url = "http://127.0.0.1:8792"
+
+The answer repeats the last user message. A user message of the form
+"tool:<name> <json arguments>" makes the model call that tool (if it was
+offered) with those arguments, and then answer with the number of characters
+of the tool result once it is sent back. Streamed answers are sent a word at a
+time, and streamed tool arguments a few characters at a time, so the delta
+accumulation of both providers is exercised. If --api-key is given, requests
+must send it (as a bearer token for openai, in x-api-key for anthropic).
+"""
+
+from __future__ import annotations
+
+import argparse
+import json
+import time
+import uuid
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+OPENAI_PATH = "/v1/chat/completions"
+ANTHROPIC_PATH = "/v1/messages"
+TOOL_PREFIX = "tool:"
+ARGUMENT_FRAGMENT_CHARS = 8
+
+
+class Reply:
+    """What the stand-in model says: text, or a single tool call."""
+
+    def __init__(self, text: str = "", tool_name: str | None = None, tool_arguments: str = "{}") -> None:
+        self.text = text
+        self.tool_name = tool_name
+        self.tool_arguments = tool_arguments
+        self.tool_id = f"call_{uuid.uuid4().hex[:12]}"
+
+
+def decide_reply(last_user_text: str, tool_result: str | None, tool_names: list[str]) -> Reply:
+    if tool_result is not None:
+        return Reply(f"The tool returned {len(tool_result)} characters.")
+    text = last_user_text.strip()
+    if text.startswith(TOOL_PREFIX):
+        name, _, arguments = text[len(TOOL_PREFIX):].partition(" ")
+        if name in tool_names:
+            arguments = arguments.strip() or "{}"
+            try:
+                json.loads(arguments)
+            except json.JSONDecodeError as e:
+                return Reply(f"Tool arguments are not JSON: {e}")
+            return Reply(tool_name=name, tool_arguments=arguments)
+        return Reply(f"Tool '{name}' was not offered.")
+    return Reply(f"Stand-in answer to: {text}" if text else "Stand-in answer.")
+
+
+def words(text: str) -> list[str]:
+    parts = text.split(" ")
+    return [part + (" " if index < len(parts) - 1 else "") for index, part in enumerate(parts)]
+
+
+def fragments(text: str) -> list[str]:
+    return [text[i:i + ARGUMENT_FRAGMENT_CHARS] for i in range(0, len(text), ARGUMENT_FRAGMENT_CHARS)] or [""]
+
+
+def openai_reply(request: dict) -> Reply:
+    messages = request.get("messages") or []
+    tool_names = [tool.get("function", {}).get("name") for tool in request.get("tools") or []]
+    last = messages[-1] if messages else {}
+    tool_result = last.get("content") or "" if last.get("role") == "tool" else None
+    last_user_text = next((m.get("content") or "" for m in reversed(messages) if m.get("role") == "user"), "")
+    return decide_reply(last_user_text, tool_result, tool_names)
+
+
+def openai_response(request: dict, reply: Reply) -> dict:
+    message: dict = {"role": "assistant", "content": reply.text or None}
+    if reply.tool_name:
+        message["tool_calls"] = [{
+            "id": reply.tool_id,
+            "type": "function",
+            "function": {"name": reply.tool_name, "arguments": reply.tool_arguments},
+        }]
+    return {
+        "id": f"chatcmpl-{uuid.uuid4().hex[:12]}",
+        "object": "chat.completion",
+        "created": int(time.time()),
+        "model": request.get("model"),
+        "choices": [{"index": 0, "message": message, "finish_reason": "tool_calls" if reply.tool_name else "stop"}],
+        "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2},
+    }
+
+
+def openai_stream_events(request: dict, reply: Reply) -> list[tuple[str | None, str]]:
+    def chunk(delta: dict) -> tuple[None, str]:
+        return None, json.dumps({"object": "chat.completion.chunk", "model": request.get("model"),
+                                 "choices": [{"index": 0, "delta": delta}]})
+
+    events = [chunk({"role": "assistant"})]
+    for word in words(reply.text) if reply.text else []:
+        events.append(chunk({"content": word}))
+    if reply.tool_name:
+        events.append(chunk({"tool_calls": [{"index": 0, "id": reply.tool_id, "type": "function",
+                                             "function": {"name": reply.tool_name, "arguments": ""}}]}))
+        for fragment in fragments(reply.tool_arguments):
+            events.append(chunk({"tool_calls": [{"index": 0, "function": {"arguments": fragment}}]}))
+    events.append((None, json.dumps({"object": "chat.completion.chunk", "choices": [],
+                                     "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}})))
+    events.append((None, "[DONE]"))
+    return events
+
+
+def anthropic_reply(request: dict) -> Reply:
+    messages = request.get("messages") or []
+    tool_names = [tool.get("name") for tool in request.get("tools") or []]
+    tool_result = None
+    last_user_text = ""
+    if messages and messages[-1].get("role") == "user":
+        for block in messages[-1].get("content") or []:
+            if block.get("type") == "tool_result":
+                tool_result = block.get("content") or ""
+    for message in reversed(messages):
+        texts = [b.get("text", "") for b in message.get("content") or [] if b.get("type") == "text"]
+        if message.get("role") == "user" and texts:
+            last_user_text = "".join(texts)
+            break
+    return decide_reply(last_user_text, tool_result, tool_names)
+
+
+def anthropic_content(reply: Reply) -> list[dict]:
+    if reply.tool_name:
+        return [{"type": "tool_use", "id": reply.tool_id, "name": reply.tool_name,
+                 "input": json.loads(reply.tool_arguments)}]
+    return [{"type": "text", "text": reply.text}]
+
+
+def anthropic_response(request: dict, reply: Reply) -> dict:
+    return {
+        "id": f"msg_{uuid.uuid4().hex[:12]}",
+        "type": "message",
+        "role": "assistant",
+        "model": request.get("model"),
+        "content": anthropic_content(reply),
+        "stop_reason": "tool_use" if reply.tool_name else "end_turn",
+        "usage": {"input_tokens": 1, "output_tokens": 1},
+    }
+
+
+def anthropic_stream_events(request: dict, reply: Reply) -> list[tuple[str | None, str]]:
+    def event(name: str, data: dict) -> tuple[str, str]:
+        return name, json.dumps({"type": name, **data})
+
+    message = {"id": f"msg_{uuid.uuid4().hex[:12]}", "type": "message", "role": "assistant",
+               "model": request.get("model"), "content": [], "usage": {"input_tokens": 1, "output_tokens": 0}}
+    events = [event("message_start", {"message": message})]
+    if reply.tool_name:
+        block = {"type": "tool_use", "id": reply.tool_id, "name": reply.tool_name, "input": {}}
+        events.append(event("content_block_start", {"index": 0, "content_block": block}))
+        for fragment in fragments(reply.tool_arguments):
+            delta = {"type": "input_json_delta", "partial_json": fragment}
+            events.append(event("content_block_delta", {"index": 0, "delta": delta}))
+    else:
+        events.append(event("content_block_start", {"index": 0, "content_block": {"type": "text", "text": ""}}))
+        for word in words(reply.text):
+            events.append(event("content_block_delta", {"index": 0, "delta": {"type": "text_delta", "text": word}}))
+    events.append(event("content_block_stop", {"index": 0}))
+    stop_reason = "tool_use" if reply.tool_name else "end_turn"
+    events.append(event("message_delta", {"delta": {"stop_reason": stop_reason}, "usage": {"output_tokens": 1}}))
+    events.append(event("message_stop", {}))
+    return events
+
+
+def make_handler(api_key: str | None, delay_secs: float) -> type[BaseHTTPRequestHandler]:
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self) -> None:
+            length = int(self.headers.get("Content-Length") or 0)
+            try:
+                request = json.loads(self.rfile.read(length) or b"{}")
+            except json.JSONDecodeError as e:
+                self.send_json(400, {"error": {"message": f"Request body is not JSON: {e}"}})
+                return
+            path = self.path.rstrip("/")
+            if path == OPENAI_PATH:
+                if api_key and self.headers.get("Authorization") != f"Bearer {api_key}":
+                    self.send_json(401, {"error": {"message": "Invalid API key."}})
+                    return
+                reply = openai_reply(request)
+                if request.get("stream"):
+                    self.send_events(openai_stream_events(request, reply))
+                else:
+                    self.send_json(200, openai_response(request, reply))
+            elif path == ANTHROPIC_PATH:
+                if api_key and self.headers.get("x-api-key") != api_key:
+                    self.send_json(401, {"type": "error", "error": {"type": "authentication_error",
+                                                                    "message": "Invalid API key."}})
+                    return
+                reply = anthropic_reply(request)
+                if request.get("stream"):
+                    self.send_events(anthropic_stream_events(request, reply))
+                else:
+                    self.send_json(200, anthropic_response(request, reply))
+            else:
+                self.send_json(404, {"error": {"message": f"Unknown path {self.path}"}})
+
+        def send_json(self, status: int, payload: dict) -> None:
+            data = json.dumps(payload).encode("utf-8")
+            self.send_response(status)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            self.end_headers()
+            self.wfile.write(data)
+
+        def send_events(self, events: list[tuple[str | None, str]]) -> None:
+            self.send_response(200)
+            self.send_header("Content-Type", "text/event-stream")
+            self.send_header("Cache-Control", "no-cache")
+            self.end_headers()
+            for name, data in events:
+                lines = f"event: {name}\n" if name else ""
+                self.wfile.write(f"{lines}data: {data}\n\n".encode("utf-8"))
+                self.wfile.flush()
+                if delay_secs > 0:
+                    time.sleep(delay_secs)
+            self.close_connection = True
+
+    return Handler
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("--host", default="127.0.0.1")
+    parser.add_argument("--port", type=int, default=8792)
+    parser.add_argument("--api-key", help="require this API key on every request")
+    parser.add_argument("--delay-secs", type=float, default=0.05, help="pause between streamed events")
+    args = parser.parse_args()
+
+    server = ThreadingHTTPServer((args.host, args.port), make_handler(args.api_key, args.delay_secs))
+    print(f"Stand-in chat model server listening on http://{args.host}:{args.port} "
+          f"({OPENAI_PATH} and {ANTHROPIC_PATH})")
+    server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
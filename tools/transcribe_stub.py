@@ -0,0 +1,107 @@
+#!/usr/bin/env python3
+
+# Copyright (C) The Infumap Authors
+# This file is part of Infumap.
+#
+# This program is free software: you can redistribute it and/or modify
+# it under the terms of the GNU Affero General Public License as
+# published by the Free Software Foundation, either version 3 of the
+# License, or (at your option) any later version.
+#
+# This program is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+# GNU Affero General Public License for more details.
+#
+# You should have received a copy of the GNU Affero General Public License
+# along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+"""Stand-in whisper-compatible transcription server for testing Infumap's audio pipeline.
+
+Answers POST /v1/audio/transcriptions with a fixed, timestamped transcript in the
+OpenAI verbose_json format, without looking at the audio. Only the Python standard
+library is used.
+
+    ./tools/transcribe_stub.py --port 8791
+
+then set transcribe_url = "http://127.0.0.1:8791" in Infumap's settings. The
+transcript is read from --transcript (one segment per line) if given, and each
+segment is --segment-secs long.
+"""
+
+from __future__ import annotations
+
+import argparse
+import json
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+
+ENDPOINT_PATH = "/v1/audio/transcriptions"
+DEFAULT_SEGMENTS = [
+    "This is a stand-in transcript produced for testing.",
+    "The recording itself was not listened to.",
+    "Each line of the transcript becomes one timestamped segment.",
+]
+
+
+def build_response(segment_texts: list[str], segment_secs: float) -> dict:
+    segments = []
+    for index, text in enumerate(segment_texts):
+        segments.append({
+            "id": index,
+            "start": index * segment_secs,
+            "end": (index + 1) * segment_secs,
+            "text": text,
+        })
+    return {
+        "task": "transcribe",
+        "language": "english",
+        "duration": len(segment_texts) * segment_secs,
+        "text": " ".join(segment_texts),
+        "segments": segments,
+    }
+
+
+def make_handler(segment_texts: list[str], segment_secs: float) -> type[BaseHTTPRequestHandler]:
+    class Handler(BaseHTTPRequestHandler):
+        def do_POST(self) -> None:
+            length = int(self.headers.get("Content-Length") or 0)
+            body = self.rfile.read(length)
+            if self.path.rstrip("/") != ENDPOINT_PATH:
+                self.send_json(404, {"error": {"message": f"Unknown path {self.path}"}})
+                return
+            if b'name="file"' not in body:
+                self.send_json(400, {"error": {"message": "Missing multipart field 'file'."}})
+                return
+            self.send_json(200, build_response(segment_texts, segment_secs))
+
+        def send_json(self, status: int, payload: dict) -> None:
+            data = json.dumps(payload).encode("utf-8")
+            self.send_response(status)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            self.end_headers()
+            self.wfile.write(data)
+
+    return Handler
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("--host", default="127.0.0.1")
+    parser.add_argument("--port", type=int, default=8791)
+    parser.add_argument("--transcript", help="file with one transcript segment per line")
+    parser.add_argument("--segment-secs", type=float, default=5.0)
+    args = parser.parse_args()
+
+    segment_texts = DEFAULT_SEGMENTS
+    if args.transcript:
+        with open(args.transcript, encoding="utf-8") as f:
+            segment_texts = [line.strip() for line in f if line.strip()]
+
+    server = ThreadingHTTPServer((args.host, args.port), make_handler(segment_texts, args.segment_secs))
+    print(f"Stand-in transcription server listening on http://{args.host}:{args.port}{ENDPOINT_PATH}")
+    server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
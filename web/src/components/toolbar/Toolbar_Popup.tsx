@@ -769,10 +769,21 @@ export const Toolbar_Popup: Component = () => {
     ev.stopPropagation();
   };
 
+  // Labelled pages and files get the more readable /{username}/{label} form.
+  const qrInfoItemUrl = (): string => {
+    const item = qrInfoItem();
+    const label = isPage(item) ? asPageItem(item).label : isFile(item) ? asFileItem(item).label : undefined;
+    const user = store.user.getUserMaybe();
+    if (label && user && item.ownerId == user.userId) {
+      return window.location.origin + "/" + user.username + "/" + label;
+    }
+    return window.location.origin + "/" + item.id;
+  }
+
   const copyItemIdClickHandler = (): void => { navigator.clipboard.writeText(qrInfoItem().id); }
   const linkItemIdClickHandler = (): void => {
     const item = qrInfoItem();
-    navigator.clipboard.writeText(window.location.origin + "/" + item.id);
+    navigator.clipboard.writeText(qrInfoItemUrl());
     store.overlay.toolbarPopupInfoMaybe.set(null);
     store.overlay.toolbarTransientMessage.set({ text: item.itemType + " id → clipboard", type: TransientMessageType.Info });
     setTimeout(() => { store.overlay.toolbarTransientMessage.set(null); }, 1000);
@@ -966,7 +977,7 @@ export const Toolbar_Popup: Component = () => {
     if (overlayTypeConst != ToolbarPopupType.QrLink) { return; }
     const canvas = document.getElementById('qrcanvas');
     if (canvas == null) { return; }
-    const url = window.location.origin + "/" + qrInfoItem().id;
+    const url = qrInfoItemUrl();
     QRCode.toCanvas(canvas, url, { scale: 7 });
   });
 
@@ -352,6 +352,7 @@ export function moving_initiate(store: StoreContextModel, activeItem: Positional
       const toClone = activeVisualElement.displayItem;
       const cloned = ItemFns.fromObject(ItemFns.toObject(toClone), null);
       cloned.id = newUid();
+      ItemFns.clearLabelOfCopy(cloned);
       cloned.creationDate = currentUnixTimeSeconds();
       cloned.lastModifiedDate = currentUnixTimeSeconds();
       cloned.dateTime = currentUnixTimeSeconds();
@@ -887,6 +888,7 @@ function moving_activeItemToPage(store: StoreContextModel, moveToVe: VisualEleme
     const toClone = activeElement.displayItem;
     const cloned = asPositionalItem(ItemFns.fromObject(ItemFns.toObject(toClone), null));
     cloned.id = newUid();
+    ItemFns.clearLabelOfCopy(cloned);
     cloned.creationDate = currentUnixTimeSeconds();
     cloned.lastModifiedDate = currentUnixTimeSeconds();
     cloned.dateTime = currentUnixTimeSeconds();
@@ -1020,6 +1022,7 @@ function moving_activeItemOutOfTable(store: StoreContextModel, shouldCreateLink:
     const toClone = activeVisualElement.displayItem;
     const cloned = asPositionalItem(ItemFns.fromObject(ItemFns.toObject(toClone), null));
     cloned.id = newUid();
+    ItemFns.clearLabelOfCopy(cloned);
     cloned.creationDate = currentUnixTimeSeconds();
     cloned.lastModifiedDate = currentUnixTimeSeconds();
     cloned.dateTime = currentUnixTimeSeconds();
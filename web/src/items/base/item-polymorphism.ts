@@ -273,6 +273,14 @@ export const ItemFns = {
     panic(`toObject: Unknown item type: ${item.itemType}`);
   },
 
+  /**
+   * Labels are unique per user, so they are not carried over to a copy of an item.
+   */
+  clearLabelOfCopy: (item: Item): void => {
+    if (isPage(item)) { delete asPageItem(item).label; }
+    if (isFile(item)) { delete asFileItem(item).label; }
+  },
+
   handleClick: (visualElementSignal: VisualElementSignal, hitboxMeta: HitboxMeta | null, hitboxFlags: HitboxFlags, store: StoreContextModel, caretAtEnd: boolean = false): void => {
     const visualElement = visualElementSignal.get();
     const item = visualElement.displayItem;
@@ -449,6 +449,7 @@ export function queryChatHasContent(store: StoreContextModel, queryItem: QueryIt
 function cloneItemForMaterializedChat(source: Item, parentId: Uid, relationshipToParent: RelationshipToParent): Item {
   const clone = ItemFns.fromObject(ItemFns.toObject(source), null);
   clone.id = newUid();
+  ItemFns.clearLabelOfCopy(clone);
   clone.parentId = parentId;
   clone.relationshipToParent = relationshipToParent;
   clone.groupId = null;
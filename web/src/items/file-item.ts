@@ -43,7 +43,12 @@ import { CursorEventState } from '../input/state';
 import { downloadRemoteFile } from '../util/remoteFile';
 
 
-export interface FileItem extends FileMeasurable, XSizableItem, AttachmentsItem, DataItem, TitledItem { }
+export interface FileItem extends FileMeasurable, XSizableItem, AttachmentsItem, DataItem, TitledItem {
+  durationMs?: number,
+  label?: string,
+  imageSizePx?: Dimensions,
+  naturalAspect?: number,
+}
 
 export interface FileMeasurable extends ItemTypeMixin, PositionalMixin, XSizableMixin, TitledMixin, FlagsMixin, AttachmentsMixin, IconMixin { }
 
@@ -66,6 +71,7 @@ export const FileFns = {
       endDateTime: o.endDateTime ?? null,
       ordering: new Uint8Array(o.ordering),
       title: o.title,
+      label: o.label ?? undefined,
       spatialPositionGr: o.spatialPositionGr,
 
       spatialWidthGr: o.spatialWidthGr,
@@ -77,6 +83,9 @@ export const FileFns = {
       originalCreationDate: o.originalCreationDate,
       mimeType: o.mimeType,
       fileSizeBytes: o.fileSizeBytes,
+      durationMs: o.durationMs ?? undefined,
+      imageSizePx: o.imageSizePx ?? undefined,
+      naturalAspect: o.naturalAspect ?? undefined,
 
       computed_attachments: [],
     });
@@ -96,6 +105,7 @@ export const FileFns = {
       endDateTime: f.endDateTime,
       ordering: Array.from(f.ordering),
       title: f.title,
+      label: f.label,
       spatialPositionGr: f.spatialPositionGr,
 
       spatialWidthGr: f.spatialWidthGr,
@@ -107,6 +117,9 @@ export const FileFns = {
       originalCreationDate: f.originalCreationDate,
       mimeType: f.mimeType,
       fileSizeBytes: f.fileSizeBytes,
+      durationMs: f.durationMs,
+      imageSizePx: f.imageSizePx,
+      naturalAspect: f.naturalAspect,
     });
   },
 
@@ -82,6 +82,7 @@ export function dockForcesEmbeddedInteractiveArrangeAlgorithm(arrangeAlgorithm:
 }
 
 export interface PageItem extends PageMeasurable, TabularItem, XSizableItem, ContainerItem, AttachmentsItem, TitledItem, PermissionFlagsMixin, ColorableMixin, AspectItem, Item {
+  label?: string;
   innerSpatialWidthGr: number;
   arrangeAlgorithm: string;
   gridNumberOfColumns: number;
@@ -662,6 +663,7 @@ export const PageFns = {
       endDateTime: o.endDateTime ?? null,
       ordering: new Uint8Array(o.ordering),
       title: o.title,
+      label: o.label ?? undefined,
       spatialPositionGr: o.spatialPositionGr,
 
 
@@ -721,6 +723,7 @@ export const PageFns = {
       endDateTime: p.endDateTime,
       ordering: Array.from(p.ordering),
       title: p.title,
+      label: p.label,
       spatialPositionGr: p.spatialPositionGr,
 
 
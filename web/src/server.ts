@@ -79,6 +79,17 @@ export interface SearchFragmentMatch {
   textTruncated: boolean,
   pageStart?: number,
   pageEnd?: number,
+  timeStartSecs?: number,
+  timeEndSecs?: number,
+  tableCell?: SearchTableCellMatch,
+}
+
+export interface SearchTableCellMatch {
+  rowItemId: Uid,
+  rowIndex: number,
+  cellItemId: Uid,
+  columnIndex: number,
+  columnName: string,
 }
 
 export interface SearchPathElement {
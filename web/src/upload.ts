@@ -47,6 +47,10 @@ const EXTERNAL_UPLOAD_CONFIRMATION_FILE_THRESHOLD = 10;
 const MAX_EXTERNAL_UPLOAD_FILES = 100;
 const TEXT_UPLOAD_MIME_TYPES = new Set(["text/plain", "text/markdown", "text/x-markdown"]);
 const TEXT_UPLOAD_EXTENSIONS = new Set(["txt", "md", "markdown"]);
+const IMAGE_UPLOAD_MIME_TYPES = new Set(["image/jpeg", "image/png", "image/heic", "image/heif", "image/avif"]);
+// Browsers don't reliably report a mime type for these, so they are recognized by extension. The
+// server keeps the original and displays a JPEG rendition.
+const IMAGE_UPLOAD_EXTENSIONS = new Set(["heic", "heif", "avif", "dng", "cr2", "cr3", "nef", "arw", "orf", "rw2", "raf"]);
 
 function normalizedUploadMimeType(file: File): string {
   return file.type.trim().toLowerCase().split(";")[0];
@@ -59,7 +63,7 @@ function uploadExtension(file: File): string {
 
 function uploadItemTypeForFile(file: File): ItemType {
   const mimeType = normalizedUploadMimeType(file);
-  if (mimeType == "image/jpeg" || mimeType == "image/png") {
+  if (IMAGE_UPLOAD_MIME_TYPES.has(mimeType) || IMAGE_UPLOAD_EXTENSIONS.has(uploadExtension(file))) {
     return ItemType.Image;
   }
   if (TEXT_UPLOAD_MIME_TYPES.has(mimeType) || TEXT_UPLOAD_EXTENSIONS.has(uploadExtension(file))) {
@@ -68,6 +68,20 @@ export function fragmentMatchPageLabel(pageStart?: number, pageEnd?: number): st
   return pageStart == pageEnd ? `Page ${pageStart}` : `Pages ${pageStart}-${pageEnd}`;
 }
 
+function formatClockTime(secs: number): string {
+  const hours = Math.floor(secs / 3600);
+  const minutes = Math.floor(secs / 60) % 60;
+  const seconds = String(secs % 60).padStart(2, "0");
+  return hours > 0 ? `${hours}:${String(minutes).padStart(2, "0")}:${seconds}` : `${minutes}:${seconds}`;
+}
+
+export function fragmentMatchTimeLabel(timeStartSecs?: number, timeEndSecs?: number): string | null {
+  if (timeStartSecs == null || timeEndSecs == null) {
+    return null;
+  }
+  return `${formatClockTime(timeStartSecs)}-${formatClockTime(timeEndSecs)}`;
+}
+
 export function formatSearchFragmentMatchText(_sourceKind: string, text: string): string {
   return text.replace(/\s+/g, " ").trim();
 }
@@ -127,7 +141,8 @@ export function searchFragmentMatchDisplayFromMatch(
   return {
     text: match.textTruncated ? appendTruncationEllipsis(formattedText) : formattedText,
     href: match.sourceKind == ITEM_TITLE_SOURCE_KIND ? null : `/files/${targetId}/fragments/${match.fragmentOrdinal}`,
-    pageLabel: fragmentMatchPageLabel(match.pageStart, match.pageEnd),
+    pageLabel: fragmentMatchPageLabel(match.pageStart, match.pageEnd)
+      ?? fragmentMatchTimeLabel(match.timeStartSecs, match.timeEndSecs),
     scoreLabel: formatSearchEvidenceScore(match),
   };
 }